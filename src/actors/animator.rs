@@ -0,0 +1,437 @@
+use engine::animation::{blend_additive, blend_override, BoneMask, ClipSampler, CompressedClip,
+                        LayerBlend, LocalPose, Skeleton};
+use engine::GameObject;
+use world::{Actor, World};
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+struct PlayingClip {
+    name: String,
+    clip: Rc<CompressedClip>,
+    time: f32,
+    looping: bool,
+    sampler: ClipSampler,
+    binding: Vec<Option<usize>>,
+    /// First frame of the clip, the reference of additive layers
+    reference: Vec<LocalPose>,
+}
+
+impl PlayingClip {
+    fn new(name: &str, clip: Rc<CompressedClip>, looping: bool) -> PlayingClip {
+        PlayingClip {
+            name: name.to_owned(),
+            clip,
+            time: 0.0,
+            looping,
+            sampler: ClipSampler::new(),
+            binding: Vec::new(),
+            reference: Vec::new(),
+        }
+    }
+
+    fn bind(&mut self, skeleton: &Skeleton) {
+        self.binding = skeleton.bind(&self.clip);
+
+        self.reference = skeleton.rest_pose();
+        let mut sampler = ClipSampler::new();
+        Skeleton::write_pose(&mut self.reference, &self.binding, sampler.sample(&self.clip, 0.0));
+    }
+
+    fn advance(&mut self, dt: f32) {
+        let duration = self.clip.duration;
+        self.time += dt;
+
+        if self.looping && duration > 0.0 {
+            self.time -= (self.time / duration).floor() * duration;
+        } else {
+            self.time = self.time.max(0.0).min(duration);
+        }
+    }
+
+    fn write(&mut self, pose: &mut [LocalPose]) {
+        let poses = self.sampler.sample(&self.clip, self.time);
+        Skeleton::write_pose(pose, &self.binding, poses);
+    }
+
+    fn mark_animated(&self, animated: &mut [bool]) {
+        for bone in self.binding.iter().filter_map(|b| *b) {
+            animated[bone] = true;
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        !self.looping && self.time >= self.clip.duration
+    }
+}
+
+/// The pose of a layer, its reference pose and the bones it animates
+struct LayerPose {
+    pose: Vec<LocalPose>,
+    reference: Vec<LocalPose>,
+    animated: Vec<bool>,
+}
+
+/// Clips played over the layers below, on the bones of its mask.
+///
+/// ```ignore
+/// // Aim with the upper body while the legs run
+/// animator.add_layer(
+///     AnimationLayer::new("aim").with_mask(BoneMask::new().with_branch("spine", 1.0)),
+/// );
+/// animator.play("run", true);
+/// animator.play_layer("aim", "aim_rifle", true);
+/// ```
+///
+/// Only the bones animated by its clips are blended, with the weight of the layer and their
+/// weight in the mask.
+pub struct AnimationLayer {
+    pub name: String,
+    pub weight: f32,
+    /// Weights per bone, `None` for all bones
+    pub mask: Option<BoneMask>,
+    pub blend: LayerBlend,
+
+    current: Option<PlayingClip>,
+    /// The previous clip fading out, its elapsed and total fade time
+    fading: Option<(PlayingClip, f32, f32)>,
+}
+
+impl AnimationLayer {
+    pub fn new(name: &str) -> AnimationLayer {
+        AnimationLayer {
+            name: name.to_owned(),
+            weight: 1.0,
+            mask: None,
+            blend: LayerBlend::Override,
+            current: None,
+            fading: None,
+        }
+    }
+
+    pub fn with_weight(mut self, weight: f32) -> AnimationLayer {
+        self.weight = weight;
+        self
+    }
+
+    pub fn with_mask(mut self, mask: BoneMask) -> AnimationLayer {
+        self.mask = Some(mask);
+        self
+    }
+
+    pub fn with_blend(mut self, blend: LayerBlend) -> AnimationLayer {
+        self.blend = blend;
+        self
+    }
+
+    /// Name of the playing clip
+    pub fn current_clip(&self) -> Option<&str> {
+        self.current.as_ref().map(|c| c.name.as_str())
+    }
+
+    pub fn time(&self) -> f32 {
+        self.current.as_ref().map_or(0.0, |c| c.time)
+    }
+
+    /// Whether the clip played without looping reached its end
+    pub fn is_finished(&self) -> bool {
+        self.current.as_ref().map_or(true, |c| c.is_finished())
+    }
+
+    fn crossfade(&mut self, mut clip: PlayingClip, duration: f32, skeleton: Option<&Skeleton>) {
+        // Not started yet, bound at the first update
+        if let Some(skeleton) = skeleton {
+            clip.bind(skeleton);
+        }
+
+        let previous = self.current.take();
+        self.fading = match previous {
+            Some(previous) if duration > 0.0 => Some((previous, 0.0, duration)),
+            _ => None,
+        };
+        self.current = Some(clip);
+    }
+
+    fn stop(&mut self) {
+        self.current = None;
+        self.fading = None;
+    }
+
+    fn bind(&mut self, skeleton: &Skeleton) {
+        if let Some(ref mut current) = self.current {
+            current.bind(skeleton);
+        }
+    }
+
+    fn evaluate(&mut self, skeleton: &Skeleton, dt: f32) -> Option<LayerPose> {
+        let mut pose = skeleton.rest_pose();
+        let mut reference = skeleton.rest_pose();
+        let mut animated = vec![false; pose.len()];
+
+        match self.current {
+            Some(ref mut current) => {
+                current.advance(dt);
+                current.write(&mut pose);
+                current.mark_animated(&mut animated);
+                reference.clone_from_slice(&current.reference);
+            }
+            None => return None,
+        }
+
+        let mut faded = false;
+        if let Some((ref mut previous, ref mut elapsed, duration)) = self.fading {
+            previous.advance(dt);
+            previous.mark_animated(&mut animated);
+            *elapsed += dt;
+
+            let mut from = skeleton.rest_pose();
+            previous.write(&mut from);
+
+            let t = (*elapsed / duration).min(1.0);
+            for (p, f) in pose.iter_mut().zip(from.iter()) {
+                *p = f.blend(p, t);
+            }
+            for (r, f) in reference.iter_mut().zip(previous.reference.iter()) {
+                *r = f.blend(r, t);
+            }
+            faded = t >= 1.0;
+        }
+        if faded {
+            self.fading = None;
+        }
+
+        Some(LayerPose {
+            pose,
+            reference,
+            animated,
+        })
+    }
+
+    /// Blend weight of each bone
+    fn bone_weights(&self, skeleton: &Skeleton, animated: &[bool]) -> Vec<f32> {
+        let weight = self.weight.max(0.0).min(1.0);
+        let mask = match self.mask {
+            Some(ref mask) => mask.weights(skeleton),
+            None => vec![1.0; animated.len()],
+        };
+
+        animated
+            .iter()
+            .zip(mask.iter())
+            .map(|(&a, &m)| if a { weight * m } else { 0.0 })
+            .collect()
+    }
+}
+
+/// Plays the animation clips on the skeleton of the game object, its descendants with a
+/// `Name`. The skeleton is captured at the first update, in the rest pose.
+///
+/// The clips play in layers, blended in order over the base layer named `"base"`. The
+/// methods without a layer name control the base layer.
+///
+/// Register it by `WorldBuilder::with_actor::<Animator>()`, before the actors modifying the
+/// animated bones (e.g. `Ragdoll`).
+#[derive(Component)]
+pub struct Animator {
+    pub speed: f32,
+    /// Weight of the animation over the current transforms of the bones
+    pub weight: f32,
+
+    clips: HashMap<String, Rc<CompressedClip>>,
+    layers: Vec<AnimationLayer>,
+    skeleton: Option<Rc<Skeleton>>,
+}
+
+impl Animator {
+    pub fn new() -> Animator {
+        Animator {
+            speed: 1.0,
+            weight: 1.0,
+            clips: HashMap::new(),
+            layers: vec![AnimationLayer::new("base")],
+            skeleton: None,
+        }
+    }
+
+    pub fn with_clip(mut self, name: &str, clip: Rc<CompressedClip>) -> Animator {
+        self.add_clip(name, clip);
+        self
+    }
+
+    pub fn add_clip(&mut self, name: &str, clip: Rc<CompressedClip>) {
+        self.clips.insert(name.to_owned(), clip);
+    }
+
+    pub fn clip(&self, name: &str) -> Option<Rc<CompressedClip>> {
+        self.clips.get(name).cloned()
+    }
+
+    pub fn with_layer(mut self, layer: AnimationLayer) -> Animator {
+        self.add_layer(layer);
+        self
+    }
+
+    /// Add a layer over the others, replace the one with the same name
+    pub fn add_layer(&mut self, mut layer: AnimationLayer) {
+        if let Some(ref skeleton) = self.skeleton {
+            layer.bind(skeleton);
+        }
+
+        match self.layers.iter().position(|l| l.name == layer.name) {
+            Some(i) => self.layers[i] = layer,
+            None => self.layers.push(layer),
+        }
+    }
+
+    /// Remove a layer, except the base layer
+    pub fn remove_layer(&mut self, name: &str) -> Option<AnimationLayer> {
+        match self.layers.iter().skip(1).position(|l| l.name == name) {
+            Some(i) => Some(self.layers.remove(i + 1)),
+            None => None,
+        }
+    }
+
+    pub fn layer(&self, name: &str) -> Option<&AnimationLayer> {
+        self.layers.iter().find(|l| l.name == name)
+    }
+
+    /// The layer, e.g. to change its weight
+    pub fn layer_mut(&mut self, name: &str) -> Option<&mut AnimationLayer> {
+        self.layers.iter_mut().find(|l| l.name == name)
+    }
+
+    /// The skeleton, after the first update
+    pub fn skeleton(&self) -> Option<Rc<Skeleton>> {
+        self.skeleton.clone()
+    }
+
+    pub fn play(&mut self, name: &str, looping: bool) -> bool {
+        self.crossfade(name, 0.0, looping)
+    }
+
+    /// Play a clip, blended from the current one over `duration` seconds
+    pub fn crossfade(&mut self, name: &str, duration: f32, looping: bool) -> bool {
+        self.crossfade_layer("base", name, duration, looping)
+    }
+
+    pub fn play_layer(&mut self, layer: &str, name: &str, looping: bool) -> bool {
+        self.crossfade_layer(layer, name, 0.0, looping)
+    }
+
+    /// Play a clip in a layer, blended from the current one of the layer over `duration`
+    /// seconds
+    pub fn crossfade_layer(
+        &mut self,
+        layer: &str,
+        name: &str,
+        duration: f32,
+        looping: bool,
+    ) -> bool {
+        let clip = match self.clips.get(name) {
+            Some(clip) => PlayingClip::new(name, clip.clone(), looping),
+            None => return false,
+        };
+
+        let skeleton = self.skeleton.clone();
+        match self.layer_mut(layer) {
+            Some(layer) => {
+                layer.crossfade(clip, duration, skeleton.as_ref().map(|s| &**s));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Stop the clips of a layer
+    pub fn stop_layer(&mut self, layer: &str) {
+        if let Some(layer) = self.layer_mut(layer) {
+            layer.stop();
+        }
+    }
+
+    /// Stop the clips of all layers
+    pub fn stop(&mut self) {
+        for layer in self.layers.iter_mut() {
+            layer.stop();
+        }
+    }
+
+    /// Name of the playing clip
+    pub fn current_clip(&self) -> Option<&str> {
+        self.layers[0].current_clip()
+    }
+
+    pub fn time(&self) -> f32 {
+        self.layers[0].time()
+    }
+
+    /// Whether the clip played without looping reached its end
+    pub fn is_finished(&self) -> bool {
+        self.layers[0].is_finished()
+    }
+
+    fn evaluate(&mut self, skeleton: &Skeleton, dt: f32) -> Option<Vec<LocalPose>> {
+        let mut pose = skeleton.rest_pose();
+        let mut playing = false;
+
+        for layer in self.layers.iter_mut() {
+            let layer_pose = match layer.evaluate(skeleton, dt) {
+                Some(layer_pose) => layer_pose,
+                None => continue,
+            };
+            let weights = layer.bone_weights(skeleton, &layer_pose.animated);
+
+            match layer.blend {
+                LayerBlend::Override => blend_override(&mut pose, &layer_pose.pose, &weights),
+                LayerBlend::Additive => blend_additive(
+                    &mut pose,
+                    &layer_pose.pose,
+                    &layer_pose.reference,
+                    &weights,
+                ),
+            }
+            playing = true;
+        }
+
+        if playing {
+            Some(pose)
+        } else {
+            None
+        }
+    }
+}
+
+impl Actor for Animator {
+    fn update(&mut self, go: &mut GameObject, world: &mut World) {
+        if self.skeleton.is_none() {
+            let skeleton = Rc::new(Skeleton::from_root(go));
+
+            // Bind the clips played before the skeleton was captured
+            for layer in self.layers.iter_mut() {
+                layer.bind(&skeleton);
+            }
+            self.skeleton = Some(skeleton);
+        }
+
+        let skeleton = self.skeleton.clone().unwrap();
+        let dt = world.time.delta_time_of(go) as f32 * self.speed;
+
+        let pose = match self.evaluate(&skeleton, dt) {
+            Some(pose) => pose,
+            None => return,
+        };
+
+        let weight = self.weight.max(0.0).min(1.0);
+        if weight >= 1.0 {
+            skeleton.set_pose(&pose);
+        } else if weight > 0.0 {
+            let current = skeleton.current_pose();
+            let blended: Vec<_> = current
+                .iter()
+                .zip(pose.iter())
+                .map(|(c, p)| c.blend(p, weight))
+                .collect();
+            skeleton.set_pose(&blended);
+        }
+    }
+}
@@ -0,0 +1,366 @@
+#[cfg(feature = "physics")]
+use engine::sound::Occlusion;
+use engine::sound::AudioLodLevel;
+use engine::{AudioListener, EmitterHandle, GameObject, VoiceHandle};
+use world::{Actor, World};
+
+use math::*;
+
+/// Meters per second
+const SPEED_OF_SOUND: f32 = 343.0;
+
+/// Frames before a looping source whose channel was taken plays again, if it keeps its voice
+const STOLEN_RETRY_FRAMES: u32 = 30;
+
+#[derive(Debug, Clone, Copy)]
+struct VoiceParams {
+    volume: f32,
+    balance: f32,
+    lowpass: f32,
+    pitch: f32,
+}
+
+impl VoiceParams {
+    fn differs(&self, other: &VoiceParams) -> bool {
+        (self.volume - other.volume).abs() > 0.001
+            || (self.balance - other.balance).abs() > 0.001
+            || (self.lowpass - other.lowpass).abs() > 0.001
+            || (self.pitch - other.pitch).abs() > 0.001
+    }
+}
+
+/// A positional sound played at the game object, e.g. a machine hum or a radio.
+///
+/// The volume, balance and doppler pitch follow the listener. With the `physics` feature, the
+/// sound is also attenuated and muffled behind the static colliders, by the materials of
+/// `world.sound.occlusion`.
+///
+/// The sources share the voices of `world.sound.lod`: a looping sound without voice is
+/// virtual, it plays again from its start once it gets a voice, or when its channel is taken
+/// by another sound. The distant and occluded sources are updated less often, the farthest
+/// ones are not spatialized and rarely updated.
+///
+/// Register it by `WorldBuilder::with_actor::<AudioSource>()`. A looping sound plays until
+/// `stop`, call it before removing the component.
+#[derive(Component)]
+pub struct AudioSource {
+    pub sound: String,
+    pub looping: bool,
+    pub volume: f32,
+    /// The volume falls linearly to 0 at this distance from the listener
+    pub max_distance: f32,
+    /// Sources of higher priority take the voices of the others
+    pub priority: usize,
+    /// Attenuate the sound behind the colliders
+    pub occluded: bool,
+    /// Seconds to fade the occlusion changes, e.g. when a door closes
+    pub occlusion_smoothing: f32,
+    /// Scale of the doppler effect, 0 to disable it
+    pub doppler: f32,
+
+    /// Play or stop at the next update
+    pending: Option<bool>,
+    /// Whether the sound should be playing
+    active: bool,
+    /// Whether a voice was requested since `play`
+    requested: bool,
+    voice: Option<VoiceHandle>,
+    emitter: Option<EmitterHandle>,
+    params: Option<VoiceParams>,
+    /// Parameters of the voice, sent when they change
+    sent: Option<VoiceParams>,
+    /// Frame the channel of the voice was taken
+    stolen: Option<u32>,
+    frame: u32,
+    /// Source and listener positions of the last update, for the doppler effect
+    last_positions: Option<(Vector3<f32>, Vector3<f32>)>,
+    #[cfg(feature = "physics")]
+    occlusion: Option<Occlusion>,
+}
+
+impl AudioSource {
+    pub fn new(sound: &str) -> AudioSource {
+        AudioSource {
+            sound: sound.to_owned(),
+            looping: true,
+            volume: 1.0,
+            max_distance: 20.0,
+            priority: 0,
+            occluded: true,
+            occlusion_smoothing: 0.2,
+            doppler: 1.0,
+
+            pending: Some(true),
+            active: false,
+            requested: false,
+            voice: None,
+            emitter: None,
+            params: None,
+            sent: None,
+            stolen: None,
+            frame: 0,
+            last_positions: None,
+            #[cfg(feature = "physics")]
+            occlusion: None,
+        }
+    }
+
+    /// Play once, when `play` is called
+    pub fn one_shot(mut self) -> AudioSource {
+        self.looping = false;
+        self.pending = None;
+        self
+    }
+
+    pub fn with_max_distance(mut self, distance: f32) -> AudioSource {
+        self.max_distance = distance;
+        self
+    }
+
+    pub fn with_priority(mut self, priority: usize) -> AudioSource {
+        self.priority = priority;
+        self
+    }
+
+    /// Restart the sound at the next update
+    pub fn play(&mut self) {
+        self.pending = Some(true);
+    }
+
+    pub fn stop(&mut self) {
+        self.pending = Some(false);
+    }
+
+    /// Whether the sound has a voice, a looping sound out of voices is virtual
+    pub fn is_audible(&self) -> bool {
+        self.voice.is_some()
+    }
+
+    /// Volume multiplier and low-pass of the occlusion
+    #[cfg(feature = "physics")]
+    fn occlusion(
+        &mut self,
+        listener: Vector3<f32>,
+        pos: Vector3<f32>,
+        dt: f32,
+        world: &World,
+    ) -> (f32, f32) {
+        if !self.occluded {
+            return (1.0, 1.0);
+        }
+
+        let target = world
+            .sound
+            .occlusion
+            .occlusion(&world.physics, listener, pos);
+
+        let t = if self.occlusion_smoothing > 0.0 {
+            1.0 - (-dt / self.occlusion_smoothing).exp()
+        } else {
+            1.0
+        };
+
+        let occlusion = match self.occlusion {
+            Some(occlusion) => occlusion.lerp(&target, t),
+            None => target,
+        };
+        self.occlusion = Some(occlusion);
+
+        (occlusion.volume, occlusion.lowpass)
+    }
+
+    #[cfg(not(feature = "physics"))]
+    fn occlusion(&mut self, _: Vector3<f32>, _: Vector3<f32>, _: f32, _: &World) -> (f32, f32) {
+        (1.0, 1.0)
+    }
+
+    /// Volume multiplier of the last occlusion
+    fn occlusion_volume(&self) -> f32 {
+        #[cfg(feature = "physics")]
+        {
+            if self.occluded {
+                return self.occlusion.map_or(1.0, |o| o.volume);
+            }
+        }
+        1.0
+    }
+
+    fn doppler_pitch(&self, listener: Vector3<f32>, pos: Vector3<f32>, dt: f32) -> f32 {
+        let (last_pos, last_listener) = match self.last_positions {
+            Some(last) if dt > 0.0 && self.doppler > 0.0 => last,
+            _ => return 1.0,
+        };
+
+        let dir = pos - listener;
+        if dir.magnitude2() < 1e-6 {
+            return 1.0;
+        }
+        let dir = dir.normalize();
+
+        // Fast objects and teleports stay below the speed of sound
+        let limit = SPEED_OF_SOUND * 0.5;
+        let source_speed = ((pos - last_pos) / dt).dot(dir).max(-limit).min(limit);
+        let listener_speed = ((listener - last_listener) / dt).dot(dir).max(-limit).min(limit);
+
+        let pitch = (SPEED_OF_SOUND + listener_speed) / (SPEED_OF_SOUND + source_speed);
+        1.0 + (pitch - 1.0) * self.doppler
+    }
+
+    fn compute_params(
+        &mut self,
+        listener: Option<AudioListener>,
+        pos: Vector3<f32>,
+        level: AudioLodLevel,
+        dt: f32,
+        world: &World,
+    ) -> VoiceParams {
+        let listener = match listener {
+            Some(listener) => listener,
+            None => {
+                return VoiceParams {
+                    volume: self.volume,
+                    balance: 0.5,
+                    lowpass: 1.0,
+                    pitch: 1.0,
+                }
+            }
+        };
+
+        let (volume, balance) = listener.attenuate(pos, self.max_distance);
+        if level == AudioLodLevel::Flat {
+            self.last_positions = None;
+            return VoiceParams {
+                volume: self.volume * volume * self.occlusion_volume(),
+                balance: 0.5,
+                lowpass: 1.0,
+                pitch: 1.0,
+            };
+        }
+
+        let (occlusion, lowpass) = self.occlusion(listener.position, pos, dt, world);
+        let pitch = self.doppler_pitch(listener.position, pos, dt);
+        self.last_positions = Some((pos, listener.position));
+
+        VoiceParams {
+            volume: self.volume * volume * occlusion,
+            balance,
+            lowpass,
+            pitch,
+        }
+    }
+}
+
+impl Actor for AudioSource {
+    fn update(&mut self, go: &mut GameObject, world: &mut World) {
+        if let Some(play) = self.pending.take() {
+            if let Some(voice) = self.voice.take() {
+                world.sound.stop_voice(voice);
+            }
+            self.active = play;
+            self.requested = false;
+            self.params = None;
+            self.stolen = None;
+        }
+
+        if !self.active {
+            return;
+        }
+
+        let emitter = match self.emitter {
+            Some(emitter) => emitter,
+            None => {
+                let emitter = world.sound.new_emitter();
+                self.emitter = Some(emitter);
+                emitter
+            }
+        };
+
+        let pos = go.transform.global().disp;
+        let listener = world.audio_listener();
+        let distance = listener.map_or(0.0, |l| (pos - l.position).magnitude());
+        let level = world.sound.lod.level(distance, self.occlusion_volume());
+
+        self.frame = self.frame.wrapping_add(1);
+        let refresh = self.params.is_none() || world.sound.lod.is_update_frame(level, self.frame);
+        if refresh {
+            // Time since the last update, for the smoothing and the doppler
+            let interval = world.sound.lod.update_interval(level);
+            let dt = world.delta_time() as f32 * interval as f32;
+            self.params = Some(self.compute_params(listener, pos, level, dt, world));
+        }
+        let params = self.params.unwrap();
+
+        world
+            .sound
+            .request_voice(emitter, self.priority, params.volume);
+        let requested = self.requested;
+        self.requested = true;
+
+        // The channel was taken by another sound, a looping sound plays again later
+        if let Some(voice) = self.voice {
+            if world.sound.is_voice_lost(voice) {
+                world.sound.stop_voice(voice);
+                self.voice = None;
+                self.stolen = Some(self.frame);
+                if !self.looping {
+                    self.active = false;
+                    return;
+                }
+            }
+        }
+
+        // The voices are allocated at the end of the frame
+        if !world.sound.has_voice(emitter) {
+            // Virtual until it gets a voice, a one shot is dropped
+            if let Some(voice) = self.voice.take() {
+                world.sound.stop_voice(voice);
+            }
+            // Promoted again later, it plays at once
+            self.stolen = None;
+            if !self.looping && requested {
+                self.active = false;
+            }
+            return;
+        }
+
+        match self.voice {
+            Some(voice) => {
+                if self.sent.map_or(true, |sent| sent.differs(&params)) {
+                    world
+                        .sound
+                        .set_voice_params(voice, params.volume, params.balance, params.lowpass);
+                    world.sound.set_voice_pitch(voice, params.pitch);
+                    self.sent = Some(params);
+                }
+
+                // A one shot plays on without the voice allocation
+                if !self.looping {
+                    self.active = false;
+                }
+            }
+            None => {
+                if let Some(frame) = self.stolen {
+                    if self.frame.wrapping_sub(frame) < STOLEN_RETRY_FRAMES {
+                        return;
+                    }
+                    self.stolen = None;
+                }
+
+                let handle = world.sound.load_sound(&self.sound);
+                let voice = world.sound.play_voice(
+                    handle,
+                    self.looping,
+                    self.priority,
+                    params.volume,
+                    params.balance,
+                );
+                world
+                    .sound
+                    .set_voice_params(voice, params.volume, params.balance, params.lowpass);
+                world.sound.set_voice_pitch(voice, params.pitch);
+                self.voice = Some(voice);
+                self.sent = Some(params);
+            }
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use engine::{Component, GameObject, Light, Material};
+use world::{Actor, World};
+
+use math::*;
+use std::cmp::Ordering;
+use std::rc::Rc;
+use std::sync::{Arc, Weak};
+
+/// Occluders bound to the receiving materials, the nearest to the camera are kept
+pub const MAX_BLOB_OCCLUDERS: usize = 8;
+
+/// Shape of a blob shadow occluder, in the local space of its game object
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OccluderShape {
+    Sphere { center: Vector3f, radius: f32 },
+    /// Segment from `a` to `b` swept by a sphere, e.g. a character body
+    Capsule { a: Vector3f, b: Vector3f, radius: f32 },
+}
+
+impl OccluderShape {
+    /// (a, b, radius) in world space, a sphere has a degenerated segment
+    fn world(&self, go: &GameObject) -> (Vector3f, Vector3f, f32) {
+        let global = go.transform.global();
+        let to_world = |p: Vector3f| global.disp + global.rot * (p * global.scale);
+
+        match *self {
+            OccluderShape::Sphere { center, radius } => {
+                let c = to_world(center);
+                (c, c, radius * global.scale)
+            }
+            OccluderShape::Capsule { a, b, radius } => {
+                (to_world(a), to_world(b), radius * global.scale)
+            }
+        }
+    }
+}
+
+/// A sphere or capsule casting a blob shadow, e.g. on a character.
+///
+/// The shape is sent each frame to the `BlobShadows` of the scene, found once at start.
+///
+/// Register it by `WorldBuilder::with_actor::<BlobOccluder>()`.
+#[derive(Component)]
+pub struct BlobOccluder {
+    pub shape: OccluderShape,
+
+    shadows: Weak<Component>,
+}
+
+impl BlobOccluder {
+    pub fn new(shape: OccluderShape) -> BlobOccluder {
+        BlobOccluder {
+            shape,
+            shadows: Weak::new(),
+        }
+    }
+
+    fn find_shadows(&mut self, world: &World) -> Option<Arc<Component>> {
+        if let Some(c) = self.shadows.upgrade() {
+            return Some(c);
+        }
+
+        // Not created yet, or removed and replaced
+        let c = world.engine().find_component::<BlobShadows>();
+        if let Some(ref c) = c {
+            self.shadows = Arc::downgrade(c);
+        }
+        c
+    }
+}
+
+impl Actor for BlobOccluder {
+    fn start(&mut self, _go: &mut GameObject, world: &mut World) {
+        self.find_shadows(world);
+    }
+
+    fn update(&mut self, go: &mut GameObject, world: &mut World) {
+        let (a, b, radius) = self.shape.world(go);
+
+        if let Some(c) = self.find_shadows(world) {
+            if let Some(shadows) = c.try_as::<BlobShadows>() {
+                shadows.borrow_mut().add_occluder(a, b, radius);
+            }
+        }
+    }
+}
+
+/// Cheap analytic soft shadows of the dynamic objects, for scenes with baked lighting.
+///
+/// The `BlobOccluder`s of the scene (spheres and capsules) shadow the `materials`, typically
+/// the lightmap materials of the static geometry, without any shadow map. The shadow is cast
+/// along the light direction with a penumbra growing with the distance, and a contact
+/// darkening is added under the occluders. The shaders read them with
+/// `unrust/blob_shadow.glsl` (`unrust/lightmap` does), from the uniforms:
+/// * `uBlobOccluderA[i]`, `uBlobOccluderB[i]` : the segment ends, with the radius in `w`
+/// * `uBlobOccluderCount`, `uBlobLightDirection` (toward the light)
+/// * `uBlobShadowStrength`, `uBlobShadowSharpness`, `uBlobAmbientOcclusion`
+///
+/// Register it by `WorldBuilder::with_actor::<BlobShadows>()`.
+#[derive(Component)]
+pub struct BlobShadows {
+    pub materials: Vec<Rc<Material>>,
+    /// Toward the light, `None` for the main directional light
+    pub light_direction: Option<Vector3f>,
+    /// Darkness of a full shadow, from 0 to 1
+    pub strength: f32,
+    /// Larger for harder penumbras
+    pub sharpness: f32,
+    /// Darkness of the contact occlusion, from 0 to 1
+    pub ambient_occlusion: f32,
+
+    occluders: Vec<(Vector3f, Vector3f, f32)>,
+}
+
+impl BlobShadows {
+    pub fn new() -> BlobShadows {
+        BlobShadows {
+            materials: Vec::new(),
+            light_direction: None,
+            strength: 0.7,
+            sharpness: 4.0,
+            ambient_occlusion: 0.5,
+            occluders: Vec::new(),
+        }
+    }
+
+    pub fn with_material(mut self, material: Rc<Material>) -> BlobShadows {
+        self.materials.push(material);
+        self
+    }
+
+    /// Cast a shadow this frame from the segment `[a, b]` swept by a sphere, in world space
+    pub fn add_occluder(&mut self, a: Vector3f, b: Vector3f, radius: f32) {
+        self.occluders.push((a, b, radius));
+    }
+
+    /// The occluders of this frame
+    pub fn occluders(&self) -> &[(Vector3f, Vector3f, f32)] {
+        &self.occluders
+    }
+
+    /// Keep the `MAX_BLOB_OCCLUDERS` occluders nearest to `eye`, without the invalid ones
+    pub fn keep_nearest(&mut self, eye: Vector3f) {
+        let dist = |o: &(Vector3f, Vector3f, f32)| ((o.0 + o.1) * 0.5 - eye).magnitude2();
+        self.occluders
+            .retain(|o| dist(o).is_finite() && o.2.is_finite() && o.2 > 0.0);
+        self.occluders
+            .sort_by(|a, b| dist(a).partial_cmp(&dist(b)).unwrap_or(Ordering::Equal));
+        self.occluders.truncate(MAX_BLOB_OCCLUDERS);
+    }
+
+    fn light_direction(&self, world: &World) -> Vector3f {
+        if let Some(d) = self.light_direction {
+            return d.normalize();
+        }
+
+        let light = world.engine().find_main_light();
+        let light = light.as_ref().and_then(|c| c.try_as::<Light>()).map(|l| l.borrow());
+
+        match light.as_ref().and_then(|l| l.directional()) {
+            Some(l) => -l.world_space_direction.normalize(),
+            None => Vector3::unit_y(),
+        }
+    }
+}
+
+impl Actor for BlobShadows {
+    // Once all occluders are updated
+    fn late_update(&mut self, _go: &mut GameObject, world: &mut World) {
+        let eye = match world.current_camera() {
+            Some(cam) => {
+                let eye = cam.borrow().eye();
+                eye
+            }
+            None => Vector3::zero(),
+        };
+
+        self.keep_nearest(eye);
+
+        let light_direction = self.light_direction(world);
+
+        for material in self.materials.iter() {
+            material.set("uBlobOccluderCount", self.occluders.len() as f32);
+            material.set("uBlobLightDirection", light_direction);
+            material.set("uBlobShadowStrength", self.strength);
+            material.set("uBlobShadowSharpness", self.sharpness);
+            material.set("uBlobAmbientOcclusion", self.ambient_occlusion);
+
+            for (i, &(a, b, r)) in self.occluders.iter().enumerate() {
+                material.set(format!("uBlobOccluderA[{}]", i), a.extend(r));
+                material.set(format!("uBlobOccluderB[{}]", i), b.extend(r));
+            }
+        }
+
+        self.occluders.clear();
+    }
+}
@@ -0,0 +1,254 @@
+//! Constraints moving their game object from other game objects, in the `late_update` phase,
+//! once the actors have moved the targets. Register them by `WorldBuilder::with_actor`.
+//!
+//! A constraint following another constrained object may lag one frame behind, depending on
+//! the registration order.
+
+use actors::LookAtTarget;
+use engine::animation::Interpolate;
+use engine::GameObject;
+use world::{Actor, Handle, World};
+
+use math::*;
+
+fn global_of(go: &Handle<GameObject>) -> Option<Isometry3<f32>> {
+    // The constrained object itself, or an object being updated
+    go.try_borrow().ok().map(|go| go.transform.global())
+}
+
+/// Blend factor of a smoothing over `time` seconds, 1 for no smoothing
+fn smoothing_factor(time: f32, dt: f32) -> f32 {
+    if time > 0.0 {
+        1.0 - (-dt / time).exp()
+    } else {
+        1.0
+    }
+}
+
+fn blend(from: Isometry3<f32>, to: Isometry3<f32>, t: f32) -> Isometry3<f32> {
+    Isometry3 {
+        disp: from.disp.interpolate(&to.disp, t),
+        rot: from.rot.interpolate(&to.rot, t),
+        scale: from.scale + (to.scale - from.scale) * t,
+    }
+}
+
+/// A target of a `ParentConstraint`
+pub struct ConstraintSource {
+    pub target: Handle<GameObject>,
+    pub weight: f32,
+    /// Transform relative to the target
+    pub offset: Isometry3<f32>,
+}
+
+/// Moves the game object as if it was a child of its sources, blended by their weights.
+///
+/// ```ignore
+/// // A prop carried with both hands
+/// let mut constraint = ParentConstraint::new();
+/// constraint.add_source(left_hand, 0.5, left_grip);
+/// constraint.add_source(right_hand, 0.5, right_grip);
+/// ```
+#[derive(Component)]
+pub struct ParentConstraint {
+    pub sources: Vec<ConstraintSource>,
+    /// Weight over the current transform
+    pub weight: f32,
+}
+
+impl ParentConstraint {
+    pub fn new() -> ParentConstraint {
+        ParentConstraint {
+            sources: Vec::new(),
+            weight: 1.0,
+        }
+    }
+
+    pub fn add_source(&mut self, target: Handle<GameObject>, weight: f32, offset: Isometry3<f32>) {
+        self.sources.push(ConstraintSource {
+            target,
+            weight,
+            offset,
+        });
+    }
+
+    /// Keep the current transform relative to the target
+    pub fn add_source_keep_offset(
+        &mut self,
+        go: &GameObject,
+        target: Handle<GameObject>,
+        weight: f32,
+    ) {
+        let offset = match global_of(&target).and_then(|t| t.inverse_transform()) {
+            Some(inverse) => inverse.concat(&go.transform.global()),
+            None => Isometry3::one(),
+        };
+        self.add_source(target, weight, offset);
+    }
+}
+
+impl Actor for ParentConstraint {
+    fn late_update(&mut self, go: &mut GameObject, _world: &mut World) {
+        let mut result: Option<Isometry3<f32>> = None;
+        let mut total = 0.0;
+
+        for source in self.sources.iter().filter(|s| s.weight > 0.0) {
+            let global = match global_of(&source.target) {
+                Some(global) => global.concat(&source.offset),
+                None => continue,
+            };
+
+            // Running average, each source weighted by its share of the weights so far
+            total += source.weight;
+            result = Some(match result {
+                Some(r) => blend(r, global, source.weight / total),
+                None => global,
+            });
+        }
+
+        if let Some(result) = result {
+            let weight = self.weight.max(0.0).min(1.0);
+            let current = go.transform.global();
+            go.transform.set_global(blend(current, result, weight));
+        }
+    }
+}
+
+/// Turns the game object so its `aim_axis` points at the target, keeping its `up_axis` toward
+/// `world_up`, e.g. a turret or a security camera.
+#[derive(Component)]
+pub struct AimConstraint {
+    pub target: Option<LookAtTarget>,
+    /// Local axis pointing at the target
+    pub aim_axis: Vector3<f32>,
+    /// Local axis kept toward `world_up`
+    pub up_axis: Vector3<f32>,
+    pub world_up: Vector3<f32>,
+    /// Seconds to catch up with the target, 0 to aim instantly
+    pub smoothing: f32,
+    pub weight: f32,
+}
+
+impl AimConstraint {
+    pub fn new() -> AimConstraint {
+        AimConstraint {
+            target: None,
+            aim_axis: Vector3::unit_z(),
+            up_axis: Vector3::unit_y(),
+            world_up: Vector3::unit_y(),
+            smoothing: 0.0,
+            weight: 1.0,
+        }
+    }
+
+    /// Global rotation pointing `aim_axis` to `dir`
+    pub fn aim_rotation(&self, dir: Vector3<f32>) -> Quaternion<f32> {
+        let dir = dir.normalize();
+        let aim = Quaternion::from_arc(self.aim_axis.normalize(), dir, None);
+
+        // Twist around the aim direction to keep the up axis
+        let up = aim * self.up_axis;
+        let up = up - dir * up.dot(dir);
+        let world_up = self.world_up - dir * self.world_up.dot(dir);
+        if up.magnitude2() < 1e-8 || world_up.magnitude2() < 1e-8 {
+            return aim;
+        }
+
+        let twist = Quaternion::from_arc(up.normalize(), world_up.normalize(), Some(dir));
+        (twist * aim).normalize()
+    }
+}
+
+impl Actor for AimConstraint {
+    fn late_update(&mut self, go: &mut GameObject, world: &mut World) {
+        let point = match self.target {
+            Some(LookAtTarget::Point(p)) => p,
+            Some(LookAtTarget::Object(ref target)) => match global_of(target) {
+                Some(global) => global.disp,
+                None => return,
+            },
+            None => return,
+        };
+
+        let mut global = go.transform.global();
+        let dir = point - global.disp;
+        if dir.magnitude2() < 1e-10 {
+            return;
+        }
+
+        let dt = world.time.delta_time_of(go) as f32;
+        let t = smoothing_factor(self.smoothing, dt) * self.weight.max(0.0).min(1.0);
+
+        global.rot = global.rot.interpolate(&self.aim_rotation(dir), t);
+        go.transform.set_global(global);
+    }
+}
+
+/// Copies the position and / or the rotation of a target with an offset, e.g. a camera
+/// following a vehicle.
+#[derive(Component)]
+pub struct FollowConstraint {
+    pub target: Option<Handle<GameObject>>,
+    /// Transform relative to the target
+    pub offset: Isometry3<f32>,
+    pub position: bool,
+    pub rotation: bool,
+    /// Seconds to catch up with the target, 0 to follow rigidly
+    pub smoothing: f32,
+    pub weight: f32,
+}
+
+impl FollowConstraint {
+    pub fn new(target: Handle<GameObject>) -> FollowConstraint {
+        FollowConstraint {
+            target: Some(target),
+            offset: Isometry3::one(),
+            position: true,
+            rotation: true,
+            smoothing: 0.0,
+            weight: 1.0,
+        }
+    }
+
+    pub fn with_offset(mut self, offset: Isometry3<f32>) -> FollowConstraint {
+        self.offset = offset;
+        self
+    }
+
+    /// Follow the position only, the offset is then in the world axes
+    pub fn position_only(mut self) -> FollowConstraint {
+        self.rotation = false;
+        self
+    }
+}
+
+impl Actor for FollowConstraint {
+    fn late_update(&mut self, go: &mut GameObject, world: &mut World) {
+        let target = match self.target.as_ref().and_then(global_of) {
+            Some(target) => target,
+            None => return,
+        };
+
+        let desired = if self.rotation {
+            target.concat(&self.offset)
+        } else {
+            Isometry3 {
+                disp: target.disp + self.offset.disp,
+                rot: self.offset.rot,
+                scale: target.scale,
+            }
+        };
+
+        let dt = world.time.delta_time_of(go) as f32;
+        let t = smoothing_factor(self.smoothing, dt) * self.weight.max(0.0).min(1.0);
+
+        let mut global = go.transform.global();
+        if self.position {
+            global.disp = global.disp.interpolate(&desired.disp, t);
+        }
+        if self.rotation {
+            global.rot = global.rot.interpolate(&desired.rot, t);
+        }
+        go.transform.set_global(global);
+    }
+}
@@ -0,0 +1,386 @@
+use engine::procgen::Random;
+use engine::{Asset, CullMode, GameObject, Material, Mesh, MeshBuffer, MeshData, Texture,
+             TextureFiltering, TextureImage};
+use world::{Actor, Handle, World};
+
+#[cfg(feature = "physics")]
+use engine::physics::HeightField;
+
+use image::{ImageBuffer, Rgba};
+use math::*;
+use std::rc::Rc;
+
+/// Objects bending the details at once
+pub const MAX_DETAIL_BENDERS: usize = 4;
+
+/// Vertices of a buffer at most, with the 2 vertices of the bounds
+const MAX_BUFFER_VERTICES: usize = 65535 - 2;
+
+/// Heights of the terrain under the details, packed in 16 bits in the red and green channels
+/// of a texture, since float textures are not always available
+pub struct DetailTerrain {
+    pub height: Rc<Texture>,
+    /// Lowest and highest height
+    pub height_range: (f32, f32),
+    /// Position (x, z) of the first sample
+    pub origin: Vector2<f32>,
+    pub cell_size: f32,
+    /// Samples along x and z
+    pub samples: (usize, usize),
+}
+
+impl DetailTerrain {
+    /// Row major heights, `columns` samples along x and `rows` along z
+    pub fn new(
+        columns: usize,
+        rows: usize,
+        cell_size: f32,
+        heights: &[f32],
+        origin: Vector3<f32>,
+    ) -> DetailTerrain {
+        let (lo, hi) = heights
+            .iter()
+            .fold((::std::f32::MAX, ::std::f32::MIN), |(lo, hi), h| {
+                (lo.min(*h), hi.max(*h))
+            });
+        let scale = if hi > lo { 65535.0 / (hi - lo) } else { 0.0 };
+
+        let img = ImageBuffer::from_fn(columns as u32, rows as u32, |x, y| {
+            let h = heights[y as usize * columns + x as usize];
+            let v = ((h - lo) * scale).round() as u32;
+            Rgba([(v >> 8) as u8, (v & 255) as u8, 0, 255])
+        });
+
+        let height = Texture::new(TextureImage::Rgba(img));
+        height.filtering.set(TextureFiltering::Nearest);
+
+        DetailTerrain {
+            height,
+            height_range: (origin.y + lo, origin.y + hi),
+            origin: Vector2::new(origin.x, origin.z),
+            cell_size,
+            samples: (columns, rows),
+        }
+    }
+
+    #[cfg(feature = "physics")]
+    pub fn from_height_field(hf: &HeightField) -> DetailTerrain {
+        DetailTerrain::new(hf.columns, hf.rows, hf.cell_size, &hf.heights, hf.origin)
+    }
+}
+
+pub enum DetailShape {
+    /// Two crossed quads, standing on their bottom edge, e.g. grass
+    Blades { width: f32, height: f32 },
+    /// Copies of a mesh with y up, standing on y = 0, e.g. pebbles
+    Mesh(MeshData),
+}
+
+impl DetailShape {
+    fn height(&self) -> f32 {
+        match *self {
+            DetailShape::Blades { height, .. } => height,
+            DetailShape::Mesh(ref data) => data.compute_bound().aabb.max.y,
+        }
+    }
+
+    /// Vertices, uvs, normals and indices of a copy
+    fn geometry(&self) -> (Vec<f32>, Vec<f32>, Vec<f32>, Vec<u16>) {
+        match *self {
+            DetailShape::Blades { width, height } => {
+                let w = width * 0.5;
+                let vertices = vec![
+                    -w, 0.0, 0.0, w, 0.0, 0.0, w, height, 0.0, -w, height, 0.0,
+                    0.0, 0.0, -w, 0.0, 0.0, w, 0.0, height, w, 0.0, height, -w,
+                ];
+                let uvs = vec![
+                    0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0, 1.0,
+                    0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0, 1.0,
+                ];
+                // Lit like the ground under
+                let normals = [0.0, 1.0, 0.0].iter().cycle().take(24).cloned().collect();
+                let indices = vec![0, 1, 2, 2, 3, 0, 4, 5, 6, 6, 7, 4];
+                (vertices, uvs, normals, indices)
+            }
+            DetailShape::Mesh(ref data) => {
+                let count = data.vertices.len() / 3;
+                let uvs = data.uvs.clone().unwrap_or_else(|| vec![0.0; count * 2]);
+                let normals = data.normals.clone().unwrap_or_else(|| {
+                    [0.0, 1.0, 0.0].iter().cycle().take(count * 3).cloned().collect()
+                });
+                (data.vertices.clone(), uvs, normals, data.indices.clone())
+            }
+        }
+    }
+}
+
+/// Meshes of `count` copies of a shape on a jittered grid in a square patch, centered on
+/// the origin. The tangents hold the base (x, z) and the random seed of each copy, and the
+/// buffers end with 2 vertices (not in the triangles) bounding the patch up to
+/// `max_height` above and below.
+pub fn build_detail_patch(
+    shape: &DetailShape,
+    count: usize,
+    patch_size: f32,
+    max_height: f32,
+    seed: u32,
+) -> Vec<MeshData> {
+    let (vertices, uvs, normals, indices) = shape.geometry();
+    let copy_vertices = vertices.len() / 3;
+    let per_buffer = (MAX_BUFFER_VERTICES / copy_vertices.max(1)).max(1);
+
+    let mut rng = Random::new(seed);
+    let cells = (count as f32).sqrt().ceil().max(1.0) as usize;
+    let cell = patch_size / cells as f32;
+    let half = patch_size * 0.5;
+
+    let mut buffers = Vec::new();
+    let mut buffer = PatchBuffer::default();
+
+    for i in 0..count {
+        let x = -half + ((i % cells) as f32 + rng.next_f32()) * cell;
+        let z = -half + ((i / cells) as f32 + rng.next_f32()) * cell;
+        let copy_seed = rng.next_f32();
+
+        let base = (buffer.vertices.len() / 3) as u16;
+        buffer.vertices.extend_from_slice(&vertices);
+        buffer.indices.extend(indices.iter().map(|j| j + base));
+        buffer.uvs.extend_from_slice(&uvs);
+        buffer.normals.extend_from_slice(&normals);
+        for _ in 0..copy_vertices {
+            buffer.tangents.extend_from_slice(&[x, z, copy_seed]);
+        }
+
+        if (i + 1) % per_buffer == 0 || i + 1 == count {
+            let full = ::std::mem::replace(&mut buffer, PatchBuffer::default());
+            buffers.push(full.finish(half, max_height));
+        }
+    }
+
+    buffers
+}
+
+#[derive(Default)]
+struct PatchBuffer {
+    vertices: Vec<f32>,
+    uvs: Vec<f32>,
+    normals: Vec<f32>,
+    tangents: Vec<f32>,
+    indices: Vec<u16>,
+}
+
+impl PatchBuffer {
+    fn finish(mut self, half: f32, max_height: f32) -> MeshData {
+        self.vertices
+            .extend_from_slice(&[-half, -max_height, -half, half, max_height, half]);
+        self.uvs.extend_from_slice(&[0.0; 4]);
+        self.normals.extend_from_slice(&[0.0, 1.0, 0.0, 0.0, 1.0, 0.0]);
+        // Seeds above any density
+        self.tangents.extend_from_slice(&[0.0, 0.0, 2.0, 0.0, 0.0, 2.0]);
+
+        MeshData {
+            vertices: self.vertices,
+            uvs: Some(self.uvs),
+            uvs2: None,
+            normals: Some(self.normals),
+            tangents: Some(self.tangents),
+            bitangents: None,
+            indices: self.indices,
+        }
+    }
+}
+
+/// Details (grass, pebbles...) scattered on a terrain by the GPU, in a square patch around
+/// the camera. Each copy has a fixed place in the world, and is kept when the density
+/// texture (e.g. a channel of a splat map, or baked noise) is over its random seed.
+///
+/// The copies fade out with the distance, sway in the wind, and bend away from the
+/// benders (e.g. the characters walking through). The layer is in world space, its own
+/// game object is not used. The positions are read from textures in the vertex shader.
+///
+/// ```ignore
+/// let terrain = DetailTerrain::from_height_field(&hf);
+/// let grass = DetailLayer::new(terrain, DetailShape::Blades { width: 0.4, height: 0.6 },
+///                              20000, "grass.png")
+///     .with_density(splat.to_texture(), 1);
+/// ```
+///
+/// Register it by `WorldBuilder::with_actor::<DetailLayer>()`.
+#[derive(Component)]
+pub struct DetailLayer {
+    pub terrain: DetailTerrain,
+    /// The first channel is used without density texture
+    pub density: Option<Rc<Texture>>,
+    pub density_channel: usize,
+    pub density_scale: f32,
+    /// Side of the patch around the camera, in world units
+    pub patch_size: f32,
+    /// Distances where the fade starts and ends
+    pub fade_distance: (f32, f32),
+    pub scale_range: (f32, f32),
+    /// Direction times strength of the wind, on the xz plane
+    pub wind: Vector2<f32>,
+    pub wind_speed: f32,
+    /// Frequency of the gusts along the wind
+    pub wind_frequency: f32,
+    pub bend_strength: f32,
+    pub color: Vector4<f32>,
+    /// Toward the light
+    pub light_direction: Vector3<f32>,
+
+    shape: DetailShape,
+    count: usize,
+    seed: u32,
+    texture: String,
+    benders: Vec<(Handle<GameObject>, f32)>,
+    material: Option<Rc<Material>>,
+    patch: Option<Handle<GameObject>>,
+    time: f32,
+}
+
+impl DetailLayer {
+    pub fn new(
+        terrain: DetailTerrain,
+        shape: DetailShape,
+        count: usize,
+        texture: &str,
+    ) -> DetailLayer {
+        DetailLayer {
+            terrain,
+            density: None,
+            density_channel: 0,
+            density_scale: 1.0,
+            patch_size: 40.0,
+            fade_distance: (15.0, 20.0),
+            scale_range: (0.8, 1.2),
+            wind: Vector2::new(0.2, 0.1),
+            wind_speed: 1.5,
+            wind_frequency: 0.3,
+            bend_strength: 1.0,
+            color: Vector4::new(1.0, 1.0, 1.0, 1.0),
+            light_direction: Vector3::new(0.3, 1.0, 0.2),
+            shape,
+            count,
+            seed: 1,
+            texture: texture.to_owned(),
+            benders: Vec::new(),
+            material: None,
+            patch: None,
+            time: 0.0,
+        }
+    }
+
+    /// The density of the copies, in a channel (0 to 3) of a texture over the terrain
+    pub fn with_density(mut self, texture: Rc<Texture>, channel: usize) -> DetailLayer {
+        self.density = Some(texture);
+        self.density_channel = channel.min(3);
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u32) -> DetailLayer {
+        self.seed = seed;
+        self
+    }
+
+    /// Bend the details within `radius` of a game object, the first `MAX_DETAIL_BENDERS`
+    /// are used
+    pub fn add_bender(&mut self, go: Handle<GameObject>, radius: f32) {
+        self.benders.push((go, radius));
+    }
+
+    pub fn remove_bender(&mut self, go: &Handle<GameObject>) {
+        self.benders.retain(|&(ref b, _)| !Rc::ptr_eq(b, go));
+    }
+
+    fn build(&mut self, world: &mut World) -> Rc<Material> {
+        let (lo, hi) = self.terrain.height_range;
+        let max_height = (hi - lo) * 0.5 + self.shape.height() * self.scale_range.1.max(1.0);
+        let buffers =
+            build_detail_patch(&self.shape, self.count, self.patch_size, max_height, self.seed);
+
+        let mut material = Material::new(world.asset_system().new_program("unrust/detail"));
+        material.states.cull = Some(CullMode::Off);
+        material.set("uDetailTexture", world.asset_system().new_texture(&self.texture));
+        let material = Rc::new(material);
+
+        let mut mesh = Mesh::new();
+        for data in buffers.into_iter() {
+            mesh.add_surface(MeshBuffer::new(data), material.clone());
+        }
+
+        let patch = world.new_game_object();
+        patch.borrow_mut().add_component(mesh);
+        self.patch = Some(patch);
+        self.material = Some(material.clone());
+        material
+    }
+
+    fn bind(&self, material: &Material, center: Vector3<f32>, world: &World) {
+        let t = &self.terrain;
+        material.set("uDetailCenter", center);
+        material.set("uDetailPatchSize", self.patch_size);
+        material.set("uDetailTime", self.time);
+        material.set("uDetailFade", Vector2::new(self.fade_distance.0, self.fade_distance.1));
+        material.set("uDetailScale", Vector2::new(self.scale_range.0, self.scale_range.1));
+        material.set("uDetailShapeHeight", self.shape.height().max(1e-3));
+
+        material.set("uDetailHeight", t.height.clone());
+        material.set("uDetailTerrain", Vector3::new(t.origin.x, t.origin.y, t.cell_size));
+        material.set("uDetailSamples", Vector2::new(t.samples.0 as f32, t.samples.1 as f32));
+        material.set("uDetailHeightRange", Vector2::new(t.height_range.0, t.height_range.1));
+
+        let density = match self.density {
+            Some(ref density) => density.clone(),
+            None => world.asset_system().new_texture("default_white"),
+        };
+        let mut mask = Vector4::zero();
+        mask[if self.density.is_some() { self.density_channel } else { 0 }] = 1.0;
+        material.set("uDetailDensity", density);
+        material.set("uDetailDensityMask", mask);
+        material.set("uDetailDensityScale", self.density_scale);
+
+        material.set(
+            "uDetailWind",
+            Vector4::new(self.wind.x, self.wind.y, self.wind_speed, self.wind_frequency),
+        );
+        material.set("uDetailBendStrength", self.bend_strength);
+        for i in 0..MAX_DETAIL_BENDERS {
+            let bender = self.benders.get(i).and_then(|&(ref go, radius)| {
+                let go = go.try_borrow().ok()?;
+                Some(go.transform.global().disp.extend(radius))
+            });
+            material.set(format!("uDetailBender{}", i), bender.unwrap_or(Vector4::zero()));
+        }
+
+        material.set("uDetailColor", self.color);
+        material.set("uDetailLightDirection", self.light_direction);
+    }
+}
+
+impl Actor for DetailLayer {
+    fn update(&mut self, _go: &mut GameObject, world: &mut World) {
+        let material = match self.material.clone() {
+            Some(material) => material,
+            None => self.build(world),
+        };
+        self.time += world.delta_time() as f32;
+
+        let center = match world.current_camera() {
+            Some(cam) => cam.borrow().eye(),
+            None => return,
+        };
+
+        // Follows the camera, for the culling by the bounds of the patch
+        if let Some(ref patch) = self.patch {
+            let (lo, hi) = self.terrain.height_range;
+            let mut patch = patch.borrow_mut();
+            let mut t = patch.transform.global();
+            t.disp = Vector3::new(center.x, (lo + hi) * 0.5, center.z);
+            t.rot = Quaternion::one();
+            t.scale = 1.0;
+            patch.transform.set_global(t);
+        }
+
+        self.bind(&material, center, world);
+    }
+}
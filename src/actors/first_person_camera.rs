@@ -18,17 +18,22 @@ bitflags! {
     }
 }
 
-#[derive(Component)]
+#[derive(Component, Inspect)]
 pub struct FirstPersonCamera {
+    #[inspect(min = 0.0, max = 100.0)]
     pub speed: f32,
+    #[inspect(min = 0.0, max = 10.0)]
     pub angle_speed: f32,
 
     pub eye: Vector3<f32>,
     pub eye_dir: Vector3<f32>,
 
+    #[inspect(skip)]
     camera: Option<Arc<Component>>,
 
+    #[inspect(skip)]
     state: Movement,
+    #[inspect(skip)]
     handlers: Vec<(Movement, String, Box<Fn(&mut FirstPersonCamera, f64)>)>,
 }
 
@@ -0,0 +1,265 @@
+use engine::{Camera, ClearOption, GameObject, Material, Mesh, MeshBuffer, MeshData,
+             RenderTexture};
+use world::{Actor, World};
+
+use math::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Field of view of the baking cameras, narrow so the views are close to orthographic
+const BAKE_FOVY: f32 = 10.0;
+
+/// A mesh baked in views from around it, shared by the `Impostor`s of its copies.
+///
+/// The views are `columns` azimuths around the local Y axis by `rows` elevations, from the
+/// horizon up to `max_elevation`, in an atlas of `columns * frame_size` by
+/// `rows * frame_size` texels. The mesh is rendered alone at the origin, with its own
+/// materials and the lights of the scene at the time of the bake.
+pub struct ImpostorAtlas {
+    pub columns: u32,
+    pub rows: u32,
+    pub frame_size: u32,
+    pub max_elevation: Rad<f32>,
+
+    baked: RefCell<Option<Rc<BakedImpostor>>>,
+}
+
+struct BakedImpostor {
+    atlas: Rc<RenderTexture>,
+    mesh: Mesh,
+}
+
+impl ImpostorAtlas {
+    pub fn new(columns: u32, rows: u32, frame_size: u32) -> ImpostorAtlas {
+        ImpostorAtlas {
+            columns: columns.max(1),
+            rows: rows.max(1),
+            frame_size: frame_size.max(1),
+            max_elevation: Deg(60.0).into(),
+            baked: RefCell::new(None),
+        }
+    }
+
+    /// Clamped below the vertical, where the views have no up direction
+    pub fn with_max_elevation<T: Into<Rad<f32>>>(mut self, max_elevation: T) -> ImpostorAtlas {
+        let max: Rad<f32> = Deg(85.0).into();
+        self.max_elevation = Rad(max_elevation.into().0.max(0.0).min(max.0));
+        self
+    }
+
+    pub fn is_baked(&self) -> bool {
+        self.baked.borrow().is_some()
+    }
+
+    /// The atlas texture, once baked
+    pub fn texture(&self) -> Option<Rc<RenderTexture>> {
+        self.baked.borrow().as_ref().map(|b| b.atlas.clone())
+    }
+
+    /// Direction toward the camera of a view, in the local space of the mesh
+    pub fn view_direction(&self, column: u32, row: u32) -> Vector3f {
+        let azimuth = column as f32 / self.columns as f32 * 2.0 * ::std::f32::consts::PI;
+        let elevation = if self.rows > 1 {
+            self.max_elevation.0 * row as f32 / (self.rows - 1) as f32
+        } else {
+            0.0
+        };
+
+        vec3(
+            azimuth.sin() * elevation.cos(),
+            elevation.sin(),
+            azimuth.cos() * elevation.cos(),
+        )
+    }
+
+    /// Bake the views of the mesh now, e.g. behind a loading screen, unless already baked.
+    /// Returns false if the mesh is not loaded yet.
+    pub fn preload(&self, mesh: &Mesh, world: &mut World) -> bool {
+        self.is_baked() || self.bake(mesh, world)
+    }
+
+    /// Bake the views of the mesh, returns false if it is not loaded yet
+    fn bake(&self, mesh: &Mesh, world: &mut World) -> bool {
+        let bounds = match mesh.bounds() {
+            Some(bounds) => bounds,
+            None => return false,
+        };
+
+        let aabb = bounds.local_aabb();
+        let center = (aabb.min + aabb.max) * 0.5;
+        let radius = ((aabb.max - aabb.min).magnitude() * 0.5).max(1e-3);
+
+        let fovy: Rad<f32> = Deg(BAKE_FOVY).into();
+        let distance = radius / (fovy.0 * 0.5).sin();
+        // Half size of a view at the center of the mesh
+        let extent = distance * (fovy.0 * 0.5).tan();
+
+        let program = world.asset_system().new_program("unrust/impostor");
+
+        let size = (self.columns * self.frame_size, self.rows * self.frame_size);
+        let atlas = Rc::new(RenderTexture::new_with_depth(size.0, size.1));
+
+        let mut camera = Camera::new();
+        camera.fovy = fovy;
+        camera.znear = (distance - radius * 1.01).max(1e-3);
+        camera.zfar = distance + radius * 1.01;
+        camera.enable_frustum_culling = false;
+        camera.render_texture = Some(atlas.clone());
+
+        let mut clear_option = ClearOption {
+            color: Some((0.0, 0.0, 0.0, 0.0)),
+            clear_color: true,
+            clear_depth: true,
+            clear_stencil: false,
+        };
+
+        let engine = world.engine_mut();
+        for row in 0..self.rows {
+            for column in 0..self.columns {
+                let eye = center + self.view_direction(column, row) * distance;
+                camera.lookat(
+                    &Point3::from_vec(eye),
+                    &Point3::from_vec(center),
+                    &Vector3::unit_y(),
+                );
+
+                let s = self.frame_size;
+                camera.rect = Some((((column * s) as i32, (row * s) as i32), (s, s)));
+
+                // The whole atlas is cleared once, before the first view
+                if !engine.render_mesh(&camera, mesh, Matrix4::identity(), clear_option) {
+                    return false;
+                }
+                clear_option.clear_color = false;
+                clear_option.clear_depth = false;
+            }
+        }
+
+        let material = Material::new(program);
+        material.set("uImpostorAtlas", atlas.as_texture());
+        material.set("uImpostorCenter", center);
+        material.set("uImpostorExtent", extent);
+        material.set(
+            "uImpostorFrames",
+            Vector2::new(self.columns as f32, self.rows as f32),
+        );
+        material.set("uImpostorMaxElevation", self.max_elevation.0);
+
+        let mut mesh = Mesh::new();
+        mesh.add_surface(impostor_quad(center, extent), material);
+
+        *self.baked.borrow_mut() = Some(Rc::new(BakedImpostor { atlas, mesh }));
+        true
+    }
+}
+
+/// The quad of the impostor, turned toward the camera by `unrust/impostor`. The corners are
+/// given by the uvs, the vertices only bound it.
+fn impostor_quad(center: Vector3f, extent: f32) -> MeshBuffer {
+    let mut data = MeshData::default();
+    for &(x, y) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)].iter() {
+        let p = center + vec3(x, y, 0.0) * extent;
+        data.vertices.extend_from_slice(&[p.x, p.y, p.z]);
+    }
+    data.uvs = Some(vec![0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0, 1.0]);
+    data.indices = vec![0, 1, 2, 2, 3, 0];
+
+    MeshBuffer::new(data)
+}
+
+/// Replaces the `Mesh` of the game object by a billboard of its baked views beyond `distance`
+/// from the main camera, a cheaper alternative to mesh LODs for trees and buildings.
+///
+/// The atlas is baked from the mesh of the first game object using it, when it starts or
+/// as soon as the mesh is loaded, so that no frame stalls when an object first crosses
+/// `distance`. `ImpostorAtlas::preload` bakes it earlier. It is shared by all the copies of
+/// the mesh:
+///
+/// ```ignore
+/// let atlas = Rc::new(ImpostorAtlas::new(8, 3, 128));
+/// for tree in trees.iter() {
+///     tree.borrow_mut().add_component(Impostor::new(atlas.clone(), 60.0));
+/// }
+/// ```
+///
+/// The billboard faces the camera and shows the view nearest to the camera direction.
+///
+/// Register it by `WorldBuilder::with_actor::<Impostor>()`.
+#[derive(Component)]
+pub struct Impostor {
+    pub atlas: Rc<ImpostorAtlas>,
+    /// Distance to the camera beyond which the impostor is shown
+    pub distance: f32,
+
+    // The original mesh while the impostor is shown
+    mesh: Option<Mesh>,
+}
+
+impl Impostor {
+    pub fn new(atlas: Rc<ImpostorAtlas>, distance: f32) -> Impostor {
+        Impostor {
+            atlas,
+            distance,
+            mesh: None,
+        }
+    }
+
+    pub fn is_shown(&self) -> bool {
+        self.mesh.is_some()
+    }
+
+    /// Bake the atlas from the mesh of the game object, returns false if it is not loaded yet
+    fn preload(&self, go: &GameObject, world: &mut World) -> bool {
+        if self.atlas.is_baked() {
+            return true;
+        }
+
+        let mesh = match go.find_component::<Mesh>() {
+            Some((mesh, _)) => (*mesh).clone(),
+            None => return false,
+        };
+        self.atlas.preload(&mesh, world)
+    }
+
+    /// Replace the mesh of the game object, returns the previous one
+    fn swap(go: &mut GameObject, mesh: Mesh) -> Option<Mesh> {
+        let current = go.find_component::<Mesh>()
+            .map(|(m, c)| ((*m).clone(), c.clone()));
+
+        let (previous, c) = current?;
+        go.remove_component(c);
+        go.add_component(mesh);
+        Some(previous)
+    }
+}
+
+impl Actor for Impostor {
+    fn start(&mut self, go: &mut GameObject, world: &mut World) {
+        self.preload(go, world);
+    }
+
+    fn update(&mut self, go: &mut GameObject, world: &mut World) {
+        // Not loaded when started
+        if !self.preload(go, world) {
+            return;
+        }
+
+        let eye = match world.current_camera() {
+            Some(cam) => {
+                let eye = cam.borrow().eye();
+                eye
+            }
+            None => return,
+        };
+
+        let far = (go.transform.global().disp - eye).magnitude() > self.distance;
+
+        if far && self.mesh.is_none() {
+            let baked = self.atlas.baked.borrow().clone().unwrap();
+            self.mesh = Impostor::swap(go, baked.mesh.clone());
+        } else if !far && self.mesh.is_some() {
+            let mesh = self.mesh.take().unwrap();
+            Impostor::swap(go, mesh);
+        }
+    }
+}
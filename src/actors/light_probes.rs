@@ -0,0 +1,56 @@
+use engine::lightmap::LightProbes;
+use engine::{GameObject, Material, Mesh};
+use world::{Actor, World};
+
+use math::*;
+use std::rc::Rc;
+
+/// Baked ambient light for a dynamic object moving in a lightmapped scene.
+///
+/// Each frame the probes are interpolated at the position of the game object, and bound to
+/// `material`, or to the materials of the `Mesh` of the game object, as `uProbe0` to `uProbe5`
+/// (the ambient cube, from +x, -x, +y, -y, +z and -z). The `unrust/probe_lit` shader adds the
+/// dynamic directional light.
+///
+/// Register it by `WorldBuilder::with_actor::<LightProbeReceiver>()`.
+#[derive(Component)]
+pub struct LightProbeReceiver {
+    pub probes: Rc<LightProbes>,
+    /// Material receiving the light, `None` for the materials of the game object's mesh
+    pub material: Option<Rc<Material>>,
+    /// Sampled point relative to the game object, e.g. the chest of a character
+    pub offset: Vector3f,
+}
+
+impl LightProbeReceiver {
+    pub fn new(probes: Rc<LightProbes>) -> LightProbeReceiver {
+        LightProbeReceiver {
+            probes,
+            material: None,
+            offset: Vector3::zero(),
+        }
+    }
+
+    fn bind(&self, material: &Material, colors: &[[f32; 3]; 6]) {
+        for (i, c) in colors.iter().enumerate() {
+            material.set(format!("uProbe{}", i), Vector3::from(*c));
+        }
+    }
+}
+
+impl Actor for LightProbeReceiver {
+    fn update(&mut self, go: &mut GameObject, _world: &mut World) {
+        let global = go.transform.global();
+        let p = global.disp + global.rot * self.offset;
+        let cube = self.probes.sample(p);
+
+        match self.material {
+            Some(ref material) => self.bind(material, &cube.colors),
+            None => if let Some((mesh, _)) = go.find_component::<Mesh>() {
+                for surface in mesh.surfaces.iter() {
+                    self.bind(&surface.material, &cube.colors);
+                }
+            },
+        }
+    }
+}
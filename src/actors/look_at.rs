@@ -0,0 +1,218 @@
+use actors::Animator;
+use engine::animation::Skeleton;
+use engine::GameObject;
+use world::{Actor, Handle, World};
+
+use math::*;
+use std::rc::Rc;
+
+pub enum LookAtTarget {
+    Point(Vector3<f32>),
+    Object(Handle<GameObject>),
+}
+
+/// A bone turned toward the target
+pub struct LookAtBone {
+    pub bone: String,
+    /// Part of the remaining rotation taken by this bone
+    pub weight: f32,
+    /// Rotation limit from the animated pose
+    pub max_angle: Rad<f32>,
+}
+
+/// Turns bones of a character toward a target, after the animation.
+///
+/// ```ignore
+/// let look_at = LookAt::new()
+///     .with_bone("chest", 0.3, Deg(20.0))
+///     .with_bone("head", 0.7, Deg(60.0))
+///     .with_bone("eye.L", 1.0, Deg(30.0))
+///     .with_bone("eye.R", 1.0, Deg(30.0));
+/// character.add_component(look_at);
+///
+/// // Later, when the player comes near
+/// look_at.look_at(LookAtTarget::Object(player));
+/// ```
+///
+/// The bones are turned in order, each one by its weight of the rotation left by the previous
+/// ones, so list them from the root to the tips. The constraint engages over `blend_time`
+/// seconds, and disengages when the target is cleared or leaves the field of view.
+///
+/// Register it by `WorldBuilder::with_actor::<LookAt>()`, after the `Animator`.
+#[derive(Component)]
+pub struct LookAt {
+    pub bones: Vec<LookAtBone>,
+    pub target: Option<LookAtTarget>,
+    /// Axis of the bones facing forward in the rest pose, in the character space
+    pub forward_axis: Vector3<f32>,
+    /// Largest angle of the target from the forward of the character
+    pub field_of_view: Rad<f32>,
+    /// Seconds to engage or disengage
+    pub blend_time: f32,
+
+    weight: f32,
+    /// Last position of the target, followed while disengaging
+    point: Option<Vector3<f32>>,
+    /// Local rotations of the bones, written by the constraint and before it
+    applied: Vec<Option<(Quaternion<f32>, Quaternion<f32>)>>,
+    skeleton: Option<Rc<Skeleton>>,
+}
+
+impl LookAt {
+    pub fn new() -> LookAt {
+        LookAt {
+            bones: Vec::new(),
+            target: None,
+            forward_axis: Vector3::unit_z(),
+            field_of_view: Deg(100.0).into(),
+            blend_time: 0.3,
+
+            weight: 0.0,
+            point: None,
+            applied: Vec::new(),
+            skeleton: None,
+        }
+    }
+
+    pub fn with_bone<A: Into<Rad<f32>>>(mut self, bone: &str, weight: f32, max_angle: A) -> LookAt {
+        self.bones.push(LookAtBone {
+            bone: bone.to_owned(),
+            weight,
+            max_angle: max_angle.into(),
+        });
+        self
+    }
+
+    pub fn look_at(&mut self, target: LookAtTarget) {
+        self.target = Some(target);
+    }
+
+    /// Disengage smoothly
+    pub fn clear(&mut self) {
+        self.target = None;
+    }
+
+    /// Current weight of the constraint, from 0 when disengaged to 1
+    pub fn weight(&self) -> f32 {
+        self.weight
+    }
+
+    fn target_position(&self) -> Option<Vector3<f32>> {
+        match self.target {
+            Some(LookAtTarget::Point(p)) => Some(p),
+            Some(LookAtTarget::Object(ref go)) => go.try_borrow()
+                .ok()
+                .map(|go| go.transform.global().disp),
+            None => None,
+        }
+    }
+
+    /// Undo the rotations of the last update on the bones which were not animated since
+    fn restore(&mut self, skeleton: &Skeleton) {
+        for (i, bone) in self.bones.iter().enumerate() {
+            let (written, original) = match self.applied.get(i).and_then(|a| *a) {
+                Some(applied) => applied,
+                None => continue,
+            };
+
+            if let Some(object) = skeleton.object(&bone.bone) {
+                let mut object = object.borrow_mut();
+                let mut local = object.transform.local();
+                if local.rot == written {
+                    local.rot = original;
+                    object.transform.set_local(local);
+                }
+            }
+        }
+
+        self.applied.clear();
+    }
+}
+
+impl Actor for LookAt {
+    fn update(&mut self, go: &mut GameObject, world: &mut World) {
+        if self.skeleton.is_none() {
+            self.skeleton = match go.find_component::<Animator>() {
+                Some((animator, _)) => animator.skeleton(),
+                None => Some(Rc::new(Skeleton::from_root(go))),
+            };
+        }
+
+        let skeleton = match self.skeleton {
+            Some(ref skeleton) => skeleton.clone(),
+            None => return,
+        };
+
+        self.restore(&skeleton);
+
+        let character = go.transform.global();
+        let target = self.target_position();
+        let engaged = target.map_or(false, |target| {
+            let forward = character.rot * self.forward_axis;
+            let to_target = target - character.disp;
+            to_target.magnitude2() > 1e-6 && forward.angle(to_target) <= self.field_of_view
+        });
+        if engaged {
+            self.point = target;
+        }
+
+        let dt = world.time.delta_time_of(go) as f32;
+        let step = if self.blend_time > 0.0 {
+            dt / self.blend_time
+        } else {
+            1.0
+        };
+        self.weight = if engaged {
+            (self.weight + step).min(1.0)
+        } else {
+            (self.weight - step).max(0.0)
+        };
+
+        let point = match self.point {
+            Some(point) if self.weight > 0.0 => point,
+            _ => return,
+        };
+
+        for bone in self.bones.iter() {
+            let index = skeleton.find(&bone.bone);
+            let (object, rest) = match index {
+                Some(i) => (&skeleton.bones[i].object, skeleton.bones[i].rest_model.rot),
+                None => {
+                    self.applied.push(None);
+                    continue;
+                }
+            };
+
+            let mut object = object.borrow_mut();
+            let global = object.transform.global();
+            let forward = global.rot * (rest.invert() * self.forward_axis);
+            let to_target = point - global.disp;
+
+            if to_target.magnitude2() < 1e-10 || forward.magnitude2() < 1e-10 {
+                self.applied.push(None);
+                continue;
+            }
+
+            let (forward, to_target) = (forward.normalize(), to_target.normalize());
+            let angle = forward.angle(to_target);
+            let limit = if angle > bone.max_angle {
+                bone.max_angle.0 / angle.0
+            } else {
+                1.0
+            };
+
+            let arc = Quaternion::from_arc(forward, to_target, None);
+            let amount = (limit * bone.weight * self.weight).max(0.0).min(1.0);
+            let delta = Quaternion::one().slerp(arc, amount);
+
+            let original = object.transform.local().rot;
+            object.transform.set_global(Isometry3 {
+                disp: global.disp,
+                rot: (delta * global.rot).normalize(),
+                scale: global.scale,
+            });
+            self.applied
+                .push(Some((object.transform.local().rot, original)));
+        }
+    }
+}
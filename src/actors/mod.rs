@@ -1,7 +1,66 @@
+mod animator;
+mod blob_shadows;
+mod constraints;
+mod detail_layer;
 mod skybox;
 mod shadow_pass;
 mod first_person_camera;
+mod impostor;
+mod light_probes;
+mod look_at;
+mod photo_mode;
+mod planar_reflection;
+mod sockets;
+mod volumetric_fog;
+mod voxel_terrain;
+#[cfg(feature = "net")]
+mod remote_transform;
+#[cfg(feature = "audio")]
+mod audio_source;
+#[cfg(feature = "audio")]
+mod reverb_zone;
+#[cfg(feature = "physics")]
+mod physics_body;
+#[cfg(feature = "physics")]
+mod ragdoll;
+#[cfg(feature = "physics")]
+mod water;
+#[cfg(feature = "ui")]
+mod screen_markers;
+#[cfg(feature = "ui")]
+mod text_popups;
 
+pub use self::animator::{AnimationLayer, Animator};
+pub use self::blob_shadows::{BlobOccluder, BlobShadows, OccluderShape, MAX_BLOB_OCCLUDERS};
+pub use self::constraints::{AimConstraint, ConstraintSource, FollowConstraint,
+                            ParentConstraint};
+pub use self::detail_layer::{build_detail_patch, DetailLayer, DetailShape, DetailTerrain,
+                             MAX_DETAIL_BENDERS};
 pub use self::skybox::SkyBox;
 pub use self::shadow_pass::ShadowPass;
 pub use self::first_person_camera::FirstPersonCamera;
+pub use self::impostor::{Impostor, ImpostorAtlas};
+pub use self::light_probes::LightProbeReceiver;
+pub use self::look_at::{LookAt, LookAtBone, LookAtTarget};
+pub use self::photo_mode::{DepthOfField, PhotoMode};
+pub use self::planar_reflection::PlanarReflection;
+pub use self::sockets::{Socket, Sockets};
+pub use self::volumetric_fog::{FogQuality, VolumetricFog, MAX_FOG_SPOT_LIGHTS};
+pub use self::voxel_terrain::VoxelTerrain;
+#[cfg(feature = "net")]
+pub use self::remote_transform::RemoteTransform;
+#[cfg(feature = "audio")]
+pub use self::audio_source::AudioSource;
+#[cfg(feature = "audio")]
+pub use self::reverb_zone::ReverbZone;
+#[cfg(feature = "physics")]
+pub use self::physics_body::PhysicsBody;
+#[cfg(feature = "physics")]
+pub use self::ragdoll::{Ragdoll, RagdollState};
+#[cfg(feature = "physics")]
+pub use self::water::Water;
+#[cfg(feature = "ui")]
+pub use self::screen_markers::{clamp_to_screen, Marker, MarkerAnchor, MarkerId, MarkerKind,
+                               ScreenMarkers};
+#[cfg(feature = "ui")]
+pub use self::text_popups::{PopupFont, PopupMotion, TextPopups, POPUP_CURVE_SAMPLES};
@@ -0,0 +1,352 @@
+use engine::{GameObject, Material, RenderQueue};
+use image::RgbaImage;
+use uni_app::AppEvent;
+use world::{Actor, Processor, World};
+
+use math::*;
+use std::collections::{BTreeSet, HashSet};
+use std::rc::Rc;
+
+pub struct DepthOfField {
+    pub enabled: bool,
+    /// Distance of the sharp plane from the camera
+    pub focus_distance: f32,
+    /// Strength of the blur
+    pub aperture: f32,
+    /// Maximum blur radius in pixels
+    pub max_blur: f32,
+}
+
+impl Default for DepthOfField {
+    fn default() -> DepthOfField {
+        DepthOfField {
+            enabled: false,
+            focus_distance: 10.0,
+            aperture: 0.5,
+            max_blur: 12.0,
+        }
+    }
+}
+
+struct SavedCamera {
+    v: Matrix4<f32>,
+    fovy: Rad<f32>,
+    included_render_queues: Option<BTreeSet<RenderQueue>>,
+}
+
+/// Photo mode, press `KeyP` to toggle.
+///
+/// While active, the world is paused and the main camera is detached as a free camera
+/// without UI. Controls:
+/// * `KeyW`/`KeyS`/`KeyA`/`KeyD`/`KeyE`/`KeyC` : move
+/// * Arrow keys : look around, `KeyZ`/`KeyX` : roll
+/// * `KeyR`/`KeyF` : zoom in/out (field of view)
+/// * `KeyV` : toggle depth of field, `KeyT`/`KeyG` : focus distance, `KeyY`/`KeyH` : aperture
+/// * `Enter` : take a photo, `capture_scale` times larger than the screen
+#[derive(Component)]
+pub struct PhotoMode {
+    pub move_speed: f32,
+    pub turn_speed: f32,
+    /// Field of view range, in degrees
+    pub fov_range: (f32, f32),
+    /// Photos are rendered in (scale x scale) tiles for super resolution
+    pub capture_scale: u32,
+    /// Prefix of the saved photo file names
+    pub file_prefix: String,
+    pub dof: DepthOfField,
+
+    active: bool,
+    eye: Vector3<f32>,
+    yaw: f32,
+    pitch: f32,
+    roll: f32,
+    fovy: f32,
+
+    keys: HashSet<String>,
+    saved: Option<SavedCamera>,
+    dof_material: Option<Rc<Material>>,
+    photo_count: u32,
+}
+
+impl Processor for PhotoMode {
+    fn new() -> PhotoMode {
+        PhotoMode {
+            move_speed: 5.0,
+            turn_speed: 1.0,
+            fov_range: (5.0, 120.0),
+            capture_scale: 2,
+            file_prefix: "photo_".to_string(),
+            dof: DepthOfField::default(),
+
+            active: false,
+            eye: Vector3::zero(),
+            yaw: 0.0,
+            pitch: 0.0,
+            roll: 0.0,
+            fovy: 45.0,
+
+            keys: HashSet::new(),
+            saved: None,
+            dof_material: None,
+            photo_count: 0,
+        }
+    }
+}
+
+impl Actor for PhotoMode {
+    fn update(&mut self, _go: &mut GameObject, world: &mut World) {
+        let mut pressed = Vec::new();
+
+        for evt in world.events().iter() {
+            match evt {
+                &AppEvent::KeyDown(ref key) => {
+                    if !self.keys.contains(&key.code) {
+                        pressed.push(key.code.clone());
+                    }
+                    self.keys.insert(key.code.clone());
+                }
+                &AppEvent::KeyUp(ref key) => {
+                    self.keys.remove(&key.code);
+                }
+                _ => (),
+            }
+        }
+
+        for key in pressed.iter() {
+            match key.as_str() {
+                "KeyP" => if self.active {
+                    self.exit(world)
+                } else {
+                    self.enter(world)
+                },
+                "KeyV" if self.active => self.dof.enabled = !self.dof.enabled,
+                "Enter" if self.active => {
+                    self.take_photo(world);
+                }
+                _ => (),
+            }
+        }
+
+        if self.active {
+            let dt = world.delta_time() as f32;
+            self.handle_controls(dt);
+            self.update_camera(world);
+            self.update_dof(world);
+        }
+    }
+
+    fn update_when_paused(&self) -> bool {
+        true
+    }
+}
+
+impl PhotoMode {
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Pause the world and detach the main camera
+    pub fn enter(&mut self, world: &mut World) {
+        if self.active {
+            return;
+        }
+
+        {
+            let cam = match world.current_camera() {
+                Some(cam) => cam,
+                None => return,
+            };
+            let cam = cam.borrow();
+
+            let forward = cam.forward();
+            self.eye = cam.eye();
+            self.yaw = forward.x.atan2(forward.z);
+            self.pitch = forward.y.max(-1.0).min(1.0).asin();
+            self.roll = 0.0;
+            self.fovy = Deg::from(cam.fovy).0;
+
+            self.saved = Some(SavedCamera {
+                v: cam.v,
+                fovy: cam.fovy,
+                included_render_queues: cam.included_render_queues.clone(),
+            });
+        }
+
+        world.set_paused(true);
+        self.active = true;
+    }
+
+    /// Restore the main camera and resume the world
+    pub fn exit(&mut self, world: &mut World) {
+        if !self.active {
+            return;
+        }
+
+        if let Some(saved) = self.saved.take() {
+            if let Some(cam) = world.current_camera() {
+                let mut cam = cam.borrow_mut();
+                cam.set_view(saved.v);
+                cam.fovy = saved.fovy;
+                cam.included_render_queues = saved.included_render_queues;
+            }
+        }
+
+        self.remove_dof(world);
+        world.set_paused(false);
+        self.active = false;
+    }
+
+    /// Capture the current view and save it, return the captured image
+    pub fn take_photo(&mut self, world: &mut World) -> Option<RgbaImage> {
+        let cam = world.current_camera()?;
+        let img = world
+            .engine_mut()
+            .capture_tiled(&mut cam.borrow_mut(), self.capture_scale)?;
+
+        self.photo_count += 1;
+        let filename = format!("{}{:03}.png", self.file_prefix, self.photo_count);
+        save_image(&img, &filename);
+
+        Some(img)
+    }
+
+    fn key(&self, code: &str) -> bool {
+        self.keys.contains(code)
+    }
+
+    fn axis(&self, pos: &str, neg: &str) -> f32 {
+        match (self.key(pos), self.key(neg)) {
+            (true, false) => 1.0,
+            (false, true) => -1.0,
+            _ => 0.0,
+        }
+    }
+
+    fn forward(&self) -> Vector3<f32> {
+        Vector3::new(
+            self.pitch.cos() * self.yaw.sin(),
+            self.pitch.sin(),
+            self.pitch.cos() * self.yaw.cos(),
+        )
+    }
+
+    fn handle_controls(&mut self, dt: f32) {
+        let turn = self.turn_speed * dt;
+        self.yaw += self.axis("ArrowLeft", "ArrowRight") * turn;
+        self.pitch += self.axis("ArrowUp", "ArrowDown") * turn;
+        self.pitch = self.pitch.max(-1.55).min(1.55);
+        self.roll += self.axis("KeyX", "KeyZ") * turn;
+
+        let forward = self.forward();
+        let right = forward.cross(Vector3::unit_y()).normalize();
+        let step = self.move_speed * dt;
+
+        self.eye += forward * self.axis("KeyW", "KeyS") * step;
+        self.eye += right * self.axis("KeyD", "KeyA") * step;
+        self.eye += Vector3::unit_y() * self.axis("KeyE", "KeyC") * step;
+
+        self.fovy += self.axis("KeyF", "KeyR") * 30.0 * dt;
+        self.fovy = self.fovy.max(self.fov_range.0).min(self.fov_range.1);
+
+        self.dof.focus_distance += self.axis("KeyT", "KeyG") * self.dof.focus_distance * dt;
+        self.dof.focus_distance = self.dof.focus_distance.max(0.1);
+        self.dof.aperture += self.axis("KeyY", "KeyH") * dt;
+        self.dof.aperture = self.dof.aperture.max(0.0).min(4.0);
+    }
+
+    fn update_camera(&self, world: &mut World) {
+        let cam = match world.current_camera() {
+            Some(cam) => cam,
+            None => return,
+        };
+        let mut cam = cam.borrow_mut();
+
+        let forward = self.forward();
+        let right = forward.cross(Vector3::unit_y()).normalize();
+        let up = Quaternion::from_axis_angle(forward, Rad(self.roll)) * right.cross(forward);
+
+        cam.lookat(
+            &Point3::from_vec(self.eye),
+            &Point3::from_vec(self.eye + forward),
+            &up,
+        );
+        cam.fovy = Rad::from(Deg(self.fovy));
+
+        // Hide UI
+        let mut queues = BTreeSet::new();
+        queues.insert(RenderQueue::Opaque);
+        queues.insert(RenderQueue::Skybox);
+        queues.insert(RenderQueue::Transparent);
+        cam.included_render_queues = Some(queues);
+    }
+
+    fn update_dof(&mut self, world: &mut World) {
+        if !self.dof.enabled {
+            self.remove_dof(world);
+            return;
+        }
+
+        if self.dof_material.is_none() {
+            let material = Rc::new(Material::new(
+                world.asset_system().new_program("unrust/depth_of_field"),
+            ));
+            world.engine_mut().post_effects.push(material.clone());
+            self.dof_material = Some(material);
+        }
+
+        let material = self.dof_material.as_ref().unwrap();
+        material.set("uFocusDistance", self.dof.focus_distance);
+        material.set("uAperture", self.dof.aperture);
+        material.set("uMaxBlur", self.dof.max_blur);
+    }
+
+    fn remove_dof(&mut self, world: &mut World) {
+        if let Some(material) = self.dof_material.take() {
+            world
+                .engine_mut()
+                .post_effects
+                .retain(|m| !Rc::ptr_eq(m, &material));
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn save_image(img: &RgbaImage, filename: &str) {
+    match img.save(filename) {
+        Ok(_) => println!("Photo saved to {}", filename),
+        Err(e) => println!("Fail to save photo {}, reason: {:?}", filename, e),
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn save_image(img: &RgbaImage, filename: &str) {
+    use image::png::PNGEncoder;
+    use image::ColorType;
+    use stdweb::UnsafeTypedArray;
+
+    let mut data = Vec::new();
+    if let Err(e) = PNGEncoder::new(&mut data).encode(
+        img,
+        img.width(),
+        img.height(),
+        ColorType::RGBA(8),
+    ) {
+        println!("Fail to encode photo {}, reason: {:?}", filename, e);
+        return;
+    }
+
+    let buffer = unsafe { UnsafeTypedArray::new(&data) };
+
+    // Trigger a download in browser
+    js! {
+        var blob = new Blob([@{buffer}], { type: "image/png" });
+        var url = URL.createObjectURL(blob);
+        var a = document.createElement("a");
+        a.href = url;
+        a.download = @{filename};
+        document.body.appendChild(a);
+        a.click();
+        document.body.removeChild(a);
+        URL.revokeObjectURL(url);
+    }
+}
@@ -0,0 +1,46 @@
+use engine::physics::BodyHandle;
+use engine::GameObject;
+use world::{Actor, World};
+
+/// Moves the game object with a body of `World::physics`, at its interpolated position.
+/// The time scale override of the game object in `World::time` applies to the body when it
+/// changes, so `RigidBody::time_scale` can still be set directly.
+///
+/// The body is owned by the physics world, remove it with `PhysicsWorld::remove_body`
+/// when the game object is destroyed. Register it by `WorldBuilder::with_actor::<PhysicsBody>()`.
+#[derive(Component)]
+pub struct PhysicsBody {
+    pub handle: BodyHandle,
+
+    time_scale: Option<f32>,
+}
+
+impl PhysicsBody {
+    pub fn new(handle: BodyHandle) -> PhysicsBody {
+        PhysicsBody {
+            handle,
+            time_scale: None,
+        }
+    }
+}
+
+impl Actor for PhysicsBody {
+    fn update(&mut self, go: &mut GameObject, world: &mut World) {
+        let time_scale = world.time.override_of(go);
+        if time_scale != self.time_scale {
+            if let Some(body) = world.physics.body_mut(self.handle) {
+                body.time_scale = time_scale;
+            }
+            self.time_scale = time_scale;
+        }
+
+        let position = match world.physics.render_position(self.handle) {
+            Some(position) => position,
+            None => return,
+        };
+
+        let mut global = go.transform.global();
+        global.disp = position;
+        go.transform.set_global(global);
+    }
+}
@@ -0,0 +1,144 @@
+use engine::{Camera, ClearOption, GameObject, Material, Mesh, RenderQueue, RenderTexture};
+use world::{Actor, Processor, World};
+
+use math::*;
+use std::collections::BTreeSet;
+use std::rc::Rc;
+
+/// Planar reflection for mirrors, water and shiny floors.
+///
+/// The reflection plane passes through the game object, facing its local Y axis. Each frame the
+/// main camera is mirrored by the plane and renders to a texture, with the objects behind the
+/// plane clipped. The texture is bound to `material`, or to the materials of the `Mesh` of the
+/// game object, as:
+/// * `uReflectionTexture` : the reflected image
+/// * `uReflectionMatrix` : world space to the projective texture coordinates,
+///   `texture2D(uReflectionTexture, p.xy / p.w)` with `p = uReflectionMatrix * world_pos`
+///
+/// `unrust/planar_reflection` is a shader for a perfect mirror.
+#[derive(Component)]
+pub struct PlanarReflection {
+    /// Material receiving the reflection, `None` for the materials of the game object's mesh
+    pub material: Option<Rc<Material>>,
+    /// Size of the reflection texture relative to the screen
+    pub resolution_scale: f32,
+    /// Offset of the clip plane along its normal, hides seams where objects touch the plane
+    pub clip_offset: f32,
+    pub included_render_queues: BTreeSet<RenderQueue>,
+
+    camera: Camera,
+    rt: Option<Rc<RenderTexture>>,
+}
+
+impl Processor for PlanarReflection {
+    fn new() -> PlanarReflection {
+        let mut queues = BTreeSet::new();
+        queues.insert(RenderQueue::Opaque);
+        queues.insert(RenderQueue::Skybox);
+        queues.insert(RenderQueue::Transparent);
+
+        PlanarReflection {
+            material: None,
+            resolution_scale: 0.5,
+            clip_offset: 0.01,
+            included_render_queues: queues,
+
+            camera: Camera::new(),
+            rt: None,
+        }
+    }
+}
+
+/// Reflection of a point by the plane through `origin` with unit `normal`
+fn reflect(p: Vector3<f32>, origin: Vector3<f32>, normal: Vector3<f32>) -> Vector3<f32> {
+    p - normal * (2.0 * (p - origin).dot(normal))
+}
+
+impl PlanarReflection {
+    fn target(&mut self, screen_size: (u32, u32)) -> Rc<RenderTexture> {
+        let scale = self.resolution_scale.max(0.01);
+        let size = (
+            ((screen_size.0 as f32 * scale) as u32).max(1),
+            ((screen_size.1 as f32 * scale) as u32).max(1),
+        );
+
+        let outdated = self.rt
+            .as_ref()
+            .map_or(true, |rt| rt.as_texture().size() != Some(size));
+
+        if outdated {
+            self.rt = Some(Rc::new(RenderTexture::new_with_depth(size.0, size.1)));
+        }
+
+        self.rt.clone().unwrap()
+    }
+
+    fn bind(&self, material: &Material, rt: &RenderTexture, screen_size: (u32, u32)) {
+        // From clip space [-1, 1] to texture space [0, 1]
+        let bias = Matrix4::from_translation(Vector3::new(0.5, 0.5, 0.5))
+            * Matrix4::from_scale(0.5);
+
+        let m = bias * self.camera.perspective(screen_size) * self.camera.v;
+
+        material.set("uReflectionTexture", rt.as_texture());
+        material.set("uReflectionMatrix", m);
+    }
+}
+
+impl Actor for PlanarReflection {
+    fn update(&mut self, go: &mut GameObject, world: &mut World) {
+        let screen_size = world.engine().screen_size;
+
+        {
+            let cam = match world.current_camera() {
+                Some(cam) => cam,
+                None => return,
+            };
+            let cam = cam.borrow();
+
+            let global = go.transform.global();
+            let origin = global.disp;
+            let normal = (global.rot * Vector3::unit_y()).normalize();
+
+            // Mirror the eye, direction and up of the main camera. Using look_at keeps a right
+            // handed view, so the triangles winding is not flipped.
+            let eye = reflect(cam.eye(), origin, normal);
+            let forward = cam.forward() - normal * (2.0 * cam.forward().dot(normal));
+            let up = cam.right().cross(cam.forward());
+            let up = up - normal * (2.0 * up.dot(normal));
+
+            self.camera.lookat(
+                &Point3::from_vec(eye),
+                &Point3::from_vec(eye + forward),
+                &up,
+            );
+            self.camera.fovy = cam.fovy;
+            self.camera.znear = cam.znear;
+            self.camera.zfar = cam.zfar;
+
+            let d = -normal.dot(origin) - self.clip_offset;
+            self.camera.clip_plane = Some(normal.extend(d));
+        }
+
+        let rt = self.target(screen_size);
+        let size = rt.as_texture().size().unwrap_or(screen_size);
+        self.camera.rect = Some(((0, 0), size));
+        self.camera.render_texture = Some(rt.clone());
+        self.camera.included_render_queues = Some(self.included_render_queues.clone());
+
+        // The game object is borrowed while updating, so it is not rendered into its own
+        // reflection.
+        world
+            .engine_mut()
+            .render_pass_with_material(&self.camera, None, ClearOption::default());
+
+        match self.material {
+            Some(ref material) => self.bind(material, &rt, screen_size),
+            None => if let Some((mesh, _)) = go.find_component::<Mesh>() {
+                for surface in mesh.surfaces.iter() {
+                    self.bind(&surface.material, &rt, screen_size);
+                }
+            },
+        }
+    }
+}
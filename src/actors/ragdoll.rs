@@ -0,0 +1,233 @@
+use actors::Animator;
+use engine::animation::{blend_pose, RagdollBodies, RagdollBuilder, Skeleton};
+use engine::GameObject;
+use world::{Actor, World};
+
+use math::*;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+fn next_collision_group() -> u32 {
+    // Far from the groups set by hand
+    static NEXT_GROUP: AtomicU32 = AtomicU32::new(0x4000_0000);
+
+    NEXT_GROUP.fetch_add(1, Ordering::SeqCst)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RagdollState {
+    /// The bones are animated by the `Animator`
+    Animated,
+    /// The bones follow the bodies, blended from the animation during `blend_in`
+    Ragdoll,
+    /// Blending from the last ragdoll pose to the get-up clip
+    GettingUp,
+}
+
+/// Switches a character driven by an `Animator` to a ragdoll and back.
+///
+/// ```ignore
+/// // Hit by an explosion
+/// ragdoll.enable(impulse_velocity);
+/// // Later, once it rests
+/// ragdoll.get_up();
+/// ```
+///
+/// When getting up, the character is moved under the ragdoll and plays `get_up_back_clip`
+/// or `get_up_front_clip` of its animator, depending on how the ragdoll lies.
+/// Register it by `WorldBuilder::with_actor::<Ragdoll>()`, after the `Animator`.
+#[derive(Component)]
+pub struct Ragdoll {
+    pub builder: RagdollBuilder,
+    /// Seconds to blend from the animation to the ragdoll
+    pub blend_in: f32,
+    /// Seconds to blend from the ragdoll to the get-up clip
+    pub blend_out: f32,
+    pub get_up_front_clip: String,
+    pub get_up_back_clip: String,
+    /// Axis of the root bone facing forward in the rest pose, in the character space
+    pub forward_axis: Vector3<f32>,
+
+    state: RagdollState,
+    elapsed: f32,
+    pending: Option<Option<Vector3<f32>>>,
+    bodies: Option<RagdollBodies>,
+    /// Global transforms of the bones when the ragdoll stopped
+    frozen: Vec<Option<Isometry3<f32>>>,
+}
+
+impl Ragdoll {
+    pub fn new() -> Ragdoll {
+        Ragdoll {
+            builder: RagdollBuilder::new(),
+            blend_in: 0.1,
+            blend_out: 0.5,
+            get_up_front_clip: "get_up_front".to_owned(),
+            get_up_back_clip: "get_up_back".to_owned(),
+            forward_axis: Vector3::unit_z(),
+
+            state: RagdollState::Animated,
+            elapsed: 0.0,
+            pending: None,
+            bodies: None,
+            frozen: Vec::new(),
+        }
+    }
+
+    pub fn state(&self) -> RagdollState {
+        self.state
+    }
+
+    /// Switch to the ragdoll at the next update, the bodies start with `velocity`
+    pub fn enable(&mut self, velocity: Vector3<f32>) {
+        self.pending = Some(Some(velocity));
+    }
+
+    /// Blend back to the animation at the next update
+    pub fn get_up(&mut self) {
+        self.pending = Some(None);
+    }
+
+    /// Average speed of the bodies, e.g. to get up once the ragdoll rests
+    pub fn speed(&self, world: &World) -> f32 {
+        let bodies = match self.bodies {
+            Some(ref bodies) => bodies,
+            None => return 0.0,
+        };
+
+        let speeds: Vec<f32> = bodies
+            .bodies
+            .iter()
+            .filter_map(|b| b.and_then(|b| world.physics.body(b)))
+            .map(|b| b.velocity.magnitude())
+            .collect();
+
+        if speeds.len() > 0 {
+            speeds.iter().sum::<f32>() / speeds.len() as f32
+        } else {
+            0.0
+        }
+    }
+
+    fn start_ragdoll(
+        &mut self,
+        go: &GameObject,
+        skeleton: &Skeleton,
+        velocity: Vector3<f32>,
+        world: &mut World,
+    ) {
+        if let Some(bodies) = self.bodies.take() {
+            bodies.remove(&mut world.physics);
+        }
+
+        let group = next_collision_group();
+        let bodies = self.builder.build(
+            skeleton,
+            go.transform.global().rot,
+            &mut world.physics,
+            group,
+        );
+        bodies.set_velocity(&mut world.physics, velocity);
+
+        self.bodies = Some(bodies);
+        self.state = RagdollState::Ragdoll;
+        self.elapsed = 0.0;
+    }
+
+    fn start_get_up(&mut self, go: &mut GameObject, skeleton: &Skeleton, world: &mut World) {
+        let bodies = match self.bodies.take() {
+            Some(bodies) => bodies,
+            None => return,
+        };
+
+        self.frozen = bodies.global_pose(skeleton, &world.physics);
+        bodies.remove(&mut world.physics);
+
+        // Move the character under the root bone, facing the same way
+        let root = self.frozen.iter().filter_map(|p| *p).next();
+        let mut clip = self.get_up_front_clip.clone();
+
+        if let Some(root) = root {
+            let root_rest = skeleton
+                .bones
+                .iter()
+                .position(|b| b.parent.is_none())
+                .map_or(Quaternion::one(), |i| skeleton.bones[i].rest_model.rot);
+
+            let forward = root.rot * (root_rest.invert() * self.forward_axis);
+            if forward.y > 0.0 {
+                clip = self.get_up_back_clip.clone();
+            }
+
+            let flat = Vector3::new(forward.x, 0.0, forward.z);
+            let mut global = go.transform.global();
+            global.disp = Vector3::new(root.disp.x, global.disp.y, root.disp.z);
+            if flat.magnitude2() > 1e-6 {
+                // Lying on the back, the head is behind the feet
+                let facing = if forward.y > 0.0 { -flat } else { flat };
+                global.rot = Quaternion::from_arc(Vector3::unit_z(), facing.normalize(), None);
+            }
+            go.transform.set_global(global);
+        }
+
+        if let Some((mut animator, _)) = go.find_component_mut::<Animator>() {
+            animator.play(&clip, false);
+        }
+
+        self.state = RagdollState::GettingUp;
+        self.elapsed = 0.0;
+    }
+
+    /// Blend the global transforms of the bones toward `pose`
+}
+
+impl Actor for Ragdoll {
+    fn update(&mut self, go: &mut GameObject, world: &mut World) {
+        let skeleton: Rc<Skeleton> = match go.find_component::<Animator>() {
+            Some((animator, _)) => match animator.skeleton() {
+                Some(skeleton) => skeleton,
+                None => return,
+            },
+            None => return,
+        };
+
+        let dt = world.time.delta_time_of(go) as f32;
+        self.elapsed += dt;
+
+        match self.pending.take() {
+            Some(Some(velocity)) => self.start_ragdoll(go, &skeleton, velocity, world),
+            Some(None) => self.start_get_up(go, &skeleton, world),
+            None => (),
+        }
+
+        match self.state {
+            RagdollState::Animated => (),
+            RagdollState::Ragdoll => {
+                if let Some(ref bodies) = self.bodies {
+                    let pose = bodies.global_pose(&skeleton, &world.physics);
+                    let weight = if self.blend_in > 0.0 {
+                        (self.elapsed / self.blend_in).min(1.0)
+                    } else {
+                        1.0
+                    };
+                    blend_pose(&skeleton, &pose, weight);
+                }
+            }
+            RagdollState::GettingUp => {
+                let t = if self.blend_out > 0.0 {
+                    (self.elapsed / self.blend_out).min(1.0)
+                } else {
+                    1.0
+                };
+
+                if t >= 1.0 {
+                    self.state = RagdollState::Animated;
+                    self.frozen.clear();
+                } else {
+                    // The animator has set the get-up pose, stay near the ragdoll pose
+                    blend_pose(&skeleton, &self.frozen, 1.0 - t);
+                }
+            }
+        }
+    }
+}
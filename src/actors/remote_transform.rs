@@ -0,0 +1,41 @@
+use engine::net::{InterpolationBuffer, NetId, NetTransform};
+use engine::GameObject;
+use world::{Actor, World};
+
+/// Moves its game object along the interpolated snapshots of a remote object.
+///
+/// Register it by `WorldBuilder::with_actor::<RemoteTransform>()`, and push the received
+/// snapshots with `go.find_component_mut::<RemoteTransform>()`.
+#[derive(Component)]
+pub struct RemoteTransform {
+    pub net_id: NetId,
+    pub buffer: InterpolationBuffer<NetTransform>,
+}
+
+impl RemoteTransform {
+    pub fn new(net_id: NetId) -> RemoteTransform {
+        RemoteTransform {
+            net_id,
+            buffer: InterpolationBuffer::default(),
+        }
+    }
+
+    pub fn with_delay(mut self, delay: f64) -> RemoteTransform {
+        self.buffer.delay = delay;
+        self
+    }
+
+    /// Add a snapshot taken at `time` in the sender's clock
+    pub fn push(&mut self, time: f64, transform: NetTransform) {
+        self.buffer.push(time, transform);
+    }
+}
+
+impl Actor for RemoteTransform {
+    fn update(&mut self, go: &mut GameObject, world: &mut World) {
+        if let Some(t) = self.buffer.step(world.delta_time()) {
+            let global = go.transform.global();
+            go.transform.set_global(t.to_isometry(&global));
+        }
+    }
+}
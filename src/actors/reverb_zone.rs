@@ -0,0 +1,74 @@
+use engine::{GameObject, ReverbParams};
+use world::{Actor, World};
+
+use math::*;
+
+/// A box volume which applies its reverb when the listener (the main camera) is inside.
+///
+/// The reverb fades in over `fade_distance` around the box, overlapping zones are blended
+/// by `SoundSystem::add_reverb_zone`. Register it by `WorldBuilder::with_actor::<ReverbZone>()`.
+#[derive(Component)]
+pub struct ReverbZone {
+    pub params: ReverbParams,
+    /// Half size of the box, in the local space of the game object
+    pub extents: Vector3<f32>,
+    pub fade_distance: f32,
+    /// Zones of higher priority (e.g. a cave inside an outdoors zone) override the others
+    pub priority: i32,
+}
+
+impl ReverbZone {
+    pub fn new(params: ReverbParams, extents: Vector3<f32>) -> ReverbZone {
+        ReverbZone {
+            params,
+            extents,
+            fade_distance: 2.0,
+            priority: 0,
+        }
+    }
+
+    pub fn with_fade_distance(mut self, distance: f32) -> ReverbZone {
+        self.fade_distance = distance;
+        self
+    }
+
+    pub fn with_priority(mut self, priority: i32) -> ReverbZone {
+        self.priority = priority;
+        self
+    }
+
+    /// 1.0 inside the box, fading to 0.0 at `fade_distance` outside
+    pub fn weight(&self, global: &Isometry3<f32>, pos: Vector3<f32>) -> f32 {
+        let local = global.rot.invert() * (pos - global.disp);
+        let scale = if global.scale > 0.0 { global.scale } else { 1.0 };
+
+        let outside = Vector3::new(
+            (local.x.abs() / scale - self.extents.x).max(0.0),
+            (local.y.abs() / scale - self.extents.y).max(0.0),
+            (local.z.abs() / scale - self.extents.z).max(0.0),
+        );
+        let distance = outside.magnitude() * scale;
+
+        if distance <= 0.0 {
+            1.0
+        } else if self.fade_distance > 0.0 {
+            (1.0 - distance / self.fade_distance).max(0.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+impl Actor for ReverbZone {
+    fn update(&mut self, go: &mut GameObject, world: &mut World) {
+        let listener = match world.audio_listener() {
+            Some(listener) => listener.position,
+            None => return,
+        };
+
+        let weight = self.weight(&go.transform.global(), listener);
+        world
+            .sound
+            .add_reverb_zone(self.params, weight, self.priority);
+    }
+}
@@ -0,0 +1,495 @@
+use engine::{CullMode, DepthTest, GameObject, Material, Mesh, MeshBuffer, MeshData, RenderQueue};
+use world::{Actor, Handle, World};
+
+use super::PopupFont;
+use math::*;
+use std::cell::RefCell;
+use std::rc::{self, Rc};
+
+/// Where a marker is in the world
+#[derive(Clone)]
+pub enum MarkerAnchor {
+    /// Follows a game object, the marker is removed with the object
+    Object(rc::Weak<RefCell<GameObject>>),
+    Position(Vector3f),
+}
+
+impl MarkerAnchor {
+    pub fn object(go: &Handle<GameObject>) -> MarkerAnchor {
+        MarkerAnchor::Object(Rc::downgrade(go))
+    }
+
+    /// None once the game object is dropped
+    fn position(&self) -> Option<Vector3f> {
+        match *self {
+            MarkerAnchor::Object(ref go) => {
+                let go = go.upgrade()?;
+                let p = go.borrow().transform.global().disp;
+                Some(p)
+            }
+            MarkerAnchor::Position(p) => Some(p),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MarkerKind {
+    /// A bar filled by `value`, from 0 to 1. The size is in pixels.
+    HealthBar {
+        value: f32,
+        width: f32,
+        height: f32,
+        color: Vector3f,
+    },
+    /// A line of text
+    Nameplate { text: String, color: Vector3f },
+    /// A diamond of `size` pixels, and an arrow on the edge of the screen toward the anchor
+    /// when it is off-screen
+    Waypoint {
+        color: Vector3f,
+        size: f32,
+        /// Shows the distance to the camera under the diamond
+        show_distance: bool,
+    },
+}
+
+#[derive(Clone)]
+pub struct Marker {
+    pub anchor: MarkerAnchor,
+    /// Offset from the anchor in world units, e.g. above the head of a character
+    pub offset: Vector3f,
+    /// Offset on screen in pixels, y up, e.g. to stack a nameplate over a health bar
+    pub screen_offset: Vector2f,
+    pub kind: MarkerKind,
+    pub visible: bool,
+}
+
+impl Marker {
+    pub fn new(anchor: MarkerAnchor, kind: MarkerKind) -> Marker {
+        Marker {
+            anchor,
+            offset: Vector3::zero(),
+            screen_offset: Vector2::zero(),
+            kind,
+            visible: true,
+        }
+    }
+
+    pub fn with_offset(mut self, offset: Vector3f) -> Marker {
+        self.offset = offset;
+        self
+    }
+
+    pub fn with_screen_offset(mut self, x: f32, y: f32) -> Marker {
+        self.screen_offset = Vector2::new(x, y);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MarkerId(usize, u32);
+
+/// Position of a projected point on the screen, in pixels from the center with y up, and
+/// whether it is on the screen. The points off-screen or behind the camera are clamped to
+/// the edges, `margin` pixels inside, in their direction from the center.
+pub fn clamp_to_screen(clip: Vector4f, screen_size: (u32, u32), margin: f32) -> (Vector2f, bool) {
+    let half = Vector2::new(screen_size.0 as f32 * 0.5, screen_size.1 as f32 * 0.5);
+    let behind = clip.w <= 1e-6;
+
+    // Behind the camera the division by w would flip the direction
+    let w = clip.w.abs().max(1e-6);
+    let mut p = Vector2::new(clip.x / w * half.x, clip.y / w * half.y);
+
+    if !behind && p.x.abs() <= half.x && p.y.abs() <= half.y {
+        return (p, true);
+    }
+
+    // Right behind, toward the bottom
+    if p.magnitude2() < 1e-6 {
+        p = Vector2::new(0.0, -1.0);
+    }
+
+    let limit = Vector2::new((half.x - margin).max(1.0), (half.y - margin).max(1.0));
+    let k = (p.x.abs() / limit.x).max(p.y.abs() / limit.y);
+    (p / k, false)
+}
+
+/// Quads of the frame, in normalized device coordinates
+#[derive(Default)]
+struct Batch {
+    half: Vector2f,
+    vertices: Vec<f32>,
+    uvs: Vec<f32>,
+    colors: Vec<f32>,
+    // Alpha and whether the font is sampled
+    params: Vec<f32>,
+    indices: Vec<u16>,
+}
+
+impl Batch {
+    /// Corners in pixels from the center of the screen, counter clockwise
+    fn quad(
+        &mut self,
+        corners: [Vector2f; 4],
+        uvs: [Vector2f; 4],
+        color: Vector4f,
+        textured: bool,
+    ) {
+        if self.vertices.len() / 3 + 4 > u16::max_value() as usize {
+            return;
+        }
+
+        let first = (self.vertices.len() / 3) as u16;
+        for k in 0..4 {
+            let p = corners[k];
+            self.vertices
+                .extend_from_slice(&[p.x / self.half.x, p.y / self.half.y, 0.0]);
+            self.uvs.extend_from_slice(&[uvs[k].x, uvs[k].y]);
+            self.colors.extend_from_slice(&[color.x, color.y, color.z]);
+            self.params
+                .extend_from_slice(&[color.w, if textured { 1.0 } else { 0.0 }, 0.0]);
+        }
+        self.indices.extend_from_slice(&[
+            first,
+            first + 1,
+            first + 2,
+            first,
+            first + 2,
+            first + 3,
+        ]);
+    }
+
+    fn rect(&mut self, min: Vector2f, max: Vector2f, color: Vector4f) {
+        let zero = Vector2::zero();
+        self.quad(
+            [min, Vector2::new(max.x, min.y), max, Vector2::new(min.x, max.y)],
+            [zero; 4],
+            color,
+            false,
+        );
+    }
+
+    /// A line of text centered on `center`, glyphs of `height` pixels
+    fn text(
+        &mut self,
+        font: &PopupFont,
+        text: &str,
+        center: Vector2f,
+        height: f32,
+        color: Vector4f,
+    ) {
+        let width = height * font.aspect();
+        let count = text.chars().count() as f32;
+        let x0 = center.x - count * width * 0.5;
+
+        for (k, c) in text.chars().enumerate() {
+            let (uv, size) = font.glyph_uv(c);
+            let min = Vector2::new(x0 + k as f32 * width, center.y - height * 0.5);
+            let max = min + Vector2::new(width, height);
+
+            // The glyphs are from the top of the texture
+            self.quad(
+                [min, Vector2::new(max.x, min.y), max, Vector2::new(min.x, max.y)],
+                [
+                    uv + Vector2::new(0.0, size.y),
+                    uv + size,
+                    uv + Vector2::new(size.x, 0.0),
+                    uv,
+                ],
+                color,
+                true,
+            );
+        }
+    }
+
+    fn finish(self) -> MeshData {
+        MeshData {
+            vertices: self.vertices,
+            uvs: Some(self.uvs),
+            uvs2: None,
+            normals: Some(self.colors),
+            tangents: Some(self.params),
+            bitangents: None,
+            indices: self.indices,
+        }
+    }
+}
+
+/// Health bars, nameplates and waypoints over the world, drawn on the screen in a single
+/// batch.
+///
+/// The anchors are projected with the main camera once per frame, after the updates, and the
+/// quads of all the visible markers are written in one buffer. Health bars and nameplates are
+/// hidden off-screen and beyond `max_distance`, waypoints are clamped to the edges of the
+/// screen with an arrow toward their anchor. All the markers shrink with the distance, down to
+/// `min_scale`. The marker slots are reused, the ones of dropped game objects are freed.
+///
+/// ```ignore
+/// let id = markers.add(
+///     Marker::new(MarkerAnchor::object(&enemy), MarkerKind::HealthBar {
+///         value: 1.0, width: 60.0, height: 6.0, color: vec3(0.8, 0.1, 0.1),
+///     }).with_offset(vec3(0.0, 2.0, 0.0)),
+/// );
+/// markers.set_value(id, 0.5);
+/// ```
+///
+/// The batch has its own game object, the game object of the actor is not used.
+///
+/// Register it by `WorldBuilder::with_actor::<ScreenMarkers>()`.
+#[derive(Component)]
+pub struct ScreenMarkers {
+    /// The font of the nameplates and distances
+    pub font: PopupFont,
+    /// Height of the text in pixels
+    pub text_size: f32,
+    /// Distance of the waypoints from the edges of the screen, in pixels
+    pub edge_margin: f32,
+    /// Markers are at full size up to this distance
+    pub scale_distance: f32,
+    pub min_scale: f32,
+    /// Health bars and nameplates farther are hidden
+    pub max_distance: f32,
+
+    slots: Vec<(u32, Option<Marker>)>,
+    free: Vec<usize>,
+
+    material: Option<Rc<Material>>,
+    buffer: Option<Rc<MeshBuffer>>,
+    batch: Option<Handle<GameObject>>,
+}
+
+impl ScreenMarkers {
+    pub fn new(font: PopupFont) -> ScreenMarkers {
+        ScreenMarkers {
+            font,
+            text_size: 16.0,
+            edge_margin: 24.0,
+            scale_distance: 10.0,
+            min_scale: 0.5,
+            max_distance: 50.0,
+
+            slots: Vec::new(),
+            free: Vec::new(),
+
+            material: None,
+            buffer: None,
+            batch: None,
+        }
+    }
+
+    pub fn add(&mut self, marker: Marker) -> MarkerId {
+        match self.free.pop() {
+            Some(i) => {
+                let slot = &mut self.slots[i];
+                slot.0 = slot.0.wrapping_add(1);
+                slot.1 = Some(marker);
+                MarkerId(i, slot.0)
+            }
+            None => {
+                self.slots.push((0, Some(marker)));
+                MarkerId(self.slots.len() - 1, 0)
+            }
+        }
+    }
+
+    pub fn remove(&mut self, id: MarkerId) -> Option<Marker> {
+        let marker = self.get_mut(id)?.clone();
+        self.slots[id.0].1 = None;
+        self.free.push(id.0);
+        Some(marker)
+    }
+
+    pub fn get_mut(&mut self, id: MarkerId) -> Option<&mut Marker> {
+        let slot = self.slots.get_mut(id.0)?;
+        if slot.0 != id.1 {
+            return None;
+        }
+
+        slot.1.as_mut()
+    }
+
+    /// Set the value of a health bar
+    pub fn set_value(&mut self, id: MarkerId, v: f32) {
+        if let Some(marker) = self.get_mut(id) {
+            if let MarkerKind::HealthBar { ref mut value, .. } = marker.kind {
+                *value = v.max(0.0).min(1.0);
+            }
+        }
+    }
+
+    /// Number of markers
+    pub fn len(&self) -> usize {
+        self.slots.len() - self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn build(&mut self, world: &mut World) {
+        let mut material =
+            Material::new(world.asset_system().new_program("unrust/screen_markers"));
+        material.render_queue = RenderQueue::UI;
+        material.states.depth_test = Some(DepthTest::Always);
+        material.states.depth_write = Some(false);
+        material.states.cull = Some(CullMode::Off);
+        material.set("uMarkerFont", self.font.texture.clone());
+        let material = Rc::new(material);
+
+        let buffer = Rc::new(MeshBuffer::new(Batch::default().finish()));
+
+        let mut mesh = Mesh::new();
+        mesh.add_surface(buffer.clone(), material.clone());
+
+        let batch = world.new_game_object();
+        batch.borrow_mut().add_component(mesh);
+
+        self.batch = Some(batch);
+        self.buffer = Some(buffer);
+        self.material = Some(material);
+    }
+
+    fn write(
+        &self,
+        batch: &mut Batch,
+        marker: &Marker,
+        eye: Vector3f,
+        view_proj: Matrix4<f32>,
+        screen_size: (u32, u32),
+        hidpi: f32,
+    ) {
+        let position = match marker.anchor.position() {
+            Some(p) => p + marker.offset,
+            None => return,
+        };
+
+        let distance = (position - eye).magnitude();
+        let waypoint = match marker.kind {
+            MarkerKind::Waypoint { .. } => true,
+            _ => false,
+        };
+        if !waypoint && distance > self.max_distance {
+            return;
+        }
+
+        let margin = self.edge_margin * hidpi;
+        let (p, on_screen) = clamp_to_screen(view_proj * position.extend(1.0), screen_size, margin);
+        if !waypoint && !on_screen {
+            return;
+        }
+
+        let scale = if distance > self.scale_distance {
+            (self.scale_distance / distance).max(self.min_scale)
+        } else {
+            1.0
+        };
+        let scale = scale * hidpi;
+
+        let center = if on_screen {
+            p + marker.screen_offset * scale
+        } else {
+            p
+        };
+
+        match marker.kind {
+            MarkerKind::HealthBar { value, width, height, color } => {
+                let half = Vector2::new(width, height) * scale * 0.5;
+                let border = Vector2::new(1.0, 1.0) * hidpi;
+                let background = vec4(0.0, 0.0, 0.0, 0.6);
+                batch.rect(center - half - border, center + half + border, background);
+
+                let fill = (center.x - half.x) + half.x * 2.0 * value.max(0.0).min(1.0);
+                batch.rect(center - half, Vector2::new(fill, center.y + half.y), color.extend(1.0));
+            }
+            MarkerKind::Nameplate { ref text, color } => {
+                batch.text(&self.font, text, center, self.text_size * scale, color.extend(1.0));
+            }
+            MarkerKind::Waypoint { color, size, show_distance } => {
+                let s = size * scale * 0.5;
+                let color = color.extend(1.0);
+                let zero = [Vector2::zero(); 4];
+
+                if on_screen {
+                    let corners = [
+                        center + Vector2::new(0.0, -s),
+                        center + Vector2::new(s, 0.0),
+                        center + Vector2::new(0.0, s),
+                        center + Vector2::new(-s, 0.0),
+                    ];
+                    batch.quad(corners, zero, color, false);
+
+                    if show_distance {
+                        let text = format!("{}m", distance.round() as i64);
+                        let h = self.text_size * scale;
+                        let at = center - Vector2::new(0.0, s + h);
+                        batch.text(&self.font, &text, at, h, color);
+                    }
+                } else {
+                    // An arrow toward the anchor, as a quad folded in a triangle
+                    let dir = p.normalize();
+                    let side = Vector2::new(-dir.y, dir.x);
+                    let tip = center + dir * s;
+                    let back = center - dir * s;
+                    let corners = [back + side * s, tip, tip, back - side * s];
+                    batch.quad(corners, zero, color, false);
+                }
+            }
+        }
+    }
+}
+
+impl Actor for ScreenMarkers {
+    // Once the anchors are moved
+    fn late_update(&mut self, _go: &mut GameObject, world: &mut World) {
+        if self.buffer.is_none() {
+            self.build(world);
+        }
+
+        let screen_size = world.engine().screen_size;
+        let hidpi = world.engine().hidpi;
+
+        let camera = match world.current_camera() {
+            Some(cam) => {
+                let cam = cam.borrow();
+                let view = (cam.eye(), cam.perspective(screen_size) * cam.v);
+                Some(view)
+            }
+            None => None,
+        };
+
+        // Free the markers of the dropped game objects
+        for i in 0..self.slots.len() {
+            let dropped = match self.slots[i].1 {
+                Some(ref m) => m.anchor.position().is_none(),
+                None => false,
+            };
+            if dropped {
+                self.slots[i].1 = None;
+                self.free.push(i);
+            }
+        }
+
+        let mut batch = Batch {
+            half: Vector2::new(screen_size.0 as f32 * 0.5, screen_size.1 as f32 * 0.5),
+            ..Default::default()
+        };
+
+        if let Some((eye, view_proj)) = camera {
+            for &(_, ref marker) in self.slots.iter() {
+                if let Some(ref marker) = *marker {
+                    if marker.visible {
+                        self.write(&mut batch, marker, eye, view_proj, screen_size, hidpi);
+                    }
+                }
+            }
+        }
+
+        let empty = batch.indices.is_empty();
+        if let Some(ref go) = self.batch {
+            go.borrow_mut().active = !empty;
+        }
+        if !empty {
+            self.buffer.as_ref().unwrap().update_mesh_data(batch.finish());
+        }
+    }
+}
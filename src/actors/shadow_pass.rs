@@ -430,7 +430,8 @@ impl ShadowPass {
         self.shadow_maps[3].partition_z = partitions[3];
     }
 
-    fn apply(&self, material: &Material) {
+    /// Bind the shadow maps to a material, e.g. of a post effect
+    pub fn apply(&self, material: &Material) {
         material.set("uShadowEnabled", true);
         material.set("uShadowMapTexture", self.rt.as_texture());
         material.set("ShadowMapParams", self.material_params.clone());
@@ -441,7 +442,11 @@ impl Actor for ShadowPass {
     fn start(&mut self, _go: &mut GameObject, world: &mut World) {
         let db = &mut world.asset_system();
 
+        // The objects cut out take a copy with their cutout, see `Material::with_cutout_of`.
+        // The uniforms keep their values between draws, so this one resets them.
         let shadow_mat = Material::new(db.new_program("unrust/shadow"));
+        shadow_mat.set_cutout(0.0);
+        shadow_mat.set("uDissolve", 0.0);
         self.shadow_material = Some(Rc::new(shadow_mat));
 
         // Setup proper viewport to render to the whole texture
@@ -537,15 +542,19 @@ impl Actor for ShadowPass {
         }
 
         // GUI
-        use imgui;
-        use imgui::Metric::*;
+        #[cfg(feature = "ui")]
+        {
+            use imgui;
+            use imgui::Metric::*;
 
-        imgui::pivot((0.0, 1.0));
-        let mut mat = Material::new(world.asset_system().new_program("unrust/shadow_display"));
-        mat.set("uDepthMap", self.rt.as_texture());
-        mat.render_queue = RenderQueue::UI;
+            imgui::pivot((0.0, 1.0));
+            let mut mat =
+                Material::new(world.asset_system().new_program("unrust/shadow_display"));
+            mat.set("uDepthMap", self.rt.as_texture());
+            mat.render_queue = RenderQueue::UI;
 
-        imgui::image_with_material(Native(0.0, 1.0), Pixel(100.0, 100.0), Rc::new(mat));
+            imgui::image_with_material(Native(0.0, 1.0), Pixel(100.0, 100.0), Rc::new(mat));
+        }
     }
 }
 
@@ -0,0 +1,140 @@
+use actors::Animator;
+use engine::animation::Skeleton;
+use engine::GameObject;
+use world::{Actor, Handle, World};
+
+use math::*;
+use std::mem;
+use std::rc::Rc;
+
+/// An attachment point on a bone
+pub struct Socket {
+    pub name: String,
+    pub bone: String,
+    /// Transform relative to the bone
+    pub offset: Isometry3<f32>,
+    /// Game object following the socket, it must not be a descendant of the character
+    pub attached: Option<Handle<GameObject>>,
+    global: Option<Isometry3<f32>>,
+}
+
+impl Socket {
+    /// Global transform of the socket, after the last update
+    pub fn global(&self) -> Option<Isometry3<f32>> {
+        self.global
+    }
+}
+
+/// Named attachment points on the bones of a character, e.g. a weapon in the hand or a
+/// hat on the head. The attached game objects follow their socket each frame.
+///
+/// ```ignore
+/// let mut sockets = Sockets::new();
+/// sockets.add("right_hand", "hand.R", Isometry3::one());
+/// sockets.attach("right_hand", sword);
+/// character.add_component(sockets);
+/// ```
+///
+/// The bones are found in the skeleton of the `Animator`, or in the named descendants of
+/// the game object. Register it by `WorldBuilder::with_actor::<Sockets>()`, after the
+/// actors animating the bones.
+#[derive(Component)]
+pub struct Sockets {
+    pub sockets: Vec<Socket>,
+
+    skeleton: Option<Rc<Skeleton>>,
+}
+
+impl Sockets {
+    pub fn new() -> Sockets {
+        Sockets {
+            sockets: Vec::new(),
+            skeleton: None,
+        }
+    }
+
+    /// Add a socket, replace the one with the same name
+    pub fn add(&mut self, name: &str, bone: &str, offset: Isometry3<f32>) {
+        self.sockets.retain(|s| s.name != name);
+        self.sockets.push(Socket {
+            name: name.to_owned(),
+            bone: bone.to_owned(),
+            offset,
+            attached: None,
+            global: None,
+        });
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Handle<GameObject>> {
+        let attached = self.detach(name);
+        self.sockets.retain(|s| s.name != name);
+        attached
+    }
+
+    pub fn find(&self, name: &str) -> Option<&Socket> {
+        self.sockets.iter().find(|s| s.name == name)
+    }
+
+    pub fn find_mut(&mut self, name: &str) -> Option<&mut Socket> {
+        self.sockets.iter_mut().find(|s| s.name == name)
+    }
+
+    /// Attach a game object to a socket, returns the game object previously attached
+    pub fn attach(&mut self, name: &str, go: Handle<GameObject>) -> Option<Handle<GameObject>> {
+        match self.find_mut(name) {
+            Some(socket) => mem::replace(&mut socket.attached, Some(go)),
+            None => {
+                println!("Fail to attach to socket {}, no such socket", name);
+                None
+            }
+        }
+    }
+
+    pub fn detach(&mut self, name: &str) -> Option<Handle<GameObject>> {
+        self.find_mut(name).and_then(|s| s.attached.take())
+    }
+
+    /// Game object attached to a socket
+    pub fn attached(&self, name: &str) -> Option<Handle<GameObject>> {
+        self.find(name).and_then(|s| s.attached.clone())
+    }
+
+    /// Global transform of a socket, after the last update
+    pub fn global(&self, name: &str) -> Option<Isometry3<f32>> {
+        self.find(name).and_then(|s| s.global)
+    }
+}
+
+impl Actor for Sockets {
+    fn update(&mut self, go: &mut GameObject, _world: &mut World) {
+        if self.skeleton.is_none() {
+            self.skeleton = match go.find_component::<Animator>() {
+                Some((animator, _)) => animator.skeleton(),
+                None => Some(Rc::new(Skeleton::from_root(go))),
+            };
+        }
+
+        let skeleton = match self.skeleton {
+            Some(ref skeleton) => skeleton.clone(),
+            None => return,
+        };
+
+        for socket in self.sockets.iter_mut() {
+            socket.global = skeleton
+                .object(&socket.bone)
+                .map(|bone| bone.borrow().transform.global().concat(&socket.offset));
+
+            let global = match socket.global {
+                Some(global) => global,
+                None => continue,
+            };
+
+            if let Some(ref attached) = socket.attached {
+                // Skip the object while its own actors are updating it
+                if let Ok(mut attached) = attached.try_borrow_mut() {
+                    attached.transform.set_global(global);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,356 @@
+use engine::animation::{Curve, Keyframe};
+use engine::{AssetSystem, GameObject, Material, Mesh, MeshBuffer, MeshData, RenderQueue, Texture};
+use world::{Actor, Handle, World};
+
+use math::*;
+use std::ops::Range;
+use std::rc::Rc;
+
+/// Samples of the motion curves sent to the shader, the size of the arrays of
+/// `unrust/text_popup`
+pub const POPUP_CURVE_SAMPLES: usize = 16;
+
+/// Glyph quads of a batch at most, with the 2 vertices of the bounds
+const MAX_QUADS: usize = (65535 - 2) / 4;
+
+/// Spawn time of the unused glyphs, long expired
+const UNUSED: f32 = -1e9;
+
+/// Half size of the bounds of the batch, the popups are anywhere in the world
+const BOUNDS: f32 = 1e5;
+
+/// A bitmap font of fixed size glyphs, in ascii order from the top left of the texture
+#[derive(Clone)]
+pub struct PopupFont {
+    pub texture: Rc<Texture>,
+    pub texture_size: (u32, u32),
+    pub glyph_size: (u32, u32),
+}
+
+impl PopupFont {
+    /// The bitmap font of the ui
+    pub fn default_font(asys: &AssetSystem) -> PopupFont {
+        PopupFont {
+            texture: asys.new_texture("default_font_bitmap"),
+            texture_size: (128, 64),
+            glyph_size: (8, 8),
+        }
+    }
+
+    /// Top left and size of a glyph in the texture
+    pub fn glyph_uv(&self, c: char) -> (Vector2f, Vector2f) {
+        let c = (c as u32).min(127);
+        let per_row = (self.texture_size.0 / self.glyph_size.0).max(1);
+        let size = Vector2::new(
+            self.glyph_size.0 as f32 / self.texture_size.0 as f32,
+            self.glyph_size.1 as f32 / self.texture_size.1 as f32,
+        );
+
+        let cell = Vector2::new((c % per_row) as f32, (c / per_row) as f32);
+        (Vector2::new(cell.x * size.x, cell.y * size.y), size)
+    }
+
+    /// Width of a glyph relative to its height
+    pub fn aspect(&self) -> f32 {
+        self.glyph_size.0 as f32 / self.glyph_size.1.max(1) as f32
+    }
+}
+
+/// Motion of the popups over their normalized lifetime, from 0 to 1
+#[derive(Debug, Clone, PartialEq)]
+pub struct PopupMotion {
+    /// Seconds
+    pub lifetime: f32,
+    /// Offset from the spawn position, in world units
+    pub offset: Curve<Vector3f>,
+    pub scale: Curve<f32>,
+    pub alpha: Curve<f32>,
+    /// Random horizontal offset on screen, in glyph heights
+    pub spread: f32,
+}
+
+impl Default for PopupMotion {
+    /// Pops up, rises and fades out
+    fn default() -> PopupMotion {
+        let key = |time, value| Keyframe { time, value };
+
+        PopupMotion {
+            lifetime: 1.0,
+            offset: Curve::new(vec![
+                key(0.0, Vector3::zero()),
+                key(0.3, vec3(0.0, 1.0, 0.0)),
+                key(1.0, vec3(0.0, 1.5, 0.0)),
+            ]),
+            scale: Curve::new(vec![key(0.0, 0.5), key(0.1, 1.3), key(0.25, 1.0)]),
+            alpha: Curve::new(vec![key(0.0, 1.0), key(0.6, 1.0), key(1.0, 0.0)]),
+            spread: 1.0,
+        }
+    }
+}
+
+/// Short-lived texts in the world facing the camera, e.g. damage numbers or scores, drawn
+/// by the GPU in a single batch.
+///
+/// The popups are pooled in a fixed number of slots of `max_chars` glyphs, a new popup
+/// replaces the oldest one when all the slots are used. Spawning only writes the glyphs of
+/// the popup, the motion (`PopupMotion`) is computed in the vertex shader from the spawn
+/// time, so the popups cost nothing to the CPU while alive. All the popups of a font are a
+/// single draw call, use one `TextPopups` per font atlas.
+///
+/// ```ignore
+/// go.add_component(TextPopups::new(PopupFont::default_font(world.asset_system()), 64, 6));
+///
+/// let popups = world.find_component::<TextPopups>().unwrap();
+/// popups.borrow_mut().spawn_number(hit_point, damage, vec3(1.0, 0.8, 0.2));
+/// ```
+///
+/// The batch has its own game object, the game object of the actor is not used.
+///
+/// Register it by `WorldBuilder::with_actor::<TextPopups>()`.
+#[derive(Component)]
+pub struct TextPopups {
+    pub font: PopupFont,
+    pub motion: PopupMotion,
+    /// Height of a glyph in world units
+    pub size: f32,
+
+    max_chars: usize,
+    time: f32,
+    next: usize,
+    spawned: u32,
+    spawn_times: Vec<f32>,
+
+    data: MeshData,
+    /// Vertices of the slots spawned since the last upload
+    dirty: Option<Range<usize>>,
+    buffer: Option<Rc<MeshBuffer>>,
+    material: Option<Rc<Material>>,
+    batch: Option<Handle<GameObject>>,
+
+    /// The motion sent to the shader, with the names of its uniforms
+    bound_motion: Option<PopupMotion>,
+    curve_uniforms: Vec<(String, String, String)>,
+}
+
+impl TextPopups {
+    /// `capacity` popups of `max_chars` glyphs at most alive at once
+    pub fn new(font: PopupFont, capacity: usize, max_chars: usize) -> TextPopups {
+        let max_chars = max_chars.max(1).min(MAX_QUADS);
+        let capacity = capacity.max(1).min(MAX_QUADS / max_chars);
+        let quads = capacity * max_chars;
+
+        let mut data = MeshData {
+            vertices: vec![0.0; quads * 12],
+            uvs: Some(vec![0.0; quads * 8]),
+            uvs2: None,
+            normals: Some(
+                [0.0, 0.0, UNUSED]
+                    .iter()
+                    .cycle()
+                    .take(quads * 12)
+                    .cloned()
+                    .collect(),
+            ),
+            tangents: Some(vec![0.0; quads * 12]),
+            bitangents: Some(vec![0.0; quads * 12]),
+            indices: Vec::with_capacity(quads * 6),
+        };
+
+        for q in 0..quads as u16 {
+            let i = q * 4;
+            data.indices
+                .extend_from_slice(&[i, i + 1, i + 2, i, i + 2, i + 3]);
+        }
+
+        // Bounds, not in the triangles
+        data.vertices
+            .extend_from_slice(&[-BOUNDS, -BOUNDS, -BOUNDS, BOUNDS, BOUNDS, BOUNDS]);
+        data.uvs.as_mut().unwrap().extend_from_slice(&[0.0; 4]);
+        data.normals
+            .as_mut()
+            .unwrap()
+            .extend_from_slice(&[0.0, 0.0, UNUSED, 0.0, 0.0, UNUSED]);
+        data.tangents.as_mut().unwrap().extend_from_slice(&[0.0; 6]);
+        data.bitangents.as_mut().unwrap().extend_from_slice(&[0.0; 6]);
+
+        TextPopups {
+            font,
+            motion: PopupMotion::default(),
+            size: 0.3,
+
+            max_chars,
+            time: 0.0,
+            next: 0,
+            spawned: 0,
+            spawn_times: vec![UNUSED; capacity],
+
+            data,
+            dirty: None,
+            buffer: None,
+            material: None,
+            batch: None,
+
+            bound_motion: None,
+            curve_uniforms: (0..POPUP_CURVE_SAMPLES)
+                .map(|i| {
+                    (
+                        format!("uPopupOffset[{}]", i),
+                        format!("uPopupScale[{}]", i),
+                        format!("uPopupAlpha[{}]", i),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    pub fn with_motion(mut self, motion: PopupMotion) -> TextPopups {
+        self.motion = motion;
+        self
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.spawn_times.len()
+    }
+
+    /// Number of popups still visible
+    pub fn alive(&self) -> usize {
+        let lifetime = self.motion.lifetime;
+        self.spawn_times
+            .iter()
+            .filter(|t| self.time - **t < lifetime)
+            .count()
+    }
+
+    pub fn spawn(&mut self, position: Vector3f, text: &str, color: Vector3f) {
+        self.spawn_scaled(position, text, color, 1.0);
+    }
+
+    pub fn spawn_number(&mut self, position: Vector3f, value: i64, color: Vector3f) {
+        self.spawn_scaled(position, &value.to_string(), color, 1.0);
+    }
+
+    /// Spawn a popup `scale` times larger, e.g. for critical hits. The text is cut at
+    /// `max_chars` glyphs.
+    pub fn spawn_scaled(&mut self, position: Vector3f, text: &str, color: Vector3f, scale: f32) {
+        let slot = self.next;
+        self.next = (self.next + 1) % self.capacity();
+        self.spawned = self.spawned.wrapping_add(1);
+        self.spawn_times[slot] = self.time;
+
+        let vertices = slot * self.max_chars * 4..(slot + 1) * self.max_chars * 4;
+        self.dirty = Some(match self.dirty.take() {
+            Some(d) => d.start.min(vertices.start)..d.end.max(vertices.end),
+            None => vertices,
+        });
+
+        let chars: Vec<char> = text.chars().take(self.max_chars).collect();
+        let half = chars.len() as f32 * 0.5;
+        let seed = (self.spawned % 1024) as f32;
+        let aspect = self.font.aspect();
+
+        let MeshData {
+            ref mut vertices,
+            ref mut uvs,
+            ref mut normals,
+            ref mut tangents,
+            ref mut bitangents,
+            ..
+        } = self.data;
+        let (uvs, normals) = (uvs.as_mut().unwrap(), normals.as_mut().unwrap());
+        let (tangents, bitangents) = (tangents.as_mut().unwrap(), bitangents.as_mut().unwrap());
+
+        for k in 0..self.max_chars {
+            let q = slot * self.max_chars + k;
+            let (uv, size) = match chars.get(k) {
+                Some(c) => self.font.glyph_uv(*c),
+                None => (Vector2::zero(), Vector2::zero()),
+            };
+            let spawn_time = if k < chars.len() { self.time } else { UNUSED };
+
+            // Corners in glyph heights from the center of the text, and their uvs
+            let corners = [
+                (0.0, -0.5, 0.0, 1.0),
+                (1.0, -0.5, 1.0, 1.0),
+                (1.0, 0.5, 1.0, 0.0),
+                (0.0, 0.5, 0.0, 0.0),
+            ];
+            for (j, &(x, y, u, v)) in corners.iter().enumerate() {
+                let (i2, i3) = ((q * 4 + j) * 2, (q * 4 + j) * 3);
+                let x = (k as f32 + x - half) * aspect;
+
+                vertices[i3..i3 + 3].copy_from_slice(&[position.x, position.y, position.z]);
+                uvs[i2..i2 + 2].copy_from_slice(&[uv.x + u * size.x, uv.y + v * size.y]);
+                normals[i3..i3 + 3].copy_from_slice(&[x, y, spawn_time]);
+                tangents[i3..i3 + 3].copy_from_slice(&[scale, seed, 0.0]);
+                bitangents[i3..i3 + 3].copy_from_slice(&[color.x, color.y, color.z]);
+            }
+        }
+    }
+
+    fn build(&mut self, world: &mut World) -> Rc<Material> {
+        let mut material = Material::new(world.asset_system().new_program("unrust/text_popup"));
+        material.render_queue = RenderQueue::Transparent;
+        material.set("uPopupFont", self.font.texture.clone());
+        let material = Rc::new(material);
+
+        let buffer = Rc::new(MeshBuffer::new(self.data.clone()));
+        self.dirty = None;
+
+        let mut mesh = Mesh::new();
+        mesh.add_surface(buffer.clone(), material.clone());
+
+        let batch = world.new_game_object();
+        batch.borrow_mut().add_component(mesh);
+
+        self.batch = Some(batch);
+        self.buffer = Some(buffer);
+        self.material = Some(material.clone());
+        material
+    }
+
+    fn bind(&mut self, material: &Material) {
+        material.set("uPopupTime", self.time);
+        material.set("uPopupSize", self.size);
+
+        // The curves are sampled again only when the motion changes
+        if self.bound_motion.as_ref() == Some(&self.motion) {
+            return;
+        }
+
+        let m = &self.motion;
+        material.set("uPopupLifetime", m.lifetime.max(1e-3));
+        material.set("uPopupSpread", m.spread);
+
+        let last = (POPUP_CURVE_SAMPLES - 1) as f32;
+        for (i, names) in self.curve_uniforms.iter().enumerate() {
+            let t = i as f32 / last;
+            let offset = m.offset.sample(t).unwrap_or(Vector3::zero());
+            material.set(names.0.clone(), offset);
+            material.set(names.1.clone(), m.scale.sample(t).unwrap_or(1.0));
+            material.set(names.2.clone(), m.alpha.sample(t).unwrap_or(1.0));
+        }
+
+        self.bound_motion = Some(m.clone());
+    }
+}
+
+impl Actor for TextPopups {
+    fn update(&mut self, _go: &mut GameObject, world: &mut World) {
+        let material = match self.material.clone() {
+            Some(material) => material,
+            None => self.build(world),
+        };
+        self.time += world.delta_time() as f32;
+
+        self.bind(&material);
+    }
+
+    // Once the popups of the frame are spawned
+    fn late_update(&mut self, _go: &mut GameObject, _world: &mut World) {
+        if let Some(ref buffer) = self.buffer {
+            if let Some(range) = self.dirty.take() {
+                buffer.update_vertex_range(&self.data, range);
+            }
+        }
+    }
+}
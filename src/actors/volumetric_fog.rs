@@ -0,0 +1,199 @@
+use actors::ShadowPass;
+use engine::{GameObject, Light, Material};
+use world::{Actor, Processor, World};
+
+use math::*;
+use std::rc::Rc;
+
+/// Number of spot lights scattered by the fog
+pub const MAX_FOG_SPOT_LIGHTS: usize = 4;
+
+/// Number of raymarching steps and shadow lookups per pixel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FogQuality {
+    /// 8 steps without shadows, for web and low end devices
+    Low,
+    /// 16 steps with shadows
+    Medium,
+    /// 32 steps with shadows
+    High,
+}
+
+impl FogQuality {
+    fn steps(&self) -> i32 {
+        match *self {
+            FogQuality::Low => 8,
+            FogQuality::Medium => 16,
+            FogQuality::High => 32,
+        }
+    }
+
+    fn shadows(&self) -> bool {
+        *self != FogQuality::Low
+    }
+}
+
+impl Default for FogQuality {
+    #[cfg(target_arch = "wasm32")]
+    fn default() -> FogQuality {
+        FogQuality::Low
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn default() -> FogQuality {
+        FogQuality::Medium
+    }
+}
+
+/// Volumetric fog, a post effect raymarching the view rays through a height fog
+/// lit by the main directional light and the spot lights.
+///
+/// Light scattered by the fog is occluded by the shadow maps of the `ShadowPass`,
+/// which makes light shafts. The spot lights, up to `MAX_FOG_SPOT_LIGHTS`, make cones
+/// of light in the fog, without shadows. The fog is applied before the other post effects.
+#[derive(Component)]
+pub struct VolumetricFog {
+    pub enabled: bool,
+    pub quality: FogQuality,
+    /// Extinction per world unit at `height`
+    pub density: f32,
+    /// World height of the fog base, the density decreases exponentially above it
+    pub height: f32,
+    pub height_falloff: f32,
+    /// Color of the fog lit by the ambient light
+    pub color: Vector3<f32>,
+    /// Henyey-Greenstein anisotropy, from -1 (back scattering) to 1 (forward scattering)
+    pub anisotropy: f32,
+    /// Scale of the light scattered from the main light
+    pub scattering: f32,
+    /// Rays are marched up to this distance from the camera
+    pub max_distance: f32,
+
+    material: Option<Rc<Material>>,
+    frame: u32,
+}
+
+impl Processor for VolumetricFog {
+    fn new() -> VolumetricFog {
+        VolumetricFog {
+            enabled: true,
+            quality: FogQuality::default(),
+            density: 0.05,
+            height: 0.0,
+            height_falloff: 0.2,
+            color: Vector3::new(0.5, 0.6, 0.7),
+            anisotropy: 0.6,
+            scattering: 1.0,
+            max_distance: 100.0,
+
+            material: None,
+            frame: 0,
+        }
+    }
+}
+
+impl Actor for VolumetricFog {
+    fn update(&mut self, _go: &mut GameObject, world: &mut World) {
+        if !self.enabled {
+            self.remove(world);
+            return;
+        }
+
+        if self.material.is_none() {
+            let material = Rc::new(Material::new(
+                world.asset_system().new_program("unrust/volumetric_fog"),
+            ));
+            world.engine_mut().post_effects.insert(0, material.clone());
+            self.material = Some(material);
+        }
+
+        let material = self.material.clone().unwrap();
+
+        let (view_proj, eye) = match world.current_camera() {
+            Some(cam) => {
+                let cam = cam.borrow();
+                (cam.perspective(world.engine().screen_size) * cam.v, cam.eye())
+            }
+            None => return,
+        };
+
+        material.set("uViewProj", view_proj);
+        material.set(
+            "uInvViewProj",
+            view_proj.inverse_transform().unwrap_or(Matrix4::identity()),
+        );
+        material.set("uCameraPos", eye);
+
+        material.set("uSteps", self.quality.steps());
+        material.set("uDensity", self.density);
+        material.set("uFogHeight", self.height);
+        material.set("uHeightFalloff", self.height_falloff);
+        material.set("uFogColor", self.color);
+        material.set("uAnisotropy", self.anisotropy.max(-0.99).min(0.99));
+        material.set("uScattering", self.scattering);
+        material.set("uMaxDistance", self.max_distance);
+
+        // Jitter the ray starts between frames to hide the banding of the few steps
+        self.frame = self.frame.wrapping_add(1);
+        material.set("uJitter", (self.frame % 16) as f32 / 16.0);
+
+        self.bind_light(&material, world);
+
+        material.set("uShadowEnabled", false);
+        if self.quality.shadows() {
+            if let Some(shadow_pass) = world.find_component::<ShadowPass>() {
+                shadow_pass.borrow().apply(&material);
+            }
+        }
+    }
+}
+
+impl VolumetricFog {
+    fn bind_light(&self, material: &Material, world: &World) {
+        let light = world.engine().find_main_light();
+        let light = light.as_ref().and_then(|c| c.try_as::<Light>()).map(|l| l.borrow());
+
+        match light.as_ref().and_then(|l| l.directional()) {
+            Some(l) => {
+                material.set("uLightDirection", l.world_space_direction.normalize());
+                material.set("uLightColor", l.diffuse);
+            }
+            None => {
+                material.set("uLightDirection", Vector3::new(0.0, -1.0, 0.0));
+                material.set("uLightColor", Vector3::zero());
+            }
+        }
+
+        let spots = world.engine().find_spot_lights();
+        let mut count = 0;
+        for com in spots.iter().take(MAX_FOG_SPOT_LIGHTS) {
+            let light = com.try_as::<Light>().unwrap().borrow();
+            let spot = match light.spot() {
+                Some(spot) => spot,
+                None => continue,
+            };
+
+            let name = format!("uSpotLights[{}]", count);
+            let (cos_outer, cos_inner) = spot.cone();
+            material.set(name.clone() + ".position", spot.world_space_position);
+            material.set(name.clone() + ".spotDirection", spot.world_space_direction);
+            material.set(name.clone() + ".spotCosOuter", cos_outer);
+            material.set(name.clone() + ".spotCosInner", cos_inner);
+            material.set(name.clone() + ".diffuse", spot.diffuse);
+            material.set(name.clone() + ".constant", spot.constant);
+            material.set(name.clone() + ".linear", spot.linear);
+            material.set(name + ".quadratic", spot.quadratic);
+            count += 1;
+        }
+        material.set("uSpotLightCount", count as i32);
+    }
+
+    fn remove(&mut self, world: &mut World) {
+        if let Some(material) = self.material.take() {
+            world
+                .engine_mut()
+                .post_effects
+                .retain(|m| !Rc::ptr_eq(m, &material));
+        }
+    }
+}
@@ -0,0 +1,243 @@
+use engine::voxel::{mesh_chunk, ChunkCoord, VoxelVolume, CHUNK_NEIGHBORS};
+use engine::{GameObject, Material, Mesh, MeshBuffer, MeshData};
+use world::{Actor, Handle, World};
+
+#[cfg(feature = "physics")]
+use engine::physics::{Collider, ColliderHandle, TriMesh};
+
+use math::*;
+use std::collections::{BTreeMap, BTreeSet};
+use std::rc::Rc;
+
+struct VoxelChunk {
+    go: Handle<GameObject>,
+    buffer: Rc<MeshBuffer>,
+    #[cfg(feature = "physics")]
+    collider: Option<ColliderHandle>,
+}
+
+/// A voxel volume meshed in chunks, which follow the game object. The chunks edited with
+/// `volume.dig` or `volume.build` (in the local space of the game object) are remeshed at
+/// the next updates, a few per update.
+///
+/// The chunks get coarser with the distance to `lod_center`, or to the current camera.
+/// With the `physics` feature and `colliders`, each chunk has a triangle mesh collider in
+/// `World::physics`, at the level of detail of the chunk, so keep the terrain in place.
+///
+/// ```ignore
+/// let fbm = Fbm::new(7, NoiseKind::Simplex).with_frequency(0.02);
+/// let volume = VoxelVolume::from_fn(16, (8, 4, 8), 1.0, |p| {
+///     fbm.sample2(p.x, p.z) * 16.0 + 24.0 - p.y
+/// });
+/// terrain.add_component(VoxelTerrain::new(volume, material));
+/// ```
+///
+/// Register it by `WorldBuilder::with_actor::<VoxelTerrain>()`.
+#[derive(Component)]
+pub struct VoxelTerrain {
+    pub volume: VoxelVolume,
+    /// Distances from the LOD center beyond which the chunks use LOD 1, 2 and so on
+    pub lod_distances: Vec<f32>,
+    /// Position of the LOD center, in the local space of the game object
+    pub lod_center: Option<Vector3<f32>>,
+    /// Chunks meshed per update at most
+    pub max_meshes_per_update: usize,
+    pub colliders: bool,
+
+    material: Rc<Material>,
+    chunks: BTreeMap<ChunkCoord, VoxelChunk>,
+    lods: BTreeMap<ChunkCoord, u32>,
+    pending: BTreeSet<ChunkCoord>,
+}
+
+impl VoxelTerrain {
+    pub fn new(volume: VoxelVolume, material: Material) -> VoxelTerrain {
+        VoxelTerrain {
+            volume,
+            lod_distances: Vec::new(),
+            lod_center: None,
+            max_meshes_per_update: 4,
+            colliders: true,
+            material: Rc::new(material),
+            chunks: BTreeMap::new(),
+            lods: BTreeMap::new(),
+            pending: BTreeSet::new(),
+        }
+    }
+
+    pub fn with_lod_distances(mut self, distances: &[f32]) -> VoxelTerrain {
+        self.lod_distances = distances.to_vec();
+        self
+    }
+
+    /// Level of detail of a chunk, after the last update
+    pub fn lod(&self, chunk: ChunkCoord) -> u32 {
+        self.lods.get(&chunk).cloned().unwrap_or(0)
+    }
+
+    /// The chunks waiting to be meshed
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Remove the game objects and colliders of the chunks, they are made again by the
+    /// next updates
+    pub fn clear(&mut self, world: &mut World) {
+        let chunks = ::std::mem::replace(&mut self.chunks, BTreeMap::new());
+        for (_, chunk) in chunks.into_iter() {
+            self.remove_chunk(chunk, world);
+        }
+        self.volume.mark_all_dirty();
+    }
+
+    fn remove_chunk(&self, chunk: VoxelChunk, world: &mut World) {
+        self.remove_collider(&chunk, world);
+        world.remove_game_object(&chunk.go);
+    }
+
+    fn update_lods(&mut self, center: Vector3<f32>) {
+        let half = Vector3::new(1.0, 1.0, 1.0)
+            * (self.volume.chunk_size as f32 * self.volume.voxel_size * 0.5);
+        let max_lod = self.volume.max_lod();
+
+        for c in self.volume.chunk_coords() {
+            let distance = (self.volume.chunk_origin(c) + half - center).magnitude();
+            let lod = self.lod_distances
+                .iter()
+                .filter(|d| distance > **d)
+                .count() as u32;
+            let lod = lod.min(max_lod);
+
+            if self.lods.insert(c, lod) != Some(lod) {
+                // The neighbors stitch to the chunk
+                self.pending.insert(c);
+                for offset in CHUNK_NEIGHBORS.iter() {
+                    if let Some(n) = self.volume.neighbor(c, *offset) {
+                        self.pending.insert(n);
+                    }
+                }
+            }
+        }
+    }
+
+    fn neighbor_lods(&self, chunk: ChunkCoord) -> [u32; 6] {
+        let mut lods = [0; 6];
+        for (i, offset) in CHUNK_NEIGHBORS.iter().enumerate() {
+            lods[i] = match self.volume.neighbor(chunk, *offset) {
+                Some(n) => self.lod(n),
+                None => 0,
+            };
+        }
+        lods
+    }
+
+    fn remesh(&mut self, c: ChunkCoord, go: &GameObject, world: &mut World) {
+        let data = mesh_chunk(&self.volume, c, self.lod(c), &self.neighbor_lods(c));
+
+        if data.indices.is_empty() {
+            if let Some(chunk) = self.chunks.remove(&c) {
+                self.remove_chunk(chunk, world);
+            }
+            return;
+        }
+
+        let collider = self.make_collider(&data, go, world);
+
+        if let Some(chunk) = self.chunks.get_mut(&c) {
+            chunk.buffer.update_mesh_data(data);
+            Self::replace_collider(chunk, collider, world);
+            return;
+        }
+
+        let chunk_go = world.new_game_object();
+        let buffer = MeshBuffer::new(data);
+        let mut mesh = Mesh::new();
+        mesh.add_surface(buffer.clone(), self.material.clone());
+        chunk_go.borrow_mut().add_component(mesh);
+        go.add_child(&chunk_go.borrow());
+
+        let mut chunk = VoxelChunk {
+            go: chunk_go,
+            buffer,
+            #[cfg(feature = "physics")]
+            collider: None,
+        };
+        Self::replace_collider(&mut chunk, collider, world);
+        self.chunks.insert(c, chunk);
+    }
+
+    #[cfg(not(feature = "physics"))]
+    fn make_collider(&self, _data: &MeshData, _go: &GameObject, _world: &mut World) -> Option<()> {
+        None
+    }
+
+    #[cfg(feature = "physics")]
+    fn make_collider(
+        &self,
+        data: &MeshData,
+        go: &GameObject,
+        world: &mut World,
+    ) -> Option<ColliderHandle> {
+        if !self.colliders {
+            return None;
+        }
+
+        let mesh = TriMesh::from_mesh_data(data, &go.transform.global());
+        Some(world.physics.add_collider(Collider::TriMesh(mesh)))
+    }
+
+    #[cfg(not(feature = "physics"))]
+    fn replace_collider(_chunk: &mut VoxelChunk, _collider: Option<()>, _world: &mut World) {}
+
+    #[cfg(feature = "physics")]
+    fn replace_collider(
+        chunk: &mut VoxelChunk,
+        collider: Option<ColliderHandle>,
+        world: &mut World,
+    ) {
+        if let Some(old) = chunk.collider.take() {
+            world.physics.remove_collider(old);
+        }
+        chunk.collider = collider;
+    }
+
+    #[cfg(not(feature = "physics"))]
+    fn remove_collider(&self, _chunk: &VoxelChunk, _world: &mut World) {}
+
+    #[cfg(feature = "physics")]
+    fn remove_collider(&self, chunk: &VoxelChunk, world: &mut World) {
+        if let Some(handle) = chunk.collider {
+            world.physics.remove_collider(handle);
+        }
+    }
+}
+
+impl Actor for VoxelTerrain {
+    fn update(&mut self, go: &mut GameObject, world: &mut World) {
+        let center = match self.lod_center {
+            Some(center) => Some(center),
+            None => world.current_camera().and_then(|cam| {
+                let eye = cam.borrow().eye();
+                go.transform
+                    .global()
+                    .inverse_transform()
+                    .map(|t| t.transform_point(Point3::from_vec(eye)).to_vec())
+            }),
+        };
+        self.update_lods(center.unwrap_or(Vector3::zero()));
+
+        for c in self.volume.take_dirty() {
+            self.pending.insert(c);
+        }
+
+        let batch: Vec<ChunkCoord> = self.pending
+            .iter()
+            .take(self.max_meshes_per_update.max(1))
+            .cloned()
+            .collect();
+        for c in batch.into_iter() {
+            self.pending.remove(&c);
+            self.remesh(c, go, world);
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use engine::physics::{WaterGuard, WaterHandle, WaterVolume};
+use engine::GameObject;
+use world::{Actor, World};
+
+#[cfg(feature = "audio")]
+use engine::physics::SplashKind;
+
+/// A water volume of `World::physics`, following the game object.
+///
+/// Bodies crossing the surface are reported by `PhysicsWorld::splashes` (e.g. to spawn
+/// particles), and play the splash sounds of the volume. Bind the waves to the water
+/// material with `world.physics.water(handle)` and `WaterVolume::bind`. The sounds need the
+/// `audio` feature. The volume is removed from the physics world with the actor.
+/// Register it by `WorldBuilder::with_actor::<Water>()`.
+#[derive(Component)]
+pub struct Water {
+    pub volume: WaterVolume,
+    pub enter_sound: Option<String>,
+    pub exit_sound: Option<String>,
+    /// Vertical speed of a body for a full volume splash
+    pub loud_speed: f32,
+    pub max_distance: f32,
+
+    guard: Option<WaterGuard>,
+}
+
+impl Water {
+    pub fn new(volume: WaterVolume) -> Water {
+        Water {
+            volume,
+            enter_sound: None,
+            exit_sound: None,
+            loud_speed: 10.0,
+            max_distance: 50.0,
+            guard: None,
+        }
+    }
+
+    pub fn with_sounds(mut self, enter: &str, exit: &str) -> Water {
+        self.enter_sound = Some(enter.to_owned());
+        self.exit_sound = Some(exit.to_owned());
+        self
+    }
+
+    /// The volume in `World::physics`, after the first update
+    pub fn handle(&self) -> Option<WaterHandle> {
+        self.guard.as_ref().map(|g| g.handle())
+    }
+
+    #[cfg(not(feature = "audio"))]
+    fn play_splashes(&self, _handle: WaterHandle, _world: &mut World) {}
+
+    #[cfg(feature = "audio")]
+    fn play_splashes(&self, handle: WaterHandle, world: &mut World) {
+        let listener = match world.audio_listener() {
+            Some(listener) => listener,
+            None => return,
+        };
+
+        let splashes: Vec<_> = world
+            .physics
+            .splashes()
+            .iter()
+            .filter(|s| s.water == handle)
+            .cloned()
+            .collect();
+
+        for splash in splashes.into_iter() {
+            let sound = match splash.kind {
+                SplashKind::Enter => self.enter_sound.as_ref(),
+                SplashKind::Exit => self.exit_sound.as_ref(),
+            };
+
+            if let Some(sound) = sound {
+                let (volume, balance) = listener.attenuate(splash.position, self.max_distance);
+                let volume = volume * (splash.speed / self.loud_speed).min(1.0);
+                if volume > 0.0 {
+                    let h = world.sound.load_sound(sound);
+                    world.sound.play_sound(h, None, false, 0, volume, balance);
+                }
+            }
+        }
+    }
+}
+
+impl Actor for Water {
+    fn update(&mut self, go: &mut GameObject, world: &mut World) {
+        self.volume.center = go.transform.global().disp;
+
+        let handle = match self.guard {
+            Some(ref guard) => guard.handle(),
+            None => {
+                let guard = world.physics.add_owned_water(self.volume.clone());
+                let handle = guard.handle();
+                self.guard = Some(guard);
+                handle
+            }
+        };
+
+        if let Some(water) = world.physics.water_mut(handle) {
+            *water = self.volume.clone();
+        }
+
+        self.play_splashes(handle, world);
+    }
+}
@@ -0,0 +1,118 @@
+//! Accessibility features
+//!
+//! Color blindness filters are applied as the final effect of the main camera, after the
+//! post effects. UI scale and high contrast theme are applied to imgui with the `ui` feature.
+
+use engine::engine::Engine;
+#[cfg(feature = "ui")]
+use engine::imgui::{self, Theme};
+use engine::{AssetSystem, Material};
+
+use std::rc::Rc;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ColorblindMode {
+    None,
+    /// Red blindness
+    Protanopia,
+    /// Green blindness
+    Deuteranopia,
+    /// Blue blindness
+    Tritanopia,
+    /// No color vision
+    Achromatopsia,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ColorblindFilter {
+    /// Show how the game looks with the deficiency, for testing
+    Simulate,
+    /// Shift colors to make them distinguishable with the deficiency
+    Compensate,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AccessibilitySettings {
+    pub colorblind_mode: ColorblindMode,
+    pub colorblind_filter: ColorblindFilter,
+    /// From 0 (no effect) to 1
+    pub colorblind_strength: f32,
+    /// UI size multiplier
+    pub ui_scale: f32,
+    pub high_contrast: bool,
+}
+
+impl Default for AccessibilitySettings {
+    fn default() -> AccessibilitySettings {
+        AccessibilitySettings {
+            colorblind_mode: ColorblindMode::None,
+            colorblind_filter: ColorblindFilter::Compensate,
+            colorblind_strength: 1.0,
+            ui_scale: 1.0,
+            high_contrast: false,
+        }
+    }
+}
+
+/// Applies `AccessibilitySettings` to the engine
+#[derive(Default)]
+pub struct Accessibility {
+    material: Option<Rc<Material>>,
+    applied: Option<AccessibilitySettings>,
+}
+
+impl Accessibility {
+    /// Apply the settings if they changed since the last call
+    pub fn apply<A: AssetSystem>(&mut self, settings: &AccessibilitySettings, engine: &mut Engine<A>) {
+        if self.applied.as_ref() == Some(settings) {
+            return;
+        }
+        self.applied = Some(settings.clone());
+
+        #[cfg(feature = "ui")]
+        {
+            imgui::set_ui_scale(settings.ui_scale.max(0.1));
+            imgui::set_theme(if settings.high_contrast {
+                Theme::HighContrast
+            } else {
+                Theme::Default
+            });
+        }
+
+        if settings.colorblind_mode == ColorblindMode::None {
+            let ours = match (engine.final_effect.as_ref(), self.material.take()) {
+                (Some(effect), Some(material)) => Rc::ptr_eq(effect, &material),
+                _ => false,
+            };
+            if ours {
+                engine.final_effect = None;
+            }
+            return;
+        }
+
+        let material = self.material.get_or_insert_with(|| {
+            Rc::new(Material::new(
+                engine.asset_system.new_program("unrust/colorblind"),
+            ))
+        });
+
+        let mode = match settings.colorblind_mode {
+            ColorblindMode::None => 0,
+            ColorblindMode::Protanopia => 1,
+            ColorblindMode::Deuteranopia => 2,
+            ColorblindMode::Tritanopia => 3,
+            ColorblindMode::Achromatopsia => 4,
+        };
+
+        material.set("uMode", mode);
+        material.set(
+            "uCompensate",
+            (settings.colorblind_filter == ColorblindFilter::Compensate) as i32,
+        );
+        material.set("uStrength", settings.colorblind_strength);
+
+        // After the post effects added later too, it affects what the player finally sees
+        engine.final_effect = Some(material.clone());
+    }
+}
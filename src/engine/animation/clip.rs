@@ -0,0 +1,174 @@
+use math::*;
+use std::cmp::Ordering;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Keyframe<T> {
+    pub time: f32,
+    pub value: T,
+}
+
+/// Keyframes sorted by time, linearly interpolated
+#[derive(Debug, Clone, PartialEq)]
+pub struct Curve<T> {
+    pub keys: Vec<Keyframe<T>>,
+}
+
+impl<T> Default for Curve<T> {
+    fn default() -> Curve<T> {
+        Curve { keys: Vec::new() }
+    }
+}
+
+/// Interpolation of the values of a curve
+pub trait Interpolate: Copy {
+    fn interpolate(&self, other: &Self, t: f32) -> Self;
+}
+
+impl Interpolate for f32 {
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Interpolate for Vector3<f32> {
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        self.lerp(*other, t)
+    }
+}
+
+impl Interpolate for Quaternion<f32> {
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        // Take the shortest path
+        let other = if self.dot(*other) < 0.0 {
+            -*other
+        } else {
+            *other
+        };
+        self.nlerp(other, t)
+    }
+}
+
+impl<T: Interpolate> Curve<T> {
+    pub fn new(keys: Vec<Keyframe<T>>) -> Curve<T> {
+        Curve { keys }
+    }
+
+    /// Value at `time`, clamped to the first and last keys. `None` for an empty curve.
+    pub fn sample(&self, time: f32) -> Option<T> {
+        let first = self.keys.first()?;
+        let last = self.keys.last()?;
+
+        if time.is_nan() || time <= first.time {
+            return Some(first.value);
+        }
+        if time >= last.time || self.keys.len() < 2 {
+            return Some(last.value);
+        }
+
+        // Index of the first key after time, keys of NaN times are ordered before
+        let i = match self.keys
+            .binary_search_by(|k| k.time.partial_cmp(&time).unwrap_or(Ordering::Less))
+        {
+            Ok(i) => return Some(self.keys[i].value),
+            Err(i) => i.max(1).min(self.keys.len() - 1),
+        };
+
+        let (a, b) = (&self.keys[i - 1], &self.keys[i]);
+        let t = (time - a.time) / (b.time - a.time);
+        Some(a.value.interpolate(&b.value, t))
+    }
+
+    /// Keys sampled every `1 / rate` seconds from 0 to `duration`, e.g. to bake curves of
+    /// different rates before the compression
+    pub fn resample(&self, rate: f32, duration: f32) -> Curve<T> {
+        if self.keys.is_empty() || rate <= 0.0 {
+            return self.clone();
+        }
+
+        let count = (duration * rate).ceil().max(1.0) as usize;
+        let keys = (0..count + 1)
+            .filter_map(|i| {
+                let time = (i as f32 / rate).min(duration);
+                self.sample(time).map(|value| Keyframe { time, value })
+            })
+            .collect();
+
+        Curve { keys }
+    }
+}
+
+/// Curves of a bone, an empty curve keeps the value of the bind pose
+#[derive(Debug, Clone, Default)]
+pub struct Track {
+    /// Name of the bone, see `engine::Name`
+    pub bone: String,
+    pub translation: Curve<Vector3<f32>>,
+    pub rotation: Curve<Quaternion<f32>>,
+    pub scale: Curve<Vector3<f32>>,
+}
+
+impl Track {
+    pub fn new(bone: &str) -> Track {
+        Track {
+            bone: bone.to_owned(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Local transform of a bone, `None` for a value not animated
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BonePose {
+    pub translation: Option<Vector3<f32>>,
+    pub rotation: Option<Quaternion<f32>>,
+    pub scale: Option<Vector3<f32>>,
+}
+
+impl Default for BonePose {
+    fn default() -> BonePose {
+        BonePose {
+            translation: None,
+            rotation: None,
+            scale: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AnimationClip {
+    pub name: String,
+    /// Length in seconds
+    pub duration: f32,
+    pub tracks: Vec<Track>,
+}
+
+impl AnimationClip {
+    /// Pose of each track at `time`
+    pub fn sample(&self, time: f32) -> Vec<BonePose> {
+        self.tracks
+            .iter()
+            .map(|t| BonePose {
+                translation: t.translation.sample(time),
+                rotation: t.rotation.sample(time),
+                scale: t.scale.sample(time),
+            })
+            .collect()
+    }
+
+    /// Resample all curves at `rate` keys per second
+    pub fn resample(&self, rate: f32) -> AnimationClip {
+        AnimationClip {
+            name: self.name.clone(),
+            duration: self.duration,
+            tracks: self.tracks
+                .iter()
+                .map(|t| Track {
+                    bone: t.bone.clone(),
+                    translation: t.translation.resample(rate, self.duration),
+                    rotation: t.rotation.resample(rate, self.duration),
+                    scale: t.scale.resample(rate, self.duration),
+                })
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,359 @@
+use super::clip::{AnimationClip, Curve, Interpolate, Keyframe, Track};
+use math::*;
+
+use std::f32;
+use std::mem;
+
+#[derive(Debug, Clone, Copy)]
+pub struct CompressionSettings {
+    /// Maximum distance between the compressed and the source translations
+    pub translation_tolerance: f32,
+    /// Maximum angle in radians between the compressed and the source rotations
+    pub rotation_tolerance: f32,
+    pub scale_tolerance: f32,
+    /// Resample the curves at this rate (keys per second) before the keyframe reduction
+    pub sample_rate: Option<f32>,
+}
+
+impl Default for CompressionSettings {
+    fn default() -> CompressionSettings {
+        CompressionSettings {
+            translation_tolerance: 0.001,
+            rotation_tolerance: 0.001,
+            scale_tolerance: 0.001,
+            sample_rate: None,
+        }
+    }
+}
+
+fn vec3_error(a: &Vector3<f32>, b: &Vector3<f32>) -> f32 {
+    (a - b).magnitude()
+}
+
+fn quat_error(a: &Quaternion<f32>, b: &Quaternion<f32>) -> f32 {
+    2.0 * a.normalize().dot(b.normalize()).abs().min(1.0).acos()
+}
+
+/// Remove the keys interpolated by the kept keys within `tolerance`
+fn reduce<T, E>(curve: &Curve<T>, tolerance: f32, error: E) -> Vec<Keyframe<T>>
+where
+    T: Interpolate,
+    E: Fn(&T, &T) -> f32,
+{
+    let keys = &curve.keys;
+    if keys.len() <= 2 {
+        // A constant curve only needs one key
+        if keys.len() == 2 && error(&keys[0].value, &keys[1].value) <= tolerance {
+            return vec![keys[0]];
+        }
+        return keys.clone();
+    }
+
+    if keys.iter().all(|k| error(&k.value, &keys[0].value) <= tolerance) {
+        return vec![keys[0]];
+    }
+
+    let mut kept = vec![keys[0]];
+    let mut start = 0;
+
+    // Extend the segment from the last kept key as long as it interpolates the keys between
+    let mut end = 2;
+    while end < keys.len() {
+        let (a, b) = (&keys[start], &keys[end]);
+        let fits = keys[start + 1..end].iter().all(|k| {
+            let t = (k.time - a.time) / (b.time - a.time);
+            error(&a.value.interpolate(&b.value, t), &k.value) <= tolerance
+        });
+
+        if !fits {
+            start = end - 1;
+            kept.push(keys[start]);
+        }
+        end += 1;
+    }
+
+    kept.push(keys[keys.len() - 1]);
+    kept
+}
+
+fn quantize_unit(v: f32) -> u16 {
+    (v.max(0.0).min(1.0) * 65535.0 + 0.5) as u16
+}
+
+fn dequantize_unit(v: u16) -> f32 {
+    v as f32 / 65535.0
+}
+
+const SMALLEST_THREE_RANGE: f32 = f32::consts::FRAC_1_SQRT_2;
+
+/// A unit quaternion in 7 bytes : the index of its largest component, and the other three
+/// components quantized to 16 bits. The largest component is recomputed from the unit length.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct QuantizedQuat {
+    pub largest: u8,
+    pub values: [u16; 3],
+}
+
+impl QuantizedQuat {
+    pub fn new(q: Quaternion<f32>) -> QuantizedQuat {
+        let q = q.normalize();
+        let mut c = [q.v.x, q.v.y, q.v.z, q.s];
+
+        let mut largest = 0;
+        for i in 1..4 {
+            if c[i].abs() > c[largest].abs() {
+                largest = i;
+            }
+        }
+
+        // q and -q are the same rotation, keep the largest component positive
+        if c[largest] < 0.0 {
+            for v in c.iter_mut() {
+                *v = -*v;
+            }
+        }
+
+        let mut values = [0; 3];
+        let mut j = 0;
+        for i in 0..4 {
+            if i != largest {
+                let v = (c[i] / SMALLEST_THREE_RANGE + 1.0) * 0.5;
+                values[j] = quantize_unit(v);
+                j += 1;
+            }
+        }
+
+        QuantizedQuat {
+            largest: largest as u8,
+            values,
+        }
+    }
+
+    pub fn get(&self) -> Quaternion<f32> {
+        let largest = self.largest as usize;
+        let mut c = [0.0; 4];
+        let mut sum = 0.0;
+        let mut j = 0;
+
+        for i in 0..4 {
+            if i != largest {
+                c[i] = (dequantize_unit(self.values[j]) * 2.0 - 1.0) * SMALLEST_THREE_RANGE;
+                sum += c[i] * c[i];
+                j += 1;
+            }
+        }
+        c[largest] = (1.0 - sum).max(0.0).sqrt();
+
+        Quaternion::new(c[3], c[0], c[1], c[2])
+    }
+}
+
+/// Keys of a 3d vector curve, quantized in the bounds of the curve
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QuantizedVec3Curve {
+    pub times: Vec<u16>,
+    pub min: [f32; 3],
+    pub extent: [f32; 3],
+    pub keys: Vec<[u16; 3]>,
+}
+
+impl QuantizedVec3Curve {
+    fn new(keys: &[Keyframe<Vector3<f32>>], duration: f32) -> QuantizedVec3Curve {
+        let mut min = [f32::MAX; 3];
+        let mut max = [f32::MIN; 3];
+        for k in keys.iter() {
+            for c in 0..3 {
+                min[c] = min[c].min(k.value[c]);
+                max[c] = max[c].max(k.value[c]);
+            }
+        }
+
+        if keys.is_empty() {
+            min = [0.0; 3];
+            max = [0.0; 3];
+        }
+
+        let extent = [max[0] - min[0], max[1] - min[1], max[2] - min[2]];
+        let quantize = |v: f32, c: usize| {
+            if extent[c] > 0.0 {
+                quantize_unit((v - min[c]) / extent[c])
+            } else {
+                0
+            }
+        };
+
+        QuantizedVec3Curve {
+            times: quantize_times(keys.iter().map(|k| k.time), duration),
+            min,
+            extent,
+            keys: keys.iter()
+                .map(|k| {
+                    [
+                        quantize(k.value.x, 0),
+                        quantize(k.value.y, 1),
+                        quantize(k.value.z, 2),
+                    ]
+                })
+                .collect(),
+        }
+    }
+
+    pub fn key(&self, i: usize) -> Vector3<f32> {
+        let k = &self.keys[i];
+        Vector3::new(
+            self.min[0] + dequantize_unit(k[0]) * self.extent[0],
+            self.min[1] + dequantize_unit(k[1]) * self.extent[1],
+            self.min[2] + dequantize_unit(k[2]) * self.extent[2],
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QuantizedQuatCurve {
+    pub times: Vec<u16>,
+    pub keys: Vec<QuantizedQuat>,
+}
+
+impl QuantizedQuatCurve {
+    fn new(keys: &[Keyframe<Quaternion<f32>>], duration: f32) -> QuantizedQuatCurve {
+        QuantizedQuatCurve {
+            times: quantize_times(keys.iter().map(|k| k.time), duration),
+            keys: keys.iter().map(|k| QuantizedQuat::new(k.value)).collect(),
+        }
+    }
+
+    pub fn key(&self, i: usize) -> Quaternion<f32> {
+        self.keys[i].get()
+    }
+}
+
+fn quantize_times<I: Iterator<Item = f32>>(times: I, duration: f32) -> Vec<u16> {
+    times
+        .map(|t| {
+            if duration > 0.0 {
+                quantize_unit(t / duration)
+            } else {
+                0
+            }
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CompressedTrack {
+    pub bone: String,
+    pub translation: QuantizedVec3Curve,
+    pub rotation: QuantizedQuatCurve,
+    pub scale: QuantizedVec3Curve,
+}
+
+/// An `AnimationClip` with reduced and quantized keys, sampled by a `ClipSampler`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CompressedClip {
+    pub name: String,
+    pub duration: f32,
+    pub tracks: Vec<CompressedTrack>,
+}
+
+impl CompressedClip {
+    pub fn compress(clip: &AnimationClip, settings: &CompressionSettings) -> CompressedClip {
+        let resampled;
+        let clip = match settings.sample_rate {
+            Some(rate) => {
+                resampled = clip.resample(rate);
+                &resampled
+            }
+            None => clip,
+        };
+
+        let duration = clip.duration;
+        let tracks = clip.tracks
+            .iter()
+            .map(|t| CompressedTrack {
+                bone: t.bone.clone(),
+                translation: QuantizedVec3Curve::new(
+                    &reduce(&t.translation, settings.translation_tolerance, vec3_error),
+                    duration,
+                ),
+                rotation: QuantizedQuatCurve::new(
+                    &reduce(&t.rotation, settings.rotation_tolerance, quat_error),
+                    duration,
+                ),
+                scale: QuantizedVec3Curve::new(
+                    &reduce(&t.scale, settings.scale_tolerance, vec3_error),
+                    duration,
+                ),
+            })
+            .collect();
+
+        CompressedClip {
+            name: clip.name.clone(),
+            duration,
+            tracks,
+        }
+    }
+
+    /// Time of the key `i` of `times`
+    pub fn key_time(&self, times: &[u16], i: usize) -> f32 {
+        dequantize_unit(times[i]) * self.duration
+    }
+
+    /// Number of keys, over all curves
+    pub fn key_count(&self) -> usize {
+        self.tracks
+            .iter()
+            .map(|t| t.translation.keys.len() + t.rotation.keys.len() + t.scale.keys.len())
+            .sum()
+    }
+
+    /// Approximate memory of the keys in bytes, for diagnostics
+    pub fn key_bytes(&self) -> usize {
+        self.tracks
+            .iter()
+            .map(|t| {
+                (t.translation.keys.len() + t.scale.keys.len())
+                    * (mem::size_of::<[u16; 3]>() + mem::size_of::<u16>())
+                    + t.rotation.keys.len()
+                        * (mem::size_of::<QuantizedQuat>() + mem::size_of::<u16>())
+            })
+            .sum()
+    }
+
+    /// The clip with the compressed keys, e.g. to edit it
+    pub fn decompress(&self) -> AnimationClip {
+        AnimationClip {
+            name: self.name.clone(),
+            duration: self.duration,
+            tracks: self.tracks
+                .iter()
+                .map(|t| Track {
+                    bone: t.bone.clone(),
+                    translation: Curve::new(
+                        (0..t.translation.keys.len())
+                            .map(|i| Keyframe {
+                                time: self.key_time(&t.translation.times, i),
+                                value: t.translation.key(i),
+                            })
+                            .collect(),
+                    ),
+                    rotation: Curve::new(
+                        (0..t.rotation.keys.len())
+                            .map(|i| Keyframe {
+                                time: self.key_time(&t.rotation.times, i),
+                                value: t.rotation.key(i),
+                            })
+                            .collect(),
+                    ),
+                    scale: Curve::new(
+                        (0..t.scale.keys.len())
+                            .map(|i| Keyframe {
+                                time: self.key_time(&t.scale.times, i),
+                                value: t.scale.key(i),
+                            })
+                            .collect(),
+                    ),
+                })
+                .collect(),
+        }
+    }
+}
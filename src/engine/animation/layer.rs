@@ -0,0 +1,96 @@
+use super::clip::Interpolate;
+use super::skeleton::{LocalPose, Skeleton};
+use math::*;
+
+/// How a layer is combined with the layers below it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayerBlend {
+    /// Replaces the pose of the bones, by the weight of the layer
+    Override,
+    /// Adds the difference from the first frame of its clip, e.g. breathing or recoil
+    Additive,
+}
+
+/// Weights of a layer per bone. A branch is a bone with all its descendants, later branches
+/// override the earlier ones :
+///
+/// ```ignore
+/// // Upper body, the arms are less weighted
+/// BoneMask::new().with_branch("spine", 1.0).with_branch("arm.L", 0.5)
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct BoneMask {
+    pub branches: Vec<(String, f32)>,
+}
+
+impl BoneMask {
+    pub fn new() -> BoneMask {
+        Default::default()
+    }
+
+    pub fn with_branch(mut self, bone: &str, weight: f32) -> BoneMask {
+        self.branches.push((bone.to_owned(), weight));
+        self
+    }
+
+    /// Weight of each bone of `skeleton`, 0 for the bones out of all branches
+    pub fn weights(&self, skeleton: &Skeleton) -> Vec<f32> {
+        let mut weights = vec![0.0; skeleton.bones.len()];
+
+        for &(ref bone, weight) in self.branches.iter() {
+            let root = match skeleton.find(bone) {
+                Some(root) => root,
+                None => continue,
+            };
+
+            for i in 0..weights.len() {
+                if skeleton.is_descendant(i, root) {
+                    weights[i] = weight;
+                }
+            }
+        }
+
+        weights
+    }
+}
+
+/// Blend `layer` over `pose` with a weight per bone
+pub fn blend_override(pose: &mut [LocalPose], layer: &[LocalPose], weights: &[f32]) {
+    for ((p, l), &w) in pose.iter_mut().zip(layer.iter()).zip(weights.iter()) {
+        if w > 0.0 {
+            *p = p.blend(l, w.min(1.0));
+        }
+    }
+}
+
+/// Add the difference of `layer` from `reference` to `pose`, with a weight per bone
+pub fn blend_additive(
+    pose: &mut [LocalPose],
+    layer: &[LocalPose],
+    reference: &[LocalPose],
+    weights: &[f32],
+) {
+    let one = Vector3::new(1.0, 1.0, 1.0);
+
+    for (((p, l), r), &w) in pose.iter_mut()
+        .zip(layer.iter())
+        .zip(reference.iter())
+        .zip(weights.iter())
+    {
+        if w <= 0.0 {
+            continue;
+        }
+
+        let rotation = Quaternion::one().interpolate(&(r.rotation.invert() * l.rotation), w);
+        let scale = Vector3::new(
+            if r.scale.x != 0.0 { l.scale.x / r.scale.x } else { 1.0 },
+            if r.scale.y != 0.0 { l.scale.y / r.scale.y } else { 1.0 },
+            if r.scale.z != 0.0 { l.scale.z / r.scale.z } else { 1.0 },
+        );
+        let scale = one.interpolate(&scale, w);
+
+        p.translation += (l.translation - r.translation) * w;
+        p.rotation = (p.rotation * rotation).normalize();
+        p.scale = Vector3::new(p.scale.x * scale.x, p.scale.y * scale.y, p.scale.z * scale.z);
+    }
+}
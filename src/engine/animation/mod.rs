@@ -0,0 +1,41 @@
+//! Skeletal animation
+//!
+//! An `AnimationClip` animates the bones of a `Skeleton`, the descendant game objects of a
+//! character found by their `Name`, with linear keyframe curves. Clips are compressed when
+//! imported :
+//!
+//! ```ignore
+//! let compressed = CompressedClip::compress(&clip, &CompressionSettings::default());
+//! let skeleton = Skeleton::from_root(&character);
+//! let binding = skeleton.bind(&compressed);
+//!
+//! // Each frame
+//! skeleton.apply(&binding, sampler.sample(&compressed, time));
+//! ```
+//!
+//! Clips of another skeleton are converted by a `Retargeter`. The `actors::Animator` plays
+//! clips on the skeleton of its game object, in layers weighted per bone by a `BoneMask`, and
+//! `actors::Ragdoll` switches it to a ragdoll built by a `RagdollBuilder`.
+//!
+//! The compression removes the keyframes which are interpolated by their neighbors within
+//! a tolerance, and quantizes the keys and their times to 16 bits. Rotations are stored
+//! with the smallest three components.
+
+mod clip;
+mod compression;
+mod layer;
+#[cfg(feature = "physics")]
+mod ragdoll;
+mod retarget;
+mod sampler;
+mod skeleton;
+
+pub use self::clip::{AnimationClip, BonePose, Curve, Interpolate, Keyframe, Track};
+pub use self::compression::{CompressedClip, CompressedTrack, CompressionSettings,
+                            QuantizedQuat, QuantizedQuatCurve, QuantizedVec3Curve};
+pub use self::layer::{blend_additive, blend_override, BoneMask, LayerBlend};
+#[cfg(feature = "physics")]
+pub use self::ragdoll::{blend_pose, RagdollBodies, RagdollBuilder};
+pub use self::retarget::{BoneMap, Retargeter};
+pub use self::sampler::ClipSampler;
+pub use self::skeleton::{Bone, LocalPose, Skeleton};
@@ -0,0 +1,331 @@
+use super::clip::Interpolate;
+use super::skeleton::Skeleton;
+use engine::physics::{BodyHandle, Joint, JointHandle, PhysicsWorld, RigidBody};
+use math::*;
+
+use std::collections::HashMap;
+
+const MAX_SEGMENT_SPHERES: usize = 16;
+
+/// Generates the bodies and joints of a ragdoll from a skeleton.
+///
+/// Each bone is a sphere body at its origin, linked to the body of its parent bone by a
+/// distance joint. The segment between them is filled by spheres held on it by distance
+/// joints to both ends, so it collides as a capsule of `radius`. The bending at each bone is
+/// limited by a cone joint.
+pub struct RagdollBuilder {
+    /// Radius of the bodies, and of the capsules between them
+    pub radius: f32,
+    /// Spacing of the spheres filling the segments, relative to `radius`. Closer spheres
+    /// make a smoother capsule, 0 leaves the segments empty.
+    pub segment_spacing: f32,
+    /// Mass of the whole ragdoll, shared by the bodies
+    pub mass: f32,
+    /// Bending limit of the joints
+    pub max_angle: Rad<f32>,
+    /// Bending limits by bone name, e.g. a stiffer spine
+    pub limits: HashMap<String, Rad<f32>>,
+    /// Bones having a body, `None` for all bones. The others follow their parents.
+    pub bones: Option<Vec<String>>,
+    pub friction: f32,
+    pub damping: f32,
+}
+
+impl Default for RagdollBuilder {
+    fn default() -> RagdollBuilder {
+        RagdollBuilder {
+            radius: 0.08,
+            segment_spacing: 1.0,
+            mass: 70.0,
+            max_angle: Deg(60.0).into(),
+            limits: HashMap::new(),
+            bones: None,
+            friction: 0.8,
+            damping: 0.5,
+        }
+    }
+}
+
+/// The bodies and joints of a ragdoll in a `PhysicsWorld`
+pub struct RagdollBodies {
+    /// Body of each bone of the skeleton
+    pub bodies: Vec<Option<BodyHandle>>,
+    /// Spheres filling the segments between the bodies
+    pub segments: Vec<BodyHandle>,
+    pub joints: Vec<JointHandle>,
+    /// Nearest ancestor with a body, of each bone
+    parents: Vec<Option<usize>>,
+    /// First child with a body, of each bone
+    children: Vec<Option<usize>>,
+    /// Global rotation of the skeleton root when built
+    root_rotation: Quaternion<f32>,
+}
+
+impl RagdollBuilder {
+    pub fn new() -> RagdollBuilder {
+        Default::default()
+    }
+
+    fn included(&self, name: &str) -> bool {
+        match self.bones {
+            Some(ref bones) => bones.iter().any(|b| b == name),
+            None => true,
+        }
+    }
+
+    /// Number of spheres filling a segment of `length`
+    pub fn segment_spheres(&self, length: f32) -> usize {
+        let spacing = self.radius * self.segment_spacing;
+        if spacing <= 0.0 || length.is_nan() || length <= spacing {
+            return 0;
+        }
+
+        // Bounded for the long segments, e.g. a misplaced bone
+        ((length / spacing).ceil() as usize - 1).min(MAX_SEGMENT_SPHERES)
+    }
+
+    /// Create the bodies at the current pose of the skeleton, whose root has the global
+    /// rotation `root_rotation`. Bodies are in the collision group `group`.
+    pub fn build(
+        &self,
+        skeleton: &Skeleton,
+        root_rotation: Quaternion<f32>,
+        physics: &mut PhysicsWorld,
+        group: u32,
+    ) -> RagdollBodies {
+        let n = skeleton.bones.len();
+        let included: Vec<bool> = skeleton
+            .bones
+            .iter()
+            .map(|b| self.included(&b.name))
+            .collect();
+        let positions: Vec<Vector3<f32>> = skeleton
+            .bones
+            .iter()
+            .map(|b| b.object.borrow().transform.global().disp)
+            .collect();
+
+        let mut parents = vec![None; n];
+        let mut children = vec![None; n];
+        for i in 0..n {
+            if !included[i] {
+                continue;
+            }
+
+            let mut p = skeleton.bones[i].parent;
+            while let Some(j) = p {
+                if included[j] {
+                    break;
+                }
+                p = skeleton.bones[j].parent;
+            }
+
+            parents[i] = p;
+            if let Some(p) = p {
+                if children[p].is_none() {
+                    children[p] = Some(i);
+                }
+            }
+        }
+
+        // The mass is shared by all the spheres, the ones filling the segments included
+        let count = (0..n)
+            .filter(|i| included[*i])
+            .map(|i| match parents[i] {
+                Some(p) => 1 + self.segment_spheres((positions[i] - positions[p]).magnitude()),
+                None => 1,
+            })
+            .sum::<usize>()
+            .max(1);
+        let new_body = |position: Vector3<f32>| {
+            let mut body = RigidBody::new(position, self.radius, self.mass / count as f32)
+                .with_friction(self.friction)
+                .with_damping(self.damping);
+            body.collision_group = group;
+            body
+        };
+
+        let mut bodies = vec![None; n];
+        for i in 0..n {
+            if included[i] {
+                bodies[i] = Some(physics.add_body(new_body(positions[i])));
+            }
+        }
+
+        let mut segments = Vec::new();
+
+        let mut joints = Vec::new();
+        for i in 0..n {
+            let (body, parent) = match (bodies[i], parents[i]) {
+                (Some(body), Some(p)) => (body, p),
+                _ => continue,
+            };
+            let parent_body = bodies[parent].unwrap();
+
+            let length = {
+                let a = physics.body(body).map(|b| b.position);
+                let b = physics.body(parent_body).map(|b| b.position);
+                match (a, b) {
+                    (Some(a), Some(b)) => (a - b).magnitude(),
+                    _ => continue,
+                }
+            };
+
+            joints.push(physics.add_joint(Joint::Distance {
+                a: parent_body,
+                b: body,
+                length,
+                stiffness: 1.0,
+            }));
+
+            // Spheres along the segment, each kept on it by its distances to both ends
+            let k = self.segment_spheres(length);
+            for s in 0..k {
+                let t = (s + 1) as f32 / (k + 1) as f32;
+                let position = positions[parent] + (positions[i] - positions[parent]) * t;
+                let filler = physics.add_body(new_body(position));
+                segments.push(filler);
+
+                joints.push(physics.add_joint(Joint::Distance {
+                    a: parent_body,
+                    b: filler,
+                    length: length * t,
+                    stiffness: 1.0,
+                }));
+                joints.push(physics.add_joint(Joint::Distance {
+                    a: filler,
+                    b: body,
+                    length: length * (1.0 - t),
+                    stiffness: 1.0,
+                }));
+            }
+
+            if let Some(grand_parent) = parents[parent] {
+                let max_angle = self.limits
+                    .get(&skeleton.bones[parent].name)
+                    .cloned()
+                    .unwrap_or(self.max_angle);
+
+                joints.push(physics.add_joint(Joint::Cone {
+                    a: bodies[grand_parent].unwrap(),
+                    center: parent_body,
+                    b: body,
+                    max_angle,
+                }));
+            }
+        }
+
+        RagdollBodies {
+            bodies,
+            segments,
+            joints,
+            parents,
+            children,
+            root_rotation,
+        }
+    }
+}
+
+impl RagdollBodies {
+    /// Remove the bodies and joints from the physics world
+    pub fn remove(&self, physics: &mut PhysicsWorld) {
+        for joint in self.joints.iter() {
+            physics.remove_joint(*joint);
+        }
+        for body in self.bodies.iter().filter_map(|b| *b) {
+            physics.remove_body(body);
+        }
+        for body in self.segments.iter() {
+            physics.remove_body(*body);
+        }
+    }
+
+    pub fn set_velocity(&self, physics: &mut PhysicsWorld, velocity: Vector3<f32>) {
+        let segments = self.segments.iter().cloned();
+        for body in self.bodies.iter().filter_map(|b| *b).chain(segments) {
+            if let Some(body) = physics.body_mut(body) {
+                body.velocity = velocity;
+            }
+        }
+    }
+
+    /// Global transforms of the bones with a body, following the bodies.
+    /// A bone is oriented to its first child, like in the rest pose.
+    pub fn global_pose(
+        &self,
+        skeleton: &Skeleton,
+        physics: &PhysicsWorld,
+    ) -> Vec<Option<Isometry3<f32>>> {
+        let n = skeleton.bones.len();
+        let position = |i: usize| -> Option<Vector3<f32>> {
+            self.bodies[i].and_then(|b| physics.render_position(b))
+        };
+
+        // Rotation from the rest pose of each bone
+        let mut deltas: Vec<Option<Quaternion<f32>>> = vec![None; n];
+        let mut pose = vec![None; n];
+
+        for i in 0..n {
+            let p = match position(i) {
+                Some(p) => p,
+                None => continue,
+            };
+
+            let bone = &skeleton.bones[i];
+            let rest_rot = self.root_rotation * bone.rest_model.rot;
+
+            let delta = match self.children[i] {
+                Some(c) => {
+                    let rest_dir = self.root_rotation
+                        * (skeleton.bones[c].rest_model.disp - bone.rest_model.disp);
+                    match position(c) {
+                        Some(pc) if rest_dir.magnitude2() > 1e-10 => {
+                            let dir = pc - p;
+                            if dir.magnitude2() > 1e-10 {
+                                Quaternion::from_arc(rest_dir.normalize(), dir.normalize(), None)
+                            } else {
+                                Quaternion::one()
+                            }
+                        }
+                        _ => Quaternion::one(),
+                    }
+                }
+                // Leaves turn with their parent
+                None => self.parents[i]
+                    .and_then(|p| deltas[p])
+                    .unwrap_or(Quaternion::one()),
+            };
+
+            deltas[i] = Some(delta);
+            pose[i] = Some(Isometry3 {
+                disp: p,
+                rot: (delta * rest_rot).normalize(),
+                scale: 1.0,
+            });
+        }
+
+        pose
+    }
+}
+
+/// Move the bones with a transform in `pose` toward it, by `weight` from their current global
+/// transforms. 1 sets the pose, 0 keeps the current one.
+pub fn blend_pose(skeleton: &Skeleton, pose: &[Option<Isometry3<f32>>], weight: f32) {
+    let current: Vec<Isometry3<f32>> = skeleton
+        .bones
+        .iter()
+        .map(|b| b.object.borrow().transform.global())
+        .collect();
+
+    // Parents first, as setting a global transform moves the children
+    for (i, bone) in skeleton.bones.iter().enumerate() {
+        if let Some(target) = pose.get(i).and_then(|p| *p) {
+            let c = current[i];
+            bone.object.borrow_mut().transform.set_global(Isometry3 {
+                disp: c.disp.lerp(target.disp, weight),
+                rot: c.rot.interpolate(&target.rot, weight),
+                scale: 1.0,
+            });
+        }
+    }
+}
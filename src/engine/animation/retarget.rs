@@ -0,0 +1,158 @@
+use super::clip::{AnimationClip, Curve, Keyframe, Track};
+use super::skeleton::Skeleton;
+use engine::asset::loader::{self, Loadable, Loader};
+use engine::asset::{AssetResult, File};
+use math::*;
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+
+/// Bone name mapping between two skeletons, a json asset :
+///
+/// ```json
+/// { "root": "mixamorig:Hips",
+///   "bones": { "mixamorig:Hips": "hips", "mixamorig:Spine": "spine", "mixamorig:Head": "head" } }
+/// ```
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct BoneMap {
+    /// Source bone carrying the motion of the character, its translation is scaled to the
+    /// height of the target
+    #[serde(default)]
+    pub root: Option<String>,
+    /// Source bone name to target bone name
+    pub bones: BTreeMap<String, String>,
+}
+
+pub struct BoneMapLoader {}
+
+impl Loader<BoneMap> for BoneMapLoader {
+    fn load<A>(_asys: A, mut file: Box<File>) -> AssetResult<BoneMap> {
+        loader::read_json(&mut file)
+    }
+}
+
+impl Loadable for BoneMap {
+    type Loader = BoneMapLoader;
+}
+
+#[derive(Debug, Clone, Copy)]
+struct RestBone {
+    translation: Vector3<f32>,
+    rotation: Quaternion<f32>,
+    height: f32,
+}
+
+fn rest_bones(skeleton: &Skeleton) -> HashMap<String, RestBone> {
+    skeleton
+        .bones
+        .iter()
+        .map(|b| {
+            let rest = RestBone {
+                translation: b.rest.disp,
+                rotation: b.rest.rot,
+                height: b.rest_model.disp.y,
+            };
+            (b.name.clone(), rest)
+        })
+        .collect()
+}
+
+/// Retargets clips authored on a source skeleton to a target skeleton.
+///
+/// The rotation of a bone relative to its rest pose is applied to the rest pose of the
+/// target bone, so skeletons must have similar bone orientations (e.g. both in T-pose).
+/// Only the root keeps its translation, scaled by the ratio of the root heights, the other
+/// bones keep the proportions of the target.
+pub struct Retargeter {
+    pub map: BoneMap,
+    source: HashMap<String, RestBone>,
+    target: HashMap<String, RestBone>,
+}
+
+impl Retargeter {
+    /// The skeletons must be in their rest pose
+    pub fn new(map: BoneMap, source: &Skeleton, target: &Skeleton) -> Retargeter {
+        Retargeter {
+            map,
+            source: rest_bones(source),
+            target: rest_bones(target),
+        }
+    }
+
+    /// Scale of the root translation
+    pub fn root_scale(&self) -> f32 {
+        let root = match self.map.root {
+            Some(ref root) => root,
+            None => return 1.0,
+        };
+
+        let source = self.source.get(root).map(|b| b.height);
+        let target = self.map
+            .bones
+            .get(root)
+            .and_then(|t| self.target.get(t))
+            .map(|b| b.height);
+
+        match (source, target) {
+            (Some(s), Some(t)) if s.abs() > 1e-5 => t / s,
+            _ => 1.0,
+        }
+    }
+
+    /// The clip for the target skeleton, tracks of unmapped bones are dropped
+    pub fn retarget(&self, clip: &AnimationClip) -> AnimationClip {
+        let root_scale = self.root_scale();
+
+        let tracks = clip.tracks
+            .iter()
+            .filter_map(|track| {
+                let target_name = self.map.bones.get(&track.bone)?;
+                let src = self.source.get(&track.bone)?;
+                let dst = self.target.get(target_name)?;
+
+                let correction = dst.rotation * src.rotation.invert();
+                let rotation = Curve::new(
+                    track
+                        .rotation
+                        .keys
+                        .iter()
+                        .map(|k| Keyframe {
+                            time: k.time,
+                            value: (correction * k.value).normalize(),
+                        })
+                        .collect(),
+                );
+
+                let is_root = self.map.root.as_ref() == Some(&track.bone);
+                let translation = if is_root {
+                    Curve::new(
+                        track
+                            .translation
+                            .keys
+                            .iter()
+                            .map(|k| Keyframe {
+                                time: k.time,
+                                value: dst.translation + (k.value - src.translation) * root_scale,
+                            })
+                            .collect(),
+                    )
+                } else {
+                    Curve::default()
+                };
+
+                Some(Track {
+                    bone: target_name.clone(),
+                    translation,
+                    rotation,
+                    scale: track.scale.clone(),
+                })
+            })
+            .collect();
+
+        AnimationClip {
+            name: clip.name.clone(),
+            duration: clip.duration,
+            tracks,
+        }
+    }
+}
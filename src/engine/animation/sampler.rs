@@ -0,0 +1,129 @@
+use super::clip::{BonePose, Interpolate};
+use super::compression::CompressedClip;
+
+/// Index of the key before the last sampled time, per curve
+#[derive(Debug, Clone, Copy, Default)]
+struct Cursor {
+    translation: usize,
+    rotation: usize,
+    scale: usize,
+}
+
+/// Samples a `CompressedClip` played forward, the keys are found from the previous sample
+/// instead of searching the whole curves.
+pub struct ClipSampler {
+    cursors: Vec<Cursor>,
+    last_time: f32,
+    poses: Vec<BonePose>,
+}
+
+impl Default for ClipSampler {
+    fn default() -> ClipSampler {
+        ClipSampler::new()
+    }
+}
+
+/// Find the key before `time` starting from `cursor`, and the interpolation factor to the next
+fn seek(times: &[u16], cursor: &mut usize, time: u16) -> (usize, usize, f32) {
+    if times.len() <= 1 {
+        return (0, 0, 0.0);
+    }
+
+    if *cursor >= times.len() || times[*cursor] > time {
+        // Seeking backward, e.g. a looping clip restarted
+        *cursor = match times.binary_search(&time) {
+            Ok(i) => i,
+            Err(i) => i.max(1) - 1,
+        };
+    }
+
+    while *cursor + 1 < times.len() && times[*cursor + 1] <= time {
+        *cursor += 1;
+    }
+
+    let i = *cursor;
+    if i + 1 >= times.len() {
+        return (i, i, 0.0);
+    }
+
+    let (a, b) = (times[i], times[i + 1]);
+    let t = if b > a {
+        (time.max(a) - a) as f32 / (b - a) as f32
+    } else {
+        0.0
+    };
+
+    (i, i + 1, t)
+}
+
+impl ClipSampler {
+    pub fn new() -> ClipSampler {
+        ClipSampler {
+            cursors: Vec::new(),
+            last_time: 0.0,
+            poses: Vec::new(),
+        }
+    }
+
+    /// Pose of each track of `clip` at `time`, in the order of `clip.tracks`
+    pub fn sample(&mut self, clip: &CompressedClip, time: f32) -> &[BonePose] {
+        if self.cursors.len() != clip.tracks.len() || time < self.last_time {
+            self.cursors = vec![Cursor::default(); clip.tracks.len()];
+        }
+        self.last_time = time;
+
+        let time = if clip.duration > 0.0 {
+            ((time / clip.duration).max(0.0).min(1.0) * 65535.0 + 0.5) as u16
+        } else {
+            0
+        };
+
+        self.poses.clear();
+        for (track, cursor) in clip.tracks.iter().zip(self.cursors.iter_mut()) {
+            let translation = if track.translation.keys.is_empty() {
+                None
+            } else {
+                let (a, b, t) = seek(&track.translation.times, &mut cursor.translation, time);
+                Some(
+                    track
+                        .translation
+                        .key(a)
+                        .interpolate(&track.translation.key(b), t),
+                )
+            };
+
+            let rotation = if track.rotation.keys.is_empty() {
+                None
+            } else {
+                let (a, b, t) = seek(&track.rotation.times, &mut cursor.rotation, time);
+                Some(track.rotation.key(a).interpolate(&track.rotation.key(b), t))
+            };
+
+            let scale = if track.scale.keys.is_empty() {
+                None
+            } else {
+                let (a, b, t) = seek(&track.scale.times, &mut cursor.scale, time);
+                Some(track.scale.key(a).interpolate(&track.scale.key(b), t))
+            };
+
+            self.poses.push(BonePose {
+                translation,
+                rotation,
+                scale,
+            });
+        }
+
+        &self.poses
+    }
+
+    /// Sample a looping clip, `time` wraps around the duration
+    pub fn sample_looping(&mut self, clip: &CompressedClip, time: f32) -> &[BonePose] {
+        let time = if clip.duration > 0.0 {
+            time - (time / clip.duration).floor() * clip.duration
+        } else {
+            0.0
+        };
+
+        self.sample(clip, time)
+    }
+}
@@ -0,0 +1,177 @@
+use super::clip::{BonePose, Interpolate};
+use super::compression::CompressedClip;
+use engine::{GameObject, Name};
+use math::*;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Local transform of a bone
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LocalPose {
+    pub translation: Vector3<f32>,
+    pub rotation: Quaternion<f32>,
+    pub scale: Vector3<f32>,
+}
+
+impl LocalPose {
+    /// From `self` at 0 to `other` at 1
+    pub fn blend(&self, other: &LocalPose, t: f32) -> LocalPose {
+        LocalPose {
+            translation: self.translation.interpolate(&other.translation, t),
+            rotation: self.rotation.interpolate(&other.rotation, t),
+            scale: self.scale.interpolate(&other.scale, t),
+        }
+    }
+}
+
+pub struct Bone {
+    pub name: String,
+    pub object: Rc<RefCell<GameObject>>,
+    /// Nearest named ancestor
+    pub parent: Option<usize>,
+    /// Local transform in the rest pose
+    pub rest: Isometry3<f32>,
+    pub rest_scale: Vector3<f32>,
+    /// Transform relative to the skeleton root in the rest pose
+    pub rest_model: Isometry3<f32>,
+}
+
+/// The bones of a character, its descendant game objects having a `Name`.
+/// The current transforms are captured as the rest pose.
+pub struct Skeleton {
+    pub bones: Vec<Bone>,
+}
+
+impl Skeleton {
+    pub fn from_root(root: &GameObject) -> Skeleton {
+        let root_inv = root.transform
+            .global()
+            .inverse_transform()
+            .unwrap_or(Isometry3::one());
+
+        let mut skeleton = Skeleton { bones: Vec::new() };
+        skeleton.add_children(root, None, &root_inv);
+        skeleton
+    }
+
+    fn add_children(&mut self, go: &GameObject, parent: Option<usize>, root_inv: &Isometry3<f32>) {
+        for child in go.childen().into_iter() {
+            let name = child
+                .borrow()
+                .find_component::<Name>()
+                .map(|(n, _)| n.0.clone());
+
+            let parent = match name {
+                Some(name) => {
+                    let (rest, rest_scale, global) = {
+                        let c = child.borrow();
+                        (c.transform.local(), c.transform.local_scale(), c.transform.global())
+                    };
+
+                    self.bones.push(Bone {
+                        name,
+                        object: child.clone(),
+                        parent,
+                        rest,
+                        rest_scale,
+                        rest_model: root_inv.concat(&global),
+                    });
+                    Some(self.bones.len() - 1)
+                }
+                None => parent,
+            };
+
+            self.add_children(&child.borrow(), parent, root_inv);
+        }
+    }
+
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.bones.iter().position(|b| b.name == name)
+    }
+
+    /// Whether the bone `i` is `ancestor` or one of its descendants
+    pub fn is_descendant(&self, i: usize, ancestor: usize) -> bool {
+        let mut bone = Some(i);
+        while let Some(b) = bone {
+            if b == ancestor {
+                return true;
+            }
+            bone = self.bones[b].parent;
+        }
+        false
+    }
+
+    pub fn object(&self, name: &str) -> Option<Rc<RefCell<GameObject>>> {
+        self.find(name).map(|i| self.bones[i].object.clone())
+    }
+
+    /// Bone of each track of `clip`
+    pub fn bind(&self, clip: &CompressedClip) -> Vec<Option<usize>> {
+        clip.tracks.iter().map(|t| self.find(&t.bone)).collect()
+    }
+
+    pub fn rest_pose(&self) -> Vec<LocalPose> {
+        self.bones
+            .iter()
+            .map(|b| LocalPose {
+                translation: b.rest.disp,
+                rotation: b.rest.rot,
+                scale: b.rest_scale,
+            })
+            .collect()
+    }
+
+    /// The local transforms of the bones
+    pub fn current_pose(&self) -> Vec<LocalPose> {
+        self.bones
+            .iter()
+            .map(|b| {
+                let go = b.object.borrow();
+                let local = go.transform.local();
+                LocalPose {
+                    translation: local.disp,
+                    rotation: local.rot,
+                    scale: go.transform.local_scale(),
+                }
+            })
+            .collect()
+    }
+
+    /// Write the animated values of `poses`, sampled from a clip bound by `binding`
+    pub fn write_pose(pose: &mut [LocalPose], binding: &[Option<usize>], poses: &[BonePose]) {
+        for (bone, p) in binding.iter().zip(poses.iter()) {
+            if let Some(i) = *bone {
+                let local = &mut pose[i];
+                local.translation = p.translation.unwrap_or(local.translation);
+                local.rotation = p.rotation.unwrap_or(local.rotation);
+                local.scale = p.scale.unwrap_or(local.scale);
+            }
+        }
+    }
+
+    /// Set the local transforms of the bones
+    pub fn set_pose(&self, pose: &[LocalPose]) {
+        for (bone, local) in self.bones.iter().zip(pose.iter()) {
+            let mut go = bone.object.borrow_mut();
+            go.transform.set_local(Isometry3 {
+                disp: local.translation,
+                rot: local.rotation,
+                scale: 1.0,
+            });
+            go.transform.set_local_scale(local.scale);
+        }
+    }
+
+    /// Set the local transforms of the bones, values not animated keep the rest pose
+    pub fn apply(&self, binding: &[Option<usize>], poses: &[BonePose]) {
+        let mut pose = self.rest_pose();
+        Skeleton::write_pose(&mut pose, binding, poses);
+        self.set_pose(&pose);
+    }
+
+    /// Put all bones back in the rest pose
+    pub fn reset(&self) {
+        self.set_pose(&self.rest_pose());
+    }
+}
@@ -6,7 +6,8 @@ use engine::asset::{CubeMesh, PlaneMesh, QuadMesh, SkyboxMesh};
 use engine::asset::default_font_bitmap::DEFAULT_FONT_DATA;
 use engine::asset::fs;
 use engine::asset::loader;
-use engine::asset::Resource;
+use engine::asset::{AssetRoot, ModInfo, ModManifest, Resource, TextureManifest};
+use engine::diagnostics::AssetStats;
 
 use engine::{Material, MeshBuffer, ShaderFs, ShaderProgram, ShaderVs, Texture, TextureFiltering,
              TextureImage};
@@ -49,7 +50,17 @@ pub trait AssetSystem {
 
     fn new_mesh_buffer(&self, name: &str) -> Rc<MeshBuffer>;
 
-    fn new_prefab(&self, name: &str, mh: MaterialHandler, f: PrefabHandler);
+    fn new_prefab(&self, name: &str, mh: MaterialHandler, f: PrefabHandler) {
+        self.new_prefab_with(name, loader::PrefabImport::default(), mh, f);
+    }
+
+    fn new_prefab_with(
+        &self,
+        name: &str,
+        import: loader::PrefabImport,
+        mh: MaterialHandler,
+        f: PrefabHandler,
+    );
 
     fn reset(&mut self);
 
@@ -57,7 +68,28 @@ pub trait AssetSystem {
 
     fn loading_files(&self) -> Vec<String>;
 
+    /// Counts of cached assets, for diagnostics
+    fn stats(&self) -> AssetStats;
+
     fn execute(&self, AssetTask);
+
+    /// Add an asset root which overlays the base assets.
+    /// Assets already loaded are not affected until `reset`.
+    fn add_root(&self, root: AssetRoot);
+
+    fn remove_root(&self, name: &str);
+
+    fn roots(&self) -> Vec<AssetRoot>;
+
+    /// Load a mod manifest and add all enabled mods as asset roots
+    fn load_mods(&self, manifest: &str);
+
+    /// All mods listed in loaded manifests, included the disabled ones
+    fn mods(&self) -> Vec<ModInfo>;
+
+    /// Load a texture manifest of the texture importer, the listed textures are loaded from
+    /// their compressed files. Textures already loaded are not affected until `reset`.
+    fn load_texture_manifest(&self, manifest: &str);
 }
 
 pub trait Asset {
@@ -101,6 +133,10 @@ pub struct AssetDatabaseContext<FS> {
 
     pending_prefabs: RefCell<Vec<(PrefabHandler, PrefabFuture)>>,
     pending_tasks: RefCell<Vec<AssetTask>>,
+
+    mods: RefCell<Vec<ModInfo>>,
+    /// Compressed files of the textures, from the texture manifests
+    compressed_textures: RefCell<HashMap<String, String>>,
 }
 
 pub struct AssetDatabase<FS, F>
@@ -150,6 +186,9 @@ where
     }
 
     fn new_texture(&self, name: &str) -> Rc<Texture> {
+        let compressed = self.compressed_textures.borrow().get(name).cloned();
+        let name = compressed.as_ref().map_or(name, |s| s.as_str());
+
         let mut a = self.textures.borrow_mut();
         self.new_asset(&mut a, name)
     }
@@ -167,8 +206,14 @@ where
         self.setup();
     }
 
-    fn new_prefab(&self, name: &str, mh: MaterialHandler, f: PrefabHandler) {
-        let prefab = loader::Prefab::load_future(self.clone(), self.new_file(name), mh);
+    fn new_prefab_with(
+        &self,
+        name: &str,
+        import: loader::PrefabImport,
+        mh: MaterialHandler,
+        f: PrefabHandler,
+    ) {
+        let prefab = loader::Prefab::load_future(self.clone(), self.new_file(name), mh, import);
         self.pending_prefabs.borrow_mut().push((f, prefab));
     }
 
@@ -176,6 +221,62 @@ where
         self.pending_tasks.borrow_mut().push(task);
     }
 
+    fn add_root(&self, root: AssetRoot) {
+        self.fs.roots().add(root);
+    }
+
+    fn remove_root(&self, name: &str) {
+        self.fs.roots().remove(name);
+    }
+
+    fn roots(&self) -> Vec<AssetRoot> {
+        self.fs.roots().list()
+    }
+
+    fn load_mods(&self, manifest: &str) {
+        let db = self.clone();
+
+        let f = self.new_file(manifest)
+            .then(|r| {
+                let mut file = r.map_err(|e| AssetError::FileIoError(e))?;
+                loader::read_json::<ModManifest>(&mut file)
+            })
+            .map(move |manifest| {
+                for m in manifest.mods.into_iter() {
+                    if m.enabled {
+                        db.add_root(m.to_root());
+                    }
+
+                    let mut mods = db.mods.borrow_mut();
+                    mods.retain(|old| old.name != m.name);
+                    mods.push(m);
+                }
+            });
+
+        self.execute(Box::new(f));
+    }
+
+    fn mods(&self) -> Vec<ModInfo> {
+        self.mods.borrow().clone()
+    }
+
+    fn load_texture_manifest(&self, manifest: &str) {
+        let db = self.clone();
+
+        let f = self.new_file(manifest)
+            .then(|r| {
+                let mut file = r.map_err(|e| AssetError::FileIoError(e))?;
+                loader::read_json::<TextureManifest>(&mut file)
+            })
+            .map(move |manifest| {
+                db.compressed_textures
+                    .borrow_mut()
+                    .extend(manifest.textures.into_iter());
+            });
+
+        self.execute(Box::new(f));
+    }
+
     fn new() -> AssetDatabase<FS, F> {
         let mut db = AssetDatabase {
             context: Rc::new(AssetDatabaseContext {
@@ -186,6 +287,8 @@ where
                 programs: RefCell::new(HashMap::new()),
                 pending_prefabs: RefCell::new(Vec::new()),
                 pending_tasks: RefCell::new(Vec::new()),
+                mods: RefCell::new(Vec::new()),
+                compressed_textures: RefCell::new(HashMap::new()),
             }),
         };
 
@@ -242,6 +345,15 @@ where
     fn loading_files(&self) -> Vec<String> {
         self.fs.loading_files()
     }
+
+    fn stats(&self) -> AssetStats {
+        AssetStats {
+            textures: self.textures.borrow().len(),
+            mesh_buffers: self.mesh_buffers.borrow().len(),
+            programs: self.programs.borrow().len(),
+            loading_files: self.fs.loading_files().len(),
+        }
+    }
 }
 
 impl<FS, F> AssetDatabase<FS, F>
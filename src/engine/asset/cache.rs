@@ -0,0 +1,378 @@
+//! Asset cache of the web builds, in IndexedDB.
+//!
+//! The downloaded files are kept by their content hash, so a repeat visit only downloads
+//! the files which changed. The hashes come from a cache manifest, published with the
+//! assets (see `content_hash`) :
+//!
+//! ```json
+//! { "version": 3, "files": { "level1/rock.png": "9c1f0e2a7b44d3e5" } }
+//! ```
+//!
+//! Changing the `version` clears the whole cache at the next visit, e.g. after a change of
+//! the asset formats. The least recently used files are evicted above `max_bytes`.
+//! Entries are stored with their hash and checked when read, a damaged entry (e.g. a write
+//! interrupted by closing the page) is downloaded again.
+//! The DXT textures transcoded from basis files are cached too, the next visits skip
+//! the transcoding.
+//!
+//! Native builds read the assets from the disk, the cache is never enabled.
+
+use engine::asset::loader::{self, Loadable, Loader};
+use engine::asset::{AssetError, AssetResult, AssetSystem, File, Resource};
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+/// Name of the IndexedDB database
+pub const DB_NAME: &'static str = "unrust-asset-cache";
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct CacheManifest {
+    #[serde(default)]
+    pub version: u32,
+    /// Content hash by file name
+    #[serde(default)]
+    pub files: BTreeMap<String, String>,
+}
+
+impl CacheManifest {
+    pub fn new(version: u32) -> CacheManifest {
+        CacheManifest {
+            version,
+            files: BTreeMap::new(),
+        }
+    }
+
+    pub fn load(asys: &AssetSystem, filename: &str) -> Resource<CacheManifest> {
+        loader::load_json(asys, filename)
+    }
+
+    pub fn add(&mut self, filename: &str, data: &[u8]) {
+        self.files
+            .insert(filename.replace("\\", "/"), content_hash(data));
+    }
+}
+
+pub struct CacheManifestLoader {}
+
+impl Loader<CacheManifest> for CacheManifestLoader {
+    fn load<A>(_asys: A, mut file: Box<File>) -> AssetResult<CacheManifest> {
+        loader::read_json(&mut file)
+    }
+}
+
+impl Loadable for CacheManifest {
+    type Loader = CacheManifestLoader;
+}
+
+/// Hash of a file content for the manifests, 64 bits FNV-1a as 16 hex digits
+pub fn content_hash(data: &[u8]) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for &b in data.iter() {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+
+    format!("{:016x}", hash)
+}
+
+const HASH_LEN: usize = 16;
+
+/// Bytes of a stored entry, the data followed by its content hash
+pub fn entry_bytes(data: &[u8]) -> Vec<u8> {
+    let mut entry = Vec::with_capacity(data.len() + HASH_LEN);
+    entry.extend_from_slice(data);
+    entry.extend_from_slice(content_hash(data).as_bytes());
+    entry
+}
+
+/// Data of a stored entry, `None` when it does not match its hash
+pub fn entry_data(entry: &[u8]) -> Option<&[u8]> {
+    if entry.len() < HASH_LEN {
+        return None;
+    }
+
+    let (data, hash) = entry.split_at(entry.len() - HASH_LEN);
+    if content_hash(data).as_bytes() == hash {
+        Some(data)
+    } else {
+        None
+    }
+}
+
+/// Cache key of the transcoded texture of a file
+pub fn transcoded_key(hash: &str) -> String {
+    format!("{}:dds", hash)
+}
+
+enum CacheState {
+    Disabled,
+    /// The manifest is downloading, the files are not cached meanwhile
+    Loading(Rc<Resource<CacheManifest>>, usize),
+    Enabled(CacheManifest),
+}
+
+thread_local!(static STATE: RefCell<CacheState> = RefCell::new(CacheState::Disabled));
+
+/// Enable the cache when the manifest is loaded, `max_bytes` is the size cap of the cache
+pub fn enable(manifest: Resource<CacheManifest>, max_bytes: usize) {
+    if cfg!(not(target_arch = "wasm32")) {
+        return;
+    }
+
+    STATE.with(|s| *s.borrow_mut() = CacheState::Loading(Rc::new(manifest), max_bytes));
+}
+
+pub fn disable() {
+    STATE.with(|s| *s.borrow_mut() = CacheState::Disabled);
+}
+
+pub fn is_enabled() -> bool {
+    poll_manifest();
+    STATE.with(|s| match *s.borrow() {
+        CacheState::Enabled(_) => true,
+        _ => false,
+    })
+}
+
+/// Content hash of a file in the manifest
+pub fn hash_of(filename: &str) -> Option<String> {
+    poll_manifest();
+    STATE.with(|s| match *s.borrow() {
+        CacheState::Enabled(ref manifest) => manifest.files.get(filename).cloned(),
+        _ => None,
+    })
+}
+
+fn poll_manifest() {
+    let pending = STATE.with(|s| match *s.borrow() {
+        CacheState::Loading(ref res, max_bytes) => Some((res.clone(), max_bytes)),
+        _ => None,
+    });
+
+    // Polled without the state borrowed, the manifest file goes through the file system
+    let (res, max_bytes) = match pending {
+        Some(pending) => pending,
+        None => return,
+    };
+
+    let next = match res.try_into() {
+        Err(AssetError::NotReady) => return,
+        Err(e) => {
+            println!("Fail to load the asset cache manifest, reason: {:?}", e);
+            CacheState::Disabled
+        }
+        Ok(manifest) => {
+            platform::open(manifest.version, max_bytes);
+            CacheState::Enabled(manifest)
+        }
+    };
+
+    STATE.with(|s| *s.borrow_mut() = next);
+}
+
+/// Result of a cache lookup, filled by the IndexedDB callbacks
+pub enum CacheLookup {
+    Pending,
+    Hit(Vec<u8>),
+    Miss,
+}
+
+/// Look a file up by its hash, the transcoded texture first. A damaged entry is a miss.
+/// `None` when the file is not cached at all (cache disabled or not in the manifest).
+pub fn lookup(filename: &str) -> Option<(String, Rc<RefCell<CacheLookup>>)> {
+    let hash = hash_of(filename)?;
+    let result = Rc::new(RefCell::new(CacheLookup::Pending));
+
+    platform::get(&[transcoded_key(&hash), hash.clone()], result.clone());
+    Some((hash, result))
+}
+
+/// Store the bytes of an entry
+pub fn store(key: &str, data: &[u8]) {
+    if is_enabled() {
+        platform::put(key, &entry_bytes(data));
+    }
+}
+
+/// Store the DXT texture transcoded from a file, as dds bytes
+pub fn store_transcoded(filename: &str, dds: &[u8]) {
+    if let Some(hash) = hash_of(filename) {
+        store(&transcoded_key(&hash), dds);
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod platform {
+    use super::{entry_data, CacheLookup, DB_NAME};
+
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use stdweb::unstable::TryInto;
+    use stdweb::web::TypedArray;
+    use stdweb::{Once, UnsafeTypedArray, Value};
+
+    pub fn open(version: u32, max_bytes: usize) {
+        let max_bytes = max_bytes as f64;
+
+        js! { @(no_return)
+            var cache = { db: null, failed: false, waiting: [], maxBytes: @{max_bytes} };
+            window.unrustCache = cache;
+
+            cache.ready = function(f) {
+                if (cache.db || cache.failed) { f(); } else { cache.waiting.push(f); }
+            };
+            var done = function() {
+                cache.waiting.forEach(function(f) { f(); });
+                cache.waiting = [];
+            };
+
+            if (!window.indexedDB) {
+                cache.failed = true;
+                return;
+            }
+
+            // "data" has the bytes, "entries" the sizes and access times for the eviction
+            var req = indexedDB.open(@{DB_NAME}, 1);
+            req.onupgradeneeded = function() {
+                req.result.createObjectStore("data");
+                req.result.createObjectStore("entries");
+                req.result.createObjectStore("meta");
+            };
+            req.onerror = function() { cache.failed = true; done(); };
+            req.onsuccess = function() {
+                var db = req.result;
+                var tx = db.transaction(["data", "entries", "meta"], "readwrite");
+                var meta = tx.objectStore("meta");
+                var get = meta.get("version");
+                get.onsuccess = function() {
+                    if (get.result !== @{version}) {
+                        tx.objectStore("data").clear();
+                        tx.objectStore("entries").clear();
+                        meta.put(@{version}, "version");
+                    }
+                };
+                tx.oncomplete = function() { cache.db = db; done(); };
+                tx.onerror = function() { cache.failed = true; done(); };
+            };
+        }
+    }
+
+    /// The first of `keys` found
+    pub fn get(keys: &[String], result: Rc<RefCell<CacheLookup>>) {
+        let keys = keys.to_vec();
+        let callback = move |value: Value| {
+            let entry: Option<TypedArray<u8>> = value.try_into().ok();
+            let entry = entry.map(|e| e.to_vec());
+            *result.borrow_mut() = match entry.as_ref().and_then(|e| entry_data(e)) {
+                Some(data) => CacheLookup::Hit(data.to_vec()),
+                None => CacheLookup::Miss,
+            };
+        };
+
+        js! { @(no_return)
+            var callback = @{Once(callback)};
+            var cache = window.unrustCache;
+            var keys = @{keys};
+            if (!cache) {
+                callback(null);
+                return;
+            }
+
+            cache.ready(function() {
+                if (!cache.db) {
+                    callback(null);
+                    return;
+                }
+
+                var tx = cache.db.transaction(["data", "entries"], "readwrite");
+                var next = function(i) {
+                    if (i >= keys.length) {
+                        callback(null);
+                        return;
+                    }
+
+                    var get = tx.objectStore("data").get(keys[i]);
+                    get.onsuccess = function() {
+                        if (get.result === undefined) {
+                            next(i + 1);
+                            return;
+                        }
+
+                        var entries = tx.objectStore("entries");
+                        entries.put({ size: get.result.byteLength, time: Date.now() }, keys[i]);
+                        callback(new Uint8Array(get.result));
+                    };
+                    get.onerror = function() { next(i + 1); };
+                };
+                next(0);
+            });
+        }
+    }
+
+    pub fn put(key: &str, data: &[u8]) {
+        let data = unsafe { UnsafeTypedArray::new(data) };
+
+        js! { @(no_return)
+            var cache = window.unrustCache;
+            if (!cache) {
+                return;
+            }
+
+            // Copied now, the rust memory may change before the database is ready
+            var key = @{key};
+            var bytes = new Uint8Array(@{data}).slice().buffer;
+
+            cache.ready(function() {
+                if (!cache.db || bytes.byteLength > cache.maxBytes) {
+                    return;
+                }
+
+                var tx = cache.db.transaction(["data", "entries"], "readwrite");
+                var entries = tx.objectStore("entries");
+                tx.objectStore("data").put(bytes, key);
+                entries.put({ size: bytes.byteLength, time: Date.now() }, key);
+
+                // Evict the least recently used entries above the size cap
+                var all = [];
+                var cursor = entries.openCursor();
+                cursor.onsuccess = function() {
+                    var c = cursor.result;
+                    if (c) {
+                        all.push({ key: c.key, size: c.value.size, time: c.value.time });
+                        c.continue();
+                        return;
+                    }
+
+                    var total = all.reduce(function(sum, e) { return sum + e.size; }, 0);
+                    all.sort(function(a, b) { return a.time - b.time; });
+                    for (var i = 0; i < all.length && total > cache.maxBytes; i++) {
+                        if (all[i].key === key) {
+                            continue;
+                        }
+                        tx.objectStore("data").delete(all[i].key);
+                        entries.delete(all[i].key);
+                        total -= all[i].size;
+                    }
+                };
+            });
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod platform {
+    use super::CacheLookup;
+
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    pub fn open(_version: u32, _max_bytes: usize) {}
+
+    pub fn get(_keys: &[String], result: Rc<RefCell<CacheLookup>>) {
+        *result.borrow_mut() = CacheLookup::Miss;
+    }
+
+    pub fn put(_key: &str, _data: &[u8]) {}
+}
@@ -1,6 +1,9 @@
 use std::default::Default;
 use futures::prelude::*;
 use std;
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::path::Path;
 
 pub type FileFuture = Box<Future<Item = Box<File>, Error = FileIoError>>;
 
@@ -10,6 +13,8 @@ pub trait FileSystem: Default {
     fn open(&self, filename: &str) -> FileFuture;
 
     fn loading_files(&self) -> Vec<String>;
+
+    fn roots(&self) -> &AssetRoots;
 }
 
 pub trait File {
@@ -31,3 +36,94 @@ impl From<std::io::Error> for FileIoError {
         FileIoError::IoError(e)
     }
 }
+
+/// An extra directory (e.g. a mod) which overlays the base assets by path
+#[derive(Debug, Clone)]
+pub struct AssetRoot {
+    pub name: String,
+    /// Directory of the root, a relative path is in the directory of the base assets
+    pub path: String,
+    /// Roots with higher priority are searched first
+    pub priority: i32,
+    /// Files provided by this root,
+    /// `None` means probing the file system, which is only supported in native.
+    pub files: Option<BTreeSet<String>>,
+}
+
+impl AssetRoot {
+    pub fn new(name: &str, path: &str, priority: i32) -> AssetRoot {
+        AssetRoot {
+            name: name.to_owned(),
+            path: path.to_owned(),
+            priority,
+            files: None,
+        }
+    }
+
+    pub fn with_files<I>(mut self, files: I) -> AssetRoot
+    where
+        I: IntoIterator<Item = String>,
+    {
+        self.files = Some(files.into_iter().map(|f| f.replace("\\", "/")).collect());
+        self
+    }
+
+    pub fn file_path(&self, filename: &str) -> String {
+        format!("{}/{}", self.path.trim_right_matches('/'), filename)
+    }
+}
+
+/// Path of `path` in the directory `base` of the base assets, absolute paths are kept
+pub fn asset_path(base: &str, path: &str) -> String {
+    if base.is_empty() || Path::new(path).is_absolute() {
+        path.to_owned()
+    } else {
+        format!("{}/{}", base.trim_right_matches('/'), path)
+    }
+}
+
+/// Priority ordered list of asset roots
+#[derive(Default)]
+pub struct AssetRoots {
+    roots: RefCell<Vec<AssetRoot>>,
+}
+
+impl AssetRoots {
+    /// Add a root, replace the old one with the same name
+    pub fn add(&self, root: AssetRoot) {
+        let mut roots = self.roots.borrow_mut();
+        roots.retain(|r| r.name != root.name);
+        // the latest added root wins for same priority, as the sort is stable
+        roots.insert(0, root);
+        roots.sort_by(|a, b| b.priority.cmp(&a.priority));
+    }
+
+    pub fn remove(&self, name: &str) {
+        self.roots.borrow_mut().retain(|r| r.name != name);
+    }
+
+    pub fn list(&self) -> Vec<AssetRoot> {
+        self.roots.borrow().clone()
+    }
+
+    /// Find the path of the file in the first root providing it, else in `base`.
+    /// Relative root paths are in `base` too, like the base assets.
+    pub fn resolve<F>(&self, base: &str, filename: &str, exists: F) -> String
+    where
+        F: Fn(&str) -> bool,
+    {
+        for root in self.roots.borrow().iter() {
+            let path = asset_path(base, &root.file_path(filename));
+            let provided = match root.files {
+                Some(ref files) => files.contains(filename),
+                None => exists(&path),
+            };
+
+            if provided {
+                return path;
+            }
+        }
+
+        asset_path(base, filename)
+    }
+}
@@ -0,0 +1,204 @@
+//! Low-priority asset work, run in the idle time between frames.
+//!
+//! Jobs are run in small steps, e.g. preloading and decoding the assets of the next level
+//! while the player is still in the current one, without hitches. On web the steps run in
+//! `requestIdleCallback` (or a timeout where it is missing), until the deadline given by the
+//! browser. Natively they run at the end of the frame, in what is left of the frame budget
+//! (see `World::set_frame_budget`, a 60 fps frame without budget).
+//!
+//! ```ignore
+//! let asys = (*world.engine().asset_system).clone();
+//! let mut names = vec!["level2/rock.png", "level2/tree.png"];
+//! world.idle.push(move || match names.pop() {
+//!     Some(name) => {
+//!         asys.new_texture(name);
+//!         false
+//!     }
+//!     None => true,
+//! });
+//! ```
+
+use engine::asset::AssetError;
+use futures::{Async, Future};
+use uni_app::now;
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+/// Frame time used as the budget when no frame budget is set
+const DEFAULT_BUDGET: f64 = 1.0 / 60.0;
+
+enum IdleJob {
+    /// Returns true when the job is done
+    Step(Box<FnMut() -> bool>),
+    Task(Box<Future<Item = (), Error = AssetError>>),
+}
+
+impl IdleJob {
+    fn is_task(&self) -> bool {
+        match *self {
+            IdleJob::Task(_) => true,
+            _ => false,
+        }
+    }
+
+    fn run(&mut self) -> bool {
+        match *self {
+            IdleJob::Step(ref mut f) => f(),
+            IdleJob::Task(ref mut task) => match task.poll() {
+                Ok(Async::NotReady) => false,
+                Ok(Async::Ready(_)) => true,
+                Err(e) => {
+                    println!("Idle task failed, reason: {:?}", e);
+                    true
+                }
+            },
+        }
+    }
+}
+
+struct IdleInner {
+    jobs: VecDeque<IdleJob>,
+    /// An idle callback is pending on web
+    scheduled: bool,
+}
+
+pub struct IdleQueue {
+    /// Longest time in seconds spent in the jobs at once
+    pub max_slice: f64,
+    /// Skip the idle work after a frame over the budget
+    pub skip_after_hitch: bool,
+
+    inner: Rc<RefCell<IdleInner>>,
+}
+
+impl Default for IdleQueue {
+    fn default() -> IdleQueue {
+        IdleQueue::new()
+    }
+}
+
+impl IdleQueue {
+    pub fn new() -> IdleQueue {
+        IdleQueue {
+            max_slice: 0.008,
+            skip_after_hitch: true,
+            inner: Rc::new(RefCell::new(IdleInner {
+                jobs: VecDeque::new(),
+                scheduled: false,
+            })),
+        }
+    }
+
+    /// Add a job run by steps, `f` returns true when the job is done
+    pub fn push<F>(&self, f: F)
+    where
+        F: FnMut() -> bool + 'static,
+    {
+        self.inner
+            .borrow_mut()
+            .jobs
+            .push_back(IdleJob::Step(Box::new(f)));
+    }
+
+    /// Add a future, polled in the idle time only
+    pub fn push_task(&self, task: Box<Future<Item = (), Error = AssetError>>) {
+        self.inner.borrow_mut().jobs.push_back(IdleJob::Task(task));
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.borrow().jobs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.borrow().jobs.is_empty()
+    }
+
+    pub fn clear(&self) {
+        self.inner.borrow_mut().jobs.clear();
+    }
+
+    /// Run the jobs in turn until `deadline` (in seconds, on the `now` clock), at least one
+    /// step is run. Returns the number of steps.
+    pub fn run(&self, deadline: f64) -> usize {
+        run_jobs(&self.inner, deadline)
+    }
+
+    /// Run or schedule the idle work after a frame which took `frame_time` seconds
+    pub fn end_frame(&self, frame_time: f64, budget: Option<f64>) {
+        let budget = budget.unwrap_or(DEFAULT_BUDGET);
+        if self.is_empty() || (self.skip_after_hitch && frame_time > budget) {
+            return;
+        }
+
+        self.schedule(budget - frame_time);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn schedule(&self, remaining: f64) {
+        let slice = remaining.min(self.max_slice);
+        if slice > 0.0 {
+            self.run(now() + slice);
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn schedule(&self, _remaining: f64) {
+        use stdweb::Once;
+
+        if self.inner.borrow().scheduled {
+            return;
+        }
+        self.inner.borrow_mut().scheduled = true;
+
+        let inner = self.inner.clone();
+        let max_slice = self.max_slice;
+        let callback = move |remaining_ms: f64| {
+            inner.borrow_mut().scheduled = false;
+            let slice = (remaining_ms / 1000.0).min(max_slice);
+            run_jobs(&inner, now() + slice);
+        };
+
+        js! { @(no_return)
+            var callback = @{Once(callback)};
+            if (window.requestIdleCallback) {
+                window.requestIdleCallback(function(deadline) {
+                    callback(deadline.timeRemaining());
+                }, { timeout: 1000 });
+            } else {
+                setTimeout(function() { callback(4.0); }, 0);
+            }
+        }
+    }
+}
+
+fn run_jobs(inner: &Rc<RefCell<IdleInner>>, deadline: f64) -> usize {
+    let mut steps = 0;
+    // Tasks in a row waiting for their files, all the jobs waiting ends the slice
+    let mut waiting = 0;
+
+    loop {
+        // The queue is not borrowed while a job runs, so jobs can add other jobs
+        let job = inner.borrow_mut().jobs.pop_front();
+        let mut job = match job {
+            Some(job) => job,
+            None => break,
+        };
+
+        let done = job.run();
+        steps += 1;
+
+        waiting = if !done && job.is_task() { waiting + 1 } else { 0 };
+
+        if !done {
+            inner.borrow_mut().jobs.push_back(job);
+        }
+
+        if now() >= deadline || waiting >= inner.borrow().jobs.len() {
+            break;
+        }
+    }
+
+    steps
+}
@@ -0,0 +1,221 @@
+//! Basis Universal textures, `.basis` and KTX2 (ETC1S or UASTC) files.
+//!
+//! The textures are transcoded at load time to the best format supported by the device, from
+//! the `WEBGL_compressed_texture_*` extensions of the engine context : ASTC 4x4, BC1 / BC3
+//! (s3tc), ETC2 (etc), ETC1 for opaque textures (etc1), PVRTC1 for square power of two
+//! textures (pvrtc), uncompressed RGBA otherwise.
+//!
+//! The transcoding uses the Basis Universal web transcoder (`basis_transcoder.js` and `.wasm`),
+//! which the page must load and initialize before loading the textures :
+//!
+//! ```js
+//! BASIS().then(function (module) {
+//!     module.initializeBasis();
+//!     window.unrustBasis = module;
+//! });
+//! ```
+//!
+//! Native builds have no transcoder, use the dds files of the texture importer instead.
+
+use engine::asset::{AssetError, AssetResult};
+use engine::TextureImage;
+
+use super::ktx2::{self, KTX2_MAGIC_BYTES};
+
+static BASIS_MAGIC_BYTES: &'static [u8] = b"sB";
+
+/// The basis signature is short, so the extension is checked too (e.g. tga has no signature).
+/// The KTX2 files of basis textures have no Vulkan format.
+pub fn is_basis(buf: &[u8], file_name: &str) -> bool {
+    let basis_ext = file_name.to_lowercase().ends_with(".basis");
+    (basis_ext && buf.starts_with(BASIS_MAGIC_BYTES)) || ktx2::vk_format(buf) == Some(0)
+}
+
+pub struct BasisReader {}
+
+impl BasisReader {
+    pub fn read(buf: Vec<u8>, file_name: &String) -> AssetResult<TextureImage> {
+        let ktx2 = buf.starts_with(KTX2_MAGIC_BYTES);
+
+        platform::transcode(&buf, ktx2).map_err(|reason| AssetError::InvalidFormat {
+            len: buf.len(),
+            path: file_name.clone(),
+            reason,
+        })
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod platform {
+    use super::super::dds::{DDSFormat, DDSImage, DDS};
+    use engine::render::capabilities;
+    use engine::{CompressedImage, TextureImage};
+    use image::RgbaImage;
+
+    use stdweb::unstable::{TryFrom, TryInto};
+    use stdweb::web::TypedArray;
+    use stdweb::{UnsafeTypedArray, Value};
+
+    // transcoder_texture_format of the basis transcoder
+    const FORMAT_ETC1_RGB: u32 = 0;
+    const FORMAT_ETC2_RGBA: u32 = 1;
+    const FORMAT_BC1_RGB: u32 = 2;
+    const FORMAT_BC3_RGBA: u32 = 3;
+    const FORMAT_PVRTC1_4_RGB: u32 = 8;
+    const FORMAT_PVRTC1_4_RGBA: u32 = 9;
+    const FORMAT_ASTC_4X4_RGBA: u32 = 10;
+    const FORMAT_RGBA32: u32 = 13;
+
+    // GL internal formats of the extensions
+    const GL_COMPRESSED_RGB8_ETC2: u32 = 0x9274;
+    const GL_COMPRESSED_RGBA8_ETC2_EAC: u32 = 0x9278;
+    const GL_ETC1_RGB8_OES: u32 = 0x8D64;
+    const GL_COMPRESSED_RGB_PVRTC_4BPPV1_IMG: u32 = 0x8C00;
+    const GL_COMPRESSED_RGBA_PVRTC_4BPPV1_IMG: u32 = 0x8C02;
+    const GL_COMPRESSED_RGBA_ASTC_4X4_KHR: u32 = 0x93B0;
+
+    /// GL format of the transcoded blocks without a `TextureImage` of their own
+    fn gl_format(format: u32) -> Option<u32> {
+        let caps = capabilities::capabilities();
+        match format {
+            // ETC1 blocks are valid ETC2 RGB blocks
+            FORMAT_ETC1_RGB if caps.etc => Some(GL_COMPRESSED_RGB8_ETC2),
+            FORMAT_ETC1_RGB => Some(GL_ETC1_RGB8_OES),
+            FORMAT_ETC2_RGBA => Some(GL_COMPRESSED_RGBA8_ETC2_EAC),
+            FORMAT_PVRTC1_4_RGB => Some(GL_COMPRESSED_RGB_PVRTC_4BPPV1_IMG),
+            FORMAT_PVRTC1_4_RGBA => Some(GL_COMPRESSED_RGBA_PVRTC_4BPPV1_IMG),
+            FORMAT_ASTC_4X4_RGBA => Some(GL_COMPRESSED_RGBA_ASTC_4X4_KHR),
+            _ => None,
+        }
+    }
+
+    fn field<T: TryFrom<Value>>(obj: &Value, name: &str) -> Option<T> {
+        let v = js! { return @{obj}[@{name}]; };
+        v.try_into().ok()
+    }
+
+    pub fn transcode(buf: &[u8], ktx2: bool) -> Result<TextureImage, String> {
+        let data = unsafe { UnsafeTypedArray::new(buf) };
+        let caps = capabilities::capabilities();
+
+        let result = js! {
+            var basis = window.unrustBasis;
+            if (!basis) {
+                return { error: "The basis transcoder is not loaded (window.unrustBasis)" };
+            }
+
+            var file = @{ktx2} ? new basis.KTX2File(new Uint8Array(@{data}))
+                               : new basis.BasisFile(new Uint8Array(@{data}));
+            var close = function () { file.close(); file.delete(); };
+
+            var levels = @{ktx2} ? file.getLevels() : file.getNumLevels(0);
+            var width = @{ktx2} ? file.getWidth() : file.getImageWidth(0, 0);
+            var height = @{ktx2} ? file.getHeight() : file.getImageHeight(0, 0);
+            var alpha = !!file.getHasAlpha();
+
+            if (!levels || !width || !height || !file.startTranscoding()) {
+                close();
+                return { error: "Invalid basis file" };
+            }
+
+            // S3TC needs sizes multiple of 4 in WebGL, PVRTC1 square power of two sizes
+            var pot = width == height && (width & (width - 1)) == 0;
+            var format = @{FORMAT_RGBA32};
+            if (@{caps.astc}) {
+                format = @{FORMAT_ASTC_4X4_RGBA};
+            } else if (@{caps.s3tc} && width % 4 == 0 && height % 4 == 0) {
+                format = alpha ? @{FORMAT_BC3_RGBA} : @{FORMAT_BC1_RGB};
+            } else if (@{caps.etc}) {
+                format = alpha ? @{FORMAT_ETC2_RGBA} : @{FORMAT_ETC1_RGB};
+            } else if (@{caps.etc1} && !alpha) {
+                format = @{FORMAT_ETC1_RGB};
+            } else if (@{caps.pvrtc} && pot) {
+                format = alpha ? @{FORMAT_PVRTC1_4_RGBA} : @{FORMAT_PVRTC1_4_RGB};
+            }
+            if (format == @{FORMAT_RGBA32}) {
+                // The engine generates the mipmaps of uncompressed textures
+                levels = 1;
+            }
+
+            var widths = [], heights = [], images = [];
+            for (var lvl = 0; lvl < levels; lvl++) {
+                var size = @{ktx2} ? file.getImageTranscodedSizeInBytes(lvl, 0, 0, format)
+                                   : file.getImageTranscodedSizeInBytes(0, lvl, format);
+                var dst = new Uint8Array(size);
+                var ok = @{ktx2} ? file.transcodeImage(dst, lvl, 0, 0, format, 0, -1, -1)
+                                 : file.transcodeImage(dst, 0, lvl, format, 0, 0);
+                if (!ok) {
+                    close();
+                    return { error: "Fail to transcode level " + lvl };
+                }
+
+                widths.push(Math.max(1, width >> lvl));
+                heights.push(Math.max(1, height >> lvl));
+                images.push(dst);
+            }
+
+            close();
+            return { error: null, format: format, alpha: alpha,
+                     widths: widths, heights: heights, images: images };
+        };
+
+        if let Some(error) = field::<String>(&result, "error") {
+            return Err(error);
+        }
+
+        let format: u32 = field(&result, "format").ok_or("Invalid transcoder result")?;
+        let alpha: bool = field(&result, "alpha").unwrap_or(false);
+        let widths: Vec<u32> = field(&result, "widths").unwrap_or_default();
+        let heights: Vec<u32> = field(&result, "heights").unwrap_or_default();
+        let images: Vec<TypedArray<u8>> = field(&result, "images").unwrap_or_default();
+
+        if images.len() == 0 || images.len() != widths.len() || images.len() != heights.len() {
+            return Err("Invalid transcoder result".to_owned());
+        }
+
+        if format == FORMAT_RGBA32 {
+            return RgbaImage::from_raw(widths[0], heights[0], images[0].to_vec())
+                .map(TextureImage::Rgba)
+                .ok_or("Invalid transcoded image size".to_owned());
+        }
+
+        let images: Vec<DDSImage> = images
+            .iter()
+            .zip(widths.iter().zip(heights.iter()))
+            .map(|(data, (&width, &height))| DDSImage {
+                width,
+                height,
+                data: data.to_vec(),
+            })
+            .collect();
+
+        if let Some(gl_format) = gl_format(format) {
+            return Ok(TextureImage::Compressed(CompressedImage { gl_format, images }));
+        }
+
+        Ok(if format == FORMAT_BC3_RGBA {
+            TextureImage::DXT5(DDS {
+                format: DDSFormat::DXT5,
+                has_alpha: alpha,
+                images,
+            })
+        } else {
+            TextureImage::DXT1(DDS {
+                format: DDSFormat::DXT1,
+                has_alpha: false,
+                images,
+            })
+        })
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod platform {
+    use engine::TextureImage;
+
+    pub fn transcode(_buf: &[u8], _ktx2: bool) -> Result<TextureImage, String> {
+        Err("Basis textures are only transcoded in web, \
+             use the dds files of the texture importer in native"
+            .to_owned())
+    }
+}
@@ -149,3 +149,79 @@ impl DDSReader {
         })
     }
 }
+
+const DDSD_CAPS: u32 = 0x1;
+const DDSD_HEIGHT: u32 = 0x2;
+const DDSD_WIDTH: u32 = 0x4;
+const DDSD_PIXELFORMAT: u32 = 0x1000;
+const DDSD_LINEARSIZE: u32 = 0x80000;
+const DDSCAPS_COMPLEX: u32 = 0x8;
+const DDSCAPS_TEXTURE: u32 = 0x1000;
+const DDSCAPS_MIPMAP: u32 = 0x400000;
+
+fn push_u32(out: &mut Vec<u8>, v: u32) {
+    out.extend_from_slice(&[v as u8, (v >> 8) as u8, (v >> 16) as u8, (v >> 24) as u8]);
+}
+
+impl DDS {
+    pub fn from_bytes(buff: Vec<u8>, file_name: &String) -> AssetResult<DDS> {
+        DDSReader::read(buff, file_name)
+    }
+
+    /// Serialize to a dds file, readable by the texture loader
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let (width, height) = self.images
+            .first()
+            .map_or((0, 0), |img| (img.width, img.height));
+        let mipmaps = self.images.len() > 1;
+
+        let mut flags = DDSD_CAPS | DDSD_HEIGHT | DDSD_WIDTH | DDSD_PIXELFORMAT | DDSD_LINEARSIZE;
+        let mut caps = DDSCAPS_TEXTURE;
+        if mipmaps {
+            flags |= DDSD_MIPMAPCOUNT;
+            caps |= DDSCAPS_COMPLEX | DDSCAPS_MIPMAP;
+        }
+
+        let mut pf_flags = DDPF_FOURCC;
+        if self.has_alpha {
+            pf_flags |= DDPF_ALPHAPIXELS;
+        }
+
+        let four_cc = match self.format {
+            DDSFormat::DXT1 => b"DXT1",
+            DDSFormat::DXT5 => b"DXT5",
+        };
+
+        let mut out = Vec::new();
+        out.extend_from_slice(b"DDS ");
+
+        push_u32(&mut out, 124);
+        push_u32(&mut out, flags);
+        push_u32(&mut out, height);
+        push_u32(&mut out, width);
+        push_u32(&mut out, self.images.first().map_or(0, |img| img.data.len() as u32));
+        push_u32(&mut out, 0);
+        push_u32(&mut out, self.images.len() as u32);
+        for _ in 0..11 {
+            push_u32(&mut out, 0);
+        }
+
+        push_u32(&mut out, 32);
+        push_u32(&mut out, pf_flags);
+        out.extend_from_slice(four_cc);
+        for _ in 0..5 {
+            push_u32(&mut out, 0);
+        }
+
+        push_u32(&mut out, caps);
+        for _ in 0..4 {
+            push_u32(&mut out, 0);
+        }
+
+        for img in self.images.iter() {
+            out.extend_from_slice(&img.data);
+        }
+
+        out
+    }
+}
@@ -1,3 +1,4 @@
+use engine::asset::cache;
 use engine::asset::loader::{Loadable, Loader};
 use engine::asset::{AssetError, AssetResult, AssetSystem, File, FileFuture};
 use engine::TextureImage;
@@ -13,7 +14,9 @@ use std::fmt::Debug;
 use uni_app;
 use std::path::Path;
 
-use super::dds::{DDSFormat, DDSReader};
+use super::basis::{self, BasisReader};
+use super::dds::{DDSFormat, DDSReader, DDS};
+use super::ktx2;
 
 pub struct ImageLoader {}
 
@@ -313,6 +316,41 @@ where
     Box::new(img)
 }
 
+fn load_future_ktx2<T>(img_buf: T) -> Box<Future<Item = TextureImage, Error = AssetError>>
+where
+    T: Future<Item = (Vec<u8>, String), Error = AssetError> + 'static,
+{
+    let img = img_buf.and_then(|(whole_buf, file_name)| {
+        DDS::from_ktx2(&whole_buf, &file_name).map(|dds| match dds.format {
+            DDSFormat::DXT1 => TextureImage::DXT1(dds),
+            DDSFormat::DXT5 => TextureImage::DXT5(dds),
+        })
+    });
+
+    Box::new(img)
+}
+
+fn load_future_basis<T>(img_buf: T) -> Box<Future<Item = TextureImage, Error = AssetError>>
+where
+    T: Future<Item = (Vec<u8>, String), Error = AssetError> + 'static,
+{
+    let img = img_buf.and_then(|(whole_buf, file_name)| {
+        let img = BasisReader::read(whole_buf, &file_name)?;
+
+        // The next visits load the dds from the asset cache, without transcoding
+        match img {
+            TextureImage::DXT1(ref dds) | TextureImage::DXT5(ref dds) => {
+                cache::store_transcoded(&file_name, &dds.to_bytes())
+            }
+            _ => (),
+        }
+
+        Ok(img)
+    });
+
+    Box::new(img)
+}
+
 impl Loadable for TextureImage {
     type Loader = ImageLoader;
 
@@ -335,6 +373,14 @@ impl Loadable for TextureImage {
                 return load_future_dds(future::result(Ok((whole_buf, file_name))));
             }
 
+            if ktx2::vk_format(&whole_buf).map_or(false, |f| f != 0) {
+                return load_future_ktx2(future::result(Ok((whole_buf, file_name))));
+            }
+
+            if basis::is_basis(&whole_buf, &file_name) {
+                return load_future_basis(future::result(Ok((whole_buf, file_name))));
+            }
+
             load_future_uncompressed(future::result(Ok((whole_buf, file_name))))
         }))
     }
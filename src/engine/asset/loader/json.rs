@@ -0,0 +1,33 @@
+use engine::asset::loader::Loadable;
+use engine::asset::{AssetError, AssetResult, AssetSystem, File, Resource};
+
+use futures::Future;
+use serde::de::DeserializeOwned;
+use serde_json;
+use std::fmt::Debug;
+
+/// Read the whole file and deserialize it as json
+pub fn read_json<T>(file: &mut Box<File>) -> AssetResult<T>
+where
+    T: DeserializeOwned,
+{
+    let buf = file.read_binary()
+        .map_err(|_| AssetError::ReadBufferFail(file.name()))?;
+
+    serde_json::from_slice(&buf).map_err(|e| AssetError::InvalidFormat {
+        path: file.name(),
+        len: buf.len(),
+        reason: format!("{:?}", e),
+    })
+}
+
+/// Load a json based asset which does not depend on other assets.
+pub fn load_json<T>(asys: &AssetSystem, name: &str) -> Resource<T>
+where
+    T: Loadable + DeserializeOwned + Debug + 'static,
+{
+    Resource::new_future(asys.new_file(name).then(|r| {
+        let mut file = r.map_err(|e| AssetError::FileIoError(e))?;
+        read_json(&mut file)
+    }))
+}
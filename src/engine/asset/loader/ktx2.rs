@@ -0,0 +1,205 @@
+//! KTX2 files of BC1 / BC3 textures, without supercompression.
+//!
+//! The KTX2 files of Basis Universal (`VK_FORMAT_UNDEFINED`) are transcoded by the basis
+//! loader instead.
+
+use engine::asset::{AssetError, AssetResult};
+
+use super::dds::{DDSFormat, DDSImage, DDS};
+
+pub static KTX2_MAGIC_BYTES: &'static [u8] = &[
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A
+];
+
+const VK_FORMAT_BC1_RGB_UNORM_BLOCK: u32 = 131;
+const VK_FORMAT_BC1_RGBA_UNORM_BLOCK: u32 = 133;
+const VK_FORMAT_BC3_UNORM_BLOCK: u32 = 137;
+
+const KHR_DF_MODEL_BC1A: u8 = 128;
+const KHR_DF_MODEL_BC3: u8 = 130;
+const KHR_DF_CHANNEL_COLOR: u8 = 0;
+const KHR_DF_CHANNEL_ALPHA: u8 = 15;
+
+const HEADER_SIZE: usize = 80;
+const LEVEL_INDEX_SIZE: usize = 24;
+
+/// The Vulkan format of a KTX2 file, 0 for the Basis Universal files
+pub fn vk_format(buf: &[u8]) -> Option<u32> {
+    if buf.starts_with(KTX2_MAGIC_BYTES) {
+        read_u32(buf, 12)
+    } else {
+        None
+    }
+}
+
+fn read_u32(buf: &[u8], offset: usize) -> Option<u32> {
+    buf.get(offset..offset + 4).map(|b| {
+        b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16 | (b[3] as u32) << 24
+    })
+}
+
+fn read_u64(buf: &[u8], offset: usize) -> Option<u64> {
+    match (read_u32(buf, offset), read_u32(buf, offset + 4)) {
+        (Some(lo), Some(hi)) => Some(lo as u64 | (hi as u64) << 32),
+        _ => None,
+    }
+}
+
+fn push_u16(out: &mut Vec<u8>, v: u16) {
+    out.extend_from_slice(&[v as u8, (v >> 8) as u8]);
+}
+
+fn push_u32(out: &mut Vec<u8>, v: u32) {
+    out.extend_from_slice(&[v as u8, (v >> 8) as u8, (v >> 16) as u8, (v >> 24) as u8]);
+}
+
+fn push_u64(out: &mut Vec<u8>, v: u64) {
+    push_u32(out, v as u32);
+    push_u32(out, (v >> 32) as u32);
+}
+
+impl DDS {
+    /// Read a KTX2 file of BC1 or BC3 blocks, as written by `to_ktx2`
+    pub fn from_ktx2(buf: &[u8], file_name: &String) -> AssetResult<DDS> {
+        let invalid = |reason: String| AssetError::InvalidFormat {
+            len: buf.len(),
+            path: file_name.clone(),
+            reason,
+        };
+
+        let header = |i: usize| read_u32(buf, 12 + i * 4).unwrap_or(0);
+        if !buf.starts_with(KTX2_MAGIC_BYTES) || buf.len() < HEADER_SIZE {
+            return Err(invalid("Invalid KTX2 Header Format".to_owned()));
+        }
+
+        let (vk_format, width, height) = (header(0), header(2), header(3));
+        let (layers, faces, levels) = (header(5), header(6), header(7));
+        let supercompression = header(8);
+
+        let (format, has_alpha, block_bytes) = match vk_format {
+            VK_FORMAT_BC1_RGB_UNORM_BLOCK => (DDSFormat::DXT1, false, 8),
+            VK_FORMAT_BC1_RGBA_UNORM_BLOCK => (DDSFormat::DXT1, true, 8),
+            VK_FORMAT_BC3_UNORM_BLOCK => (DDSFormat::DXT5, true, 16),
+            _ => {
+                return Err(invalid(format!(
+                    "Unsupported Format, only support BC1, BC3 (current: {})",
+                    vk_format
+                )))
+            }
+        };
+
+        if supercompression != 0 || layers > 1 || faces != 1 || width == 0 || height == 0 {
+            return Err(invalid(
+                "Unsupported KTX2 texture, only support 2D textures without supercompression"
+                    .to_owned(),
+            ));
+        }
+
+        let mut images = Vec::new();
+        for level in 0..levels.max(1) as usize {
+            let entry = HEADER_SIZE + level * LEVEL_INDEX_SIZE;
+            let (offset, length) = match (read_u64(buf, entry), read_u64(buf, entry + 8)) {
+                (Some(offset), Some(length)) => (offset as usize, length as usize),
+                _ => return Err(invalid("Invalid KTX2 Level Index".to_owned())),
+            };
+
+            let w = (width >> level).max(1);
+            let h = (height >> level).max(1);
+            let expected = ((w + 3) / 4 * ((h + 3) / 4) * block_bytes) as usize;
+            let data = match buf.get(offset..offset.saturating_add(length)) {
+                Some(data) if length == expected => data.to_vec(),
+                _ => return Err(invalid(format!("Invalid KTX2 Level {}", level))),
+            };
+
+            images.push(DDSImage {
+                width: w,
+                height: h,
+                data,
+            });
+        }
+
+        Ok(DDS {
+            format,
+            has_alpha,
+            images,
+        })
+    }
+
+    /// Serialize to a KTX2 file, readable by the texture loader and the KTX tools
+    pub fn to_ktx2(&self) -> Vec<u8> {
+        let (width, height) = self.images
+            .first()
+            .map_or((0, 0), |img| (img.width, img.height));
+
+        let (vk_format, model, block_bytes) = match self.format {
+            DDSFormat::DXT1 if self.has_alpha => {
+                (VK_FORMAT_BC1_RGBA_UNORM_BLOCK, KHR_DF_MODEL_BC1A, 8)
+            }
+            DDSFormat::DXT1 => (VK_FORMAT_BC1_RGB_UNORM_BLOCK, KHR_DF_MODEL_BC1A, 8),
+            DDSFormat::DXT5 => (VK_FORMAT_BC3_UNORM_BLOCK, KHR_DF_MODEL_BC3, 16),
+        };
+
+        // Data format descriptor, a basic block with a sample per 64 bits block
+        let samples: &[(u16, u8)] = match self.format {
+            DDSFormat::DXT1 => &[(0, KHR_DF_CHANNEL_COLOR)],
+            DDSFormat::DXT5 => &[(0, KHR_DF_CHANNEL_ALPHA), (64, KHR_DF_CHANNEL_COLOR)],
+        };
+        let block_size = 24 + 16 * samples.len();
+
+        let mut dfd = Vec::new();
+        push_u32(&mut dfd, (4 + block_size) as u32);
+        push_u32(&mut dfd, 0);
+        push_u16(&mut dfd, 2);
+        push_u16(&mut dfd, block_size as u16);
+        // Model, BT.709 primaries, linear transfer, straight alpha
+        dfd.extend_from_slice(&[model, 1, 1, 0]);
+        dfd.extend_from_slice(&[3, 3, 0, 0]);
+        dfd.extend_from_slice(&[block_bytes, 0, 0, 0, 0, 0, 0, 0]);
+        for &(offset, channel) in samples.iter() {
+            push_u16(&mut dfd, offset);
+            dfd.extend_from_slice(&[63, channel, 0, 0, 0, 0]);
+            push_u32(&mut dfd, 0);
+            push_u32(&mut dfd, 0xFFFF_FFFF);
+        }
+
+        let levels = self.images.len();
+        let dfd_offset = HEADER_SIZE + levels * LEVEL_INDEX_SIZE;
+
+        // The levels are stored from the smallest, aligned to the block size
+        let align = block_bytes as usize;
+        let mut offsets = vec![0; levels];
+        let mut end = dfd_offset + dfd.len();
+        for level in (0..levels).rev() {
+            end = (end + align - 1) / align * align;
+            offsets[level] = end;
+            end += self.images[level].data.len();
+        }
+
+        let mut out = Vec::with_capacity(end);
+        out.extend_from_slice(KTX2_MAGIC_BYTES);
+        for v in [vk_format, 1, width, height, 0, 0, 1, levels as u32, 0].iter() {
+            push_u32(&mut out, *v);
+        }
+        push_u32(&mut out, dfd_offset as u32);
+        push_u32(&mut out, dfd.len() as u32);
+        // No key/value data nor supercompression global data
+        push_u32(&mut out, 0);
+        push_u32(&mut out, 0);
+        push_u64(&mut out, 0);
+        push_u64(&mut out, 0);
+
+        for (level, img) in self.images.iter().enumerate() {
+            push_u64(&mut out, offsets[level] as u64);
+            push_u64(&mut out, img.data.len() as u64);
+            push_u64(&mut out, img.data.len() as u64);
+        }
+
+        out.extend_from_slice(&dfd);
+        for level in (0..levels).rev() {
+            out.resize(offsets[level], 0);
+            out.extend_from_slice(&self.images[level].data);
+        }
+
+        out
+    }
+}
@@ -50,6 +50,7 @@ impl Loader<MeshData> for MeshDataLoader {
             indices,
             vertices,
             uvs: Some(uvs),
+            uvs2: None,
             normals: Some(normals),
             tangents: None,
             bitangents: None,
@@ -4,9 +4,13 @@ mod shader;
 mod mesh_data;
 mod prefab;
 mod dds;
+mod basis;
+mod ktx2;
+mod json;
 
 pub use self::loader::{Loadable, Loader};
 pub use self::image::ImageLoader;
 pub use self::shader::{ShaderFSLoader, ShaderVSLoader};
-pub use self::prefab::{ObjMaterial, Prefab, PrefabLoader};
-pub use self::dds::DDS;
+pub use self::prefab::{ObjMaterial, Prefab, PrefabImport, PrefabLoader};
+pub use self::dds::{DDSFormat, DDSImage, DDS};
+pub use self::json::{load_json, read_json};
@@ -1,5 +1,7 @@
 use engine::asset::{Asset, AssetError, AssetSystem, FileFuture, Resource};
+use engine::lightmap::{generate_lightmap_uvs, DEFAULT_LIGHTMAP_SIZE};
 use engine::render::{Material, Mesh, MeshBuffer, MeshData};
+use engine::unwrap::{unwrap_uvs, UnwrapOptions, UvChannel};
 use std::borrow::Cow;
 use std::path::Path;
 
@@ -17,6 +19,23 @@ pub struct Prefab {
     pub meshes: Vec<Mesh>,
 }
 
+/// How the meshes of an obj prefab are imported
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PrefabImport {
+    /// Generate the lightmap uvs (`MeshData::uvs2`) for lightmaps of this size, for the static
+    /// geometry only: the vertices are split along the seams of the charts. None by default.
+    pub lightmap_size: Option<u32>,
+}
+
+impl PrefabImport {
+    /// Static geometry lit by lightmaps of `DEFAULT_LIGHTMAP_SIZE`
+    pub fn lightmap_static() -> PrefabImport {
+        PrefabImport {
+            lightmap_size: Some(DEFAULT_LIGHTMAP_SIZE),
+        }
+    }
+}
+
 fn parent_path(filename: &str) -> String {
     let path = Path::new(filename);
     let parent = path.parent();
@@ -285,6 +304,7 @@ impl PrefabLoader {
         parent: String,
         model: obj::Obj<SimplePolygon>,
         builder: MaterialBuilder,
+        import: PrefabImport,
     ) -> Prefab
     where
         A: AssetSystem + Clone + 'static,
@@ -356,24 +376,42 @@ impl PrefabLoader {
                     None
                 };
 
-                let tangent_space = if has_normal_map.0 {
-                    compute_tangents(&v_array, &uv_array, &n_array, &indices)
-                } else {
-                    TangentSpace {
-                        tangents: None,
-                        bitangents: None,
-                    }
-                };
-
-                let mesh_data = MeshData {
+                let mut mesh_data = MeshData {
                     indices: indices,
                     vertices: v_array,
                     uvs: uv_array,
-                    tangents: tangent_space.tangents,
-                    bitangents: tangent_space.bitangents,
+                    uvs2: None,
+                    tangents: None,
+                    bitangents: None,
                     normals: n_array,
                 };
 
+                // Lightmap uvs, deterministic so they match the ones of the bake. Unwrapped
+                // once, the lightmap layout is also the uv0 of the meshes without uvs.
+                let missing_uvs = mesh_data.uvs.is_none();
+                match import.lightmap_size {
+                    Some(size) => {
+                        if generate_lightmap_uvs(&mut mesh_data, size) && missing_uvs {
+                            mesh_data.uvs = mesh_data.uvs2.clone();
+                        }
+                    }
+                    None if missing_uvs => {
+                        unwrap_uvs(&mut mesh_data, UvChannel::Uv0, &UnwrapOptions::default());
+                    }
+                    None => (),
+                }
+
+                if has_normal_map.0 {
+                    let tangent_space = compute_tangents(
+                        &mesh_data.vertices,
+                        &mesh_data.uvs,
+                        &mesh_data.normals,
+                        &mesh_data.indices,
+                    );
+                    mesh_data.tangents = tangent_space.tangents;
+                    mesh_data.bitangents = tangent_space.bitangents;
+                }
+
                 mesh.add_surface(
                     MeshBuffer::new_from_resource(Resource::new(mesh_data)),
                     material,
@@ -402,6 +440,7 @@ impl Prefab {
         asys: A,
         objfile: FileFuture,
         builder: MaterialBuilder,
+        import: PrefabImport,
     ) -> Box<Future<Item = Self, Error = AssetError>>
     where
         Self: 'static,
@@ -445,7 +484,7 @@ impl Prefab {
                 }
             }
 
-            Ok(PrefabLoader::load_model(asys, parent, model, builder))
+            Ok(PrefabLoader::load_model(asys, parent, model, builder, import))
         });
 
         // futurize
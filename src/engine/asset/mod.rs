@@ -2,17 +2,28 @@ mod asset_database;
 mod default_font_bitmap;
 mod quad;
 mod fs;
+mod idle;
+mod mods;
 mod primitives;
 mod resource;
 mod skybox;
+mod texture_manifest;
 
+#[cfg(not(target_arch = "wasm32"))]
+pub mod texture_import;
+
+pub mod cache;
 pub mod loader;
+pub mod offline;
 pub use self::primitives::{CubeMesh, PlaneMesh};
 pub use self::quad::QuadMesh;
 pub use self::skybox::SkyboxMesh;
 pub use self::asset_database::{Asset, AssetDatabase, AssetError, AssetResult, AssetSystem,
                                LoadableAsset};
-pub use self::loader::{ObjMaterial, Prefab, DDS};
+pub use self::loader::{DDSFormat, DDSImage, ObjMaterial, Prefab, PrefabImport, DDS};
 
 pub use self::resource::Resource;
+pub use self::idle::IdleQueue;
+pub use self::mods::{ModInfo, ModManifest};
+pub use self::texture_manifest::TextureManifest;
 pub use self::fs::*;
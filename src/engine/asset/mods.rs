@@ -0,0 +1,62 @@
+use engine::asset::loader::{self, Loadable, Loader};
+use engine::asset::{AssetResult, AssetRoot, File};
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// An installed mod, as listed in the mod manifest
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ModInfo {
+    pub name: String,
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub description: String,
+    /// Directory (or url in web) of the mod assets
+    pub path: String,
+    /// Mods with higher priority overlay the lower ones
+    #[serde(default)]
+    pub priority: i32,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Files replaced by this mod, required in web as files cannot be probed
+    #[serde(default)]
+    pub files: Option<Vec<String>>,
+}
+
+impl ModInfo {
+    pub fn to_root(&self) -> AssetRoot {
+        let root = AssetRoot::new(&self.name, &self.path, self.priority);
+
+        match self.files {
+            Some(ref files) => root.with_files(files.iter().cloned()),
+            None => root,
+        }
+    }
+}
+
+/// Mod manifest, a json file enumerating installed mods :
+///
+/// ```json
+/// { "mods": [
+///     { "name": "hd_textures", "path": "mods/hd_textures", "priority": 10 },
+///     { "name": "red_cube", "path": "mods/red_cube", "files": ["tex_a.png"] }
+/// ]}
+/// ```
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ModManifest {
+    pub mods: Vec<ModInfo>,
+}
+
+pub struct ModManifestLoader {}
+
+impl Loader<ModManifest> for ModManifestLoader {
+    fn load<A>(_asys: A, mut file: Box<File>) -> AssetResult<ModManifest> {
+        loader::read_json(&mut file)
+    }
+}
+
+impl Loadable for ModManifest {
+    type Loader = ModManifestLoader;
+}
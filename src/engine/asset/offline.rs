@@ -0,0 +1,288 @@
+//! Offline mode of the web builds, with a service worker.
+//!
+//! `OfflineManifest::generate` lists the published files with their content hashes, and
+//! writes the manifest, the service worker and the asset cache manifest (see `cache`) :
+//!
+//! ```ignore
+//! // Build script or tool, after `cargo web deploy`
+//! OfflineManifest::generate(Path::new("target/deploy"), 3)?;
+//!
+//! // In the game
+//! offline::register_service_worker(offline::SERVICE_WORKER);
+//! if offline::status() == OfflineStatus::Ready { show_offline_badge(); }
+//! ```
+//!
+//! At the first visit, the service worker pre-caches all the listed files, the engine wasm
+//! and the asset bundles, then serves them from the cache so the game runs without network.
+//! A new `version` replaces the cached files at the next visit with network.
+
+use engine::asset::cache::content_hash;
+
+#[cfg(not(target_arch = "wasm32"))]
+use engine::asset::cache::CacheManifest;
+#[cfg(not(target_arch = "wasm32"))]
+use serde_json;
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs;
+#[cfg(not(target_arch = "wasm32"))]
+use std::io;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::{Path, PathBuf};
+
+/// Name of the manifest read by the service worker
+pub const OFFLINE_MANIFEST: &'static str = "offline.json";
+/// Name of the service worker script
+pub const SERVICE_WORKER: &'static str = "unrust-sw.js";
+/// Name of the asset cache manifest written beside, see `World::enable_asset_cache`
+pub const CACHE_MANIFEST: &'static str = "asset-cache.json";
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct OfflineEntry {
+    /// Url relative to the service worker
+    pub url: String,
+    pub hash: String,
+    pub size: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct OfflineManifest {
+    pub version: u32,
+    pub files: Vec<OfflineEntry>,
+}
+
+impl OfflineManifest {
+    pub fn new(version: u32) -> OfflineManifest {
+        OfflineManifest {
+            version,
+            files: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, url: &str, data: &[u8]) {
+        let url = url.replace("\\", "/");
+        self.files.retain(|f| f.url != url);
+        self.files.push(OfflineEntry {
+            url,
+            hash: content_hash(data),
+            size: data.len(),
+        });
+    }
+
+    /// Total size of the pre-cached files, in bytes
+    pub fn total_size(&self) -> usize {
+        self.files.iter().map(|f| f.size).sum()
+    }
+
+    /// List the files of the deploy directory `dir` and write the offline manifest, the
+    /// service worker and the asset cache manifest in it
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn generate(dir: &Path, version: u32) -> io::Result<OfflineManifest> {
+        let mut files = Vec::new();
+        list_files(dir, &mut files)?;
+
+        let generated = [OFFLINE_MANIFEST, SERVICE_WORKER, CACHE_MANIFEST];
+
+        let mut manifest = OfflineManifest::new(version);
+        let mut cache = CacheManifest::new(version);
+        for file in files.iter() {
+            let rel = file.strip_prefix(dir).unwrap_or(file);
+            let url = rel.to_string_lossy().replace("\\", "/");
+            if generated.contains(&url.as_str()) {
+                continue;
+            }
+
+            let data = fs::read(file)?;
+            manifest.add(&url, &data);
+            cache.add(&url, &data);
+        }
+
+        let to_io = |e: serde_json::Error| io::Error::new(io::ErrorKind::Other, e);
+        fs::write(
+            dir.join(OFFLINE_MANIFEST),
+            serde_json::to_string_pretty(&manifest).map_err(&to_io)?,
+        )?;
+        fs::write(
+            dir.join(CACHE_MANIFEST),
+            serde_json::to_string_pretty(&cache).map_err(&to_io)?,
+        )?;
+        fs::write(dir.join(SERVICE_WORKER), SERVICE_WORKER_JS)?;
+
+        Ok(manifest)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn list_files(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut entries = fs::read_dir(dir)?
+        .map(|e| e.map(|e| e.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+
+    for path in entries.into_iter() {
+        if path.is_dir() {
+            list_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// The service worker, pre-caching the files of the offline manifest by version.
+/// Posts `{ type: "progress", done, total }` then `{ type: "ready" }` to the pages.
+pub const SERVICE_WORKER_JS: &'static str = r#"// Generated by unrust, see engine::asset::offline
+var PREFIX = "unrust-offline-";
+
+function notify(msg) {
+    self.clients.matchAll({ includeUncontrolled: true }).then(function (clients) {
+        clients.forEach(function (c) { c.postMessage(msg); });
+    });
+}
+
+self.addEventListener("install", function (event) {
+    event.waitUntil(fetch("offline.json", { cache: "no-store" })
+        .then(function (r) { return r.json(); })
+        .then(function (manifest) {
+            return caches.open(PREFIX + manifest.version).then(function (cache) {
+                var urls = ["./"].concat(manifest.files.map(function (f) { return f.url; }));
+                var done = 0;
+                return Promise.all(urls.map(function (url) {
+                    return cache.add(url).then(function () {
+                        done++;
+                        notify({ type: "progress", done: done, total: urls.length });
+                    });
+                }));
+            });
+        })
+        .then(function () { return self.skipWaiting(); }));
+});
+
+self.addEventListener("activate", function (event) {
+    event.waitUntil(fetch("offline.json", { cache: "no-store" })
+        .then(function (r) { return r.json(); })
+        .then(function (manifest) {
+            return caches.keys().then(function (keys) {
+                return Promise.all(keys.filter(function (k) {
+                    return k.indexOf(PREFIX) === 0 && k !== PREFIX + manifest.version;
+                }).map(function (k) { return caches.delete(k); }));
+            });
+        })
+        .catch(function () {})
+        .then(function () { return self.clients.claim(); })
+        .then(function () { notify({ type: "ready" }); }));
+});
+
+self.addEventListener("fetch", function (event) {
+    if (event.request.method !== "GET") {
+        return;
+    }
+
+    event.respondWith(caches.match(event.request).then(function (cached) {
+        return cached || fetch(event.request);
+    }));
+});
+"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OfflineStatus {
+    /// No service worker, e.g. native or an insecure page
+    Unsupported,
+    /// Pre-caching the files
+    Installing,
+    /// All files are cached, the game runs offline
+    Ready,
+    Failed,
+}
+
+/// Register the service worker script, at the root of the deployed files
+pub fn register_service_worker(script: &str) {
+    platform::register(script);
+}
+
+pub fn status() -> OfflineStatus {
+    platform::status()
+}
+
+/// Files pre-cached and files to cache, while `Installing`
+pub fn progress() -> (usize, usize) {
+    platform::progress()
+}
+
+#[cfg(target_arch = "wasm32")]
+mod platform {
+    use super::OfflineStatus;
+    use stdweb::unstable::TryInto;
+
+    pub fn register(script: &str) {
+        js! { @(no_return)
+            var state = { status: "unsupported", done: 0, total: 0 };
+            window.unrustOffline = state;
+            if (!("serviceWorker" in navigator)) {
+                return;
+            }
+
+            navigator.serviceWorker.addEventListener("message", function (e) {
+                if (e.data.type === "progress") {
+                    state.status = "installing";
+                    state.done = e.data.done;
+                    state.total = e.data.total;
+                } else if (e.data.type === "ready") {
+                    state.status = "ready";
+                }
+            });
+
+            state.status = "installing";
+            navigator.serviceWorker.register(@{script}).then(function (reg) {
+                // Already installed at a previous visit
+                if (reg.active && !reg.installing && !reg.waiting) {
+                    state.status = "ready";
+                }
+            }).catch(function () {
+                state.status = "failed";
+            });
+        }
+    }
+
+    pub fn status() -> OfflineStatus {
+        let status: String = js! {
+            return window.unrustOffline ? window.unrustOffline.status : "unsupported";
+        }.try_into()
+            .unwrap_or_default();
+
+        match status.as_str() {
+            "installing" => OfflineStatus::Installing,
+            "ready" => OfflineStatus::Ready,
+            "failed" => OfflineStatus::Failed,
+            _ => OfflineStatus::Unsupported,
+        }
+    }
+
+    pub fn progress() -> (usize, usize) {
+        let done: u32 = js! {
+            return window.unrustOffline ? window.unrustOffline.done : 0;
+        }.try_into()
+            .unwrap_or(0);
+        let total: u32 = js! {
+            return window.unrustOffline ? window.unrustOffline.total : 0;
+        }.try_into()
+            .unwrap_or(0);
+
+        (done as usize, total as usize)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod platform {
+    use super::OfflineStatus;
+
+    pub fn register(_script: &str) {}
+
+    pub fn status() -> OfflineStatus {
+        OfflineStatus::Unsupported
+    }
+
+    pub fn progress() -> (usize, usize) {
+        (0, 0)
+    }
+}
@@ -27,8 +27,22 @@ struct PointLight {
     vec3 specular;
 
     float rate;
+
+    vec3 spotDirection;
+    float spotCosOuter;
+    float spotCosInner;
 };
 
+float spotFactor(PointLight light, vec3 lightDir) {
+    if (light.spotCosOuter < -1.0) {
+        return 1.0;
+    }
+
+    float cosTheta = dot(-lightDir, normalize(light.spotDirection));
+    float width = max(light.spotCosInner - light.spotCosOuter, 0.0001);
+    return clamp((cosTheta - light.spotCosOuter) / width, 0.0, 1.0);
+}
+
 struct Material {
     sampler2D diffuse;
     float shininess;
@@ -92,7 +106,7 @@ vec3 CalcPointLight(PointLight light, vec3 normal, vec3 fragPos, vec3 viewDir)
     // attenuation
     float distance = length(light.position - fragPos);
     float d = (light.constant + light.linear * distance + light.quadratic * (distance * distance));
-    float attenuation = 1.0 / max(d, 0.001);
+    float attenuation = spotFactor(light, normalize(light.position - fragPos)) / max(d, 0.001);
     
     // combine results
     vec3 ambient = light.ambient * vec3(texture2D(uMaterial.diffuse, vTexCoords));
@@ -119,6 +119,7 @@ impl CubeMesh {
         MeshData {
             vertices: vertices,
             uvs: Some(uvs),
+            uvs2: None,
             normals: Some(normals),
             indices: indices,
             tangents: None,
@@ -160,6 +161,7 @@ impl PlaneMesh {
         MeshData {
             vertices: vertices,
             uvs: Some(uvs),
+            uvs2: None,
             normals: Some(normals),
             indices: indices,
             tangents: None,
@@ -26,6 +26,7 @@ impl QuadMesh {
         MeshData {
             vertices: vertices,
             uvs: Some(uvs),
+            uvs2: None,
             normals: None,
             indices: indices,
             tangents: None,
@@ -1,5 +1,5 @@
 use futures::{Async, Future};
-use std::cell::{Ref, RefCell};
+use std::cell::{Ref, RefCell, RefMut};
 use std::fmt;
 use std::fmt::Debug;
 use std::mem;
@@ -46,6 +46,13 @@ impl<T: Debug> ResourceKind<T> {
             _ => None,
         }
     }
+
+    fn try_as_data_mut(&mut self) -> Option<&mut T> {
+        match self {
+            &mut ResourceKind::Data(ref mut d) => Some(d),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -104,6 +111,31 @@ impl<T: Debug + loader::Loadable> Resource<T> {
         return Ok(Ref::map(b0, |t| t.try_as_data().unwrap()));
     }
 
+    /// Change the data in place, once loaded
+    pub fn try_borrow_mut(&self) -> AssetResult<RefMut<T>> {
+        self.try_borrow()?;
+
+        let b0 = self.0.borrow_mut();
+        Ok(RefMut::map(b0, |t| t.try_as_data_mut().unwrap()))
+    }
+
+    /// Whether the data was taken by `try_into`, e.g. when uploaded to the gpu
+    pub fn is_consumed(&self) -> bool {
+        match *self.0.borrow() {
+            ResourceKind::Consumed => true,
+            _ => false,
+        }
+    }
+
+    /// Poll the resource without taking the data, `Ok` once loaded
+    pub fn status(&self) -> AssetResult<()> {
+        if self.is_consumed() {
+            return Ok(());
+        }
+
+        self.try_borrow().map(|_| ())
+    }
+
     pub fn replace(&self, t: T) {
         self.0.borrow_mut().replace(ResourceKind::Data(t));
     }
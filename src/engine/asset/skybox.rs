@@ -24,6 +24,7 @@ impl SkyboxMesh {
         MeshData {
             vertices: vertices,
             uvs: None,
+            uvs2: None,
             normals: None,
             indices: indices,
             tangents: None,
@@ -0,0 +1,426 @@
+//! Offline texture import, native only
+//!
+//! Compresses the source textures of an asset directory into the asset bundle, so the game
+//! downloads and uploads GPU compressed textures instead of decoding PNGs :
+//!
+//! ```ignore
+//! let importer = TextureImporter::new(ImportPlatform::Web);
+//! importer.import(Path::new("assets"), Path::new("static"))?;
+//!
+//! // In the game
+//! asys.load_texture_manifest("textures.json");
+//! ```
+//!
+//! Textures are compressed to DXT1, or DXT5 when they have transparent pixels, in dds files or
+//! KTX2 files (`TextureContainer::Ktx2`). The KTX2 files hold the BC1 / BC3 blocks as is, the
+//! basis supercompression needs the Basis Universal encoder.
+
+use engine::asset::{DDSFormat, DDSImage, TextureManifest, DDS};
+use image::{self, RgbaImage};
+use serde_json;
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Name of the manifest written in the output directory
+pub const TEXTURE_MANIFEST: &'static str = "textures.json";
+
+const CUBEMAP_FACES: [&'static str; 6] = ["right", "left", "top", "bottom", "front", "back"];
+
+/// Target platform of the imported assets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportPlatform {
+    /// S3TC is supported by all desktop GPUs
+    Desktop,
+    /// S3TC is supported by most desktop browsers, the source textures are kept as fallback
+    Web,
+    /// Mobile GPUs need ETC or ASTC, which are not supported yet, the textures are copied as is
+    Mobile,
+}
+
+impl ImportPlatform {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            ImportPlatform::Desktop => "desktop",
+            ImportPlatform::Web => "web",
+            ImportPlatform::Mobile => "mobile",
+        }
+    }
+
+    fn compresses(&self) -> bool {
+        *self != ImportPlatform::Mobile
+    }
+}
+
+/// File format of the compressed textures
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureContainer {
+    Dds,
+    Ktx2,
+}
+
+impl TextureContainer {
+    pub fn extension(&self) -> &'static str {
+        match *self {
+            TextureContainer::Dds => "dds",
+            TextureContainer::Ktx2 => "ktx2",
+        }
+    }
+}
+
+pub struct TextureImporter {
+    pub platform: ImportPlatform,
+    pub container: TextureContainer,
+    /// Generate the mipmaps of power of two textures
+    pub mipmaps: bool,
+    /// Copy the source textures beside the compressed ones, for devices without S3TC
+    pub keep_sources: bool,
+}
+
+impl TextureImporter {
+    pub fn new(platform: ImportPlatform) -> TextureImporter {
+        TextureImporter {
+            platform,
+            container: TextureContainer::Dds,
+            mipmaps: true,
+            keep_sources: platform == ImportPlatform::Web,
+        }
+    }
+
+    /// Compress an image, `None` if its size is not a multiple of 4 as required by S3TC
+    pub fn compress(&self, img: &RgbaImage) -> Option<DDS> {
+        let (w, h) = img.dimensions();
+        if w == 0 || h == 0 || w % 4 != 0 || h % 4 != 0 {
+            return None;
+        }
+
+        let has_alpha = img.pixels().any(|p| p.data[3] < 255);
+        let format = if has_alpha {
+            DDSFormat::DXT5
+        } else {
+            DDSFormat::DXT1
+        };
+
+        let mut levels = vec![img.clone()];
+        if self.mipmaps && w.is_power_of_two() && h.is_power_of_two() {
+            loop {
+                let next = match levels.last() {
+                    Some(last) if last.width() > 1 || last.height() > 1 => downsample(last),
+                    _ => break,
+                };
+                levels.push(next);
+            }
+        }
+
+        let images = levels
+            .iter()
+            .map(|level| DDSImage {
+                width: level.width(),
+                height: level.height(),
+                data: encode(level, has_alpha),
+            })
+            .collect();
+
+        Some(DDS {
+            format,
+            has_alpha,
+            images,
+        })
+    }
+
+    /// Import all files of `source` into `output`, compress the png and tga textures and write
+    /// the texture manifest. Other files are copied as is.
+    pub fn import(&self, source: &Path, output: &Path) -> io::Result<TextureManifest> {
+        let mut manifest = TextureManifest {
+            platform: self.platform.name().to_owned(),
+            ..Default::default()
+        };
+
+        let ext = self.container.extension();
+        let mut files = Vec::new();
+        list_files(source, &mut files)?;
+
+        for file in files.iter() {
+            let rel = file.strip_prefix(source).unwrap_or(file);
+            let dest = output.join(rel);
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            let compressed = if self.platform.compresses() && is_texture(file) {
+                self.import_texture(file, &dest.with_extension(ext))?
+            } else {
+                false
+            };
+
+            if compressed {
+                manifest
+                    .textures
+                    .insert(asset_name(rel), asset_name(&rel.with_extension(ext)));
+            }
+
+            if !compressed || self.keep_sources {
+                fs::copy(file, &dest)?;
+            }
+        }
+
+        add_cubemaps(&mut manifest, ext);
+
+        let json = serde_json::to_string_pretty(&manifest)
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        fs::write(output.join(TEXTURE_MANIFEST), json)?;
+
+        Ok(manifest)
+    }
+
+    fn import_texture(&self, file: &Path, dest: &Path) -> io::Result<bool> {
+        let img = match image::open(file) {
+            Ok(img) => img.to_rgba(),
+            Err(e) => {
+                println!("Fail to import texture {:?}, reason: {:?}", file, e);
+                return Ok(false);
+            }
+        };
+
+        match self.compress(&img) {
+            Some(dds) => {
+                let bytes = match self.container {
+                    TextureContainer::Dds => dds.to_bytes(),
+                    TextureContainer::Ktx2 => dds.to_ktx2(),
+                };
+                fs::write(dest, bytes)?;
+                Ok(true)
+            }
+            None => {
+                println!(
+                    "Texture {:?} is not compressed, its size {:?} is not a multiple of 4",
+                    file,
+                    img.dimensions()
+                );
+                Ok(false)
+            }
+        }
+    }
+}
+
+fn list_files(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut entries = fs::read_dir(dir)?
+        .map(|e| e.map(|e| e.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+
+    for path in entries.into_iter() {
+        if path.is_dir() {
+            list_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+fn is_texture(path: &Path) -> bool {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some(ext) => {
+            let ext = ext.to_lowercase();
+            ext == "png" || ext == "tga"
+        }
+        None => false,
+    }
+}
+
+/// Path relative to the asset root, as passed to `AssetSystem::new_texture`
+fn asset_name(rel: &Path) -> String {
+    rel.to_string_lossy().replace("\\", "/")
+}
+
+/// Cube maps are loaded by the name `x_cubemap.png`, for the six faces `x_right.png`, ...
+fn add_cubemaps(manifest: &mut TextureManifest, compressed_ext: &str) {
+    let mut cubemaps = Vec::new();
+
+    for name in manifest.textures.keys() {
+        let path = Path::new(name);
+        let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+        let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("");
+
+        let prefix = match stem.rfind('_') {
+            Some(i) if &stem[i + 1..] == CUBEMAP_FACES[0] => &stem[..i],
+            _ => continue,
+        };
+
+        let face = |f: &str| asset_name(&path.with_file_name(format!("{}_{}.{}", prefix, f, ext)));
+        if CUBEMAP_FACES
+            .iter()
+            .all(|f| manifest.textures.contains_key(&face(f)))
+        {
+            let cubemap = path.with_file_name(format!("{}_cubemap.{}", prefix, ext));
+            let compressed = cubemap.with_extension(compressed_ext);
+            cubemaps.push((asset_name(&cubemap), asset_name(&compressed)));
+        }
+    }
+
+    manifest.textures.extend(cubemaps.into_iter());
+}
+
+/// Half size image, averaging 2x2 pixels
+fn downsample(img: &RgbaImage) -> RgbaImage {
+    let (w, h) = img.dimensions();
+    let (nw, nh) = ((w / 2).max(1), (h / 2).max(1));
+
+    RgbaImage::from_fn(nw, nh, |x, y| {
+        let mut sum = [0u32; 4];
+        for &(dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)].iter() {
+            let p = img.get_pixel((x * 2 + dx).min(w - 1), (y * 2 + dy).min(h - 1));
+            for c in 0..4 {
+                sum[c] += p.data[c] as u32;
+            }
+        }
+
+        image::Rgba([
+            ((sum[0] + 2) / 4) as u8,
+            ((sum[1] + 2) / 4) as u8,
+            ((sum[2] + 2) / 4) as u8,
+            ((sum[3] + 2) / 4) as u8,
+        ])
+    })
+}
+
+/// Encode to DXT1 blocks, or DXT5 blocks with `alpha`.
+/// Blocks of levels smaller than 4x4 repeat the edge pixels.
+fn encode(img: &RgbaImage, alpha: bool) -> Vec<u8> {
+    let (w, h) = img.dimensions();
+    let mut out = Vec::new();
+
+    for by in 0..(h + 3) / 4 {
+        for bx in 0..(w + 3) / 4 {
+            let mut block = [[0u8; 4]; 16];
+            for i in 0..16 {
+                let x = (bx * 4 + i as u32 % 4).min(w - 1);
+                let y = (by * 4 + i as u32 / 4).min(h - 1);
+                block[i] = img.get_pixel(x, y).data;
+            }
+
+            if alpha {
+                encode_alpha_block(&block, &mut out);
+            }
+            encode_color_block(&block, &mut out);
+        }
+    }
+
+    out
+}
+
+fn to_565(c: [i32; 3]) -> u16 {
+    let r = (c[0].max(0).min(255) * 31 + 127) / 255;
+    let g = (c[1].max(0).min(255) * 63 + 127) / 255;
+    let b = (c[2].max(0).min(255) * 31 + 127) / 255;
+    ((r << 11) | (g << 5) | b) as u16
+}
+
+fn from_565(c: u16) -> [i32; 3] {
+    let r = ((c >> 11) & 31) as i32;
+    let g = ((c >> 5) & 63) as i32;
+    let b = (c & 31) as i32;
+    [(r << 3) | (r >> 2), (g << 2) | (g >> 4), (b << 3) | (b >> 2)]
+}
+
+fn encode_color_block(block: &[[u8; 4]; 16], out: &mut Vec<u8>) {
+    let mut min = [255i32; 3];
+    let mut max = [0i32; 3];
+    let mut mean = [0i32; 3];
+    for p in block.iter() {
+        for c in 0..3 {
+            min[c] = min[c].min(p[c] as i32);
+            max[c] = max[c].max(p[c] as i32);
+            mean[c] += p[c] as i32;
+        }
+    }
+
+    // Pick the diagonal of the bounding box following the correlation of the channels to red
+    let mean = [mean[0] / 16, mean[1] / 16, mean[2] / 16];
+    let (mut cov_rg, mut cov_rb) = (0, 0);
+    for p in block.iter() {
+        let dr = p[0] as i32 - mean[0];
+        cov_rg += dr * (p[1] as i32 - mean[1]);
+        cov_rb += dr * (p[2] as i32 - mean[2]);
+    }
+    if cov_rg < 0 {
+        ::std::mem::swap(&mut min[1], &mut max[1]);
+    }
+    if cov_rb < 0 {
+        ::std::mem::swap(&mut min[2], &mut max[2]);
+    }
+
+    let mut c0 = to_565(max);
+    let mut c1 = to_565(min);
+    // c0 > c1 selects the four colors mode
+    if c0 < c1 {
+        ::std::mem::swap(&mut c0, &mut c1);
+    }
+
+    let mut indices = 0u32;
+    if c0 != c1 {
+        let p0 = from_565(c0);
+        let p1 = from_565(c1);
+        let palette = [
+            p0,
+            p1,
+            [
+                (2 * p0[0] + p1[0]) / 3,
+                (2 * p0[1] + p1[1]) / 3,
+                (2 * p0[2] + p1[2]) / 3,
+            ],
+            [
+                (p0[0] + 2 * p1[0]) / 3,
+                (p0[1] + 2 * p1[1]) / 3,
+                (p0[2] + 2 * p1[2]) / 3,
+            ],
+        ];
+
+        for (i, p) in block.iter().enumerate() {
+            let dist = |q: &[i32; 3]| {
+                (0..3)
+                    .map(|c| (p[c] as i32 - q[c]) * (p[c] as i32 - q[c]))
+                    .sum::<i32>()
+            };
+            let best = (0..4).min_by_key(|&k| dist(&palette[k])).unwrap_or(0);
+            indices |= (best as u32) << (i * 2);
+        }
+    }
+
+    out.extend_from_slice(&[c0 as u8, (c0 >> 8) as u8, c1 as u8, (c1 >> 8) as u8]);
+    out.extend_from_slice(&[
+        indices as u8,
+        (indices >> 8) as u8,
+        (indices >> 16) as u8,
+        (indices >> 24) as u8,
+    ]);
+}
+
+fn encode_alpha_block(block: &[[u8; 4]; 16], out: &mut Vec<u8>) {
+    let a0 = block.iter().map(|p| p[3]).max().unwrap_or(255) as i32;
+    let a1 = block.iter().map(|p| p[3]).min().unwrap_or(255) as i32;
+
+    let mut indices = 0u64;
+    if a0 != a1 {
+        // a0 > a1 selects the eight alphas mode
+        let mut palette = [a0, a1, 0, 0, 0, 0, 0, 0];
+        for k in 2..8 {
+            palette[k] = ((8 - k as i32) * a0 + (k as i32 - 1) * a1) / 7;
+        }
+
+        for (i, p) in block.iter().enumerate() {
+            let a = p[3] as i32;
+            let best = (0..8).min_by_key(|&k| (a - palette[k]).abs()).unwrap_or(0);
+            indices |= (best as u64) << (i * 3);
+        }
+    }
+
+    out.push(a0 as u8);
+    out.push(a1 as u8);
+    for i in 0..6 {
+        out.push((indices >> (i * 8)) as u8);
+    }
+}
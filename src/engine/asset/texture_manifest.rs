@@ -0,0 +1,35 @@
+use engine::asset::loader::{self, Loadable, Loader};
+use engine::asset::{AssetResult, File};
+
+use std::collections::BTreeMap;
+
+/// Texture manifest, written by the texture importer next to the imported assets.
+/// It maps the source textures to their compressed files :
+///
+/// ```json
+/// { "platform": "web",
+///   "textures": { "rust.png": "rust.dds", "sky_cubemap.png": "sky_cubemap.dds" } }
+/// ```
+///
+/// Load it with `AssetSystem::load_texture_manifest`, then `new_texture("rust.png")` loads
+/// `rust.dds`. In web, only load it when the device supports `WEBGL_compressed_texture_s3tc`,
+/// the source textures are kept for the other devices.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct TextureManifest {
+    /// Platform the textures were compressed for
+    #[serde(default)]
+    pub platform: String,
+    pub textures: BTreeMap<String, String>,
+}
+
+pub struct TextureManifestLoader {}
+
+impl Loader<TextureManifest> for TextureManifestLoader {
+    fn load<A>(_asys: A, mut file: Box<File>) -> AssetResult<TextureManifest> {
+        loader::read_json(&mut file)
+    }
+}
+
+impl Loadable for TextureManifest {
+    type Loader = TextureManifestLoader;
+}
@@ -9,6 +9,20 @@ varying vec3 vColor;
 varying vec2 vTextureCoord;
 uniform sampler2D uDiffuse;
 
+// High contrast theme, both are zero by default
+uniform vec4 uBackground;
+uniform float uContrast;
+
 void main(void) {
-    gl_FragColor = texture2D(uDiffuse, vec2(vTextureCoord.s, vTextureCoord.t));
+    vec4 color = texture2D(uDiffuse, vec2(vTextureCoord.s, vTextureCoord.t));
+
+    if (uContrast > 0.0) {
+        color.rgb = clamp((color.rgb - 0.5) * uContrast + 0.5, 0.0, 1.0);
+    }
+
+    if (uBackground.a > 0.0) {
+        color = vec4(mix(uBackground.rgb, color.rgb, color.a), max(color.a, uBackground.a));
+    }
+
+    gl_FragColor = color;
 }
@@ -0,0 +1,306 @@
+//! Subtitles and closed captions
+//!
+//! Caption tracks are timed lists of cues, keyed by the sound file or marker name which
+//! triggers them. `World` triggers the tracks of played sounds automatically, other
+//! events (e.g. timeline markers) can call `Captions::trigger` directly.
+
+use engine::asset::loader::{self, Loadable, Loader};
+use engine::asset::{AssetError, AssetResult, AssetSystem, File, Resource};
+use engine::imgui::{self, Metric, TextAlign};
+use engine::localization::Localization;
+
+use std::cmp::Ordering;
+use std::collections::{HashMap, VecDeque};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CaptionCue {
+    /// Start time in seconds, relative to the trigger of the track
+    #[serde(default)]
+    pub at: f32,
+    /// Display time in seconds
+    pub duration: f32,
+    /// Localization key (or raw text) of the speaker
+    #[serde(default)]
+    pub speaker: Option<String>,
+    /// Localization key (or raw text) of the line
+    pub text: String,
+    /// Description of non-speech sound (e.g. "[door creaks]"), only shown as closed caption
+    #[serde(default)]
+    pub sound_effect: bool,
+}
+
+/// Caption asset, a json file mapping sound file or marker names to cues :
+///
+/// ```json
+/// { "tracks": {
+///     "sounds/guard.wav": [
+///         { "at": 0.0, "duration": 2.5, "speaker": "npc.guard", "text": "guard.halt" },
+///         { "at": 2.5, "duration": 2.0, "speaker": "npc.guard", "text": "guard.who" }
+///     ],
+///     "door_open": [ { "duration": 1.5, "text": "sfx.door_creaks", "sound_effect": true } ]
+/// }}
+/// ```
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct CaptionDatabase {
+    pub tracks: HashMap<String, Vec<CaptionCue>>,
+}
+
+impl CaptionDatabase {
+    pub fn load(asys: &AssetSystem, filename: &str) -> Resource<CaptionDatabase> {
+        loader::load_json(asys, filename)
+    }
+}
+
+pub struct CaptionDatabaseLoader {}
+
+impl Loader<CaptionDatabase> for CaptionDatabaseLoader {
+    fn load<A>(_asys: A, mut file: Box<File>) -> AssetResult<CaptionDatabase> {
+        loader::read_json(&mut file)
+    }
+}
+
+impl Loadable for CaptionDatabase {
+    type Loader = CaptionDatabaseLoader;
+}
+
+#[derive(Debug, Clone)]
+pub struct CaptionSettings {
+    /// Show dialogue subtitles
+    pub subtitles: bool,
+    /// Show sound effect captions
+    pub closed_captions: bool,
+    pub speaker_labels: bool,
+    /// Text size multiplier
+    pub text_scale: f32,
+    /// Lines shown at the same time, others are queued
+    pub max_lines: usize,
+    /// Long lines are wrapped at this number of characters
+    pub wrap_width: usize,
+}
+
+impl Default for CaptionSettings {
+    fn default() -> CaptionSettings {
+        CaptionSettings {
+            subtitles: true,
+            closed_captions: false,
+            speaker_labels: true,
+            text_scale: 2.0,
+            max_lines: 2,
+            wrap_width: 48,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct CaptionLine {
+    speaker: Option<String>,
+    text: String,
+    remaining: f32,
+}
+
+struct PlayingTrack {
+    cues: Vec<CaptionCue>,
+    time: f32,
+    next: usize,
+}
+
+/// Runtime caption service, shows the current captions by imgui
+pub struct Captions {
+    pub settings: CaptionSettings,
+
+    localization: Localization,
+    tracks: HashMap<String, Vec<CaptionCue>>,
+    playing: Vec<PlayingTrack>,
+    shown: VecDeque<CaptionLine>,
+    queue: VecDeque<CaptionLine>,
+
+    pending: Vec<Resource<CaptionDatabase>>,
+}
+
+impl Captions {
+    pub fn new(localization: Localization) -> Captions {
+        Captions {
+            settings: CaptionSettings::default(),
+            localization,
+            tracks: HashMap::new(),
+            playing: Vec::new(),
+            shown: VecDeque::new(),
+            queue: VecDeque::new(),
+            pending: Vec::new(),
+        }
+    }
+
+    /// Load caption tracks from a json file, they will be added when the file is ready
+    pub fn load(&mut self, asys: &AssetSystem, filename: &str) {
+        self.pending.push(CaptionDatabase::load(asys, filename));
+    }
+
+    pub fn is_loading(&self) -> bool {
+        self.pending.len() > 0
+    }
+
+    /// Add the cues of a track, the cues without a valid time are dropped
+    pub fn add_track(&mut self, name: &str, mut cues: Vec<CaptionCue>) {
+        let count = cues.len();
+        cues.retain(|c| c.at.is_finite());
+        if cues.len() < count {
+            println!("Caption track {}: {} cues without a valid time", name, count - cues.len());
+        }
+
+        cues.sort_by(|a, b| a.at.partial_cmp(&b.at).unwrap_or(Ordering::Equal));
+        self.tracks.insert(name.to_owned(), cues);
+    }
+
+    pub fn has_track(&self, name: &str) -> bool {
+        self.tracks.contains_key(name)
+    }
+
+    /// Start the track of a sound or marker, return false if there is no such track
+    pub fn trigger(&mut self, name: &str) -> bool {
+        match self.tracks.get(name) {
+            Some(cues) => {
+                self.playing.push(PlayingTrack {
+                    cues: cues.clone(),
+                    time: 0.0,
+                    next: 0,
+                });
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Show a line immediately (or queue it), the texts are localization keys
+    pub fn show(&mut self, speaker: Option<&str>, text: &str, duration: f32) {
+        let line = CaptionLine {
+            speaker: speaker.map(|s| s.to_owned()),
+            text: text.to_owned(),
+            remaining: duration,
+        };
+
+        if self.shown.len() < self.settings.max_lines.max(1) {
+            self.shown.push_back(line);
+        } else {
+            self.queue.push_back(line);
+        }
+    }
+
+    /// Remove all shown, queued and playing captions
+    pub fn clear(&mut self) {
+        self.playing.clear();
+        self.shown.clear();
+        self.queue.clear();
+    }
+
+    /// The localized texts currently shown
+    pub fn current(&self) -> Vec<String> {
+        self.shown.iter().map(|l| self.format_line(l)).collect()
+    }
+
+    pub fn step(&mut self, dt: f32) {
+        self.load_pending();
+
+        let mut cues = Vec::new();
+        for track in self.playing.iter_mut() {
+            track.time += dt;
+
+            while track.next < track.cues.len() && track.cues[track.next].at <= track.time {
+                cues.push(track.cues[track.next].clone());
+                track.next += 1;
+            }
+        }
+        self.playing.retain(|t| t.next < t.cues.len());
+
+        for cue in cues.into_iter() {
+            let enabled = if cue.sound_effect {
+                self.settings.closed_captions
+            } else {
+                self.settings.subtitles
+            };
+
+            if enabled {
+                self.show(
+                    cue.speaker.as_ref().map(|s| s.as_str()),
+                    &cue.text,
+                    cue.duration,
+                );
+            }
+        }
+
+        for line in self.shown.iter_mut() {
+            line.remaining -= dt;
+        }
+        self.shown.retain(|l| l.remaining > 0.0);
+
+        while self.shown.len() < self.settings.max_lines.max(1) {
+            match self.queue.pop_front() {
+                Some(line) => self.shown.push_back(line),
+                None => break,
+            }
+        }
+
+        self.render();
+    }
+
+    fn load_pending(&mut self) {
+        let pending: Vec<_> = self.pending.drain(0..).collect();
+
+        for res in pending.into_iter() {
+            match res.try_into() {
+                Ok(db) => for (name, cues) in db.tracks.into_iter() {
+                    self.add_track(&name, cues);
+                },
+                Err(AssetError::NotReady) => self.pending.push(res),
+                Err(e) => println!("Fail to load captions, reason: {:?}", e),
+            }
+        }
+    }
+
+    fn format_line(&self, line: &CaptionLine) -> String {
+        let text = self.localization.tr(&line.text);
+
+        match line.speaker {
+            Some(ref speaker) if self.settings.speaker_labels => {
+                format!("{}: {}", self.localization.tr(speaker), text)
+            }
+            _ => text,
+        }
+    }
+
+    fn render(&self) {
+        if self.shown.len() == 0 {
+            return;
+        }
+
+        let text = self.shown
+            .iter()
+            .map(|l| wrap_text(&self.format_line(l), self.settings.wrap_width))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        imgui::pivot((0.5, 1.0));
+        imgui::text_align(TextAlign::Center);
+        imgui::text_scale(self.settings.text_scale);
+        imgui::label(Metric::Native(0.5, 1.0) + Metric::Pixel(0.0, -24.0), &text);
+    }
+}
+
+fn wrap_text(s: &str, width: usize) -> String {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+
+    for word in s.split_whitespace() {
+        if line.len() > 0 && line.len() + word.len() + 1 > width {
+            lines.push(line);
+            line = String::new();
+        }
+
+        if line.len() > 0 {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    lines.push(line);
+
+    lines.join("\n")
+}
@@ -1,9 +1,12 @@
 use std::any::{Any, TypeId};
 use std::cell::{Ref, RefCell};
 use std::collections::HashMap;
+use std::mem::size_of;
 use std::rc::Rc;
 use typed_arena::Arena;
 
+use engine::diagnostics::ComponentStats;
+
 struct ComponentContainer<T> {
     components: Arena<T>,
     com_map: RefCell<HashMap<u64, *mut T>>,
@@ -62,8 +65,28 @@ impl<T> ComponentContainer<T> {
     }
 }
 
+trait AnyContainer {
+    fn as_any(&self) -> &Any;
+
+    /// (live, free, bytes)
+    fn stats(&self) -> (usize, usize, usize);
+}
+
+impl<T: 'static> AnyContainer for Rc<ComponentContainer<T>> {
+    fn as_any(&self) -> &Any {
+        self
+    }
+
+    fn stats(&self) -> (usize, usize, usize) {
+        let live = self.com_map.borrow().len();
+        let free = self.free_list.borrow().len();
+
+        (live, free, (live + free) * size_of::<T>())
+    }
+}
+
 pub struct ComponentArena {
-    arenas: RefCell<HashMap<TypeId, Box<Any>>>,
+    arenas: RefCell<HashMap<TypeId, Box<AnyContainer>>>,
 }
 
 impl ComponentArena {
@@ -96,6 +119,7 @@ impl ComponentArena {
             let container = a.get(&typeid).unwrap();
 
             container
+                .as_any()
                 .downcast_ref::<Rc<ComponentContainer<T>>>()
                 .unwrap()
         }).clone()
@@ -113,6 +137,20 @@ impl ComponentArena {
         self.container().as_vec()
     }
 
+    pub fn stats(&self) -> ComponentStats {
+        self.arenas
+            .borrow()
+            .values()
+            .fold(ComponentStats::default(), |mut acc, c| {
+                let (live, free, bytes) = c.stats();
+                acc.types += 1;
+                acc.live += live;
+                acc.free += free;
+                acc.bytes += bytes;
+                acc
+            })
+    }
+
     pub fn new() -> ComponentArena {
         ComponentArena {
             arenas: Default::default(),
@@ -1,11 +1,13 @@
 mod component_arena;
 mod game_object;
 mod math;
+mod name;
 mod scene_tree;
 
 pub use self::component_arena::ComponentArena;
 pub use self::game_object::{Component, ComponentBased, ComponentType, GameObject, IntoComponentPtr};
 pub use self::math::*;
+pub use self::name::Name;
 pub use self::scene_tree::{ComponentEvent, SceneTree};
 
 pub mod internal {
@@ -0,0 +1,10 @@
+/// Name of a game object, e.g. to find it from a script or in the reports of
+/// `World::validate_scene`
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct Name(pub String);
+
+impl Name {
+    pub fn new(name: &str) -> Name {
+        Name(name.to_owned())
+    }
+}
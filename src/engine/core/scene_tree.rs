@@ -283,6 +283,10 @@ impl SceneTree {
         self.nodes.borrow().len()
     }
 
+    pub fn contains(&self, node_id: u64) -> bool {
+        self.nodes.borrow().contains_key(&node_id)
+    }
+
     pub fn notifiy_component(&self, evt: ComponentEvent, node_id: u64, c: Arc<Component>) {
         let go = { self.nodes.borrow().get(&node_id).unwrap().go.clone() };
 
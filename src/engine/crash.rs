@@ -0,0 +1,292 @@
+//! Crash reports
+//!
+//! `install` sets a panic hook which collects the panic message, a backtrace (the js stack
+//! in web), the last lines logged by `crash::log` and a summary of the scene into a
+//! `CrashReport`, for the panics of all the threads. The report is printed, saved (to a file
+//! in native, to `localStorage` in web) and, in web, shown in an overlay on top of the frozen
+//! canvas.
+//!
+//! ```ignore
+//! crash::install(CrashHandler::default().with_reporter(|report| upload(&report.to_text())));
+//! crash::log("level 2 loaded");
+//! ```
+
+use std::collections::VecDeque;
+use std::panic::{self, PanicInfo};
+use std::sync::{Mutex, MutexGuard};
+
+#[derive(Debug, Clone, Default)]
+pub struct CrashReport {
+    pub message: String,
+    /// file:line of the panic
+    pub location: Option<String>,
+    pub backtrace: Option<String>,
+    /// Last logged lines, oldest first
+    pub log: Vec<String>,
+    pub scene: String,
+}
+
+impl CrashReport {
+    pub fn to_text(&self) -> String {
+        let mut s = format!("panic: {}\n", self.message);
+
+        if let Some(ref location) = self.location {
+            s += &format!("at: {}\n", location);
+        }
+        if self.scene.len() > 0 {
+            s += &format!("\nscene:\n{}\n", self.scene);
+        }
+        if self.log.len() > 0 {
+            s += &format!("\nlog:\n{}\n", self.log.join("\n"));
+        }
+        if let Some(ref backtrace) = self.backtrace {
+            s += &format!("\nbacktrace:\n{}\n", backtrace);
+        }
+
+        s
+    }
+}
+
+pub struct CrashHandler {
+    /// Number of log lines kept for the report
+    pub max_log_lines: usize,
+    /// File name (native) or `localStorage` key (web) of the saved report
+    pub dump_name: Option<String>,
+    /// Show the report over the canvas (web only)
+    pub overlay: bool,
+    reporter: Option<Box<Fn(&CrashReport) + Send + Sync>>,
+}
+
+impl Default for CrashHandler {
+    fn default() -> CrashHandler {
+        CrashHandler {
+            max_log_lines: 50,
+            dump_name: Some("unrust_crash.txt".to_owned()),
+            overlay: true,
+            reporter: None,
+        }
+    }
+}
+
+impl CrashHandler {
+    pub fn with_max_log_lines(mut self, n: usize) -> CrashHandler {
+        self.max_log_lines = n;
+        self
+    }
+
+    pub fn with_dump_name(mut self, name: Option<&str>) -> CrashHandler {
+        self.dump_name = name.map(|s| s.to_owned());
+        self
+    }
+
+    pub fn with_overlay(mut self, overlay: bool) -> CrashHandler {
+        self.overlay = overlay;
+        self
+    }
+
+    /// Called with the report after it is saved, e.g. to send it to a server. It may be
+    /// called from any thread.
+    pub fn with_reporter<F>(mut self, f: F) -> CrashHandler
+    where
+        F: Fn(&CrashReport) + Send + Sync + 'static,
+    {
+        self.reporter = Some(Box::new(f));
+        self
+    }
+}
+
+#[derive(Default)]
+struct CrashState {
+    handler: Option<CrashHandler>,
+    log: VecDeque<String>,
+    scene: String,
+    reporting: bool,
+}
+
+lazy_static! {
+    // Shared by all the threads, the panic hook is global
+    static ref STATE: Mutex<CrashState> = Mutex::new(CrashState::default());
+}
+
+/// The state, even after a panic while it was locked
+fn state() -> MutexGuard<'static, CrashState> {
+    STATE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Install the panic hook, the previous hook still runs first
+pub fn install(handler: CrashHandler) {
+    let first = {
+        let mut s = state();
+        let first = s.handler.is_none();
+        s.handler = Some(handler);
+        first
+    };
+
+    if !first {
+        return;
+    }
+
+    let prev = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        prev(info);
+        handle_panic(info);
+    }));
+}
+
+pub fn is_installed() -> bool {
+    state().handler.is_some()
+}
+
+/// Keep a line for crash reports, it is printed too
+pub fn log(line: &str) {
+    println!("{}", line);
+
+    let mut s = state();
+    let max = match s.handler {
+        Some(ref h) => h.max_log_lines,
+        None => return,
+    };
+
+    s.log.push_back(line.to_owned());
+    while s.log.len() > max {
+        s.log.pop_front();
+    }
+}
+
+/// Describe the current scene, `World` refreshes it with its diagnostics
+pub fn set_scene_summary(summary: String) {
+    state().scene = summary;
+}
+
+fn payload_message(info: &PanicInfo) -> String {
+    let payload = info.payload();
+
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "unknown panic".to_owned()
+    }
+}
+
+fn handle_panic(info: &PanicInfo) {
+    // A panic inside the handler (e.g. while the state is locked) must not recurse, and
+    // another thread panicking at the same time is not reported
+    let handler = match STATE.try_lock() {
+        Ok(mut s) => if s.reporting {
+            None
+        } else {
+            s.reporting = true;
+            s.handler.take()
+        },
+        Err(_) => None,
+    };
+
+    let handler = match handler {
+        Some(h) => h,
+        None => return,
+    };
+
+    let backtrace = platform::backtrace();
+    let report = {
+        let s = state();
+        CrashReport {
+            message: payload_message(info),
+            location: info
+                .location()
+                .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column())),
+            backtrace,
+            log: s.log.iter().cloned().collect(),
+            scene: s.scene.clone(),
+        }
+    };
+
+    let text = report.to_text();
+    platform::print(&text);
+
+    if let Some(ref name) = handler.dump_name {
+        platform::save(name, &text);
+    }
+
+    if handler.overlay {
+        platform::show_overlay(&text);
+    }
+
+    if let Some(ref reporter) = handler.reporter {
+        reporter(&report);
+    }
+
+    let mut s = state();
+    s.handler = Some(handler);
+    s.reporting = false;
+}
+
+#[cfg(target_arch = "wasm32")]
+mod platform {
+    use stdweb::unstable::TryInto;
+
+    pub fn backtrace() -> Option<String> {
+        let stack = js! {
+            return new Error().stack || null;
+        };
+        stack.try_into().ok()
+    }
+
+    pub fn print(text: &str) {
+        js! { @(no_return)
+            console.error(@{text});
+        }
+    }
+
+    pub fn save(name: &str, text: &str) {
+        js! { @(no_return)
+            try {
+                window.localStorage.setItem(@{name}, @{text});
+            } catch (e) {
+                console.log("Fail to save crash report: " + e);
+            }
+        }
+    }
+
+    pub fn show_overlay(text: &str) {
+        js! { @(no_return)
+            var div = document.getElementById("unrust-crash");
+            if (!div) {
+                div = document.createElement("div");
+                div.id = "unrust-crash";
+                div.style.cssText = "position:fixed;left:0;top:0;right:0;bottom:0;z-index:10000;" +
+                    "overflow:auto;padding:16px;background:rgba(32,0,0,0.92);color:#fff;" +
+                    "font:13px monospace;white-space:pre-wrap;";
+                document.body.appendChild(div);
+            }
+            div.textContent = "The application has crashed.\n\n" + @{text};
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod platform {
+    use backtrace::Backtrace;
+    use std::fs::File;
+    use std::io::Write;
+
+    pub fn backtrace() -> Option<String> {
+        Some(format!("{:?}", Backtrace::new()))
+    }
+
+    pub fn print(text: &str) {
+        eprintln!("{}", text);
+    }
+
+    pub fn save(name: &str, text: &str) {
+        let result = File::create(name).and_then(|mut f| f.write_all(text.as_bytes()));
+
+        match result {
+            Ok(_) => eprintln!("Crash report saved to {}", name),
+            Err(e) => eprintln!("Fail to save crash report {}, reason: {:?}", name, e),
+        }
+    }
+
+    pub fn show_overlay(_text: &str) {}
+}
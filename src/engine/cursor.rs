@@ -0,0 +1,205 @@
+//! Cursor drawn by the engine, a sprite following the pointer in the UI pass.
+//!
+//! Used when the hardware cursor is hidden or locked (the game moves the cursor itself, e.g.
+//! with a gamepad), or for a stylized look. Each state has its own sprite, the states without
+//! one use the `Normal` sprite :
+//!
+//! ```ignore
+//! world.cursor.set_sprite(CursorState::Normal, CursorSprite::new("ui/arrow.png", (32.0, 32.0)));
+//! world.cursor.set_sprite(
+//!     CursorState::Hover,
+//!     CursorSprite::new("ui/hand.png", (32.0, 32.0)).with_hotspot((10.0, 2.0)),
+//! );
+//! world.cursor.set_enabled(true);
+//!
+//! // In the UI code, each frame
+//! world.cursor.set_state(if hovered { CursorState::Hover } else { CursorState::Normal });
+//! ```
+
+use engine::asset::AssetSystem;
+use engine::imgui::{self, Metric};
+use uni_app::AppEvent;
+
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CursorState {
+    Normal,
+    /// Over an interactive element
+    Hover,
+    /// Dragging an element
+    Drag,
+    /// Over a disabled element
+    Disabled,
+    Busy,
+}
+
+impl Default for CursorState {
+    fn default() -> CursorState {
+        CursorState::Normal
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CursorSprite {
+    /// Texture name
+    pub texture: String,
+    /// Size in pixels
+    pub size: (f32, f32),
+    /// Point of the sprite at the pointer, in pixels from its top-left corner
+    pub hotspot: (f32, f32),
+}
+
+impl CursorSprite {
+    pub fn new(texture: &str, size: (f32, f32)) -> CursorSprite {
+        CursorSprite {
+            texture: texture.to_owned(),
+            size,
+            hotspot: (0.0, 0.0),
+        }
+    }
+
+    pub fn with_hotspot(mut self, hotspot: (f32, f32)) -> CursorSprite {
+        self.hotspot = hotspot;
+        self
+    }
+}
+
+pub struct Cursor {
+    /// Shown while enabled, e.g. hidden during gameplay and shown in menus
+    pub visible: bool,
+    pub state: CursorState,
+
+    enabled: bool,
+    /// Css selector of the canvas on web
+    canvas: String,
+    sprites: HashMap<CursorState, CursorSprite>,
+    /// Position in pixels of the window, without the hidpi factor
+    position: (f32, f32),
+}
+
+impl Default for Cursor {
+    fn default() -> Cursor {
+        Cursor::new()
+    }
+}
+
+impl Cursor {
+    pub fn new() -> Cursor {
+        Cursor {
+            visible: true,
+            state: CursorState::Normal,
+            enabled: false,
+            canvas: "canvas".to_owned(),
+            sprites: HashMap::new(),
+            position: (0.0, 0.0),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Draw the cursor in the engine, the hardware cursor is hidden over the canvas
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        set_hardware_cursor_visible(&self.canvas, !enabled);
+    }
+
+    /// The canvas of the world on web, see `WorldBuilder::with_canvas`
+    pub fn set_canvas(&mut self, selector: &str) {
+        self.canvas = selector.to_owned();
+    }
+
+    pub fn set_sprite(&mut self, state: CursorState, sprite: CursorSprite) {
+        self.sprites.insert(state, sprite);
+    }
+
+    pub fn remove_sprite(&mut self, state: CursorState) {
+        self.sprites.remove(&state);
+    }
+
+    /// Sprite drawn for a state, the `Normal` sprite for the states without one
+    pub fn sprite(&self, state: CursorState) -> Option<&CursorSprite> {
+        self.sprites
+            .get(&state)
+            .or_else(|| self.sprites.get(&CursorState::Normal))
+    }
+
+    pub fn set_state(&mut self, state: CursorState) {
+        self.state = state;
+    }
+
+    pub fn position(&self) -> (f32, f32) {
+        self.position
+    }
+
+    /// Move the cursor, e.g. a virtual cursor driven by a gamepad while the pointer is locked
+    pub fn set_position(&mut self, position: (f32, f32)) {
+        self.position = position;
+    }
+
+    /// Move the cursor by a delta, kept inside the window of size `bounds`
+    pub fn move_by(&mut self, delta: (f32, f32), bounds: (f32, f32)) {
+        self.position = (
+            (self.position.0 + delta.0).max(0.0).min(bounds.0),
+            (self.position.1 + delta.1).max(0.0).min(bounds.1),
+        );
+    }
+
+    /// Follow the pointer
+    pub fn step(&mut self, events: &[AppEvent]) {
+        for evt in events.iter() {
+            if let AppEvent::MousePos((x, y)) = *evt {
+                self.position = (x as f32, y as f32);
+            }
+        }
+    }
+
+    /// Draw the sprite of the state, `screen` the window size without the hidpi factor.
+    /// Called after the other UI so the cursor is on top.
+    pub fn render(&self, asys: &AssetSystem, screen: (f32, f32)) {
+        if !self.enabled || !self.visible || screen.0 <= 0.0 || screen.1 <= 0.0 {
+            return;
+        }
+
+        let sprite = match self.sprite(self.state) {
+            Some(sprite) => sprite,
+            None => return,
+        };
+
+        if sprite.size.0 <= 0.0 || sprite.size.1 <= 0.0 {
+            return;
+        }
+
+        // Placed by the hotspot, without changing the pivot of the UI drawn after
+        let saved_pivot = imgui::saved_pivot();
+        imgui::pivot((
+            sprite.hotspot.0 / sprite.size.0,
+            sprite.hotspot.1 / sprite.size.1,
+        ));
+        imgui::image(
+            Metric::Native(self.position.0 / screen.0, self.position.1 / screen.1),
+            Metric::Pixel(sprite.size.0, sprite.size.1),
+            asys.new_texture(&sprite.texture),
+        );
+        imgui::restore_pivot(saved_pivot);
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn set_hardware_cursor_visible(canvas: &str, visible: bool) {
+    let cursor = if visible { "auto" } else { "none" };
+
+    js! { @(no_return)
+        var canvas = document.querySelector(@{canvas});
+        if (canvas) {
+            canvas.style.cursor = @{cursor};
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn set_hardware_cursor_visible(_canvas: &str, _visible: bool) {
+    // The native window of uni-app has no cursor option, the hardware cursor stays visible
+}
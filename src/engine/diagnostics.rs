@@ -0,0 +1,215 @@
+//! Memory introspection
+//!
+//! `Diagnostics` is a snapshot of counts and approximate sizes of engine-owned allocations,
+//! `World::diagnostics` refreshes it periodically. Sizes are estimations from the data
+//! uploaded or allocated, not what the driver or allocator really uses.
+//!
+//! `HitchDetector` keeps a `HitchReport` of the frames which exceed a time budget.
+
+use engine::asset::AssetSystem;
+use engine::engine::{Engine, EngineStats};
+use engine::profiler::{self, ScopeTiming};
+use uni_app::{now, AppEvent};
+
+use std::cell::Cell;
+use std::collections::VecDeque;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GpuStats {
+    pub textures: usize,
+    pub texture_bytes: usize,
+    pub buffers: usize,
+    pub buffer_bytes: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AssetStats {
+    pub textures: usize,
+    pub mesh_buffers: usize,
+    pub programs: usize,
+    pub loading_files: usize,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ComponentStats {
+    /// Number of component types with an arena
+    pub types: usize,
+    pub live: usize,
+    /// Slots of removed components, reused by new ones
+    pub free: usize,
+    /// Size of all slots
+    pub bytes: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Diagnostics {
+    pub game_objects: usize,
+    pub components: ComponentStats,
+    pub assets: AssetStats,
+    pub gpu: GpuStats,
+    /// Time of the snapshot, in seconds
+    pub time: f64,
+}
+
+impl Diagnostics {
+    pub fn collect<A: AssetSystem>(engine: &Engine<A>, time: f64) -> Diagnostics {
+        Diagnostics {
+            game_objects: engine
+                .objects
+                .iter()
+                .filter(|o| o.upgrade().is_some())
+                .count(),
+            components: engine.arena.stats(),
+            assets: engine.asset_system.stats(),
+            gpu: gpu_stats(),
+            time,
+        }
+    }
+}
+
+thread_local!(static GPU: Cell<GpuStats> = Cell::new(GpuStats::default()));
+
+pub fn gpu_stats() -> GpuStats {
+    GPU.with(|g| g.get())
+}
+
+pub(crate) fn track_texture(bytes: usize, alloc: bool) {
+    GPU.with(|g| {
+        let mut s = g.get();
+        if alloc {
+            s.textures += 1;
+            s.texture_bytes += bytes;
+        } else {
+            s.textures = s.textures.saturating_sub(1);
+            s.texture_bytes = s.texture_bytes.saturating_sub(bytes);
+        }
+        g.set(s);
+    });
+}
+
+pub(crate) fn track_buffers(count: usize, bytes: usize, alloc: bool) {
+    GPU.with(|g| {
+        let mut s = g.get();
+        if alloc {
+            s.buffers += count;
+            s.buffer_bytes += bytes;
+        } else {
+            s.buffers = s.buffers.saturating_sub(count);
+            s.buffer_bytes = s.buffer_bytes.saturating_sub(bytes);
+        }
+        g.set(s);
+    });
+}
+
+/// A frame which took longer than the budget
+#[derive(Debug, Clone)]
+pub struct HitchReport {
+    /// Start of the frame, in seconds
+    pub time: f64,
+    pub frame_time: f64,
+    pub budget: f64,
+    /// Profiler scopes of the frame
+    pub scopes: Vec<ScopeTiming>,
+    pub stats: EngineStats,
+    /// The last events received, up to this frame
+    pub events: Vec<String>,
+}
+
+pub struct HitchDetector {
+    /// Maximum frame time in seconds, None to disable
+    budget: Option<f64>,
+    pub max_reports: usize,
+    pub max_events: usize,
+
+    frame_start: f64,
+    events: VecDeque<String>,
+    reports: Vec<HitchReport>,
+}
+
+impl Default for HitchDetector {
+    fn default() -> HitchDetector {
+        HitchDetector {
+            budget: None,
+            max_reports: 16,
+            max_events: 32,
+            frame_start: 0.0,
+            events: VecDeque::new(),
+            reports: Vec::new(),
+        }
+    }
+}
+
+impl HitchDetector {
+    pub fn new() -> HitchDetector {
+        Default::default()
+    }
+
+    /// Scopes are recorded by the profiler while a budget is set
+    pub fn set_budget(&mut self, budget: Option<f64>) {
+        self.budget = budget;
+        profiler::set_recording(budget.is_some());
+    }
+
+    pub fn budget(&self) -> Option<f64> {
+        self.budget
+    }
+
+    pub fn begin_frame(&mut self) {
+        self.frame_start = now();
+    }
+
+    pub fn record_events(&mut self, events: &[AppEvent]) {
+        if self.budget.is_none() {
+            return;
+        }
+
+        for evt in events.iter() {
+            self.events.push_back(format!("{:?}", evt));
+        }
+        while self.events.len() > self.max_events {
+            self.events.pop_front();
+        }
+    }
+
+    pub fn end_frame(&mut self, stats: EngineStats) {
+        let budget = match self.budget {
+            Some(budget) => budget,
+            None => return,
+        };
+
+        let scopes = profiler::take_recorded();
+        let frame_time = now() - self.frame_start;
+        if frame_time <= budget {
+            return;
+        }
+
+        println!(
+            "Hitch: frame took {:.2}ms, budget {:.2}ms",
+            frame_time * 1000.0,
+            budget * 1000.0
+        );
+
+        self.reports.push(HitchReport {
+            time: self.frame_start,
+            frame_time,
+            budget,
+            scopes,
+            stats,
+            events: self.events.iter().cloned().collect(),
+        });
+
+        if self.reports.len() > self.max_reports {
+            let n = self.reports.len() - self.max_reports;
+            self.reports.drain(0..n);
+        }
+    }
+
+    /// The latest reports, oldest first
+    pub fn reports(&self) -> &[HitchReport] {
+        &self.reports
+    }
+
+    pub fn take_reports(&mut self) -> Vec<HitchReport> {
+        self.reports.drain(0..).collect()
+    }
+}
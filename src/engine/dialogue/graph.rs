@@ -0,0 +1,202 @@
+use engine::asset::loader::{self, Loadable, Loader};
+use engine::asset::{AssetResult, AssetSystem, File, Resource};
+
+use std::collections::HashMap;
+
+/// A value stored in dialogue variables
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum DialogueValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+}
+
+pub type DialogueVariables = HashMap<String, DialogueValue>;
+
+impl DialogueValue {
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            &DialogueValue::Int(i) => Some(i as f64),
+            &DialogueValue::Float(f) => Some(f),
+            _ => None,
+        }
+    }
+
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            &DialogueValue::Bool(b) => b,
+            &DialogueValue::Int(i) => i != 0,
+            &DialogueValue::Float(f) => f != 0.0,
+            &DialogueValue::Str(ref s) => !s.is_empty(),
+        }
+    }
+
+    /// Parse a literal used in conditions: `true`, `false`, numbers or (quoted) strings
+    pub fn parse(s: &str) -> DialogueValue {
+        let s = s.trim();
+        match s {
+            "true" => return DialogueValue::Bool(true),
+            "false" => return DialogueValue::Bool(false),
+            _ => (),
+        }
+
+        if let Ok(i) = s.parse::<i64>() {
+            return DialogueValue::Int(i);
+        }
+
+        if let Ok(f) = s.parse::<f64>() {
+            return DialogueValue::Float(f);
+        }
+
+        DialogueValue::Str(s.trim_matches('"').to_owned())
+    }
+}
+
+impl From<bool> for DialogueValue {
+    fn from(b: bool) -> DialogueValue {
+        DialogueValue::Bool(b)
+    }
+}
+
+impl From<i64> for DialogueValue {
+    fn from(i: i64) -> DialogueValue {
+        DialogueValue::Int(i)
+    }
+}
+
+impl From<f64> for DialogueValue {
+    fn from(f: f64) -> DialogueValue {
+        DialogueValue::Float(f)
+    }
+}
+
+impl<'a> From<&'a str> for DialogueValue {
+    fn from(s: &'a str) -> DialogueValue {
+        DialogueValue::Str(s.to_owned())
+    }
+}
+
+/// Evaluate a condition expression against variables.
+///
+/// Supported forms, joined by `&&` :
+///     `flag`, `!flag`, `name == value`, `name != value`,
+///     `name > value`, `name >= value`, `name < value`, `name <= value`
+///
+/// A missing variable is treated as false.
+pub fn eval_condition(expr: &str, vars: &DialogueVariables) -> bool {
+    expr.split("&&").all(|clause| eval_clause(clause.trim(), vars))
+}
+
+fn eval_clause(clause: &str, vars: &DialogueVariables) -> bool {
+    if clause.is_empty() {
+        return true;
+    }
+
+    // two chars operators must be tested first
+    for op in ["==", "!=", ">=", "<=", ">", "<"].iter() {
+        if let Some(pos) = clause.find(op) {
+            let name = clause[..pos].trim();
+            let rhs = DialogueValue::parse(&clause[pos + op.len()..]);
+
+            let lhs = match vars.get(name) {
+                Some(v) => v,
+                None => return *op == "!=",
+            };
+
+            return match (lhs.as_f64(), rhs.as_f64()) {
+                (Some(a), Some(b)) => match *op {
+                    "==" => a == b,
+                    "!=" => a != b,
+                    ">=" => a >= b,
+                    "<=" => a <= b,
+                    ">" => a > b,
+                    _ => a < b,
+                },
+                _ => match *op {
+                    "==" => *lhs == rhs,
+                    "!=" => *lhs != rhs,
+                    _ => false,
+                },
+            };
+        }
+    }
+
+    let is_truthy = |name: &str| vars.get(name).map_or(false, |v| v.is_truthy());
+
+    if clause.starts_with("!") {
+        return !is_truthy(clause[1..].trim());
+    }
+
+    is_truthy(clause)
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DialogueChoice {
+    /// Localization key (or raw text) of this choice
+    pub text: String,
+    #[serde(default)]
+    pub next: Option<String>,
+    /// Choice is hidden if the condition is not met
+    #[serde(default)]
+    pub condition: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DialogueBranch {
+    pub condition: String,
+    pub next: String,
+}
+
+/// A node in the dialogue graph.
+///
+/// When entered, the node applies `set` and raises `events` first,
+/// then jumps to the first branch which condition is met.
+/// Otherwise the `text` line is shown, followed by the `choices` or `next` node.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct DialogueNode {
+    #[serde(default)]
+    pub speaker: Option<String>,
+    #[serde(default)]
+    pub text: Option<String>,
+    #[serde(default)]
+    pub next: Option<String>,
+    #[serde(default)]
+    pub choices: Vec<DialogueChoice>,
+    #[serde(default)]
+    pub branches: Vec<DialogueBranch>,
+    #[serde(default)]
+    pub events: Vec<String>,
+    #[serde(default)]
+    pub set: DialogueVariables,
+}
+
+/// Dialogue asset, a graph of named nodes
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DialogueGraph {
+    pub start: String,
+    pub nodes: HashMap<String, DialogueNode>,
+}
+
+impl DialogueGraph {
+    pub fn load(asys: &AssetSystem, filename: &str) -> Resource<DialogueGraph> {
+        loader::load_json(asys, filename)
+    }
+
+    pub fn node(&self, name: &str) -> Option<&DialogueNode> {
+        self.nodes.get(name)
+    }
+}
+
+pub struct DialogueGraphLoader {}
+
+impl Loader<DialogueGraph> for DialogueGraphLoader {
+    fn load<A>(_asys: A, mut file: Box<File>) -> AssetResult<DialogueGraph> {
+        loader::read_json(&mut file)
+    }
+}
+
+impl Loadable for DialogueGraph {
+    type Loader = DialogueGraphLoader;
+}
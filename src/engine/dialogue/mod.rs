@@ -0,0 +1,30 @@
+//! Dialogue system
+//!
+//! Dialogues are json assets describing a graph of nodes :
+//!
+//! ```json
+//! {
+//!     "start": "hello",
+//!     "nodes": {
+//!         "hello": { "speaker": "npc.name", "text": "dlg.hello", "next": "ask" },
+//!         "ask": {
+//!             "text": "dlg.ask",
+//!             "choices": [
+//!                 { "text": "dlg.yes", "next": "yes" },
+//!                 { "text": "dlg.pay", "next": "yes", "condition": "gold >= 10" }
+//!             ]
+//!         },
+//!         "yes": { "text": "dlg.thanks", "events": ["give_key"], "set": { "has_key": true } }
+//!     }
+//! }
+//! ```
+//!
+//! All texts are localization keys, which are translated by the `Localization` given to
+//! the `DialoguePlayer`.
+
+mod graph;
+mod player;
+
+pub use self::graph::{eval_condition, DialogueBranch, DialogueChoice, DialogueGraph,
+                      DialogueNode, DialogueValue, DialogueVariables};
+pub use self::player::{DialogueEvent, DialoguePlayer};
@@ -0,0 +1,217 @@
+use engine::localization::Localization;
+
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+use super::graph::{eval_condition, DialogueGraph, DialogueValue, DialogueVariables};
+
+// Prevent bad graphs (e.g. branches pointing to each other) from hanging the game
+const MAX_NODE_JUMPS: usize = 1024;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DialogueEvent {
+    /// A line should be shown, text is already localized
+    Line {
+        node: String,
+        speaker: Option<String>,
+        text: String,
+    },
+    /// Choices should be shown, (choice index, localized text)
+    Choices(Vec<(usize, String)>),
+    /// A custom event named in the graph
+    Event(String),
+    VariableChanged(String, DialogueValue),
+    End,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum PlayerState {
+    Idle,
+    WaitAdvance(Option<String>),
+    WaitChoice(Vec<(usize, Option<String>)>),
+}
+
+/// Runtime player of a dialogue graph.
+///
+/// The player does not render anything, UI should consume the events by `poll_events`
+/// and call `advance` or `choose` in response.
+pub struct DialoguePlayer {
+    pub variables: DialogueVariables,
+
+    graph: Rc<DialogueGraph>,
+    localization: Localization,
+    state: PlayerState,
+    current: Option<String>,
+    events: VecDeque<DialogueEvent>,
+}
+
+impl DialoguePlayer {
+    pub fn new(graph: Rc<DialogueGraph>, localization: Localization) -> DialoguePlayer {
+        DialoguePlayer {
+            variables: DialogueVariables::new(),
+            graph,
+            localization,
+            state: PlayerState::Idle,
+            current: None,
+            events: VecDeque::new(),
+        }
+    }
+
+    pub fn graph(&self) -> &Rc<DialogueGraph> {
+        &self.graph
+    }
+
+    pub fn start(&mut self) {
+        let start = self.graph.start.clone();
+        self.start_at(&start);
+    }
+
+    pub fn start_at(&mut self, node: &str) {
+        self.enter(Some(node.to_owned()));
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.state != PlayerState::Idle
+    }
+
+    pub fn is_waiting_choice(&self) -> bool {
+        match self.state {
+            PlayerState::WaitChoice(_) => true,
+            _ => false,
+        }
+    }
+
+    /// The name of the current node
+    pub fn current_node(&self) -> Option<&str> {
+        self.current.as_ref().map(|s| s.as_str())
+    }
+
+    /// Continue to next node after a line, ignored while waiting for a choice
+    pub fn advance(&mut self) {
+        if let PlayerState::WaitAdvance(next) = self.state.clone() {
+            self.enter(next);
+        }
+    }
+
+    /// Pick a choice by the index given in `DialogueEvent::Choices`
+    pub fn choose(&mut self, index: usize) {
+        let next = match self.state {
+            PlayerState::WaitChoice(ref choices) => choices
+                .iter()
+                .find(|&&(i, _)| i == index)
+                .map(|&(_, ref next)| next.clone()),
+            _ => None,
+        };
+
+        if let Some(next) = next {
+            self.enter(next);
+        }
+    }
+
+    /// Stop the dialogue immediately
+    pub fn stop(&mut self) {
+        if self.is_running() {
+            self.finish();
+        }
+    }
+
+    pub fn set_variable<T: Into<DialogueValue>>(&mut self, name: &str, value: T) {
+        let value = value.into();
+        self.variables.insert(name.to_owned(), value.clone());
+        self.events
+            .push_back(DialogueEvent::VariableChanged(name.to_owned(), value));
+    }
+
+    pub fn poll_events(&mut self) -> Vec<DialogueEvent> {
+        self.events.drain(..).collect()
+    }
+
+    fn finish(&mut self) {
+        self.state = PlayerState::Idle;
+        self.current = None;
+        self.events.push_back(DialogueEvent::End);
+    }
+
+    fn enter(&mut self, node_name: Option<String>) {
+        let graph = self.graph.clone();
+        let mut node_name = node_name;
+
+        for _ in 0..MAX_NODE_JUMPS {
+            let name = match node_name {
+                Some(name) => name,
+                None => return self.finish(),
+            };
+
+            let node = match graph.node(&name) {
+                Some(node) => node,
+                None => {
+                    println!("Dialogue node {} not found", name);
+                    return self.finish();
+                }
+            };
+
+            self.current = Some(name.clone());
+
+            for (k, v) in node.set.iter() {
+                self.set_variable(k, v.clone());
+            }
+
+            for e in node.events.iter() {
+                self.events.push_back(DialogueEvent::Event(e.clone()));
+            }
+
+            if let Some(branch) = node.branches
+                .iter()
+                .find(|b| eval_condition(&b.condition, &self.variables))
+            {
+                node_name = Some(branch.next.clone());
+                continue;
+            }
+
+            let choices: Vec<_> = node.choices
+                .iter()
+                .enumerate()
+                .filter(|&(_, c)| {
+                    c.condition
+                        .as_ref()
+                        .map_or(true, |cond| eval_condition(cond, &self.variables))
+                })
+                .collect();
+
+            let localization = self.localization.clone();
+
+            if let Some(ref text) = node.text {
+                self.events.push_back(DialogueEvent::Line {
+                    node: name.clone(),
+                    speaker: node.speaker.as_ref().map(|s| localization.tr(s)),
+                    text: localization.tr(text),
+                });
+            }
+
+            if choices.len() > 0 {
+                self.events.push_back(DialogueEvent::Choices(
+                    choices
+                        .iter()
+                        .map(|&(i, c)| (i, localization.tr(&c.text)))
+                        .collect(),
+                ));
+
+                self.state = PlayerState::WaitChoice(
+                    choices.iter().map(|&(i, c)| (i, c.next.clone())).collect(),
+                );
+                return;
+            }
+
+            if node.text.is_some() {
+                self.state = PlayerState::WaitAdvance(node.next.clone());
+                return;
+            }
+
+            // Nothing to show in this node, just pass through
+            node_name = node.next.clone();
+        }
+
+        println!("Dialogue jumps too many times, stopped.");
+        self.finish();
+    }
+}
@@ -8,16 +8,20 @@ use std::sync::Arc;
 
 use engine::asset::{AssetError, AssetResult, AssetSystem};
 use engine::context::EngineContext;
+use engine::profiler;
 use engine::core::{Component, ComponentArena, ComponentBased, GameObject, SceneTree};
+use engine::core::internal::GameObjectUtil;
 use engine::render::Camera;
-use engine::render::{DepthTest, DirectionalLight, Light, Material, MaterialState, Mesh,
-                     MeshSurface, ShaderProgram};
-use engine::render::{Frustum, RenderQueue};
+use engine::render::capabilities;
+use engine::render::{CullMode, DepthTest, DirectionalLight, Light, Material, MaterialState, Mesh,
+                     MeshSurface, RenderTexture, ShaderProgram, Texture};
+use engine::render::{Frustum, RenderQueue, TemporalAA};
 use image;
 use math::Aabb;
 
 use std::default::Default;
 
+#[cfg(feature = "ui")]
 use super::imgui;
 
 pub trait IEngine {
@@ -27,6 +31,7 @@ pub trait IEngine {
 
     fn asset_system_mut<'a>(&'a mut self) -> &'a mut AssetSystem;
 
+    #[cfg(feature = "ui")]
     fn gui_context(&mut self) -> Rc<RefCell<imgui::Context>>;
 
     fn screen_size(&self) -> (u32, u32);
@@ -34,7 +39,7 @@ pub trait IEngine {
     fn hidpi_factor(&self) -> f32;
 }
 
-#[derive(Default, Copy, Clone)]
+#[derive(Debug, Default, Copy, Clone)]
 pub struct EngineStats {
     pub surfaces_count: u32,
     pub opaque_count: u32,
@@ -54,15 +59,80 @@ where
     pub screen_size: (u32, u32),
     pub hidpi: f32,
     pub current_camera: RefCell<Option<Arc<Component>>>,
+    #[cfg(feature = "ui")]
     pub gui_context: Rc<RefCell<imgui::Context>>,
+    #[cfg(feature = "ui")]
+    imgui_scope: imgui::ImguiScope,
     pub arena: Rc<ComponentArena>,
 
+    /// Full screen effects applied in order to the main camera output.
+    /// Each effect material receives the previous result as `uDiffuse`
+    /// and the scene depth as `uDepth`.
+    pub post_effects: Vec<Rc<Material>>,
+    /// Effect applied after `post_effects`, whatever is added to them, e.g. the color
+    /// blindness filter of the accessibility settings which changes what the player sees
+    pub final_effect: Option<Rc<Material>>,
+    post_targets: Option<PostTargets>,
+
+    /// Cameras rendered in their `rect` instead of the main camera, e.g. for split-screen.
+    /// The UI is rendered once over the whole screen and the post effects are not applied.
+    pub viewports: Vec<Arc<Component>>,
+
+    /// Render the screen space motion of the opaque objects since the last frame, passed to
+    /// the post effects as `uMotion` (in uv units), read with `readMotion` of
+    /// `unrust/float_target.glsl` as it is packed without float render targets.
+    /// Used by motion blur and temporal antialiasing effects.
+    pub motion_vectors: bool,
+    motion: MotionState,
+
     pub stats: EngineStats,
 }
 
+/// Previous frame transforms for the motion vectors
+#[derive(Default)]
+struct MotionState {
+    prev_models: HashMap<u64, Matrix4<f32>>,
+    prev_view_proj: Option<Matrix4<f32>>,
+    target: Option<Rc<RenderTexture>>,
+    material: Option<Rc<Material>>,
+}
+
+struct PostTargets {
+    size: (u32, u32),
+    hdr: bool,
+    scene: Rc<RenderTexture>,
+    swap: [Rc<RenderTexture>; 2],
+    /// Copy to the output when there is no effect
+    copy: Option<Rc<Material>>,
+}
+
+impl PostTargets {
+    fn new(size: (u32, u32), hdr: bool) -> PostTargets {
+        use engine::render::TextureAttachment;
+
+        let new_rt = || Rc::new(RenderTexture::new(size.0, size.1, TextureAttachment::Color0));
+
+        let scene = if hdr {
+            RenderTexture::new_hdr_with_depth(size.0, size.1)
+        } else {
+            RenderTexture::new_with_depth(size.0, size.1)
+        };
+
+        PostTargets {
+            size,
+            hdr,
+            scene: Rc::new(scene),
+            swap: [new_rt(), new_rt()],
+            copy: None,
+        }
+    }
+}
+
 struct RenderCommand {
     pub surface: Rc<MeshSurface>,
     pub model_m: Matrix4<f32>,
+    /// Model matrix of the previous frame
+    pub prev_model_m: Matrix4<f32>,
     pub cam_distance: f32,
 }
 
@@ -138,6 +208,11 @@ impl RenderQueueList {
         state.states.depth_write = Some(false);
         qlist.queues.insert(RenderQueue::Transparent, state);
 
+        // Viewmodel Queue
+        let mut state = RenderQueueState::default();
+        state.states.alpha_blending = Some(false);
+        qlist.queues.insert(RenderQueue::Viewmodel, state);
+
         // UI Queue
         let mut state = RenderQueueState::default();
         state.states.alpha_blending = Some(true);
@@ -159,6 +234,7 @@ fn compute_model_m(object: &GameObject) -> Matrix4<f32> {
     object.transform.as_global_matrix()
 }
 
+#[derive(Debug, Clone, Copy)]
 pub struct ClearOption {
     pub color: Option<(f32, f32, f32, f32)>,
     pub clear_color: bool,
@@ -246,7 +322,13 @@ where
     }
 
     #[cfg_attr(feature = "flame_it", flame)]
-    fn setup_camera(&self, ctx: &mut EngineContext, modelm: Matrix4<f32>, camera: &Camera) {
+    fn setup_camera(
+        &self,
+        ctx: &mut EngineContext,
+        modelm: Matrix4<f32>,
+        prev_modelm: Matrix4<f32>,
+        camera: &Camera,
+    ) {
         let prog = ctx.prog.upgrade().unwrap();
         // setup_camera
         let perspective = camera.perspective(self.screen_size);
@@ -265,7 +347,10 @@ where
 
         prog.set("uNMatrix", modelm.inverse_transform().unwrap().transpose());
         prog.set("uMMatrix", modelm);
+        prog.set("uPrevMMatrix", prev_modelm);
         prog.set("uViewPos", camera.eye());
+        // Never clips without a plane
+        prog.set("uClipPlane", camera.clip_plane.unwrap_or(Vector4::unit_w()));
     }
 
     #[cfg_attr(feature = "flame_it", flame)]
@@ -301,18 +386,28 @@ where
     }
 
     #[cfg_attr(feature = "flame_it", flame)]
+    /// Returns the number of commands rendered, the others are not loaded yet
     fn render_commands(
         &self,
         ctx: &mut EngineContext,
         q: &RenderQueueState,
         camera: &Camera,
         material: Option<&Rc<Material>>,
-    ) {
+    ) -> usize {
         let gl = &self.gl;
+        let mut rendered = 0;
 
         for cmd in q.commands.iter() {
+            // A material replacing the ones of the objects keeps their cutout
+            let cutout;
             let mat = match material.as_ref() {
-                Some(&m) => &m,
+                Some(&m) => match m.with_cutout_of(&cmd.surface.material) {
+                    Some(with_cutout) => {
+                        cutout = Rc::new(with_cutout);
+                        &cutout
+                    }
+                    None => m,
+                },
                 None => &cmd.surface.material,
             };
 
@@ -339,7 +434,7 @@ where
 
             match r {
                 Ok(_) => {
-                    self.setup_camera(ctx, cmd.model_m, camera);
+                    self.setup_camera(ctx, cmd.model_m, cmd.prev_model_m, camera);
                     prog.commit(gl);
                     // if let RenderQueue::UI = mat.render_queue
                     {
@@ -347,6 +442,7 @@ where
                     }
 
                     cmd.surface.buffer.unbind(gl);
+                    rendered += 1;
                 }
                 Err(ref err) => match *err {
                     AssetError::NotReady => (),
@@ -354,6 +450,8 @@ where
                 },
             }
         }
+
+        rendered
     }
 
     fn map_component<T, F>(&self, mut func: F)
@@ -415,6 +513,14 @@ where
             .nth(0)
     }
 
+    /// The spot lights of the scene
+    pub fn find_spot_lights(&self) -> Vec<Arc<Component>> {
+        self.find_all_components::<Light>()
+            .into_iter()
+            .filter(|c| c.try_as::<Light>().unwrap().borrow().spot().is_some())
+            .collect()
+    }
+
     fn prepare_ctx(&self, ctx: &mut EngineContext) {
         // Update all components which need to update
         // Update lights
@@ -436,11 +542,11 @@ where
                 .filter(|c| {
                     let light_com = c.try_as::<Light>().unwrap();
                     match *light_com.borrow() {
-                        Light::Point(_) => true,
+                        Light::Point(_) | Light::Spot(_) => true,
                         _ => false,
                     }
                 })
-                .take(4)            // only take 4 points light.
+                .take(4)            // only take 4 points light, the spot lights included.
                 .map(
                     |c| c.clone()
                 )
@@ -487,7 +593,7 @@ where
                 // TODO: should use a material flag to skip
                 if let &Some(ref frustum) = frustum_opt {
                     match surface.material.render_queue {
-                        RenderQueue::Skybox | RenderQueue::UI => (),
+                        RenderQueue::Skybox | RenderQueue::Viewmodel | RenderQueue::UI => (),
                         _ => {
                             let bounds = surface.buffer.bounds();
                             if bounds.is_none() {
@@ -541,9 +647,16 @@ where
 
                     let cam_dist = (cam_pos - object.transform.global().disp).magnitude();
 
+                    let prev_m = self.motion
+                        .prev_models
+                        .get(&GameObjectUtil::node_id(object))
+                        .cloned()
+                        .unwrap_or(m);
+
                     q.commands.push(RenderCommand {
                         surface: surface.clone(),
                         model_m: m,
+                        prev_model_m: prev_m,
                         cam_distance: cam_dist,
                     })
                 }
@@ -552,7 +665,8 @@ where
     }
 
     pub fn get_bounds(&self, camera: &Camera) -> Option<Aabb> {
-        let render_q = self.gather_all_render_commands(camera, true, None);
+        let queues = &camera.included_render_queues;
+        let render_q = self.gather_all_render_commands(camera, queues, true, None);
 
         return render_q.aabb;
     }
@@ -560,6 +674,7 @@ where
     fn gather_all_render_commands(
         &self,
         camera: &Camera,
+        included_render_queues: &Option<BTreeSet<RenderQueue>>,
         update_bounds_only: bool,
         mut eng_stats: Option<&mut EngineStats>,
     ) -> RenderQueueList {
@@ -581,7 +696,7 @@ where
                         update_bounds_only,
                         &frustum,
                         &mut render_q,
-                        &camera.included_render_queues,
+                        included_render_queues,
                         &mut eng_stats,
                     )
                 }
@@ -597,10 +712,34 @@ where
         camera: &Camera,
         material: Option<&Rc<Material>>,
         clear_option: ClearOption,
+    ) -> EngineStats {
+        let rt = camera.render_texture.clone();
+        self.render_pass_to(camera, material, clear_option, rt.as_ref())
+    }
+
+    fn render_pass_to(
+        &mut self,
+        camera: &Camera,
+        material: Option<&Rc<Material>>,
+        clear_option: ClearOption,
+        target: Option<&Rc<RenderTexture>>,
+    ) -> EngineStats {
+        let queues = camera.included_render_queues.clone();
+        self.render_queues_to(camera, &queues, material, clear_option, target)
+    }
+
+    /// Render the `queues` of the camera, instead of its `included_render_queues`
+    fn render_queues_to(
+        &mut self,
+        camera: &Camera,
+        queues: &Option<BTreeSet<RenderQueue>>,
+        material: Option<&Rc<Material>>,
+        clear_option: ClearOption,
+        target: Option<&Rc<RenderTexture>>,
     ) -> EngineStats {
         let mut ctx: EngineContext = EngineContext::new();
 
-        if let Some(ref rt) = camera.render_texture {
+        if let Some(rt) = target {
             rt.bind_frame_buffer(&self.gl);
         }
 
@@ -619,7 +758,8 @@ where
         self.prepare_ctx(&mut ctx);
 
         // gather commands
-        let mut render_q = self.gather_all_render_commands(&camera, false, Some(&mut ctx.stats));
+        let mut render_q =
+            self.gather_all_render_commands(&camera, queues, false, Some(&mut ctx.stats));
 
         // Sort the opaque queue
         render_q
@@ -650,17 +790,352 @@ where
             .commands
             .len() as u32;
 
-        for (_, q) in render_q.queues.iter() {
-            self.render_commands(&mut ctx, &q, camera, material);
+        let viewmodel_camera = camera.viewmodel_camera();
+
+        for (queue, q) in render_q.queues.iter() {
+            match (*queue, viewmodel_camera.as_ref()) {
+                (RenderQueue::Viewmodel, Some(vm)) => {
+                    self.render_commands(&mut ctx, &q, vm, material);
+                }
+                _ => {
+                    self.render_commands(&mut ctx, &q, camera, material);
+                }
+            }
         }
 
-        if let Some(ref rt) = camera.render_texture {
+        if let Some(rt) = target {
             rt.unbind_frame_buffer(&self.gl);
         }
 
         ctx.stats
     }
 
+    /// Render a full screen quad with the material, to the screen if target is None
+    pub fn render_screen_quad(
+        &mut self,
+        material: &Rc<Material>,
+        target: Option<&Rc<RenderTexture>>,
+    ) {
+        let size = self.screen_size;
+        self.render_quad(material, target, size);
+    }
+
+    /// Render a quad covering a viewport of `size` pixels, e.g. the whole target texture
+    pub fn render_quad(
+        &mut self,
+        material: &Rc<Material>,
+        target: Option<&Rc<RenderTexture>>,
+        size: (u32, u32),
+    ) {
+        let mut ctx: EngineContext = EngineContext::new();
+
+        if let Some(rt) = target {
+            rt.bind_frame_buffer(&self.gl);
+        }
+
+        self.gl.viewport(0, 0, size.0, size.1);
+
+        self.prepare_ctx(&mut ctx);
+
+        let mut q = RenderQueueState::default();
+        q.states.alpha_blending = Some(false);
+        q.states.depth_write = Some(false);
+        q.states.depth_test = Some(DepthTest::Always);
+        q.states.cull = Some(CullMode::Off);
+
+        q.commands.push(RenderCommand {
+            surface: Rc::new(MeshSurface {
+                buffer: self.asset_system.new_mesh_buffer("screen_quad"),
+                material: material.clone(),
+            }),
+            model_m: Matrix4::identity(),
+            prev_model_m: Matrix4::identity(),
+            cam_distance: 0.0,
+        });
+
+        self.render_commands(&mut ctx, &q, &Camera::default(), None);
+
+        if let Some(rt) = target {
+            rt.unbind_frame_buffer(&self.gl);
+        }
+    }
+
+    /// Bind a material without a mesh and call `draw` with the context, e.g. for transform
+    /// feedback passes. Returns false if the material is not loaded yet.
+    pub fn draw_with_material<F>(&mut self, material: &Rc<Material>, draw: F) -> bool
+    where
+        F: FnOnce(&WebGLRenderingContext),
+    {
+        let mut ctx: EngineContext = EngineContext::new();
+        self.prepare_ctx(&mut ctx);
+
+        ctx.states.apply_defaults();
+        ctx.states.apply(&material.states);
+        ctx.states.commit(&self.gl);
+
+        if let Err(err) = self.setup_material(&mut ctx, material) {
+            if let AssetError::NotReady = err {
+                return false;
+            }
+
+            panic!(format!("Failed to load material, reason {:?}", err));
+        }
+
+        ctx.prog.upgrade().unwrap().commit(&self.gl);
+        draw(&self.gl);
+        true
+    }
+
+    /// Render the surfaces of a mesh alone with the camera, to its render texture if any, e.g.
+    /// to bake the mesh in a texture. Returns false if some surfaces are not loaded yet.
+    pub fn render_mesh(
+        &mut self,
+        camera: &Camera,
+        mesh: &Mesh,
+        model_m: Matrix4<f32>,
+        clear_option: ClearOption,
+    ) -> bool {
+        let mut ctx: EngineContext = EngineContext::new();
+        let target = camera.render_texture.clone();
+
+        if let Some(ref rt) = target {
+            rt.bind_frame_buffer(&self.gl);
+        }
+
+        match camera.rect {
+            Some(((x, y), (w, h))) => {
+                self.gl.viewport(x, y, w, h);
+            }
+            None => {
+                self.gl
+                    .viewport(0, 0, self.screen_size.0, self.screen_size.1);
+            }
+        }
+
+        self.clear(clear_option);
+
+        self.prepare_ctx(&mut ctx);
+
+        let mut q = RenderQueueState::default();
+        q.states.alpha_blending = Some(false);
+
+        for surface in mesh.surfaces.iter() {
+            q.commands.push(RenderCommand {
+                surface: surface.clone(),
+                model_m,
+                prev_model_m: model_m,
+                cam_distance: 0.0,
+            });
+        }
+
+        let rendered = self.render_commands(&mut ctx, &q, camera, None);
+
+        if let Some(ref rt) = target {
+            rt.unbind_frame_buffer(&self.gl);
+        }
+
+        rendered == q.commands.len()
+    }
+
+    /// Render the motion vectors of the opaque objects, see `motion_vectors`
+    fn render_motion_vectors(&mut self, camera: &Camera) -> Rc<Texture> {
+        let _scope = profiler::scope("motion_vectors");
+        let size = self.screen_size;
+
+        let need_new_target = self.motion
+            .target
+            .as_ref()
+            .map_or(true, |rt| rt.as_texture().size() != Some(size));
+
+        if need_new_target {
+            self.motion.target = Some(Rc::new(RenderTexture::new_hdr_with_depth(size.0, size.1)));
+        }
+
+        if self.motion.material.is_none() {
+            let program = self.asset_system.new_program("unrust/motion_vectors");
+            self.motion.material = Some(Rc::new(Material::new(program)));
+        }
+
+        let target = self.motion.target.clone().unwrap();
+        let material = self.motion.material.clone().unwrap();
+
+        // Without the antialiasing jitter
+        let mut motion_camera = camera.clone_view();
+        let view_proj = motion_camera.perspective(size) * motion_camera.v;
+        material.set(
+            "uPrevPVMatrix",
+            self.motion.prev_view_proj.unwrap_or(view_proj),
+        );
+
+        let mut queues = BTreeSet::new();
+        queues.insert(RenderQueue::Opaque);
+        motion_camera.included_render_queues = Some(queues);
+
+        // No motion, which is packed as 0.5 per axis without float render targets
+        let color = if capabilities::capabilities().float_render_targets {
+            (0.0, 0.0, 0.0, 0.0)
+        } else {
+            let c = TemporalAA::encode_motion(Vector2::new(0.0, 0.0));
+            let unit = |i: usize| c[i] as f32 / 255.0;
+            (unit(0), unit(1), unit(2), unit(3))
+        };
+        let clear_option = ClearOption {
+            color: Some(color),
+            clear_color: true,
+            clear_depth: true,
+            clear_stencil: false,
+        };
+        self.render_pass_to(&motion_camera, Some(&material), clear_option, Some(&target));
+
+        // Tiles of a capture are not consecutive frames
+        if camera.tile.is_none() {
+            self.motion.prev_view_proj = Some(view_proj);
+        }
+
+        target.as_texture()
+    }
+
+    /// Motion vectors of the last frame, if `motion_vectors` is enabled
+    pub fn motion_texture(&self) -> Option<Rc<Texture>> {
+        if !self.motion_vectors {
+            return None;
+        }
+
+        self.motion.target.as_ref().map(|rt| rt.as_texture())
+    }
+
+    fn render_with_post_effects(
+        &mut self,
+        camera: &Camera,
+        clear_option: ClearOption,
+        output: Option<&Rc<RenderTexture>>,
+    ) -> EngineStats {
+        let _scope = profiler::scope("post_effects");
+
+        let hdr = camera.exposure.is_some();
+        let need_new_targets = self.post_targets
+            .as_ref()
+            .map_or(true, |t| t.size != self.screen_size || t.hdr != hdr);
+
+        if need_new_targets {
+            self.post_targets = Some(PostTargets::new(self.screen_size, hdr));
+        }
+
+        let (scene, swap) = {
+            let targets = self.post_targets.as_ref().unwrap();
+            (targets.scene.clone(), targets.swap.clone())
+        };
+
+        // The UI is drawn after the post chain, without the exposure and the antialiasing
+        let all_queues = [
+            RenderQueue::Opaque,
+            RenderQueue::Skybox,
+            RenderQueue::Transparent,
+            RenderQueue::Viewmodel,
+        ];
+        let scene_queues: BTreeSet<_> = all_queues
+            .iter()
+            .cloned()
+            .filter(|q| {
+                camera
+                    .included_render_queues
+                    .as_ref()
+                    .map_or(true, |included| included.contains(q))
+            })
+            .collect();
+        let stats =
+            self.render_queues_to(camera, &Some(scene_queues), None, clear_option, Some(&scene));
+
+        let motion = if self.motion_vectors || camera.taa.is_some() {
+            Some(self.render_motion_vectors(camera))
+        } else {
+            None
+        };
+
+        let mut color = scene.as_texture();
+        // The tiles of a capture are not consecutive frames
+        let taa = if camera.tile.is_none() {
+            camera.taa.as_ref()
+        } else {
+            None
+        };
+        if let (Some(taa), Some(motion)) = (taa, motion.as_ref()) {
+            let _scope = profiler::scope("taa");
+            let (material, history) = taa.resolve(&*self.asset_system, hdr, self.screen_size);
+            material.set("uDiffuse", color.clone());
+            material.set("uMotion", motion.clone());
+            self.render_screen_quad(&material, Some(&history));
+            color = history.as_texture();
+        }
+
+        let mut effects = Vec::new();
+        if let Some(ref exposure) = camera.exposure {
+            let (passes, material) = exposure.passes(&*self.asset_system, &color);
+            for &(ref m, ref rt, size) in passes.iter() {
+                self.render_quad(m, Some(rt), size);
+            }
+            effects.push(material);
+        }
+        effects.extend(self.post_effects.iter().cloned());
+        effects.extend(self.final_effect.iter().cloned());
+
+        if effects.len() == 0 {
+            if self.post_targets.as_ref().unwrap().copy.is_none() {
+                let copy = self.asset_system.new_program("unrust/copy");
+                self.post_targets.as_mut().unwrap().copy = Some(Rc::new(Material::new(copy)));
+            }
+            effects.push(self.post_targets.as_ref().unwrap().copy.clone().unwrap());
+        }
+
+        let screen_size = Vector2::new(self.screen_size.0 as f32, self.screen_size.1 as f32);
+        let mut src = color;
+
+        for (i, effect) in effects.iter().enumerate() {
+            effect.set("uDiffuse", src.clone());
+            if let Some(depth) = scene.depth_texture() {
+                effect.set("uDepth", depth);
+            }
+            effect.set("uScreenSize", screen_size);
+            effect.set("uZNear", camera.znear);
+            effect.set("uZFar", camera.zfar);
+            if let Some(ref motion) = motion {
+                effect.set("uMotion", motion.clone());
+            }
+
+            if i + 1 == effects.len() {
+                self.render_screen_quad(effect, output);
+            } else {
+                let dst = swap[i % 2].clone();
+                self.render_screen_quad(effect, Some(&dst));
+                src = dst.as_texture();
+            }
+        }
+
+        // Drawn over the output by a UI-only camera, like `render_viewports`.
+        // The tiles of a capture have no UI, it would be repeated on each of them.
+        let has_ui = camera
+            .included_render_queues
+            .as_ref()
+            .map_or(true, |included| included.contains(&RenderQueue::UI));
+        if has_ui && camera.tile.is_none() {
+            // The depth of the output is not the one of the scene
+            let depth_only = ClearOption {
+                color: None,
+                clear_color: false,
+                clear_depth: true,
+                clear_stencil: false,
+            };
+
+            let mut ui_camera = Camera::new();
+            let mut queues = BTreeSet::new();
+            queues.insert(RenderQueue::UI);
+            ui_camera.included_render_queues = Some(queues);
+            self.render_pass_to(&ui_camera, None, depth_only, output);
+        }
+
+        stats
+    }
+
     #[cfg_attr(feature = "flame_it", flame)]
     pub fn render_pass(&mut self, camera: &Camera, clear_option: ClearOption) -> EngineStats {
         self.render_pass_with_material(camera, None, clear_option)
@@ -680,13 +1155,64 @@ where
         None
     }
 
+    fn render_viewports(&mut self, clear_option: ClearOption) -> EngineStats {
+        let no_clear = ClearOption {
+            color: None,
+            clear_color: false,
+            clear_depth: false,
+            clear_stencil: false,
+        };
+
+        self.gl
+            .viewport(0, 0, self.screen_size.0, self.screen_size.1);
+        self.clear(clear_option);
+
+        let mut stats = EngineStats::default();
+        for c in self.viewports.clone().iter() {
+            let camera = match c.try_as::<Camera>() {
+                Some(camera) => camera.borrow(),
+                None => continue,
+            };
+
+            // The viewports do not overlap, so the depth is cleared once
+            let s = self.render_pass(&camera, no_clear);
+            stats.surfaces_count += s.surfaces_count;
+            stats.opaque_count += s.opaque_count;
+            stats.transparent_count += s.transparent_count;
+            stats.total_opaque_count += s.total_opaque_count;
+            stats.total_transparent_count += s.total_transparent_count;
+        }
+
+        let mut ui_camera = Camera::new();
+        let mut queues = BTreeSet::new();
+        queues.insert(RenderQueue::UI);
+        ui_camera.included_render_queues = Some(queues);
+        self.render_pass(&ui_camera, no_clear);
+
+        stats
+    }
+
     #[cfg_attr(feature = "flame_it", flame)]
     pub fn render(&mut self, clear_option: ClearOption) {
-        imgui::pre_render(self);
+        #[cfg(feature = "ui")]
+        {
+            let _scope = profiler::scope("imgui");
+            imgui::pre_render(self);
+        }
+
+        if self.viewports.len() > 0 {
+            self.stats = self.render_viewports(clear_option);
+        } else if let Some(ref camera) = self.main_camera() {
+            let camera = camera.try_as::<Camera>().unwrap().borrow();
+
+            let post = self.post_effects.len() > 0 || self.final_effect.is_some()
+                || camera.exposure.is_some() || camera.taa.is_some();
 
-        if let Some(ref camera) = self.main_camera() {
-            self.stats =
-                self.render_pass(&camera.try_as::<Camera>().unwrap().borrow(), clear_option);
+            self.stats = if post && camera.render_texture.is_none() {
+                self.render_with_post_effects(&camera, clear_option, None)
+            } else {
+                self.render_pass(&camera, clear_option)
+            };
         } else {
             // We dont have a main camera here, just clean the screen.
             self.clear(clear_option);
@@ -695,6 +1221,7 @@ where
 
     pub fn new(webgl_ctx: WebGLContext, size: (u32, u32), hidpi: f32) -> Engine<A> {
         let gl = WebGLRenderingContext::new(webgl_ctx);
+        capabilities::init(&gl);
 
         /*=========Drawing the triangle===========*/
 
@@ -715,24 +1242,40 @@ where
         // Set the view port
         gl.viewport(0, 0, size.0, size.1);
 
-        let gui_tree = SceneTree::new();
+        #[cfg(feature = "ui")]
+        let imgui_scope = imgui::ImguiScope::new();
+        #[cfg(feature = "ui")]
+        imgui_scope.enter();
 
         Engine {
             gl: gl,
             objects: vec![],
             program_cache: RefCell::new(HashMap::new()),
             asset_system: Box::new(A::new()),
-            gui_context: Rc::new(RefCell::new(imgui::Context::new(gui_tree))),
+            #[cfg(feature = "ui")]
+            gui_context: Rc::new(RefCell::new(imgui::Context::new(SceneTree::new()))),
+            #[cfg(feature = "ui")]
+            imgui_scope,
             screen_size: size,
             hidpi: hidpi,
             current_camera: RefCell::new(None),
             stats: Default::default(),
             arena: Rc::new(ComponentArena::new()),
+            post_effects: Vec::new(),
+            final_effect: None,
+            post_targets: None,
+            viewports: Vec::new(),
+            motion_vectors: false,
+            motion: MotionState::default(),
         }
     }
 
     pub fn begin(&mut self) {
-        imgui::begin();
+        #[cfg(feature = "ui")]
+        {
+            self.imgui_scope.enter();
+            imgui::begin();
+        }
 
         self.asset_system_mut().step();
     }
@@ -741,6 +1284,21 @@ where
         // drop all gameobjects if there are no other references
         self.objects.retain(|obj| obj.upgrade().is_some());
 
+        // remember the transforms for the motion vectors of the next frame
+        if self.motion_vectors {
+            self.motion.prev_models = self.objects
+                .iter()
+                .filter_map(|obj| obj.upgrade())
+                .filter_map(|obj| {
+                    obj.try_borrow().ok().map(|o| {
+                        (GameObjectUtil::node_id(&o), compute_model_m(&o))
+                    })
+                })
+                .collect();
+        } else {
+            self.motion.prev_models.clear();
+        }
+
         // drop camera cache if it is only by holded by ourself
         let mut cam_mut = self.current_camera.borrow_mut();
         if let Some(ref c) = *cam_mut {
@@ -772,6 +1330,52 @@ where
         // we flip it vertically
         img.map(|img| imageops::flip_vertical(&img))
     }
+
+    /// Render the camera in (scale x scale) tiles and assemble them to a single image,
+    /// which is `scale` times larger than the screen. Post effects are applied to each tile.
+    pub fn capture_tiled(&mut self, camera: &mut Camera, scale: u32) -> Option<image::RgbaImage> {
+        use image::imageops;
+
+        let scale = scale.max(1);
+        let (width, height) = self.screen_size;
+        let rt = Rc::new(RenderTexture::new_with_depth(width, height));
+        let mut result = image::RgbaImage::new(width * scale, height * scale);
+
+        let old_tile = camera.tile;
+        let old_rect = camera.rect.take();
+        let mut completed = true;
+
+        'tiles: for row in 0..scale {
+            for col in 0..scale {
+                camera.tile = Some((col, row, scale));
+
+                if self.post_effects.len() > 0 || self.final_effect.is_some()
+                    || camera.exposure.is_some() || camera.taa.is_some()
+                {
+                    self.render_with_post_effects(camera, ClearOption::default(), Some(&rt));
+                } else {
+                    self.render_pass_to(camera, None, ClearOption::default(), Some(&rt));
+                }
+
+                match rt.capture(&self.gl) {
+                    Some(tile) => imageops::replace(&mut result, &tile, col * width, row * height),
+                    None => {
+                        completed = false;
+                        break 'tiles;
+                    }
+                }
+            }
+        }
+
+        camera.tile = old_tile;
+        camera.rect = old_rect;
+
+        if completed {
+            Some(result)
+        } else {
+            None
+        }
+    }
 }
 
 impl<A: AssetSystem> IEngine for Engine<A> {
@@ -782,6 +1386,7 @@ impl<A: AssetSystem> IEngine for Engine<A> {
         go
     }
 
+    #[cfg(feature = "ui")]
     fn gui_context(&mut self) -> Rc<RefCell<imgui::Context>> {
         self.gui_context.clone()
     }
@@ -0,0 +1,718 @@
+//! GPU simulation
+//!
+//! Runs data-parallel simulations (particles, boids, grass bending) on the GPU. Each element
+//! is a texel and each state of the elements (e.g. position, velocity) a float texture.
+//!
+//! In WebGL2 the states are updated by transform feedback: a vertex shader pass per state
+//! captures the new values of all elements in a buffer, copied into the state texture once
+//! all the states are updated. The passes go through `js!` (see `capabilities`) as uni_gl
+//! does not expose transform feedback. Its native backend does not either, although desktop
+//! GL 3.2 has it, so native simulations use the fragment backend.
+//!
+//! Otherwise each state is a float render texture, updated by a fragment shader pass into a
+//! second texture which is swapped with the first (ping-pong). Without float render targets
+//! (see `capabilities`), each state is packed in two RGBA8 textures, the high and the low
+//! bytes of 16 bits per channel in [-`SimulationKernel::range`, range], written in two
+//! passes. The states are read with `state_<name>(uv)` with any backend,
+//! `GpuSimulation::glsl` declares it for the other shaders.
+//!
+//! ```ignore
+//! struct Particles;
+//!
+//! impl SimulationKernel for Particles {
+//!     fn states(&self) -> Vec<&'static str> {
+//!         vec!["position", "velocity"]
+//!     }
+//!
+//!     fn init(&self, state: &str) -> String {
+//!         match state {
+//!             "position" => "return vec4(0.0);".to_owned(),
+//!             _ => "return vec4(sin(index), 5.0, cos(index), 0.0);".to_owned(),
+//!         }
+//!     }
+//!
+//!     fn update(&self, state: &str) -> String {
+//!         match state {
+//!             "position" => "return position + velocity * uDeltaTime;".to_owned(),
+//!             _ => "return velocity + vec4(0.0, -9.8, 0.0, 0.0) * uDeltaTime;".to_owned(),
+//!         }
+//!     }
+//! }
+//!
+//! let mut sim = GpuSimulation::new(Particles, 10000);
+//! sim.step(world.engine_mut(), dt);
+//! sim.bind(&particle_material);
+//! ```
+
+use engine::asset::{AssetSystem, Resource};
+use engine::engine::Engine;
+use engine::render::capabilities;
+use engine::render::{Material, RenderTexture, ShaderFs, ShaderProgram, ShaderVs, Texture,
+                     TextureAttachment, TextureFiltering};
+use engine::MaterialParam;
+use uni_gl::WebGLRenderingContext;
+
+use math::*;
+use std::rc::Rc;
+
+use self::feedback::FeedbackBuffer;
+
+const SIM_VS: &'static str = r#"
+#ifndef GL_ES
+#define attribute in
+#define varying out
+#endif
+
+attribute vec3 aVertexPosition;
+attribute vec2 aTextureCoord;
+varying vec2 vTexCoords;
+uniform mat4 uMMatrix;
+
+void main(void) {
+    gl_Position = uMMatrix * vec4(aVertexPosition, 1.0);
+    vTexCoords = aTextureCoord;
+}
+"#;
+
+const SIM_FS_HEADER: &'static str = r#"
+#ifndef GL_ES
+#define varying in
+#define gl_FragColor FragColor
+#define texture2D texture
+out vec4 FragColor;
+#endif
+
+varying vec2 vTexCoords;
+
+uniform vec2 uSimSize;
+uniform float uTime;
+uniform float uDeltaTime;
+
+float element_index() {
+    vec2 cell = floor(vTexCoords * uSimSize);
+    return cell.y * uSimSize.x + cell.x;
+}
+"#;
+
+/// The states in RGBA8, 16 bits per channel in [-range, range]
+const SIM_PACKING: &'static str = r#"
+// the pass writes the low bytes of the state, otherwise the high ones
+uniform bool uSimLowBytes;
+
+vec4 simPack(vec4 v, float range) {
+    vec4 u = floor(clamp(v / (2.0 * range) + 0.5, 0.0, 1.0) * 65280.0 + 0.5);
+    vec4 hi = floor(u / 256.0);
+    return (uSimLowBytes ? u - hi * 256.0 : hi) / 255.0;
+}
+
+vec4 simUnpack(vec4 hi, vec4 lo, float range) {
+    vec4 u = (floor(hi * 255.0 + 0.5) * 256.0 + floor(lo * 255.0 + 0.5)) / 65280.0;
+    return (u - 0.5) * 2.0 * range;
+}
+"#;
+
+/// The output of the transform feedback passes
+const FEEDBACK_VARYING: &'static str = "vSimValue";
+
+/// The transform feedback passes are vertex shaders drawing a point per element, which read
+/// the states
+const FEEDBACK_VS_HEADER: &'static str = r#"#define USE_GLSL_300ES
+#define texture2D texture
+#ifdef GL_ES
+precision highp sampler2D;
+#endif
+
+out vec4 vSimValue;
+
+uniform vec2 uSimSize;
+uniform float uTime;
+uniform float uDeltaTime;
+
+// The texel of the element, set first in main
+vec2 vTexCoords;
+
+float element_index() {
+    return float(gl_VertexID);
+}
+"#;
+
+/// Nothing is rasterized during the transform feedback passes
+const FEEDBACK_FS: &'static str = r#"#define USE_GLSL_300ES
+out vec4 FragColor;
+
+void main(void) {
+    FragColor = vec4(0.0);
+}
+"#;
+
+/// Definition of the per element update shader of a `GpuSimulation`
+///
+/// The GLSL snippets can use `uTime`, `uDeltaTime`, `uSimSize` (the size of the state
+/// textures), `vTexCoords` (the texel of the element), `element_index()` and the uniforms
+/// declared in `common`. Updates can read any texel of the states with `state_<name>(uv)`
+/// too (e.g. the neighbours of the element).
+pub trait SimulationKernel {
+    /// Names of the states of each element, each state is a vec4
+    fn states(&self) -> Vec<&'static str>;
+
+    /// Declarations shared by all passes, e.g. uniforms and functions
+    fn common(&self) -> String {
+        String::new()
+    }
+
+    /// Range of the values of a state, [-range, range], when it is packed in RGBA8 without
+    /// float render targets
+    fn range(&self, _state: &str) -> f32 {
+        1024.0
+    }
+
+    /// Body of `vec4 init(float index)`, the initial value of a state
+    fn init(&self, state: &str) -> String;
+
+    /// Body of `vec4 update(vec4 <state>...)`, the next value of a state from the
+    /// current values of all states, which are named after them
+    fn update(&self, state: &str) -> String;
+}
+
+/// Where the passes of a state write
+enum StateTargets {
+    /// Fragment passes into ping-pong render textures
+    Render {
+        /// The high bytes when packed
+        targets: [Rc<RenderTexture>; 2],
+        /// The low bytes when packed
+        low_targets: Option<[Rc<RenderTexture>; 2]>,
+        current: usize,
+    },
+    /// Transform feedback passes into a buffer, copied into the texture once all the states
+    /// are updated. The buffer is created at the first step, with the context.
+    Feedback {
+        texture: Rc<Texture>,
+        buffer: Option<FeedbackBuffer>,
+    },
+}
+
+struct SimState {
+    name: &'static str,
+    targets: StateTargets,
+    init: Rc<Material>,
+    update: Rc<Material>,
+}
+
+pub struct GpuSimulation {
+    count: usize,
+    size: (u32, u32),
+    states: Vec<SimState>,
+    readers: String,
+    time: f32,
+    initialized: bool,
+}
+
+fn new_program(name: &str, fs: String) -> Rc<ShaderProgram> {
+    let vs = ShaderVs::new(&format!("{}_vs.glsl", name), SIM_VS);
+    let fs = ShaderFs::new(&format!("{}_fs.glsl", name), &fs);
+
+    ShaderProgram::new((Resource::new(vs), Resource::new(fs)))
+}
+
+fn new_feedback_program(name: &str, vs: String) -> Rc<ShaderProgram> {
+    let vs = ShaderVs::new(&format!("{}_vs.glsl", name), &vs);
+    let fs = ShaderFs::new(&format!("{}_fs.glsl", name), FEEDBACK_FS);
+
+    ShaderProgram::new_with_feedback(
+        (Resource::new(vs), Resource::new(fs)),
+        vec![FEEDBACK_VARYING.to_owned()],
+    )
+}
+
+/// Transform feedback is in WebGL2. Desktop GL 3.2 has it too, but not the native backend of
+/// uni_gl, and the native float render targets do not need the states to be packed.
+fn use_feedback() -> bool {
+    cfg!(target_arch = "wasm32") && capabilities::capabilities().webgl2
+}
+
+/// `main` of a transform feedback pass, capturing `value`
+fn feedback_main(value: &str) -> String {
+    format!(
+        "void main(void) {{
+    float index = element_index();
+    vTexCoords = (vec2(mod(index, uSimSize.x), floor(index / uSimSize.x)) + 0.5) / uSimSize;
+    {} = {};
+    gl_Position = vec4(0.0, 0.0, 0.0, 1.0);
+}}
+",
+        FEEDBACK_VARYING, value
+    )
+}
+
+fn sampler_name(state: &str) -> String {
+    format!("uState_{}", state)
+}
+
+fn low_sampler_name(state: &str) -> String {
+    format!("uStateLow_{}", state)
+}
+
+/// The samplers of a state and its `state_<name>(uv)` function
+fn state_reader(state: &str, range: f32, packed: bool) -> String {
+    if !packed {
+        return format!(
+            "uniform sampler2D {0};
+vec4 state_{1}(vec2 uv) {{ return texture2D({0}, uv); }}
+",
+            sampler_name(state),
+            state
+        );
+    }
+
+    format!(
+        "uniform sampler2D {0};
+uniform sampler2D {1};
+         vec4 state_{2}(vec2 uv) {{
+             return simUnpack(texture2D({0}, uv), texture2D({1}, uv), {3:?});
+}}
+",
+        sampler_name(state),
+        low_sampler_name(state),
+        state,
+        range
+    )
+}
+
+impl SimState {
+    /// The current values, the high bytes when packed
+    fn texture(&self) -> Rc<Texture> {
+        match self.targets {
+            StateTargets::Render {
+                ref targets,
+                current,
+                ..
+            } => targets[current].as_texture(),
+            StateTargets::Feedback { ref texture, .. } => texture.clone(),
+        }
+    }
+
+    /// The current low bytes when packed
+    fn low_texture(&self) -> Option<Rc<Texture>> {
+        match self.targets {
+            StateTargets::Render {
+                low_targets: Some(ref low_targets),
+                current,
+                ..
+            } => Some(low_targets[current].as_texture()),
+            _ => None,
+        }
+    }
+
+    fn prepare(&mut self, gl: &WebGLRenderingContext, texels: u32) {
+        if let StateTargets::Feedback { ref mut buffer, .. } = self.targets {
+            if buffer.is_none() {
+                *buffer = Some(FeedbackBuffer::new(gl, texels));
+            }
+        }
+    }
+
+    /// Run a pass, into the other render textures when `next`. Packed states are rendered
+    /// twice.
+    fn render<A: AssetSystem>(
+        &self,
+        engine: &mut Engine<A>,
+        material: &Rc<Material>,
+        size: (u32, u32),
+        next: bool,
+    ) {
+        match self.targets {
+            StateTargets::Render {
+                ref targets,
+                ref low_targets,
+                current,
+            } => {
+                let index = if next { 1 - current } else { current };
+                match *low_targets {
+                    None => engine.render_quad(material, Some(&targets[index]), size),
+                    Some(ref low_targets) => {
+                        material.set("uSimLowBytes", false);
+                        engine.render_quad(material, Some(&targets[index]), size);
+                        material.set("uSimLowBytes", true);
+                        engine.render_quad(material, Some(&low_targets[index]), size);
+                    }
+                }
+            }
+            StateTargets::Feedback { ref buffer, .. } => {
+                if let Some(ref buffer) = *buffer {
+                    engine.draw_with_material(material, |gl| buffer.capture(gl, size.0 * size.1));
+                }
+            }
+        }
+    }
+
+    /// Make the values written by the passes current, swapping the render textures when
+    /// `next`
+    fn commit<A: AssetSystem>(&mut self, engine: &Engine<A>, size: (u32, u32), next: bool) {
+        match self.targets {
+            StateTargets::Render { ref mut current, .. } => {
+                if next {
+                    *current = 1 - *current;
+                }
+            }
+            StateTargets::Feedback {
+                ref texture,
+                ref buffer,
+            } => {
+                if let Some(ref buffer) = *buffer {
+                    if texture.bind(&engine.gl, 0).is_ok() {
+                        buffer.copy_to_texture(&engine.gl, size);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl GpuSimulation {
+    /// A simulation of `count` elements
+    pub fn new<K: SimulationKernel>(kernel: K, count: usize) -> GpuSimulation {
+        let count = count.max(1);
+        let width = (count as f32).sqrt().ceil() as usize;
+        let height = (count + width - 1) / width;
+        let size = (width as u32, height as u32);
+
+        let feedback = use_feedback();
+        let packed = !feedback && !capabilities::capabilities().float_render_targets;
+        let header = if packed {
+            format!("{}{}", SIM_FS_HEADER, SIM_PACKING)
+        } else {
+            SIM_FS_HEADER.to_owned()
+        };
+
+        let names = kernel.states();
+        let mut readers = if packed {
+            SIM_PACKING.to_owned()
+        } else {
+            String::new()
+        };
+        for name in names.iter() {
+            readers.push_str(&state_reader(name, kernel.range(name), packed));
+        }
+        let params = names
+            .iter()
+            .map(|n| format!("vec4 {}", n))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let args = names
+            .iter()
+            .map(|n| format!("state_{}(vTexCoords)", n))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let states = names
+            .iter()
+            .map(|&name| {
+                if feedback {
+                    // The states are written by the init passes, they can not be read
+                    let init_vs = format!(
+                        "{}\n{}\nvec4 init(float index) {{\n{}\n}}\n{}",
+                        FEEDBACK_VS_HEADER,
+                        kernel.common(),
+                        kernel.init(name),
+                        feedback_main("init(index)")
+                    );
+                    let update_vs = format!(
+                        "{}{}\n{}\nvec4 update({}) {{\n{}\n}}\n{}",
+                        FEEDBACK_VS_HEADER,
+                        readers,
+                        kernel.common(),
+                        params,
+                        kernel.update(name),
+                        feedback_main(&format!("update({})", args))
+                    );
+
+                    let texture =
+                        Texture::new_render_texture(size.0, size.1, TextureAttachment::Color0);
+                    texture.filtering.set(TextureFiltering::Nearest);
+
+                    return SimState {
+                        name,
+                        targets: StateTargets::Feedback {
+                            texture,
+                            buffer: None,
+                        },
+                        init: Rc::new(Material::new(new_feedback_program(
+                            &format!("gpu_sim_{}_init", name),
+                            init_vs,
+                        ))),
+                        update: Rc::new(Material::new(new_feedback_program(
+                            &format!("gpu_sim_{}_update", name),
+                            update_vs,
+                        ))),
+                    };
+                }
+
+                let output = if packed {
+                    format!("simPack(value, {:?})", kernel.range(name))
+                } else {
+                    "value".to_owned()
+                };
+
+                // The states are written by the init passes, they can not be read
+                let init_fs = format!(
+                    "{}\n{}\nvec4 init(float index) {{\n{}\n}}\n\
+                     void main(void) {{\n    vec4 value = init(element_index());\n    \
+                     gl_FragColor = {};\n}}\n",
+                    header,
+                    kernel.common(),
+                    kernel.init(name),
+                    output
+                );
+                let update_fs = format!(
+                    "{}{}\n{}\nvec4 update({}) {{\n{}\n}}\n\
+                     void main(void) {{\n    vec4 value = update({});\n    \
+                     gl_FragColor = {};\n}}\n",
+                    SIM_FS_HEADER,
+                    readers,
+                    kernel.common(),
+                    params,
+                    kernel.update(name),
+                    args,
+                    output
+                );
+
+                let new_target = || {
+                    let attach = if packed {
+                        TextureAttachment::Color0
+                    } else {
+                        TextureAttachment::Color0Float
+                    };
+                    Rc::new(RenderTexture::new(size.0, size.1, attach))
+                };
+
+                SimState {
+                    name,
+                    targets: StateTargets::Render {
+                        targets: [new_target(), new_target()],
+                        low_targets: if packed {
+                            Some([new_target(), new_target()])
+                        } else {
+                            None
+                        },
+                        current: 0,
+                    },
+                    init: Rc::new(Material::new(new_program(
+                        &format!("gpu_sim_{}_init", name),
+                        init_fs,
+                    ))),
+                    update: Rc::new(Material::new(new_program(
+                        &format!("gpu_sim_{}_update", name),
+                        update_fs,
+                    ))),
+                }
+            })
+            .collect();
+
+        GpuSimulation {
+            count,
+            size,
+            states,
+            readers,
+            time: 0.0,
+            initialized: false,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Size of the state textures, the elements are in rows from the bottom left texel
+    pub fn size(&self) -> (u32, u32) {
+        self.size
+    }
+
+    /// Set a uniform declared in `SimulationKernel::common`
+    pub fn set<T, S>(&self, name: S, value: T)
+    where
+        T: Into<MaterialParam> + Clone,
+        S: Into<String>,
+    {
+        let name = name.into();
+        for state in self.states.iter() {
+            state.init.set(name.clone(), value.clone());
+            state.update.set(name.clone(), value.clone());
+        }
+    }
+
+    /// Run the init passes again at next step
+    pub fn reset(&mut self) {
+        self.initialized = false;
+        self.time = 0.0;
+    }
+
+    /// The states are packed in RGBA8, without float render targets
+    pub fn is_packed(&self) -> bool {
+        self.states.iter().any(|s| s.low_texture().is_some())
+    }
+
+    /// The states are updated by transform feedback, in WebGL2
+    pub fn uses_feedback(&self) -> bool {
+        self.states.iter().any(|s| match s.targets {
+            StateTargets::Feedback { .. } => true,
+            _ => false,
+        })
+    }
+
+    /// Declarations of the `state_<name>(uv)` functions reading the states, for the shaders
+    /// of the materials set by `bind`
+    pub fn glsl(&self) -> &str {
+        &self.readers
+    }
+
+    /// The current values of a state, its high bytes when packed
+    pub fn state(&self, name: &str) -> Option<Rc<Texture>> {
+        self.states
+            .iter()
+            .find(|s| s.name == name)
+            .map(|s| s.texture())
+    }
+
+    /// Set the state textures read by `state_<name>(uv)` (see `glsl`) and `uSimSize` of a
+    /// material, e.g. for a vertex shader which places the particles
+    pub fn bind(&self, material: &Material) {
+        for state in self.states.iter() {
+            material.set(sampler_name(state.name), state.texture());
+            if let Some(low_texture) = state.low_texture() {
+                material.set(low_sampler_name(state.name), low_texture);
+            }
+        }
+        material.set("uSimSize", self.size_vec());
+    }
+
+    fn size_vec(&self) -> Vector2<f32> {
+        Vector2::new(self.size.0 as f32, self.size.1 as f32)
+    }
+
+    fn set_inputs(&self, material: &Material, dt: f32) {
+        material.set("uSimSize", self.size_vec());
+        material.set("uTime", self.time);
+        material.set("uDeltaTime", dt);
+    }
+
+    /// Update all the elements, in one pass per state
+    pub fn step<A: AssetSystem>(&mut self, engine: &mut Engine<A>, dt: f32) {
+        let size = self.size;
+        for state in self.states.iter_mut() {
+            state.prepare(&engine.gl, size.0 * size.1);
+        }
+
+        if !self.initialized {
+            for state in self.states.iter() {
+                self.set_inputs(&state.init, 0.0);
+                state.render(engine, &state.init, size, false);
+            }
+            for state in self.states.iter_mut() {
+                state.commit(engine, size, false);
+            }
+            self.initialized = true;
+        }
+
+        self.time += dt;
+
+        // All passes read the current states, and write to the other textures or buffers
+        for state in self.states.iter() {
+            self.set_inputs(&state.update, dt);
+            self.bind(&state.update);
+            state.render(engine, &state.update, size, true);
+        }
+
+        for state in self.states.iter_mut() {
+            state.commit(engine, size, true);
+        }
+    }
+}
+
+/// The transform feedback passes, through `js!` as uni_gl does not expose them
+#[cfg(target_arch = "wasm32")]
+mod feedback {
+    use stdweb::Value;
+    use uni_gl::WebGLRenderingContext;
+
+    /// A vec4 per element, captured from the output of the vertex shader of a pass
+    pub struct FeedbackBuffer(Value);
+
+    impl FeedbackBuffer {
+        pub fn new(gl: &WebGLRenderingContext, texels: u32) -> FeedbackBuffer {
+            FeedbackBuffer(js! {
+                var ctx = @{&gl.reference};
+                var buffer = ctx.createBuffer();
+                ctx.bindBuffer(ctx.TRANSFORM_FEEDBACK_BUFFER, buffer);
+                ctx.bufferData(ctx.TRANSFORM_FEEDBACK_BUFFER, @{texels * 16}, ctx.DYNAMIC_COPY);
+                ctx.bindBuffer(ctx.TRANSFORM_FEEDBACK_BUFFER, null);
+
+                // No attributes are read, the elements are `gl_VertexID`
+                return { ctx: ctx, buffer: buffer, vao: ctx.createVertexArray() };
+            })
+        }
+
+        /// Draw a point per texel with the bound program, capturing its output
+        pub fn capture(&self, gl: &WebGLRenderingContext, texels: u32) {
+            js! { @(no_return)
+                var ctx = @{&gl.reference};
+                var state = @{&self.0};
+                var vao = ctx.getParameter(ctx.VERTEX_ARRAY_BINDING);
+
+                ctx.bindVertexArray(state.vao);
+                ctx.bindBufferBase(ctx.TRANSFORM_FEEDBACK_BUFFER, 0, state.buffer);
+                ctx.enable(ctx.RASTERIZER_DISCARD);
+                ctx.beginTransformFeedback(ctx.POINTS);
+                ctx.drawArrays(ctx.POINTS, 0, @{texels});
+                ctx.endTransformFeedback();
+                ctx.disable(ctx.RASTERIZER_DISCARD);
+                ctx.bindBufferBase(ctx.TRANSFORM_FEEDBACK_BUFFER, 0, null);
+                ctx.bindVertexArray(vao);
+            }
+        }
+
+        /// Copy the values into the bound 2D texture, as RGBA32F
+        pub fn copy_to_texture(&self, gl: &WebGLRenderingContext, size: (u32, u32)) {
+            js! { @(no_return)
+                var ctx = @{&gl.reference};
+                var flip = ctx.getParameter(ctx.UNPACK_FLIP_Y_WEBGL);
+
+                // The buffer uploads can not be flipped
+                ctx.pixelStorei(ctx.UNPACK_FLIP_Y_WEBGL, false);
+                ctx.bindBuffer(ctx.PIXEL_UNPACK_BUFFER, @{&self.0}.buffer);
+                ctx.texImage2D(ctx.TEXTURE_2D, 0, ctx.RGBA32F, @{size.0}, @{size.1}, 0,
+                    ctx.RGBA, ctx.FLOAT, 0);
+                ctx.bindBuffer(ctx.PIXEL_UNPACK_BUFFER, null);
+                ctx.pixelStorei(ctx.UNPACK_FLIP_Y_WEBGL, flip);
+            }
+        }
+    }
+
+    impl Drop for FeedbackBuffer {
+        fn drop(&mut self) {
+            js! { @(no_return)
+                var state = @{&self.0};
+                state.ctx.deleteBuffer(state.buffer);
+                state.ctx.deleteVertexArray(state.vao);
+            }
+        }
+    }
+}
+
+/// Never created, the native simulations use the fragment backend (see `use_feedback`)
+#[cfg(not(target_arch = "wasm32"))]
+mod feedback {
+    use uni_gl::WebGLRenderingContext;
+
+    pub struct FeedbackBuffer;
+
+    impl FeedbackBuffer {
+        pub fn new(_gl: &WebGLRenderingContext, _texels: u32) -> FeedbackBuffer {
+            FeedbackBuffer
+        }
+
+        pub fn capture(&self, _gl: &WebGLRenderingContext, _texels: u32) {}
+
+        pub fn copy_to_texture(&self, _gl: &WebGLRenderingContext, _size: (u32, u32)) {}
+    }
+}
@@ -0,0 +1,275 @@
+//! Haptic feedback (gamepad rumble)
+//!
+//! Effects are described by envelopes, which could be combined in timed patterns.
+//! `Haptics` mixes all playing effects of a gamepad and sends the result to the device.
+//! The native gamepads of uni-pad have no vibration yet, the effects play without output.
+
+use engine::asset::loader::{self, Loadable, Loader};
+use engine::asset::{AssetResult, AssetSystem, File, Resource};
+use uni_pad as pad;
+
+use std::collections::HashMap;
+
+// Interval of refreshing a running vibration, the device keeps vibrating twice as long
+const UPDATE_DURATION: f32 = 0.1;
+const MIN_CHANGE: f32 = 0.02;
+
+/// Vibration with attack, sustain and release phases, in seconds.
+/// Magnitudes of the strong (low frequency) and weak (high frequency) motors are from 0 to 1.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct HapticEnvelope {
+    #[serde(default)]
+    pub attack: f32,
+    pub sustain: f32,
+    #[serde(default)]
+    pub release: f32,
+    pub strong: f32,
+    pub weak: f32,
+}
+
+impl HapticEnvelope {
+    /// A constant vibration
+    pub fn new(strong: f32, weak: f32, duration: f32) -> HapticEnvelope {
+        HapticEnvelope {
+            attack: 0.0,
+            sustain: duration,
+            release: 0.0,
+            strong,
+            weak,
+        }
+    }
+
+    pub fn with_fade(mut self, attack: f32, release: f32) -> HapticEnvelope {
+        self.attack = attack;
+        self.release = release;
+        self
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.attack + self.sustain + self.release
+    }
+
+    /// (strong, weak) magnitudes at time t
+    pub fn sample(&self, t: f32) -> (f32, f32) {
+        let k = if t < 0.0 || t >= self.duration() {
+            0.0
+        } else if t < self.attack {
+            t / self.attack
+        } else if t < self.attack + self.sustain {
+            1.0
+        } else {
+            1.0 - (t - self.attack - self.sustain) / self.release
+        };
+
+        (self.strong * k, self.weak * k)
+    }
+
+    /// Highest (strong, weak) magnitudes between `from` and `to`,
+    /// an envelope shorter than a frame is still felt
+    pub fn peak(&self, from: f32, to: f32) -> (f32, f32) {
+        if to < 0.0 || from >= self.duration() {
+            return (0.0, 0.0);
+        }
+
+        let k = if to < self.attack {
+            to / self.attack
+        } else if from > self.attack + self.sustain {
+            1.0 - (from - self.attack - self.sustain) / self.release
+        } else {
+            1.0
+        };
+
+        (self.strong * k, self.weak * k)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct HapticStep {
+    /// Start time relative to the start of the pattern
+    #[serde(default)]
+    pub at: f32,
+    pub envelope: HapticEnvelope,
+}
+
+/// A sequence of envelopes, as a json file :
+///
+/// ```json
+/// { "steps": [
+///     { "at": 0.0, "envelope": { "sustain": 0.1, "strong": 1.0, "weak": 0.5 } },
+///     { "at": 0.2, "envelope": { "sustain": 0.3, "release": 0.5, "strong": 0.6, "weak": 0.2 } }
+/// ]}
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct HapticPattern {
+    pub steps: Vec<HapticStep>,
+}
+
+impl HapticPattern {
+    pub fn new() -> HapticPattern {
+        Default::default()
+    }
+
+    pub fn then(mut self, at: f32, envelope: HapticEnvelope) -> HapticPattern {
+        self.steps.push(HapticStep { at, envelope });
+        self
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.steps
+            .iter()
+            .fold(0.0, |acc, s| acc.max(s.at + s.envelope.duration()))
+    }
+
+    pub fn sample(&self, t: f32) -> (f32, f32) {
+        self.steps.iter().fold((0.0, 0.0), |acc, s| {
+            let (strong, weak) = s.envelope.sample(t - s.at);
+            (acc.0 + strong, acc.1 + weak)
+        })
+    }
+
+    /// Sum of the peaks of the steps between `from` and `to`
+    pub fn peak(&self, from: f32, to: f32) -> (f32, f32) {
+        self.steps.iter().fold((0.0, 0.0), |acc, s| {
+            let (strong, weak) = s.envelope.peak(from - s.at, to - s.at);
+            (acc.0 + strong, acc.1 + weak)
+        })
+    }
+
+    pub fn load(asys: &AssetSystem, filename: &str) -> Resource<HapticPattern> {
+        loader::load_json(asys, filename)
+    }
+}
+
+pub struct HapticPatternLoader {}
+
+impl Loader<HapticPattern> for HapticPatternLoader {
+    fn load<A>(_asys: A, mut file: Box<File>) -> AssetResult<HapticPattern> {
+        loader::read_json(&mut file)
+    }
+}
+
+impl Loadable for HapticPattern {
+    type Loader = HapticPatternLoader;
+}
+
+struct PlayingPattern {
+    gamepad: i32,
+    pattern: HapticPattern,
+    time: f32,
+}
+
+#[derive(Default, Clone, Copy)]
+struct DeviceState {
+    strong: f32,
+    weak: f32,
+    refresh: f32,
+}
+
+/// Haptic feedback player
+pub struct Haptics {
+    pub enabled: bool,
+    /// Global magnitude multiplier
+    pub intensity: f32,
+
+    playing: Vec<PlayingPattern>,
+    devices: HashMap<i32, DeviceState>,
+}
+
+impl Default for Haptics {
+    fn default() -> Haptics {
+        Haptics {
+            enabled: true,
+            intensity: 1.0,
+            playing: Vec::new(),
+            devices: HashMap::new(),
+        }
+    }
+}
+
+impl Haptics {
+    pub fn new() -> Haptics {
+        Default::default()
+    }
+
+    /// A constant vibration
+    pub fn rumble(&mut self, gamepad: i32, strong: f32, weak: f32, duration: f32) {
+        self.play(gamepad, HapticEnvelope::new(strong, weak, duration));
+    }
+
+    pub fn play(&mut self, gamepad: i32, envelope: HapticEnvelope) {
+        self.play_pattern(gamepad, HapticPattern::new().then(0.0, envelope));
+    }
+
+    pub fn play_pattern(&mut self, gamepad: i32, pattern: HapticPattern) {
+        self.playing.push(PlayingPattern {
+            gamepad,
+            pattern,
+            time: 0.0,
+        });
+    }
+
+    pub fn is_playing(&self, gamepad: i32) -> bool {
+        self.playing.iter().any(|p| p.gamepad == gamepad)
+    }
+
+    pub fn stop(&mut self, gamepad: i32) {
+        self.playing.retain(|p| p.gamepad != gamepad);
+    }
+
+    pub fn stop_all(&mut self) {
+        self.playing.clear();
+    }
+
+    pub fn step(&mut self, dt: f32) {
+        let mut outputs: HashMap<i32, (f32, f32)> = HashMap::new();
+
+        for p in self.playing.iter_mut() {
+            let from = p.time;
+            p.time += dt;
+
+            // The peak over the frame, not to skip the steps shorter than it
+            let (strong, weak) = p.pattern.peak(from, p.time);
+            let out = outputs.entry(p.gamepad).or_insert((0.0, 0.0));
+            out.0 += strong;
+            out.1 += weak;
+        }
+
+        self.playing.retain(|p| p.time < p.pattern.duration());
+
+        // Devices which were vibrating should be stopped
+        for gamepad in self.devices.keys() {
+            outputs.entry(*gamepad).or_insert((0.0, 0.0));
+        }
+
+        let scale = if self.enabled { self.intensity } else { 0.0 };
+
+        for (gamepad, (strong, weak)) in outputs.into_iter() {
+            let strong = (strong * scale).max(0.0).min(1.0);
+            let weak = (weak * scale).max(0.0).min(1.0);
+
+            let state = self.devices.entry(gamepad).or_insert(Default::default());
+            state.refresh -= dt;
+
+            let stopping = strong == 0.0 && weak == 0.0 && (state.strong > 0.0 || state.weak > 0.0);
+            let changed = stopping || (state.strong - strong).abs() > MIN_CHANGE
+                || (state.weak - weak).abs() > MIN_CHANGE;
+            let expiring = state.refresh <= 0.0 && (strong > 0.0 || weak > 0.0);
+
+            if changed || expiring {
+                pad::gamepad_vibrate(
+                    gamepad,
+                    strong,
+                    weak,
+                    (UPDATE_DURATION * 2.0 * 1000.0) as u32,
+                );
+
+                state.strong = strong;
+                state.weak = weak;
+                state.refresh = UPDATE_DURATION;
+            }
+        }
+
+        self.devices
+            .retain(|_, s| s.strong > 0.0 || s.weak > 0.0);
+    }
+}
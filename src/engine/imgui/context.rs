@@ -10,6 +10,7 @@ use super::label::Label;
 use super::widgets;
 
 use engine::IEngine;
+use math::Vector4;
 
 struct LabelRenderer {
     go: Option<Rc<RefCell<GameObject>>>,
@@ -65,7 +66,11 @@ impl LabelRenderer {
             gomut.add_component(mesh)
         });
 
-        let hidpi = engine.hidpi_factor();
+        let (bg, contrast) = label.state.theme.shader_params(true);
+        material.set("uBackground", Vector4::new(bg.0, bg.1, bg.2, bg.3));
+        material.set("uContrast", contrast);
+
+        let hidpi = engine.hidpi_factor() * label.state.ui_scale;
         let mesh_data = {
             let mut mesh_data = label.bind(ssize, hidpi);
             let disp = widgets::compute_translate(
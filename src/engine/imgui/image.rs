@@ -1,10 +1,11 @@
-use super::Metric;
+use super::{Metric, Theme};
 use super::instance::ImguiState;
 use super::widgets;
 use super::widgets::Widget;
 
 use engine::{Asset, GameObject, IEngine, Material, Mesh, MeshBuffer, MeshData, RenderQueue,
              Texture};
+use math::Vector4;
 use std::cell::RefCell;
 use std::fmt::Debug;
 use std::rc::Rc;
@@ -35,6 +36,7 @@ fn make_quad_mesh_data(ndc_size: (f32, f32)) -> MeshData {
     MeshData {
         vertices: vertices,
         uvs: Some(uvs),
+        uvs2: None,
         normals: None,
         indices: indices,
         tangents: None,
@@ -94,6 +96,8 @@ pub struct Image {
     pos: Metric,
     size: Metric,
     pivot: Metric,
+    ui_scale: f32,
+    theme: Theme,
     kind: ImageKind,
 }
 
@@ -107,6 +111,8 @@ impl Image {
             pos,
             size,
             pivot: state.pivot,
+            ui_scale: state.ui_scale,
+            theme: state.theme,
             kind: t.into(),
         })
     }
@@ -120,6 +126,10 @@ impl Image {
                 let mut m = Material::new(db.new_program("default_ui"));
                 m.render_queue = RenderQueue::UI;
                 m.set("uDiffuse", t.0.clone());
+
+                let (bg, contrast) = self.theme.shader_params(false);
+                m.set("uBackground", Vector4::new(bg.0, bg.1, bg.2, bg.3));
+                m.set("uContrast", contrast);
                 Rc::new(m)
             }
         }
@@ -131,7 +141,7 @@ impl Image {
         parent: &GameObject,
         engine: &mut IEngine,
     ) -> Rc<RefCell<GameObject>> {
-        let hidpi = engine.hidpi_factor();
+        let hidpi = engine.hidpi_factor() * self.ui_scale;
 
         // Mesh Data
         let meshdata = make_quad_mesh_data(compute_size_to_ndc(&self.size, &ssize, hidpi));
@@ -1,11 +1,28 @@
 use super::widgets;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
 use std::rc::Rc;
 use std::sync::{Arc, Mutex};
 
-#[derive(Default, Debug, PartialEq, Copy, Clone)]
+#[derive(Debug, PartialEq, Copy, Clone)]
 pub struct ImguiState {
     pub pivot: super::Metric,
     pub text_align: super::TextAlign,
+    pub text_scale: f32,
+    pub ui_scale: f32,
+    pub theme: super::Theme,
+}
+
+impl Default for ImguiState {
+    fn default() -> ImguiState {
+        ImguiState {
+            pivot: Default::default(),
+            text_align: Default::default(),
+            text_scale: 1.0,
+            ui_scale: 1.0,
+            theme: Default::default(),
+        }
+    }
 }
 
 #[derive(Default, Debug)]
@@ -20,11 +37,37 @@ pub struct Imgui {
 }
 
 thread_local!(
-    static INSTANCE: Arc<Mutex<ImguiRaw>> = Arc::new(Mutex::new(Default::default()))
+    static INSTANCES: RefCell<HashMap<u32, Arc<Mutex<ImguiRaw>>>> = RefCell::new(HashMap::new())
 );
+thread_local!(static CURRENT: Cell<u32> = Cell::new(0));
+thread_local!(static NEXT_SCOPE: Cell<u32> = Cell::new(1));
 
+/// The state of the current scope, see `ImguiScope`
 pub fn imgui_inst() -> Imgui {
-    return Imgui {
-        inner: INSTANCE.with(|f| f.clone()),
-    };
+    let current = CURRENT.with(|c| c.get());
+    let inner = INSTANCES.with(|instances| {
+        instances
+            .borrow_mut()
+            .entry(current)
+            .or_insert_with(|| Arc::new(Mutex::new(Default::default())))
+            .clone()
+    });
+
+    Imgui { inner }
+}
+
+pub fn new_scope() -> u32 {
+    NEXT_SCOPE.with(|next| {
+        let id = next.get();
+        next.set(id + 1);
+        id
+    })
+}
+
+pub fn enter_scope(id: u32) {
+    CURRENT.with(|c| c.set(id));
+}
+
+pub fn drop_scope(id: u32) {
+    INSTANCES.with(|instances| instances.borrow_mut().remove(&id));
 }
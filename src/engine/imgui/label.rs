@@ -118,6 +118,7 @@ fn make_text_mesh_data(text_data: TextData) -> MeshData {
     MeshData {
         vertices: vertices,
         uvs: Some(uvs),
+        uvs2: None,
         normals: None,
         indices: indices,
         tangents: None,
@@ -150,7 +151,7 @@ impl Label {
                 s: self.s.clone(),
                 align: self.state.text_align,
                 font_data: BitmapFontData {
-                    hidpi,
+                    hidpi: hidpi * self.state.text_scale,
                     screen_size: ssize,
                     texture_size: (128, 64),
                     font_size: (8, 8),
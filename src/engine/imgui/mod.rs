@@ -13,6 +13,13 @@
 //!     pivot(0,0) => represent the top-left corner of element will be placed in (x,y)
 //!     pivot(1,1) => represent the bottom-right corner of element will be place in (x,y)
 //!
+//! Global settings
+//!     `set_ui_scale` and `set_theme` affect all elements until they are changed again.
+//!     The other settings are reset by `begin`, at the start of each frame.
+//!
+//! Each engine has its own `ImguiScope`, so several worlds on one page have their own
+//! elements and settings. The functions act on the world of the current frame, or the
+//! world built last outside of a frame.
 //!
 
 mod context;
@@ -42,12 +49,72 @@ impl Default for TextAlign {
     }
 }
 
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Theme {
+    Default,
+    /// Text on opaque black backdrop, images with higher contrast
+    HighContrast,
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme::Default
+    }
+}
+
+impl Theme {
+    /// (backdrop color, contrast) of ui shader, for text or images
+    pub(crate) fn shader_params(&self, text: bool) -> ((f32, f32, f32, f32), f32) {
+        match *self {
+            Theme::Default => ((0.0, 0.0, 0.0, 0.0), 0.0),
+            Theme::HighContrast if text => ((0.0, 0.0, 0.0, 1.0), 1.5),
+            Theme::HighContrast => ((0.0, 0.0, 0.0, 0.0), 1.5),
+        }
+    }
+}
+
+/// The elements and settings of a world, see `Engine::begin`
+#[derive(Debug)]
+pub struct ImguiScope(u32);
+
+impl ImguiScope {
+    pub fn new() -> ImguiScope {
+        ImguiScope(instance::new_scope())
+    }
+
+    /// The imgui functions act on this scope until another is entered
+    pub fn enter(&self) {
+        instance::enter_scope(self.0);
+    }
+}
+
+impl Default for ImguiScope {
+    fn default() -> ImguiScope {
+        ImguiScope::new()
+    }
+}
+
+impl Drop for ImguiScope {
+    fn drop(&mut self) {
+        instance::drop_scope(self.0);
+    }
+}
+
+/// Start the elements of a frame, in the current scope
 pub fn begin() {
     let imgui = instance::imgui_inst();
     let mut inner = imgui.inner.lock().unwrap();
     inner.id = 0;
 
     inner.render_list.clear();
+
+    // Only the global settings last from a frame to the next
+    let state = inner.state;
+    inner.state = instance::ImguiState {
+        ui_scale: state.ui_scale,
+        theme: state.theme,
+        ..Default::default()
+    };
 }
 
 fn add_widget<F>(f: F)
@@ -73,6 +140,19 @@ pub fn pivot(p: (f32, f32)) {
     inner.state.pivot = Metric::Native(p.0, p.1);
 }
 
+/// The current pivot, to restore it after placing an element with another
+pub(crate) fn saved_pivot() -> Metric {
+    let imgui = instance::imgui_inst();
+    let inner = imgui.inner.lock().unwrap();
+    inner.state.pivot
+}
+
+pub(crate) fn restore_pivot(p: Metric) {
+    let imgui = instance::imgui_inst();
+    let mut inner = imgui.inner.lock().unwrap();
+    inner.state.pivot = p;
+}
+
 /// Text align setting
 pub fn text_align(align: TextAlign) {
     let imgui = instance::imgui_inst();
@@ -80,12 +160,87 @@ pub fn text_align(align: TextAlign) {
     inner.state.text_align = align;
 }
 
+/// Size multiplier of all elements, on top of the hidpi factor
+pub fn set_ui_scale(scale: f32) {
+    let imgui = instance::imgui_inst();
+    let mut inner = imgui.inner.lock().unwrap();
+    inner.state.ui_scale = scale;
+}
+
+pub fn ui_scale() -> f32 {
+    let imgui = instance::imgui_inst();
+    let inner = imgui.inner.lock().unwrap();
+    inner.state.ui_scale
+}
+
+pub fn set_theme(theme: Theme) {
+    let imgui = instance::imgui_inst();
+    let mut inner = imgui.inner.lock().unwrap();
+    inner.state.theme = theme;
+}
+
+pub fn theme() -> Theme {
+    let imgui = instance::imgui_inst();
+    let inner = imgui.inner.lock().unwrap();
+    inner.state.theme
+}
+
+/// Text size multiplier of the next label
+pub fn text_scale(scale: f32) {
+    let imgui = instance::imgui_inst();
+    let mut inner = imgui.inner.lock().unwrap();
+    inner.state.text_scale = scale;
+}
+
 /// Label
 pub fn label(pos: Metric, s: &str) {
     add_widget(|id, state| label::Label::new(id, pos, state, s.into()));
 
     // reset text settings
     text_align(TextAlign::default());
+    text_scale(1.0);
+}
+
+/// A part of a `rich_label`
+#[derive(Debug, Clone)]
+pub enum RichSpan {
+    Text(String),
+    /// An icon of the size of a character, e.g. an input glyph
+    Icon(Rc<Texture>),
+}
+
+/// Label with inline icons, on a single line. The spans are placed from `pos` by the
+/// top-left corner, whatever the pivot.
+pub fn rich_label(pos: Metric, spans: &[RichSpan]) {
+    let scale = {
+        let imgui = instance::imgui_inst();
+        let inner = imgui.inner.lock().unwrap();
+        inner.state.text_scale
+    };
+    // Characters of the bitmap font are 8 pixels wide
+    let size = 8.0 * scale;
+
+    let saved_pivot = saved_pivot();
+    pivot((0.0, 0.0));
+
+    let mut x = 0.0;
+    for span in spans.iter() {
+        let at = pos + Metric::Pixel(x, 0.0);
+        match *span {
+            RichSpan::Text(ref s) => {
+                text_scale(scale);
+                label(at, s);
+                x += s.chars().count() as f32 * size;
+            }
+            RichSpan::Icon(ref tex) => {
+                image(at, Metric::Pixel(size, size), tex.clone());
+                x += size;
+            }
+        }
+    }
+
+    restore_pivot(saved_pivot);
+    text_scale(1.0);
 }
 
 /// Image
@@ -0,0 +1,188 @@
+//! Action map, named game actions bound to keys, mouse and gamepad buttons
+//!
+//! Gameplay code asks for actions (e.g. `"jump"`) instead of raw keys, so the bindings
+//! could be changed by the player or loaded from a json file.
+
+use engine::asset::loader::{self, Loadable, Loader};
+use engine::asset::{AssetResult, AssetSystem, File, Resource};
+use uni_app::AppEvent;
+use uni_pad as pad;
+
+use std::collections::{BTreeMap, HashSet};
+use std::mem;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum Binding {
+    /// Key code, e.g. "KeyT", "Space"
+    Key(String),
+    MouseButton(usize),
+    GamepadButton { gamepad: i32, button: i32 },
+}
+
+/// Input devices of a player, see `ActionMap::for_device`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum InputDevice {
+    KeyboardMouse,
+    Gamepad(i32),
+}
+
+/// Action bindings, as a json file :
+///
+/// ```json
+/// { "actions": {
+///     "jump": [ { "Key": "Space" }, { "GamepadButton": { "gamepad": 0, "button": 0 } } ],
+///     "push_to_talk": [ { "Key": "KeyT" } ]
+/// }}
+/// ```
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ActionMap {
+    #[serde(default)]
+    pub actions: BTreeMap<String, Vec<Binding>>,
+
+    #[serde(skip)]
+    keys: HashSet<String>,
+    #[serde(skip)]
+    mouse_buttons: HashSet<usize>,
+    #[serde(skip)]
+    down: HashSet<String>,
+    #[serde(skip)]
+    prev_down: HashSet<String>,
+    /// Gamepad buttons of an input playback, instead of the polled gamepads
+    #[serde(skip)]
+    replayed_buttons: Option<HashSet<(i32, i32)>>,
+}
+
+impl ActionMap {
+    pub fn new() -> ActionMap {
+        Default::default()
+    }
+
+    pub fn load(asys: &AssetSystem, filename: &str) -> Resource<ActionMap> {
+        loader::load_json(asys, filename)
+    }
+
+    pub fn bind(&mut self, action: &str, binding: Binding) {
+        let bindings = self.actions
+            .entry(action.to_owned())
+            .or_insert_with(Vec::new);
+
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    pub fn unbind(&mut self, action: &str, binding: &Binding) {
+        if let Some(bindings) = self.actions.get_mut(action) {
+            bindings.retain(|b| b != binding);
+        }
+    }
+
+    pub fn clear_bindings(&mut self, action: &str) {
+        self.actions.remove(action);
+    }
+
+    pub fn bindings(&self, action: &str) -> &[Binding] {
+        self.actions
+            .get(action)
+            .map(|b| b.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Replace the bindings by those of another map (e.g. loaded from file), keeping the input state
+    pub fn set_bindings(&mut self, other: &ActionMap) {
+        self.actions = other.actions.clone();
+    }
+
+    /// The bindings of one player's device, e.g. in split-screen.
+    /// Gamepad bindings are moved to the gamepad of the device, whatever their gamepad index.
+    pub fn for_device(&self, device: InputDevice) -> ActionMap {
+        let mut map = ActionMap::new();
+
+        for (action, bindings) in self.actions.iter() {
+            for binding in bindings.iter() {
+                let binding = match (device, binding) {
+                    (InputDevice::KeyboardMouse, &Binding::Key(_))
+                    | (InputDevice::KeyboardMouse, &Binding::MouseButton(_)) => binding.clone(),
+                    (InputDevice::Gamepad(gamepad), &Binding::GamepadButton { button, .. }) => {
+                        Binding::GamepadButton { gamepad, button }
+                    }
+                    _ => continue,
+                };
+
+                map.bind(action, binding);
+            }
+        }
+
+        map
+    }
+
+    pub fn is_down(&self, action: &str) -> bool {
+        self.down.contains(action)
+    }
+
+    /// The action is down this frame but not in the last frame
+    pub fn just_pressed(&self, action: &str) -> bool {
+        self.down.contains(action) && !self.prev_down.contains(action)
+    }
+
+    pub fn just_released(&self, action: &str) -> bool {
+        !self.down.contains(action) && self.prev_down.contains(action)
+    }
+
+    pub fn is_binding_down(&self, binding: &Binding) -> bool {
+        match *binding {
+            Binding::Key(ref code) => self.keys.contains(code),
+            Binding::MouseButton(button) => self.mouse_buttons.contains(&button),
+            Binding::GamepadButton { gamepad, button } => match self.replayed_buttons {
+                Some(ref buttons) => buttons.contains(&(gamepad, button)),
+                None => pad::gamepad_button(gamepad, button),
+            },
+        }
+    }
+
+    /// Gamepad buttons (gamepad, button) down, replacing the real gamepads until `None`,
+    /// see `InputRecorder`
+    pub fn set_replayed_buttons(&mut self, buttons: Option<HashSet<(i32, i32)>>) {
+        self.replayed_buttons = buttons;
+    }
+
+    pub fn step(&mut self, events: &[AppEvent]) {
+        for evt in events.iter() {
+            match evt {
+                &AppEvent::KeyDown(ref key) => {
+                    self.keys.insert(key.code.clone());
+                }
+                &AppEvent::KeyUp(ref key) => {
+                    self.keys.remove(&key.code);
+                }
+                &AppEvent::MouseDown(ref e) => {
+                    self.mouse_buttons.insert(e.button);
+                }
+                &AppEvent::MouseUp(ref e) => {
+                    self.mouse_buttons.remove(&e.button);
+                }
+                _ => (),
+            }
+        }
+
+        let down: HashSet<String> = self.actions
+            .iter()
+            .filter(|&(_, bindings)| bindings.iter().any(|b| self.is_binding_down(b)))
+            .map(|(action, _)| action.clone())
+            .collect();
+
+        self.prev_down = mem::replace(&mut self.down, down);
+    }
+}
+
+pub struct ActionMapLoader {}
+
+impl Loader<ActionMap> for ActionMapLoader {
+    fn load<A>(_asys: A, mut file: Box<File>) -> AssetResult<ActionMap> {
+        loader::read_json(&mut file)
+    }
+}
+
+impl Loadable for ActionMap {
+    type Loader = ActionMapLoader;
+}
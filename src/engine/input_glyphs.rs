@@ -0,0 +1,265 @@
+//! Input glyphs, the icons of the bindings of the actions for the last used device.
+//!
+//! UI text refers to actions by `{action}`, replaced by the icon of its binding on the device
+//! the player used last, e.g. "Press {jump} to jump" shows the space bar after a key press
+//! and the A button after a gamepad press. The icons are set by style, as a json file :
+//!
+//! ```json
+//! { "styles": {
+//!     "Keyboard": { "Space": "glyphs/kb_space.png", "Mouse0": "glyphs/mouse_left.png" },
+//!     "Xbox": { "Button0": "glyphs/xbox_a.png" },
+//!     "PlayStation": { "Button0": "glyphs/ps_cross.png" }
+//! }}
+//! ```
+//!
+//! Keys are named by their key code, mouse and gamepad buttons by their index.
+
+use engine::asset::loader::{self, Loadable, Loader};
+use engine::asset::{AssetResult, AssetSystem, File, Resource};
+#[cfg(feature = "ui")]
+use engine::imgui::RichSpan;
+use engine::input::{ActionMap, Binding, InputDevice};
+use uni_app::AppEvent;
+use uni_pad as pad;
+
+use std::collections::BTreeMap;
+
+/// Gamepads polled for the last used device
+const MAX_GAMEPADS: i32 = 4;
+
+/// Family of icons of a device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+pub enum GlyphStyle {
+    Keyboard,
+    Xbox,
+    PlayStation,
+    Nintendo,
+}
+
+impl GlyphStyle {
+    /// Style of a gamepad from its id, Xbox for the unknown gamepads
+    pub fn from_gamepad_id(id: &str) -> GlyphStyle {
+        fn has(id: &str, names: &[&str]) -> bool {
+            names.iter().any(|n| id.contains(n))
+        }
+
+        let id = id.to_lowercase();
+        if has(&id, &["054c", "playstation", "dualshock", "dualsense", "sony"]) {
+            GlyphStyle::PlayStation
+        } else if has(&id, &["057e", "nintendo", "switch", "joy-con"]) {
+            GlyphStyle::Nintendo
+        } else {
+            GlyphStyle::Xbox
+        }
+    }
+}
+
+/// A part of a text with glyphs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GlyphSpan {
+    Text(String),
+    /// Texture name of the icon
+    Icon(String),
+}
+
+/// Name of a binding in the icon tables
+pub fn binding_name(binding: &Binding) -> String {
+    match *binding {
+        Binding::Key(ref code) => code.clone(),
+        Binding::MouseButton(button) => format!("Mouse{}", button),
+        Binding::GamepadButton { button, .. } => format!("Button{}", button),
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct InputGlyphs {
+    #[serde(default)]
+    pub styles: BTreeMap<GlyphStyle, BTreeMap<String, String>>,
+
+    #[serde(skip, default = "default_device")]
+    device: InputDevice,
+    #[serde(skip, default = "default_style")]
+    style: GlyphStyle,
+    #[serde(skip)]
+    changed: bool,
+}
+
+fn default_device() -> InputDevice {
+    InputDevice::KeyboardMouse
+}
+
+fn default_style() -> GlyphStyle {
+    GlyphStyle::Keyboard
+}
+
+impl Default for InputGlyphs {
+    fn default() -> InputGlyphs {
+        InputGlyphs {
+            styles: BTreeMap::new(),
+            device: default_device(),
+            style: default_style(),
+            changed: false,
+        }
+    }
+}
+
+impl InputGlyphs {
+    pub fn new() -> InputGlyphs {
+        Default::default()
+    }
+
+    pub fn load(asys: &AssetSystem, filename: &str) -> Resource<InputGlyphs> {
+        loader::load_json(asys, filename)
+    }
+
+    /// Replace the icons by those of another set (e.g. loaded from file), keeping the device
+    pub fn set_icons(&mut self, other: &InputGlyphs) {
+        self.styles = other.styles.clone();
+    }
+
+    pub fn set_icon(&mut self, style: GlyphStyle, binding: &Binding, texture: &str) {
+        self.styles
+            .entry(style)
+            .or_insert_with(BTreeMap::new)
+            .insert(binding_name(binding), texture.to_owned());
+    }
+
+    /// The last used device
+    pub fn device(&self) -> InputDevice {
+        self.device
+    }
+
+    pub fn style(&self) -> GlyphStyle {
+        self.style
+    }
+
+    /// Whether the device changed at the last step, e.g. to refresh the prompts
+    pub fn changed(&self) -> bool {
+        self.changed
+    }
+
+    /// Use the icons of a device, until another one is used
+    pub fn set_device(&mut self, device: InputDevice) {
+        let style = match device {
+            InputDevice::KeyboardMouse => GlyphStyle::Keyboard,
+            InputDevice::Gamepad(gamepad) => pad::gamepad_id(gamepad)
+                .map_or(GlyphStyle::Xbox, |id| GlyphStyle::from_gamepad_id(&id)),
+        };
+
+        self.changed = self.changed || device != self.device || style != self.style;
+        self.device = device;
+        self.style = style;
+    }
+
+    /// Binding of an action on the last used device
+    pub fn binding<'a>(&self, actions: &'a ActionMap, action: &str) -> Option<&'a Binding> {
+        actions
+            .bindings(action)
+            .iter()
+            .find(|b| match (self.device, *b) {
+                (InputDevice::KeyboardMouse, &Binding::Key(_))
+                | (InputDevice::KeyboardMouse, &Binding::MouseButton(_)) => true,
+                (InputDevice::Gamepad(_), &Binding::GamepadButton { .. }) => true,
+                _ => false,
+            })
+    }
+
+    /// Texture name of the icon of an action on the last used device
+    pub fn glyph(&self, actions: &ActionMap, action: &str) -> Option<&str> {
+        let binding = self.binding(actions, action)?;
+        self.styles
+            .get(&self.style)
+            .and_then(|icons| icons.get(&binding_name(binding)))
+            .map(|s| s.as_str())
+    }
+
+    /// Split a text at its `{action}` tags, replaced by their icons. Actions without icon
+    /// show the name of their binding, e.g. `[KeyE]`, and `{{` is a literal brace.
+    pub fn markup(&self, actions: &ActionMap, text: &str) -> Vec<GlyphSpan> {
+        let mut spans = Vec::new();
+        let mut current = String::new();
+        let mut chars = text.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            if c != '{' {
+                current.push(c);
+                continue;
+            }
+            if chars.peek() == Some(&'{') {
+                chars.next();
+                current.push('{');
+                continue;
+            }
+
+            let action: String = chars.by_ref().take_while(|c| *c != '}').collect();
+            match self.glyph(actions, &action) {
+                Some(icon) => {
+                    if current.len() > 0 {
+                        spans.push(GlyphSpan::Text(current.clone()));
+                        current.clear();
+                    }
+                    spans.push(GlyphSpan::Icon(icon.to_owned()));
+                }
+                None => {
+                    let name = self.binding(actions, &action)
+                        .map_or(action.clone(), binding_name);
+                    current.push_str(&format!("[{}]", name));
+                }
+            }
+        }
+
+        if current.len() > 0 {
+            spans.push(GlyphSpan::Text(current));
+        }
+        spans
+    }
+
+    /// The markup of a text as imgui spans, see `imgui::rich_label`
+    #[cfg(feature = "ui")]
+    pub fn rich_text(&self, actions: &ActionMap, text: &str, asys: &AssetSystem) -> Vec<RichSpan> {
+        self.markup(actions, text)
+            .into_iter()
+            .map(|span| match span {
+                GlyphSpan::Text(s) => RichSpan::Text(s),
+                GlyphSpan::Icon(name) => RichSpan::Icon(asys.new_texture(&name)),
+            })
+            .collect()
+    }
+
+    /// Switch to the device which got an input
+    pub fn step(&mut self, events: &[AppEvent]) {
+        self.changed = false;
+
+        let keyboard = events.iter().any(|evt| match *evt {
+            AppEvent::KeyDown(_) | AppEvent::MouseDown(_) => true,
+            _ => false,
+        });
+        if keyboard {
+            self.set_device(InputDevice::KeyboardMouse);
+            return;
+        }
+
+        // Stay on the current gamepad while it is used
+        if let InputDevice::Gamepad(gamepad) = self.device {
+            if pad::gamepad_any_input(gamepad) {
+                return;
+            }
+        }
+
+        if let Some(gamepad) = (0..MAX_GAMEPADS).find(|g| pad::gamepad_any_input(*g)) {
+            self.set_device(InputDevice::Gamepad(gamepad));
+        }
+    }
+}
+
+pub struct InputGlyphsLoader {}
+
+impl Loader<InputGlyphs> for InputGlyphsLoader {
+    fn load<A>(_asys: A, mut file: Box<File>) -> AssetResult<InputGlyphs> {
+        loader::read_json(&mut file)
+    }
+}
+
+impl Loadable for InputGlyphs {
+    type Loader = InputGlyphsLoader;
+}
@@ -0,0 +1,450 @@
+//! Input recording and playback, for attract-mode demos, tutorials and UI walkthrough tests.
+//!
+//! The raw keyboard, mouse and gamepad input is recorded with its timestamps, and played back
+//! through the event stream of the world, so the game reacts as if a player was there.
+//! This is not a deterministic replay: the simulation is not recorded, a playback only
+//! matches the recording when the game starts from the same state.
+//!
+//! ```ignore
+//! world.input_recorder.start_recording();
+//! // ...
+//! let json = world.input_recorder.stop_recording().to_json();
+//!
+//! // Attract mode
+//! let demo = InputRecording::load(world.asset_system(), "demo_input.json");
+//! world.input_recorder.looping = true;
+//! world.input_recorder.play(demo.try_into().unwrap());
+//! ```
+//!
+//! Replayed gamepad buttons are seen by the `ActionMap` only, not by `uni_pad`.
+
+use engine::asset::loader::{self, Loadable, Loader};
+use engine::asset::{AssetResult, AssetSystem, File, Resource};
+use engine::input::ActionMap;
+use serde_json;
+use uni_app::{now, AppEvent, KeyEvent, MouseButtonEvent};
+use uni_pad as pad;
+
+use std::collections::{BTreeMap, HashSet};
+use std::mem;
+
+/// Gamepads and buttons (standard mapping) polled while recording
+const MAX_GAMEPADS: i32 = 4;
+const MAX_BUTTONS: i32 = 17;
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub enum RecordedEvent {
+    KeyDown {
+        code: String,
+        key: String,
+        shift: bool,
+        alt: bool,
+        ctrl: bool,
+    },
+    KeyUp {
+        code: String,
+        key: String,
+        shift: bool,
+        alt: bool,
+        ctrl: bool,
+    },
+    MouseDown(usize),
+    MouseUp(usize),
+    Click(usize),
+    MousePos(f64, f64),
+    Char(char),
+    GamepadButton {
+        gamepad: i32,
+        button: i32,
+        pressed: bool,
+    },
+}
+
+impl RecordedEvent {
+    /// The recorded input event, `None` for the events which are not input (e.g. resize)
+    pub fn from_event(evt: &AppEvent) -> Option<RecordedEvent> {
+        Some(match *evt {
+            AppEvent::KeyDown(ref k) => RecordedEvent::KeyDown {
+                code: k.code.clone(),
+                key: k.key.clone(),
+                shift: k.shift,
+                alt: k.alt,
+                ctrl: k.ctrl,
+            },
+            AppEvent::KeyUp(ref k) => RecordedEvent::KeyUp {
+                code: k.code.clone(),
+                key: k.key.clone(),
+                shift: k.shift,
+                alt: k.alt,
+                ctrl: k.ctrl,
+            },
+            AppEvent::MouseDown(ref e) => RecordedEvent::MouseDown(e.button),
+            AppEvent::MouseUp(ref e) => RecordedEvent::MouseUp(e.button),
+            AppEvent::Click(ref e) => RecordedEvent::Click(e.button),
+            AppEvent::MousePos((x, y)) => RecordedEvent::MousePos(x, y),
+            AppEvent::CharEvent(c) => RecordedEvent::Char(c),
+            _ => return None,
+        })
+    }
+
+    /// The app event to play back, `None` for the gamepad buttons
+    pub fn to_event(&self) -> Option<AppEvent> {
+        Some(match *self {
+            RecordedEvent::KeyDown {
+                ref code,
+                ref key,
+                shift,
+                alt,
+                ctrl,
+            } => AppEvent::KeyDown(KeyEvent {
+                code: code.clone(),
+                key: key.clone(),
+                shift,
+                alt,
+                ctrl,
+            }),
+            RecordedEvent::KeyUp {
+                ref code,
+                ref key,
+                shift,
+                alt,
+                ctrl,
+            } => AppEvent::KeyUp(KeyEvent {
+                code: code.clone(),
+                key: key.clone(),
+                shift,
+                alt,
+                ctrl,
+            }),
+            RecordedEvent::MouseDown(button) => AppEvent::MouseDown(MouseButtonEvent { button }),
+            RecordedEvent::MouseUp(button) => AppEvent::MouseUp(MouseButtonEvent { button }),
+            RecordedEvent::Click(button) => AppEvent::Click(MouseButtonEvent { button }),
+            RecordedEvent::MousePos(x, y) => AppEvent::MousePos((x, y)),
+            RecordedEvent::Char(c) => AppEvent::CharEvent(c),
+            RecordedEvent::GamepadButton { .. } => return None,
+        })
+    }
+}
+
+/// The input of one frame, `time` in seconds since the start of the recording
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct InputFrame {
+    pub time: f64,
+    pub events: Vec<RecordedEvent>,
+}
+
+/// A recorded input stream, as a json file. Frames without input are not stored.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct InputRecording {
+    pub frames: Vec<InputFrame>,
+    /// Length of the recording in seconds, the playback lasts until then
+    pub duration: f64,
+}
+
+impl InputRecording {
+    pub fn new() -> InputRecording {
+        Default::default()
+    }
+
+    pub fn load(asys: &AssetSystem, filename: &str) -> Resource<InputRecording> {
+        loader::load_json(asys, filename)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+pub struct InputRecordingLoader {}
+
+impl Loader<InputRecording> for InputRecordingLoader {
+    fn load<A>(_asys: A, mut file: Box<File>) -> AssetResult<InputRecording> {
+        loader::read_json(&mut file)
+    }
+}
+
+impl Loadable for InputRecording {
+    type Loader = InputRecordingLoader;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecorderState {
+    Idle,
+    Recording,
+    Playing,
+}
+
+pub struct InputRecorder {
+    /// Restart the playback at its end, e.g. an attract-mode demo
+    pub looping: bool,
+    /// Drop the live keyboard and mouse input while playing
+    pub block_live_input: bool,
+
+    state: RecorderState,
+    recording: InputRecording,
+    start_time: f64,
+    /// Next frame to play
+    cursor: usize,
+    /// Gamepad buttons down, polled while recording or replayed while playing
+    buttons: HashSet<(i32, i32)>,
+    /// Keys and mouse buttons down in the playback, released when it stops
+    held: BTreeMap<String, RecordedEvent>,
+    /// Release events of a stopped playback, sent at the next step
+    released: Vec<AppEvent>,
+}
+
+impl Default for InputRecorder {
+    fn default() -> InputRecorder {
+        InputRecorder::new()
+    }
+}
+
+impl InputRecorder {
+    pub fn new() -> InputRecorder {
+        InputRecorder {
+            looping: false,
+            block_live_input: true,
+            state: RecorderState::Idle,
+            recording: InputRecording::new(),
+            start_time: 0.0,
+            cursor: 0,
+            buttons: HashSet::new(),
+            held: BTreeMap::new(),
+            released: Vec::new(),
+        }
+    }
+
+    pub fn state(&self) -> RecorderState {
+        self.state
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.state == RecorderState::Recording
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.state == RecorderState::Playing
+    }
+
+    pub fn start_recording(&mut self) {
+        self.start_recording_at(now());
+    }
+
+    /// Start a recording, `time` in seconds on the clock given to `record`
+    pub fn start_recording_at(&mut self, time: f64) {
+        self.stop_playback();
+        self.state = RecorderState::Recording;
+        self.recording = InputRecording::new();
+        self.start_time = time;
+        self.buttons.clear();
+    }
+
+    pub fn stop_recording(&mut self) -> InputRecording {
+        self.stop_recording_at(now())
+    }
+
+    pub fn stop_recording_at(&mut self, time: f64) -> InputRecording {
+        if !self.is_recording() {
+            return InputRecording::new();
+        }
+
+        self.state = RecorderState::Idle;
+        self.recording.duration = time - self.start_time;
+        self.buttons.clear();
+        mem::replace(&mut self.recording, InputRecording::new())
+    }
+
+    pub fn play(&mut self, recording: InputRecording) {
+        self.play_at(recording, now());
+    }
+
+    pub fn play_at(&mut self, recording: InputRecording, time: f64) {
+        if self.is_recording() {
+            self.stop_recording_at(time);
+        }
+
+        self.state = RecorderState::Playing;
+        self.recording = recording;
+        self.start_time = time;
+        self.cursor = 0;
+        self.buttons.clear();
+        self.held.clear();
+    }
+
+    /// Stop the playback, the keys and mouse buttons still down in it are released
+    /// at the next step
+    pub fn stop_playback(&mut self) {
+        if !self.is_playing() {
+            return;
+        }
+
+        self.state = RecorderState::Idle;
+        self.buttons.clear();
+        let released = self.release_held();
+        self.released.extend(released);
+    }
+
+    /// Record the input of a frame, `buttons` the gamepad buttons (gamepad, button) down
+    pub fn record(&mut self, time: f64, events: &[AppEvent], buttons: &HashSet<(i32, i32)>) {
+        if !self.is_recording() {
+            return;
+        }
+
+        let mut recorded: Vec<RecordedEvent> = events
+            .iter()
+            .filter_map(RecordedEvent::from_event)
+            .collect();
+
+        for &(gamepad, button) in buttons.difference(&self.buttons) {
+            recorded.push(RecordedEvent::GamepadButton {
+                gamepad,
+                button,
+                pressed: true,
+            });
+        }
+        for &(gamepad, button) in self.buttons.difference(buttons) {
+            recorded.push(RecordedEvent::GamepadButton {
+                gamepad,
+                button,
+                pressed: false,
+            });
+        }
+        self.buttons = buttons.clone();
+
+        if !recorded.is_empty() {
+            self.recording.frames.push(InputFrame {
+                time: time - self.start_time,
+                events: recorded,
+            });
+        }
+    }
+
+    /// The recorded events due at `time`, the replayed gamepad buttons are in `buttons`
+    pub fn playback(&mut self, time: f64) -> Vec<AppEvent> {
+        let mut events = mem::replace(&mut self.released, Vec::new());
+        if !self.is_playing() {
+            return events;
+        }
+
+        let elapsed = time - self.start_time;
+        while self.cursor < self.recording.frames.len() {
+            if self.recording.frames[self.cursor].time > elapsed {
+                break;
+            }
+
+            for evt in self.recording.frames[self.cursor].events.iter() {
+                match *evt {
+                    RecordedEvent::KeyDown { ref code, .. } => {
+                        self.held.insert(code.clone(), evt.clone());
+                    }
+                    RecordedEvent::KeyUp { ref code, .. } => {
+                        self.held.remove(code);
+                    }
+                    RecordedEvent::MouseDown(button) => {
+                        self.held.insert(format!("mouse{}", button), evt.clone());
+                    }
+                    RecordedEvent::MouseUp(button) => {
+                        self.held.remove(&format!("mouse{}", button));
+                    }
+                    RecordedEvent::GamepadButton {
+                        gamepad,
+                        button,
+                        pressed,
+                    } => {
+                        if pressed {
+                            self.buttons.insert((gamepad, button));
+                        } else {
+                            self.buttons.remove(&(gamepad, button));
+                        }
+                    }
+                    _ => (),
+                }
+
+                events.extend(evt.to_event());
+            }
+
+            self.cursor += 1;
+        }
+
+        if self.cursor >= self.recording.frames.len() && elapsed >= self.recording.duration {
+            events.extend(self.release_held());
+            self.buttons.clear();
+
+            if self.looping {
+                self.start_time = time;
+                self.cursor = 0;
+            } else {
+                self.state = RecorderState::Idle;
+            }
+        }
+
+        events
+    }
+
+    /// Gamepad buttons down in the playback
+    pub fn buttons(&self) -> &HashSet<(i32, i32)> {
+        &self.buttons
+    }
+
+    /// Record or play back the input of this frame, through the world events
+    pub fn step(&mut self, events: &mut Vec<AppEvent>, actions: &mut ActionMap) {
+        events.extend(self.released.drain(..));
+
+        match self.state {
+            RecorderState::Idle => (),
+            RecorderState::Recording => {
+                let mut buttons = HashSet::new();
+                for gamepad in 0..MAX_GAMEPADS {
+                    for button in 0..MAX_BUTTONS {
+                        if pad::gamepad_button(gamepad, button) {
+                            buttons.insert((gamepad, button));
+                        }
+                    }
+                }
+
+                self.record(now(), events, &buttons);
+            }
+            RecorderState::Playing => {
+                if self.block_live_input {
+                    events.retain(|evt| RecordedEvent::from_event(evt).is_none());
+                }
+
+                let played = self.playback(now());
+                events.extend(played);
+
+                if self.is_playing() {
+                    actions.set_replayed_buttons(Some(self.buttons.clone()));
+                    return;
+                }
+            }
+        }
+
+        actions.set_replayed_buttons(None);
+    }
+
+    fn release_held(&mut self) -> Vec<AppEvent> {
+        let held = mem::replace(&mut self.held, BTreeMap::new());
+
+        held.into_iter()
+            .filter_map(|(_, evt)| match evt {
+                RecordedEvent::KeyDown {
+                    code,
+                    key,
+                    shift,
+                    alt,
+                    ctrl,
+                } => Some(AppEvent::KeyUp(KeyEvent {
+                    code,
+                    key,
+                    shift,
+                    alt,
+                    ctrl,
+                })),
+                RecordedEvent::MouseDown(button) => {
+                    Some(AppEvent::MouseUp(MouseButtonEvent { button }))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+}
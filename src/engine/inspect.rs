@@ -0,0 +1,293 @@
+//! Field reflection of components
+//!
+//! `#[derive(Inspect)]` exposes the named fields of a struct with their kind and range, so the
+//! inspector, scene serialization, data binding and prefab overrides work on any component
+//! without glue code:
+//!
+//! ```ignore
+//! #[derive(Component, Inspect)]
+//! pub struct Health {
+//!     #[inspect(min = 0.0, max = 100.0)]
+//!     pub value: f32,
+//!     pub invincible: bool,
+//!     #[inspect(skip)]
+//!     timer: f32,
+//! }
+//! ```
+//!
+//! Fields must implement `InspectField`. Negative bounds are written as strings
+//! (`min = "-1.0"`), attribute literals can not be negative.
+
+#[cfg(feature = "ui")]
+use engine::imgui::{self, Metric};
+
+use math::*;
+use serde_json;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FieldKind {
+    Bool,
+    Int,
+    Float,
+    String,
+    Vec2,
+    Vec3,
+    Vec4,
+}
+
+/// Value of a field, numbers are widened to 64 bits
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum FieldValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    Vec2([f32; 2]),
+    Vec3([f32; 3]),
+    Vec4([f32; 4]),
+}
+
+impl FieldValue {
+    /// Numbers clamped to the range, other values unchanged
+    pub fn clamp(&self, range: Option<(f64, f64)>) -> FieldValue {
+        let (lo, hi) = match range {
+            Some(r) => r,
+            None => return self.clone(),
+        };
+
+        match *self {
+            FieldValue::Int(v) => FieldValue::Int(v.max(lo.ceil() as i64).min(hi.floor() as i64)),
+            FieldValue::Float(v) => FieldValue::Float(v.max(lo).min(hi)),
+            _ => self.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldInfo {
+    pub name: &'static str,
+    pub kind: FieldKind,
+    /// Bounds of a number, from `#[inspect(min = .., max = ..)]`
+    pub range: Option<(f64, f64)>,
+}
+
+/// Conversion of a field type from and to `FieldValue`
+pub trait InspectField: Sized {
+    fn kind() -> FieldKind;
+    fn to_value(&self) -> FieldValue;
+    fn from_value(value: &FieldValue) -> Option<Self>;
+}
+
+macro_rules! impl_inspect_int {
+    ($($t:ty),*) => {$(
+        impl InspectField for $t {
+            fn kind() -> FieldKind {
+                FieldKind::Int
+            }
+
+            fn to_value(&self) -> FieldValue {
+                FieldValue::Int(*self as i64)
+            }
+
+            fn from_value(value: &FieldValue) -> Option<$t> {
+                match *value {
+                    FieldValue::Int(v) => Some(v as $t),
+                    FieldValue::Float(v) => Some(v.round() as $t),
+                    _ => None,
+                }
+            }
+        }
+    )*};
+}
+
+impl_inspect_int!(i8, i16, i32, i64, u8, u16, u32, u64, usize, isize);
+
+macro_rules! impl_inspect_float {
+    ($($t:ty),*) => {$(
+        impl InspectField for $t {
+            fn kind() -> FieldKind {
+                FieldKind::Float
+            }
+
+            fn to_value(&self) -> FieldValue {
+                FieldValue::Float(*self as f64)
+            }
+
+            fn from_value(value: &FieldValue) -> Option<$t> {
+                match *value {
+                    FieldValue::Float(v) => Some(v as $t),
+                    FieldValue::Int(v) => Some(v as $t),
+                    _ => None,
+                }
+            }
+        }
+    )*};
+}
+
+impl_inspect_float!(f32, f64);
+
+impl InspectField for bool {
+    fn kind() -> FieldKind {
+        FieldKind::Bool
+    }
+
+    fn to_value(&self) -> FieldValue {
+        FieldValue::Bool(*self)
+    }
+
+    fn from_value(value: &FieldValue) -> Option<bool> {
+        match *value {
+            FieldValue::Bool(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+impl InspectField for String {
+    fn kind() -> FieldKind {
+        FieldKind::String
+    }
+
+    fn to_value(&self) -> FieldValue {
+        FieldValue::String(self.clone())
+    }
+
+    fn from_value(value: &FieldValue) -> Option<String> {
+        match *value {
+            FieldValue::String(ref v) => Some(v.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl InspectField for Vector2<f32> {
+    fn kind() -> FieldKind {
+        FieldKind::Vec2
+    }
+
+    fn to_value(&self) -> FieldValue {
+        FieldValue::Vec2([self.x, self.y])
+    }
+
+    fn from_value(value: &FieldValue) -> Option<Vector2<f32>> {
+        match *value {
+            FieldValue::Vec2(v) => Some(Vector2::new(v[0], v[1])),
+            _ => None,
+        }
+    }
+}
+
+impl InspectField for Vector3<f32> {
+    fn kind() -> FieldKind {
+        FieldKind::Vec3
+    }
+
+    fn to_value(&self) -> FieldValue {
+        FieldValue::Vec3([self.x, self.y, self.z])
+    }
+
+    fn from_value(value: &FieldValue) -> Option<Vector3<f32>> {
+        match *value {
+            FieldValue::Vec3(v) => Some(Vector3::new(v[0], v[1], v[2])),
+            _ => None,
+        }
+    }
+}
+
+impl InspectField for Vector4<f32> {
+    fn kind() -> FieldKind {
+        FieldKind::Vec4
+    }
+
+    fn to_value(&self) -> FieldValue {
+        FieldValue::Vec4([self.x, self.y, self.z, self.w])
+    }
+
+    fn from_value(value: &FieldValue) -> Option<Vector4<f32>> {
+        match *value {
+            FieldValue::Vec4(v) => Some(Vector4::new(v[0], v[1], v[2], v[3])),
+            _ => None,
+        }
+    }
+}
+
+/// Named fields of a component, implemented by `#[derive(Inspect)]`
+pub trait Inspect {
+    fn type_name(&self) -> &'static str;
+
+    fn fields(&self) -> Vec<FieldInfo>;
+
+    fn get(&self, name: &str) -> Option<FieldValue>;
+
+    /// Set a field, clamped to its range. Return false for an unknown field or a value
+    /// of another kind
+    fn set(&mut self, name: &str, value: &FieldValue) -> bool;
+
+    fn values(&self) -> BTreeMap<String, FieldValue> {
+        self.fields()
+            .into_iter()
+            .filter_map(|f| self.get(f.name).map(|v| (f.name.to_owned(), v)))
+            .collect()
+    }
+
+    /// Set the given fields, e.g. from a prefab override or a data binding.
+    /// Return the names of the fields which could not be set.
+    fn apply(&mut self, values: &BTreeMap<String, FieldValue>) -> Vec<String> {
+        values
+            .iter()
+            .filter(|&(name, value)| !self.set(name, value))
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+}
+
+/// Fields of `target` which differ from `base`, e.g. the overrides of a prefab instance
+pub fn diff(base: &Inspect, target: &Inspect) -> BTreeMap<String, FieldValue> {
+    target
+        .values()
+        .into_iter()
+        .filter(|&(ref name, ref value)| base.get(name).as_ref() != Some(value))
+        .collect()
+}
+
+/// The fields as a json object, for scene serialization
+pub fn to_json(target: &Inspect) -> serde_json::Value {
+    serde_json::to_value(target.values()).unwrap_or(serde_json::Value::Null)
+}
+
+/// Set the fields from a json object made by `to_json`, unknown fields are ignored
+pub fn apply_json(target: &mut Inspect, json: &serde_json::Value) -> Result<(), serde_json::Error> {
+    let values: BTreeMap<String, FieldValue> = serde_json::from_value(json.clone())?;
+    target.apply(&values);
+    Ok(())
+}
+
+fn format_value(value: &FieldValue) -> String {
+    match *value {
+        FieldValue::Bool(v) => format!("{}", v),
+        FieldValue::Int(v) => format!("{}", v),
+        FieldValue::Float(v) => format!("{:.3}", v),
+        FieldValue::String(ref v) => format!("{:?}", v),
+        FieldValue::Vec2(v) => format!("({:.3}, {:.3})", v[0], v[1]),
+        FieldValue::Vec3(v) => format!("({:.3}, {:.3}, {:.3})", v[0], v[1], v[2]),
+        FieldValue::Vec4(v) => format!("({:.3}, {:.3}, {:.3}, {:.3})", v[0], v[1], v[2], v[3]),
+    }
+}
+
+/// One line per field, `name: value`
+pub fn to_text(target: &Inspect) -> String {
+    let mut lines = vec![target.type_name().to_owned()];
+    for field in target.fields().iter() {
+        if let Some(value) = target.get(field.name) {
+            lines.push(format!("  {}: {}", field.name, format_value(&value)));
+        }
+    }
+    lines.join("\n")
+}
+
+/// Show the fields of a component with imgui, read only
+#[cfg(feature = "ui")]
+pub fn show(pos: Metric, target: &Inspect) {
+    imgui::label(pos, &to_text(target));
+}
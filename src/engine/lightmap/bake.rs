@@ -0,0 +1,595 @@
+//! Offline lightmap bake, native only
+//!
+//! A CPU path tracer computes the light received by each lightmap texel of the static meshes
+//! (direct light with shadows, plus the bounces on the scene and the sky), and the ambient
+//! cubes of the light probes. The meshes need their lightmap uvs, generated with
+//! `generate_lightmap_uvs` (`PrefabImport::lightmap_static` for obj prefabs). Bake once,
+//! then ship the textures with the game:
+//!
+//! ```ignore
+//! let mut lightmapper = Lightmapper::new(DEFAULT_LIGHTMAP_SIZE);
+//! lightmapper.add_mesh("house", &house_data, house_transform);
+//! lightmapper.add_light(BakeLight::Directional { direction, color });
+//! lightmapper.build();
+//!
+//! let lightmaps = lightmapper.bake();
+//! let mut probes = LightProbes::new(origin, spacing, [8, 2, 8]);
+//! lightmapper.bake_probes(&mut probes);
+//! lightmapper.save(&lightmaps, Some(&probes), Path::new("static/lightmaps"))?;
+//! ```
+//!
+//! The lightmaps are written as `{name}_lightmap.png` and the probes as `probes.json`.
+
+use engine::lightmap::{LightProbes, LIGHTMAP_RANGE};
+use engine::procgen::Random;
+use engine::render::MeshData;
+use image::{Rgba, RgbaImage};
+use math::*;
+use serde_json;
+
+use std::cmp::Ordering;
+use std::f32::consts::PI;
+use std::f32::MAX;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Offset of the rays from the surfaces, against self intersections
+const EPSILON: f32 = 0.001;
+
+/// Passes filling the empty texels around the charts, so the filtering does not bleed
+const DILATE_PASSES: usize = 2;
+
+#[derive(Debug, Clone, Copy)]
+pub enum BakeLight {
+    /// `direction` is where the light goes to
+    Directional { direction: Vector3f, color: Vector3f },
+    Point {
+        position: Vector3f,
+        color: Vector3f,
+        range: f32,
+    },
+}
+
+pub struct BakedLightmap {
+    pub name: String,
+    pub image: RgbaImage,
+}
+
+struct Triangle {
+    p: [Vector3f; 3],
+    n: [Vector3f; 3],
+    uv: [Vector2f; 3],
+    mesh: usize,
+}
+
+impl Triangle {
+    fn centroid(&self) -> Vector3f {
+        (self.p[0] + self.p[1] + self.p[2]) / 3.0
+    }
+
+    /// Möller-Trumbore, returns (t, u, v)
+    fn intersect(&self, o: Vector3f, d: Vector3f) -> Option<(f32, f32, f32)> {
+        let e1 = self.p[1] - self.p[0];
+        let e2 = self.p[2] - self.p[0];
+        let h = d.cross(e2);
+        let a = e1.dot(h);
+        if a.abs() < 1e-9 {
+            return None;
+        }
+
+        let f = 1.0 / a;
+        let s = o - self.p[0];
+        let u = f * s.dot(h);
+        if u < 0.0 || u > 1.0 {
+            return None;
+        }
+
+        let q = s.cross(e1);
+        let v = f * d.dot(q);
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        let t = f * e2.dot(q);
+        if t > EPSILON {
+            Some((t, u, v))
+        } else {
+            None
+        }
+    }
+
+    fn point(&self, w: (f32, f32, f32)) -> (Vector3f, Vector3f) {
+        let p = self.p[0] * w.0 + self.p[1] * w.1 + self.p[2] * w.2;
+        let n = self.n[0] * w.0 + self.n[1] * w.1 + self.n[2] * w.2;
+        (p, n.normalize())
+    }
+}
+
+struct BvhNode {
+    min: Vector3f,
+    max: Vector3f,
+    start: usize,
+    /// Triangles of a leaf, 0 for the inner nodes whose left child is the next node
+    count: usize,
+    right: usize,
+}
+
+#[derive(Default)]
+struct Bvh {
+    nodes: Vec<BvhNode>,
+    order: Vec<usize>,
+}
+
+impl Bvh {
+    fn new(triangles: &[Triangle]) -> Bvh {
+        let mut bvh = Bvh {
+            nodes: Vec::new(),
+            order: (0..triangles.len()).collect(),
+        };
+
+        if triangles.len() > 0 {
+            bvh.build(triangles, 0, triangles.len());
+        }
+        bvh
+    }
+
+    fn build(&mut self, triangles: &[Triangle], start: usize, end: usize) -> usize {
+        let mut min = Vector3::new(MAX, MAX, MAX);
+        let mut max = -min;
+        let mut cmin = min;
+        let mut cmax = max;
+        for i in self.order[start..end].iter() {
+            let t = &triangles[*i];
+            for p in t.p.iter() {
+                for a in 0..3 {
+                    min[a] = min[a].min(p[a]);
+                    max[a] = max[a].max(p[a]);
+                }
+            }
+
+            let c = t.centroid();
+            for a in 0..3 {
+                cmin[a] = cmin[a].min(c[a]);
+                cmax[a] = cmax[a].max(c[a]);
+            }
+        }
+
+        let index = self.nodes.len();
+        self.nodes.push(BvhNode {
+            min,
+            max,
+            start,
+            count: end - start,
+            right: 0,
+        });
+
+        let extent = cmax - cmin;
+        let axis = if extent.x > extent.y && extent.x > extent.z {
+            0
+        } else if extent.y > extent.z {
+            1
+        } else {
+            2
+        };
+
+        if end - start <= 4 || extent[axis] < 1e-6 {
+            return index;
+        }
+
+        self.order[start..end].sort_by(|a, b| {
+            let ca = triangles[*a].centroid()[axis];
+            let cb = triangles[*b].centroid()[axis];
+            ca.partial_cmp(&cb).unwrap_or(Ordering::Equal)
+        });
+
+        let mid = (start + end) / 2;
+        self.nodes[index].count = 0;
+        self.build(triangles, start, mid);
+        let right = self.build(triangles, mid, end);
+        self.nodes[index].right = right;
+
+        index
+    }
+
+    fn hits_box(node: &BvhNode, o: Vector3f, inv: Vector3f, max_t: f32) -> bool {
+        let (mut t0, mut t1) = (0.0f32, max_t);
+        for a in 0..3 {
+            let mut near = (node.min[a] - o[a]) * inv[a];
+            let mut far = (node.max[a] - o[a]) * inv[a];
+            if near > far {
+                ::std::mem::swap(&mut near, &mut far);
+            }
+
+            t0 = t0.max(near);
+            t1 = t1.min(far);
+            if t0 > t1 {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// Nearest hit before `max_t`, returns (triangle, t, barycentric weights)
+    fn intersect(
+        &self,
+        triangles: &[Triangle],
+        o: Vector3f,
+        d: Vector3f,
+        max_t: f32,
+    ) -> Option<(usize, f32, (f32, f32, f32))> {
+        if self.nodes.len() == 0 {
+            return None;
+        }
+
+        let inv = vec3(1.0 / d.x, 1.0 / d.y, 1.0 / d.z);
+        let mut best: Option<(usize, f32, (f32, f32, f32))> = None;
+        let mut stack = vec![0];
+
+        while let Some(i) = stack.pop() {
+            let node = &self.nodes[i];
+            let limit = best.map_or(max_t, |b| b.1);
+            if !Bvh::hits_box(node, o, inv, limit) {
+                continue;
+            }
+
+            if node.count == 0 {
+                stack.push(i + 1);
+                stack.push(node.right);
+                continue;
+            }
+
+            for k in self.order[node.start..node.start + node.count].iter() {
+                if let Some((t, u, v)) = triangles[*k].intersect(o, d) {
+                    if t < best.map_or(max_t, |b| b.1) {
+                        best = Some((*k, t, (1.0 - u - v, u, v)));
+                    }
+                }
+            }
+        }
+
+        best
+    }
+}
+
+fn cosine_sample(rng: &mut Random, n: Vector3f) -> Vector3f {
+    let a = if n.x.abs() > 0.9 {
+        Vector3::unit_y()
+    } else {
+        Vector3::unit_x()
+    };
+    let t = n.cross(a).normalize();
+    let b = n.cross(t);
+
+    let phi = 2.0 * PI * rng.next_f32();
+    let r2 = rng.next_f32();
+    let r = r2.sqrt();
+
+    t * (r * phi.cos()) + b * (r * phi.sin()) + n * (1.0 - r2).max(0.0).sqrt()
+}
+
+fn barycentric(p: Vector2f, t: &[Vector2f; 3]) -> Option<(f32, f32, f32)> {
+    let v0 = t[1] - t[0];
+    let v1 = t[2] - t[0];
+    let v2 = p - t[0];
+    let d = v0.x * v1.y - v1.x * v0.y;
+    if d.abs() < 1e-12 {
+        return None;
+    }
+
+    let v = (v2.x * v1.y - v1.x * v2.y) / d;
+    let w = (v0.x * v2.y - v2.x * v0.y) / d;
+    Some((1.0 - v - w, v, w))
+}
+
+fn encode_rgbm(c: Vector3f) -> Rgba<u8> {
+    let c = c / LIGHTMAP_RANGE;
+    let m = c.x.max(c.y).max(c.z).max(1e-6).min(1.0);
+    let m = (m * 255.0).ceil() / 255.0;
+    let byte = |v: f32| ((v / m).min(1.0).max(0.0) * 255.0).round() as u8;
+
+    Rgba {
+        data: [byte(c.x), byte(c.y), byte(c.z), (m * 255.0) as u8],
+    }
+}
+
+pub struct Lightmapper {
+    /// Size of the lightmaps, in texels
+    pub size: u32,
+    /// Rays per texel for the indirect light
+    pub samples: u32,
+    pub bounces: u32,
+    /// Light coming from the sky, where the rays escape the scene
+    pub sky: Vector3f,
+    /// Reflectance of all surfaces for the bounces
+    pub albedo: f32,
+    pub seed: u32,
+
+    names: Vec<String>,
+    lights: Vec<BakeLight>,
+    triangles: Vec<Triangle>,
+    bvh: Bvh,
+}
+
+impl Lightmapper {
+    pub fn new(size: u32) -> Lightmapper {
+        Lightmapper {
+            size,
+            samples: 64,
+            bounces: 2,
+            sky: vec3(0.4, 0.45, 0.5),
+            albedo: 0.5,
+            seed: 1,
+            names: Vec::new(),
+            lights: Vec::new(),
+            triangles: Vec::new(),
+            bvh: Bvh::default(),
+        }
+    }
+
+    /// Add a static mesh with its world transform, it needs its lightmap uvs
+    pub fn add_mesh(&mut self, name: &str, data: &MeshData, transform: Matrix4<f32>) {
+        let uvs2 = match data.uvs2 {
+            Some(ref uvs2) => uvs2,
+            None => {
+                println!("Mesh {} has no lightmap uvs, it will not be baked", name);
+                return;
+            }
+        };
+
+        let mesh = self.names.len();
+        self.names.push(name.to_string());
+
+        let normal_m = transform.invert().unwrap_or(Matrix4::identity()).transpose();
+        let vertex = |i: usize| {
+            let p = vec3(data.vertices[i * 3], data.vertices[i * 3 + 1], data.vertices[i * 3 + 2]);
+            transform.transform_point(Point3::from_vec(p)).to_vec()
+        };
+        let uv = |i: usize| vec2(uvs2[i * 2], uvs2[i * 2 + 1]);
+
+        for t in data.indices.chunks(3) {
+            let i = [t[0] as usize, t[1] as usize, t[2] as usize];
+            let p = [vertex(i[0]), vertex(i[1]), vertex(i[2])];
+            let face = (p[1] - p[0]).cross(p[2] - p[0]).normalize();
+
+            let normal = |k: usize| match data.normals {
+                Some(ref n) => {
+                    let n = vec3(n[i[k] * 3], n[i[k] * 3 + 1], n[i[k] * 3 + 2]);
+                    normal_m.transform_vector(n).normalize()
+                }
+                None => face,
+            };
+
+            self.triangles.push(Triangle {
+                p,
+                n: [normal(0), normal(1), normal(2)],
+                uv: [uv(i[0]), uv(i[1]), uv(i[2])],
+                mesh,
+            });
+        }
+    }
+
+    pub fn add_light(&mut self, light: BakeLight) {
+        self.lights.push(light);
+    }
+
+    /// Build the acceleration structure, once all meshes are added
+    pub fn build(&mut self) {
+        self.bvh = Bvh::new(&self.triangles);
+    }
+
+    fn occluded(&self, o: Vector3f, d: Vector3f, max_t: f32) -> bool {
+        self.bvh.intersect(&self.triangles, o, d, max_t).is_some()
+    }
+
+    /// Light received from the lights at `p`
+    fn direct(&self, p: Vector3f, n: Vector3f) -> Vector3f {
+        let o = p + n * EPSILON;
+        let mut sum = Vector3::zero();
+
+        for light in self.lights.iter() {
+            match *light {
+                BakeLight::Directional { direction, color } => {
+                    let l = -direction.normalize();
+                    let d = n.dot(l);
+                    if d > 0.0 && !self.occluded(o, l, MAX) {
+                        sum += color * d;
+                    }
+                }
+                BakeLight::Point {
+                    position,
+                    color,
+                    range,
+                } => {
+                    let to = position - p;
+                    let dist = to.magnitude();
+                    if dist >= range || dist < 1e-6 {
+                        continue;
+                    }
+
+                    let l = to / dist;
+                    let d = n.dot(l);
+                    if d > 0.0 && !self.occluded(o, l, dist) {
+                        let att = 1.0 - dist / range;
+                        sum += color * (d * att * att);
+                    }
+                }
+            }
+        }
+
+        sum
+    }
+
+    /// Light bounced on the scene or coming from the sky to `p`
+    fn indirect(&self, rng: &mut Random, p: Vector3f, n: Vector3f) -> Vector3f {
+        let mut sum = Vector3::zero();
+        let samples = self.samples.max(1);
+
+        for _ in 0..samples {
+            let mut throughput = 1.0;
+            let (mut pos, mut normal) = (p, n);
+
+            for _ in 0..self.bounces.max(1) {
+                let dir = cosine_sample(rng, normal);
+                let hit = self.bvh
+                    .intersect(&self.triangles, pos + normal * EPSILON, dir, MAX);
+
+                match hit {
+                    None => {
+                        sum += self.sky * throughput;
+                        break;
+                    }
+                    Some((t, _, w)) => {
+                        let (hp, mut hn) = self.triangles[t].point(w);
+                        if hn.dot(dir) > 0.0 {
+                            hn = -hn;
+                        }
+
+                        throughput *= self.albedo;
+                        sum += self.direct(hp, hn) * throughput;
+                        pos = hp;
+                        normal = hn;
+                    }
+                }
+            }
+        }
+
+        sum / samples as f32
+    }
+
+    fn bake_mesh(&self, mesh: usize) -> BakedLightmap {
+        let size = self.size as usize;
+        let mut texels: Vec<Option<Vector3f>> = vec![None; size * size];
+        let mut rng = Random::new(self.seed.wrapping_add(mesh as u32));
+
+        for tri in self.triangles.iter().filter(|t| t.mesh == mesh) {
+            let uv = [tri.uv[0] * size as f32, tri.uv[1] * size as f32, tri.uv[2] * size as f32];
+            let min_x = uv.iter().map(|p| p.x).fold(MAX, f32::min).floor().max(0.0) as usize;
+            let min_y = uv.iter().map(|p| p.y).fold(MAX, f32::min).floor().max(0.0) as usize;
+            let max_x = uv.iter().map(|p| p.x).fold(-MAX, f32::max).ceil() as usize;
+            let max_y = uv.iter().map(|p| p.y).fold(-MAX, f32::max).ceil() as usize;
+
+            for y in min_y..max_y.min(size) {
+                for x in min_x..max_x.min(size) {
+                    let i = y * size + x;
+                    if texels[i].is_some() {
+                        continue;
+                    }
+
+                    let center = vec2(x as f32 + 0.5, y as f32 + 0.5);
+                    let w = match barycentric(center, &uv) {
+                        Some(w) if w.0 >= 0.0 && w.1 >= 0.0 && w.2 >= 0.0 => w,
+                        _ => continue,
+                    };
+
+                    let (p, n) = tri.point(w);
+                    texels[i] = Some(self.direct(p, n) + self.indirect(&mut rng, p, n));
+                }
+            }
+        }
+
+        dilate(&mut texels, size);
+
+        let mut image = RgbaImage::new(self.size, self.size);
+        for (i, texel) in texels.iter().enumerate() {
+            let c = texel.unwrap_or(Vector3::zero());
+            image.put_pixel((i % size) as u32, (i / size) as u32, encode_rgbm(c));
+        }
+
+        BakedLightmap {
+            name: self.names[mesh].clone(),
+            image,
+        }
+    }
+
+    /// Bake the lightmaps of all meshes, call `build` before
+    pub fn bake(&self) -> Vec<BakedLightmap> {
+        (0..self.names.len()).map(|m| self.bake_mesh(m)).collect()
+    }
+
+    /// Bake the ambient cubes of the probes
+    pub fn bake_probes(&self, probes: &mut LightProbes) {
+        let mut rng = Random::new(self.seed);
+        let axes = [
+            Vector3::unit_x(),
+            -Vector3::unit_x(),
+            Vector3::unit_y(),
+            -Vector3::unit_y(),
+            Vector3::unit_z(),
+            -Vector3::unit_z(),
+        ];
+
+        let counts = probes.counts;
+        for k in 0..counts[2] {
+            for j in 0..counts[1] {
+                for i in 0..counts[0] {
+                    let p = probes.position(i, j, k);
+                    let index = probes.index(i, j, k);
+
+                    for (a, n) in axes.iter().enumerate() {
+                        let c = self.direct(p, *n) + self.indirect(&mut rng, p, *n);
+                        probes.probes[index].colors[a] = c.into();
+                    }
+                }
+            }
+        }
+    }
+
+    /// Write the lightmaps and the probes in `dir`
+    pub fn save(
+        &self,
+        lightmaps: &[BakedLightmap],
+        probes: Option<&LightProbes>,
+        dir: &Path,
+    ) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+
+        for lightmap in lightmaps.iter() {
+            lightmap
+                .image
+                .save(dir.join(format!("{}_lightmap.png", lightmap.name)))?;
+        }
+
+        if let Some(probes) = probes {
+            let json = serde_json::to_string_pretty(probes)
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            fs::write(dir.join("probes.json"), json)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Fill the empty texels next to the baked ones with their average
+fn dilate(texels: &mut [Option<Vector3f>], size: usize) {
+    for _ in 0..DILATE_PASSES {
+        let source = texels.to_vec();
+
+        for y in 0..size {
+            for x in 0..size {
+                if source[y * size + x].is_some() {
+                    continue;
+                }
+
+                let mut sum = Vector3::zero();
+                let mut count = 0;
+                for &(dx, dy) in [(-1i32, 0i32), (1, 0), (0, -1), (0, 1)].iter() {
+                    let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+                    if nx < 0 || ny < 0 || nx >= size as i32 || ny >= size as i32 {
+                        continue;
+                    }
+
+                    if let Some(c) = source[ny as usize * size + nx as usize] {
+                        sum += c;
+                        count += 1;
+                    }
+                }
+
+                if count > 0 {
+                    texels[y * size + x] = Some(sum / count as f32);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,158 @@
+//! Baked lighting: lightmaps for the static geometry, light probes for the dynamic objects
+//!
+//! The lightmaps are sampled with a second uv channel (`MeshData::uvs2`), unwrapped by
+//! `generate_lightmap_uvs`, or when an obj prefab is imported with
+//! `PrefabImport::lightmap_static` (not by default, it splits the vertices along the seams
+//! of the charts). Static meshes are rendered with `lightmap_material`, which multiplies
+//! their diffuse texture with the baked light.
+//! Dynamic objects get the baked ambient light of the nearest probes with the
+//! `LightProbeReceiver` actor, their direct light stays dynamic. They shadow the lightmapped
+//! geometry with the `BlobShadows` actor.
+//!
+//! The bake itself runs offline, see `bake::Lightmapper` (native only).
+//! Lightmaps are stored in RGBM, the light is `rgb * a * LIGHTMAP_RANGE`.
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod bake;
+
+use engine::asset::loader::{self, Loadable, Loader};
+use engine::asset::{AssetResult, AssetSystem, File};
+use engine::render::{Material, MeshData, Texture};
+use engine::unwrap::{unwrap_uvs, UnwrapOptions, UvChannel};
+use math::*;
+
+use std::rc::Rc;
+
+/// Size of the lightmaps the uvs are generated for, in texels
+pub const DEFAULT_LIGHTMAP_SIZE: u32 = 256;
+
+/// Largest light stored in a lightmap
+pub const LIGHTMAP_RANGE: f32 = 6.0;
+
+/// Texels between two charts of the lightmap, so the filtering does not bleed
+const PADDING: f32 = 2.0;
+
+/// Generate the lightmap uvs of a mesh with `unwrap_uvs`, the charts are padded for a lightmap
+/// of `size` texels
+pub fn generate_lightmap_uvs(data: &mut MeshData, size: u32) -> bool {
+    let mut options = UnwrapOptions::new(size);
+    options.padding = PADDING;
+    unwrap_uvs(data, UvChannel::Uv1, &options)
+}
+
+/// Material of a static mesh lit by its lightmap
+pub fn lightmap_material(
+    asys: &AssetSystem,
+    diffuse: Rc<Texture>,
+    lightmap: Rc<Texture>,
+) -> Material {
+    let material = Material::new(asys.new_program("unrust/lightmap"));
+    material.set("uMaterial.diffuse", diffuse);
+    material.set("uLightmap", lightmap);
+    material.set("uLightmapRange", LIGHTMAP_RANGE);
+    material
+}
+
+/// Incoming light from the 6 axis directions (+x, -x, +y, -y, +z, -z)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+pub struct AmbientCube {
+    pub colors: [[f32; 3]; 6],
+}
+
+impl AmbientCube {
+    /// Light received by a surface of normal `n`
+    pub fn sample(&self, n: Vector3f) -> Vector3f {
+        let n2 = vec3(n.x * n.x, n.y * n.y, n.z * n.z);
+        let c = |i: usize| Vector3::from(self.colors[i]);
+
+        let x = if n.x >= 0.0 { c(0) } else { c(1) };
+        let y = if n.y >= 0.0 { c(2) } else { c(3) };
+        let z = if n.z >= 0.0 { c(4) } else { c(5) };
+
+        x * n2.x + y * n2.y + z * n2.z
+    }
+
+    fn add_scaled(&mut self, other: &AmbientCube, w: f32) {
+        for i in 0..6 {
+            for k in 0..3 {
+                self.colors[i][k] += other.colors[i][k] * w;
+            }
+        }
+    }
+}
+
+/// Grid of ambient cubes, baked with `bake::Lightmapper::bake_probes`
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct LightProbes {
+    pub origin: [f32; 3],
+    /// Distance between two probes on each axis
+    pub spacing: [f32; 3],
+    pub counts: [u32; 3],
+    pub probes: Vec<AmbientCube>,
+}
+
+impl LightProbes {
+    pub fn new(origin: Vector3f, spacing: Vector3f, counts: [u32; 3]) -> LightProbes {
+        let n = counts.iter().map(|c| (*c).max(1) as usize).product();
+        LightProbes {
+            origin: origin.into(),
+            spacing: spacing.into(),
+            counts: [counts[0].max(1), counts[1].max(1), counts[2].max(1)],
+            probes: vec![AmbientCube::default(); n],
+        }
+    }
+
+    pub fn index(&self, i: u32, j: u32, k: u32) -> usize {
+        ((k * self.counts[1] + j) * self.counts[0] + i) as usize
+    }
+
+    pub fn position(&self, i: u32, j: u32, k: u32) -> Vector3f {
+        let s = self.spacing;
+        Vector3::from(self.origin) + vec3(i as f32 * s[0], j as f32 * s[1], k as f32 * s[2])
+    }
+
+    /// Light at `p`, interpolated between the 8 nearest probes, clamped to the grid
+    pub fn sample(&self, p: Vector3f) -> AmbientCube {
+        let mut cell = [0u32; 3];
+        let mut t = [0.0f32; 3];
+        for a in 0..3 {
+            let max = (self.counts[a] - 1) as f32;
+            let f = if self.spacing[a] > 0.0 {
+                ((p[a] - self.origin[a]) / self.spacing[a]).max(0.0).min(max)
+            } else {
+                0.0
+            };
+            cell[a] = (f.floor() as u32).min(self.counts[a].saturating_sub(2));
+            t[a] = f - cell[a] as f32;
+        }
+
+        let mut cube = AmbientCube::default();
+        for corner in 0..8 {
+            let mut w = 1.0;
+            let mut c = [0u32; 3];
+            for a in 0..3 {
+                let upper = (corner >> a) & 1 == 1;
+                c[a] = (cell[a] + upper as u32).min(self.counts[a] - 1);
+                w *= if upper { t[a] } else { 1.0 - t[a] };
+            }
+
+            if w > 0.0 {
+                cube.add_scaled(&self.probes[self.index(c[0], c[1], c[2])], w);
+            }
+        }
+
+        cube
+    }
+}
+
+pub struct LightProbesLoader {}
+
+impl Loader<LightProbes> for LightProbesLoader {
+    fn load<A>(_asys: A, mut file: Box<File>) -> AssetResult<LightProbes> {
+        loader::read_json(&mut file)
+    }
+}
+
+impl Loadable for LightProbes {
+    type Loader = LightProbesLoader;
+}
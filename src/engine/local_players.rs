@@ -0,0 +1,200 @@
+//! Local multiplayer, the input devices claimed by the players of one machine.
+//!
+//! While `joining`, pressing the join button of a free gamepad (or the join key of the
+//! keyboard) adds a player, pressing the leave button removes it :
+//!
+//! ```ignore
+//! world.local_players.joining = true;
+//!
+//! // Each frame, in the lobby
+//! for evt in world.local_players.poll_events() {
+//!     match evt {
+//!         PlayerEvent::Joined { player, .. } => show_player_card(player),
+//!         PlayerEvent::Left { player, .. } => hide_player_card(player),
+//!     }
+//! }
+//!
+//! // When the game starts
+//! world.local_players.joining = false;
+//! let cameras = world.set_split_screen_players(SplitLayout::Grid);
+//! ```
+//!
+//! Players keep their slot while the others leave, so the player indices are stable.
+
+use engine::input::InputDevice;
+use uni_app::AppEvent;
+use uni_pad as pad;
+
+use std::collections::HashSet;
+
+/// Gamepads polled for the joins
+const MAX_GAMEPADS: i32 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayerEvent {
+    Joined { player: usize, device: InputDevice },
+    Left { player: usize, device: InputDevice },
+}
+
+pub struct LocalPlayers {
+    pub max_players: usize,
+    /// Whether the join and leave buttons are listened to
+    pub joining: bool,
+    /// Gamepad button joining, A of the standard mapping
+    pub join_button: i32,
+    /// Gamepad button leaving, B of the standard mapping
+    pub leave_button: i32,
+    /// Key codes of the keyboard
+    pub join_key: String,
+    pub leave_key: String,
+
+    slots: Vec<Option<InputDevice>>,
+    events: Vec<PlayerEvent>,
+    /// Gamepad buttons down at the last step, (gamepad, button)
+    prev_buttons: HashSet<(i32, i32)>,
+}
+
+impl Default for LocalPlayers {
+    fn default() -> LocalPlayers {
+        LocalPlayers::new()
+    }
+}
+
+impl LocalPlayers {
+    pub fn new() -> LocalPlayers {
+        LocalPlayers {
+            max_players: 4,
+            joining: false,
+            join_button: 0,
+            leave_button: 1,
+            join_key: "Enter".to_owned(),
+            leave_key: "Escape".to_owned(),
+            slots: Vec::new(),
+            events: Vec::new(),
+            prev_buttons: HashSet::new(),
+        }
+    }
+
+    /// (player, device) of the joined players
+    pub fn players(&self) -> Vec<(usize, InputDevice)> {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter_map(|(i, d)| d.map(|d| (i, d)))
+            .collect()
+    }
+
+    pub fn count(&self) -> usize {
+        self.slots.iter().filter(|d| d.is_some()).count()
+    }
+
+    pub fn device(&self, player: usize) -> Option<InputDevice> {
+        self.slots.get(player).and_then(|d| *d)
+    }
+
+    /// The player who claimed a device
+    pub fn player_of(&self, device: InputDevice) -> Option<usize> {
+        self.slots.iter().position(|d| *d == Some(device))
+    }
+
+    /// Claim a device for a new player in the first free slot, `None` when the device is
+    /// already claimed or the players are full
+    pub fn join(&mut self, device: InputDevice) -> Option<usize> {
+        if self.player_of(device).is_some() {
+            return None;
+        }
+
+        let player = match self.slots.iter().position(|d| d.is_none()) {
+            Some(free) => free,
+            None if self.slots.len() < self.max_players => {
+                self.slots.push(None);
+                self.slots.len() - 1
+            }
+            None => return None,
+        };
+
+        self.slots[player] = Some(device);
+        self.events.push(PlayerEvent::Joined { player, device });
+        Some(player)
+    }
+
+    /// Release the device of a player
+    pub fn leave(&mut self, player: usize) {
+        let device = match self.slots.get_mut(player).and_then(|d| d.take()) {
+            Some(device) => device,
+            None => return,
+        };
+
+        while self.slots.last() == Some(&None) {
+            self.slots.pop();
+        }
+        self.events.push(PlayerEvent::Left { player, device });
+    }
+
+    pub fn clear(&mut self) {
+        for player in 0..self.slots.len() {
+            self.leave(player);
+        }
+    }
+
+    /// Joins and leaves since the last call
+    pub fn poll_events(&mut self) -> Vec<PlayerEvent> {
+        self.events.drain(..).collect()
+    }
+
+    pub fn step(&mut self, events: &[AppEvent]) {
+        let buttons: HashSet<(i32, i32)> = (0..MAX_GAMEPADS)
+            .flat_map(|g| {
+                let (join, leave) = (self.join_button, self.leave_button);
+                vec![(g, join), (g, leave)]
+            })
+            .filter(|&(g, b)| pad::gamepad_button(g, b))
+            .collect();
+        let pressed: Vec<(i32, i32)> = buttons
+            .iter()
+            .filter(|b| !self.prev_buttons.contains(b))
+            .cloned()
+            .collect();
+        self.prev_buttons = buttons;
+
+        // Disconnected gamepads, native builds have no gamepad ids
+        #[cfg(target_arch = "wasm32")]
+        {
+            for (player, device) in self.players() {
+                if let InputDevice::Gamepad(g) = device {
+                    if pad::gamepad_id(g).is_none() {
+                        self.leave(player);
+                    }
+                }
+            }
+        }
+
+        if !self.joining {
+            return;
+        }
+
+        for evt in events.iter() {
+            if let AppEvent::KeyDown(ref key) = *evt {
+                let keyboard = InputDevice::KeyboardMouse;
+                if key.code == self.join_key {
+                    self.join(keyboard);
+                } else if key.code == self.leave_key {
+                    if let Some(player) = self.player_of(keyboard) {
+                        self.leave(player);
+                    }
+                }
+            }
+        }
+
+        for (gamepad, button) in pressed.into_iter() {
+            let device = InputDevice::Gamepad(gamepad);
+            if button == self.join_button {
+                self.join(device);
+            } else if button == self.leave_button {
+                if let Some(player) = self.player_of(device) {
+                    self.leave(player);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use engine::asset::loader::{self, Loadable, Loader};
+use engine::asset::{AssetError, AssetResult, AssetSystem, File, Resource};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// A table of localized strings, loaded from a json object of `key: text` pairs.
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct StringTable(pub HashMap<String, String>);
+
+pub struct StringTableLoader {}
+
+impl Loader<StringTable> for StringTableLoader {
+    fn load<A>(_asys: A, mut file: Box<File>) -> AssetResult<StringTable> {
+        loader::read_json(&mut file)
+    }
+}
+
+impl Loadable for StringTable {
+    type Loader = StringTableLoader;
+}
+
+struct LocalizationInner {
+    language: String,
+    fallback: String,
+    tables: HashMap<String, StringTable>,
+    pending: Vec<(String, Resource<StringTable>)>,
+}
+
+/// Localization lookup shared between subsystems.
+///
+/// Cloning it is cheap, all clones share the same string tables.
+#[derive(Clone)]
+pub struct Localization {
+    inner: Rc<RefCell<LocalizationInner>>,
+}
+
+impl Default for Localization {
+    fn default() -> Localization {
+        Localization::new("en")
+    }
+}
+
+impl Localization {
+    pub fn new(language: &str) -> Localization {
+        Localization {
+            inner: Rc::new(RefCell::new(LocalizationInner {
+                language: language.to_owned(),
+                fallback: language.to_owned(),
+                tables: HashMap::new(),
+                pending: Vec::new(),
+            })),
+        }
+    }
+
+    pub fn language(&self) -> String {
+        self.inner.borrow().language.clone()
+    }
+
+    pub fn set_language(&self, language: &str) {
+        self.inner.borrow_mut().language = language.to_owned();
+    }
+
+    /// The language used when a key is missing in the current language.
+    pub fn set_fallback(&self, language: &str) {
+        self.inner.borrow_mut().fallback = language.to_owned();
+    }
+
+    /// Merge a string table into the given language.
+    pub fn add_table(&self, language: &str, table: StringTable) {
+        let mut inner = self.inner.borrow_mut();
+        let entry = inner
+            .tables
+            .entry(language.to_owned())
+            .or_insert_with(StringTable::default);
+
+        entry.0.extend(table.0.into_iter());
+    }
+
+    /// Load a json string table for the given language,
+    /// it will be merged when the file is ready.
+    pub fn load_table(&self, asys: &AssetSystem, language: &str, filename: &str) {
+        let res = loader::load_json::<StringTable>(asys, filename);
+        self.inner
+            .borrow_mut()
+            .pending
+            .push((language.to_owned(), res));
+    }
+
+    pub fn is_loading(&self) -> bool {
+        self.inner.borrow().pending.len() > 0
+    }
+
+    pub fn step(&self) {
+        let pending: Vec<_> = self.inner.borrow_mut().pending.drain(0..).collect();
+        let mut new_pending = Vec::new();
+
+        for (lang, res) in pending.into_iter() {
+            match res.try_into() {
+                Ok(table) => self.add_table(&lang, table),
+                Err(AssetError::NotReady) => new_pending.push((lang, res)),
+                Err(e) => println!("Fail to load string table for {}, reason: {:?}", lang, e),
+            }
+        }
+
+        self.inner.borrow_mut().pending.append(&mut new_pending);
+    }
+
+    pub fn get(&self, key: &str) -> Option<String> {
+        let inner = self.inner.borrow();
+
+        let lookup = |lang: &str| {
+            inner
+                .tables
+                .get(lang)
+                .and_then(|t| t.0.get(key))
+                .map(|s| s.clone())
+        };
+
+        lookup(&inner.language).or_else(|| lookup(&inner.fallback))
+    }
+
+    /// Translate a key, return the key itself if it is not found,
+    /// so untranslated text can be used directly.
+    pub fn tr(&self, key: &str) -> String {
+        self.get(key).unwrap_or_else(|| key.to_owned())
+    }
+
+    /// Translate a key and replace all `{name}` placeholders by the given arguments.
+    pub fn tr_args(&self, key: &str, args: &[(&str, &str)]) -> String {
+        let mut s = self.tr(key);
+        for &(name, value) in args.iter() {
+            s = s.replace(&format!("{{{}}}", name), value);
+        }
+        s
+    }
+}
@@ -2,21 +2,63 @@ mod asset;
 mod core;
 mod render;
 
+pub mod accessibility;
+pub mod animation;
+#[cfg(feature = "ui")]
+pub mod captions;
 pub mod context;
+pub mod crash;
+#[cfg(feature = "ui")]
+pub mod cursor;
+pub mod diagnostics;
+pub mod dialogue;
 pub mod engine;
+pub mod gpu_sim;
+pub mod haptics;
+#[cfg(feature = "ui")]
 pub mod imgui;
+pub mod input;
+pub mod input_glyphs;
+pub mod input_recording;
+pub mod lightmap;
+pub mod local_players;
+pub mod inspect;
+pub mod localization;
+#[cfg(feature = "net")]
+pub mod net;
+pub mod noise;
+#[cfg(feature = "physics")]
+pub mod physics;
+pub mod procgen;
+pub mod profiler;
+pub mod quest;
+pub mod settings;
+pub mod split_screen;
+#[cfg(feature = "audio")]
 pub mod sound;
+pub mod tables;
+pub mod time;
+pub mod unwrap;
+pub mod validation;
+#[cfg(all(feature = "net", feature = "audio"))]
+pub mod voice;
+pub mod voxel;
 
+#[cfg(feature = "ui")]
 pub use self::imgui::Metric;
 
 pub use self::asset::*;
 pub use self::core::Aabb;
 pub use self::core::{Component, ComponentArena, ComponentBased, ComponentEvent, ComponentType,
-                     GameObject, IntoComponentPtr, SceneTree};
+                     GameObject, IntoComponentPtr, Name, SceneTree};
 pub use self::render::*;
 
 pub use self::engine::{ClearOption, IEngine};
 
-pub use self::sound::{SoundHandle, SoundSystem};
+#[cfg(feature = "audio")]
+pub use self::sound::{AudioListener, EmitterHandle, Music, ReverbParams, SoundHandle, SoundSystem,
+                      StreamHandle, VoiceHandle};
+
+pub use self::localization::Localization;
 
 pub type Engine<FS, F> = engine::Engine<AssetDatabase<FS, F>>;
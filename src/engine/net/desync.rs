@@ -0,0 +1,330 @@
+use super::{Channel, Network, PeerId, RpcAuthority, RpcMessage};
+
+use fnv::{FnvHashMap, FnvHashSet, FnvHasher};
+use std::collections::VecDeque;
+use std::hash::{Hash, Hasher};
+
+/// Hashes of the checked state at the end of a tick, by section (e.g. component name)
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct TickHashes {
+    pub tick: u64,
+    pub sections: Vec<(String, u64)>,
+}
+
+impl TickHashes {
+    pub fn total(&self) -> u64 {
+        let mut hasher = FnvHasher::default();
+        self.sections.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// The first section which differs, in the order of `self`
+    fn diverging_section(&self, other: &TickHashes) -> String {
+        let others: FnvHashMap<&str, u64> = other
+            .sections
+            .iter()
+            .map(|s| (s.0.as_str(), s.1))
+            .collect();
+
+        for &(ref name, hash) in self.sections.iter() {
+            if others.get(name.as_str()) != Some(&hash) {
+                return name.clone();
+            }
+        }
+
+        let names: FnvHashSet<&str> = self.sections.iter().map(|s| s.0.as_str()).collect();
+        other
+            .sections
+            .iter()
+            .find(|s| !names.contains(s.0.as_str()))
+            .map(|s| s.0.clone())
+            .unwrap_or_default()
+    }
+}
+
+/// Hashes of a whole run, saved to compare with another run
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct DesyncRecording {
+    pub ticks: Vec<TickHashes>,
+}
+
+impl DesyncRecording {
+    pub fn from_json(s: &str) -> Result<DesyncRecording, String> {
+        ::serde_json::from_str(s).map_err(|e| format!("{:?}", e))
+    }
+
+    pub fn to_json(&self) -> String {
+        ::serde_json::to_string(self).unwrap()
+    }
+}
+
+/// State which differs from a reference run or a peer
+#[derive(Debug, Clone, PartialEq)]
+pub struct Desync {
+    pub tick: u64,
+    pub section: String,
+    /// The peer whose state differs, None for a reference run
+    pub peer: Option<PeerId>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct StateHashMessage {
+    hashes: TickHashes,
+}
+
+impl RpcMessage for StateHashMessage {
+    const NAME: &'static str = "unrust.state_hash";
+    const CHANNEL: Channel = Channel::ReliableOrdered;
+    const AUTHORITY: RpcAuthority = RpcAuthority::Any;
+}
+
+/// Finds the first tick where the simulation diverges
+///
+/// The state selected for checking is hashed at each fixed tick, and compared with
+/// a recording of a previous run, or with the hashes sent by the other peers :
+///
+/// ```ignore
+/// checker.begin_tick(tick);
+/// checker.hash("Player", &player.cell);
+/// checker.hash_floats("Transform", &[pos.x, pos.y, pos.z]);
+/// checker.end_tick();
+/// checker.sync(&mut world.net);
+///
+/// if let Some(desync) = checker.first_desync() {
+///     println!("desync at tick {} in {}", desync.tick, desync.section);
+/// }
+/// ```
+pub struct DesyncChecker {
+    pub enabled: bool,
+    /// Ticks kept for comparing with late peers
+    pub history_size: usize,
+
+    current: Option<TickHashes>,
+    /// Index of the sections of the current tick
+    section_index: FnvHashMap<String, usize>,
+    history: VecDeque<TickHashes>,
+    recording: Option<DesyncRecording>,
+    reference: Option<DesyncRecording>,
+    remote: Vec<(PeerId, TickHashes)>,
+    unsent: Vec<TickHashes>,
+    desyncs: Vec<Desync>,
+    /// The reference run (None) and the peers diverging since their last reported desync
+    diverged: Vec<Option<PeerId>>,
+    registered: bool,
+}
+
+impl Default for DesyncChecker {
+    fn default() -> DesyncChecker {
+        DesyncChecker {
+            enabled: true,
+            history_size: 600,
+            current: None,
+            section_index: FnvHashMap::default(),
+            history: VecDeque::new(),
+            recording: None,
+            reference: None,
+            remote: Vec::new(),
+            unsent: Vec::new(),
+            desyncs: Vec::new(),
+            diverged: Vec::new(),
+            registered: false,
+        }
+    }
+}
+
+impl DesyncChecker {
+    pub fn new() -> DesyncChecker {
+        Default::default()
+    }
+
+    /// Keep all tick hashes, see `take_recording`
+    pub fn start_recording(&mut self) {
+        self.recording = Some(DesyncRecording::default());
+    }
+
+    pub fn take_recording(&mut self) -> Option<DesyncRecording> {
+        self.recording.take()
+    }
+
+    /// Compare the following ticks with a recorded run
+    pub fn compare_with(&mut self, reference: DesyncRecording) {
+        self.reference = Some(reference);
+    }
+
+    pub fn begin_tick(&mut self, tick: u64) {
+        if !self.enabled {
+            return;
+        }
+
+        self.current = Some(TickHashes {
+            tick,
+            sections: Vec::new(),
+        });
+        self.section_index.clear();
+    }
+
+    /// Add a value to the hash of a section
+    pub fn hash<T: Hash + ?Sized>(&mut self, section: &str, value: &T) {
+        if let Some(hash) = self.section_hash(section) {
+            let mut hasher = FnvHasher::with_key(*hash);
+            value.hash(&mut hasher);
+            *hash = hasher.finish();
+        }
+    }
+
+    /// Floats are hashed by their bits, so -0.0 and 0.0 differ
+    pub fn hash_floats(&mut self, section: &str, values: &[f32]) {
+        let bits: Vec<u32> = values.iter().map(|v| v.to_bits()).collect();
+        self.hash(section, &bits[..]);
+    }
+
+    pub fn end_tick(&mut self) {
+        let current = match self.current.take() {
+            Some(current) => current,
+            None => return,
+        };
+
+        if let Some(ref mut recording) = self.recording {
+            recording.ticks.push(current.clone());
+        }
+
+        // The recorded ticks are in order
+        let expected = self.reference.as_ref().and_then(|r| {
+            r.ticks
+                .binary_search_by_key(&current.tick, |t| t.tick)
+                .ok()
+                .map(|i| r.ticks[i].clone())
+        });
+        if let Some(expected) = expected {
+            self.check(&current, &expected, None);
+        }
+
+        if self.registered {
+            self.unsent.push(current.clone());
+        }
+        self.history.push_back(current);
+        while self.history.len() > self.history_size {
+            self.history.pop_front();
+        }
+
+        self.check_remote();
+    }
+
+    /// Exchange the tick hashes with the other peers
+    pub fn sync(&mut self, net: &mut Network) {
+        if !self.registered {
+            net.register::<StateHashMessage>();
+            self.registered = true;
+        }
+
+        for hashes in self.unsent.drain(0..) {
+            net.call(&StateHashMessage { hashes });
+        }
+
+        for call in net.receive::<StateHashMessage>().into_iter() {
+            self.remote.push((call.sender, call.msg.hashes));
+        }
+
+        self.check_remote();
+    }
+
+    /// Tick hashes received from a peer, when they are exchanged by other means
+    pub fn add_remote(&mut self, peer: PeerId, hashes: TickHashes) {
+        self.remote.push((peer, hashes));
+        self.check_remote();
+    }
+
+    pub fn latest(&self) -> Option<&TickHashes> {
+        self.history.back()
+    }
+
+    /// The earliest divergence found so far. A divergence is reported once, until the state
+    /// matches again.
+    pub fn first_desync(&self) -> Option<&Desync> {
+        self.desyncs.iter().min_by_key(|d| d.tick)
+    }
+
+    pub fn poll_desyncs(&mut self) -> Vec<Desync> {
+        self.desyncs.drain(0..).collect()
+    }
+
+    pub fn clear(&mut self) {
+        self.current = None;
+        self.history.clear();
+        self.remote.clear();
+        self.unsent.clear();
+        self.desyncs.clear();
+        self.diverged.clear();
+    }
+
+    fn section_hash(&mut self, section: &str) -> Option<&mut u64> {
+        let current = self.current.as_mut()?;
+
+        let index = match self.section_index.get(section) {
+            Some(index) => *index,
+            None => {
+                current.sections.push((section.to_owned(), 0));
+                let index = current.sections.len() - 1;
+                self.section_index.insert(section.to_owned(), index);
+                index
+            }
+        };
+
+        Some(&mut current.sections[index].1)
+    }
+
+    fn check_remote(&mut self) {
+        let latest = match self.history.back() {
+            Some(latest) => latest.tick,
+            None => return,
+        };
+
+        // Peers ahead of us are kept until we reach their tick
+        let (ready, waiting): (Vec<_>, Vec<_>) = self.remote
+            .drain(0..)
+            .partition(|r| r.1.tick <= latest);
+        self.remote = waiting;
+
+        for (peer, hashes) in ready.into_iter() {
+            let local = self.history
+                .iter()
+                .find(|t| t.tick == hashes.tick)
+                .cloned();
+
+            // Too old to compare
+            if let Some(local) = local {
+                self.check(&local, &hashes, Some(peer));
+            }
+        }
+    }
+
+    fn check(&mut self, local: &TickHashes, other: &TickHashes, peer: Option<PeerId>) {
+        let reported = self.diverged.iter().position(|p| *p == peer);
+
+        if local.sections == other.sections {
+            // In sync again, the next divergence is a new desync
+            if let Some(i) = reported {
+                self.diverged.swap_remove(i);
+            }
+            return;
+        }
+
+        // The following ticks of a divergence differ too
+        if reported.is_some() {
+            return;
+        }
+        self.diverged.push(peer);
+
+        let desync = Desync {
+            tick: local.tick,
+            section: local.diverging_section(other),
+            peer,
+        };
+
+        println!(
+            "Desync at tick {} in {} (peer {:?})",
+            desync.tick, desync.section, desync.peer
+        );
+        self.desyncs.push(desync);
+    }
+}
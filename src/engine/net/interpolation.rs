@@ -0,0 +1,178 @@
+use math::*;
+
+use std::collections::VecDeque;
+
+// Snapshots kept, older ones are dropped even if still needed by a very large delay
+const MAX_SNAPSHOTS: usize = 64;
+// The playback time snaps to the target when it is farther than this, in seconds
+const MAX_DRIFT: f64 = 1.0;
+
+/// Values which could be blended between two network states
+pub trait Interpolate: Clone {
+    /// Blend from self (t = 0) to other (t = 1), t > 1 extrapolates
+    fn interpolate(&self, other: &Self, t: f32) -> Self;
+}
+
+impl Interpolate for f32 {
+    fn interpolate(&self, other: &f32, t: f32) -> f32 {
+        self + (other - self) * t
+    }
+}
+
+impl Interpolate for Vector3<f32> {
+    fn interpolate(&self, other: &Vector3<f32>, t: f32) -> Vector3<f32> {
+        self + (other - self) * t
+    }
+}
+
+impl Interpolate for Quaternion<f32> {
+    fn interpolate(&self, other: &Quaternion<f32>, t: f32) -> Quaternion<f32> {
+        // take the shortest path
+        let other = if self.dot(*other) < 0.0 { -*other } else { *other };
+        (self * (1.0 - t) + other * t).normalize()
+    }
+}
+
+/// Position and rotation of a networked object
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NetTransform {
+    pub position: Vector3<f32>,
+    pub rotation: Quaternion<f32>,
+}
+
+impl NetTransform {
+    pub fn new(position: Vector3<f32>, rotation: Quaternion<f32>) -> NetTransform {
+        NetTransform { position, rotation }
+    }
+
+    pub fn from_isometry(iso: &Isometry3<f32>) -> NetTransform {
+        NetTransform::new(iso.disp, iso.rot)
+    }
+
+    /// Keep the scale of `base`
+    pub fn to_isometry(&self, base: &Isometry3<f32>) -> Isometry3<f32> {
+        Isometry3 {
+            disp: self.position,
+            rot: self.rotation,
+            scale: base.scale,
+        }
+    }
+}
+
+impl Interpolate for NetTransform {
+    fn interpolate(&self, other: &NetTransform, t: f32) -> NetTransform {
+        NetTransform {
+            position: self.position.interpolate(&other.position, t),
+            // do not extrapolate rotations, it overshoots badly
+            rotation: self.rotation.interpolate(&other.rotation, t.min(1.0)),
+        }
+    }
+}
+
+/// Snapshots of a remote object, played back `delay` seconds behind the latest one,
+/// so there is (almost) always a pair of snapshots to interpolate between.
+pub struct InterpolationBuffer<T> {
+    /// Playback delay behind the latest snapshot, usually 2 or 3 snapshot intervals
+    pub delay: f64,
+    /// How long to extrapolate when snapshots stop arriving
+    pub max_extrapolation: f64,
+    /// Rate (per second) of correcting the playback time toward its target
+    pub smoothing: f64,
+
+    snapshots: VecDeque<(f64, T)>,
+    time: Option<f64>,
+}
+
+impl<T: Interpolate> Default for InterpolationBuffer<T> {
+    fn default() -> InterpolationBuffer<T> {
+        InterpolationBuffer {
+            delay: 0.1,
+            max_extrapolation: 0.25,
+            smoothing: 2.0,
+            snapshots: VecDeque::new(),
+            time: None,
+        }
+    }
+}
+
+impl<T: Interpolate> InterpolationBuffer<T> {
+    pub fn new(delay: f64) -> InterpolationBuffer<T> {
+        InterpolationBuffer {
+            delay,
+            ..Default::default()
+        }
+    }
+
+    /// Add a snapshot taken at `time` (of the sender), late snapshots are ignored
+    pub fn push(&mut self, time: f64, state: T) {
+        if let Some(&(last, _)) = self.snapshots.back() {
+            if time <= last {
+                return;
+            }
+        }
+
+        self.snapshots.push_back((time, state));
+        while self.snapshots.len() > MAX_SNAPSHOTS {
+            self.snapshots.pop_front();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+        self.time = None;
+    }
+
+    pub fn latest(&self) -> Option<&T> {
+        self.snapshots.back().map(|&(_, ref s)| s)
+    }
+
+    /// Current playback time, in the sender's clock
+    pub fn time(&self) -> Option<f64> {
+        self.time
+    }
+
+    /// Advance the playback and return the state to show
+    pub fn step(&mut self, dt: f64) -> Option<T> {
+        let target = match self.snapshots.back() {
+            Some(&(last, _)) => last - self.delay,
+            None => return None,
+        };
+
+        let time = match self.time {
+            Some(t) if (t + dt - target).abs() < MAX_DRIFT => {
+                let t = t + dt;
+                t + (target - t) * (self.smoothing * dt).min(1.0)
+            }
+            _ => target,
+        };
+        self.time = Some(time);
+
+        // keep one snapshot before the playback time
+        while self.snapshots.len() > 2 && self.snapshots[1].0 <= time {
+            self.snapshots.pop_front();
+        }
+
+        self.sample(time)
+    }
+
+    /// The state at `time`, interpolated or extrapolated
+    pub fn sample(&self, time: f64) -> Option<T> {
+        let n = self.snapshots.len();
+        if n == 0 {
+            return None;
+        }
+        if n == 1 || time <= self.snapshots[0].0 {
+            return Some(self.snapshots[0].1.clone());
+        }
+
+        let i = (1..n)
+            .find(|i| self.snapshots[*i].0 >= time)
+            .unwrap_or(n - 1);
+
+        let (t0, ref s0) = self.snapshots[i - 1];
+        let (t1, ref s1) = self.snapshots[i];
+        let time = time.min(t1 + self.max_extrapolation);
+
+        Some(s0.interpolate(s1, ((time - t0) / (t1 - t0)) as f32))
+    }
+}
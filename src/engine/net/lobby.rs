@@ -0,0 +1,393 @@
+//! Lobby client, a small json protocol over a `Transport` (usually a `WebSocketTransport`)
+//!
+//! Client requests and server messages are json objects tagged by `"type"` :
+//!
+//! ```json
+//! { "type": "create_room", "name": "Alice's game", "max_players": 4, "metadata": { "map": "docks" } }
+//! { "type": "joined", "room": { "id": "r1", "name": "Alice's game", "host": 7, "max_players": 4,
+//!                               "players": [ { "id": 7, "name": "Alice", "metadata": {} } ] } }
+//! ```
+
+use super::{Channel, PeerId, Transport, TransportEvent, WebSocketTransport,
+            WEBSOCKET_SERVER_PEER};
+
+use serde_json::{self, Value};
+use std::collections::{BTreeMap, VecDeque};
+use std::mem;
+
+pub type RoomId = String;
+pub type PlayerId = u32;
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct PlayerInfo {
+    pub id: PlayerId,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub metadata: BTreeMap<String, Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct RoomInfo {
+    pub id: RoomId,
+    #[serde(default)]
+    pub name: String,
+    pub host: PlayerId,
+    #[serde(default)]
+    pub max_players: u32,
+    #[serde(default)]
+    pub players: Vec<PlayerInfo>,
+    #[serde(default)]
+    pub metadata: BTreeMap<String, Value>,
+}
+
+impl RoomInfo {
+    pub fn player(&self, id: PlayerId) -> Option<&PlayerInfo> {
+        self.players.iter().find(|p| p.id == id)
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.max_players > 0 && self.players.len() as u32 >= self.max_players
+    }
+}
+
+/// Messages from client to lobby server
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LobbyRequest {
+    Hello {
+        name: String,
+        metadata: BTreeMap<String, Value>,
+    },
+    ListRooms,
+    CreateRoom {
+        name: String,
+        max_players: u32,
+        metadata: BTreeMap<String, Value>,
+    },
+    JoinRoom {
+        room: RoomId,
+    },
+    LeaveRoom,
+    SetPlayerData {
+        metadata: BTreeMap<String, Value>,
+    },
+    /// Only accepted from the host
+    SetRoomData {
+        metadata: BTreeMap<String, Value>,
+    },
+    /// Only accepted from the host
+    SetHost {
+        host: PlayerId,
+    },
+    /// Relayed to another player of the room (e.g. connection offers of a peer transport)
+    Signal {
+        to: PlayerId,
+        data: Value,
+    },
+}
+
+/// Messages from lobby server to client
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LobbyMessage {
+    Welcome { player: PlayerId },
+    RoomList { rooms: Vec<RoomInfo> },
+    Joined { room: RoomInfo },
+    Left,
+    PlayerJoined { player: PlayerInfo },
+    PlayerLeft { player: PlayerId },
+    PlayerData { player: PlayerInfo },
+    RoomData { metadata: BTreeMap<String, Value> },
+    HostChanged { host: PlayerId },
+    Signal { from: PlayerId, data: Value },
+    Error { message: String },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LobbyEvent {
+    Connected(PlayerId),
+    Disconnected,
+    RoomList(Vec<RoomInfo>),
+    JoinedRoom(RoomInfo),
+    LeftRoom,
+    PlayerJoined(PlayerInfo),
+    PlayerLeft(PlayerId),
+    PlayerDataChanged(PlayerInfo),
+    RoomDataChanged,
+    /// The host of the current room has changed, `local` is true when this client becomes the host.
+    /// Games should move the server role (e.g. `Network::host`) to the new host here.
+    HostMigrated {
+        old: PlayerId,
+        new: PlayerId,
+        local: bool,
+    },
+    Signal { from: PlayerId, data: Value },
+    Error(String),
+}
+
+/// Lobby client, create, join and list rooms and share player metadata.
+///
+/// Call `step` every frame and handle the events from `poll_events`.
+/// When the host leaves, the player with the lowest id is elected until the server
+/// says otherwise, so all clients agree on the new host without waiting.
+pub struct LobbyClient {
+    transport: Box<Transport>,
+    server: PeerId,
+    name: String,
+    metadata: BTreeMap<String, Value>,
+
+    player: Option<PlayerId>,
+    room: Option<RoomInfo>,
+    rooms: Vec<RoomInfo>,
+    events: VecDeque<LobbyEvent>,
+}
+
+impl LobbyClient {
+    /// Connect to a lobby server by websocket
+    pub fn connect(url: &str, name: &str) -> LobbyClient {
+        let transport = WebSocketTransport::connect(url).with_text_frames(true);
+        LobbyClient::with_transport(Box::new(transport), WEBSOCKET_SERVER_PEER, name)
+    }
+
+    /// Use another transport, the lobby server is the peer `server`
+    pub fn with_transport(transport: Box<Transport>, server: PeerId, name: &str) -> LobbyClient {
+        LobbyClient {
+            transport,
+            server,
+            name: name.to_owned(),
+            metadata: BTreeMap::new(),
+            player: None,
+            room: None,
+            rooms: Vec::new(),
+            events: VecDeque::new(),
+        }
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.player.is_some()
+    }
+
+    /// Id given by the lobby server
+    pub fn player_id(&self) -> Option<PlayerId> {
+        self.player
+    }
+
+    pub fn room(&self) -> Option<&RoomInfo> {
+        self.room.as_ref()
+    }
+
+    /// Rooms of the last `list_rooms` answer
+    pub fn rooms(&self) -> &[RoomInfo] {
+        &self.rooms
+    }
+
+    pub fn is_host(&self) -> bool {
+        match (self.player, self.room.as_ref()) {
+            (Some(player), Some(room)) => room.host == player,
+            _ => false,
+        }
+    }
+
+    pub fn list_rooms(&mut self) {
+        self.send(&LobbyRequest::ListRooms);
+    }
+
+    pub fn create_room(&mut self, name: &str, max_players: u32, metadata: BTreeMap<String, Value>) {
+        self.send(&LobbyRequest::CreateRoom {
+            name: name.to_owned(),
+            max_players,
+            metadata,
+        });
+    }
+
+    pub fn join_room(&mut self, room: &str) {
+        self.send(&LobbyRequest::JoinRoom {
+            room: room.to_owned(),
+        });
+    }
+
+    pub fn leave_room(&mut self) {
+        self.send(&LobbyRequest::LeaveRoom);
+    }
+
+    /// Set a metadata of the local player (e.g. team, character, ready state)
+    pub fn set_player_data(&mut self, key: &str, value: Value) {
+        self.metadata.insert(key.to_owned(), value);
+
+        let metadata = self.metadata.clone();
+        self.send(&LobbyRequest::SetPlayerData { metadata });
+    }
+
+    /// Set a metadata of the current room, only for the host
+    pub fn set_room_data(&mut self, key: &str, value: Value) {
+        if !self.is_host() {
+            println!("Only the room host can set room data");
+            return;
+        }
+
+        let metadata = match self.room {
+            Some(ref mut room) => {
+                room.metadata.insert(key.to_owned(), value);
+                room.metadata.clone()
+            }
+            None => return,
+        };
+
+        self.send(&LobbyRequest::SetRoomData { metadata });
+    }
+
+    /// Hand the host role to another player, only for the host
+    pub fn transfer_host(&mut self, host: PlayerId) {
+        if !self.is_host() {
+            println!("Only the room host can transfer the host role");
+            return;
+        }
+
+        self.send(&LobbyRequest::SetHost { host });
+    }
+
+    /// Send signaling data to another player of the room
+    pub fn signal(&mut self, to: PlayerId, data: Value) {
+        self.send(&LobbyRequest::Signal { to, data });
+    }
+
+    pub fn poll_events(&mut self) -> Vec<LobbyEvent> {
+        self.events.drain(..).collect()
+    }
+
+    pub fn disconnect(&mut self) {
+        let server = self.server;
+        self.transport.disconnect(server);
+    }
+
+    pub fn step(&mut self) {
+        for evt in self.transport.poll().into_iter() {
+            match evt {
+                TransportEvent::Connected(peer) if peer == self.server => {
+                    let hello = LobbyRequest::Hello {
+                        name: self.name.clone(),
+                        metadata: self.metadata.clone(),
+                    };
+                    self.send(&hello);
+                }
+                TransportEvent::Disconnected(peer) if peer == self.server => {
+                    self.player = None;
+                    self.room = None;
+                    self.events.push_back(LobbyEvent::Disconnected);
+                }
+                TransportEvent::Message(peer, _, data) if peer == self.server => {
+                    match serde_json::from_slice(&data) {
+                        Ok(msg) => self.handle(msg),
+                        Err(e) => println!("Invalid lobby message, reason: {:?}", e),
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn send(&mut self, req: &LobbyRequest) {
+        match serde_json::to_vec(req) {
+            Ok(data) => {
+                let server = self.server;
+                self.transport
+                    .send(server, Channel::ReliableOrdered, &data);
+            }
+            Err(e) => println!("Fail to encode lobby request, reason: {:?}", e),
+        }
+    }
+
+    fn handle(&mut self, msg: LobbyMessage) {
+        let evt = match msg {
+            LobbyMessage::Welcome { player } => {
+                self.player = Some(player);
+                LobbyEvent::Connected(player)
+            }
+            LobbyMessage::RoomList { rooms } => {
+                self.rooms = rooms.clone();
+                LobbyEvent::RoomList(rooms)
+            }
+            LobbyMessage::Joined { room } => {
+                self.room = Some(room.clone());
+                LobbyEvent::JoinedRoom(room)
+            }
+            LobbyMessage::Left => {
+                self.room = None;
+                LobbyEvent::LeftRoom
+            }
+            LobbyMessage::PlayerJoined { player } => {
+                if let Some(ref mut room) = self.room {
+                    room.players.retain(|p| p.id != player.id);
+                    room.players.push(player.clone());
+                }
+                LobbyEvent::PlayerJoined(player)
+            }
+            LobbyMessage::PlayerLeft { player } => {
+                let old_host = match self.room {
+                    Some(ref mut room) => {
+                        room.players.retain(|p| p.id != player);
+                        room.host
+                    }
+                    None => return,
+                };
+
+                self.events.push_back(LobbyEvent::PlayerLeft(player));
+
+                if old_host == player {
+                    let next = self.room
+                        .as_ref()
+                        .and_then(|room| room.players.iter().map(|p| p.id).min());
+
+                    if let Some(next) = next {
+                        self.migrate_host(next);
+                    }
+                }
+                return;
+            }
+            LobbyMessage::PlayerData { player } => {
+                if let Some(ref mut room) = self.room {
+                    if let Some(p) = room.players.iter_mut().find(|p| p.id == player.id) {
+                        *p = player.clone();
+                    }
+                }
+                LobbyEvent::PlayerDataChanged(player)
+            }
+            LobbyMessage::RoomData { metadata } => {
+                match self.room {
+                    Some(ref mut room) => room.metadata = metadata,
+                    None => return,
+                }
+                LobbyEvent::RoomDataChanged
+            }
+            LobbyMessage::HostChanged { host } => {
+                self.migrate_host(host);
+                return;
+            }
+            LobbyMessage::Signal { from, data } => LobbyEvent::Signal { from, data },
+            LobbyMessage::Error { message } => {
+                println!("Lobby error: {}", message);
+                LobbyEvent::Error(message)
+            }
+        };
+
+        self.events.push_back(evt);
+    }
+
+    fn migrate_host(&mut self, new: PlayerId) {
+        let old = match self.room {
+            Some(ref mut room) => mem::replace(&mut room.host, new),
+            None => return,
+        };
+
+        if old == new {
+            return;
+        }
+
+        self.events.push_back(LobbyEvent::HostMigrated {
+            old,
+            new,
+            local: self.player == Some(new),
+        });
+    }
+}
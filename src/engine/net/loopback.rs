@@ -0,0 +1,198 @@
+//! In-memory transport, for running server and clients in the same process (e.g. tests)
+
+use super::{Channel, PeerId, Transport, TransportEvent};
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::rc::Rc;
+
+struct Pending {
+    event: TransportEvent,
+    /// Remaining polls of the receiver before delivery
+    delay: u32,
+}
+
+#[derive(Default)]
+struct HubState {
+    next_id: PeerId,
+    endpoints: BTreeMap<PeerId, VecDeque<Pending>>,
+    /// Disconnected pairs of endpoints, lower id first
+    cut: BTreeSet<(PeerId, PeerId)>,
+    latency: u32,
+    drop_every: u32,
+    unreliable_count: u32,
+}
+
+/// Connects all its loopback transports to each other.
+///
+/// Delivery is deterministic : messages arrive in sent order after `latency` polls,
+/// and every `drop_every`-th unreliable message is lost.
+#[derive(Clone, Default)]
+pub struct LoopbackHub {
+    state: Rc<RefCell<HubState>>,
+}
+
+impl LoopbackHub {
+    pub fn new() -> LoopbackHub {
+        Default::default()
+    }
+
+    /// Delay messages by a number of polls of the receiver
+    pub fn set_latency(&self, polls: u32) {
+        self.state.borrow_mut().latency = polls;
+    }
+
+    /// Lose every n-th unreliable message, 0 never loses
+    pub fn set_drop_every(&self, n: u32) {
+        self.state.borrow_mut().drop_every = n;
+    }
+
+    /// A new transport connected to all existing ones
+    pub fn connect(&self) -> LoopbackTransport {
+        let mut state = self.state.borrow_mut();
+        let id = state.next_id;
+        state.next_id += 1;
+
+        let others: Vec<PeerId> = state.endpoints.keys().cloned().collect();
+        let mut queue = VecDeque::new();
+
+        for other in others.into_iter() {
+            queue.push_back(Pending {
+                event: TransportEvent::Connected(other),
+                delay: 0,
+            });
+            state.endpoints.get_mut(&other).unwrap().push_back(Pending {
+                event: TransportEvent::Connected(id),
+                delay: 0,
+            });
+        }
+
+        state.endpoints.insert(id, queue);
+
+        LoopbackTransport {
+            id,
+            hub: self.clone(),
+        }
+    }
+
+    fn cut(&self, a: PeerId, b: PeerId) {
+        let mut state = self.state.borrow_mut();
+
+        if !state.is_linked(a, b) {
+            return;
+        }
+        state.cut.insert((a.min(b), a.max(b)));
+
+        for &(from, to) in [(a, b), (b, a)].iter() {
+            if let Some(queue) = state.endpoints.get_mut(&to) {
+                queue.push_back(Pending {
+                    event: TransportEvent::Disconnected(from),
+                    delay: 0,
+                });
+            }
+        }
+    }
+
+    fn remove(&self, id: PeerId) {
+        let mut state = self.state.borrow_mut();
+
+        if state.endpoints.remove(&id).is_none() {
+            return;
+        }
+
+        let others: Vec<PeerId> = state.endpoints.keys().cloned().collect();
+        for other in others.into_iter() {
+            if !state.cut.contains(&(id.min(other), id.max(other))) {
+                state.endpoints.get_mut(&other).unwrap().push_back(Pending {
+                    event: TransportEvent::Disconnected(id),
+                    delay: 0,
+                });
+            }
+        }
+    }
+}
+
+impl HubState {
+    fn is_linked(&self, a: PeerId, b: PeerId) -> bool {
+        a != b && self.endpoints.contains_key(&a) && self.endpoints.contains_key(&b)
+            && !self.cut.contains(&(a.min(b), a.max(b)))
+    }
+}
+
+pub struct LoopbackTransport {
+    id: PeerId,
+    hub: LoopbackHub,
+}
+
+impl Transport for LoopbackTransport {
+    fn local_id(&self) -> PeerId {
+        self.id
+    }
+
+    fn peers(&self) -> Vec<PeerId> {
+        let state = self.hub.state.borrow();
+
+        state
+            .endpoints
+            .keys()
+            .filter(|id| state.is_linked(self.id, **id))
+            .cloned()
+            .collect()
+    }
+
+    fn send(&mut self, peer: PeerId, channel: Channel, data: &[u8]) {
+        let mut state = self.hub.state.borrow_mut();
+
+        if !state.is_linked(self.id, peer) {
+            return;
+        }
+
+        if channel == Channel::Unreliable && state.drop_every > 0 {
+            state.unreliable_count += 1;
+            if state.unreliable_count % state.drop_every == 0 {
+                return;
+            }
+        }
+
+        let delay = state.latency;
+        if let Some(queue) = state.endpoints.get_mut(&peer) {
+            queue.push_back(Pending {
+                event: TransportEvent::Message(self.id, channel, data.to_vec()),
+                delay,
+            });
+        }
+    }
+
+    fn poll(&mut self) -> Vec<TransportEvent> {
+        let mut state = self.hub.state.borrow_mut();
+        let queue = match state.endpoints.get_mut(&self.id) {
+            Some(queue) => queue,
+            None => return Vec::new(),
+        };
+
+        let mut events = Vec::new();
+        while queue.front().map(|p| p.delay == 0).unwrap_or(false) {
+            events.push(queue.pop_front().unwrap().event);
+        }
+
+        for p in queue.iter_mut() {
+            p.delay = p.delay.saturating_sub(1);
+        }
+
+        events
+    }
+
+    fn disconnect(&mut self, peer: PeerId) {
+        self.hub.cut(self.id, peer);
+    }
+
+    fn supports_unreliable(&self) -> bool {
+        true
+    }
+}
+
+impl Drop for LoopbackTransport {
+    fn drop(&mut self) {
+        self.hub.remove(self.id);
+    }
+}
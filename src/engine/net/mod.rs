@@ -0,0 +1,49 @@
+//! Networking
+//!
+//! `Network` sends typed RPC messages (see `RpcMessage`) over a `Transport`,
+//! and checks whether the sender has the authority to send them.
+//! `LobbyClient` finds and joins rooms of other players on a lobby server.
+//!
+//! Transports are selected by passing one to `Network::host` or `Network::connect` :
+//! `WebSocketTransport` to a dedicated server, or `WebRtcTransport` between players,
+//! which avoids the head-of-line blocking of websockets for `Channel::Unreliable` messages.
+//!
+//! Networked movement is smoothed by `InterpolationBuffer` for remote objects, and
+//! `ClientPrediction` with `InputQueue` on the server for the local player.
+//! `TransformQuantization` packs transforms in a few bytes, or less as a delta.
+//! `DesyncChecker` finds the first tick where peers (or runs) of a deterministic
+//! simulation diverge.
+
+mod desync;
+mod interpolation;
+mod lobby;
+mod loopback;
+mod network;
+mod prediction;
+mod quantize;
+mod rpc;
+mod transport;
+mod webrtc;
+mod websocket;
+
+pub use self::desync::{Desync, DesyncChecker, DesyncRecording, TickHashes};
+pub use self::interpolation::{Interpolate, InterpolationBuffer, NetTransform};
+pub use self::lobby::{LobbyClient, LobbyEvent, LobbyMessage, LobbyRequest, PlayerId, PlayerInfo,
+                      RoomId, RoomInfo};
+
+pub use self::loopback::{LoopbackHub, LoopbackTransport};
+pub use self::network::{NetEvent, NetRole, Network};
+pub use self::prediction::{ClientPrediction, InputQueue, Predicted, PredictedInput};
+pub use self::quantize::{BitReader, BitWriter, Quantizer, RotationQuantizer,
+                         TransformQuantization};
+pub use self::rpc::{RpcAuthority, RpcCall, RpcMessage};
+pub use self::transport::{Channel, Transport, TransportEvent};
+pub use self::webrtc::WebRtcTransport;
+pub use self::websocket::{WebSocketTransport, WEBSOCKET_SERVER_PEER};
+
+/// Id of a connected peer, given by the transport
+pub type PeerId = u32;
+
+/// Id of a networked object, shared by all peers
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Ord, PartialOrd, Deserialize, Serialize)]
+pub struct NetId(pub u32);
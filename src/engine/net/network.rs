@@ -0,0 +1,320 @@
+use super::rpc::{RpcEnvelope, RpcInfo};
+use super::{NetId, PeerId, RpcAuthority, RpcCall, RpcMessage, Transport, TransportEvent};
+
+use serde_json::{self, Value};
+use std::collections::{HashMap, VecDeque};
+
+// Calls kept for each message type which nobody has received yet
+const MAX_PENDING_CALLS: usize = 256;
+// Unregistered message types kept, the names come from the peers
+const MAX_PENDING_NAMES: usize = 64;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NetRole {
+    Offline,
+    Server,
+    Client { server: PeerId },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NetEvent {
+    PeerConnected(PeerId),
+    PeerDisconnected(PeerId),
+    /// A call was dropped because the sender has no authority to send it
+    Rejected { sender: PeerId, name: String },
+}
+
+/// Networking service, sends and receives typed RPC messages over a `Transport`.
+///
+/// Actors usually call `receive` or `receive_for` (for a network object) every frame.
+/// Calls of a message type received before it is registered are kept until `register`,
+/// which checks their authority. Only the last `MAX_PENDING_NAMES` unregistered types are kept.
+pub struct Network {
+    role: NetRole,
+    transport: Option<Box<Transport>>,
+    registry: HashMap<String, RpcInfo>,
+    inbox: HashMap<String, VecDeque<(PeerId, Option<NetId>, Value)>>,
+    unregistered: HashMap<String, VecDeque<(PeerId, Option<NetId>, Value)>>,
+    /// Names of `unregistered`, the oldest first
+    unregistered_names: VecDeque<String>,
+    events: VecDeque<NetEvent>,
+}
+
+impl Default for Network {
+    fn default() -> Network {
+        Network {
+            role: NetRole::Offline,
+            transport: None,
+            registry: HashMap::new(),
+            inbox: HashMap::new(),
+            unregistered: HashMap::new(),
+            unregistered_names: VecDeque::new(),
+            events: VecDeque::new(),
+        }
+    }
+}
+
+impl Network {
+    pub fn new() -> Network {
+        Default::default()
+    }
+
+    /// Start as server on the transport
+    pub fn host(&mut self, transport: Box<Transport>) {
+        self.transport = Some(transport);
+        self.role = NetRole::Server;
+    }
+
+    /// Start as client, the server is the peer `server` of the transport
+    pub fn connect(&mut self, transport: Box<Transport>, server: PeerId) {
+        self.transport = Some(transport);
+        self.role = NetRole::Client { server };
+    }
+
+    pub fn disconnect(&mut self) {
+        if let Some(mut transport) = self.transport.take() {
+            for peer in transport.peers().into_iter() {
+                transport.disconnect(peer);
+            }
+        }
+
+        self.role = NetRole::Offline;
+        self.inbox.clear();
+        self.unregistered.clear();
+        self.unregistered_names.clear();
+    }
+
+    pub fn role(&self) -> NetRole {
+        self.role
+    }
+
+    pub fn is_server(&self) -> bool {
+        self.role == NetRole::Server
+    }
+
+    pub fn is_client(&self) -> bool {
+        match self.role {
+            NetRole::Client { .. } => true,
+            _ => false,
+        }
+    }
+
+    pub fn local_id(&self) -> Option<PeerId> {
+        self.transport.as_ref().map(|t| t.local_id())
+    }
+
+    pub fn peers(&self) -> Vec<PeerId> {
+        self.transport.as_ref().map_or(Vec::new(), |t| t.peers())
+    }
+
+    pub fn transport(&self) -> Option<&Transport> {
+        self.transport.as_ref().map(|t| &**t)
+    }
+
+    pub fn transport_mut(&mut self) -> Option<&mut Transport> {
+        match self.transport {
+            Some(ref mut t) => Some(&mut **t),
+            None => None,
+        }
+    }
+
+    /// Register a message type, so it could be received
+    pub fn register<T: RpcMessage>(&mut self) {
+        if self.registry.contains_key(T::NAME) {
+            return;
+        }
+
+        self.registry.insert(T::NAME.to_owned(), RpcInfo::of::<T>());
+
+        let pending = self.unregistered.remove(T::NAME).unwrap_or_default();
+        self.unregistered_names.retain(|name| name != T::NAME);
+        for (sender, target, payload) in pending.into_iter() {
+            self.enqueue(T::NAME.to_owned(), sender, target, payload);
+        }
+    }
+
+    /// Call on the server (from client), or on all clients (from server)
+    pub fn call<T: RpcMessage>(&mut self, msg: &T) -> bool {
+        self.send(None, None, msg)
+    }
+
+    /// Call on a network object
+    pub fn call_target<T: RpcMessage>(&mut self, target: NetId, msg: &T) -> bool {
+        self.send(None, Some(target), msg)
+    }
+
+    /// Call on a specific peer only
+    pub fn call_peer<T: RpcMessage>(&mut self, peer: PeerId, target: Option<NetId>, msg: &T) -> bool {
+        self.send(Some(peer), target, msg)
+    }
+
+    /// All received calls of a message type
+    pub fn receive<T: RpcMessage>(&mut self) -> Vec<RpcCall<T>> {
+        self.receive_filtered(|_| true)
+    }
+
+    /// Received calls of a message type sent to a network object
+    pub fn receive_for<T: RpcMessage>(&mut self, target: NetId) -> Vec<RpcCall<T>> {
+        self.receive_filtered(|t| t == Some(target))
+    }
+
+    pub fn poll_events(&mut self) -> Vec<NetEvent> {
+        self.events.drain(..).collect()
+    }
+
+    pub fn step(&mut self) {
+        let events = match self.transport {
+            Some(ref mut t) => t.poll(),
+            None => return,
+        };
+
+        for evt in events.into_iter() {
+            match evt {
+                TransportEvent::Connected(peer) => {
+                    self.events.push_back(NetEvent::PeerConnected(peer))
+                }
+                TransportEvent::Disconnected(peer) => {
+                    self.events.push_back(NetEvent::PeerDisconnected(peer))
+                }
+                TransportEvent::Message(peer, _, data) => self.handle_message(peer, &data),
+            }
+        }
+    }
+
+    fn can_send(&self, authority: RpcAuthority) -> bool {
+        match (authority, self.role) {
+            (_, NetRole::Offline) => false,
+            (RpcAuthority::Server, NetRole::Server) => true,
+            (RpcAuthority::Client, NetRole::Client { .. }) => true,
+            (RpcAuthority::Any, _) => true,
+            _ => false,
+        }
+    }
+
+    fn can_receive(&self, authority: RpcAuthority, sender: PeerId) -> bool {
+        match (authority, self.role) {
+            (_, NetRole::Offline) => false,
+            (RpcAuthority::Server, NetRole::Client { server }) => sender == server,
+            (RpcAuthority::Client, NetRole::Server) => true,
+            (RpcAuthority::Any, _) => true,
+            _ => false,
+        }
+    }
+
+    fn send<T: RpcMessage>(&mut self, peer: Option<PeerId>, target: Option<NetId>, msg: &T) -> bool {
+        self.register::<T>();
+
+        if !self.can_send(T::AUTHORITY) {
+            println!("No authority to send {}", T::NAME);
+            return false;
+        }
+
+        let data = match RpcEnvelope::encode(target, msg) {
+            Some(data) => data,
+            None => {
+                println!("Fail to encode {}", T::NAME);
+                return false;
+            }
+        };
+
+        let role = self.role;
+        let transport = self.transport.as_mut().unwrap();
+
+        match (peer, role) {
+            (Some(peer), _) => transport.send(peer, T::CHANNEL, &data),
+            (None, NetRole::Client { server }) => transport.send(server, T::CHANNEL, &data),
+            (None, _) => transport.broadcast(T::CHANNEL, &data),
+        }
+
+        true
+    }
+
+    fn handle_message(&mut self, sender: PeerId, data: &[u8]) {
+        let envelope = match RpcEnvelope::decode(data) {
+            Some(envelope) => envelope,
+            None => {
+                println!("Fail to decode message from peer {}", sender);
+                return;
+            }
+        };
+
+        if !self.registry.contains_key(&envelope.name) {
+            if !self.unregistered.contains_key(&envelope.name) {
+                println!(
+                    "Message {} from peer {} is kept until it is registered",
+                    envelope.name, sender
+                );
+
+                self.unregistered_names.push_back(envelope.name.clone());
+                while self.unregistered_names.len() > MAX_PENDING_NAMES {
+                    if let Some(oldest) = self.unregistered_names.pop_front() {
+                        self.unregistered.remove(&oldest);
+                    }
+                }
+            }
+
+            let queue = self.unregistered
+                .entry(envelope.name.clone())
+                .or_insert_with(VecDeque::new);
+
+            queue.push_back((sender, envelope.target, envelope.payload));
+            while queue.len() > MAX_PENDING_CALLS {
+                queue.pop_front();
+            }
+            return;
+        }
+
+        self.enqueue(envelope.name, sender, envelope.target, envelope.payload);
+    }
+
+    /// Queue a call of a registered message type, if the sender has the authority to send it
+    fn enqueue(&mut self, name: String, sender: PeerId, target: Option<NetId>, payload: Value) {
+        let info = self.registry[&name];
+        if !self.can_receive(info.authority, sender) {
+            self.events.push_back(NetEvent::Rejected { sender, name });
+            return;
+        }
+
+        let queue = self.inbox.entry(name).or_insert_with(VecDeque::new);
+
+        queue.push_back((sender, target, payload));
+        while queue.len() > MAX_PENDING_CALLS {
+            queue.pop_front();
+        }
+    }
+
+    fn receive_filtered<T, F>(&mut self, f: F) -> Vec<RpcCall<T>>
+    where
+        T: RpcMessage,
+        F: Fn(Option<NetId>) -> bool,
+    {
+        self.register::<T>();
+
+        let queue = match self.inbox.get_mut(T::NAME) {
+            Some(queue) => queue,
+            None => return Vec::new(),
+        };
+
+        let mut calls = Vec::new();
+        let mut rest = VecDeque::new();
+
+        for (sender, target, payload) in queue.drain(..) {
+            if !f(target) {
+                rest.push_back((sender, target, payload));
+                continue;
+            }
+
+            match serde_json::from_value(payload) {
+                Ok(msg) => calls.push(RpcCall {
+                    sender,
+                    target,
+                    msg,
+                }),
+                Err(e) => println!("Fail to decode {}, reason: {}", T::NAME, e),
+            }
+        }
+
+        *queue = rest;
+        calls
+    }
+}
@@ -0,0 +1,199 @@
+use super::interpolation::Interpolate;
+
+use std::collections::{BTreeMap, VecDeque};
+
+/// A state which is simulated by the client before the server confirms it
+pub trait Predicted: Interpolate {
+    type Input: Clone;
+
+    /// Simulate one input, must be the same code as in the server
+    fn apply_input(&mut self, input: &Self::Input, dt: f32);
+
+    /// Size of the difference between two states, e.g. the distance of positions
+    fn error(&self, other: &Self) -> f32;
+}
+
+/// Sequence numbers wrap around, `a` is before `b` when it is less than half the range behind
+fn seq_before(a: u32, b: u32) -> bool {
+    a != b && b.wrapping_sub(a) < u32::max_value() / 2
+}
+
+/// An input tagged by sequence number, sent from client to server
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct PredictedInput<I> {
+    pub seq: u32,
+    pub dt: f32,
+    pub input: I,
+}
+
+/// Client side prediction of the local player.
+///
+/// Inputs are applied immediately and kept until the server acknowledges them. When a server
+/// state arrives, unacknowledged inputs are replayed on top of it, and the shown state is
+/// smoothly corrected toward the result.
+pub struct ClientPrediction<S: Predicted> {
+    /// Rate (per second) of correcting the shown state
+    pub smoothing: f32,
+    /// Errors larger than this are corrected immediately
+    pub snap_error: f32,
+    /// Inputs kept while waiting for the server, older ones are dropped
+    pub max_pending: usize,
+
+    predicted: S,
+    shown: S,
+    pending: VecDeque<PredictedInput<S::Input>>,
+    next_seq: u32,
+}
+
+impl<S: Predicted> ClientPrediction<S> {
+    pub fn new(state: S) -> ClientPrediction<S> {
+        ClientPrediction {
+            smoothing: 10.0,
+            snap_error: 3.0,
+            max_pending: 128,
+            predicted: state.clone(),
+            shown: state,
+            pending: VecDeque::new(),
+            next_seq: 0,
+        }
+    }
+
+    /// The predicted state
+    pub fn state(&self) -> &S {
+        &self.predicted
+    }
+
+    /// The predicted state with smoothed corrections, for rendering
+    pub fn shown(&self) -> &S {
+        &self.shown
+    }
+
+    pub fn pending_inputs(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Apply an input locally, return the message to send to the server
+    pub fn apply(&mut self, input: S::Input, dt: f32) -> PredictedInput<S::Input> {
+        self.next_seq = self.next_seq.wrapping_add(1);
+        let msg = PredictedInput {
+            seq: self.next_seq,
+            dt,
+            input,
+        };
+
+        self.predicted.apply_input(&msg.input, dt);
+        self.shown.apply_input(&msg.input, dt);
+
+        self.pending.push_back(msg.clone());
+        while self.pending.len() > self.max_pending {
+            self.pending.pop_front();
+        }
+
+        msg
+    }
+
+    /// Server state after processing the input `last_seq`
+    pub fn reconcile(&mut self, server_state: S, last_seq: u32) {
+        while let Some(seq) = self.pending.front().map(|i| i.seq) {
+            if seq_before(last_seq, seq) {
+                break;
+            }
+            self.pending.pop_front();
+        }
+
+        let mut state = server_state;
+        for i in self.pending.iter() {
+            state.apply_input(&i.input, i.dt);
+        }
+
+        if state.error(&self.shown) > self.snap_error {
+            self.shown = state.clone();
+        }
+        self.predicted = state;
+    }
+
+    /// Smooth the shown state toward the predicted one, call it every frame
+    pub fn step(&mut self, dt: f32) {
+        let k = (self.smoothing * dt).min(1.0);
+        self.shown = self.shown.interpolate(&self.predicted, k);
+    }
+}
+
+/// Server side buffer of a client's inputs, in sequence order
+pub struct InputQueue<I> {
+    /// Inputs buffered before processing, to absorb network jitter
+    pub buffer_size: usize,
+    /// Inputs kept while waiting to be processed, the farthest ones are dropped
+    pub max_pending: usize,
+
+    inputs: BTreeMap<u32, PredictedInput<I>>,
+    last_processed: Option<u32>,
+}
+
+impl<I> Default for InputQueue<I> {
+    fn default() -> InputQueue<I> {
+        InputQueue {
+            buffer_size: 0,
+            max_pending: 128,
+            inputs: BTreeMap::new(),
+            last_processed: None,
+        }
+    }
+}
+
+impl<I: Clone> InputQueue<I> {
+    pub fn new() -> InputQueue<I> {
+        Default::default()
+    }
+
+    /// Add a received input, duplicated and already processed inputs are ignored
+    pub fn push(&mut self, input: PredictedInput<I>) {
+        if let Some(last) = self.last_processed {
+            if !seq_before(last, input.seq) {
+                return;
+            }
+        }
+
+        self.inputs.insert(input.seq, input);
+
+        // Inputs sent faster than processed, or far in the future
+        while self.inputs.len() > self.max_pending.max(self.buffer_size + 1) {
+            match self.last_seq() {
+                Some(seq) => self.inputs.remove(&seq),
+                None => break,
+            };
+        }
+    }
+
+    /// The first input in sequence order, which is not the smallest key across a wrap around
+    fn first_seq(&self) -> Option<u32> {
+        self.inputs.keys().cloned().fold(None, |first, seq| match first {
+            Some(first) if seq_before(first, seq) => Some(first),
+            _ => Some(seq),
+        })
+    }
+
+    fn last_seq(&self) -> Option<u32> {
+        self.inputs.keys().cloned().fold(None, |last, seq| match last {
+            Some(last) if seq_before(seq, last) => Some(last),
+            _ => Some(seq),
+        })
+    }
+
+    /// Next input to process, `None` when waiting for more
+    pub fn pop(&mut self) -> Option<PredictedInput<I>> {
+        if self.inputs.len() <= self.buffer_size {
+            return None;
+        }
+
+        let seq = self.first_seq()?;
+        let input = self.inputs.remove(&seq);
+        self.last_processed = Some(seq);
+        input
+    }
+
+    /// Sequence number to acknowledge with the next state sent to the client
+    pub fn last_processed(&self) -> Option<u32> {
+        self.last_processed
+    }
+}
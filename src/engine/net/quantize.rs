@@ -0,0 +1,293 @@
+//! Compact encoding of transforms, quantized to fixed ranges and bit widths,
+//! optionally as a delta against a baseline known by the receiver.
+
+use super::NetTransform;
+use math::*;
+
+/// Packs values of any bit width into bytes
+#[derive(Debug, Clone, Default)]
+pub struct BitWriter {
+    bytes: Vec<u8>,
+    bits: usize,
+}
+
+impl BitWriter {
+    pub fn new() -> BitWriter {
+        Default::default()
+    }
+
+    /// Write the lowest `bits` bits of value, up to 32
+    pub fn write_bits(&mut self, value: u32, bits: u8) {
+        for i in 0..bits as usize {
+            if self.bits % 8 == 0 {
+                self.bytes.push(0);
+            }
+            if (value >> i) & 1 == 1 {
+                let last = self.bytes.len() - 1;
+                self.bytes[last] |= 1 << (self.bits % 8);
+            }
+            self.bits += 1;
+        }
+    }
+
+    pub fn write_bool(&mut self, b: bool) {
+        self.write_bits(b as u32, 1);
+    }
+
+    pub fn bit_len(&self) -> usize {
+        self.bits
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+pub struct BitReader<'a> {
+    data: &'a [u8],
+    bits: usize,
+}
+
+impl<'a> BitReader<'a> {
+    pub fn new(data: &'a [u8]) -> BitReader<'a> {
+        BitReader { data, bits: 0 }
+    }
+
+    /// `None` when reading past the end
+    pub fn read_bits(&mut self, bits: u8) -> Option<u32> {
+        let mut value = 0;
+
+        for i in 0..bits as usize {
+            let byte = *self.data.get(self.bits / 8)?;
+            if (byte >> (self.bits % 8)) & 1 == 1 {
+                value |= 1 << i;
+            }
+            self.bits += 1;
+        }
+
+        Some(value)
+    }
+
+    pub fn read_bool(&mut self) -> Option<bool> {
+        self.read_bits(1).map(|b| b == 1)
+    }
+}
+
+/// Maps a float range to an integer of `bits` bits, values outside are clamped
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quantizer {
+    pub min: f32,
+    pub max: f32,
+    pub bits: u8,
+}
+
+impl Quantizer {
+    pub fn new(min: f32, max: f32, bits: u8) -> Quantizer {
+        assert!(bits > 0 && bits <= 32 && max > min);
+        Quantizer { min, max, bits }
+    }
+
+    fn max_value(&self) -> u32 {
+        if self.bits == 32 {
+            u32::max_value()
+        } else {
+            (1 << self.bits) - 1
+        }
+    }
+
+    /// Largest error of a quantized value
+    pub fn precision(&self) -> f32 {
+        (self.max - self.min) / self.max_value() as f32 * 0.5
+    }
+
+    pub fn quantize(&self, v: f32) -> u32 {
+        let k = ((v - self.min) / (self.max - self.min)).max(0.0).min(1.0);
+        (k as f64 * self.max_value() as f64).round() as u32
+    }
+
+    pub fn dequantize(&self, q: u32) -> f32 {
+        let k = q.min(self.max_value()) as f64 / self.max_value() as f64;
+        self.min + (k as f32) * (self.max - self.min)
+    }
+
+    pub fn write(&self, w: &mut BitWriter, v: f32) {
+        w.write_bits(self.quantize(v), self.bits);
+    }
+
+    pub fn read(&self, r: &mut BitReader) -> Option<f32> {
+        r.read_bits(self.bits).map(|q| self.dequantize(q))
+    }
+
+    pub fn write_vec3(&self, w: &mut BitWriter, v: Vector3<f32>) {
+        self.write(w, v.x);
+        self.write(w, v.y);
+        self.write(w, v.z);
+    }
+
+    pub fn read_vec3(&self, r: &mut BitReader) -> Option<Vector3<f32>> {
+        Some(Vector3::new(self.read(r)?, self.read(r)?, self.read(r)?))
+    }
+}
+
+const SQRT_HALF: f32 = 0.707107;
+
+/// Rotations encoded by the "smallest three" method : the index of the largest
+/// component (2 bits) and the three others in `bits` bits each
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RotationQuantizer {
+    component: Quantizer,
+}
+
+impl RotationQuantizer {
+    pub fn new(bits: u8) -> RotationQuantizer {
+        RotationQuantizer {
+            component: Quantizer::new(-SQRT_HALF, SQRT_HALF, bits),
+        }
+    }
+
+    pub fn quantize(&self, q: Quaternion<f32>) -> [u32; 4] {
+        let q = q.normalize();
+        let c = [q.v.x, q.v.y, q.v.z, q.s];
+
+        let largest = (1..4).fold(0, |m, i| if c[i].abs() > c[m].abs() { i } else { m });
+        // q and -q are the same rotation, make the largest positive
+        let sign = if c[largest] < 0.0 { -1.0 } else { 1.0 };
+
+        let mut out = [largest as u32, 0, 0, 0];
+        let mut j = 1;
+        for i in 0..4 {
+            if i != largest {
+                out[j] = self.component.quantize(c[i] * sign);
+                j += 1;
+            }
+        }
+        out
+    }
+
+    pub fn dequantize(&self, q: [u32; 4]) -> Quaternion<f32> {
+        let largest = (q[0] & 3) as usize;
+        let mut c = [0.0; 4];
+        let mut sum = 0.0;
+        let mut j = 1;
+
+        for i in 0..4 {
+            if i != largest {
+                c[i] = self.component.dequantize(q[j]);
+                sum += c[i] * c[i];
+                j += 1;
+            }
+        }
+        c[largest] = (1.0 - sum).max(0.0).sqrt();
+
+        Quaternion::new(c[3], c[0], c[1], c[2]).normalize()
+    }
+
+    pub fn write(&self, w: &mut BitWriter, rot: Quaternion<f32>) {
+        self.write_quantized(w, self.quantize(rot));
+    }
+
+    pub fn read(&self, r: &mut BitReader) -> Option<Quaternion<f32>> {
+        self.read_quantized(r).map(|q| self.dequantize(q))
+    }
+
+    fn write_quantized(&self, w: &mut BitWriter, q: [u32; 4]) {
+        w.write_bits(q[0], 2);
+        for v in q[1..].iter() {
+            w.write_bits(*v, self.component.bits);
+        }
+    }
+
+    fn read_quantized(&self, r: &mut BitReader) -> Option<[u32; 4]> {
+        let bits = self.component.bits;
+        Some([
+            r.read_bits(2)?,
+            r.read_bits(bits)?,
+            r.read_bits(bits)?,
+            r.read_bits(bits)?,
+        ])
+    }
+}
+
+/// Encoding of `NetTransform`, e.g. for replication or recording
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TransformQuantization {
+    pub position: Quantizer,
+    pub rotation: RotationQuantizer,
+}
+
+impl Default for TransformQuantization {
+    /// Positions within +-1024 units at ~1mm, rotations at ~0.01 degree
+    fn default() -> TransformQuantization {
+        TransformQuantization {
+            position: Quantizer::new(-1024.0, 1024.0, 21),
+            rotation: RotationQuantizer::new(15),
+        }
+    }
+}
+
+impl TransformQuantization {
+    pub fn write(&self, w: &mut BitWriter, t: &NetTransform) {
+        self.position.write_vec3(w, t.position);
+        self.rotation.write(w, t.rotation);
+    }
+
+    pub fn read(&self, r: &mut BitReader) -> Option<NetTransform> {
+        Some(NetTransform::new(
+            self.position.read_vec3(r)?,
+            self.rotation.read(r)?,
+        ))
+    }
+
+    /// Write only the components which differ from `baseline` once quantized,
+    /// one bit per position axis and one for the rotation
+    pub fn write_delta(&self, w: &mut BitWriter, t: &NetTransform, baseline: &NetTransform) {
+        let axes = [
+            (t.position.x, baseline.position.x),
+            (t.position.y, baseline.position.y),
+            (t.position.z, baseline.position.z),
+        ];
+
+        for &(v, base) in axes.iter() {
+            let q = self.position.quantize(v);
+            let changed = q != self.position.quantize(base);
+
+            w.write_bool(changed);
+            if changed {
+                w.write_bits(q, self.position.bits);
+            }
+        }
+
+        let q = self.rotation.quantize(t.rotation);
+        let changed = q != self.rotation.quantize(baseline.rotation);
+
+        w.write_bool(changed);
+        if changed {
+            self.rotation.write_quantized(w, q);
+        }
+    }
+
+    pub fn read_delta(&self, r: &mut BitReader, baseline: &NetTransform) -> Option<NetTransform> {
+        let mut axes = [baseline.position.x, baseline.position.y, baseline.position.z];
+
+        for v in axes.iter_mut() {
+            if r.read_bool()? {
+                *v = self.position.read(r)?;
+            } else {
+                // the receiver sees the quantized baseline, like the sender
+                *v = self.position.dequantize(self.position.quantize(*v));
+            }
+        }
+
+        let rotation = if r.read_bool()? {
+            self.rotation.read(r)?
+        } else {
+            self.rotation
+                .dequantize(self.rotation.quantize(baseline.rotation))
+        };
+
+        Some(NetTransform::new(
+            Vector3::new(axes[0], axes[1], axes[2]),
+            rotation,
+        ))
+    }
+}
@@ -0,0 +1,82 @@
+use super::{Channel, NetId, PeerId};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{self, Value};
+
+/// Who is allowed to send a message
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RpcAuthority {
+    /// Sent by server only, e.g. state updates
+    Server,
+    /// Sent by clients to the server only, e.g. player inputs
+    Client,
+    /// Sent by anyone
+    Any,
+}
+
+/// A typed remote procedure call message
+///
+/// ```ignore
+/// #[derive(Serialize, Deserialize)]
+/// struct Fire { dir: [f32; 3] }
+///
+/// impl RpcMessage for Fire {
+///     const NAME: &'static str = "fire";
+///     const AUTHORITY: RpcAuthority = RpcAuthority::Client;
+/// }
+/// ```
+pub trait RpcMessage: Serialize + DeserializeOwned + 'static {
+    const NAME: &'static str;
+    const CHANNEL: Channel = Channel::ReliableOrdered;
+    const AUTHORITY: RpcAuthority = RpcAuthority::Any;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct RpcInfo {
+    pub authority: RpcAuthority,
+}
+
+impl RpcInfo {
+    pub fn of<T: RpcMessage>() -> RpcInfo {
+        RpcInfo {
+            authority: T::AUTHORITY,
+        }
+    }
+}
+
+/// Wire format of a call
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub(crate) struct RpcEnvelope {
+    #[serde(rename = "m")]
+    pub name: String,
+    #[serde(rename = "t", default)]
+    pub target: Option<NetId>,
+    #[serde(rename = "p")]
+    pub payload: Value,
+}
+
+impl RpcEnvelope {
+    pub fn encode<T: RpcMessage>(target: Option<NetId>, msg: &T) -> Option<Vec<u8>> {
+        let envelope = RpcEnvelope {
+            name: T::NAME.to_owned(),
+            target,
+            payload: serde_json::to_value(msg).ok()?,
+        };
+
+        serde_json::to_vec(&envelope).ok()
+    }
+
+    pub fn decode(data: &[u8]) -> Option<RpcEnvelope> {
+        serde_json::from_slice(data).ok()
+    }
+}
+
+/// A received call
+#[derive(Debug, Clone)]
+pub struct RpcCall<T> {
+    pub sender: PeerId,
+    /// The network object this call is sent to, if any
+    pub target: Option<NetId>,
+    pub msg: T,
+}
@@ -0,0 +1,46 @@
+use super::PeerId;
+
+/// Delivery guarantee of a message
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum Channel {
+    /// Messages arrive once and in the sent order
+    ReliableOrdered,
+    /// Messages could be lost or reordered, but never wait for each other
+    Unreliable,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransportEvent {
+    Connected(PeerId),
+    Disconnected(PeerId),
+    Message(PeerId, Channel, Vec<u8>),
+}
+
+/// A connection to other peers.
+///
+/// Transports which cannot provide unreliable delivery should send
+/// `Channel::Unreliable` messages reliably.
+pub trait Transport {
+    /// The id of this peer
+    fn local_id(&self) -> PeerId;
+
+    fn peers(&self) -> Vec<PeerId>;
+
+    fn send(&mut self, peer: PeerId, channel: Channel, data: &[u8]);
+
+    fn broadcast(&mut self, channel: Channel, data: &[u8]) {
+        for peer in self.peers().into_iter() {
+            self.send(peer, channel, data);
+        }
+    }
+
+    /// Events received since last poll
+    fn poll(&mut self) -> Vec<TransportEvent>;
+
+    fn disconnect(&mut self, peer: PeerId);
+
+    /// Whether `Channel::Unreliable` is really unreliable
+    fn supports_unreliable(&self) -> bool {
+        false
+    }
+}
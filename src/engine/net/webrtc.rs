@@ -0,0 +1,684 @@
+//! Peer to peer transport with unreliable delivery, for fast paced games.
+//!
+//! In web, it uses WebRTC data channels, one ordered and reliable, one unordered without
+//! retransmission. Natively it is not WebRTC but plain UDP with a small reliable layer: no
+//! encryption, no NAT traversal (LAN only), and the peers are only identified by the
+//! addresses they signaled. Both need a signaling channel to exchange connection offers,
+//! usually the lobby :
+//!
+//! ```ignore
+//! // the client connects to the host, signals are relayed by the lobby
+//! rtc.connect(host);
+//! for (to, data) in rtc.take_signals() { lobby.signal(to, data); }
+//! for evt in lobby.poll_events() {
+//!     if let LobbyEvent::Signal { from, data } = evt { rtc.handle_signal(from, data); }
+//! }
+//! ```
+
+use super::{Channel, PeerId, Transport, TransportEvent};
+use serde_json::Value;
+
+#[cfg(target_arch = "wasm32")]
+mod imp {
+    use super::*;
+    use serde_json;
+    use std::cell::RefCell;
+    use std::collections::{HashSet, VecDeque};
+    use std::rc::Rc;
+    use stdweb::web::ArrayBuffer;
+    use stdweb::{UnsafeTypedArray, Value as JsValue};
+
+    const DEFAULT_STUN_SERVER: &'static str = "stun:stun.l.google.com:19302";
+
+    #[derive(Default)]
+    struct Shared {
+        events: VecDeque<TransportEvent>,
+        signals: Vec<(PeerId, Value)>,
+        open: HashSet<PeerId>,
+    }
+
+    pub struct WebRtcTransport {
+        local: PeerId,
+        state: JsValue,
+        shared: Rc<RefCell<Shared>>,
+    }
+
+    impl WebRtcTransport {
+        pub fn new(local: PeerId) -> WebRtcTransport {
+            WebRtcTransport::with_stun_server(local, DEFAULT_STUN_SERVER)
+        }
+
+        pub fn with_stun_server(local: PeerId, stun: &str) -> WebRtcTransport {
+            let shared = Rc::new(RefCell::new(Shared::default()));
+
+            let on_signal = {
+                let shared = shared.clone();
+                move |peer: u32, data: String| match serde_json::from_str(&data) {
+                    Ok(value) => shared.borrow_mut().signals.push((peer, value)),
+                    Err(e) => println!("Invalid webrtc signal, reason: {:?}", e),
+                }
+            };
+
+            let on_open = {
+                let shared = shared.clone();
+                move |peer: u32| {
+                    let mut shared = shared.borrow_mut();
+                    shared.open.insert(peer);
+                    shared.events.push_back(TransportEvent::Connected(peer));
+                }
+            };
+
+            let on_close = {
+                let shared = shared.clone();
+                move |peer: u32| {
+                    let mut shared = shared.borrow_mut();
+                    if shared.open.remove(&peer) {
+                        shared.events.push_back(TransportEvent::Disconnected(peer));
+                    }
+                }
+            };
+
+            let on_message = {
+                let shared = shared.clone();
+                move |peer: u32, channel: u32, data: ArrayBuffer| {
+                    let channel = if channel == 0 {
+                        Channel::ReliableOrdered
+                    } else {
+                        Channel::Unreliable
+                    };
+
+                    shared
+                        .borrow_mut()
+                        .events
+                        .push_back(TransportEvent::Message(peer, channel, data.into()));
+                }
+            };
+
+            let state = js! {
+                var state = {
+                    pcs: {},
+                    config: { iceServers: [{ urls: @{stun} }] },
+                    on_signal: @{on_signal},
+                    on_open: @{on_open},
+                    on_close: @{on_close},
+                    on_message: @{on_message}
+                };
+
+                state.signal = function(peer, msg) {
+                    state.on_signal(peer, JSON.stringify(msg));
+                };
+
+                // Both sides create the same negotiated channels, 0 is reliable and 1 unreliable
+                state.setup = function(peer) {
+                    var pc = new RTCPeerConnection(state.config);
+                    var entry = { pc: pc, channels: [], open: 0, closed: false, candidates: [] };
+
+                    pc.onicecandidate = function(e) {
+                        if (e.candidate) {
+                            state.signal(peer, { candidate: e.candidate });
+                        }
+                    };
+
+                    var close = function() {
+                        if (!entry.closed) {
+                            entry.closed = true;
+                            state.on_close(peer);
+                        }
+                    };
+
+                    pc.oniceconnectionstatechange = function() {
+                        if (pc.iceConnectionState === "failed" || pc.iceConnectionState === "closed") {
+                            close();
+                        }
+                    };
+
+                    var options = [
+                        { negotiated: true, id: 0 },
+                        { negotiated: true, id: 1, ordered: false, maxRetransmits: 0 }
+                    ];
+
+                    options.forEach(function(opt, i) {
+                        var dc = pc.createDataChannel(i == 0 ? "reliable" : "unreliable", opt);
+                        dc.binaryType = "arraybuffer";
+                        dc.onopen = function() {
+                            entry.open += 1;
+                            if (entry.open == 2) {
+                                state.on_open(peer);
+                            }
+                        };
+                        dc.onclose = close;
+                        dc.onmessage = function(e) {
+                            var data = e.data;
+                            if (typeof data === "string") {
+                                data = new TextEncoder().encode(data).buffer;
+                            }
+                            state.on_message(peer, i, data);
+                        };
+                        entry.channels.push(dc);
+                    });
+
+                    state.pcs[peer] = entry;
+                    return entry;
+                };
+
+                state.describe = function(peer, pc) {
+                    state.signal(peer, { sdp: pc.localDescription });
+                };
+
+                return state;
+            };
+
+            WebRtcTransport {
+                local,
+                state,
+                shared,
+            }
+        }
+
+        /// Start a connection, the other peer should not connect back
+        pub fn connect(&mut self, peer: PeerId) {
+            js! { @(no_return)
+                var state = @{&self.state};
+                var peer = @{peer};
+                var entry = state.pcs[peer] || state.setup(peer);
+                var pc = entry.pc;
+
+                pc.createOffer().then(function(offer) {
+                    return pc.setLocalDescription(offer);
+                }).then(function() {
+                    state.describe(peer, pc);
+                }).catch(function(e) {
+                    console.log("Fail to create webrtc offer: " + e);
+                });
+            }
+        }
+
+        /// Signals to send to other peers by the signaling channel
+        pub fn take_signals(&mut self) -> Vec<(PeerId, Value)> {
+            self.shared.borrow_mut().signals.drain(..).collect()
+        }
+
+        /// Handle a signal received from another peer
+        pub fn handle_signal(&mut self, from: PeerId, data: Value) {
+            let data = data.to_string();
+
+            js! { @(no_return)
+                var state = @{&self.state};
+                var peer = @{from};
+                var msg = JSON.parse(@{data});
+                var entry = state.pcs[peer] || state.setup(peer);
+                var pc = entry.pc;
+
+                var flush = function() {
+                    entry.candidates.forEach(function(c) { pc.addIceCandidate(c); });
+                    entry.candidates = [];
+                };
+
+                if (msg.sdp) {
+                    pc.setRemoteDescription(msg.sdp).then(function() {
+                        flush();
+                        if (msg.sdp.type === "offer") {
+                            return pc.createAnswer().then(function(answer) {
+                                return pc.setLocalDescription(answer);
+                            }).then(function() {
+                                state.describe(peer, pc);
+                            });
+                        }
+                    }).catch(function(e) {
+                        console.log("Fail to handle webrtc description: " + e);
+                    });
+                } else if (msg.candidate) {
+                    if (pc.remoteDescription) {
+                        pc.addIceCandidate(msg.candidate);
+                    } else {
+                        entry.candidates.push(msg.candidate);
+                    }
+                }
+            }
+        }
+    }
+
+    impl Transport for WebRtcTransport {
+        fn local_id(&self) -> PeerId {
+            self.local
+        }
+
+        fn peers(&self) -> Vec<PeerId> {
+            self.shared.borrow().open.iter().cloned().collect()
+        }
+
+        fn send(&mut self, peer: PeerId, channel: Channel, data: &[u8]) {
+            let index = match channel {
+                Channel::ReliableOrdered => 0,
+                Channel::Unreliable => 1,
+            };
+            let buffer = unsafe { UnsafeTypedArray::new(data) };
+
+            js! { @(no_return)
+                var entry = @{&self.state}.pcs[@{peer}];
+                if (entry && !entry.closed) {
+                    var dc = entry.channels[@{index}];
+                    if (dc.readyState === "open") {
+                        dc.send(@{buffer});
+                    }
+                }
+            }
+        }
+
+        fn poll(&mut self) -> Vec<TransportEvent> {
+            self.shared.borrow_mut().events.drain(..).collect()
+        }
+
+        fn disconnect(&mut self, peer: PeerId) {
+            js! { @(no_return)
+                var state = @{&self.state};
+                var entry = state.pcs[@{peer}];
+                if (entry) {
+                    delete state.pcs[@{peer}];
+                    entry.pc.close();
+                }
+            }
+
+            let mut shared = self.shared.borrow_mut();
+            if shared.open.remove(&peer) {
+                shared.events.push_back(TransportEvent::Disconnected(peer));
+            }
+        }
+
+        fn supports_unreliable(&self) -> bool {
+            true
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod imp {
+    use super::*;
+    use serde_json;
+    use std::collections::{BTreeMap, HashMap, VecDeque};
+    use std::io;
+    use std::net::{SocketAddr, UdpSocket};
+    use std::time::{Duration, Instant};
+
+    const KIND_UNRELIABLE: u8 = 0;
+    const KIND_RELIABLE: u8 = 1;
+    const KIND_ACK: u8 = 2;
+    const KIND_HELLO: u8 = 3;
+    const KIND_BYE: u8 = 4;
+
+    const RESEND_INTERVAL_MS: u64 = 200;
+    const HELLO_INTERVAL_MS: u64 = 500;
+    const TIMEOUT_MS: u64 = 10000;
+    const MAX_DATAGRAM: usize = 65507;
+    const HEADER_SIZE: usize = 5;
+    /// Reliable messages received ahead of the next one to deliver, the later ones are
+    /// dropped without an ack and sent again
+    const REORDER_WINDOW: u32 = 256;
+
+    #[derive(Deserialize, Serialize)]
+    struct UdpSignal {
+        addr: String,
+        #[serde(default)]
+        offer: bool,
+    }
+
+    struct UdpPeer {
+        addr: SocketAddr,
+        connected: bool,
+        last_heard: Instant,
+        last_hello: Option<Instant>,
+
+        next_seq: u32,
+        unacked: BTreeMap<u32, (Vec<u8>, Instant)>,
+        expected_seq: u32,
+        received: BTreeMap<u32, Vec<u8>>,
+    }
+
+    impl UdpPeer {
+        fn new(addr: SocketAddr) -> UdpPeer {
+            UdpPeer {
+                addr,
+                connected: false,
+                last_heard: Instant::now(),
+                last_hello: None,
+                next_seq: 0,
+                unacked: BTreeMap::new(),
+                expected_seq: 0,
+                received: BTreeMap::new(),
+            }
+        }
+    }
+
+    /// Native counterpart of the WebRTC transport, using plain UDP
+    ///
+    /// The packets are neither encrypted nor authenticated. A packet is attributed to the
+    /// peer which signaled its source address, and dropped if no peer did.
+    pub struct WebRtcTransport {
+        local: PeerId,
+        socket: Option<UdpSocket>,
+        peers: HashMap<PeerId, UdpPeer>,
+        events: VecDeque<TransportEvent>,
+        signals: Vec<(PeerId, Value)>,
+    }
+
+    impl WebRtcTransport {
+        pub fn new(local: PeerId) -> WebRtcTransport {
+            WebRtcTransport::bind(local, "0.0.0.0:0")
+        }
+
+        /// STUN is not used in native
+        pub fn with_stun_server(local: PeerId, _stun: &str) -> WebRtcTransport {
+            WebRtcTransport::new(local)
+        }
+
+        /// Listen on a specific address, e.g. "0.0.0.0:7777"
+        pub fn bind(local: PeerId, addr: &str) -> WebRtcTransport {
+            let socket = match UdpSocket::bind(addr) {
+                Ok(socket) => match socket.set_nonblocking(true) {
+                    Ok(_) => Some(socket),
+                    Err(e) => {
+                        println!("Fail to setup udp socket, reason: {:?}", e);
+                        None
+                    }
+                },
+                Err(e) => {
+                    println!("Fail to bind udp socket {}, reason: {:?}", addr, e);
+                    None
+                }
+            };
+
+            WebRtcTransport {
+                local,
+                socket,
+                peers: HashMap::new(),
+                events: VecDeque::new(),
+                signals: Vec::new(),
+            }
+        }
+
+        /// Start a connection, the other peer should not connect back
+        pub fn connect(&mut self, peer: PeerId) {
+            self.signal_address(peer, true);
+        }
+
+        pub fn take_signals(&mut self) -> Vec<(PeerId, Value)> {
+            self.signals.drain(..).collect()
+        }
+
+        pub fn handle_signal(&mut self, from: PeerId, data: Value) {
+            let signal: Option<UdpSignal> = serde_json::from_value(data).ok();
+            let addr: Option<SocketAddr> = signal.as_ref().and_then(|s| s.addr.parse().ok());
+
+            let (signal, addr) = match (signal, addr) {
+                (Some(signal), Some(addr)) => (signal, addr),
+                _ => {
+                    println!("Invalid udp signal from peer {}", from);
+                    return;
+                }
+            };
+
+            // An address identifies a single peer
+            if let Some(other) = self.peer_at(addr) {
+                if other != from {
+                    println!("Udp address {} signaled by peers {} and {}", addr, other, from);
+                    return;
+                }
+            }
+
+            self.peers
+                .entry(from)
+                .or_insert_with(|| UdpPeer::new(addr))
+                .addr = addr;
+
+            if signal.offer {
+                self.signal_address(from, false);
+            }
+        }
+
+        fn signal_address(&mut self, peer: PeerId, offer: bool) {
+            let signal = match self.local_address() {
+                Some(addr) => UdpSignal {
+                    addr: addr.to_string(),
+                    offer,
+                },
+                None => {
+                    println!("Fail to find the local udp address");
+                    return;
+                }
+            };
+
+            match serde_json::to_value(&signal) {
+                Ok(value) => self.signals.push((peer, value)),
+                Err(e) => println!("Fail to encode udp signal, reason: {:?}", e),
+            }
+        }
+
+        /// The address other peers in LAN should send to
+        fn local_address(&self) -> Option<SocketAddr> {
+            let port = self.socket.as_ref()?.local_addr().ok()?.port();
+
+            // Find the outgoing interface, nothing is sent by connecting an udp socket
+            let probe = UdpSocket::bind("0.0.0.0:0").ok()?;
+            let ip = match probe.connect("8.8.8.8:80") {
+                Ok(_) => probe.local_addr().ok()?.ip(),
+                Err(_) => "127.0.0.1".parse().unwrap(),
+            };
+
+            Some(SocketAddr::new(ip, port))
+        }
+
+        fn peer_at(&self, addr: SocketAddr) -> Option<PeerId> {
+            self.peers
+                .iter()
+                .find(|&(_, p)| p.addr == addr)
+                .map(|(id, _)| *id)
+        }
+
+        fn send_raw(&self, addr: SocketAddr, kind: u8, seq: u32, data: &[u8]) {
+            if let Some(ref socket) = self.socket {
+                let mut packet = Vec::with_capacity(data.len() + HEADER_SIZE);
+                packet.push(kind);
+                packet.extend_from_slice(&u32_bytes(seq));
+                packet.extend_from_slice(data);
+
+                if let Err(e) = socket.send_to(&packet, addr) {
+                    if e.kind() != io::ErrorKind::WouldBlock {
+                        println!("Fail to send udp packet, reason: {:?}", e);
+                    }
+                }
+            }
+        }
+
+        fn receive_packets(&mut self) {
+            let mut buf = vec![0u8; MAX_DATAGRAM];
+
+            loop {
+                let (n, addr) = match self.socket {
+                    Some(ref socket) => match socket.recv_from(&mut buf) {
+                        Ok(r) => r,
+                        Err(_) => break,
+                    },
+                    None => break,
+                };
+
+                if n < HEADER_SIZE {
+                    continue;
+                }
+
+                let kind = buf[0];
+                let seq = u32_from(&buf[1..5]);
+                let payload = buf[HEADER_SIZE..n].to_vec();
+
+                self.handle_packet(addr, kind, seq, payload);
+            }
+        }
+
+        fn handle_packet(&mut self, addr: SocketAddr, kind: u8, seq: u32, payload: Vec<u8>) {
+            // Only accept the addresses introduced by signaling
+            let sender = match self.peer_at(addr) {
+                Some(sender) => sender,
+                None => return,
+            };
+
+            let mut acks = Vec::new();
+
+            {
+                let peer = self.peers.get_mut(&sender).unwrap();
+                peer.last_heard = Instant::now();
+
+                if !peer.connected && kind != KIND_BYE {
+                    peer.connected = true;
+                    self.events.push_back(TransportEvent::Connected(sender));
+                }
+
+                match kind {
+                    KIND_UNRELIABLE => self.events.push_back(TransportEvent::Message(
+                        sender,
+                        Channel::Unreliable,
+                        payload,
+                    )),
+                    KIND_RELIABLE => {
+                        let ahead = seq.wrapping_sub(peer.expected_seq);
+                        if ahead < REORDER_WINDOW {
+                            acks.push(seq);
+                            peer.received.insert(seq, payload);
+                        } else if ahead > u32::max_value() / 2 {
+                            // Delivered already, the ack was lost
+                            acks.push(seq);
+                        }
+
+                        while let Some(data) = peer.received.remove(&peer.expected_seq) {
+                            self.events.push_back(TransportEvent::Message(
+                                sender,
+                                Channel::ReliableOrdered,
+                                data,
+                            ));
+                            peer.expected_seq = peer.expected_seq.wrapping_add(1);
+                        }
+                    }
+                    KIND_ACK => {
+                        peer.unacked.remove(&seq);
+                    }
+                    KIND_BYE => {
+                        if peer.connected {
+                            peer.connected = false;
+                            self.events.push_back(TransportEvent::Disconnected(sender));
+                        }
+                    }
+                    _ => (),
+                }
+            }
+
+            for seq in acks.into_iter() {
+                self.send_raw(addr, KIND_ACK, seq, &[]);
+            }
+
+            if kind == KIND_BYE {
+                self.peers.remove(&sender);
+            }
+        }
+
+        fn update_peers(&mut self) {
+            let now = Instant::now();
+            let mut outgoing = Vec::new();
+            let mut lost = Vec::new();
+
+            for (id, peer) in self.peers.iter_mut() {
+                if now.duration_since(peer.last_heard) > Duration::from_millis(TIMEOUT_MS) {
+                    lost.push((*id, peer.connected));
+                    continue;
+                }
+
+                let hello_due = match peer.last_hello {
+                    Some(t) => now.duration_since(t) > Duration::from_millis(HELLO_INTERVAL_MS),
+                    None => true,
+                };
+                if hello_due {
+                    peer.last_hello = Some(now);
+                    outgoing.push((peer.addr, KIND_HELLO, 0, Vec::new()));
+                }
+
+                for (seq, &mut (ref data, ref mut sent)) in peer.unacked.iter_mut() {
+                    if now.duration_since(*sent) > Duration::from_millis(RESEND_INTERVAL_MS) {
+                        *sent = now;
+                        outgoing.push((peer.addr, KIND_RELIABLE, *seq, data.clone()));
+                    }
+                }
+            }
+
+            for (addr, kind, seq, data) in outgoing.into_iter() {
+                self.send_raw(addr, kind, seq, &data);
+            }
+
+            for (id, connected) in lost.into_iter() {
+                self.peers.remove(&id);
+                if connected {
+                    self.events.push_back(TransportEvent::Disconnected(id));
+                }
+            }
+        }
+    }
+
+    impl Transport for WebRtcTransport {
+        fn local_id(&self) -> PeerId {
+            self.local
+        }
+
+        fn peers(&self) -> Vec<PeerId> {
+            self.peers
+                .iter()
+                .filter(|&(_, p)| p.connected)
+                .map(|(id, _)| *id)
+                .collect()
+        }
+
+        fn send(&mut self, peer: PeerId, channel: Channel, data: &[u8]) {
+            let (addr, seq) = match self.peers.get_mut(&peer) {
+                Some(p) => match channel {
+                    Channel::Unreliable => (p.addr, None),
+                    Channel::ReliableOrdered => {
+                        let seq = p.next_seq;
+                        p.next_seq = p.next_seq.wrapping_add(1);
+                        p.unacked.insert(seq, (data.to_vec(), Instant::now()));
+                        (p.addr, Some(seq))
+                    }
+                },
+                None => return,
+            };
+
+            match seq {
+                Some(seq) => self.send_raw(addr, KIND_RELIABLE, seq, data),
+                None => self.send_raw(addr, KIND_UNRELIABLE, 0, data),
+            }
+        }
+
+        fn poll(&mut self) -> Vec<TransportEvent> {
+            self.receive_packets();
+            self.update_peers();
+            self.events.drain(..).collect()
+        }
+
+        fn disconnect(&mut self, peer: PeerId) {
+            if let Some(p) = self.peers.remove(&peer) {
+                self.send_raw(p.addr, KIND_BYE, 0, &[]);
+                if p.connected {
+                    self.events.push_back(TransportEvent::Disconnected(peer));
+                }
+            }
+        }
+
+        fn supports_unreliable(&self) -> bool {
+            true
+        }
+    }
+
+    fn u32_bytes(v: u32) -> [u8; 4] {
+        [v as u8, (v >> 8) as u8, (v >> 16) as u8, (v >> 24) as u8]
+    }
+
+    fn u32_from(b: &[u8]) -> u32 {
+        (b[0] as u32) | ((b[1] as u32) << 8) | ((b[2] as u32) << 16) | ((b[3] as u32) << 24)
+    }
+}
+
+pub use self::imp::WebRtcTransport;
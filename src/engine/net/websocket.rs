@@ -0,0 +1,338 @@
+//! WebSocket client transport, the only peer is the server (`WEBSOCKET_SERVER_PEER`)
+
+use super::{Channel, PeerId, Transport, TransportEvent};
+
+pub const WEBSOCKET_SERVER_PEER: PeerId = 0;
+const WEBSOCKET_LOCAL_PEER: PeerId = 1;
+
+#[cfg(target_arch = "wasm32")]
+mod imp {
+    use super::*;
+    use std::cell::RefCell;
+    use std::collections::VecDeque;
+    use std::rc::Rc;
+
+    use stdweb::traits::*;
+    use stdweb::web::event::{SocketCloseEvent, SocketErrorEvent, SocketMessageData,
+                             SocketMessageEvent, SocketOpenEvent};
+    use stdweb::web::{SocketBinaryType, WebSocket};
+
+    #[derive(Default)]
+    struct Shared {
+        open: bool,
+        events: VecDeque<TransportEvent>,
+    }
+
+    pub struct WebSocketTransport {
+        ws: Option<WebSocket>,
+        shared: Rc<RefCell<Shared>>,
+        outgoing: Vec<Vec<u8>>,
+        text_frames: bool,
+    }
+
+    impl WebSocketTransport {
+        pub fn connect(url: &str) -> WebSocketTransport {
+            let shared = Rc::new(RefCell::new(Shared::default()));
+
+            let ws = match WebSocket::new(url) {
+                Ok(ws) => ws,
+                Err(e) => {
+                    println!("Fail to connect {}, reason: {:?}", url, e);
+                    shared
+                        .borrow_mut()
+                        .events
+                        .push_back(TransportEvent::Disconnected(WEBSOCKET_SERVER_PEER));
+
+                    return WebSocketTransport {
+                        ws: None,
+                        shared,
+                        outgoing: Vec::new(),
+                        text_frames: false,
+                    };
+                }
+            };
+
+            ws.set_binary_type(SocketBinaryType::ArrayBuffer);
+
+            ws.add_event_listener({
+                let shared = shared.clone();
+                move |_: SocketOpenEvent| {
+                    let mut shared = shared.borrow_mut();
+                    shared.open = true;
+                    shared
+                        .events
+                        .push_back(TransportEvent::Connected(WEBSOCKET_SERVER_PEER));
+                }
+            });
+
+            ws.add_event_listener({
+                let shared = shared.clone();
+                move |e: SocketMessageEvent| {
+                    let data: Vec<u8> = match e.data() {
+                        SocketMessageData::Text(s) => s.into_bytes(),
+                        SocketMessageData::ArrayBuffer(buf) => buf.into(),
+                        SocketMessageData::Blob(_) => return,
+                    };
+
+                    shared.borrow_mut().events.push_back(TransportEvent::Message(
+                        WEBSOCKET_SERVER_PEER,
+                        Channel::ReliableOrdered,
+                        data,
+                    ));
+                }
+            });
+
+            ws.add_event_listener(move |_: SocketErrorEvent| {
+                println!("WebSocket error");
+            });
+
+            ws.add_event_listener({
+                let shared = shared.clone();
+                move |_: SocketCloseEvent| {
+                    let mut shared = shared.borrow_mut();
+                    shared.open = false;
+                    shared
+                        .events
+                        .push_back(TransportEvent::Disconnected(WEBSOCKET_SERVER_PEER));
+                }
+            });
+
+            WebSocketTransport {
+                ws: Some(ws),
+                shared,
+                outgoing: Vec::new(),
+                text_frames: false,
+            }
+        }
+
+        pub fn with_text_frames(mut self, text: bool) -> WebSocketTransport {
+            self.text_frames = text;
+            self
+        }
+
+        pub fn is_open(&self) -> bool {
+            self.shared.borrow().open
+        }
+
+        fn flush(&mut self) {
+            if !self.is_open() {
+                return;
+            }
+
+            if let Some(ref ws) = self.ws {
+                for data in self.outgoing.drain(..) {
+                    let r = if self.text_frames {
+                        ws.send_text(&String::from_utf8_lossy(&data))
+                    } else {
+                        ws.send_bytes(&data)
+                    };
+
+                    if let Err(e) = r {
+                        println!("Fail to send websocket message, reason: {:?}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    impl Transport for WebSocketTransport {
+        fn local_id(&self) -> PeerId {
+            WEBSOCKET_LOCAL_PEER
+        }
+
+        fn peers(&self) -> Vec<PeerId> {
+            if self.is_open() {
+                vec![WEBSOCKET_SERVER_PEER]
+            } else {
+                Vec::new()
+            }
+        }
+
+        fn send(&mut self, peer: PeerId, _channel: Channel, data: &[u8]) {
+            if peer == WEBSOCKET_SERVER_PEER {
+                self.outgoing.push(data.to_vec());
+                self.flush();
+            }
+        }
+
+        fn poll(&mut self) -> Vec<TransportEvent> {
+            self.flush();
+            self.shared.borrow_mut().events.drain(..).collect()
+        }
+
+        fn disconnect(&mut self, _peer: PeerId) {
+            if let Some(ws) = self.ws.take() {
+                ws.close();
+            }
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod imp {
+    use super::*;
+    use std::io;
+    use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
+    use std::thread;
+    use std::time::Duration;
+
+    use tungstenite::{self, Message};
+    use url::Url;
+
+    enum Outgoing {
+        Data(Vec<u8>, bool),
+        Close,
+    }
+
+    /// Native websocket runs in its own thread, messages are passed by channels
+    pub struct WebSocketTransport {
+        incoming: Receiver<TransportEvent>,
+        outgoing: Sender<Outgoing>,
+        open: bool,
+        text_frames: bool,
+    }
+
+    impl WebSocketTransport {
+        pub fn connect(url: &str) -> WebSocketTransport {
+            let (in_tx, in_rx) = channel();
+            let (out_tx, out_rx) = channel();
+            let url = url.to_owned();
+
+            thread::spawn(move || run(&url, in_tx, out_rx));
+
+            WebSocketTransport {
+                incoming: in_rx,
+                outgoing: out_tx,
+                open: false,
+                text_frames: false,
+            }
+        }
+
+        pub fn with_text_frames(mut self, text: bool) -> WebSocketTransport {
+            self.text_frames = text;
+            self
+        }
+
+        pub fn is_open(&self) -> bool {
+            self.open
+        }
+    }
+
+    fn run(url: &str, events: Sender<TransportEvent>, outgoing: Receiver<Outgoing>) {
+        let disconnected = || TransportEvent::Disconnected(WEBSOCKET_SERVER_PEER);
+
+        let url = match Url::parse(url) {
+            Ok(url) => url,
+            Err(e) => {
+                println!("Invalid websocket url {}, reason: {:?}", url, e);
+                let _ = events.send(disconnected());
+                return;
+            }
+        };
+
+        let mut socket = match tungstenite::connect(url) {
+            Ok((socket, _)) => socket,
+            Err(e) => {
+                println!("Fail to connect websocket, reason: {:?}", e);
+                let _ = events.send(disconnected());
+                return;
+            }
+        };
+
+        // Poll both directions in this thread
+        let _ = socket
+            .get_mut()
+            .set_read_timeout(Some(Duration::from_millis(5)));
+
+        let _ = events.send(TransportEvent::Connected(WEBSOCKET_SERVER_PEER));
+
+        loop {
+            match socket.read_message() {
+                Ok(Message::Binary(data)) => {
+                    let _ = events.send(TransportEvent::Message(
+                        WEBSOCKET_SERVER_PEER,
+                        Channel::ReliableOrdered,
+                        data,
+                    ));
+                }
+                Ok(Message::Text(s)) => {
+                    let _ = events.send(TransportEvent::Message(
+                        WEBSOCKET_SERVER_PEER,
+                        Channel::ReliableOrdered,
+                        s.into_bytes(),
+                    ));
+                }
+                Ok(_) => (),
+                Err(tungstenite::Error::Io(ref e))
+                    if e.kind() == io::ErrorKind::WouldBlock
+                        || e.kind() == io::ErrorKind::TimedOut => {}
+                Err(_) => break,
+            }
+
+            loop {
+                let msg = match outgoing.try_recv() {
+                    Ok(Outgoing::Data(data, true)) => {
+                        Message::Text(String::from_utf8_lossy(&data).into_owned())
+                    }
+                    Ok(Outgoing::Data(data, false)) => Message::Binary(data),
+                    Ok(Outgoing::Close) | Err(TryRecvError::Disconnected) => {
+                        let _ = socket.close(None);
+                        let _ = socket.write_pending();
+                        let _ = events.send(disconnected());
+                        return;
+                    }
+                    Err(TryRecvError::Empty) => break,
+                };
+
+                if socket.write_message(msg).is_err() {
+                    break;
+                }
+            }
+
+            let _ = socket.write_pending();
+        }
+
+        let _ = events.send(disconnected());
+    }
+
+    impl Transport for WebSocketTransport {
+        fn local_id(&self) -> PeerId {
+            WEBSOCKET_LOCAL_PEER
+        }
+
+        fn peers(&self) -> Vec<PeerId> {
+            if self.open {
+                vec![WEBSOCKET_SERVER_PEER]
+            } else {
+                Vec::new()
+            }
+        }
+
+        fn send(&mut self, peer: PeerId, _channel: Channel, data: &[u8]) {
+            if peer == WEBSOCKET_SERVER_PEER {
+                let _ = self.outgoing
+                    .send(Outgoing::Data(data.to_vec(), self.text_frames));
+            }
+        }
+
+        fn poll(&mut self) -> Vec<TransportEvent> {
+            let events: Vec<_> = self.incoming.try_iter().collect();
+
+            for evt in events.iter() {
+                match *evt {
+                    TransportEvent::Connected(_) => self.open = true,
+                    TransportEvent::Disconnected(_) => self.open = false,
+                    _ => (),
+                }
+            }
+
+            events
+        }
+
+        fn disconnect(&mut self, _peer: PeerId) {
+            let _ = self.outgoing.send(Outgoing::Close);
+        }
+    }
+}
+
+pub use self::imp::WebSocketTransport;
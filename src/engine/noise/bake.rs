@@ -0,0 +1,165 @@
+#[cfg(feature = "physics")]
+use engine::physics::HeightField;
+use engine::{Asset, IdleQueue, Texture, TextureImage, TextureWrap};
+
+use image::{ImageBuffer, Rgba, RgbaImage};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Samples baked per idle step, in whole rows
+const SAMPLES_PER_STEP: usize = 4096;
+
+/// A grid of values, row major, e.g. baked noise
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heightmap {
+    pub width: usize,
+    pub height: usize,
+    pub values: Vec<f32>,
+}
+
+impl Heightmap {
+    pub fn new(width: usize, height: usize) -> Heightmap {
+        Heightmap {
+            width,
+            height,
+            values: vec![0.0; width * height],
+        }
+    }
+
+    /// Bake all the values at once
+    pub fn from_fn<F>(width: usize, height: usize, f: F) -> Heightmap
+    where
+        F: Fn(usize, usize) -> f32,
+    {
+        let mut hm = Heightmap::new(width, height);
+        for y in 0..height {
+            hm.fill_row(y, &f);
+        }
+        hm
+    }
+
+    /// Bake the values by rows in the idle time of the frames
+    pub fn bake_idle<F>(idle: &IdleQueue, width: usize, height: usize, f: F) -> HeightmapBake
+    where
+        F: Fn(usize, usize) -> f32 + 'static,
+    {
+        let bake = HeightmapBake {
+            state: Rc::new(RefCell::new(BakeState {
+                heightmap: Heightmap::new(width, height),
+                row: 0,
+            })),
+        };
+
+        let rows_per_step = (SAMPLES_PER_STEP / width.max(1)).max(1);
+        let state = bake.state.clone();
+        idle.push(move || {
+            let mut state = state.borrow_mut();
+            let end = (state.row + rows_per_step).min(height);
+            for y in state.row..end {
+                state.heightmap.fill_row(y, &f);
+            }
+            state.row = end;
+            end >= height
+        });
+
+        bake
+    }
+
+    fn fill_row<F>(&mut self, y: usize, f: &F)
+    where
+        F: Fn(usize, usize) -> f32,
+    {
+        for x in 0..self.width {
+            self.values[y * self.width + x] = f(x, y);
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> f32 {
+        self.values[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, value: f32) {
+        self.values[y * self.width + x] = value;
+    }
+
+    /// Smallest and largest value
+    pub fn range(&self) -> (f32, f32) {
+        self.values
+            .iter()
+            .fold((::std::f32::MAX, ::std::f32::MIN), |(lo, hi), v| {
+                (lo.min(*v), hi.max(*v))
+            })
+    }
+
+    /// Rescale the values to [0, 1]
+    pub fn normalize(&mut self) {
+        let (lo, hi) = self.range();
+        let scale = if hi > lo { 1.0 / (hi - lo) } else { 0.0 };
+        for v in self.values.iter_mut() {
+            *v = (*v - lo) * scale;
+        }
+    }
+
+    /// Gray levels of the values in [0, 1], clamped
+    pub fn to_image(&self) -> RgbaImage {
+        ImageBuffer::from_fn(self.width as u32, self.height as u32, |x, y| {
+            let v = self.get(x as usize, y as usize).max(0.0).min(1.0);
+            let l = (v * 255.0).round() as u8;
+            Rgba([l, l, l, 255])
+        })
+    }
+
+    /// The texture of `to_image`, repeating when the values are tileable
+    pub fn to_texture(&self, tileable: bool) -> Rc<Texture> {
+        let texture = Texture::new(TextureImage::Rgba(self.to_image()));
+        if tileable {
+            texture.wrap_u.set(TextureWrap::Repeat);
+            texture.wrap_v.set(TextureWrap::Repeat);
+        }
+        texture
+    }
+
+    /// Terrain of `cell_size` units between samples, the values scaled by `scale`, None for
+    /// less than 2x2 values
+    #[cfg(feature = "physics")]
+    pub fn to_height_field(&self, cell_size: f32, scale: f32) -> Option<HeightField> {
+        let heights = self.values.iter().map(|v| v * scale).collect();
+        HeightField::new(self.width, self.height, cell_size, heights)
+    }
+}
+
+struct BakeState {
+    heightmap: Heightmap,
+    /// Next row to bake
+    row: usize,
+}
+
+/// A heightmap baking in the idle queue
+#[derive(Clone)]
+pub struct HeightmapBake {
+    state: Rc<RefCell<BakeState>>,
+}
+
+impl HeightmapBake {
+    /// Ratio of the rows baked, in [0, 1]
+    pub fn progress(&self) -> f32 {
+        let state = self.state.borrow();
+        if state.heightmap.height == 0 {
+            return 1.0;
+        }
+        state.row as f32 / state.heightmap.height as f32
+    }
+
+    pub fn is_done(&self) -> bool {
+        let state = self.state.borrow();
+        state.row >= state.heightmap.height
+    }
+
+    /// The baked heightmap, `None` until done
+    pub fn heightmap(&self) -> Option<Heightmap> {
+        if !self.is_done() {
+            return None;
+        }
+        Some(self.state.borrow().heightmap.clone())
+    }
+}
@@ -0,0 +1,97 @@
+use super::{Noise, NoiseKind};
+
+/// Fractal Brownian motion: octaves of noise, each of a higher frequency and a lower
+/// amplitude, normalized to the range of the noise
+#[derive(Debug, Clone)]
+pub struct Fbm {
+    pub kind: NoiseKind,
+    pub octaves: u32,
+    /// Frequency of the first octave, in cycles per unit
+    pub frequency: f32,
+    /// Frequency multiplier between octaves
+    pub lacunarity: f32,
+    /// Amplitude multiplier between octaves
+    pub gain: f32,
+    noise: Noise,
+}
+
+impl Fbm {
+    pub fn new(seed: u32, kind: NoiseKind) -> Fbm {
+        Fbm {
+            kind,
+            octaves: 4,
+            frequency: 1.0,
+            lacunarity: 2.0,
+            gain: 0.5,
+            noise: Noise::new(seed),
+        }
+    }
+
+    pub fn with_octaves(mut self, octaves: u32) -> Fbm {
+        self.octaves = octaves;
+        self
+    }
+
+    pub fn with_frequency(mut self, frequency: f32) -> Fbm {
+        self.frequency = frequency;
+        self
+    }
+
+    pub fn with_lacunarity(mut self, lacunarity: f32) -> Fbm {
+        self.lacunarity = lacunarity;
+        self
+    }
+
+    pub fn with_gain(mut self, gain: f32) -> Fbm {
+        self.gain = gain;
+        self
+    }
+
+    pub fn noise(&self) -> &Noise {
+        &self.noise
+    }
+
+    /// Sum of the octaves, `sample(frequency)` gives an octave at a frequency
+    fn sum_octaves<F>(&self, mut sample: F) -> f32
+    where
+        F: FnMut(f32) -> f32,
+    {
+        let (mut sum, mut total) = (0.0, 0.0);
+        let (mut frequency, mut amplitude) = (self.frequency, 1.0);
+
+        for _ in 0..self.octaves.max(1) {
+            sum += sample(frequency) * amplitude;
+            total += amplitude;
+            frequency *= self.lacunarity;
+            amplitude *= self.gain;
+        }
+
+        sum / total
+    }
+
+    pub fn sample2(&self, x: f32, y: f32) -> f32 {
+        self.sum_octaves(|f| self.noise.sample2(self.kind, x * f, y * f))
+    }
+
+    pub fn sample3(&self, x: f32, y: f32, z: f32) -> f32 {
+        self.sum_octaves(|f| self.noise.sample3(self.kind, x * f, y * f, z * f))
+    }
+
+    /// Repeats every `period` units. The frequency of each octave is rounded to a whole
+    /// number of cycles per period.
+    pub fn sample2_tiled(&self, x: f32, y: f32, period: f32) -> f32 {
+        self.sum_octaves(|f| {
+            let cycles = (period * f).round().max(1.0);
+            let f = cycles / period;
+            self.noise.sample2_tiled(self.kind, x * f, y * f, cycles as u32)
+        })
+    }
+
+    pub fn sample3_tiled(&self, x: f32, y: f32, z: f32, period: f32) -> f32 {
+        self.sum_octaves(|f| {
+            let cycles = (period * f).round().max(1.0);
+            let f = cycles / period;
+            self.noise.sample3_tiled(self.kind, x * f, y * f, z * f, cycles as u32)
+        })
+    }
+}
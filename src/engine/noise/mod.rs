@@ -0,0 +1,349 @@
+//! Coherent noise from a seed, for terrain, clouds and procedural materials
+//!
+//! `Noise` samples Perlin, simplex and Worley (cellular) noise in 2D and 3D, and `Fbm` sums
+//! octaves of one of them. The `_tiled` variants repeat with a period, for textures which
+//! wrap. The results are baked into a `Heightmap`, turned into a texture or a height field,
+//! either at once or by rows in the idle time between frames.
+//!
+//! ```ignore
+//! let fbm = Fbm::new(42, NoiseKind::Simplex).with_octaves(5);
+//! let bake = Heightmap::bake_idle(&world.idle, 256, 256, move |x, y| {
+//!     fbm.sample2_tiled(x as f32 / 256.0, y as f32 / 256.0, 1.0) * 0.5 + 0.5
+//! });
+//! // Later, once `bake.is_done()`
+//! let clouds = bake.heightmap().unwrap().to_texture(true);
+//! ```
+
+mod bake;
+mod fbm;
+
+pub use self::bake::{Heightmap, HeightmapBake};
+pub use self::fbm::Fbm;
+
+use engine::procgen::Random;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum NoiseKind {
+    Perlin,
+    Simplex,
+    /// Distance to the nearest feature point
+    Worley,
+}
+
+/// Permutation table of a seed, shared by all the kinds of noise
+#[derive(Debug, Clone)]
+pub struct Noise {
+    perm: Vec<u8>,
+}
+
+const F2: f32 = 0.366_025_42; // (sqrt(3) - 1) / 2
+const G2: f32 = 0.211_324_87; // (3 - sqrt(3)) / 6
+const F3: f32 = 1.0 / 3.0;
+const G3: f32 = 1.0 / 6.0;
+
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+/// Lattice coordinate wrapped in [0, period), unchanged without period
+fn wrap(i: i32, period: i32) -> i32 {
+    if period > 0 {
+        ((i % period) + period) % period
+    } else {
+        i
+    }
+}
+
+fn grad2(h: usize, x: f32, y: f32) -> f32 {
+    match h & 7 {
+        0 => x + y,
+        1 => -x + y,
+        2 => x - y,
+        3 => -x - y,
+        4 => x,
+        5 => -x,
+        6 => y,
+        _ => -y,
+    }
+}
+
+fn grad3(h: usize, x: f32, y: f32, z: f32) -> f32 {
+    let h = h & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 {
+        y
+    } else if h == 12 || h == 14 {
+        x
+    } else {
+        z
+    };
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+impl Noise {
+    pub fn new(seed: u32) -> Noise {
+        let mut rng = Random::new(seed);
+        let mut p: Vec<u8> = (0..256).map(|i| i as u8).collect();
+        for i in (1..256).rev() {
+            let j = rng.range_usize(0, i + 1);
+            p.swap(i, j);
+        }
+
+        let mut perm = p.clone();
+        perm.extend(p);
+        Noise { perm }
+    }
+
+    fn hash2(&self, x: i32, y: i32) -> usize {
+        let p = &self.perm;
+        p[p[(x & 255) as usize] as usize + (y & 255) as usize] as usize
+    }
+
+    fn hash3(&self, x: i32, y: i32, z: i32) -> usize {
+        let p = &self.perm;
+        p[self.hash2(x, y) + (z & 255) as usize] as usize
+    }
+
+    /// About [-1, 1], 0 on the integer coordinates
+    pub fn perlin2(&self, x: f32, y: f32) -> f32 {
+        self.perlin2_periodic(x, y, 0)
+    }
+
+    /// Perlin noise repeating every `period` units on both axes
+    pub fn perlin2_tiled(&self, x: f32, y: f32, period: u32) -> f32 {
+        self.perlin2_periodic(x, y, period.max(1) as i32)
+    }
+
+    fn perlin2_periodic(&self, x: f32, y: f32, period: i32) -> f32 {
+        let (x0, y0) = (x.floor(), y.floor());
+        let (xi, yi) = (x0 as i32, y0 as i32);
+        let (fx, fy) = (x - x0, y - y0);
+
+        let g = |dx: i32, dy: i32| {
+            let h = self.hash2(wrap(xi + dx, period), wrap(yi + dy, period));
+            grad2(h, fx - dx as f32, fy - dy as f32)
+        };
+
+        let (u, v) = (fade(fx), fade(fy));
+        lerp(lerp(g(0, 0), g(1, 0), u), lerp(g(0, 1), g(1, 1), u), v)
+    }
+
+    /// About [-1, 1], 0 on the integer coordinates
+    pub fn perlin3(&self, x: f32, y: f32, z: f32) -> f32 {
+        self.perlin3_periodic(x, y, z, 0)
+    }
+
+    pub fn perlin3_tiled(&self, x: f32, y: f32, z: f32, period: u32) -> f32 {
+        self.perlin3_periodic(x, y, z, period.max(1) as i32)
+    }
+
+    fn perlin3_periodic(&self, x: f32, y: f32, z: f32, period: i32) -> f32 {
+        let (x0, y0, z0) = (x.floor(), y.floor(), z.floor());
+        let (xi, yi, zi) = (x0 as i32, y0 as i32, z0 as i32);
+        let (fx, fy, fz) = (x - x0, y - y0, z - z0);
+
+        let g = |dx: i32, dy: i32, dz: i32| {
+            let h = self.hash3(
+                wrap(xi + dx, period),
+                wrap(yi + dy, period),
+                wrap(zi + dz, period),
+            );
+            grad3(h, fx - dx as f32, fy - dy as f32, fz - dz as f32)
+        };
+
+        let (u, v, w) = (fade(fx), fade(fy), fade(fz));
+        let near = lerp(lerp(g(0, 0, 0), g(1, 0, 0), u), lerp(g(0, 1, 0), g(1, 1, 0), u), v);
+        let far = lerp(lerp(g(0, 0, 1), g(1, 0, 1), u), lerp(g(0, 1, 1), g(1, 1, 1), u), v);
+        lerp(near, far, w)
+    }
+
+    /// About [-1, 1], cheaper than Perlin and without its axis aligned artifacts
+    pub fn simplex2(&self, x: f32, y: f32) -> f32 {
+        let s = (x + y) * F2;
+        let (i, j) = ((x + s).floor() as i32, (y + s).floor() as i32);
+        let t = (i + j) as f32 * G2;
+        let (x0, y0) = (x - (i as f32 - t), y - (j as f32 - t));
+
+        let (i1, j1) = if x0 > y0 { (1, 0) } else { (0, 1) };
+        let corners = [
+            (0, 0, x0, y0),
+            (i1, j1, x0 - i1 as f32 + G2, y0 - j1 as f32 + G2),
+            (1, 1, x0 - 1.0 + 2.0 * G2, y0 - 1.0 + 2.0 * G2),
+        ];
+
+        let mut n = 0.0;
+        for &(di, dj, cx, cy) in corners.iter() {
+            let t = 0.5 - cx * cx - cy * cy;
+            if t > 0.0 {
+                n += t * t * t * t * grad2(self.hash2(i + di, j + dj), cx, cy);
+            }
+        }
+        70.0 * n
+    }
+
+    pub fn simplex3(&self, x: f32, y: f32, z: f32) -> f32 {
+        let s = (x + y + z) * F3;
+        let (i, j, k) = (
+            (x + s).floor() as i32,
+            (y + s).floor() as i32,
+            (z + s).floor() as i32,
+        );
+        let t = (i + j + k) as f32 * G3;
+        let (x0, y0, z0) = (x - (i as f32 - t), y - (j as f32 - t), z - (k as f32 - t));
+
+        // The two middle corners of the simplex, by the order of the offsets
+        let (a, b) = if x0 >= y0 {
+            if y0 >= z0 {
+                ((1, 0, 0), (1, 1, 0))
+            } else if x0 >= z0 {
+                ((1, 0, 0), (1, 0, 1))
+            } else {
+                ((0, 0, 1), (1, 0, 1))
+            }
+        } else if y0 < z0 {
+            ((0, 0, 1), (0, 1, 1))
+        } else if x0 < z0 {
+            ((0, 1, 0), (0, 1, 1))
+        } else {
+            ((0, 1, 0), (1, 1, 0))
+        };
+
+        let mut n = 0.0;
+        for (c, &(di, dj, dk)) in [(0, 0, 0), a, b, (1, 1, 1)].iter().enumerate() {
+            let g = c as f32 * G3;
+            let (cx, cy, cz) = (
+                x0 - di as f32 + g,
+                y0 - dj as f32 + g,
+                z0 - dk as f32 + g,
+            );
+            let t = 0.6 - cx * cx - cy * cy - cz * cz;
+            if t > 0.0 {
+                n += t * t * t * t * grad3(self.hash3(i + di, j + dj, k + dk), cx, cy, cz);
+            }
+        }
+        32.0 * n
+    }
+
+    /// Simplex noise repeating every `period` units, blended from four samples
+    pub fn simplex2_tiled(&self, x: f32, y: f32, period: u32) -> f32 {
+        let p = period.max(1) as f32;
+        let (x, y) = (x - (x / p).floor() * p, y - (y / p).floor() * p);
+        let (u, v) = (x / p, y / p);
+
+        let a = lerp(self.simplex2(x, y), self.simplex2(x - p, y), u);
+        let b = lerp(self.simplex2(x, y - p), self.simplex2(x - p, y - p), u);
+        lerp(a, b, v)
+    }
+
+    /// Simplex noise repeating every `period` units, blended from eight samples
+    pub fn simplex3_tiled(&self, x: f32, y: f32, z: f32, period: u32) -> f32 {
+        let p = period.max(1) as f32;
+        let (x, y, z) = (
+            x - (x / p).floor() * p,
+            y - (y / p).floor() * p,
+            z - (z / p).floor() * p,
+        );
+        let (u, v, w) = (x / p, y / p, z / p);
+
+        let layer = |z: f32| {
+            let a = lerp(self.simplex3(x, y, z), self.simplex3(x - p, y, z), u);
+            let b = lerp(self.simplex3(x, y - p, z), self.simplex3(x - p, y - p, z), u);
+            lerp(a, b, v)
+        };
+        lerp(layer(z), layer(z - p), w)
+    }
+
+    /// Distance to the nearest feature point, one point per unit cell, mostly in [0, 1]
+    pub fn worley2(&self, x: f32, y: f32) -> f32 {
+        self.worley2_periodic(x, y, 0)
+    }
+
+    pub fn worley2_tiled(&self, x: f32, y: f32, period: u32) -> f32 {
+        self.worley2_periodic(x, y, period.max(1) as i32)
+    }
+
+    fn worley2_periodic(&self, x: f32, y: f32, period: i32) -> f32 {
+        let (xi, yi) = (x.floor() as i32, y.floor() as i32);
+        let mut nearest = ::std::f32::MAX;
+
+        for dy in -1..2 {
+            for dx in -1..2 {
+                let (cx, cy) = (xi + dx, yi + dy);
+                let h = self.hash2(wrap(cx, period), wrap(cy, period));
+                let px = cx as f32 + self.perm[h] as f32 / 255.0;
+                let py = cy as f32 + self.perm[h + 1] as f32 / 255.0;
+
+                let d = (px - x) * (px - x) + (py - y) * (py - y);
+                nearest = nearest.min(d);
+            }
+        }
+
+        nearest.sqrt()
+    }
+
+    pub fn worley3(&self, x: f32, y: f32, z: f32) -> f32 {
+        self.worley3_periodic(x, y, z, 0)
+    }
+
+    pub fn worley3_tiled(&self, x: f32, y: f32, z: f32, period: u32) -> f32 {
+        self.worley3_periodic(x, y, z, period.max(1) as i32)
+    }
+
+    fn worley3_periodic(&self, x: f32, y: f32, z: f32, period: i32) -> f32 {
+        let (xi, yi, zi) = (x.floor() as i32, y.floor() as i32, z.floor() as i32);
+        let mut nearest = ::std::f32::MAX;
+
+        for dz in -1..2 {
+            for dy in -1..2 {
+                for dx in -1..2 {
+                    let (cx, cy, cz) = (xi + dx, yi + dy, zi + dz);
+                    let h = self.hash3(wrap(cx, period), wrap(cy, period), wrap(cz, period));
+                    let px = cx as f32 + self.perm[h] as f32 / 255.0;
+                    let py = cy as f32 + self.perm[h + 1] as f32 / 255.0;
+                    let pz = cz as f32 + self.perm[h + 2] as f32 / 255.0;
+
+                    let d = (px - x) * (px - x) + (py - y) * (py - y) + (pz - z) * (pz - z);
+                    nearest = nearest.min(d);
+                }
+            }
+        }
+
+        nearest.sqrt()
+    }
+
+    pub fn sample2(&self, kind: NoiseKind, x: f32, y: f32) -> f32 {
+        match kind {
+            NoiseKind::Perlin => self.perlin2(x, y),
+            NoiseKind::Simplex => self.simplex2(x, y),
+            NoiseKind::Worley => self.worley2(x, y),
+        }
+    }
+
+    pub fn sample3(&self, kind: NoiseKind, x: f32, y: f32, z: f32) -> f32 {
+        match kind {
+            NoiseKind::Perlin => self.perlin3(x, y, z),
+            NoiseKind::Simplex => self.simplex3(x, y, z),
+            NoiseKind::Worley => self.worley3(x, y, z),
+        }
+    }
+
+    pub fn sample2_tiled(&self, kind: NoiseKind, x: f32, y: f32, period: u32) -> f32 {
+        match kind {
+            NoiseKind::Perlin => self.perlin2_tiled(x, y, period),
+            NoiseKind::Simplex => self.simplex2_tiled(x, y, period),
+            NoiseKind::Worley => self.worley2_tiled(x, y, period),
+        }
+    }
+
+    pub fn sample3_tiled(&self, kind: NoiseKind, x: f32, y: f32, z: f32, period: u32) -> f32 {
+        match kind {
+            NoiseKind::Perlin => self.perlin3_tiled(x, y, z, period),
+            NoiseKind::Simplex => self.simplex3_tiled(x, y, z, period),
+            NoiseKind::Worley => self.worley3_tiled(x, y, z, period),
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use math::*;
+
+use super::world::BodyHandle;
+
+/// A constraint between bodies, solved with the contacts
+#[derive(Debug, Clone, Copy)]
+pub enum Joint {
+    /// Keeps the centers of two bodies at `length`, like a rigid rod
+    Distance {
+        a: BodyHandle,
+        b: BodyHandle,
+        length: f32,
+        /// From 0 (no effect) to 1 (rigid)
+        stiffness: f32,
+    },
+    /// Limits the bending at `center` of the chain `a` - `center` - `b`: the angle between
+    /// the segments `a` to `center` and `center` to `b` stays below `max_angle`
+    Cone {
+        a: BodyHandle,
+        center: BodyHandle,
+        b: BodyHandle,
+        max_angle: Rad<f32>,
+    },
+}
+
+/// Corrections of the positions of `a` and `b` for a distance joint
+pub(crate) fn solve_distance(
+    (pa, wa): (Vector3<f32>, f32),
+    (pb, wb): (Vector3<f32>, f32),
+    length: f32,
+    stiffness: f32,
+) -> Option<(Vector3<f32>, Vector3<f32>, Vector3<f32>)> {
+    let d = pb - pa;
+    let dist = d.magnitude();
+    if wa + wb <= 0.0 || dist < 1e-6 {
+        return None;
+    }
+
+    let normal = d / dist;
+    let error = (dist - length) * stiffness.max(0.0).min(1.0);
+    let da = normal * (error * wa / (wa + wb));
+    let db = -normal * (error * wb / (wa + wb));
+
+    Some((da, db, normal))
+}
+
+/// Corrections of the positions of `a` and `b` for a cone joint, the center is not moved
+pub(crate) fn solve_cone(
+    (pa, wa): (Vector3<f32>, f32),
+    pc: Vector3<f32>,
+    (pb, wb): (Vector3<f32>, f32),
+    max_angle: Rad<f32>,
+) -> Option<(Vector3<f32>, Vector3<f32>)> {
+    let d1 = pc - pa;
+    let d2 = pb - pc;
+    let (l1, l2) = (d1.magnitude(), d2.magnitude());
+    if wa + wb <= 0.0 || l1 < 1e-6 || l2 < 1e-6 {
+        return None;
+    }
+
+    let (d1, d2) = (d1 / l1, d2 / l2);
+    let angle = d1.dot(d2).max(-1.0).min(1.0).acos();
+    if angle <= max_angle.0 {
+        return None;
+    }
+
+    let axis = d1.cross(d2);
+    if axis.magnitude2() < 1e-12 {
+        return None;
+    }
+    let axis = axis.normalize();
+
+    // Share the excess angle between the two segments, by their inverse masses
+    let excess = angle - max_angle.0;
+    let turn_b = excess * wb / (wa + wb);
+    let turn_a = excess * wa / (wa + wb);
+
+    let target_b = pc + Quaternion::from_axis_angle(axis, Rad(-turn_b)) * d2 * l2;
+    let target_a = pc - Quaternion::from_axis_angle(axis, Rad(turn_a)) * d1 * l1;
+
+    Some((target_a - pa, target_b - pb))
+}
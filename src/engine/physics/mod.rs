@@ -0,0 +1,24 @@
+//! Physics
+//!
+//! Collision of dynamic spheres (players, props) with static level geometry: height fields
+//! from terrain height maps and triangle meshes cooked from render meshes, so levels are
+//! walkable without authoring separate collision shapes.
+//!
+//! Meshes are cooked synchronously, into a uniform grid of triangles. Triangle meshes are
+//! collided as they are, there is no convex decomposition.
+//!
+//! Bodies are linked by `Joint`s, e.g. the ragdolls of `animation::Ragdoll`.
+//!
+//! Water volumes make bodies float, and report `Splash` events when bodies cross the surface.
+
+mod joint;
+mod shape;
+mod water;
+mod world;
+
+pub use self::shape::{closest_point_on_triangle, ray_triangle, sphere_triangle, Contact,
+                      HeightField, RayHit, Triangle, TriMesh};
+pub use self::joint::Joint;
+pub use self::water::{Wave, WaterVolume, MAX_SHADER_WAVES, WATER_GLSL};
+pub use self::world::{BodyHandle, Collider, ColliderHandle, Interpolation, JointHandle,
+                      PhysicsWorld, RigidBody, Splash, SplashKind, WaterGuard, WaterHandle};
@@ -0,0 +1,531 @@
+use engine::asset::AssetResult;
+use engine::{Aabb, Mesh, MeshData};
+use image::RgbaImage;
+
+use math::*;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayHit {
+    pub distance: f32,
+    pub point: Vector3<f32>,
+    pub normal: Vector3<f32>,
+}
+
+/// Penetration of a shape into a collider
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Contact {
+    /// Closest point on the collider surface
+    pub point: Vector3<f32>,
+    /// Direction to push the shape out of the collider
+    pub normal: Vector3<f32>,
+    pub depth: f32,
+}
+
+pub type Triangle = [Vector3<f32>; 3];
+
+/// Closest point of a triangle to `p` (from Real-Time Collision Detection, 5.1.5)
+pub fn closest_point_on_triangle(p: Vector3<f32>, tri: &Triangle) -> Vector3<f32> {
+    let (a, b, c) = (tri[0], tri[1], tri[2]);
+    let ab = b - a;
+    let ac = c - a;
+    let ap = p - a;
+
+    let d1 = ab.dot(ap);
+    let d2 = ac.dot(ap);
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return a;
+    }
+
+    let bp = p - b;
+    let d3 = ab.dot(bp);
+    let d4 = ac.dot(bp);
+    if d3 >= 0.0 && d4 <= d3 {
+        return b;
+    }
+
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        return a + ab * (d1 / (d1 - d3));
+    }
+
+    let cp = p - c;
+    let d5 = ab.dot(cp);
+    let d6 = ac.dot(cp);
+    if d6 >= 0.0 && d5 <= d6 {
+        return c;
+    }
+
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        return a + ac * (d2 / (d2 - d6));
+    }
+
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && (d4 - d3) >= 0.0 && (d5 - d6) >= 0.0 {
+        return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+    }
+
+    let denom = 1.0 / (va + vb + vc);
+    a + ab * (vb * denom) + ac * (vc * denom)
+}
+
+/// Möller–Trumbore intersection, both faces are hit
+pub fn ray_triangle(
+    origin: Vector3<f32>,
+    dir: Vector3<f32>,
+    tri: &Triangle,
+    max_distance: f32,
+) -> Option<RayHit> {
+    let e1 = tri[1] - tri[0];
+    let e2 = tri[2] - tri[0];
+    let pvec = dir.cross(e2);
+    let det = e1.dot(pvec);
+    if det.abs() < 1e-8 {
+        return None;
+    }
+
+    let inv_det = 1.0 / det;
+    let tvec = origin - tri[0];
+    let u = tvec.dot(pvec) * inv_det;
+    if u < 0.0 || u > 1.0 {
+        return None;
+    }
+
+    let qvec = tvec.cross(e1);
+    let v = dir.dot(qvec) * inv_det;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    let t = e2.dot(qvec) * inv_det;
+    if t < 0.0 || t > max_distance {
+        return None;
+    }
+
+    let mut normal = e1.cross(e2).normalize();
+    if normal.dot(dir) > 0.0 {
+        normal = -normal;
+    }
+
+    Some(RayHit {
+        distance: t,
+        point: origin + dir * t,
+        normal,
+    })
+}
+
+/// Contact of a sphere with a triangle
+pub fn sphere_triangle(center: Vector3<f32>, radius: f32, tri: &Triangle) -> Option<Contact> {
+    let q = closest_point_on_triangle(center, tri);
+    let d = center - q;
+    let dist2 = d.magnitude2();
+    if dist2 >= radius * radius {
+        return None;
+    }
+
+    let dist = dist2.sqrt();
+    let normal = if dist > 1e-6 {
+        d / dist
+    } else {
+        (tri[1] - tri[0]).cross(tri[2] - tri[0]).normalize()
+    };
+
+    Some(Contact {
+        point: q,
+        normal,
+        depth: radius - dist,
+    })
+}
+
+fn aabb_overlaps(a: &Aabb, b: &Aabb) -> bool {
+    a.min.x <= b.max.x && a.max.x >= b.min.x && a.min.y <= b.max.y && a.max.y >= b.min.y
+        && a.min.z <= b.max.z && a.max.z >= b.min.z
+}
+
+/// A grid of heights, e.g. of a terrain. Samples are `cell_size` apart on the x and z axes,
+/// from `origin`, each cell is made of two triangles.
+#[derive(Debug, Clone)]
+pub struct HeightField {
+    pub origin: Vector3<f32>,
+    pub cell_size: f32,
+    /// Number of samples along x
+    pub columns: usize,
+    /// Number of samples along z
+    pub rows: usize,
+    /// Row major heights, relative to `origin.y`
+    pub heights: Vec<f32>,
+}
+
+impl HeightField {
+    /// None without a full cell, a positive `cell_size` or `columns * rows` heights
+    pub fn new(
+        columns: usize,
+        rows: usize,
+        cell_size: f32,
+        heights: Vec<f32>,
+    ) -> Option<HeightField> {
+        if columns < 2 || rows < 2 || heights.len() != columns * rows {
+            return None;
+        }
+        if cell_size.is_nan() || cell_size <= 0.0 {
+            return None;
+        }
+
+        Some(HeightField {
+            origin: Vector3::zero(),
+            cell_size,
+            columns,
+            rows,
+            heights,
+        })
+    }
+
+    /// A height field from the red channel of a height map, 255 is `height_scale` high.
+    /// None for a map smaller than 2x2 pixels.
+    pub fn from_image(img: &RgbaImage, cell_size: f32, height_scale: f32) -> Option<HeightField> {
+        let heights = img.pixels()
+            .map(|p| p.data[0] as f32 / 255.0 * height_scale)
+            .collect();
+
+        HeightField::new(
+            img.width() as usize,
+            img.height() as usize,
+            cell_size,
+            heights,
+        )
+    }
+
+    pub fn with_origin(mut self, origin: Vector3<f32>) -> HeightField {
+        self.origin = origin;
+        self
+    }
+
+    pub fn aabb(&self) -> Aabb {
+        let (min, max) = self.heights
+            .iter()
+            .fold((::std::f32::MAX, ::std::f32::MIN), |acc, h| {
+                (acc.0.min(*h), acc.1.max(*h))
+            });
+
+        Aabb {
+            min: self.origin + Vector3::new(0.0, min, 0.0),
+            max: self.origin
+                + Vector3::new(
+                    (self.columns - 1) as f32 * self.cell_size,
+                    max,
+                    (self.rows - 1) as f32 * self.cell_size,
+                ),
+        }
+    }
+
+    fn vertex(&self, col: usize, row: usize) -> Vector3<f32> {
+        self.origin
+            + Vector3::new(
+                col as f32 * self.cell_size,
+                self.heights[row * self.columns + col],
+                row as f32 * self.cell_size,
+            )
+    }
+
+    fn cell_triangles(&self, col: usize, row: usize) -> [Triangle; 2] {
+        let v00 = self.vertex(col, row);
+        let v10 = self.vertex(col + 1, row);
+        let v01 = self.vertex(col, row + 1);
+        let v11 = self.vertex(col + 1, row + 1);
+
+        [[v00, v01, v11], [v00, v11, v10]]
+    }
+
+    /// Cell (col, row) containing the world position, if inside
+    fn cell_at(&self, x: f32, z: f32) -> Option<(usize, usize, f32, f32)> {
+        let fx = (x - self.origin.x) / self.cell_size;
+        let fz = (z - self.origin.z) / self.cell_size;
+        let max_x = (self.columns - 1) as f32;
+        let max_z = (self.rows - 1) as f32;
+
+        if fx < 0.0 || fz < 0.0 || fx > max_x || fz > max_z {
+            return None;
+        }
+
+        let col = (fx.floor() as usize).min(self.columns - 2);
+        let row = (fz.floor() as usize).min(self.rows - 2);
+        Some((col, row, fx - col as f32, fz - row as f32))
+    }
+
+    /// Height of the surface at a world position, None outside of the field
+    pub fn height_at(&self, x: f32, z: f32) -> Option<f32> {
+        let (col, row, u, v) = self.cell_at(x, z)?;
+
+        let h = |c, r| self.heights[r * self.columns + c];
+        let h00 = h(col, row);
+        let h10 = h(col + 1, row);
+        let h01 = h(col, row + 1);
+        let h11 = h(col + 1, row + 1);
+
+        // Same split as the triangles
+        let height = if v > u {
+            h00 + (h11 - h01) * u + (h01 - h00) * v
+        } else {
+            h00 + (h10 - h00) * u + (h11 - h10) * v
+        };
+
+        Some(self.origin.y + height)
+    }
+
+    pub fn normal_at(&self, x: f32, z: f32) -> Option<Vector3<f32>> {
+        let (col, row, u, v) = self.cell_at(x, z)?;
+        let tris = self.cell_triangles(col, row);
+        let tri = if v > u { &tris[0] } else { &tris[1] };
+
+        let n = (tri[1] - tri[0]).cross(tri[2] - tri[0]).normalize();
+        Some(if n.y < 0.0 { -n } else { n })
+    }
+
+    /// Call `f` with the triangles of the cells under the box
+    pub fn for_each_triangle<F: FnMut(&Triangle)>(&self, aabb: &Aabb, mut f: F) {
+        let to_cell = |v: f32, origin: f32, count: usize| -> usize {
+            let c = ((v - origin) / self.cell_size).floor();
+            if c < 0.0 {
+                0
+            } else {
+                (c as usize).min(count - 2)
+            }
+        };
+
+        let bounds = self.aabb();
+        if !aabb_overlaps(&bounds, aabb) {
+            return;
+        }
+
+        let c0 = to_cell(aabb.min.x, self.origin.x, self.columns);
+        let c1 = to_cell(aabb.max.x, self.origin.x, self.columns);
+        let r0 = to_cell(aabb.min.z, self.origin.z, self.rows);
+        let r1 = to_cell(aabb.max.z, self.origin.z, self.rows);
+
+        for row in r0..r1 + 1 {
+            for col in c0..c1 + 1 {
+                for tri in self.cell_triangles(col, row).iter() {
+                    f(tri);
+                }
+            }
+        }
+    }
+
+    /// Hit of a ray from above the surface
+    pub fn raycast(&self, origin: Vector3<f32>, dir: Vector3<f32>, max_distance: f32) -> Option<RayHit> {
+        let above = |t: f32| -> Option<f32> {
+            let p = origin + dir * t;
+            self.height_at(p.x, p.z).map(|h| p.y - h)
+        };
+
+        // March by half cells, then refine the crossing by bisection
+        let step = self.cell_size * 0.5;
+        let mut t0 = 0.0;
+        let mut prev = above(0.0);
+
+        while t0 < max_distance {
+            let t1 = (t0 + step).min(max_distance);
+            let cur = above(t1);
+
+            if let (Some(a), Some(b)) = (prev, cur) {
+                if a >= 0.0 && b < 0.0 {
+                    let (mut lo, mut hi) = (t0, t1);
+                    for _ in 0..16 {
+                        let mid = (lo + hi) * 0.5;
+                        match above(mid) {
+                            Some(d) if d >= 0.0 => lo = mid,
+                            _ => hi = mid,
+                        }
+                    }
+
+                    let point = origin + dir * hi;
+                    return Some(RayHit {
+                        distance: hi,
+                        point,
+                        normal: self.normal_at(point.x, point.z).unwrap_or(Vector3::unit_y()),
+                    });
+                }
+            }
+
+            prev = cur;
+            t0 = t1;
+        }
+
+        None
+    }
+}
+
+/// A static triangle mesh, e.g. cooked from level geometry.
+/// Triangles are bucketed in a uniform grid to speed up the queries.
+#[derive(Debug, Clone)]
+pub struct TriMesh {
+    vertices: Vec<Vector3<f32>>,
+    triangles: Vec<[usize; 3]>,
+    aabb: Aabb,
+    grid_size: [usize; 3],
+    cells: Vec<Vec<usize>>,
+}
+
+impl TriMesh {
+    pub fn new(vertices: Vec<Vector3<f32>>, triangles: Vec<[usize; 3]>) -> TriMesh {
+        let mut aabb = Aabb::empty();
+        for v in vertices.iter() {
+            aabb.merge_point(v);
+        }
+
+        let mut mesh = TriMesh {
+            vertices,
+            triangles,
+            aabb,
+            grid_size: [1, 1, 1],
+            cells: Vec::new(),
+        };
+        mesh.build_grid();
+        mesh
+    }
+
+    /// Cook the triangles of a render mesh, placed by `transform`
+    pub fn from_mesh_data(data: &MeshData, transform: &Isometry3<f32>) -> TriMesh {
+        let vertices = data.vertices
+            .chunks(3)
+            .map(|v| transform.transform_point(Point3::new(v[0], v[1], v[2])).to_vec())
+            .collect();
+        let triangles = data.indices
+            .chunks(3)
+            .filter(|t| t.len() == 3)
+            .map(|t| [t[0] as usize, t[1] as usize, t[2] as usize])
+            .collect();
+
+        TriMesh::new(vertices, triangles)
+    }
+
+    /// Cook all the surfaces of a mesh, fails while a buffer is not loaded
+    pub fn from_mesh(mesh: &Mesh, transform: &Isometry3<f32>) -> AssetResult<TriMesh> {
+        let mut vertices = Vec::new();
+        let mut triangles = Vec::new();
+
+        for surface in mesh.surfaces.iter() {
+            let data = surface.buffer.mesh_data()?;
+            let part = TriMesh::from_mesh_data(&data, transform);
+            let base = vertices.len();
+
+            vertices.extend(part.vertices.into_iter());
+            triangles.extend(
+                part.triangles
+                    .into_iter()
+                    .map(|t| [t[0] + base, t[1] + base, t[2] + base]),
+            );
+        }
+
+        Ok(TriMesh::new(vertices, triangles))
+    }
+
+    pub fn aabb(&self) -> Aabb {
+        self.aabb
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.triangles.len()
+    }
+
+    fn triangle(&self, i: usize) -> Triangle {
+        let t = self.triangles[i];
+        [self.vertices[t[0]], self.vertices[t[1]], self.vertices[t[2]]]
+    }
+
+    fn build_grid(&mut self) {
+        if self.triangles.len() == 0 {
+            return;
+        }
+
+        // About a few triangles per cell, at most 32 cells per axis
+        let n = ((self.triangles.len() as f32).cbrt().ceil() as usize).max(1).min(32);
+        self.grid_size = [n, n, n];
+        self.cells = vec![Vec::new(); n * n * n];
+
+        for i in 0..self.triangles.len() {
+            let tri = self.triangle(i);
+            let mut bounds = Aabb::empty();
+            for v in tri.iter() {
+                bounds.merge_point(v);
+            }
+
+            let (lo, hi) = self.cell_range(&bounds);
+            for z in lo[2]..hi[2] + 1 {
+                for y in lo[1]..hi[1] + 1 {
+                    for x in lo[0]..hi[0] + 1 {
+                        let idx = self.cell_index(x, y, z);
+                        self.cells[idx].push(i);
+                    }
+                }
+            }
+        }
+    }
+
+    fn cell_index(&self, x: usize, y: usize, z: usize) -> usize {
+        (z * self.grid_size[1] + y) * self.grid_size[0] + x
+    }
+
+    fn cell_range(&self, bounds: &Aabb) -> ([usize; 3], [usize; 3]) {
+        let mut lo = [0; 3];
+        let mut hi = [0; 3];
+
+        for axis in 0..3 {
+            let extent = (self.aabb.max[axis] - self.aabb.min[axis]).max(1e-6);
+            let n = self.grid_size[axis];
+            let to_cell = |v: f32| {
+                let c = ((v - self.aabb.min[axis]) / extent * n as f32).floor();
+                if c < 0.0 {
+                    0
+                } else {
+                    (c as usize).min(n - 1)
+                }
+            };
+
+            lo[axis] = to_cell(bounds.min[axis]);
+            hi[axis] = to_cell(bounds.max[axis]);
+        }
+
+        (lo, hi)
+    }
+
+    /// Call `f` with the triangles which may overlap the box
+    pub fn for_each_triangle<F: FnMut(&Triangle)>(&self, aabb: &Aabb, mut f: F) {
+        if self.cells.len() == 0 || !aabb_overlaps(&self.aabb, aabb) {
+            return;
+        }
+
+        let (lo, hi) = self.cell_range(aabb);
+        let mut visited = Vec::new();
+
+        for z in lo[2]..hi[2] + 1 {
+            for y in lo[1]..hi[1] + 1 {
+                for x in lo[0]..hi[0] + 1 {
+                    visited.extend(self.cells[self.cell_index(x, y, z)].iter().cloned());
+                }
+            }
+        }
+
+        visited.sort();
+        visited.dedup();
+        for i in visited.into_iter() {
+            f(&self.triangle(i));
+        }
+    }
+
+    pub fn raycast(&self, origin: Vector3<f32>, dir: Vector3<f32>, max_distance: f32) -> Option<RayHit> {
+        let mut bounds = Aabb::empty();
+        bounds.merge_point(&origin);
+        bounds.merge_point(&(origin + dir * max_distance));
+
+        let mut best: Option<RayHit> = None;
+        self.for_each_triangle(&bounds, |tri| {
+            let limit = best.map(|b| b.distance).unwrap_or(max_distance);
+            if let Some(hit) = ray_triangle(origin, dir, tri, limit) {
+                best = Some(hit);
+            }
+        });
+
+        best
+    }
+}
@@ -0,0 +1,162 @@
+use engine::Material;
+
+use math::*;
+use std::f32::consts::PI;
+
+/// Waves are sampled in shaders by `water_height(vec2 xz, float time)`, with the uniforms
+/// set by `WaterVolume::bind`, so the rendered surface matches the simulated one
+pub const WATER_GLSL: &'static str = r#"
+uniform float uWaterLevel;
+uniform float uWaterTime;
+uniform vec4 uWave0;
+uniform vec4 uWave1;
+uniform vec4 uWave2;
+uniform vec4 uWave3;
+uniform vec4 uWaveSpeed;
+
+float wave_height(vec4 wave, float speed, vec2 xz, float time) {
+    if (wave.w <= 0.0) {
+        return 0.0;
+    }
+    float k = 6.2831853 / wave.w;
+    return wave.z * sin((dot(wave.xy, xz) - speed * time) * k);
+}
+
+float water_height(vec2 xz, float time) {
+    return uWaterLevel
+        + wave_height(uWave0, uWaveSpeed.x, xz, time)
+        + wave_height(uWave1, uWaveSpeed.y, xz, time)
+        + wave_height(uWave2, uWaveSpeed.z, xz, time)
+        + wave_height(uWave3, uWaveSpeed.w, xz, time);
+}
+"#;
+
+/// Waves passed to shaders, the others are simulated only
+pub const MAX_SHADER_WAVES: usize = 4;
+
+/// A sine wave of the water surface
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Wave {
+    /// Normalized direction on the xz plane
+    pub direction: Vector2<f32>,
+    pub amplitude: f32,
+    pub wavelength: f32,
+    /// In units per second
+    pub speed: f32,
+}
+
+impl Wave {
+    pub fn new(direction: Vector2<f32>, amplitude: f32, wavelength: f32, speed: f32) -> Wave {
+        Wave {
+            direction: direction.normalize(),
+            amplitude,
+            wavelength,
+            speed,
+        }
+    }
+
+    pub fn height(&self, x: f32, z: f32, time: f32) -> f32 {
+        if self.wavelength <= 0.0 {
+            return 0.0;
+        }
+
+        let k = 2.0 * PI / self.wavelength;
+        let d = self.direction.x * x + self.direction.y * z;
+        self.amplitude * ((d - self.speed * time) * k).sin()
+    }
+}
+
+/// A box of water applying buoyancy and drag to the bodies of a `PhysicsWorld`
+#[derive(Debug, Clone)]
+pub struct WaterVolume {
+    /// Center of the surface at rest
+    pub center: Vector3<f32>,
+    /// Half size on the x and z axes
+    pub extents: Vector2<f32>,
+    pub depth: f32,
+    /// Mass per cubic unit, 1000.0 for water in meters and kilograms
+    pub density: f32,
+    /// Velocity lost per second when fully submerged
+    pub drag: f32,
+    pub waves: Vec<Wave>,
+}
+
+impl WaterVolume {
+    pub fn new(center: Vector3<f32>, extents: Vector2<f32>, depth: f32) -> WaterVolume {
+        WaterVolume {
+            center,
+            extents,
+            depth,
+            density: 1000.0,
+            drag: 2.0,
+            waves: Vec::new(),
+        }
+    }
+
+    pub fn with_density(mut self, density: f32) -> WaterVolume {
+        self.density = density;
+        self
+    }
+
+    pub fn with_drag(mut self, drag: f32) -> WaterVolume {
+        self.drag = drag;
+        self
+    }
+
+    pub fn with_wave(mut self, wave: Wave) -> WaterVolume {
+        self.waves.push(wave);
+        self
+    }
+
+    /// Whether (x, z) is above or below the water
+    pub fn covers(&self, x: f32, z: f32) -> bool {
+        (x - self.center.x).abs() <= self.extents.x && (z - self.center.z).abs() <= self.extents.y
+    }
+
+    /// Height of the surface at (x, z), including the waves
+    pub fn surface_height(&self, x: f32, z: f32, time: f32) -> f32 {
+        self.waves
+            .iter()
+            .fold(self.center.y, |h, w| h + w.height(x, z, time))
+    }
+
+    /// Submerged volume of a sphere
+    pub fn submerged_volume(&self, center: Vector3<f32>, radius: f32, time: f32) -> f32 {
+        if !self.covers(center.x, center.z) {
+            return 0.0;
+        }
+
+        let surface = self.surface_height(center.x, center.z, time);
+        let bottom = self.center.y - self.depth;
+        if center.y + radius < bottom {
+            return 0.0;
+        }
+
+        // Volume of the spherical cap under the surface
+        let h = (surface - (center.y - radius)).max(0.0).min(2.0 * radius);
+        PI * h * h * (3.0 * radius - h) / 3.0
+    }
+
+    /// Set `WATER_GLSL` uniforms of a material
+    pub fn bind(&self, material: &Material, time: f32) {
+        material.set("uWaterLevel", self.center.y);
+        material.set("uWaterTime", time);
+
+        let mut speeds = [0.0; MAX_SHADER_WAVES];
+        for i in 0..MAX_SHADER_WAVES {
+            let param = match self.waves.get(i) {
+                Some(w) => {
+                    speeds[i] = w.speed;
+                    Vector4::new(w.direction.x, w.direction.y, w.amplitude, w.wavelength)
+                }
+                None => Vector4::zero(),
+            };
+            material.set(format!("uWave{}", i), param);
+        }
+
+        material.set(
+            "uWaveSpeed",
+            Vector4::new(speeds[0], speeds[1], speeds[2], speeds[3]),
+        );
+    }
+}
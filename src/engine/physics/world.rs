@@ -0,0 +1,849 @@
+use engine::Aabb;
+
+use math::*;
+
+use super::joint::{solve_cone, solve_distance, Joint};
+use super::shape::{sphere_triangle, Contact, HeightField, RayHit, TriMesh};
+use super::water::WaterVolume;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+#[derive(Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct ColliderHandle(usize);
+
+#[derive(Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct BodyHandle(usize);
+
+#[derive(Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct WaterHandle(usize);
+
+#[derive(Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct JointHandle(usize);
+
+/// A water volume owned by its holder, see `PhysicsWorld::add_owned_water`.
+/// Dropping it removes the volume at the next `PhysicsWorld::step`.
+pub struct WaterGuard {
+    handle: WaterHandle,
+    released: Rc<RefCell<Vec<WaterHandle>>>,
+}
+
+impl WaterGuard {
+    pub fn handle(&self) -> WaterHandle {
+        self.handle
+    }
+}
+
+impl Drop for WaterGuard {
+    fn drop(&mut self) {
+        self.released.borrow_mut().push(self.handle);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplashKind {
+    Enter,
+    Exit,
+}
+
+/// A body crossed the surface of a water volume, e.g. to spawn particles or play a sound
+#[derive(Debug, Clone, Copy)]
+pub struct Splash {
+    pub kind: SplashKind,
+    pub body: BodyHandle,
+    pub water: WaterHandle,
+    /// Where the body crossed the surface
+    pub position: Vector3<f32>,
+    /// Vertical speed of the body
+    pub speed: f32,
+}
+
+/// Static level geometry
+#[derive(Debug, Clone)]
+pub enum Collider {
+    HeightField(HeightField),
+    TriMesh(TriMesh),
+    /// Points `p` with `normal.dot(p) <= distance` are inside
+    Plane {
+        normal: Vector3<f32>,
+        distance: f32,
+    },
+}
+
+impl Collider {
+    pub fn raycast(&self, origin: Vector3<f32>, dir: Vector3<f32>, max_distance: f32) -> Option<RayHit> {
+        match *self {
+            Collider::HeightField(ref hf) => hf.raycast(origin, dir, max_distance),
+            Collider::TriMesh(ref mesh) => mesh.raycast(origin, dir, max_distance),
+            Collider::Plane { normal, distance } => {
+                let denom = normal.dot(dir);
+                if denom.abs() < 1e-8 {
+                    return None;
+                }
+
+                let t = (distance - normal.dot(origin)) / denom;
+                if t < 0.0 || t > max_distance {
+                    return None;
+                }
+
+                Some(RayHit {
+                    distance: t,
+                    point: origin + dir * t,
+                    normal: if denom < 0.0 { normal } else { -normal },
+                })
+            }
+        }
+    }
+
+    /// The vertex closest to `p`, within `max_distance`
+    pub fn nearest_vertex(&self, p: Vector3<f32>, max_distance: f32) -> Option<Vector3<f32>> {
+        let mut bounds = Aabb::empty();
+        bounds.merge_sphere(&p, max_distance);
+
+        let mut best: Option<(f32, Vector3<f32>)> = None;
+        {
+            let mut check = |tri: &[Vector3<f32>; 3]| {
+                for v in tri.iter() {
+                    let d = (v - p).magnitude();
+                    let closer = match best {
+                        Some((bd, _)) => d < bd,
+                        None => d <= max_distance,
+                    };
+                    if closer {
+                        best = Some((d, *v));
+                    }
+                }
+            };
+
+            match *self {
+                Collider::HeightField(ref hf) => hf.for_each_triangle(&bounds, &mut check),
+                Collider::TriMesh(ref mesh) => mesh.for_each_triangle(&bounds, &mut check),
+                Collider::Plane { .. } => {}
+            }
+        }
+
+        best.map(|b| b.1)
+    }
+
+    /// All contacts of a sphere with the collider
+    pub fn sphere_contacts(&self, center: Vector3<f32>, radius: f32, out: &mut Vec<Contact>) {
+        let mut bounds = Aabb::empty();
+        bounds.merge_sphere(&center, radius);
+
+        match *self {
+            Collider::HeightField(ref hf) => {
+                hf.for_each_triangle(&bounds, |tri| {
+                    out.extend(sphere_triangle(center, radius, tri));
+                });
+
+                // Below the surface, e.g. after a fast fall, push back up
+                if let Some(h) = hf.height_at(center.x, center.z) {
+                    if center.y < h {
+                        out.push(Contact {
+                            point: Vector3::new(center.x, h, center.z),
+                            normal: Vector3::unit_y(),
+                            depth: h - center.y + radius,
+                        });
+                    }
+                }
+            }
+            Collider::TriMesh(ref mesh) => {
+                mesh.for_each_triangle(&bounds, |tri| {
+                    out.extend(sphere_triangle(center, radius, tri));
+                });
+            }
+            Collider::Plane { normal, distance } => {
+                let d = normal.dot(center) - distance;
+                if d < radius {
+                    out.push(Contact {
+                        point: center - normal * d,
+                        normal,
+                        depth: radius - d,
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// A dynamic sphere
+#[derive(Debug, Clone)]
+pub struct RigidBody {
+    pub position: Vector3<f32>,
+    pub velocity: Vector3<f32>,
+    pub radius: f32,
+    /// 0.0 for a kinematic body, moved by setting its position
+    pub mass: f32,
+    pub restitution: f32,
+    pub friction: f32,
+    /// Velocity lost per second
+    pub damping: f32,
+    pub gravity_scale: f32,
+    /// Overrides `PhysicsWorld::time_scale` for this body, e.g. a player in bullet time
+    pub time_scale: Option<f32>,
+    /// Bodies of the same non zero group do not collide, e.g. the parts of a ragdoll
+    pub collision_group: u32,
+
+    force: Vector3<f32>,
+    grounded: bool,
+    previous_position: Vector3<f32>,
+    water: Option<WaterHandle>,
+}
+
+impl RigidBody {
+    pub fn new(position: Vector3<f32>, radius: f32, mass: f32) -> RigidBody {
+        RigidBody {
+            position,
+            velocity: Vector3::zero(),
+            radius,
+            mass,
+            restitution: 0.0,
+            friction: 0.5,
+            damping: 0.0,
+            gravity_scale: 1.0,
+            time_scale: None,
+            collision_group: 0,
+            force: Vector3::zero(),
+            grounded: false,
+            previous_position: position,
+            water: None,
+        }
+    }
+
+    pub fn with_restitution(mut self, restitution: f32) -> RigidBody {
+        self.restitution = restitution;
+        self
+    }
+
+    pub fn with_friction(mut self, friction: f32) -> RigidBody {
+        self.friction = friction;
+        self
+    }
+
+    pub fn with_damping(mut self, damping: f32) -> RigidBody {
+        self.damping = damping;
+        self
+    }
+
+    pub fn inv_mass(&self) -> f32 {
+        if self.mass > 0.0 {
+            1.0 / self.mass
+        } else {
+            0.0
+        }
+    }
+
+    /// Move the body without interpolating from its previous position
+    pub fn teleport(&mut self, position: Vector3<f32>) {
+        self.position = position;
+        self.previous_position = position;
+    }
+
+    /// Applied during the next tick
+    pub fn apply_force(&mut self, force: Vector3<f32>) {
+        self.force += force;
+    }
+
+    pub fn apply_impulse(&mut self, impulse: Vector3<f32>) {
+        self.velocity += impulse * self.inv_mass();
+    }
+
+    /// Whether the body rested on a walkable surface at last tick
+    pub fn is_grounded(&self) -> bool {
+        self.grounded
+    }
+
+    /// The water volume containing the center of the body
+    pub fn water(&self) -> Option<WaterHandle> {
+        self.water
+    }
+}
+
+/// How bodies are placed between two physics ticks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interpolation {
+    /// At their last simulated position, stutters when the tick rate is below the frame rate
+    None,
+    /// Between the two last ticks, smooth but one tick late
+    Interpolate,
+    /// Predicted from the velocity, no latency but may overshoot on impacts
+    Extrapolate,
+}
+
+/// Minimal physics world: spheres colliding with static level geometry and each other
+///
+/// The simulation runs at a fixed tick (`time_step`), whatever the frame rate. Each tick is
+/// split in `substeps`, so fast bodies move less than their radius per substep and do not
+/// tunnel through thin geometry, and contacts are solved `solver_iterations` times per substep.
+/// Between ticks, bodies are placed by `render_position` following `interpolation`.
+///
+/// ```ignore
+/// let terrain = HeightField::from_image(&heightmap, 1.0, 20.0)?;
+/// world.physics.add_collider(Collider::HeightField(terrain));
+///
+/// let level = TriMesh::from_mesh(&mesh, &go.transform.global())?;
+/// world.physics.add_collider(Collider::TriMesh(level));
+///
+/// let player = world.physics.add_body(RigidBody::new(vec3(0.0, 10.0, 0.0), 0.5, 70.0));
+/// ```
+pub struct PhysicsWorld {
+    pub gravity: Vector3<f32>,
+    /// Surfaces with a normal above this (cosine of the slope) are walkable
+    pub walkable_slope: f32,
+
+    /// Duration of a tick, in seconds
+    pub time_step: f32,
+    pub substeps: u32,
+    pub solver_iterations: u32,
+    /// Ticks run at most per `step`, the remaining time is dropped (e.g. after a hitch)
+    pub max_ticks: u32,
+    pub interpolation: Interpolation,
+    /// Speed of the simulated time, the ticks still run at real time so that slow motion
+    /// is as smooth and stable as normal speed
+    pub time_scale: f32,
+
+    accumulator: f32,
+    time: f32,
+    colliders: Vec<Option<Collider>>,
+    bodies: Vec<Option<RigidBody>>,
+    joints: Vec<Option<Joint>>,
+    waters: Vec<Option<WaterVolume>>,
+    released_waters: Rc<RefCell<Vec<WaterHandle>>>,
+    splashes: Vec<Splash>,
+}
+
+impl Default for PhysicsWorld {
+    fn default() -> PhysicsWorld {
+        PhysicsWorld {
+            gravity: Vector3::new(0.0, -9.81, 0.0),
+            walkable_slope: 0.7,
+            time_step: 1.0 / 60.0,
+            substeps: 1,
+            solver_iterations: 1,
+            max_ticks: 5,
+            interpolation: Interpolation::Interpolate,
+            time_scale: 1.0,
+            accumulator: 0.0,
+            time: 0.0,
+            colliders: Vec::new(),
+            bodies: Vec::new(),
+            joints: Vec::new(),
+            waters: Vec::new(),
+            released_waters: Default::default(),
+            splashes: Vec::new(),
+        }
+    }
+}
+
+fn insert<T>(slots: &mut Vec<Option<T>>, value: T) -> usize {
+    match slots.iter().position(|s| s.is_none()) {
+        Some(i) => {
+            slots[i] = Some(value);
+            i
+        }
+        None => {
+            slots.push(Some(value));
+            slots.len() - 1
+        }
+    }
+}
+
+impl PhysicsWorld {
+    pub fn new() -> PhysicsWorld {
+        Default::default()
+    }
+
+    pub fn add_collider(&mut self, collider: Collider) -> ColliderHandle {
+        ColliderHandle(insert(&mut self.colliders, collider))
+    }
+
+    pub fn remove_collider(&mut self, handle: ColliderHandle) -> Option<Collider> {
+        self.colliders.get_mut(handle.0).and_then(|c| c.take())
+    }
+
+    pub fn collider(&self, handle: ColliderHandle) -> Option<&Collider> {
+        self.colliders.get(handle.0).and_then(|c| c.as_ref())
+    }
+
+    pub fn collider_mut(&mut self, handle: ColliderHandle) -> Option<&mut Collider> {
+        self.colliders.get_mut(handle.0).and_then(|c| c.as_mut())
+    }
+
+    pub fn add_body(&mut self, body: RigidBody) -> BodyHandle {
+        BodyHandle(insert(&mut self.bodies, body))
+    }
+
+    pub fn remove_body(&mut self, handle: BodyHandle) -> Option<RigidBody> {
+        self.bodies.get_mut(handle.0).and_then(|b| b.take())
+    }
+
+    pub fn body(&self, handle: BodyHandle) -> Option<&RigidBody> {
+        self.bodies.get(handle.0).and_then(|b| b.as_ref())
+    }
+
+    pub fn body_mut(&mut self, handle: BodyHandle) -> Option<&mut RigidBody> {
+        self.bodies.get_mut(handle.0).and_then(|b| b.as_mut())
+    }
+
+    pub fn body_count(&self) -> usize {
+        self.bodies.iter().filter(|b| b.is_some()).count()
+    }
+
+    pub fn add_joint(&mut self, joint: Joint) -> JointHandle {
+        JointHandle(insert(&mut self.joints, joint))
+    }
+
+    pub fn remove_joint(&mut self, handle: JointHandle) -> Option<Joint> {
+        self.joints.get_mut(handle.0).and_then(|j| j.take())
+    }
+
+    pub fn joint(&self, handle: JointHandle) -> Option<&Joint> {
+        self.joints.get(handle.0).and_then(|j| j.as_ref())
+    }
+
+    pub fn add_water(&mut self, water: WaterVolume) -> WaterHandle {
+        WaterHandle(insert(&mut self.waters, water))
+    }
+
+    /// Add a water volume which lives as long as the returned guard, e.g. held by an actor
+    pub fn add_owned_water(&mut self, water: WaterVolume) -> WaterGuard {
+        WaterGuard {
+            handle: self.add_water(water),
+            released: self.released_waters.clone(),
+        }
+    }
+
+    pub fn remove_water(&mut self, handle: WaterHandle) -> Option<WaterVolume> {
+        self.waters.get_mut(handle.0).and_then(|w| w.take())
+    }
+
+    pub fn water(&self, handle: WaterHandle) -> Option<&WaterVolume> {
+        self.waters.get(handle.0).and_then(|w| w.as_ref())
+    }
+
+    pub fn water_mut(&mut self, handle: WaterHandle) -> Option<&mut WaterVolume> {
+        self.waters.get_mut(handle.0).and_then(|w| w.as_mut())
+    }
+
+    /// Simulated time, in seconds, e.g. to bind the waves of a water volume
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    /// Height of the water surface at (x, z), None outside of the water volumes
+    pub fn water_height(&self, x: f32, z: f32) -> Option<f32> {
+        let time = self.time;
+        self.waters
+            .iter()
+            .filter_map(|w| w.as_ref())
+            .filter(|w| w.covers(x, z))
+            .map(|w| w.surface_height(x, z, time))
+            .fold(None, |best, h| match best {
+                Some(b) if b >= h => Some(b),
+                _ => Some(h),
+            })
+    }
+
+    /// Bodies which crossed a water surface during the last `step`
+    pub fn splashes(&self) -> &[Splash] {
+        &self.splashes
+    }
+
+    /// Closest hit of a ray with the static colliders, `dir` must be normalized
+    pub fn raycast(
+        &self,
+        origin: Vector3<f32>,
+        dir: Vector3<f32>,
+        max_distance: f32,
+    ) -> Option<(ColliderHandle, RayHit)> {
+        let mut best: Option<(ColliderHandle, RayHit)> = None;
+
+        for (i, c) in self.colliders.iter().enumerate() {
+            let c = match *c {
+                Some(ref c) => c,
+                None => continue,
+            };
+
+            let limit = best.map(|b| b.1.distance).unwrap_or(max_distance);
+            if let Some(hit) = c.raycast(origin, dir, limit) {
+                best = Some((ColliderHandle(i), hit));
+            }
+        }
+
+        best
+    }
+
+    /// The vertex of the static colliders closest to `p`, within `max_distance`
+    pub fn nearest_vertex(&self, p: Vector3<f32>, max_distance: f32) -> Option<Vector3<f32>> {
+        self.colliders
+            .iter()
+            .filter_map(|c| c.as_ref())
+            .filter_map(|c| c.nearest_vertex(p, max_distance))
+            .fold(None, |best: Option<Vector3<f32>>, v| match best {
+                Some(b) if (b - p).magnitude2() <= (v - p).magnitude2() => Some(b),
+                _ => Some(v),
+            })
+    }
+
+    /// Contacts of a sphere with the static colliders
+    pub fn sphere_contacts(&self, center: Vector3<f32>, radius: f32) -> Vec<Contact> {
+        let mut out = Vec::new();
+        for c in self.colliders.iter() {
+            if let Some(ref c) = *c {
+                c.sphere_contacts(center, radius, &mut out);
+            }
+        }
+        out
+    }
+
+    /// Advance the simulation by `dt` seconds, running the ticks which are due
+    pub fn step(&mut self, dt: f32) {
+        self.splashes.clear();
+
+        let released: Vec<_> = self.released_waters.borrow_mut().drain(..).collect();
+        for handle in released.into_iter() {
+            self.remove_water(handle);
+        }
+
+        if dt <= 0.0 || self.time_step <= 0.0 {
+            return;
+        }
+
+        self.accumulator += dt;
+
+        let mut ticks = 0;
+        while self.accumulator >= self.time_step {
+            if ticks >= self.max_ticks {
+                self.accumulator = 0.0;
+                break;
+            }
+
+            let time_step = self.time_step;
+            self.tick(time_step);
+            self.accumulator -= time_step;
+            ticks += 1;
+        }
+    }
+
+    /// Run a single tick of `dt` seconds, regardless of the accumulated time
+    pub fn tick(&mut self, dt: f32) {
+        for body in self.bodies.iter_mut().filter_map(|b| b.as_mut()) {
+            body.previous_position = body.position;
+            body.grounded = false;
+        }
+
+        let substeps = self.substeps.max(1);
+        let h = dt / substeps as f32;
+
+        for _ in 0..substeps {
+            self.apply_water(h);
+            self.integrate(h);
+
+            for _ in 0..self.solver_iterations.max(1) {
+                self.solve_joints();
+                self.solve_bodies();
+                self.solve_colliders();
+            }
+        }
+
+        for body in self.bodies.iter_mut().filter_map(|b| b.as_mut()) {
+            body.force = Vector3::zero();
+        }
+
+        self.time += dt * self.time_scale;
+        self.detect_splashes();
+    }
+
+    /// Time since the last tick, in ticks
+    pub fn alpha(&self) -> f32 {
+        if self.time_step > 0.0 {
+            (self.accumulator / self.time_step).min(1.0)
+        } else {
+            0.0
+        }
+    }
+
+    /// Position of a body to render at this frame
+    pub fn render_position(&self, handle: BodyHandle) -> Option<Vector3<f32>> {
+        let alpha = self.alpha();
+        let time_step = self.time_step;
+        let time_scale = self.time_scale;
+        let mode = self.interpolation;
+
+        self.body(handle).map(|body| match mode {
+            Interpolation::None => body.position,
+            Interpolation::Interpolate => body.previous_position.lerp(body.position, alpha),
+            Interpolation::Extrapolate => {
+                let dt = alpha * time_step * body.time_scale.unwrap_or(time_scale);
+                body.position + body.velocity * dt
+            }
+        })
+    }
+
+    fn apply_water(&mut self, dt: f32) {
+        if self.waters.len() == 0 {
+            return;
+        }
+
+        let gravity = self.gravity;
+        let time = self.time;
+        let time_scale = self.time_scale;
+
+        for body in self.bodies.iter_mut().filter_map(|b| b.as_mut()) {
+            let inv_mass = body.inv_mass();
+            if inv_mass <= 0.0 {
+                continue;
+            }
+
+            let dt = dt * body.time_scale.unwrap_or(time_scale);
+
+            let full = 4.0 / 3.0 * ::std::f32::consts::PI * body.radius.powi(3);
+
+            for water in self.waters.iter().filter_map(|w| w.as_ref()) {
+                let volume = water.submerged_volume(body.position, body.radius, time);
+                if volume <= 0.0 {
+                    continue;
+                }
+
+                // Archimedes, the weight of the displaced water
+                let buoyancy = -gravity * (water.density * volume);
+                body.velocity += buoyancy * (inv_mass * dt);
+
+                let k = (water.drag * volume / full * dt).min(1.0);
+                body.velocity = body.velocity * (1.0 - k);
+            }
+        }
+    }
+
+    fn detect_splashes(&mut self) {
+        let time = self.time;
+
+        for (i, slot) in self.bodies.iter_mut().enumerate() {
+            let body = match *slot {
+                Some(ref mut body) => body,
+                None => continue,
+            };
+
+            // Bodies enter when their center goes under the surface, and exit when they are
+            // fully out, so floating bodies do not splash at each wave
+            let (x, z) = (body.position.x, body.position.z);
+            let was_in = body.water;
+            let inside = self.waters.iter().enumerate().find(|&(j, w)| match *w {
+                Some(ref w) => {
+                    let probe = if was_in == Some(WaterHandle(j)) {
+                        body.position.y - body.radius
+                    } else {
+                        body.position.y
+                    };
+
+                    w.covers(x, z) && probe < w.surface_height(x, z, time)
+                        && body.position.y > w.center.y - w.depth
+                }
+                None => false,
+            });
+            let current = inside.map(|(j, _)| WaterHandle(j));
+
+            if current == body.water {
+                continue;
+            }
+
+            let (kind, water) = match (body.water, current) {
+                (_, Some(water)) => (SplashKind::Enter, water),
+                (Some(water), None) => (SplashKind::Exit, water),
+                (None, None) => continue,
+            };
+
+            let surface = match self.waters[water.0] {
+                Some(ref w) => w.surface_height(x, z, time),
+                None => body.position.y,
+            };
+
+            self.splashes.push(Splash {
+                kind,
+                body: BodyHandle(i),
+                water,
+                position: Vector3::new(x, surface, z),
+                speed: body.velocity.y.abs(),
+            });
+            body.water = current;
+        }
+    }
+
+    fn integrate(&mut self, dt: f32) {
+        let gravity = self.gravity;
+        let time_scale = self.time_scale;
+
+        for body in self.bodies.iter_mut().filter_map(|b| b.as_mut()) {
+            let dt = dt * body.time_scale.unwrap_or(time_scale);
+            let inv_mass = body.inv_mass();
+            if inv_mass > 0.0 {
+                body.velocity += (gravity * body.gravity_scale + body.force * inv_mass) * dt;
+                body.velocity = body.velocity * (1.0 / (1.0 + body.damping * dt));
+            }
+
+            body.position += body.velocity * dt;
+        }
+    }
+
+    fn solve_bodies(&mut self) {
+        let n = self.bodies.len();
+
+        for i in 0..n {
+            for j in i + 1..n {
+                let (head, tail) = self.bodies.split_at_mut(j);
+                let (a, b) = match (head[i].as_mut(), tail[0].as_mut()) {
+                    (Some(a), Some(b)) => (a, b),
+                    _ => continue,
+                };
+
+                let (wa, wb) = (a.inv_mass(), b.inv_mass());
+                if wa + wb <= 0.0 {
+                    continue;
+                }
+
+                if a.collision_group != 0 && a.collision_group == b.collision_group {
+                    continue;
+                }
+
+                let d = b.position - a.position;
+                let dist = d.magnitude();
+                let r = a.radius + b.radius;
+                if dist >= r || dist < 1e-6 {
+                    continue;
+                }
+
+                let normal = d / dist;
+                let depth = r - dist;
+                a.position -= normal * (depth * wa / (wa + wb));
+                b.position += normal * (depth * wb / (wa + wb));
+
+                let vn = (b.velocity - a.velocity).dot(normal);
+                if vn < 0.0 {
+                    let e = a.restitution.max(b.restitution);
+                    let impulse = -(1.0 + e) * vn / (wa + wb);
+                    a.velocity -= normal * (impulse * wa);
+                    b.velocity += normal * (impulse * wb);
+                }
+            }
+        }
+    }
+
+    fn body_state(&self, handle: BodyHandle) -> Option<(Vector3<f32>, f32)> {
+        self.body(handle).map(|b| (b.position, b.inv_mass()))
+    }
+
+    fn solve_joints(&mut self) {
+        for i in 0..self.joints.len() {
+            let joint = match self.joints[i] {
+                Some(joint) => joint,
+                None => continue,
+            };
+
+            match joint {
+                Joint::Distance {
+                    a,
+                    b,
+                    length,
+                    stiffness,
+                } => {
+                    let (sa, sb) = match (self.body_state(a), self.body_state(b)) {
+                        (Some(sa), Some(sb)) => (sa, sb),
+                        _ => continue,
+                    };
+                    let (wa, wb) = (sa.1, sb.1);
+
+                    if let Some((da, db, normal)) = solve_distance(sa, sb, length, stiffness) {
+                        // Remove the relative velocity along the rod
+                        let va = self.body(a).map_or(Vector3::zero(), |b| b.velocity);
+                        let vb = self.body(b).map_or(Vector3::zero(), |b| b.velocity);
+                        let vn = (vb - va).dot(normal) * stiffness.max(0.0).min(1.0);
+
+                        if let Some(body) = self.body_mut(a) {
+                            body.position += da;
+                            body.velocity += normal * (vn * wa / (wa + wb));
+                        }
+                        if let Some(body) = self.body_mut(b) {
+                            body.position += db;
+                            body.velocity -= normal * (vn * wb / (wa + wb));
+                        }
+                    }
+                }
+                Joint::Cone {
+                    a,
+                    center,
+                    b,
+                    max_angle,
+                } => {
+                    let states = (self.body_state(a), self.body_state(center), self.body_state(b));
+                    let (sa, sc, sb) = match states {
+                        (Some(sa), Some(sc), Some(sb)) => (sa, sc, sb),
+                        _ => continue,
+                    };
+
+                    if let Some((da, db)) = solve_cone(sa, sc.0, sb, max_angle) {
+                        if let Some(body) = self.body_mut(a) {
+                            body.position += da;
+                        }
+                        if let Some(body) = self.body_mut(b) {
+                            body.position += db;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn solve_colliders(&mut self) {
+        let mut contacts = Vec::new();
+
+        for slot in 0..self.bodies.len() {
+            let (position, radius) = match self.bodies[slot] {
+                Some(ref b) if b.inv_mass() > 0.0 => (b.position, b.radius),
+                _ => continue,
+            };
+
+            contacts.clear();
+            for c in self.colliders.iter() {
+                if let Some(ref c) = *c {
+                    c.sphere_contacts(position, radius, &mut contacts);
+                }
+            }
+
+            let walkable_slope = self.walkable_slope;
+            let body = self.bodies[slot].as_mut().unwrap();
+
+            // Deepest first, later contacts may be resolved by the previous ones
+            contacts.sort_by(|a, b| {
+                b.depth
+                    .partial_cmp(&a.depth)
+                    .unwrap_or(::std::cmp::Ordering::Equal)
+            });
+
+            for contact in contacts.iter() {
+                let depth = radius - (body.position - contact.point).dot(contact.normal);
+                if depth <= 0.0 {
+                    continue;
+                }
+
+                body.position += contact.normal * depth;
+
+                let vn = body.velocity.dot(contact.normal);
+                if vn < 0.0 {
+                    // Coulomb friction, proportional to the normal impulse
+                    let tangent = body.velocity - contact.normal * vn;
+                    let speed = tangent.magnitude();
+                    let tangent = if speed > 1e-6 {
+                        tangent * ((speed + vn * body.friction).max(0.0) / speed)
+                    } else {
+                        tangent
+                    };
+                    body.velocity = tangent - contact.normal * (vn * body.restitution);
+                }
+
+                if contact.normal.y >= walkable_slope {
+                    body.grounded = true;
+                }
+            }
+        }
+    }
+}
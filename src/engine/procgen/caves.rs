@@ -0,0 +1,79 @@
+use super::{Random, Tile, Tilemap};
+
+/// Caves by cellular automata: random walls smoothed by the count of their neighbors
+#[derive(Debug, Clone)]
+pub struct Caves {
+    pub width: usize,
+    pub height: usize,
+    /// Ratio of walls of the initial noise
+    pub fill: f32,
+    pub steps: usize,
+    /// A floor with at least this count of wall neighbors becomes a wall
+    pub birth: usize,
+    /// A wall with at least this count of wall neighbors stays a wall
+    pub survival: usize,
+    /// Fill the caves not connected to the largest one
+    pub keep_largest: bool,
+    pub seed: u32,
+}
+
+impl Caves {
+    pub fn new(width: usize, height: usize) -> Caves {
+        Caves {
+            width,
+            height,
+            fill: 0.45,
+            steps: 5,
+            birth: 5,
+            survival: 4,
+            keep_largest: true,
+            seed: 1,
+        }
+    }
+
+    pub fn with_seed(mut self, seed: u32) -> Caves {
+        self.seed = seed;
+        self
+    }
+
+    pub fn generate(&self) -> Tilemap {
+        let mut rng = Random::new(self.seed);
+        let mut map = Tilemap::new(self.width, self.height, Tile::Floor);
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let border = x == 0 || y == 0 || x + 1 == self.width || y + 1 == self.height;
+                if border || rng.chance(self.fill) {
+                    map.set(x, y, Tile::Wall);
+                }
+            }
+        }
+
+        for _ in 0..self.steps {
+            let mut next = map.clone();
+
+            for y in 1..self.height.saturating_sub(1) {
+                for x in 1..self.width.saturating_sub(1) {
+                    let walls = map.neighbors(x, y, Tile::Wall, Tile::Wall);
+                    let wall = match map.get(x as i32, y as i32) {
+                        Some(Tile::Wall) => walls >= self.survival,
+                        _ => walls >= self.birth,
+                    };
+                    next.set(x, y, if wall { Tile::Wall } else { Tile::Floor });
+                }
+            }
+
+            map = next;
+        }
+
+        if self.keep_largest {
+            for region in map.walkable_regions().iter().skip(1) {
+                for &(x, y) in region.iter() {
+                    map.set(x, y, Tile::Wall);
+                }
+            }
+        }
+
+        map
+    }
+}
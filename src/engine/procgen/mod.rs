@@ -0,0 +1,73 @@
+//! Procedural level generation from a seed
+//!
+//! The generators output a `Tilemap`: `RoomsAndCorridors` for dungeons, `Caves` by cellular
+//! automata, and `Wfc` (wave function collapse) for levels assembled from tiles with matching
+//! edges. A tilemap turns into prefab placements, grouped by chunks for the levels loaded in
+//! parts, and its walkable regions give the cells reachable by the characters.
+//!
+//! The same seed always gives the same level.
+
+mod caves;
+mod rooms;
+mod tilemap;
+mod wfc;
+
+pub use self::caves::Caves;
+pub use self::rooms::{Room, RoomsAndCorridors};
+pub use self::tilemap::{group_by_chunk, Placement, Tile, TilePrefabs, Tilemap};
+pub use self::wfc::{Wfc, WfcTile};
+
+/// Xorshift generator, so a level is reproducible from its seed
+#[derive(Debug, Clone)]
+pub struct Random(u32);
+
+impl Random {
+    pub fn new(seed: u32) -> Random {
+        Random(seed.max(1))
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.0 = x;
+        x
+    }
+
+    /// In [0, 1)
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 / (1 << 24) as f32
+    }
+
+    pub fn range(&mut self, lo: f32, hi: f32) -> f32 {
+        lo + (hi - lo) * self.next_f32()
+    }
+
+    /// In [lo, hi)
+    pub fn range_usize(&mut self, lo: usize, hi: usize) -> usize {
+        if hi <= lo {
+            return lo;
+        }
+        lo + (self.next_u32() as usize) % (hi - lo)
+    }
+
+    pub fn chance(&mut self, p: f32) -> bool {
+        self.next_f32() < p
+    }
+
+    /// Index picked with a probability proportional to its weight
+    pub fn pick_weighted(&mut self, weights: &[f32]) -> usize {
+        let total: f32 = weights.iter().sum();
+        let mut r = self.next_f32() * total;
+
+        for (i, w) in weights.iter().enumerate() {
+            if r < *w {
+                return i;
+            }
+            r -= *w;
+        }
+
+        weights.len().saturating_sub(1)
+    }
+}
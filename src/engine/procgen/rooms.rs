@@ -0,0 +1,182 @@
+use super::{Random, Tile, Tilemap};
+
+/// A rectangle of floor, in cells
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Room {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Room {
+    pub fn center(&self) -> (usize, usize) {
+        (self.x + self.width / 2, self.y + self.height / 2)
+    }
+
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        x >= self.x && y >= self.y && x < self.x + self.width && y < self.y + self.height
+    }
+
+    /// The rooms overlap, or are closer than `margin` cells
+    pub fn intersects(&self, other: &Room, margin: usize) -> bool {
+        self.x < other.x + other.width + margin && other.x < self.x + self.width + margin
+            && self.y < other.y + other.height + margin
+            && other.y < self.y + self.height + margin
+    }
+}
+
+/// Rectangular rooms linked by corridors, each room to the previous one so all are connected.
+/// Floors are surrounded by walls, corridors open in the rooms by doors.
+#[derive(Debug, Clone)]
+pub struct RoomsAndCorridors {
+    pub width: usize,
+    pub height: usize,
+    pub max_rooms: usize,
+    /// Smallest and largest side of the rooms, in cells
+    pub room_size: (usize, usize),
+    /// Extra corridors between random rooms, making loops, as a ratio of the room count
+    pub loops: f32,
+    pub doors: bool,
+    pub seed: u32,
+}
+
+impl RoomsAndCorridors {
+    pub fn new(width: usize, height: usize) -> RoomsAndCorridors {
+        RoomsAndCorridors {
+            width,
+            height,
+            max_rooms: 12,
+            room_size: (4, 10),
+            loops: 0.1,
+            doors: true,
+            seed: 1,
+        }
+    }
+
+    pub fn with_seed(mut self, seed: u32) -> RoomsAndCorridors {
+        self.seed = seed;
+        self
+    }
+
+    pub fn generate(&self) -> (Tilemap, Vec<Room>) {
+        let mut rng = Random::new(self.seed);
+        let mut map = Tilemap::new(self.width, self.height, Tile::Empty);
+        let mut rooms: Vec<Room> = Vec::new();
+
+        let (min_size, max_size) = (self.room_size.0.max(1), self.room_size.1.max(1));
+
+        // A few attempts per room, for the overlapping ones
+        for _ in 0..self.max_rooms * 4 {
+            if rooms.len() >= self.max_rooms {
+                break;
+            }
+
+            let width = rng.range_usize(min_size, max_size + 1);
+            let height = rng.range_usize(min_size, max_size + 1);
+            if width + 2 > self.width || height + 2 > self.height {
+                continue;
+            }
+
+            // One cell kept for the walls around
+            let room = Room {
+                x: rng.range_usize(1, self.width - width),
+                y: rng.range_usize(1, self.height - height),
+                width,
+                height,
+            };
+            if rooms.iter().any(|r| r.intersects(&room, 2)) {
+                continue;
+            }
+
+            for y in room.y..room.y + room.height {
+                for x in room.x..room.x + room.width {
+                    map.set(x, y, Tile::Floor);
+                }
+            }
+            rooms.push(room);
+        }
+
+        let mut corridors = Vec::new();
+        for i in 1..rooms.len() {
+            let (a, b) = (rooms[i - 1].center(), rooms[i].center());
+            corridors.extend(carve_corridor(&mut map, a, b, rng.chance(0.5)));
+        }
+
+        let extra = (rooms.len() as f32 * self.loops).round() as usize;
+        if rooms.len() > 2 {
+            for _ in 0..extra {
+                let a = rooms[rng.range_usize(0, rooms.len())].center();
+                let b = rooms[rng.range_usize(0, rooms.len())].center();
+                corridors.extend(carve_corridor(&mut map, a, b, rng.chance(0.5)));
+            }
+        }
+
+        add_walls(&mut map);
+
+        if self.doors {
+            for &(x, y) in corridors.iter() {
+                if is_doorway(&map, &rooms, x, y) {
+                    map.set(x, y, Tile::Door);
+                }
+            }
+        }
+
+        (map, rooms)
+    }
+}
+
+/// L-shaped corridor, returns the cells carved outside of the rooms
+fn carve_corridor(
+    map: &mut Tilemap,
+    a: (usize, usize),
+    b: (usize, usize),
+    horizontal_first: bool,
+) -> Vec<(usize, usize)> {
+    let corner = if horizontal_first { (b.0, a.1) } else { (a.0, b.1) };
+    let mut carved = Vec::new();
+
+    for &(from, to) in [(a, corner), (corner, b)].iter() {
+        let (x0, x1) = (from.0.min(to.0), from.0.max(to.0));
+        let (y0, y1) = (from.1.min(to.1), from.1.max(to.1));
+
+        for y in y0..y1 + 1 {
+            for x in x0..x1 + 1 {
+                if map.get(x as i32, y as i32) == Some(Tile::Empty) {
+                    map.set(x, y, Tile::Floor);
+                    carved.push((x, y));
+                }
+            }
+        }
+    }
+
+    carved
+}
+
+/// Empty cells next to a floor become walls
+fn add_walls(map: &mut Tilemap) {
+    for y in 0..map.height {
+        for x in 0..map.width {
+            if map.get(x as i32, y as i32) == Some(Tile::Empty)
+                && map.neighbors(x, y, Tile::Floor, Tile::Empty) > 0
+            {
+                map.set(x, y, Tile::Wall);
+            }
+        }
+    }
+}
+
+/// A corridor cell in the wall ring of a room, between two walls
+fn is_doorway(map: &Tilemap, rooms: &[Room], x: usize, y: usize) -> bool {
+    let on_ring = rooms.iter().any(|r| {
+        let inside_x = x + 1 >= r.x && x <= r.x + r.width;
+        let inside_y = y + 1 >= r.y && y <= r.y + r.height;
+        inside_x && inside_y && !r.contains(x, y)
+    });
+    if !on_ring {
+        return false;
+    }
+
+    let wall = |dx: i32, dy: i32| map.get(x as i32 + dx, y as i32 + dy) == Some(Tile::Wall);
+    (wall(-1, 0) && wall(1, 0)) || (wall(0, -1) && wall(0, 1))
+}
@@ -0,0 +1,216 @@
+use math::*;
+
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+pub enum Tile {
+    Empty,
+    Wall,
+    Floor,
+    Door,
+    /// Tiles of the game, e.g. solved by `Wfc`
+    Custom(u16),
+}
+
+impl Tile {
+    pub fn is_walkable(&self) -> bool {
+        match *self {
+            Tile::Floor | Tile::Door => true,
+            _ => false,
+        }
+    }
+
+    fn to_char(&self) -> char {
+        match *self {
+            Tile::Empty => ' ',
+            Tile::Wall => '#',
+            Tile::Floor => '.',
+            Tile::Door => '+',
+            Tile::Custom(i) => ::std::char::from_digit(i as u32 % 36, 36).unwrap_or('?'),
+        }
+    }
+}
+
+/// Prefab name of each tile, the tiles without prefab are not placed
+pub type TilePrefabs = BTreeMap<Tile, String>;
+
+/// A prefab to instantiate in the level
+#[derive(Debug, Clone, PartialEq)]
+pub struct Placement {
+    pub prefab: String,
+    pub cell: (usize, usize),
+    /// Center of the cell, the tilemap is on the XZ plane with y up
+    pub position: Vector3<f32>,
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct Tilemap {
+    pub width: usize,
+    pub height: usize,
+    tiles: Vec<Tile>,
+}
+
+impl Tilemap {
+    pub fn new(width: usize, height: usize, fill: Tile) -> Tilemap {
+        Tilemap {
+            width,
+            height,
+            tiles: vec![fill; width * height],
+        }
+    }
+
+    pub fn in_bounds(&self, x: i32, y: i32) -> bool {
+        x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height
+    }
+
+    /// `None` out of the map
+    pub fn get(&self, x: i32, y: i32) -> Option<Tile> {
+        if !self.in_bounds(x, y) {
+            return None;
+        }
+        Some(self.tiles[y as usize * self.width + x as usize])
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, tile: Tile) {
+        if x < self.width && y < self.height {
+            self.tiles[y * self.width + x] = tile;
+        }
+    }
+
+    pub fn tiles(&self) -> &[Tile] {
+        &self.tiles
+    }
+
+    pub fn count(&self, tile: Tile) -> usize {
+        self.tiles.iter().filter(|t| **t == tile).count()
+    }
+
+    /// Count of the 8 neighbors which are `tile`, the cells out of the map count as `outside`
+    pub fn neighbors(&self, x: usize, y: usize, tile: Tile, outside: Tile) -> usize {
+        let mut n = 0;
+        for dy in -1..2 {
+            for dx in -1..2 {
+                if dx == 0 && dy == 0 {
+                    continue;
+                }
+
+                let t = self.get(x as i32 + dx, y as i32 + dy).unwrap_or(outside);
+                if t == tile {
+                    n += 1;
+                }
+            }
+        }
+        n
+    }
+
+    /// Walkable cells reachable from `start`, by the 4 directions
+    pub fn flood_fill(&self, start: (usize, usize)) -> Vec<(usize, usize)> {
+        let mut visited = vec![false; self.tiles.len()];
+        self.fill_region(start, &mut visited)
+    }
+
+    /// Sets of connected walkable cells, the largest first
+    pub fn walkable_regions(&self) -> Vec<Vec<(usize, usize)>> {
+        let mut visited = vec![false; self.tiles.len()];
+        let mut regions = Vec::new();
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if !visited[y * self.width + x] && self.tiles[y * self.width + x].is_walkable() {
+                    regions.push(self.fill_region((x, y), &mut visited));
+                }
+            }
+        }
+
+        regions.sort_by(|a, b| b.len().cmp(&a.len()));
+        regions
+    }
+
+    fn fill_region(&self, start: (usize, usize), visited: &mut Vec<bool>) -> Vec<(usize, usize)> {
+        let mut region = Vec::new();
+        let mut queue = VecDeque::new();
+
+        let walkable = |x: i32, y: i32| self.get(x, y).map_or(false, |t| t.is_walkable());
+        if !walkable(start.0 as i32, start.1 as i32) {
+            return region;
+        }
+
+        visited[start.1 * self.width + start.0] = true;
+        queue.push_back(start);
+
+        while let Some((x, y)) = queue.pop_front() {
+            region.push((x, y));
+
+            for &(dx, dy) in [(1, 0), (-1, 0), (0, 1), (0, -1)].iter() {
+                let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+                if !walkable(nx, ny) {
+                    continue;
+                }
+
+                let i = ny as usize * self.width + nx as usize;
+                if !visited[i] {
+                    visited[i] = true;
+                    queue.push_back((nx as usize, ny as usize));
+                }
+            }
+        }
+
+        region
+    }
+
+    /// Prefabs of the tiles, for cells of `cell_size` world units
+    pub fn placements(&self, cell_size: f32, prefabs: &TilePrefabs) -> Vec<Placement> {
+        let mut result = Vec::new();
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if let Some(prefab) = prefabs.get(&self.tiles[y * self.width + x]) {
+                    result.push(Placement {
+                        prefab: prefab.clone(),
+                        cell: (x, y),
+                        position: Vector3::new(
+                            (x as f32 + 0.5) * cell_size,
+                            0.0,
+                            (y as f32 + 0.5) * cell_size,
+                        ),
+                    });
+                }
+            }
+        }
+
+        result
+    }
+}
+
+/// Placements by chunk of `chunk_cells` x `chunk_cells` cells, to load the level in parts
+pub fn group_by_chunk(
+    placements: &[Placement],
+    chunk_cells: usize,
+) -> BTreeMap<(usize, usize), Vec<Placement>> {
+    let chunk_cells = chunk_cells.max(1);
+    let mut chunks = BTreeMap::new();
+
+    for p in placements.iter() {
+        chunks
+            .entry((p.cell.0 / chunk_cells, p.cell.1 / chunk_cells))
+            .or_insert_with(Vec::new)
+            .push(p.clone());
+    }
+
+    chunks
+}
+
+impl fmt::Debug for Tilemap {
+    /// One line per row, '#' walls, '.' floors and '+' doors
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for y in 0..self.height {
+            let row: String = self.tiles[y * self.width..(y + 1) * self.width]
+                .iter()
+                .map(|t| t.to_char())
+                .collect();
+            writeln!(f, "{}", row)?;
+        }
+        Ok(())
+    }
+}
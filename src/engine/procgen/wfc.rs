@@ -0,0 +1,202 @@
+use super::{Random, Tile, Tilemap};
+
+/// A tile of the wave function collapse, with the sockets of its edges: two tiles may be
+/// neighbors when their facing edges have the same socket
+#[derive(Debug, Clone, PartialEq)]
+pub struct WfcTile {
+    pub tile: Tile,
+    /// Sockets of the up, right, down and left edges, up is toward y - 1
+    pub edges: [u32; 4],
+    /// Relative frequency of the tile
+    pub weight: f32,
+}
+
+/// Wave function collapse solver: the cell with the fewest possible tiles is set to a random
+/// one of them, and the tiles which cannot neighbor it are removed around, until all cells
+/// are set. Contradictions restart with the next seed.
+#[derive(Debug, Clone)]
+pub struct Wfc {
+    pub width: usize,
+    pub height: usize,
+    pub max_attempts: usize,
+    pub seed: u32,
+    tiles: Vec<WfcTile>,
+    /// Cells set before solving, (x, y, tile index)
+    fixed: Vec<(usize, usize, usize)>,
+}
+
+const DIRS: [(i32, i32); 4] = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+
+impl Wfc {
+    pub fn new(width: usize, height: usize) -> Wfc {
+        Wfc {
+            width,
+            height,
+            max_attempts: 10,
+            seed: 1,
+            tiles: Vec::new(),
+            fixed: Vec::new(),
+        }
+    }
+
+    pub fn with_seed(mut self, seed: u32) -> Wfc {
+        self.seed = seed;
+        self
+    }
+
+    /// Add a tile, returns its index
+    pub fn add_tile(&mut self, tile: Tile, edges: [u32; 4], weight: f32) -> usize {
+        self.tiles.push(WfcTile {
+            tile,
+            edges,
+            weight: weight.max(0.0),
+        });
+        self.tiles.len() - 1
+    }
+
+    pub fn tiles(&self) -> &[WfcTile] {
+        &self.tiles
+    }
+
+    /// Force the tile of a cell, e.g. the entrance of the level
+    pub fn fix(&mut self, x: usize, y: usize, tile: usize) {
+        self.fixed.retain(|&(fx, fy, _)| fx != x || fy != y);
+        self.fixed.push((x, y, tile));
+    }
+
+    /// Tile `b` may be at direction `dir` of tile `a`
+    fn compatible(&self, a: usize, b: usize, dir: usize) -> bool {
+        self.tiles[a].edges[dir] == self.tiles[b].edges[(dir + 2) % 4]
+    }
+
+    /// The solved tilemap, `None` when every attempt met a contradiction
+    pub fn solve(&self) -> Option<Tilemap> {
+        if self.tiles.is_empty() {
+            return None;
+        }
+
+        for attempt in 0..self.max_attempts.max(1) {
+            let seed = self.seed.wrapping_add(attempt as u32);
+            if let Some(cells) = self.attempt(&mut Random::new(seed)) {
+                let mut map = Tilemap::new(self.width, self.height, Tile::Empty);
+                for (i, &t) in cells.iter().enumerate() {
+                    map.set(i % self.width, i / self.width, self.tiles[t].tile);
+                }
+                return Some(map);
+            }
+        }
+
+        None
+    }
+
+    /// Tile index of each cell
+    fn attempt(&self, rng: &mut Random) -> Option<Vec<usize>> {
+        let n = self.tiles.len();
+        let mut wave = vec![vec![true; n]; self.width * self.height];
+
+        for &(x, y, t) in self.fixed.iter() {
+            if x < self.width && y < self.height && t < n {
+                let cell = y * self.width + x;
+                for (i, possible) in wave[cell].iter_mut().enumerate() {
+                    *possible = i == t;
+                }
+                if !self.propagate(&mut wave, cell) {
+                    return None;
+                }
+            }
+        }
+
+        loop {
+            // Fewest possibilities, ties broken at random
+            let mut best = None;
+            let mut best_count = usize::max_value();
+            let mut ties = 0;
+            for (cell, options) in wave.iter().enumerate() {
+                let count = options.iter().filter(|p| **p).count();
+                if count == 0 {
+                    return None;
+                }
+                if count == 1 {
+                    continue;
+                }
+
+                if count < best_count {
+                    best = Some(cell);
+                    best_count = count;
+                    ties = 1;
+                } else if count == best_count {
+                    ties += 1;
+                    if rng.range_usize(0, ties) == 0 {
+                        best = Some(cell);
+                    }
+                }
+            }
+
+            let cell = match best {
+                Some(cell) => cell,
+                None => break,
+            };
+
+            let weights: Vec<f32> = wave[cell]
+                .iter()
+                .enumerate()
+                .map(|(i, p)| if *p { self.tiles[i].weight } else { 0.0 })
+                .collect();
+            let picked = rng.pick_weighted(&weights);
+            for (i, possible) in wave[cell].iter_mut().enumerate() {
+                *possible = i == picked;
+            }
+
+            if !self.propagate(&mut wave, cell) {
+                return None;
+            }
+        }
+
+        Some(
+            wave.iter()
+                .map(|options| options.iter().position(|p| *p).unwrap_or(0))
+                .collect(),
+        )
+    }
+
+    /// Remove the tiles incompatible with the changed cell, and so on, false on contradiction
+    fn propagate(&self, wave: &mut Vec<Vec<bool>>, start: usize) -> bool {
+        let mut stack = vec![start];
+
+        while let Some(cell) = stack.pop() {
+            let (x, y) = ((cell % self.width) as i32, (cell / self.width) as i32);
+
+            for (dir, &(dx, dy)) in DIRS.iter().enumerate() {
+                let (nx, ny) = (x + dx, y + dy);
+                if nx < 0 || ny < 0 || nx as usize >= self.width || ny as usize >= self.height {
+                    continue;
+                }
+
+                let neighbor = ny as usize * self.width + nx as usize;
+                let mut changed = false;
+
+                for b in 0..self.tiles.len() {
+                    if !wave[neighbor][b] {
+                        continue;
+                    }
+
+                    let supported = (0..self.tiles.len())
+                        .any(|a| wave[cell][a] && self.compatible(a, b, dir));
+                    if !supported {
+                        wave[neighbor][b] = false;
+                        changed = true;
+                    }
+                }
+
+                if changed {
+                    if !wave[neighbor].iter().any(|p| *p) {
+                        return false;
+                    }
+                    stack.push(neighbor);
+                }
+            }
+        }
+
+        true
+    }
+}
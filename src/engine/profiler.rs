@@ -0,0 +1,262 @@
+//! Profiler markers
+//!
+//! Scopes are emitted as `performance.mark/measure` in web, so they show in the "Timings"
+//! track of the browser performance tools, and as `tracing` spans in native (with the
+//! `profile_tracing` feature) or flame graph spans (with `flame_it`).
+//! Nested scopes are named by their path, e.g. `frame/step/actors`.
+//!
+//! While recording, the durations of the scopes are kept too, until `take_recorded`.
+//!
+//! ```ignore
+//! profiler::set_enabled(true);
+//! {
+//!     let _scope = profiler::scope("pathfinding");
+//!     // ...
+//! }
+//! ```
+
+use std::cell::RefCell;
+use uni_app::now;
+
+/// A finished scope, times are in seconds
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScopeTiming {
+    pub path: String,
+    /// Nesting level, 0 for outermost scopes
+    pub depth: usize,
+    pub start: f64,
+    pub duration: f64,
+}
+
+#[derive(Default)]
+struct ProfilerState {
+    enabled: bool,
+    recording: bool,
+    stack: Vec<(String, f64)>,
+    recorded: Vec<ScopeTiming>,
+    /// Paths of the scopes and names of the instant marks emitted in the current outermost
+    /// scope, cleared from the browser buffers at its end
+    emitted_scopes: Vec<String>,
+    emitted_marks: Vec<String>,
+}
+
+fn push_unique(names: &mut Vec<String>, name: &str) {
+    if !names.iter().any(|n| n == name) {
+        names.push(name.to_owned());
+    }
+}
+
+thread_local!(static STATE: RefCell<ProfilerState> = RefCell::new(ProfilerState::default()));
+
+/// Markers are not emitted until the profiler is enabled
+pub fn set_enabled(enabled: bool) {
+    STATE.with(|s| s.borrow_mut().enabled = enabled);
+}
+
+pub fn is_enabled() -> bool {
+    STATE.with(|s| s.borrow().enabled)
+}
+
+/// Keep the timings of the scopes, independently of emitting markers
+pub fn set_recording(recording: bool) {
+    STATE.with(|s| {
+        let mut s = s.borrow_mut();
+        s.recording = recording;
+        if !recording {
+            s.recorded.clear();
+        }
+    });
+}
+
+pub fn is_recording() -> bool {
+    STATE.with(|s| s.borrow().recording)
+}
+
+/// The scopes finished since the last call, in order of completion
+pub fn take_recorded() -> Vec<ScopeTiming> {
+    STATE.with(|s| s.borrow_mut().recorded.drain(0..).collect())
+}
+
+/// Start a scope, which ends when the returned guard is dropped
+pub fn scope(name: &str) -> ProfileScope {
+    let path = STATE.with(|s| {
+        let mut s = s.borrow_mut();
+        if !s.enabled && !s.recording {
+            return None;
+        }
+
+        let path = match s.stack.last() {
+            Some(parent) => format!("{}/{}", parent.0, name),
+            None => name.to_owned(),
+        };
+        s.stack.push((path.clone(), now()));
+        Some((path, s.enabled))
+    });
+
+    match path {
+        Some((path, emit)) => ProfileScope::begin(name, path, emit),
+        None => ProfileScope::inactive(),
+    }
+}
+
+/// Emit a single instant marker
+pub fn mark(name: &str) {
+    if is_enabled() {
+        backend::mark(name);
+        STATE.with(|s| push_unique(&mut s.borrow_mut().emitted_marks, name));
+    }
+}
+
+pub struct ProfileScope {
+    path: Option<String>,
+    _span: Option<backend::Span>,
+}
+
+impl ProfileScope {
+    fn begin(name: &str, path: String, emit: bool) -> ProfileScope {
+        let span = if emit {
+            Some(backend::begin(name, &path))
+        } else {
+            None
+        };
+
+        ProfileScope {
+            path: Some(path),
+            _span: span,
+        }
+    }
+
+    fn inactive() -> ProfileScope {
+        ProfileScope {
+            path: None,
+            _span: None,
+        }
+    }
+}
+
+impl Drop for ProfileScope {
+    fn drop(&mut self) {
+        if let Some(path) = self.path.take() {
+            let emitted = self._span.is_some();
+            let finished = STATE.with(|s| {
+                let mut s = s.borrow_mut();
+                let start = s.stack.pop().map(|e| e.1).unwrap_or(0.0);
+
+                if s.recording {
+                    let depth = s.stack.len();
+                    s.recorded.push(ScopeTiming {
+                        path: path.clone(),
+                        depth,
+                        start,
+                        duration: now() - start,
+                    });
+                }
+
+                if emitted {
+                    push_unique(&mut s.emitted_scopes, &path);
+                }
+
+                // Recorded by the devtools already, avoid growing the buffers
+                if s.stack.is_empty() {
+                    let scopes: Vec<_> = s.emitted_scopes.drain(0..).collect();
+                    let marks: Vec<_> = s.emitted_marks.drain(0..).collect();
+                    Some((scopes, marks))
+                } else {
+                    None
+                }
+            });
+
+            if emitted {
+                backend::end(&path);
+            }
+            if let Some((scopes, marks)) = finished {
+                backend::clear(scopes, marks);
+            }
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod backend {
+    pub struct Span;
+
+    pub fn begin(_name: &str, path: &str) -> Span {
+        js! { @(no_return)
+            if (window.performance && performance.mark) {
+                performance.mark(@{path} + ":start");
+            }
+        }
+        Span
+    }
+
+    pub fn end(path: &str) {
+        js! { @(no_return)
+            if (window.performance && performance.measure) {
+                var path = @{path};
+                performance.measure(path, path + ":start");
+            }
+        }
+    }
+
+    /// Clear the entries of the profiler only, not the ones of the application
+    pub fn clear(scopes: Vec<String>, marks: Vec<String>) {
+        if scopes.is_empty() && marks.is_empty() {
+            return;
+        }
+
+        js! { @(no_return)
+            if (window.performance && performance.clearMarks) {
+                var scopes = @{scopes};
+                var marks = @{marks};
+                for (var i = 0; i < scopes.length; i++) {
+                    performance.clearMarks(scopes[i] + ":start");
+                    performance.clearMeasures(scopes[i]);
+                }
+                for (var i = 0; i < marks.length; i++) {
+                    performance.clearMarks(marks[i]);
+                }
+            }
+        }
+    }
+
+    pub fn mark(name: &str) {
+        js! { @(no_return)
+            if (window.performance && performance.mark) {
+                performance.mark(@{name});
+            }
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod backend {
+    pub struct Span {
+        #[cfg(feature = "profile_tracing")]
+        _span: ::tracing::span::EnteredSpan,
+        #[cfg(feature = "flame_it")]
+        _flame: ::flame::SpanGuard,
+    }
+
+    pub fn begin(name: &str, path: &str) -> Span {
+        let _ = path;
+
+        Span {
+            #[cfg(feature = "profile_tracing")]
+            _span: span!(::tracing::Level::INFO, "scope", scope = name, path = path).entered(),
+            #[cfg(feature = "flame_it")]
+            _flame: ::flame::start_guard(name.to_owned()),
+        }
+    }
+
+    pub fn end(_path: &str) {}
+
+    pub fn clear(_scopes: Vec<String>, _marks: Vec<String>) {}
+
+    pub fn mark(name: &str) {
+        #[cfg(feature = "profile_tracing")]
+        event!(::tracing::Level::INFO, mark = name);
+
+        #[cfg(not(feature = "profile_tracing"))]
+        let _ = name;
+    }
+}
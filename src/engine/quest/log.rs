@@ -0,0 +1,292 @@
+use engine::asset::{AssetError, AssetSystem, Resource};
+
+use std::collections::{HashMap, VecDeque};
+
+use super::quest::{QuestDatabase, QuestDef};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub enum QuestStatus {
+    /// Some prerequisites are not completed yet
+    Locked,
+    Available,
+    Active,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum QuestEvent {
+    Available(String),
+    Started(String),
+    Progress {
+        quest: String,
+        objective: String,
+        count: u32,
+        target: u32,
+    },
+    ObjectiveCompleted {
+        quest: String,
+        objective: String,
+    },
+    Completed(String),
+    Failed(String),
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct QuestState {
+    pub status: QuestStatus,
+    pub counters: HashMap<String, u32>,
+}
+
+/// Serializable snapshot of all quests progress, used for saving and loading games.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct QuestLogState {
+    pub quests: HashMap<String, QuestState>,
+}
+
+/// Runtime quest tracking.
+///
+/// Gameplay code reports progress by `notify` (or `progress` for a specific quest),
+/// and UI can query the status or consume the events by `poll_events`.
+#[derive(Default)]
+pub struct QuestLog {
+    defs: Vec<QuestDef>,
+    states: HashMap<String, QuestState>,
+    events: VecDeque<QuestEvent>,
+
+    pending: Vec<Resource<QuestDatabase>>,
+}
+
+impl QuestLog {
+    pub fn new() -> QuestLog {
+        Default::default()
+    }
+
+    /// Load quests from a json quest database, they will be added when the file is ready
+    pub fn load(&mut self, asys: &AssetSystem, filename: &str) {
+        self.pending.push(QuestDatabase::load(asys, filename));
+    }
+
+    pub fn is_loading(&self) -> bool {
+        self.pending.len() > 0
+    }
+
+    pub fn add_quests(&mut self, db: QuestDatabase) {
+        for def in db.quests.into_iter() {
+            self.states
+                .entry(def.id.clone())
+                .or_insert_with(|| QuestState {
+                    status: QuestStatus::Locked,
+                    counters: HashMap::new(),
+                });
+
+            self.defs.retain(|d| d.id != def.id);
+            self.defs.push(def);
+        }
+
+        self.update_availability();
+    }
+
+    pub fn step(&mut self) {
+        let pending: Vec<_> = self.pending.drain(0..).collect();
+
+        for res in pending.into_iter() {
+            match res.try_into() {
+                Ok(db) => self.add_quests(db),
+                Err(AssetError::NotReady) => self.pending.push(res),
+                Err(e) => println!("Fail to load quests, reason: {:?}", e),
+            }
+        }
+    }
+
+    pub fn quest(&self, id: &str) -> Option<&QuestDef> {
+        self.defs.iter().find(|d| d.id == id)
+    }
+
+    pub fn quests(&self) -> &[QuestDef] {
+        &self.defs
+    }
+
+    pub fn status(&self, id: &str) -> Option<QuestStatus> {
+        self.states.get(id).map(|s| s.status)
+    }
+
+    pub fn is_active(&self, id: &str) -> bool {
+        self.status(id) == Some(QuestStatus::Active)
+    }
+
+    pub fn is_completed(&self, id: &str) -> bool {
+        self.status(id) == Some(QuestStatus::Completed)
+    }
+
+    pub fn with_status(&self, status: QuestStatus) -> Vec<&QuestDef> {
+        self.defs
+            .iter()
+            .filter(|d| self.status(&d.id) == Some(status))
+            .collect()
+    }
+
+    pub fn active_quests(&self) -> Vec<&QuestDef> {
+        self.with_status(QuestStatus::Active)
+    }
+
+    pub fn available_quests(&self) -> Vec<&QuestDef> {
+        self.with_status(QuestStatus::Available)
+    }
+
+    /// Return (count, target) of an objective
+    pub fn objective_progress(&self, quest: &str, objective: &str) -> Option<(u32, u32)> {
+        let target = self.quest(quest)?.objective(objective)?.target;
+        let count = self.states
+            .get(quest)
+            .and_then(|s| s.counters.get(objective).map(|c| *c))
+            .unwrap_or(0);
+
+        Some((count, target))
+    }
+
+    pub fn is_objective_completed(&self, quest: &str, objective: &str) -> bool {
+        self.objective_progress(quest, objective)
+            .map_or(false, |(count, target)| count >= target)
+    }
+
+    /// Start an available quest, return false if it cannot be started
+    pub fn start(&mut self, id: &str) -> bool {
+        if self.status(id) != Some(QuestStatus::Available) {
+            return false;
+        }
+
+        self.set_status(id, QuestStatus::Active);
+        self.events.push_back(QuestEvent::Started(id.to_owned()));
+
+        // An objective could already be completed by loaded counters
+        self.check_completion(id);
+        true
+    }
+
+    pub fn fail(&mut self, id: &str) {
+        if self.is_active(id) {
+            self.set_status(id, QuestStatus::Failed);
+            self.events.push_back(QuestEvent::Failed(id.to_owned()));
+        }
+    }
+
+    /// Add progress to an objective of an active quest
+    pub fn progress(&mut self, quest: &str, objective: &str, amount: u32) {
+        if let Some((count, _)) = self.objective_progress(quest, objective) {
+            self.set_progress(quest, objective, count.saturating_add(amount));
+        }
+    }
+
+    /// Set the counter of an objective of an active quest
+    pub fn set_progress(&mut self, quest: &str, objective: &str, count: u32) {
+        if !self.is_active(quest) {
+            return;
+        }
+
+        let (old, target) = match self.objective_progress(quest, objective) {
+            Some(p) => p,
+            None => return,
+        };
+
+        let count = count.min(target);
+        if count == old {
+            return;
+        }
+
+        self.states
+            .get_mut(quest)
+            .unwrap()
+            .counters
+            .insert(objective.to_owned(), count);
+
+        self.events.push_back(QuestEvent::Progress {
+            quest: quest.to_owned(),
+            objective: objective.to_owned(),
+            count,
+            target,
+        });
+
+        if count >= target {
+            self.events.push_back(QuestEvent::ObjectiveCompleted {
+                quest: quest.to_owned(),
+                objective: objective.to_owned(),
+            });
+        }
+
+        self.check_completion(quest);
+    }
+
+    /// Add progress to the objective with this id in all active quests
+    pub fn notify(&mut self, objective: &str, amount: u32) {
+        let quests: Vec<String> = self.active_quests()
+            .into_iter()
+            .filter(|q| q.objective(objective).is_some())
+            .map(|q| q.id.clone())
+            .collect();
+
+        for q in quests.iter() {
+            self.progress(q, objective, amount);
+        }
+    }
+
+    pub fn poll_events(&mut self) -> Vec<QuestEvent> {
+        self.events.drain(..).collect()
+    }
+
+    pub fn save_state(&self) -> QuestLogState {
+        QuestLogState {
+            quests: self.states.clone(),
+        }
+    }
+
+    /// Restore the progress from a saved state, no event will be raised
+    pub fn load_state(&mut self, state: &QuestLogState) {
+        for (id, s) in state.quests.iter() {
+            self.states.insert(id.clone(), s.clone());
+        }
+
+        self.update_availability();
+        self.events.clear();
+    }
+
+    fn set_status(&mut self, id: &str, status: QuestStatus) {
+        if let Some(s) = self.states.get_mut(id) {
+            s.status = status;
+        }
+    }
+
+    fn check_completion(&mut self, id: &str) {
+        let done = match self.quest(id) {
+            Some(def) => def.objectives
+                .iter()
+                .filter(|o| !o.optional)
+                .all(|o| self.is_objective_completed(id, &o.id)),
+            None => false,
+        };
+
+        if done && self.is_active(id) {
+            self.set_status(id, QuestStatus::Completed);
+            self.events.push_back(QuestEvent::Completed(id.to_owned()));
+            self.update_availability();
+        }
+    }
+
+    fn update_availability(&mut self) {
+        let unlocked: Vec<(String, bool)> = self.defs
+            .iter()
+            .filter(|d| self.status(&d.id) == Some(QuestStatus::Locked))
+            .filter(|d| d.prerequisites.iter().all(|p| self.is_completed(p)))
+            .map(|d| (d.id.clone(), d.auto_start))
+            .collect();
+
+        for (id, auto_start) in unlocked.into_iter() {
+            self.set_status(&id, QuestStatus::Available);
+            self.events.push_back(QuestEvent::Available(id.clone()));
+
+            if auto_start {
+                self.start(&id);
+            }
+        }
+    }
+}
@@ -0,0 +1,10 @@
+//! Quest and objective tracking
+//!
+//! Quests are defined in json assets (see `QuestDatabase`) and tracked at runtime by `QuestLog`.
+//! The progress can be saved and restored by `QuestLog::save_state` and `QuestLog::load_state`.
+
+mod log;
+mod quest;
+
+pub use self::log::{QuestEvent, QuestLog, QuestLogState, QuestState, QuestStatus};
+pub use self::quest::{ObjectiveDef, QuestDatabase, QuestDef};
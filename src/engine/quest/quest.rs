@@ -0,0 +1,75 @@
+use engine::asset::loader::{self, Loadable, Loader};
+use engine::asset::{AssetResult, AssetSystem, File, Resource};
+
+fn default_target() -> u32 {
+    1
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ObjectiveDef {
+    pub id: String,
+    /// Localization key (or raw text) of this objective
+    pub text: String,
+    /// The counter value to reach to complete this objective
+    #[serde(default = "default_target")]
+    pub target: u32,
+    /// Optional objectives are not required to complete the quest
+    #[serde(default)]
+    pub optional: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct QuestDef {
+    pub id: String,
+    /// Localization key (or raw text) of the title
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    /// Quests which must be completed before this quest can be started
+    #[serde(default)]
+    pub prerequisites: Vec<String>,
+    /// Start automatically when it becomes available
+    #[serde(default)]
+    pub auto_start: bool,
+    pub objectives: Vec<ObjectiveDef>,
+}
+
+impl QuestDef {
+    pub fn objective(&self, id: &str) -> Option<&ObjectiveDef> {
+        self.objectives.iter().find(|o| o.id == id)
+    }
+}
+
+/// Quest asset, a json file containing a list of quests :
+///
+/// ```json
+/// { "quests": [
+///     { "id": "wolves", "title": "quest.wolves", "objectives": [
+///         { "id": "kill_wolf", "text": "quest.wolves.kill", "target": 5 }
+///     ]},
+///     { "id": "report", "title": "quest.report", "prerequisites": ["wolves"],
+///       "auto_start": true, "objectives": [ { "id": "talk_mayor", "text": "quest.report.talk" } ] }
+/// ]}
+/// ```
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct QuestDatabase {
+    pub quests: Vec<QuestDef>,
+}
+
+impl QuestDatabase {
+    pub fn load(asys: &AssetSystem, filename: &str) -> Resource<QuestDatabase> {
+        loader::load_json(asys, filename)
+    }
+}
+
+pub struct QuestDatabaseLoader {}
+
+impl Loader<QuestDatabase> for QuestDatabaseLoader {
+    fn load<A>(_asys: A, mut file: Box<File>) -> AssetResult<QuestDatabase> {
+        loader::read_json(&mut file)
+    }
+}
+
+impl Loadable for QuestDatabase {
+    type Loader = QuestDatabaseLoader;
+}
@@ -1,4 +1,4 @@
-use engine::render::{RenderQueue, RenderTexture};
+use engine::render::{Exposure, RenderQueue, RenderTexture, TemporalAA};
 use math::*;
 use std::collections::BTreeSet;
 use std::rc::Rc;
@@ -46,12 +46,62 @@ pub struct Camera {
     pub rect: Option<((i32, i32), (u32, u32))>,
     pub znear: f32,
     pub zfar: f32,
+    /// Vertical field of view
+    pub fovy: Rad<f32>,
+
+    /// Only render one tile of the view split into (n x n) tiles, as (column, row, n)
+    /// where row 0 is the top. Used for capturing images larger than the screen.
+    pub tile: Option<(u32, u32, u32)>,
 
     pub included_render_queues: Option<BTreeSet<RenderQueue>>,
 
     eye: Point3<f32>,
 
     pub render_texture: Option<Rc<RenderTexture>>,
+
+    /// Exposure of the main camera output, `None` renders without tone mapping
+    pub exposure: Option<Exposure>,
+
+    /// World space plane (a, b, c, d) replacing the near plane, only the points with
+    /// `ax + by + cz + d >= 0` are rendered. Used to clip the objects behind a mirror.
+    /// It is also the `uClipPlane` uniform, the shaders including `unrust/cutout.glsl` clip
+    /// per fragment too, exactly at the plane.
+    pub clip_plane: Option<Vector4<f32>>,
+
+    /// Part of the depth buffer, from 0 to 1, the depth is remapped to
+    pub depth_range: Option<(f32, f32)>,
+
+    /// Separate projection of the `RenderQueue::Viewmodel` objects, `None` renders them
+    /// like the other objects
+    pub viewmodel: Option<Viewmodel>,
+
+    /// Temporal antialiasing of the main camera output, jitters the projection
+    pub taa: Option<TemporalAA>,
+}
+
+/// Rendering of the `RenderQueue::Viewmodel` objects, e.g. the weapon of a first person game.
+///
+/// The viewmodel uses the view of the camera with its own field of view and clip planes, and
+/// is drawn in the front part of the depth buffer so it does not clip into walls.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewmodel {
+    pub fovy: Rad<f32>,
+    pub znear: f32,
+    pub zfar: f32,
+    /// Part of the depth buffer used by the viewmodel, from 0 to 1.
+    /// Only the scene closer than `depth_range.1` can hide the viewmodel.
+    pub depth_range: (f32, f32),
+}
+
+impl Default for Viewmodel {
+    fn default() -> Viewmodel {
+        Viewmodel {
+            fovy: Rad(3.1415 / 3.0),
+            znear: 0.01,
+            zfar: 10.0,
+            depth_range: (0.0, 0.02),
+        }
+    }
 }
 
 impl Default for Camera {
@@ -81,6 +131,10 @@ impl Camera {
         extract_forward(&self.v)
     }
 
+    pub fn right(&self) -> Vector3<f32> {
+        extract_right(&self.v)
+    }
+
     pub fn lookat(&mut self, eye: &Point3<f32>, target: &Point3<f32>, up: &Vector3<f32>) {
         self.v = Matrix4::look_at(*eye, *target, *up);
         self.eye = *eye;
@@ -122,12 +176,113 @@ impl Camera {
 
         let aspect = self.calc_aspect(screen_size).max(0.001);
 
-        PerspectiveFov {
-            fovy: Rad(3.1415 / 4.0),
+        let p: Matrix4<f32> = PerspectiveFov {
+            fovy: self.fovy,
             aspect,
             near: self.znear,
             far: self.zfar,
-        }.into()
+        }.into();
+
+        let p = match self.clip_plane {
+            Some(plane) => self.oblique(p, plane),
+            None => p,
+        };
+
+        let p = match self.depth_range {
+            Some((near, far)) => {
+                // Scale the ndc z from [-1, 1] to [2 * near - 1, 2 * far - 1]
+                let mut p = p;
+                let row = p.row(2) * (far - near) + p.row(3) * (near + far - 1.0);
+                p.x.z = row.x;
+                p.y.z = row.y;
+                p.z.z = row.z;
+                p.w.z = row.w;
+                p
+            }
+            None => p,
+        };
+
+        match self.tile {
+            Some((col, row, n)) if n > 1 => {
+                // scale the tile region to fill the whole clip space
+                let n = n as f32;
+                let tx = n - 1.0 - 2.0 * col as f32;
+                let ty = 2.0 * row as f32 + 1.0 - n;
+
+                Matrix4::from_translation(vec3(tx, ty, 0.0))
+                    * Matrix4::from_nonuniform_scale(n, n, 1.0) * p
+            }
+            Some(_) => p,
+            None => match self.taa {
+                Some(ref taa) => {
+                    // Offset by a subpixel jitter, in clip space
+                    let (w, h) = self.rect.map_or(screen_size, |(_, size)| size);
+                    let j = taa.jitter();
+                    let tx = 2.0 * j.x / w.max(1) as f32;
+                    let ty = 2.0 * j.y / h.max(1) as f32;
+
+                    Matrix4::from_translation(vec3(tx, ty, 0.0)) * p
+                }
+                None => p,
+            },
+        }
+    }
+
+    /// Oblique near plane clipping, by Eric Lengyel
+    fn oblique(&self, mut p: Matrix4<f32>, plane: Vector4<f32>) -> Matrix4<f32> {
+        let c = match self.v.inverse_transform() {
+            Some(inv) => inv.transpose() * plane,
+            None => return p,
+        };
+
+        // The camera must be behind the plane
+        if c.w >= 0.0 {
+            return p;
+        }
+
+        let q = match p.inverse_transform() {
+            Some(inv) => inv * Vector4::new(c.x.signum(), c.y.signum(), 1.0, 1.0),
+            None => return p,
+        };
+
+        let c = c * (2.0 / c.dot(q));
+        let row = c - p.row(3);
+        p.x.z = row.x;
+        p.y.z = row.y;
+        p.z.z = row.z;
+        p.w.z = row.w;
+        p
+    }
+
+    /// A camera with the same view and projection, without render texture and post processing
+    pub fn clone_view(&self) -> Camera {
+        let mut cam = Camera::new();
+        cam.v = self.v;
+        cam.eye = self.eye;
+        cam.rect = self.rect;
+        cam.tile = self.tile;
+        cam.fovy = self.fovy;
+        cam.znear = self.znear;
+        cam.zfar = self.zfar;
+        cam.enable_frustum_culling = self.enable_frustum_culling;
+        cam.included_render_queues = self.included_render_queues.clone();
+        cam.clip_plane = self.clip_plane;
+        cam.depth_range = self.depth_range;
+        cam.viewmodel = self.viewmodel;
+        cam
+    }
+
+    /// The camera rendering the `RenderQueue::Viewmodel` objects, if `viewmodel` is set
+    pub fn viewmodel_camera(&self) -> Option<Camera> {
+        self.viewmodel.map(|vm| {
+            let mut cam = self.clone_view();
+            cam.viewmodel = None;
+            cam.fovy = vm.fovy;
+            cam.znear = vm.znear;
+            cam.zfar = vm.zfar;
+            cam.depth_range = Some(vm.depth_range);
+            cam
+        })
     }
 
     pub fn new() -> Camera {
@@ -137,9 +292,25 @@ impl Camera {
             rect: None,
             znear: 0.03,
             zfar: 1000.0,
+            fovy: Rad(3.1415 / 4.0),
+            tile: None,
             enable_frustum_culling: true,
             included_render_queues: None,
             render_texture: None,
+            exposure: None,
+            clip_plane: None,
+            depth_range: None,
+            viewmodel: None,
+            taa: None,
+        }
+    }
+
+    /// Set the view matrix directly, the eye position is derived from it
+    pub fn set_view(&mut self, v: Matrix4<f32>) {
+        self.v = v;
+
+        if let Some(inv) = v.inverse_transform() {
+            self.eye = Point3::from_vec(inv.w.truncate());
         }
     }
 
@@ -156,7 +327,7 @@ impl Camera {
         let near_center = self.eye.to_vec() + forward * self.znear;
         let far_center = self.eye.to_vec() + forward * self.zfar;
 
-        let fovy: f32 = 3.1415 / 4.0;
+        let fovy: f32 = self.fovy.0;
 
         let near_height = 2.0 * (fovy * 0.5).tan() * self.znear;
         let far_height = 2.0 * (fovy * 0.5).tan() * self.zfar;
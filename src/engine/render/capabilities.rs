@@ -0,0 +1,288 @@
+//! What the GL context of the engine supports
+//!
+//! The context is queried once when the engine is created, which also enables the WebGL
+//! extensions the engine uses on it. Desktop GL 3.2 has all the features.
+//!
+//! Without float render targets (`float_render_targets`), the `Color0Float` render textures
+//! are RGBA8 and `UNRUST_NO_FLOAT_TARGETS` is defined in the shaders, which pack the values
+//! they write with `unrust/float_target.glsl`.
+
+use uni_gl::{WebGLProgram, WebGLRenderingContext};
+
+use std::cell::Cell;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GlCapabilities {
+    pub webgl2: bool,
+    /// Float textures can be sampled (`OES_texture_float` in WebGL1)
+    pub float_textures: bool,
+    /// Float textures can be rendered to, checked with a frame buffer
+    /// (`WEBGL_color_buffer_float` in WebGL1, `EXT_color_buffer_float` in WebGL2)
+    pub float_render_targets: bool,
+    /// `highp` floats in the fragment shaders
+    pub fragment_highp: bool,
+    /// `OES_standard_derivatives` in WebGL1
+    pub derivatives: bool,
+    /// `EXT_shader_texture_lod` in WebGL1
+    pub texture_lod: bool,
+    /// `EXT_frag_depth` in WebGL1
+    pub frag_depth: bool,
+    /// Compressed texture formats, `WEBGL_compressed_texture_*`
+    pub s3tc: bool,
+    /// ETC2 and EAC (`etc`), which also decode the ETC1 blocks
+    pub etc: bool,
+    pub etc1: bool,
+    /// ASTC 4x4 blocks (`astc`)
+    pub astc: bool,
+    pub pvrtc: bool,
+}
+
+impl GlCapabilities {
+    pub fn desktop() -> GlCapabilities {
+        GlCapabilities {
+            webgl2: false,
+            float_textures: true,
+            float_render_targets: true,
+            fragment_highp: true,
+            derivatives: true,
+            texture_lod: true,
+            frag_depth: true,
+            s3tc: true,
+            etc: false,
+            etc1: false,
+            astc: false,
+            pvrtc: false,
+        }
+    }
+}
+
+thread_local!(static CAPABILITIES: Cell<Option<GlCapabilities>> = Cell::new(None));
+
+/// The capabilities of the context of the engine, the ones of desktop GL until the engine
+/// is created
+pub fn capabilities() -> GlCapabilities {
+    CAPABILITIES
+        .with(|c| c.get())
+        .unwrap_or_else(GlCapabilities::desktop)
+}
+
+/// Whether the context of the engine was queried
+pub fn is_queried() -> bool {
+    CAPABILITIES.with(|c| c.get().is_some())
+}
+
+/// Override the capabilities, e.g. to test the fallbacks on desktop. The textures and
+/// shaders already created keep the previous ones.
+pub fn set_capabilities(capabilities: GlCapabilities) {
+    CAPABILITIES.with(|c| c.set(Some(capabilities)));
+}
+
+/// Query the context of the engine and enable its extensions
+pub(crate) fn init(gl: &WebGLRenderingContext) -> GlCapabilities {
+    let capabilities = query(gl);
+    if !capabilities.float_render_targets {
+        println!("No float render targets, the float render textures are packed in RGBA8");
+    }
+
+    set_capabilities(capabilities);
+    capabilities
+}
+
+#[cfg(target_arch = "wasm32")]
+fn query(gl: &WebGLRenderingContext) -> GlCapabilities {
+    use stdweb::unstable::TryInto;
+
+    let flags = js! {
+        var ctx = @{&gl.reference};
+        var webgl2 = typeof WebGL2RenderingContext !== "undefined"
+            && ctx instanceof WebGL2RenderingContext;
+        var ext = function (name) {
+            return webgl2 || ctx.getExtension(name) !== null;
+        };
+
+        var flags = webgl2 ? 1 : 0;
+        if (ext("OES_texture_float")) {
+            flags |= 2;
+        }
+        if (webgl2) {
+            ctx.getExtension("EXT_color_buffer_float");
+        } else {
+            ctx.getExtension("WEBGL_color_buffer_float");
+        }
+
+        var high = ctx.getShaderPrecisionFormat(ctx.FRAGMENT_SHADER, ctx.HIGH_FLOAT);
+        if (high !== null && high.precision > 0) {
+            flags |= 8;
+        }
+        if (ext("OES_standard_derivatives")) {
+            flags |= 16;
+        }
+        if (ext("EXT_shader_texture_lod")) {
+            flags |= 32;
+        }
+        if (ext("EXT_frag_depth")) {
+            flags |= 64;
+        }
+
+        // Compressed formats, also in WebGL2
+        var compressed = function (name) {
+            return ctx.getExtension("WEBGL_compressed_texture_" + name) !== null
+                || ctx.getExtension("WEBKIT_WEBGL_compressed_texture_" + name) !== null;
+        };
+        if (compressed("s3tc")) {
+            flags |= 128;
+        }
+        if (compressed("etc")) {
+            flags |= 256;
+        }
+        if (compressed("etc1")) {
+            flags |= 512;
+        }
+        if (compressed("astc")) {
+            flags |= 1024;
+        }
+        if (compressed("pvrtc")) {
+            flags |= 2048;
+        }
+
+        // Rendering to a float texture, the extensions do not guarantee it
+        if (flags & 2) {
+            var tex = ctx.createTexture();
+            ctx.bindTexture(ctx.TEXTURE_2D, tex);
+            ctx.texImage2D(ctx.TEXTURE_2D, 0, webgl2 ? ctx.RGBA32F : ctx.RGBA, 1, 1, 0,
+                ctx.RGBA, ctx.FLOAT, null);
+            var fb = ctx.createFramebuffer();
+            ctx.bindFramebuffer(ctx.FRAMEBUFFER, fb);
+            ctx.framebufferTexture2D(ctx.FRAMEBUFFER, ctx.COLOR_ATTACHMENT0, ctx.TEXTURE_2D,
+                tex, 0);
+            if (ctx.checkFramebufferStatus(ctx.FRAMEBUFFER) === ctx.FRAMEBUFFER_COMPLETE) {
+                flags |= 4;
+            }
+            ctx.bindFramebuffer(ctx.FRAMEBUFFER, null);
+            ctx.bindTexture(ctx.TEXTURE_2D, null);
+            ctx.deleteFramebuffer(fb);
+            ctx.deleteTexture(tex);
+        }
+
+        return flags;
+    };
+
+    let flags: i32 = flags.try_into().unwrap_or(0);
+    GlCapabilities {
+        webgl2: flags & 1 != 0,
+        float_textures: flags & 2 != 0,
+        float_render_targets: flags & 4 != 0,
+        fragment_highp: flags & 8 != 0,
+        derivatives: flags & 16 != 0,
+        texture_lod: flags & 32 != 0,
+        frag_depth: flags & 64 != 0,
+        s3tc: flags & 128 != 0,
+        etc: flags & 256 != 0,
+        etc1: flags & 512 != 0,
+        astc: flags & 1024 != 0,
+        pvrtc: flags & 2048 != 0,
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn query(_gl: &WebGLRenderingContext) -> GlCapabilities {
+    GlCapabilities::desktop()
+}
+
+/// Allocate the bound 2D texture as a float texture. In WebGL2 the float textures need a
+/// sized internal format to be rendered to, which uni_gl does not give.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn alloc_float_texture(gl: &WebGLRenderingContext, size: (u32, u32)) {
+    if !capabilities().webgl2 {
+        return;
+    }
+
+    js! { @(no_return)
+        var ctx = @{&gl.reference};
+        ctx.texImage2D(ctx.TEXTURE_2D, 0, ctx.RGBA32F, @{size.0}, @{size.1}, 0, ctx.RGBA,
+            ctx.FLOAT, null);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn alloc_float_texture(_gl: &WebGLRenderingContext, _size: (u32, u32)) {}
+
+/// Capture the vertex shader outputs `varyings` of a program by transform feedback, in a
+/// buffer each, before it is linked. WebGL2 only, `uni_gl` does not expose it.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn transform_feedback_varyings(
+    gl: &WebGLRenderingContext,
+    program: &WebGLProgram,
+    varyings: &[String],
+) {
+    if !capabilities().webgl2 {
+        println!("Transform feedback needs WebGL2");
+        return;
+    }
+
+    js! { @(no_return)
+        var ctx = @{&gl.reference};
+        ctx.transformFeedbackVaryings(@{&**program}, @{varyings}, ctx.SEPARATE_ATTRIBS);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn transform_feedback_varyings(
+    _gl: &WebGLRenderingContext,
+    _program: &WebGLProgram,
+    _varyings: &[String],
+) {
+    println!("Transform feedback is only used in WebGL2");
+}
+
+/// Upload a level of compressed blocks of the GL format `format`, for the formats without a
+/// `uni_gl` enum. `target` is the GL texture target, e.g. a face of a cube map.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn compressed_tex_image2d(
+    gl: &WebGLRenderingContext,
+    target: u32,
+    level: u32,
+    format: u32,
+    size: (u32, u32),
+    data: &[u8],
+) {
+    use stdweb::UnsafeTypedArray;
+
+    let data = unsafe { UnsafeTypedArray::new(data) };
+    js! { @(no_return)
+        var ctx = @{&gl.reference};
+        ctx.compressedTexImage2D(@{target}, @{level}, @{format}, @{size.0}, @{size.1}, 0,
+            @{data});
+    }
+}
+
+/// Upload `data` at the byte `offset` of the bound array buffer, which `uni_gl` does not
+/// expose. Return false when not supported, the whole buffer is uploaded instead.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn buffer_sub_data(gl: &WebGLRenderingContext, offset: usize, data: &[u8]) -> bool {
+    use stdweb::UnsafeTypedArray;
+
+    let data = unsafe { UnsafeTypedArray::new(data) };
+    js! { @(no_return)
+        var ctx = @{&gl.reference};
+        ctx.bufferSubData(ctx.ARRAY_BUFFER, @{offset as u32}, @{data});
+    }
+    true
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn buffer_sub_data(_gl: &WebGLRenderingContext, _offset: usize, _data: &[u8]) -> bool {
+    false
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn compressed_tex_image2d(
+    _gl: &WebGLRenderingContext,
+    _target: u32,
+    _level: u32,
+    _format: u32,
+    _size: (u32, u32),
+    _data: &[u8],
+) {
+    println!("Compressed texture formats other than S3TC are only uploaded in web");
+}
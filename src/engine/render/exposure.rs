@@ -0,0 +1,170 @@
+use engine::asset::AssetSystem;
+use engine::render::{Material, RenderTexture, Texture, TextureAttachment};
+
+use math::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+use uni_app::now;
+
+/// Exposure adapting to the average luminance of the scene, like the eye does
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AutoExposure {
+    /// Scene luminance mapped to the middle grey
+    pub key: f32,
+    /// Range of the adapted luminance, darker or brighter scenes stay under or over exposed
+    pub min_luminance: f32,
+    pub max_luminance: f32,
+    /// Adaptation rates per second, the eye adapts faster to bright light
+    pub speed_up: f32,
+    pub speed_down: f32,
+}
+
+impl Default for AutoExposure {
+    fn default() -> AutoExposure {
+        AutoExposure {
+            key: 0.18,
+            min_luminance: 0.03,
+            max_luminance: 8.0,
+            speed_up: 3.0,
+            speed_down: 1.0,
+        }
+    }
+}
+
+/// Exposure of a camera, see `Camera::exposure`
+///
+/// The scene is rendered to a float target, scaled by the exposure and tone mapped before
+/// the post effects. Without float render targets (see `capabilities`) the scene is clamped
+/// to [0, 1] and the luminances are packed in RGBA8.
+#[derive(Default)]
+pub struct Exposure {
+    /// Compensation in stops, each stop doubles the brightness
+    pub compensation: f32,
+    pub auto: Option<AutoExposure>,
+
+    state: RefCell<Option<ExposureState>>,
+}
+
+impl Exposure {
+    pub fn new(compensation: f32) -> Exposure {
+        Exposure {
+            compensation,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_auto(mut self, auto: AutoExposure) -> Exposure {
+        self.auto = Some(auto);
+        self
+    }
+
+    /// Restart the adaptation from the current scene luminance, e.g. after a teleport
+    pub fn reset(&self) {
+        if let Some(ref mut state) = *self.state.borrow_mut() {
+            state.frame = 0;
+        }
+    }
+
+    /// The luminance passes to render as (material, target, size), and the exposure material
+    /// which is the first post effect
+    pub(crate) fn passes(
+        &self,
+        asys: &AssetSystem,
+        scene: &Rc<Texture>,
+    ) -> (Vec<(Rc<Material>, Rc<RenderTexture>, (u32, u32))>, Rc<Material>) {
+        let mut state_ref = self.state.borrow_mut();
+        let state = state_ref.get_or_insert_with(|| ExposureState::new(asys));
+
+        let t = now();
+        let dt = if state.frame == 0 {
+            0.0
+        } else {
+            (t - state.last_time) as f32
+        };
+        state.last_time = t;
+
+        let material = state.exposure.clone();
+        material.set("uExposure", 2.0f32.powf(self.compensation));
+
+        let auto = match self.auto {
+            Some(auto) => auto,
+            None => {
+                material.set("uAuto", false);
+                return (Vec::new(), material);
+            }
+        };
+
+        let mut passes = Vec::new();
+        let mut src = scene.clone();
+
+        for (i, &(ref m, ref rt, size)) in state.luminance.iter().enumerate() {
+            m.set("uDiffuse", src);
+            m.set("uFirst", i == 0);
+            m.set("uTexel", Vector2::new(1.0 / size as f32, 1.0 / size as f32));
+
+            passes.push((m.clone(), rt.clone(), (size, size)));
+            src = rt.as_texture();
+        }
+
+        let prev = state.adapted[state.frame % 2].clone();
+        let next = state.adapted[(state.frame + 1) % 2].clone();
+
+        let adapt = &state.adapt;
+        adapt.set("uCurrent", src);
+        adapt.set("uPrevious", prev.as_texture());
+        adapt.set("uReset", state.frame == 0);
+        adapt.set("uDeltaTime", dt);
+        adapt.set("uSpeedUp", auto.speed_up);
+        adapt.set("uSpeedDown", auto.speed_down);
+        adapt.set("uMinLuminance", auto.min_luminance);
+        adapt.set("uMaxLuminance", auto.max_luminance);
+        passes.push((adapt.clone(), next.clone(), (1, 1)));
+
+        material.set("uAuto", true);
+        material.set("uAdapted", next.as_texture());
+        material.set("uKey", auto.key);
+
+        state.frame += 1;
+        (passes, material)
+    }
+}
+
+/// Size of the first luminance target, each pass downsamples it by 4
+const LUMINANCE_SIZE: u32 = 64;
+
+struct ExposureState {
+    luminance: Vec<(Rc<Material>, Rc<RenderTexture>, u32)>,
+    adapt: Rc<Material>,
+    /// Adapted luminance of the previous and current frames
+    adapted: [Rc<RenderTexture>; 2],
+    exposure: Rc<Material>,
+    frame: usize,
+    last_time: f64,
+}
+
+impl ExposureState {
+    fn new(asys: &AssetSystem) -> ExposureState {
+        let new_rt = |size| Rc::new(RenderTexture::new(size, size, TextureAttachment::Color0Float));
+
+        let mut luminance = Vec::new();
+        let mut size = LUMINANCE_SIZE;
+        loop {
+            let m = Rc::new(Material::new(asys.new_program("unrust/luminance")));
+            luminance.push((m, new_rt(size), size));
+
+            if size == 1 {
+                break;
+            }
+            size = (size / 4).max(1);
+        }
+
+        ExposureState {
+            luminance,
+            adapt: Rc::new(Material::new(asys.new_program("unrust/exposure_adapt"))),
+            adapted: [new_rt(1), new_rt(1)],
+            exposure: Rc::new(Material::new(asys.new_program("unrust/exposure"))),
+            frame: 0,
+            last_time: 0.0,
+        }
+    }
+}
@@ -6,6 +6,7 @@ use engine::render::{Texture, TextureAttachment};
 
 pub struct FrameBuffer {
     pub texture: Rc<Texture>,
+    pub depth: Option<Rc<Texture>>,
     handle: RefCell<Option<WebGLFrameBuffer>>,
 }
 
@@ -13,7 +14,23 @@ impl FrameBuffer {
     pub fn new(width: u32, height: u32, attach: TextureAttachment) -> FrameBuffer {
         let texture = Texture::new_render_texture(width, height, attach);
         let handle = RefCell::new(None);
-        FrameBuffer { texture, handle }
+        FrameBuffer {
+            texture,
+            depth: None,
+            handle,
+        }
+    }
+
+    /// A frame buffer with both color and depth texture attached
+    pub fn new_with_depth(width: u32, height: u32, attach: TextureAttachment) -> FrameBuffer {
+        let texture = Texture::new_render_texture(width, height, attach);
+        let depth = Texture::new_render_texture(width, height, TextureAttachment::Depth);
+        let handle = RefCell::new(None);
+        FrameBuffer {
+            texture,
+            depth: Some(depth),
+            handle,
+        }
     }
 
     fn create_fb(&self, gl: &WebGLRenderingContext) {
@@ -36,6 +53,10 @@ impl FrameBuffer {
 
         gl.bind_framebuffer(Buffers::Framebuffer, &h);
         self.texture.bind_with_frame_buffer(gl, 0).unwrap();
+
+        if let Some(ref depth) = self.depth {
+            depth.bind_as_depth_buffer(gl).unwrap();
+        }
     }
 
     pub fn unbind(&self, gl: &WebGLRenderingContext) {
@@ -8,6 +8,7 @@ use unrust::engine::{Component, ComponentArena, IntoComponentPtr};
 pub enum Light {
     Directional(DirectionalLight),
     Point(PointLight),
+    Spot(SpotLight),
 }
 
 macro_rules! impl_light {
@@ -33,6 +34,7 @@ macro_rules! impl_light {
 impl Light {
     impl_light!(directional, directional_mut, Directional, DirectionalLight);
     impl_light!(point, point_mut, Point, PointLight);
+    impl_light!(spot, spot_mut, Spot, SpotLight);
 
     pub fn new<T>(a: T) -> Light
     where
@@ -45,6 +47,7 @@ impl Light {
         match *self {
             Light::Directional(ref mut l) => l.update(model),
             Light::Point(ref mut l) => l.update(model),
+            Light::Spot(ref mut l) => l.update(model),
         }
     }
 
@@ -52,6 +55,7 @@ impl Light {
         match *self {
             Light::Directional(ref l) => l.bind(lightname, prog),
             Light::Point(ref l) => l.bind(lightname, prog),
+            Light::Spot(ref l) => l.bind(lightname, prog),
         }
     }
 }
@@ -159,12 +163,108 @@ impl PointLight {
         prog.set(lightname.to_string() + ".quadratic", self.quadratic);
 
         prog.set(lightname.to_string() + ".rate", 1.0);
+
+        // Lit in all directions
+        prog.set(lightname.to_string() + ".spotCosOuter", -2.0);
+        prog.set(lightname.to_string() + ".spotCosInner", -1.0);
+    }
+
+    fn update(&mut self, modelm: &Matrix4f) {
+        self.world_space_position = modelm
+            .transform_point(Point3::from_vec(self.position))
+            .to_vec();
+    }
+}
+
+/// A point light lighting a cone around `direction`, shaded as a point light by the
+/// `uPointLights` of the shaders
+pub struct SpotLight {
+    pub position: Vector3<f32>,
+    pub direction: Vector3<f32>,
+    /// Half angle of the fully lit cone
+    pub inner_angle: Deg<f32>,
+    /// Half angle of the cone, the light fades out between `inner_angle` and this
+    pub outer_angle: Deg<f32>,
+
+    pub ambient: Vector3<f32>,
+    pub diffuse: Vector3<f32>,
+    pub specular: Vector3<f32>,
+
+    pub constant: f32,
+    pub linear: f32,
+    pub quadratic: f32,
+
+    pub world_space_position: Vector3f,
+    pub world_space_direction: Vector3f,
+}
+
+impl From<SpotLight> for Light {
+    fn from(w: SpotLight) -> Light {
+        Light::Spot(w)
+    }
+}
+
+impl Default for SpotLight {
+    fn default() -> SpotLight {
+        SpotLight {
+            position: Vector3::new(0.0, 0.0, 0.0),
+            direction: Vector3::new(0.0, -1.0, 0.0),
+            inner_angle: Deg(20.0),
+            outer_angle: Deg(30.0),
+            ambient: Vector3::new(0.0, 0.0, 0.0),
+            diffuse: Vector3::new(0.8, 0.8, 0.8),
+            specular: Vector3::new(1.0, 1.0, 1.0),
+            constant: 1.0,
+            linear: 0.022,
+            quadratic: 0.0019,
+            world_space_position: Vector3f::zero(),
+            world_space_direction: Vector3::new(0.0, -1.0, 0.0),
+        }
+    }
+}
+
+impl SpotLight {
+    /// Cosines of the outer and inner half angles
+    pub fn cone(&self) -> (f32, f32) {
+        let outer = self.outer_angle.0.max(0.0).min(90.0);
+        let inner = self.inner_angle.0.max(0.0).min(outer);
+        (outer.to_radians().cos(), inner.to_radians().cos())
+    }
+
+    fn bind(&self, lightname: &str, prog: &ShaderProgram) {
+        prog.set(
+            lightname.to_string() + ".position",
+            self.world_space_position,
+        );
+
+        prog.set(lightname.to_string() + ".ambient", self.ambient);
+        prog.set(lightname.to_string() + ".diffuse", self.diffuse);
+        prog.set(lightname.to_string() + ".specular", self.specular);
+
+        prog.set(lightname.to_string() + ".constant", self.constant);
+        prog.set(lightname.to_string() + ".linear", self.linear);
+        prog.set(lightname.to_string() + ".quadratic", self.quadratic);
+
+        prog.set(lightname.to_string() + ".rate", 1.0);
+
+        let (cos_outer, cos_inner) = self.cone();
+        prog.set(
+            lightname.to_string() + ".spotDirection",
+            self.world_space_direction,
+        );
+        prog.set(lightname.to_string() + ".spotCosOuter", cos_outer);
+        prog.set(lightname.to_string() + ".spotCosInner", cos_inner);
     }
 
     fn update(&mut self, modelm: &Matrix4f) {
         self.world_space_position = modelm
             .transform_point(Point3::from_vec(self.position))
             .to_vec();
+
+        let direction = modelm.transform_vector(self.direction);
+        if direction.magnitude2() > 1e-10 {
+            self.world_space_direction = direction.normalize();
+        }
     }
 }
 
@@ -181,3 +281,10 @@ impl IntoComponentPtr for PointLight {
         Component::new(light, arena)
     }
 }
+
+impl IntoComponentPtr for SpotLight {
+    fn into_component_ptr(self, arena: &Rc<ComponentArena>) -> Arc<Component> {
+        let light: Light = self.into();
+        Component::new(light, arena)
+    }
+}
@@ -126,6 +126,86 @@ impl Material {
         self.params.borrow_mut().insert(name.into(), t.into());
     }
 
+    /// Discard the fragments of the diffuse texture with an alpha below `threshold`, e.g. for
+    /// foliage and fences. Only for the shaders including `unrust/cutout.glsl`, like `phong`.
+    pub fn set_cutout(&self, threshold: f32) {
+        self.set("uCutoff", threshold);
+    }
+
+    /// Dissolve the surface in noise, from 0 (opaque) to 1 (invisible). The border of the
+    /// holes glows in `edge_color` over `edge_width`, from 0 to 1. Only for the shaders
+    /// including `unrust/cutout.glsl`, like `phong`.
+    pub fn set_dissolve(&self, amount: f32, edge_width: f32, edge_color: Vector3<f32>) {
+        self.set("uDissolve", amount.max(0.0).min(1.0));
+        self.set("uDissolveEdge", edge_width.max(0.0).min(1.0));
+        self.set("uDissolveColor", edge_color);
+    }
+
+    /// This material with the cutout and dissolve of `source`, for a pass replacing the
+    /// materials of the objects like the shadow pass. None if `source` cuts nothing out.
+    pub fn with_cutout_of(&self, source: &Material) -> Option<Material> {
+        let positive = |name: &str| match source.get(name) {
+            Some(MaterialParam::Float(v)) => v > 0.0,
+            _ => false,
+        };
+        if !positive("uCutoff") && !positive("uDissolve") {
+            return None;
+        }
+
+        let material = Material {
+            render_queue: self.render_queue,
+            program: self.program.clone(),
+            params: RefCell::new(self.params.borrow().clone()),
+            states: self.states,
+        };
+
+        let names = [
+            "uCutoff",
+            "uDissolve",
+            "uDissolveEdge",
+            "uDissolveColor",
+            "uDissolveScale",
+        ];
+        for name in names.iter() {
+            if let Some(param) = source.get(name) {
+                material.set(*name, param);
+            }
+        }
+
+        // The alpha of the cutout
+        if let Some(MaterialParam::Params(ref params)) = source.get("uMaterial") {
+            if let Some(diffuse) = params.get("diffuse") {
+                let mut map = MaterialParamMap::default();
+                map.insert("diffuse".into(), diffuse.clone());
+                material.set("uMaterial", map);
+            }
+        }
+
+        Some(material)
+    }
+
+    /// Value of a parameter
+    pub fn get(&self, name: &str) -> Option<MaterialParam> {
+        self.params.borrow().get(name).cloned()
+    }
+
+    /// Textures of the parameters, included the nested ones
+    pub fn textures(&self) -> Vec<Rc<Texture>> {
+        fn collect(params: &MaterialParamMap, out: &mut Vec<Rc<Texture>>) {
+            for param in params.values() {
+                match param {
+                    &MaterialParam::Texture(ref tex) => out.push(tex.0.clone()),
+                    &MaterialParam::Params(ref pm) => collect(pm, out),
+                    _ => (),
+                }
+            }
+        }
+
+        let mut out = Vec::new();
+        collect(&self.params.borrow(), &mut out);
+        out
+    }
+
     fn bind_params<F>(
         &self,
         params: &MaterialParamMap,
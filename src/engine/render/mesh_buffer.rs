@@ -4,15 +4,20 @@ use uni_gl::*;
 use super::ShaderProgram;
 use engine::asset::{Asset, AssetResult, AssetSystem, FileFuture, LoadableAsset, Resource};
 use engine::core::Aabb;
+use engine::diagnostics;
+use engine::render::capabilities;
 use engine::render::mesh::MeshBound;
 use engine::render::shader_program::ShaderAttrib;
 
 use math::*;
 use std::cell::Cell;
+use std::cell::Ref;
 use std::cell::RefCell;
 use std::f32::{MAX, MIN};
+use std::ops::Range;
 use std::rc::Rc;
 use std::rc::Weak;
+use std::slice;
 
 trait IntoBytes {
     fn into_bytes(self) -> Vec<u8>;
@@ -31,6 +36,7 @@ impl<T> IntoBytes for Vec<T> {
 enum RebindAction {
     Vertices,
     UV,
+    UV2,
     Normal,
     Tangent,
     Bitangent,
@@ -41,6 +47,7 @@ struct MeshGLState {
     pub vao: WebGLVertexArray,
     pub vb: WebGLBuffer,
     pub uvb: Option<WebGLBuffer>,
+    pub uv2b: Option<WebGLBuffer>,
 
     pub nb: Option<WebGLBuffer>,
     pub tb: Option<WebGLBuffer>,
@@ -50,6 +57,12 @@ struct MeshGLState {
     pub gl: WebGLRenderingContext,
 
     pub rebind_actions: Vec<RebindAction>,
+    /// Vertices changed by `update_vertex_range`, uploaded alone
+    pub dirty_ranges: Vec<Range<usize>>,
+
+    // Buffer count and bytes, for diagnostics
+    buffers: usize,
+    bytes: usize,
 }
 
 impl MeshGLState {
@@ -69,6 +82,11 @@ impl MeshGLState {
                 data.uvs.clone().unwrap().into_bytes(),
                 self.uvb.as_mut().unwrap(),
             ),
+            RebindAction::UV2 => (
+                BufferKind::Array,
+                data.uvs2.clone().unwrap().into_bytes(),
+                self.uv2b.as_mut().unwrap(),
+            ),
             RebindAction::Normal => (
                 BufferKind::Array,
                 data.normals.clone().unwrap().into_bytes(),
@@ -101,18 +119,80 @@ impl MeshGLState {
             gl.unbind_buffer(k);
         }
     }
+
+    fn rebind_ranges(
+        &self,
+        ranges: &[Range<usize>],
+        data: &MeshData,
+        gl: &WebGLRenderingContext,
+    ) {
+        let attributes = [
+            (Some(&data.vertices), Some(&self.vb), 3),
+            (data.uvs.as_ref(), self.uvb.as_ref(), 2),
+            (data.uvs2.as_ref(), self.uv2b.as_ref(), 2),
+            (data.normals.as_ref(), self.nb.as_ref(), 3),
+            (data.tangents.as_ref(), self.tb.as_ref(), 3),
+            (data.bitangents.as_ref(), self.btb.as_ref(), 3),
+        ];
+
+        for &(values, buffer, size) in attributes.iter() {
+            let (values, buffer) = match (values, buffer) {
+                (Some(values), Some(buffer)) => (values, buffer),
+                _ => continue,
+            };
+
+            gl.bind_buffer(BufferKind::Array, buffer);
+            for range in ranges.iter() {
+                let end = (range.end * size).min(values.len());
+                let start = (range.start * size).min(end);
+                let bytes = f32_bytes(&values[start..end]);
+
+                if !capabilities::buffer_sub_data(gl, start * 4, bytes) {
+                    let all = values.clone().into_bytes();
+                    gl.buffer_data(BufferKind::Array, &all, DrawMode::Static);
+                    break;
+                }
+            }
+            gl.unbind_buffer(BufferKind::Array);
+        }
+    }
+}
+
+fn f32_bytes(values: &[f32]) -> &[u8] {
+    unsafe { slice::from_raw_parts(values.as_ptr() as *const u8, values.len() * 4) }
+}
+
+/// Copy the `range` vertices of `size` components
+fn copy_range(to: &mut [f32], from: &[f32], range: &Range<usize>, size: usize) {
+    let end = (range.end * size).min(to.len()).min(from.len());
+    let start = (range.start * size).min(end);
+    to[start..end].copy_from_slice(&from[start..end]);
+}
+
+fn copy_optional_range(
+    to: &mut Option<Vec<f32>>,
+    from: &Option<Vec<f32>>,
+    range: &Range<usize>,
+    size: usize,
+) {
+    if let (&mut Some(ref mut to), &Some(ref from)) = (to, from) {
+        copy_range(to, from, range, size);
+    }
 }
 
 impl Drop for MeshGLState {
     fn drop(&mut self) {
         self.gl.delete_buffer(&self.vb);
         self.uvb.as_ref().map(|b| self.gl.delete_buffer(&b));
+        self.uv2b.as_ref().map(|b| self.gl.delete_buffer(&b));
         self.nb.as_ref().map(|b| self.gl.delete_buffer(&b));
         self.tb.as_ref().map(|b| self.gl.delete_buffer(&b));
         self.btb.as_ref().map(|b| self.gl.delete_buffer(&b));
         self.gl.delete_buffer(&self.ib);
 
         self.gl.delete_vertex_array(&self.vao);
+
+        diagnostics::track_buffers(self.buffers, self.bytes, false);
     }
 }
 
@@ -120,6 +200,8 @@ impl Drop for MeshGLState {
 pub struct MeshData {
     pub vertices: Vec<f32>,
     pub uvs: Option<Vec<f32>>,
+    /// Second uv channel, e.g. the lightmap coordinates
+    pub uvs2: Option<Vec<f32>>,
     pub normals: Option<Vec<f32>>,
 
     pub tangents: Option<Vec<f32>>,
@@ -205,6 +287,11 @@ pub fn bind_buffer(
 }
 
 impl MeshBuffer {
+    /// The vertices and indices of the buffer, when loaded
+    pub fn mesh_data(&self) -> AssetResult<Ref<MeshData>> {
+        self.data.try_borrow()
+    }
+
     pub fn update_mesh_data(&self, mesh_data: MeshData) {
         let mut actions = Vec::new();
 
@@ -217,6 +304,10 @@ impl MeshBuffer {
                     actions.push(RebindAction::UV);
                 });
 
+                mesh_data.uvs2.as_ref().map(|_| {
+                    actions.push(RebindAction::UV2);
+                });
+
                 mesh_data.normals.as_ref().map(|_| {
                     actions.push(RebindAction::Normal);
                 });
@@ -245,6 +336,27 @@ impl MeshBuffer {
         }
     }
 
+    /// Copy the vertices of `range` from `source`, of the same layout, and upload them alone.
+    /// Cheaper than `update_mesh_data` when a few vertices of a large buffer change.
+    pub fn update_vertex_range(&self, source: &MeshData, range: Range<usize>) {
+        match self.data.try_borrow_mut() {
+            Ok(mut data) => {
+                let data = &mut *data;
+                copy_range(&mut data.vertices, &source.vertices, &range, 3);
+                copy_optional_range(&mut data.uvs, &source.uvs, &range, 2);
+                copy_optional_range(&mut data.uvs2, &source.uvs2, &range, 2);
+                copy_optional_range(&mut data.normals, &source.normals, &range, 3);
+                copy_optional_range(&mut data.tangents, &source.tangents, &range, 3);
+                copy_optional_range(&mut data.bitangents, &source.bitangents, &range, 3);
+            }
+            Err(_) => return,
+        }
+
+        if let Some(ref mut state) = *self.gl_state.borrow_mut() {
+            state.dirty_ranges.push(range);
+        }
+    }
+
     pub fn prepare(&self, gl: &WebGLRenderingContext) -> AssetResult<()> {
         if let Some(ref mut state) = *self.gl_state.borrow_mut() {
             if state.rebind_actions.len() > 0 {
@@ -257,6 +369,14 @@ impl MeshBuffer {
                 state.rebind(&rebind_actions, &data, gl);
             }
 
+            if state.dirty_ranges.len() > 0 {
+                gl.bind_vertex_array(&state.vao);
+
+                let data = self.data.try_borrow()?;
+                let ranges: Vec<_> = state.dirty_ranges.drain(..).collect();
+                state.rebind_ranges(&ranges, &data, gl);
+            }
+
             return Ok(());
         }
 
@@ -265,6 +385,7 @@ impl MeshBuffer {
         self.gl_state.replace(Some(mesh_bind_buffer(
             &data.vertices,
             &data.uvs,
+            &data.uvs2,
             &data.normals,
             &data.tangents,
             &data.bitangents,
@@ -322,6 +443,11 @@ impl MeshBuffer {
             bind_buffer(gl, uvb, ShaderAttrib::UV0 as u32, AttributeSize::Two);
         }
 
+        // "aTextureCoord2"
+        if let Some(ref uv2b) = state.uv2b {
+            bind_buffer(gl, uv2b, ShaderAttrib::UV1 as u32, AttributeSize::Two);
+        }
+
         // "aVertexNormal"
         if let Some(ref nb) = state.nb {
             bind_buffer(gl, nb, ShaderAttrib::Normal as u32, AttributeSize::Three);
@@ -387,6 +513,7 @@ fn bind_f32_array(gl: &WebGLRenderingContext, data: &Vec<f32>) -> WebGLBuffer {
 fn mesh_bind_buffer(
     vertices: &Vec<f32>,
     uvs: &Option<Vec<f32>>,
+    uvs2: &Option<Vec<f32>>,
     normals: &Option<Vec<f32>>,
     tangents: &Option<Vec<f32>>,
     bitangents: &Option<Vec<f32>>,
@@ -399,6 +526,7 @@ fn mesh_bind_buffer(
 
     let vertex_buffer = bind_f32_array(&gl, vertices);
     let uv_buffer = uvs.as_ref().map(|data| bind_f32_array(gl, data));
+    let uv2_buffer = uvs2.as_ref().map(|data| bind_f32_array(gl, data));
     let normal_buffer = normals.as_ref().map(|data| bind_f32_array(gl, data));
     let tangent_buffer = tangents.as_ref().map(|data| bind_f32_array(gl, data));
     let bitangent_buffer = bitangents.as_ref().map(|data| bind_f32_array(gl, data));
@@ -417,10 +545,21 @@ fn mesh_bind_buffer(
         gl.unbind_buffer(BufferKind::ElementArray);
     }
 
+    let optionals = [uvs, uvs2, normals, tangents, bitangents];
+    let buffers = 2 + optionals.iter().filter(|d| d.is_some()).count();
+    let bytes = vertices.len() * 4 + indices.len() * 2
+        + optionals
+            .iter()
+            .map(|d| d.as_ref().map_or(0, |d| d.len() * 4))
+            .sum::<usize>();
+
+    diagnostics::track_buffers(buffers, bytes, true);
+
     MeshGLState {
         vao,
         vb: vertex_buffer,
         uvb: uv_buffer,
+        uv2b: uv2_buffer,
 
         nb: normal_buffer,
         tb: tangent_buffer,
@@ -430,5 +569,9 @@ fn mesh_bind_buffer(
         gl: gl.clone(),
 
         rebind_actions: Vec::new(),
+        dirty_ranges: Vec::new(),
+
+        buffers,
+        bytes,
     }
 }
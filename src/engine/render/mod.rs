@@ -9,26 +9,34 @@ mod uniforms;
 mod frame_buffer;
 mod render_texture;
 mod mesh_buffer;
+mod exposure;
+mod taa;
 
 #[derive(Hash, Eq, Ord, PartialOrd, PartialEq, Copy, Clone, Debug)]
 pub enum RenderQueue {
     Opaque = 1000,
     Skybox = 2000,
     Transparent = 3000,
+    /// First person weapons and hands, rendered with `Camera::viewmodel`
+    Viewmodel = 4000,
     UI = 5000,
 }
 
+pub mod capabilities;
 pub mod mesh_util;
+pub mod shader_platform;
 
-pub use self::camera::{Camera, Frustum};
+pub use self::camera::{Camera, Frustum, Viewmodel};
+pub use self::exposure::{AutoExposure, Exposure};
+pub use self::taa::TemporalAA;
 pub use self::shader::{PreprocessedShaderCode, Shader, ShaderFs, ShaderKind, ShaderKindFs,
                        ShaderKindProvider, ShaderKindVs, ShaderVs};
 pub use self::shader_program::ShaderProgram;
-pub use self::texture::{Texture, TextureAsset, TextureAttachment, TextureFiltering, TextureImage,
-                        TextureWrap};
+pub use self::texture::{CompressedImage, Texture, TextureAsset, TextureAttachment,
+                        TextureFiltering, TextureImage, TextureWrap};
 pub use self::mesh::{Mesh, MeshSurface};
 pub use self::mesh_buffer::{MeshBuffer, MeshData};
 pub use self::material::{CullMode, DepthTest, Material, MaterialParam, MaterialParamMap,
                          MaterialState};
-pub use self::light::{DirectionalLight, Light, PointLight};
+pub use self::light::{DirectionalLight, Light, PointLight, SpotLight};
 pub use self::render_texture::RenderTexture;
@@ -2,7 +2,8 @@ use engine::render::{Texture, TextureAttachment};
 use std::rc::Rc;
 use std::ops::Deref;
 use engine::render::frame_buffer::FrameBuffer;
-use uni_gl::WebGLRenderingContext;
+use uni_gl::{self, WebGLRenderingContext};
+use image::{self, imageops, RgbaImage};
 
 pub struct RenderTexture(FrameBuffer);
 
@@ -19,6 +20,24 @@ impl RenderTexture {
         RenderTexture(FrameBuffer::new(width, height, attach))
     }
 
+    /// A color render texture with its own depth buffer, the depth can be sampled by `depth_texture`
+    pub fn new_with_depth(width: u32, height: u32) -> RenderTexture {
+        RenderTexture(FrameBuffer::new_with_depth(width, height, TextureAttachment::Color0))
+    }
+
+    /// Like `new_with_depth` with a float per color channel, for HDR rendering
+    pub fn new_hdr_with_depth(width: u32, height: u32) -> RenderTexture {
+        RenderTexture(FrameBuffer::new_with_depth(
+            width,
+            height,
+            TextureAttachment::Color0Float,
+        ))
+    }
+
+    pub fn depth_texture(&self) -> Option<Rc<Texture>> {
+        self.0.depth.clone()
+    }
+
     pub fn bind_frame_buffer(&self, gl: &WebGLRenderingContext) {
         self.0.prepare(gl);
         self.0.bind(gl);
@@ -31,4 +50,26 @@ impl RenderTexture {
     pub fn as_texture(&self) -> Rc<Texture> {
         self.0.texture.clone()
     }
+
+    /// Read back the content of a color render texture
+    pub fn capture(&self, gl: &WebGLRenderingContext) -> Option<RgbaImage> {
+        let (width, height) = self.0.texture.size()?;
+
+        let mut values: Vec<u8> = vec![0; (width * height * 4) as usize];
+        self.bind_frame_buffer(gl);
+        gl.read_pixels(
+            0,
+            0,
+            width,
+            height,
+            uni_gl::PixelFormat::Rgba,
+            uni_gl::PixelType::UnsignedByte,
+            &mut values,
+        );
+        self.unbind_frame_buffer(gl);
+
+        let img = image::RgbaImage::from_raw(width, height, values);
+        // opengl (0,0) is in left bottom
+        img.map(|img| imageops::flip_vertical(&img))
+    }
 }
@@ -5,6 +5,7 @@ use uni_glsl::preprocessor::PreprocessError;
 // use uni_glsl::TypeQualifier;
 // use uni_glsl::query::*;
 
+use engine::render::shader_platform::{self, FallbackChoice, ShaderTarget};
 use uni_gl;
 use std::collections::HashMap;
 use std::marker::PhantomData;
@@ -36,48 +37,78 @@ impl ShaderKindProvider for ShaderKindFs {
 }
 
 #[derive(Debug)]
-pub struct PreprocessedShaderCode(String);
+pub struct PreprocessedShaderCode(String, Vec<FallbackChoice>);
 
 impl PreprocessedShaderCode {
     pub fn as_string(&self) -> &String {
         &self.0
     }
 
+    /// The fallbacks chosen for the target, see `shader_platform`
+    pub fn fallbacks(&self) -> &[FallbackChoice] {
+        &self.1
+    }
+
     pub fn new(
         kind: ShaderKind,
         s: &str,
         external_files: &HashMap<String, String>,
     ) -> Result<PreprocessedShaderCode, PreprocessError> {
-        let prefix = match kind {
-            ShaderKind::Vertex => if !uni_gl::IS_GL_ES {
-                "#version 150\n".to_owned()
-            } else {
-                if s.starts_with("#define USE_GLSL_300ES") {
-                    "#version 300 es\n".to_owned()
-                } else {
-                    "".to_owned()
-                }
-            },
-
-            ShaderKind::Fragment => if !uni_gl::IS_GL_ES {
-                "#version 150\n".to_owned()
-            } else {
-                if s.starts_with("#define USE_GLSL_300ES") {
-                    "#version 300 es\n".to_owned() + "precision highp float;\n"
-                } else {
-                    "precision highp float;\n".to_owned()
-                }
-            },
+        PreprocessedShaderCode::new_for_target(kind, s, external_files, &shader_platform::target())
+    }
+
+    /// Preprocess the code and patch it for a target, see `shader_platform`
+    pub fn new_for_target(
+        kind: ShaderKind,
+        s: &str,
+        external_files: &HashMap<String, String>,
+        target: &ShaderTarget,
+    ) -> Result<PreprocessedShaderCode, PreprocessError> {
+        let glsl_300es = s.starts_with("#define USE_GLSL_300ES");
+        let version = if !target.gl_es {
+            "#version 150\n"
+        } else if glsl_300es {
+            "#version 300 es\n"
+        } else {
+            ""
         };
 
         let mut predefs: HashMap<String, String> = HashMap::new();
-        if uni_gl::IS_GL_ES {
+        if target.gl_es {
             predefs.insert("GL_ES".to_string(), "".to_string());
         }
+        if !target.float_render_targets {
+            predefs.insert("UNRUST_NO_FLOAT_TARGETS".to_string(), "".to_string());
+        }
+
+        let processed = preprocessor::preprocess(&s, &predefs, external_files)?;
+
+        // Again with the fallbacks of the missing features
+        let missing = shader_platform::missing_features(kind, glsl_300es, &processed, target);
+        let processed = if missing.is_empty() {
+            processed
+        } else {
+            for feature in missing.iter() {
+                predefs.insert(format!("UNRUST_NO_{}", feature.name()), "".to_string());
+            }
+            preprocessor::preprocess(&s, &predefs, external_files)?
+        };
+
+        let (extensions, code, fallbacks) =
+            shader_platform::patch(kind, glsl_300es, &processed, target, &missing);
 
-        let processed = preprocessor::preprocess(&s, &predefs, external_files);
+        let precision = match kind {
+            ShaderKind::Fragment if target.gl_es => format!(
+                "precision {} float;\n",
+                target.fragment_precision.qualifier()
+            ),
+            _ => "".to_owned(),
+        };
 
-        processed.map(|s| PreprocessedShaderCode(prefix + &s))
+        Ok(PreprocessedShaderCode(
+            version.to_owned() + &extensions + &precision + &code,
+            fallbacks,
+        ))
     }
 }
 
@@ -98,6 +129,7 @@ where
 {
     pub fn new(filename: &str, s: &str) -> Shader<T> {
         let code = PreprocessedShaderCode::new(T::kind(), s, &HashMap::new()).unwrap();
+        shader_platform::record(filename, code.fallbacks());
 
         Shader {
             //unit: unit,
@@ -109,6 +141,7 @@ where
 
     pub fn from_preprocessed(filename: &str, code: PreprocessedShaderCode) -> Shader<T> {
         uni_gl::print(&format!("preprocessing {}...\n", filename));
+        shader_platform::record(filename, code.fallbacks());
 
         Shader {
             //unit: unit,
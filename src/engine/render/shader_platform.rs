@@ -0,0 +1,446 @@
+//! Precision and feature fallbacks of the shaders per target
+//!
+//! Shaders working on desktop often fail on mobile GPUs: `highp` is optional in the fragment
+//! shaders of GLSL ES, and the derivatives, texture lod and frag depth are extensions in
+//! WebGL 1. The shaders are patched when preprocessed, for the `ShaderTarget` of the
+//! platform (see `detect`):
+//!
+//! - The default float precision of the fragment shaders is the one of the target, and the
+//!   `highp` qualifiers are lowered where the target has no highp.
+//! - The extensions used by a shader are enabled when the target has them. Otherwise
+//!   `UNRUST_NO_<FEATURE>` is defined, so the shader can take a fallback path:
+//!
+//! ```ignore
+//! #ifdef UNRUST_NO_DERIVATIVES
+//!     float width = 0.01;
+//! #else
+//!     float width = fwidth(d);
+//! #endif
+//! ```
+//!
+//! Every fallback is recorded, `report()` lists them by shader. The fallbacks a shader
+//! cannot take (a missing feature used outside of an `#ifdef`) are also printed, as the
+//! shader will not compile.
+//!
+//! Without float render targets, `UNRUST_NO_FLOAT_TARGETS` is defined for the shaders
+//! writing or reading the `Color0Float` render textures, see `unrust/float_target.glsl`.
+
+use engine::render::{capabilities, ShaderKind};
+
+use std::cell::RefCell;
+use std::fmt;
+use uni_gl;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Precision {
+    Low,
+    Medium,
+    High,
+}
+
+impl Precision {
+    /// The GLSL qualifier
+    pub fn qualifier(&self) -> &'static str {
+        match *self {
+            Precision::Low => "lowp",
+            Precision::Medium => "mediump",
+            Precision::High => "highp",
+        }
+    }
+}
+
+/// Optional features of the GLSL ES 1.0 fragment shaders
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShaderFeature {
+    /// `dFdx`, `dFdy` and `fwidth`
+    Derivatives,
+    /// `texture2DLod` and `textureCubeLod`
+    TextureLod,
+    /// `gl_FragDepth`
+    FragDepth,
+}
+
+impl ShaderFeature {
+    pub fn all() -> Vec<ShaderFeature> {
+        vec![
+            ShaderFeature::Derivatives,
+            ShaderFeature::TextureLod,
+            ShaderFeature::FragDepth,
+        ]
+    }
+
+    /// Suffix of the `UNRUST_NO_` define set when the feature is missing
+    pub fn name(&self) -> &'static str {
+        match *self {
+            ShaderFeature::Derivatives => "DERIVATIVES",
+            ShaderFeature::TextureLod => "TEXTURE_LOD",
+            ShaderFeature::FragDepth => "FRAG_DEPTH",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match *self {
+            ShaderFeature::Derivatives => "GL_OES_standard_derivatives",
+            ShaderFeature::TextureLod => "GL_EXT_shader_texture_lod",
+            ShaderFeature::FragDepth => "GL_EXT_frag_depth",
+        }
+    }
+
+    /// The builtins of the feature, and their names with the extension
+    fn builtins(&self) -> &'static [(&'static str, &'static str)] {
+        match *self {
+            ShaderFeature::Derivatives => {
+                &[("dFdx", "dFdx"), ("dFdy", "dFdy"), ("fwidth", "fwidth")]
+            }
+            ShaderFeature::TextureLod => &[
+                ("texture2DLod", "texture2DLodEXT"),
+                ("texture2DProjLod", "texture2DProjLodEXT"),
+                ("textureCubeLod", "textureCubeLodEXT"),
+            ],
+            ShaderFeature::FragDepth => &[("gl_FragDepth", "gl_FragDepthEXT")],
+        }
+    }
+
+    fn is_used(&self, code: &str) -> bool {
+        self.builtins()
+            .iter()
+            .any(|&(name, ext)| has_identifier(code, name) || has_identifier(code, ext))
+    }
+}
+
+/// What the shaders of a platform can use
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShaderTarget {
+    /// GLSL ES, it must match the platform
+    pub gl_es: bool,
+    /// Best float precision of the fragment shaders
+    pub fragment_precision: Precision,
+    /// Features of the GLSL ES 1.0 fragment shaders, all of them are in GLSL ES 3.0 and
+    /// desktop GLSL
+    pub features: Vec<ShaderFeature>,
+    /// The `Color0Float` render textures are float, otherwise `UNRUST_NO_FLOAT_TARGETS` is
+    /// defined, see `capabilities`
+    pub float_render_targets: bool,
+}
+
+impl ShaderTarget {
+    pub fn desktop() -> ShaderTarget {
+        ShaderTarget {
+            gl_es: false,
+            fragment_precision: Precision::High,
+            features: ShaderFeature::all(),
+            float_render_targets: true,
+        }
+    }
+
+    /// WebGL on a desktop browser
+    pub fn webgl() -> ShaderTarget {
+        ShaderTarget {
+            gl_es: true,
+            fragment_precision: Precision::High,
+            features: ShaderFeature::all(),
+            float_render_targets: true,
+        }
+    }
+
+    /// The least a mobile GPU supports in WebGL
+    pub fn mobile_webgl() -> ShaderTarget {
+        ShaderTarget {
+            gl_es: true,
+            fragment_precision: Precision::Medium,
+            features: vec![ShaderFeature::Derivatives],
+            float_render_targets: false,
+        }
+    }
+
+    pub fn with_fragment_precision(mut self, precision: Precision) -> ShaderTarget {
+        self.fragment_precision = precision;
+        self
+    }
+
+    pub fn with_features(mut self, features: Vec<ShaderFeature>) -> ShaderTarget {
+        self.features = features;
+        self
+    }
+
+    pub fn with_float_render_targets(mut self, float_render_targets: bool) -> ShaderTarget {
+        self.float_render_targets = float_render_targets;
+        self
+    }
+
+    pub fn supports(&self, feature: ShaderFeature) -> bool {
+        !self.gl_es || self.features.contains(&feature)
+    }
+}
+
+/// A fallback chosen while patching a shader
+#[derive(Debug, Clone, PartialEq)]
+pub enum FallbackChoice {
+    /// The float precision of the fragment shader is lowered
+    Precision(Precision),
+    /// The feature is missing, `UNRUST_NO_<FEATURE>` is defined
+    Disabled(ShaderFeature),
+    /// The feature is missing and the shader uses it anyway
+    Unsupported(ShaderFeature),
+}
+
+impl FallbackChoice {
+    pub fn is_error(&self) -> bool {
+        match *self {
+            FallbackChoice::Unsupported(_) => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for FallbackChoice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FallbackChoice::Precision(p) => {
+                write!(f, "float precision lowered to {}", p.qualifier())
+            }
+            FallbackChoice::Disabled(feature) => {
+                write!(f, "{} missing, UNRUST_NO_{} defined", feature.extension(), feature.name())
+            }
+            FallbackChoice::Unsupported(feature) => write!(
+                f,
+                "{} missing and used without an UNRUST_NO_{} fallback",
+                feature.extension(),
+                feature.name()
+            ),
+        }
+    }
+}
+
+/// A fallback of a shader file, see `report`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShaderFallback {
+    pub shader: String,
+    pub choice: FallbackChoice,
+}
+
+impl fmt::Display for ShaderFallback {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.shader, self.choice)
+    }
+}
+
+#[derive(Default)]
+struct State {
+    target: Option<ShaderTarget>,
+    report: Vec<ShaderFallback>,
+}
+
+thread_local!(static STATE: RefCell<State> = RefCell::new(State::default()));
+
+/// The target of the shaders, detected the first time once the engine is created
+pub fn target() -> ShaderTarget {
+    STATE.with(|s| {
+        let mut s = s.borrow_mut();
+        if s.target.is_none() && !capabilities::is_queried() {
+            return detect();
+        }
+
+        let target = s.target.get_or_insert_with(detect).clone();
+        target
+    })
+}
+
+/// Override the detected target, before the shaders are loaded. The shaders already loaded
+/// are not patched again.
+pub fn set_target(target: ShaderTarget) {
+    STATE.with(|s| s.borrow_mut().target = Some(target));
+}
+
+/// The fallbacks chosen for the shaders loaded so far, the last time each was loaded
+pub fn report() -> Vec<ShaderFallback> {
+    STATE.with(|s| s.borrow().report.clone())
+}
+
+pub fn clear_report() {
+    STATE.with(|s| s.borrow_mut().report.clear());
+}
+
+pub(crate) fn record(shader: &str, choices: &[FallbackChoice]) {
+    for choice in choices.iter() {
+        if choice.is_error() {
+            println!("Shader {} will not compile: {}", shader, choice);
+        }
+    }
+
+    // A reloaded shader replaces its previous fallbacks
+    STATE.with(|s| {
+        let report = &mut s.borrow_mut().report;
+        report.retain(|f| f.shader != shader);
+        report.extend(choices.iter().map(|choice| ShaderFallback {
+            shader: shader.to_string(),
+            choice: choice.clone(),
+        }))
+    });
+}
+
+/// The target of the current platform, from the capabilities of the context of the engine
+/// on the web (see `capabilities`)
+pub fn detect() -> ShaderTarget {
+    if !uni_gl::IS_GL_ES {
+        return ShaderTarget::desktop();
+    }
+
+    // A native GLES device (e.g. a phone), or no engine yet
+    if !cfg!(target_arch = "wasm32") || !capabilities::is_queried() {
+        return ShaderTarget::mobile_webgl();
+    }
+
+    let caps = capabilities::capabilities();
+    let features = [
+        (caps.derivatives, ShaderFeature::Derivatives),
+        (caps.texture_lod, ShaderFeature::TextureLod),
+        (caps.frag_depth, ShaderFeature::FragDepth),
+    ].iter()
+        .filter(|&&(supported, _)| supported)
+        .map(|&(_, feature)| feature)
+        .collect();
+
+    ShaderTarget::webgl()
+        .with_fragment_precision(if caps.fragment_highp {
+            Precision::High
+        } else {
+            Precision::Medium
+        })
+        .with_features(features)
+        .with_float_render_targets(caps.float_render_targets)
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Byte offsets of the whole identifier `name` in the code
+pub(crate) fn find_identifier(code: &str, name: &str) -> Vec<usize> {
+    code.match_indices(name)
+        .map(|(i, _)| i)
+        .filter(|i| {
+            let before = code[..*i].chars().next_back();
+            let after = code[*i + name.len()..].chars().next();
+            !before.map_or(false, is_identifier_char) && !after.map_or(false, is_identifier_char)
+        })
+        .collect()
+}
+
+/// The code with its comments replaced by spaces, the lines are kept
+pub(crate) fn strip_comments(code: &str) -> String {
+    let mut out = String::with_capacity(code.len());
+    let mut rest = code;
+
+    loop {
+        let line = rest.find("//");
+        let block = rest.find("/*");
+        let (start, end_marker) = match (line, block) {
+            (Some(l), Some(b)) if b < l => (b, "*/"),
+            (Some(l), _) => (l, "\n"),
+            (None, Some(b)) => (b, "*/"),
+            (None, None) => break,
+        };
+
+        out.push_str(&rest[..start]);
+        out.push(' ');
+        rest = &rest[start + 2..];
+
+        match rest.find(end_marker) {
+            Some(end) => {
+                out.extend(rest[..end].chars().filter(|c| *c == '\n'));
+                rest = match end_marker {
+                    "*/" => &rest[end + 2..],
+                    _ => &rest[end..],
+                };
+            }
+            // Unterminated, the comment runs to the end
+            None => return out,
+        }
+    }
+
+    out.push_str(rest);
+    out
+}
+
+fn has_identifier(code: &str, name: &str) -> bool {
+    !find_identifier(code, name).is_empty()
+}
+
+fn replace_identifier(code: &str, name: &str, with: &str) -> String {
+    let mut out = String::with_capacity(code.len());
+    let mut last = 0;
+    for i in find_identifier(code, name).into_iter() {
+        out.push_str(&code[last..i]);
+        out.push_str(with);
+        last = i + name.len();
+    }
+    out.push_str(&code[last..]);
+    out
+}
+
+/// The `UNRUST_NO_<FEATURE>` defines of the features used by a preprocessed shader and
+/// missing on the target. Only the GLSL ES 1.0 fragment shaders have optional features.
+pub(crate) fn missing_features(
+    kind: ShaderKind,
+    glsl_300es: bool,
+    code: &str,
+    target: &ShaderTarget,
+) -> Vec<ShaderFeature> {
+    if kind != ShaderKind::Fragment || glsl_300es {
+        return Vec::new();
+    }
+
+    ShaderFeature::all()
+        .into_iter()
+        .filter(|f| !target.supports(*f) && f.is_used(code))
+        .collect()
+}
+
+/// Patch a preprocessed shader for the target, once the missing features are defined.
+/// Returns the extension directives to put before the code, the patched code and the
+/// fallbacks chosen.
+pub(crate) fn patch(
+    kind: ShaderKind,
+    glsl_300es: bool,
+    code: &str,
+    target: &ShaderTarget,
+    disabled: &[ShaderFeature],
+) -> (String, String, Vec<FallbackChoice>) {
+    let mut choices = Vec::new();
+    let mut extensions = String::new();
+    let mut code = code.to_string();
+
+    if !target.gl_es || kind != ShaderKind::Fragment {
+        return (extensions, code, choices);
+    }
+
+    if !glsl_300es {
+        for feature in ShaderFeature::all().into_iter() {
+            if !feature.is_used(&code) {
+                if disabled.contains(&feature) {
+                    choices.push(FallbackChoice::Disabled(feature));
+                }
+                continue;
+            }
+
+            if !target.supports(feature) {
+                choices.push(FallbackChoice::Unsupported(feature));
+                continue;
+            }
+
+            extensions += &format!("#extension {} : enable\n", feature.extension());
+            for &(name, ext) in feature.builtins().iter() {
+                code = replace_identifier(&code, name, ext);
+            }
+        }
+    }
+
+    if target.fragment_precision < Precision::High {
+        let qualifier = target.fragment_precision.qualifier();
+        code = replace_identifier(&code, "highp", qualifier);
+        choices.push(FallbackChoice::Precision(target.fragment_precision));
+    }
+
+    (extensions, code, choices)
+}
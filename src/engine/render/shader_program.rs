@@ -1,5 +1,7 @@
 use engine::asset::{Asset, AssetResult, AssetSystem, FileFuture, LoadableAsset, Resource};
+use engine::render::capabilities;
 use engine::render::shader::{ShaderFs, ShaderVs};
+use engine::render::shader_platform::{find_identifier, strip_comments};
 use engine::render::uniforms::*;
 use std::cell::RefCell;
 use std::collections::HashMap;
@@ -16,6 +18,7 @@ pub enum ShaderAttrib {
     Normal = 2,
     Tangent = 3,
     Bitangent = 4,
+    UV1 = 5,
 }
 
 impl Asset for ShaderProgram {
@@ -30,6 +33,7 @@ impl Asset for ShaderProgram {
 
             vs_shader: vs,
             fs_shader: fs,
+            feedback_varyings: Vec::new(),
         })
     }
 }
@@ -68,9 +72,30 @@ pub struct ShaderProgram {
     fs_shader: Resource<ShaderFs>,
 
     uniform_cache: UniformCache,
+
+    /// Outputs of the vertex shader captured by transform feedback
+    feedback_varyings: Vec<String>,
 }
 
 impl ShaderProgram {
+    /// A program whose vertex shader outputs `varyings` are captured by transform feedback,
+    /// in a buffer each. Only in WebGL2, see `capabilities::transform_feedback_varyings`.
+    pub fn new_with_feedback(
+        (vs, fs): (Resource<ShaderVs>, Resource<ShaderFs>),
+        varyings: Vec<String>,
+    ) -> Rc<ShaderProgram> {
+        Rc::new(ShaderProgram {
+            gl_state: RefCell::new(None),
+
+            coord_map: Default::default(),
+            uniform_cache: Default::default(),
+
+            vs_shader: vs,
+            fs_shader: fs,
+            feedback_varyings: varyings,
+        })
+    }
+
     pub fn bind(&self, gl: &WebGLRenderingContext) -> AssetResult<()> {
         self.prepare(gl)?;
 
@@ -92,12 +117,33 @@ impl ShaderProgram {
         let vs = self.vs_shader.try_borrow()?;
         let fs = self.fs_shader.try_borrow()?;
 
-        let state = Some(ShaderProgramGLState::new(gl, &vs, &fs));
+        let state = Some(ShaderProgramGLState::new(
+            gl,
+            &vs,
+            &fs,
+            &self.feedback_varyings,
+        ));
         *self.gl_state.borrow_mut() = state;
 
         Ok(())
     }
 
+    /// Poll the shader files, `Ok` once loaded
+    pub fn status(&self) -> AssetResult<()> {
+        self.vs_shader.status()?;
+        self.fs_shader.status()
+    }
+
+    /// Whether the vertex shader reads an attribute (e.g. `aVertexNormal`),
+    /// `None` until the shader is loaded
+    pub fn uses_attribute(&self, name: &str) -> Option<bool> {
+        let vs = self.vs_shader.try_borrow().ok()?;
+        let code = strip_comments(&vs.code.as_string());
+
+        // Whole names only, `aTextureCoord` is not used by a shader reading `aTextureCoord2`
+        Some(!find_identifier(&code, name).is_empty())
+    }
+
     pub fn attrib_loc(&self, gl: &WebGLRenderingContext, s: &str) -> Option<u32> {
         let mut m = self.coord_map.borrow_mut();
 
@@ -134,6 +180,7 @@ impl ShaderProgramGLState {
         gl: &WebGLRenderingContext,
         vs_unit: &ShaderVs,
         fs_unit: &ShaderFs,
+        feedback_varyings: &[String],
     ) -> ShaderProgramGLState {
         /*================ Shaders ====================*/
 
@@ -182,6 +229,7 @@ impl ShaderProgramGLState {
             ShaderAttrib::Position as _,
         );
         gl.bind_attrib_location(&shader_program, "aTextureCoord", ShaderAttrib::UV0 as _);
+        gl.bind_attrib_location(&shader_program, "aTextureCoord2", ShaderAttrib::UV1 as _);
         gl.bind_attrib_location(&shader_program, "aVertexNormal", ShaderAttrib::Normal as _);
         gl.bind_attrib_location(
             &shader_program,
@@ -194,6 +242,11 @@ impl ShaderProgramGLState {
             ShaderAttrib::Bitangent as _,
         );
 
+        // The captured outputs are set before linking
+        if !feedback_varyings.is_empty() {
+            capabilities::transform_feedback_varyings(gl, &shader_program, feedback_varyings);
+        }
+
         // Link both the programs
         gl.link_program(&shader_program);
 
@@ -0,0 +1,161 @@
+use engine::asset::AssetSystem;
+use engine::render::{Material, RenderTexture, TextureAttachment};
+
+use math::*;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+/// Number of jitter positions before the sequence repeats
+const JITTER_SAMPLES: usize = 8;
+
+fn halton(mut index: usize, base: usize) -> f32 {
+    let mut f = 1.0;
+    let mut r = 0.0;
+
+    while index > 0 {
+        f /= base as f32;
+        r += f * (index % base) as f32;
+        index /= base;
+    }
+
+    r
+}
+
+/// `packUnit16` of `unrust/float_target.glsl`
+fn pack_unit16(v: f32) -> (u8, u8) {
+    let v = (v.max(0.0).min(1.0) * 65280.0 + 0.5).floor() as u32;
+    ((v / 256) as u8, (v % 256) as u8)
+}
+
+fn unpack_unit16(hi: u8, lo: u8) -> f32 {
+    (hi as u32 * 256 + lo as u32) as f32 / 65280.0
+}
+
+/// Temporal antialiasing of a camera, see `Camera::taa`
+///
+/// The projection is jittered by a subpixel offset each frame, and the frames are accumulated
+/// in a history texture reprojected by the motion vectors. The history is clamped to the
+/// neighborhood of the current pixel to limit the ghosting. It is resolved before the
+/// exposure and the post effects.
+pub struct TemporalAA {
+    /// Weight of the current frame, lower is smoother but ghosts longer
+    pub blend: f32,
+    /// Size of the jitter in pixels
+    pub jitter_scale: f32,
+
+    frame: Cell<usize>,
+    state: RefCell<Option<TaaState>>,
+}
+
+impl Default for TemporalAA {
+    fn default() -> TemporalAA {
+        TemporalAA {
+            blend: 0.1,
+            jitter_scale: 1.0,
+            frame: Cell::new(0),
+            state: RefCell::new(None),
+        }
+    }
+}
+
+impl TemporalAA {
+    pub fn new() -> TemporalAA {
+        Default::default()
+    }
+
+    /// Subpixel offset of the projection for the current frame, in pixels
+    pub fn jitter(&self) -> Vector2<f32> {
+        let i = self.frame.get() % JITTER_SAMPLES + 1;
+        Vector2::new(halton(i, 2) - 0.5, halton(i, 3) - 0.5) * self.jitter_scale
+    }
+
+    /// A motion in uv units as packed in the RGBA8 motion vectors, without float render
+    /// targets. It is clamped to [-1, 1].
+    pub fn encode_motion(motion: Vector2<f32>) -> [u8; 4] {
+        let x = pack_unit16(motion.x * 0.5 + 0.5);
+        let y = pack_unit16(motion.y * 0.5 + 0.5);
+        [x.0, x.1, y.0, y.1]
+    }
+
+    /// A motion packed by `encode_motion`
+    pub fn decode_motion(texel: [u8; 4]) -> Vector2<f32> {
+        Vector2::new(
+            unpack_unit16(texel[0], texel[1]) * 2.0 - 1.0,
+            unpack_unit16(texel[2], texel[3]) * 2.0 - 1.0,
+        )
+    }
+
+    /// Drop the history, e.g. after a camera cut
+    pub fn reset(&self) {
+        if let Some(ref mut state) = *self.state.borrow_mut() {
+            state.valid = false;
+        }
+    }
+
+    /// The material resolving the current frame into the returned history texture.
+    /// `uDiffuse` and `uMotion` are set by the caller.
+    pub(crate) fn resolve(
+        &self,
+        asys: &AssetSystem,
+        hdr: bool,
+        size: (u32, u32),
+    ) -> (Rc<Material>, Rc<RenderTexture>) {
+        let mut state_ref = self.state.borrow_mut();
+
+        let outdated = state_ref
+            .as_ref()
+            .map_or(true, |s| s.size != size || s.hdr != hdr);
+        if outdated {
+            *state_ref = Some(TaaState::new(asys, hdr, size));
+        }
+
+        let state = state_ref.as_mut().unwrap();
+        let frame = self.frame.get();
+        let prev = state.history[frame % 2].clone();
+        let next = state.history[(frame + 1) % 2].clone();
+
+        let material = state.material.clone();
+        material.set("uHistory", prev.as_texture());
+        material.set("uReset", !state.valid);
+        material.set("uBlend", self.blend.max(0.0).min(1.0));
+        material.set(
+            "uTexel",
+            Vector2::new(1.0 / size.0 as f32, 1.0 / size.1 as f32),
+        );
+
+        state.valid = true;
+        self.frame.set(frame.wrapping_add(1));
+
+        (material, next)
+    }
+}
+
+struct TaaState {
+    size: (u32, u32),
+    hdr: bool,
+    history: [Rc<RenderTexture>; 2],
+    material: Rc<Material>,
+    /// The history contains a previous frame
+    valid: bool,
+}
+
+impl TaaState {
+    fn new(asys: &AssetSystem, hdr: bool, size: (u32, u32)) -> TaaState {
+        let new_rt = || {
+            let attach = if hdr {
+                TextureAttachment::Color0Float
+            } else {
+                TextureAttachment::Color0
+            };
+            Rc::new(RenderTexture::new(size.0, size.1, attach))
+        };
+
+        TaaState {
+            size,
+            hdr,
+            history: [new_rt(), new_rt()],
+            material: Rc::new(Material::new(asys.new_program("unrust/taa"))),
+            valid: false,
+        }
+    }
+}
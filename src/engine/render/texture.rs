@@ -3,7 +3,10 @@ use uni_gl::*;
 
 use image::{RgbImage, RgbaImage};
 
-use engine::asset::{Asset, AssetResult, AssetSystem, FileFuture, LoadableAsset, Resource, DDS};
+use engine::asset::{Asset, AssetResult, AssetSystem, DDSImage, FileFuture, LoadableAsset, Resource,
+                    DDS};
+use engine::diagnostics;
+use engine::render::capabilities;
 use std::cell::{Cell, RefCell};
 use std::path::Path;
 use std::rc::Rc;
@@ -27,11 +30,40 @@ pub enum TextureImage {
     Rgb(RgbImage),
     DXT1(DDS),
     DXT5(DDS),
+    /// Blocks of the other compressed formats, e.g. transcoded from a basis texture
+    Compressed(CompressedImage),
+}
+
+/// Mip levels of a compressed format without a `uni_gl` enum, e.g. ETC2, ASTC or PVRTC
+#[derive(Debug, Clone)]
+pub struct CompressedImage {
+    /// GL internal format, e.g. `COMPRESSED_RGBA_ASTC_4x4_KHR`
+    pub gl_format: u32,
+    pub images: Vec<DDSImage>,
+}
+
+const GL_TEXTURE_2D: u32 = 0x0DE1;
+const GL_TEXTURE_CUBE_MAP_POSITIVE_X: u32 = 0x8515;
+
+fn upload_compressed(gl: &WebGLRenderingContext, target: u32, img: &CompressedImage) {
+    for (lvl, level) in img.images.iter().enumerate() {
+        capabilities::compressed_tex_image2d(
+            gl,
+            target,
+            lvl as u32,
+            img.gl_format,
+            (level.width, level.height),
+            &level.data,
+        );
+    }
 }
 
 #[derive(Debug)]
 pub enum TextureAttachment {
     Color0,
+    /// Color with a float per channel. Without float render targets it is RGBA8, and the
+    /// shaders writing it pack their values with `unrust/float_target.glsl`.
+    Color0Float,
     Depth,
 }
 
@@ -150,6 +182,14 @@ impl LoadableAsset for Texture {
 struct TextureGLState {
     tex: WebGLTexture,
     size: (u32, u32),
+    // Estimated bytes, for diagnostics
+    bytes: usize,
+}
+
+impl Drop for TextureGLState {
+    fn drop(&mut self) {
+        diagnostics::track_texture(self.bytes, false);
+    }
 }
 
 impl Texture {
@@ -167,8 +207,45 @@ impl Texture {
         })
     }
 
+    /// Size of the texture once loaded, the size of the first face for a cube map
     pub fn size(&self) -> Option<(u32, u32)> {
-        self.gl_state.borrow().as_ref().map(|s| s.size)
+        if let Some(ref state) = *self.gl_state.borrow() {
+            return Some(state.size);
+        }
+
+        let res = match self.kind {
+            TextureKind::Image(ref res) => res,
+            TextureKind::CubeMap(ref res) => &res[0],
+            TextureKind::RenderTexture { size, .. } => return Some(size),
+        };
+
+        if res.is_consumed() {
+            return None;
+        }
+
+        let img = res.try_borrow().ok()?;
+        match *img {
+            TextureImage::Rgba(ref img) => Some((img.width(), img.height())),
+            TextureImage::Rgb(ref img) => Some((img.width(), img.height())),
+            TextureImage::DXT1(ref dds) | TextureImage::DXT5(ref dds) => {
+                dds.images.first().map(|i| (i.width, i.height))
+            }
+            TextureImage::Compressed(ref img) => img.images.first().map(|i| (i.width, i.height)),
+        }
+    }
+
+    /// Poll the image files, `Ok` once loaded
+    pub fn status(&self) -> AssetResult<()> {
+        match self.kind {
+            TextureKind::Image(ref res) => res.status(),
+            TextureKind::CubeMap(ref res) => {
+                for face in res.iter() {
+                    face.status()?;
+                }
+                Ok(())
+            }
+            TextureKind::RenderTexture { .. } => Ok(()),
+        }
     }
 
     pub fn bind(&self, gl: &WebGLRenderingContext, unit: u32) -> AssetResult<()> {
@@ -200,7 +277,7 @@ impl Texture {
 
         if let TextureKind::RenderTexture { ref attach, .. } = self.kind {
             match attach {
-                &TextureAttachment::Color0 => {
+                &TextureAttachment::Color0 | &TextureAttachment::Color0Float => {
                     bind_to_framebuffer(gl, &state.tex, Buffers::ColorAttachment0);
                 }
                 &TextureAttachment::Depth => {
@@ -213,6 +290,18 @@ impl Texture {
         Ok(())
     }
 
+    /// Attach a depth render texture to current frame buffer, along with a color attachment
+    pub fn bind_as_depth_buffer(&self, gl: &WebGLRenderingContext) -> AssetResult<()> {
+        self.prepare(gl, 0)?;
+
+        let state_option = self.gl_state.borrow();
+        let state = state_option.as_ref().unwrap();
+
+        bind_to_framebuffer(gl, &state.tex, Buffers::DepthAttachment);
+
+        Ok(())
+    }
+
     pub fn prepare(&self, gl: &WebGLRenderingContext, unit: u32) -> AssetResult<()> {
         if self.gl_state.borrow().is_some() {
             return Ok(());
@@ -351,6 +440,12 @@ fn texture_bind_buffer(
 
                     has_midmap = dds.images.len() > 1;
                 }
+
+                TextureImage::Compressed(img) => {
+                    size = (img.images[0].width, img.images[0].height);
+                    upload_compressed(gl, GL_TEXTURE_2D, &img);
+                    has_midmap = img.images.len() > 1;
+                }
             }
 
             (tex, size, has_midmap)
@@ -447,6 +542,13 @@ fn texture_bind_buffer(
 
                         has_midmap = dds.images.len() > 1;
                     }
+
+                    &TextureImage::Compressed(ref img) => {
+                        size = (img.images[0].width, img.images[0].height);
+                        let target = GL_TEXTURE_CUBE_MAP_POSITIVE_X + i as u32;
+                        upload_compressed(gl, target, img);
+                        has_midmap = img.images.len() > 1;
+                    }
                 }
             }
 
@@ -461,8 +563,20 @@ fn texture_bind_buffer(
         }
 
         &TextureKind::RenderTexture { size, ref attach } => {
+            let mut float_storage = false;
             let (fmt, data_type) = match attach {
                 &TextureAttachment::Color0 => (PixelFormat::Rgba, PixelType::UnsignedByte),
+                &TextureAttachment::Color0Float => {
+                    // Linear filtering of float textures needs another extension, and
+                    // the packed values cannot be filtered
+                    force_nearest_filtering = true;
+                    if capabilities::capabilities().float_render_targets {
+                        float_storage = true;
+                        (PixelFormat::Rgba, PixelType::Float)
+                    } else {
+                        (PixelFormat::Rgba, PixelType::UnsignedByte)
+                    }
+                }
                 &TextureAttachment::Depth => {
                     force_nearest_filtering = true;
                     (PixelFormat::DepthComponent, PixelType::UnsignedShort)
@@ -481,6 +595,9 @@ fn texture_bind_buffer(
                 data_type,                   // type
                 &[],                         // data
             );
+            if float_storage {
+                capabilities::alloc_float_texture(gl, size);
+            }
 
             (tex, size, false)
         }
@@ -545,5 +662,20 @@ fn texture_bind_buffer(
 
     //unbind_texture(gl, kind);
 
-    Ok(TextureGLState { tex, size })
+    // 4 bytes per pixel (16 for float), a third more with mipmaps
+    let (faces, pixel_bytes) = match kind {
+        &TextureKind::CubeMap(..) => (6, 4),
+        &TextureKind::RenderTexture {
+            attach: TextureAttachment::Color0Float,
+            ..
+        } if capabilities::capabilities().float_render_targets => (1, 16),
+        _ => (1, 4),
+    };
+    let mut bytes = size.0 as usize * size.1 as usize * pixel_bytes * faces;
+    if has_midmap {
+        bytes += bytes / 3;
+    }
+    diagnostics::track_texture(bytes, true);
+
+    Ok(TextureGLState { tex, size, bytes })
 }
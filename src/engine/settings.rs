@@ -0,0 +1,47 @@
+//! User settings
+//!
+//! `Settings` is a plain serializable struct, `World` applies it every frame,
+//! so changing a field takes effect immediately.
+
+use engine::accessibility::AccessibilitySettings;
+use engine::asset::loader::{self, Loadable, Loader};
+use engine::asset::{AssetResult, AssetSystem, File, Resource};
+use serde_json;
+
+/// Settings file, e.g. :
+///
+/// ```json
+/// { "accessibility": { "colorblind_mode": "Deuteranopia", "colorblind_filter": "Compensate",
+///                      "ui_scale": 1.5, "high_contrast": true } }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct Settings {
+    #[serde(default)]
+    pub accessibility: AccessibilitySettings,
+}
+
+impl Settings {
+    pub fn load(asys: &AssetSystem, filename: &str) -> Resource<Settings> {
+        loader::load_json(asys, filename)
+    }
+
+    pub fn from_json(s: &str) -> Result<Settings, String> {
+        serde_json::from_str(s).map_err(|e| format!("{}", e))
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+
+pub struct SettingsLoader {}
+
+impl Loader<Settings> for SettingsLoader {
+    fn load<A>(_asys: A, mut file: Box<File>) -> AssetResult<Settings> {
+        loader::read_json(&mut file)
+    }
+}
+
+impl Loadable for Settings {
+    type Loader = SettingsLoader;
+}
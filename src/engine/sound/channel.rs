@@ -3,13 +3,60 @@ use std::sync::Arc;
 use super::SoundPlayEvent;
 use super::generator::SoundBuffer;
 
+use std::f32::consts::PI;
+
+/// One pole low-pass filter of the voices, for the occlusion
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LowPass {
+    state: f32,
+}
+
+impl LowPass {
+    pub fn new() -> LowPass {
+        Default::default()
+    }
+
+    /// Cutoff frequency in Hz of the `lowpass` of the voices, from 1.0 (20 kHz) to 0.0
+    /// (silent), on a logarithmic scale
+    pub fn cutoff(lowpass: f32) -> f32 {
+        if lowpass <= 0.0 {
+            0.0
+        } else {
+            20.0 * 1000f32.powf(lowpass.min(1.0))
+        }
+    }
+
+    /// Coefficient of the filter at `cutoff` Hz, from 0.0 (silent) to 1.0 (unfiltered)
+    pub fn alpha(cutoff: f32, sample_rate: f32) -> f32 {
+        if cutoff.is_nan() || sample_rate.is_nan() || cutoff <= 0.0 || sample_rate <= 0.0 {
+            return 0.0;
+        }
+
+        1.0 - (-2.0 * PI * cutoff / sample_rate).exp()
+    }
+
+    pub fn process(&mut self, input: f32, cutoff: f32, sample_rate: f32) -> f32 {
+        // A filter closed at 0 Hz is silent, instead of holding its last value
+        if cutoff <= 0.0 {
+            self.state = 0.0;
+            return 0.0;
+        }
+
+        self.state += (input - self.state) * LowPass::alpha(cutoff, sample_rate);
+        self.state
+    }
+}
+
 pub struct Channel {
     event: Option<SoundPlayEvent>,
     buffer: Option<Arc<SoundBuffer>>,
     sample_rate: f32,
     t: f32,
     delta_t: f32,
+    pitch: f32,
     cur_output: usize,
+    /// Low-pass filter of each output
+    filtered: [LowPass; 2],
 }
 
 impl Channel {
@@ -19,19 +66,41 @@ impl Channel {
             buffer: None,
             t: 0.0,
             delta_t: 0.0,
+            pitch: 1.0,
             sample_rate: 1.0,
             cur_output: 0,
+            filtered: [LowPass::new(); 2],
         }
     }
     pub fn set_sample_rate(&mut self, sample_rate: f32) {
         self.sample_rate = sample_rate;
     }
+    pub fn set_pitch(&mut self, pitch: f32) {
+        self.pitch = pitch;
+    }
     pub fn set_event(&mut self, evt: SoundPlayEvent, buffer: Arc<SoundBuffer>) {
         self.event = Some(evt);
         self.delta_t = buffer.sample_rate as f32 / self.sample_rate;
         self.buffer = Some(buffer);
         self.cur_output = 0;
         self.t = 0.0;
+        self.filtered = [LowPass::new(); 2];
+    }
+    /// The voice of the playing sound, 0 for none
+    pub fn voice(&self) -> usize {
+        self.event.map_or(0, |e| e.voice)
+    }
+    pub fn set_voice_pitch(&mut self, pitch: f32) {
+        if let Some(ref mut event) = self.event {
+            event.pitch = pitch;
+        }
+    }
+    pub fn set_params(&mut self, volume: f32, balance: f32, lowpass: f32) {
+        if let Some(ref mut event) = self.event {
+            event.volume = volume;
+            event.balance = balance;
+            event.lowpass = lowpass;
+        }
     }
     pub fn is_free(&self) -> bool {
         self.event.is_none()
@@ -45,7 +114,8 @@ impl Channel {
             } else {
                 ret = buffer.samples[sample_idx * buffer.output_count + self.cur_output];
             }
-            if self.delta_t != 1.0 {
+            let delta_t = self.delta_t * self.pitch * self.event.map_or(1.0, |e| e.pitch);
+            if delta_t != 1.0 {
                 // interpolate samples when buffer sample rate is not equal to driver sample rate
                 let interpol_coef = self.t - sample_idx as f32;
                 if interpol_coef > 0.0 {
@@ -69,10 +139,14 @@ impl Channel {
                     + (1.0 - event.balance) * (1.0 - self.cur_output as f32);
             }
             ret *= event.volume;
+            if event.lowpass < 1.0 {
+                let cutoff = LowPass::cutoff(event.lowpass);
+                ret = self.filtered[self.cur_output].process(ret, cutoff, self.sample_rate);
+            }
             // alternate between left/right output channels
             self.cur_output = 1 - self.cur_output;
             if self.cur_output == 0 {
-                self.t += self.delta_t;
+                self.t += delta_t;
                 if self.t as usize * buffer.output_count >= buffer.samples.len() {
                     if self.event.unwrap().do_loop {
                         self.t = 0.0;
@@ -1,11 +1,15 @@
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use uni_snd::SoundGenerator;
 use hound::WavReader;
 
 use super::{SoundEvent, SoundPlayEvent};
 use super::channel::Channel;
+use super::music_mixer::MusicMixer;
+use super::reverb::Reverb;
+use super::stream::Stream;
+use super::synth::{Synth, SynthGraph};
 
 pub struct SoundBuffer {
     /// number of channels. 1:mono, 2: stereo
@@ -19,11 +23,20 @@ pub struct SoundBuffer {
 pub struct Generator {
     cache: HashMap<usize, Arc<SoundBuffer>>,
     channels: Vec<Channel>,
+    streams: HashMap<usize, Stream>,
+    /// Playing synths by voice
+    synths: HashMap<usize, Synth>,
     next_channel: usize,
+    sample_rate: f32,
+    reverb: Reverb,
+    music: MusicMixer,
+    cur_output: usize,
+    /// Voices whose channel was taken, or which found no channel
+    lost_voices: Arc<Mutex<Vec<usize>>>,
 }
 
 impl Generator {
-    pub fn new(channel_count: usize) -> Self {
+    pub fn new(channel_count: usize, lost_voices: Arc<Mutex<Vec<usize>>>) -> Self {
         let mut channels = Vec::new();
         for _ in 0..channel_count {
             channels.push(Channel::new());
@@ -31,7 +44,21 @@ impl Generator {
         Self {
             cache: HashMap::new(),
             channels,
+            streams: HashMap::new(),
+            synths: HashMap::new(),
             next_channel: 0,
+            sample_rate: 1.0,
+            reverb: Reverb::new(44100.0),
+            music: MusicMixer::new(44100.0),
+            cur_output: 0,
+            lost_voices,
+        }
+    }
+    fn lose_voice(&self, voice: usize) {
+        if voice != 0 {
+            if let Ok(mut lost) = self.lost_voices.lock() {
+                lost.push(voice);
+            }
         }
     }
     fn handle_play_event(&mut self, evt: &SoundPlayEvent) {
@@ -61,8 +88,10 @@ impl Generator {
             }
         }
         match free_channel_id {
-            None => return, // no channel available. skip this sound
+            // no channel available. skip this sound
+            None => self.lose_voice(evt.voice),
             Some(id) => {
+                self.lose_voice(self.channels[id].voice());
                 self.channels[id].set_event(*evt, self.cache.get(&evt.id).unwrap().clone());
             }
         }
@@ -76,6 +105,49 @@ impl Generator {
             self.channels[channel].clear();
         }
     }
+    fn handle_voice_params_event(&mut self, voice: usize, volume: f32, balance: f32, lowpass: f32) {
+        if let Some(synth) = self.synths.get_mut(&voice) {
+            synth.set_params(volume, balance);
+        }
+        for chan in self.channels.iter_mut().filter(|c| c.voice() == voice) {
+            chan.set_params(volume, balance, lowpass);
+        }
+    }
+    fn handle_voice_pitch_event(&mut self, voice: usize, pitch: f32) {
+        for chan in self.channels.iter_mut().filter(|c| c.voice() == voice) {
+            chan.set_voice_pitch(pitch);
+        }
+    }
+    fn handle_play_synth_event(
+        &mut self,
+        voice: usize,
+        graph: SynthGraph,
+        volume: f32,
+        balance: f32,
+    ) {
+        let synth = Synth::new(graph, self.sample_rate, volume, balance);
+        self.synths.insert(voice, synth);
+    }
+    fn handle_stop_voice_event(&mut self, voice: usize) {
+        self.synths.remove(&voice);
+        for chan in self.channels.iter_mut().filter(|c| c.voice() == voice) {
+            chan.clear();
+        }
+    }
+    fn handle_stream_data_event(&mut self, id: usize, sample_rate: usize, samples: Vec<f32>) {
+        let driver_sample_rate = self.sample_rate;
+        self.streams
+            .entry(id)
+            .or_insert_with(|| Stream::new(driver_sample_rate))
+            .push(sample_rate, samples);
+    }
+    fn handle_stream_params_event(&mut self, id: usize, volume: f32, balance: f32) {
+        let driver_sample_rate = self.sample_rate;
+        self.streams
+            .entry(id)
+            .or_insert_with(|| Stream::new(driver_sample_rate))
+            .set_params(volume, balance);
+    }
     fn new_buffer(&mut self, buffer: Vec<u8>, filepath: &str) -> SoundBuffer {
         let mut wav =
             WavReader::new(&buffer[..]).expect(&format!("error cannot read from {}", filepath));
@@ -110,6 +182,9 @@ impl Generator {
 
 impl SoundGenerator<SoundEvent> for Generator {
     fn init(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.reverb = Reverb::new(sample_rate);
+        self.music = MusicMixer::new(sample_rate);
         for chan in self.channels.iter_mut() {
             chan.set_sample_rate(sample_rate);
         }
@@ -121,6 +196,39 @@ impl SoundGenerator<SoundEvent> for Generator {
                 self.handle_load_buffer_event(id, buffer, filepath)
             }
             SoundEvent::StopChannel(channel) => self.handle_stop_channel_event(channel),
+            SoundEvent::VoiceParams(voice, volume, balance, lowpass) => {
+                self.handle_voice_params_event(voice, volume, balance, lowpass)
+            }
+            SoundEvent::VoicePitch(voice, pitch) => self.handle_voice_pitch_event(voice, pitch),
+            SoundEvent::PlaySynth(voice, graph, volume, balance) => {
+                self.handle_play_synth_event(voice, graph, volume, balance)
+            }
+            SoundEvent::SynthParam(voice, name, value) => {
+                if let Some(synth) = self.synths.get_mut(&voice) {
+                    synth.set_param(&name, value);
+                }
+            }
+            SoundEvent::StopVoice(voice) => self.handle_stop_voice_event(voice),
+            SoundEvent::StreamData(id, sample_rate, samples) => {
+                self.handle_stream_data_event(id, sample_rate, samples)
+            }
+            SoundEvent::StreamParams(id, volume, balance) => {
+                self.handle_stream_params_event(id, volume, balance)
+            }
+            SoundEvent::StopStream(id) => {
+                self.streams.remove(&id);
+            }
+            SoundEvent::Reverb(params, fade_time) => self.reverb.set_params(params, fade_time),
+            SoundEvent::Pitch(pitch) => {
+                for chan in self.channels.iter_mut() {
+                    chan.set_pitch(pitch);
+                }
+            }
+            SoundEvent::Music(music_evt) => {
+                let cache = &self.cache;
+                self.music
+                    .handle_event(music_evt, |id| cache.get(&id).cloned())
+            }
         }
     }
     fn next_value(&mut self) -> f32 {
@@ -128,6 +236,22 @@ impl SoundGenerator<SoundEvent> for Generator {
         for chan in self.channels.iter_mut() {
             sample += chan.next_value();
         }
-        sample / self.channels.len() as f32
+        for stream in self.streams.values_mut() {
+            sample += stream.next_value();
+        }
+        for synth in self.synths.values_mut() {
+            sample += synth.next_value();
+        }
+        if self.cur_output == 1 {
+            self.synths.retain(|_, s| !s.is_finished());
+        }
+        let sample = self.reverb
+            .process(sample / self.channels.len() as f32, self.cur_output);
+        // music is not part of the scene, so it is not reverberated
+        let sample = sample + self.music.next_value();
+
+        // alternate between left/right output channels
+        self.cur_output = 1 - self.cur_output;
+        sample
     }
 }
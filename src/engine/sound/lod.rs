@@ -0,0 +1,92 @@
+use std::cmp::Ordering;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioLodLevel {
+    /// Updated each frame
+    Full,
+    /// Updated every `AudioLod::reduced_interval` frames
+    Reduced,
+    /// Not spatialized: centered, without occlusion or doppler, and updated every
+    /// `AudioLod::flat_interval` frames
+    Flat,
+}
+
+/// Levels of detail of the positional sounds, and the limit of voices they play.
+///
+/// When more emitters request a voice than `max_voices`, the voices go to the highest
+/// priorities, then to the loudest. The others are virtual: silent until they get a voice
+/// again.
+#[derive(Debug, Clone)]
+pub struct AudioLod {
+    pub max_voices: usize,
+    /// Distance from the listener of the `Reduced` level
+    pub reduced_distance: f32,
+    /// Distance from the listener of the `Flat` level
+    pub flat_distance: f32,
+    /// Frames between the updates of the `Reduced` level
+    pub reduced_interval: u32,
+    /// Frames between the updates of the `Flat` level
+    pub flat_interval: u32,
+    /// Sounds attenuated by the occlusion below this volume are `Reduced` at any distance
+    pub occluded_volume: f32,
+    /// Voices quieter than this are virtual
+    pub min_audibility: f32,
+}
+
+impl AudioLod {
+    pub fn new(max_voices: usize) -> AudioLod {
+        AudioLod {
+            max_voices,
+            reduced_distance: 15.0,
+            flat_distance: 40.0,
+            reduced_interval: 4,
+            flat_interval: 16,
+            occluded_volume: 0.3,
+            min_audibility: 0.001,
+        }
+    }
+
+    /// Level of an emitter at `distance` from the listener, with the volume multiplier of
+    /// its occlusion
+    pub fn level(&self, distance: f32, occlusion: f32) -> AudioLodLevel {
+        if distance >= self.flat_distance {
+            AudioLodLevel::Flat
+        } else if distance >= self.reduced_distance || occlusion < self.occluded_volume {
+            AudioLodLevel::Reduced
+        } else {
+            AudioLodLevel::Full
+        }
+    }
+
+    /// Frames between the updates of an emitter of `level`
+    pub fn update_interval(&self, level: AudioLodLevel) -> u32 {
+        match level {
+            AudioLodLevel::Full => 1,
+            AudioLodLevel::Reduced => self.reduced_interval.max(1),
+            AudioLodLevel::Flat => self.flat_interval.max(1),
+        }
+    }
+
+    /// Whether an emitter of `level` updates at `frame`
+    pub fn is_update_frame(&self, level: AudioLodLevel, frame: u32) -> bool {
+        frame % self.update_interval(level) == 0
+    }
+
+    /// Indices of the requests getting a voice, from (priority, audibility) requests, the
+    /// audibility being the volume heard by the listener
+    pub fn allocate(&self, requests: &[(usize, f32)]) -> Vec<usize> {
+        let mut indices: Vec<usize> = (0..requests.len())
+            .filter(|&i| requests[i].1 >= self.min_audibility)
+            .collect();
+
+        indices.sort_by(|&a, &b| {
+            let (a, b) = (requests[a], requests[b]);
+            b.0
+                .cmp(&a.0)
+                .then(b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal))
+        });
+
+        indices.truncate(self.max_voices);
+        indices
+    }
+}
@@ -1,43 +1,145 @@
 mod channel;
 mod generator;
+mod lod;
+mod music;
+mod music_mixer;
+#[cfg(feature = "physics")]
+mod occlusion;
+mod reverb;
+mod stream;
+mod synth;
 
 use std::cell::RefCell;
 use std::rc::Rc;
+use std::sync::{Arc, Mutex};
 
-use engine::{AssetError, AssetSystem};
+use engine::{AssetError, AssetSystem, Camera};
+use math::*;
 use futures::Future;
-use std::collections::BTreeSet;
+use std::collections::{BTreeSet, HashSet};
 use std::collections::HashMap;
 use uni_snd::SoundDriver;
 
 use self::generator::Generator;
 
+pub use self::channel::LowPass;
+pub use self::lod::{AudioLod, AudioLodLevel};
+pub use self::music::{Music, MusicSection, MusicStem, MusicTrack};
+pub use self::music_mixer::Quantize;
+#[cfg(feature = "physics")]
+pub use self::occlusion::{AudioMaterial, AudioOcclusion, Occlusion};
+pub use self::reverb::ReverbParams;
+pub use self::synth::{Envelope, NodeId, SynthGraph, SynthInput, SynthNode, Waveform};
+
+use self::music_mixer::MusicEvent;
+
 const CHANNEL_COUNT: usize = 4;
 
 #[derive(Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq)]
 pub struct SoundHandle(usize);
 
+/// A sound fed progressively by `SoundSystem::queue_stream`
+#[derive(Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct StreamHandle(usize);
+
+/// A playing sound whose parameters change while playing, see `SoundSystem::play_voice`
+#[derive(Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct VoiceHandle(usize);
+
+/// A source of sounds sharing the voices, see `SoundSystem::request_voice`
+#[derive(Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct EmitterHandle(usize);
+
+/// Where sounds are heard from, for positional sounds
+#[derive(Debug, Clone, Copy)]
+pub struct AudioListener {
+    pub position: Vector3<f32>,
+    pub right: Vector3<f32>,
+}
+
+impl AudioListener {
+    pub fn from_camera(cam: &Camera) -> AudioListener {
+        AudioListener {
+            position: cam.eye(),
+            right: cam.right(),
+        }
+    }
+
+    /// (volume, balance) of a sound at `pos`, the volume falls linearly to 0 at `max_distance`
+    pub fn attenuate(&self, pos: Vector3<f32>, max_distance: f32) -> (f32, f32) {
+        let dir = pos - self.position;
+        let distance = dir.magnitude();
+
+        let volume = (1.0 - distance / max_distance).max(0.0).min(1.0);
+        let balance = if distance > 0.0001 {
+            0.5 + 0.5 * dir.dot(self.right) / distance
+        } else {
+            0.5
+        };
+
+        (volume, balance)
+    }
+}
+
 pub struct SoundSystem {
+    /// Reverb outside of any reverb zone
+    pub default_reverb: ReverbParams,
+    /// Crossfade time of reverb changes, in seconds
+    pub reverb_fade_time: f32,
+    /// Attenuation of the sounds behind the static colliders
+    #[cfg(feature = "physics")]
+    pub occlusion: AudioOcclusion,
+    pub lod: AudioLod,
+
     cache: HashMap<String, SoundHandle>,
 
     loading: Rc<RefCell<BTreeSet<SoundHandle>>>,
     pending_play: Vec<SoundPlayEvent>,
+    played: Vec<String>,
 
     next_handle: usize,
+    next_stream: usize,
+    next_voice: usize,
+    next_emitter: usize,
+    voice_requests: Vec<(EmitterHandle, usize, f32)>,
+    allowed_emitters: HashSet<EmitterHandle>,
+    lost_voices: Arc<Mutex<Vec<usize>>>,
+    lost: HashSet<usize>,
+    reverb_zones: Vec<(ReverbParams, f32, i32)>,
+    reverb: ReverbParams,
+    pitch: f32,
     driver: Rc<RefCell<SoundDriver<SoundEvent>>>,
     asys: Box<AssetSystem>,
 }
 
 impl SoundSystem {
     pub fn new(asys: Box<AssetSystem>) -> Self {
-        let mut driver = SoundDriver::new(Box::new(Generator::new(CHANNEL_COUNT)));
+        let lost_voices = Arc::new(Mutex::new(Vec::new()));
+        let generator = Generator::new(CHANNEL_COUNT, lost_voices.clone());
+        let mut driver = SoundDriver::new(Box::new(generator));
         driver.start();
         Self {
+            default_reverb: ReverbParams::none(),
+            reverb_fade_time: 0.5,
+            #[cfg(feature = "physics")]
+            occlusion: AudioOcclusion::new(),
+            lod: AudioLod::new(CHANNEL_COUNT),
             cache: HashMap::new(),
             next_handle: 0,
+            next_stream: 0,
+            next_voice: 0,
+            next_emitter: 0,
+            voice_requests: Vec::new(),
+            allowed_emitters: HashSet::new(),
+            lost_voices,
+            lost: HashSet::new(),
+            reverb_zones: Vec::new(),
+            reverb: ReverbParams::none(),
+            pitch: 1.0,
             driver: Rc::new(RefCell::new(driver)),
             loading: Rc::new(RefCell::new(BTreeSet::new())),
             pending_play: Vec::new(),
+            played: Vec::new(),
             asys,
         }
     }
@@ -87,14 +189,150 @@ impl SoundSystem {
         volume: f32,
         balance: f32,
     ) {
-        let evt = SoundPlayEvent {
+        self.send_play(SoundPlayEvent {
             id: id.0,
+            voice: 0,
             channel,
             do_loop,
             priority,
             volume,
             balance,
-        };
+            lowpass: 1.0,
+            pitch: 1.0,
+        });
+    }
+
+    /// Play a sound whose parameters are changed by `set_voice_params` while playing, e.g. a
+    /// positional sound following its source. The voice is lost when its channel is taken by
+    /// a sound of higher priority, see `is_voice_lost`.
+    pub fn play_voice(
+        &mut self,
+        id: SoundHandle,
+        do_loop: bool,
+        priority: usize,
+        volume: f32,
+        balance: f32,
+    ) -> VoiceHandle {
+        self.next_voice += 1;
+        let voice = self.next_voice;
+
+        self.send_play(SoundPlayEvent {
+            id: id.0,
+            voice,
+            channel: None,
+            do_loop,
+            priority,
+            volume,
+            balance,
+            lowpass: 1.0,
+            pitch: 1.0,
+        });
+
+        VoiceHandle(voice)
+    }
+
+    pub fn new_emitter(&mut self) -> EmitterHandle {
+        self.next_emitter += 1;
+        EmitterHandle(self.next_emitter)
+    }
+
+    /// Request a voice for this frame. The voices are allocated by `lod` at the end of the
+    /// frame, see `has_voice`.
+    pub fn request_voice(&mut self, emitter: EmitterHandle, priority: usize, audibility: f32) {
+        self.voice_requests.push((emitter, priority, audibility));
+    }
+
+    /// Whether the emitter got a voice at the last allocation
+    pub fn has_voice(&self, emitter: EmitterHandle) -> bool {
+        self.allowed_emitters.contains(&emitter)
+    }
+
+    fn allocate_voices(&mut self) {
+        let requests: Vec<_> = self.voice_requests.iter().map(|r| (r.1, r.2)).collect();
+        self.allowed_emitters = self.lod
+            .allocate(&requests)
+            .into_iter()
+            .map(|i| self.voice_requests[i].0)
+            .collect();
+        self.voice_requests.clear();
+    }
+
+    /// `lowpass` is from 1.0 (unfiltered) to 0.0 (silent), lower values muffle the sound
+    pub fn set_voice_params(
+        &mut self,
+        voice: VoiceHandle,
+        volume: f32,
+        balance: f32,
+        lowpass: f32,
+    ) {
+        if let Some(evt) = self.pending_play.iter_mut().find(|e| e.voice == voice.0) {
+            evt.volume = volume;
+            evt.balance = balance;
+            evt.lowpass = lowpass;
+            return;
+        }
+
+        self.driver.borrow_mut().send_event(SoundEvent::VoiceParams(
+            voice.0,
+            volume,
+            balance,
+            lowpass,
+        ));
+    }
+
+    /// Playback speed of a voice, multiplied by the pitch of the sounds, e.g. for doppler
+    pub fn set_voice_pitch(&mut self, voice: VoiceHandle, pitch: f32) {
+        if let Some(evt) = self.pending_play.iter_mut().find(|e| e.voice == voice.0) {
+            evt.pitch = pitch;
+            return;
+        }
+
+        self.driver
+            .borrow_mut()
+            .send_event(SoundEvent::VoicePitch(voice.0, pitch));
+    }
+
+    /// Play a synth graph, its voice is stopped by `stop_voice` or at the end of its duration.
+    /// Synths do not take the channels of the sounds, `set_voice_params` ignores the low-pass.
+    pub fn play_synth(&mut self, graph: SynthGraph, volume: f32, balance: f32) -> VoiceHandle {
+        self.next_voice += 1;
+        let voice = self.next_voice;
+
+        self.driver
+            .borrow_mut()
+            .send_event(SoundEvent::PlaySynth(voice, graph, volume, balance));
+
+        VoiceHandle(voice)
+    }
+
+    /// Set a `Param` node of a playing synth, e.g. the rpm of an engine hum
+    pub fn set_synth_param(&mut self, voice: VoiceHandle, name: &str, value: f32) {
+        self.driver.borrow_mut().send_event(SoundEvent::SynthParam(
+            voice.0,
+            name.to_owned(),
+            value,
+        ));
+    }
+
+    /// Whether the channel of a voice was taken by another sound, or it found no channel.
+    /// A lost voice plays again only by `play_voice`.
+    pub fn is_voice_lost(&self, voice: VoiceHandle) -> bool {
+        self.lost.contains(&voice.0)
+    }
+
+    pub fn stop_voice(&mut self, voice: VoiceHandle) {
+        self.lost.remove(&voice.0);
+        self.pending_play.retain(|e| e.voice != voice.0);
+        self.driver
+            .borrow_mut()
+            .send_event(SoundEvent::StopVoice(voice.0));
+    }
+
+    fn send_play(&mut self, evt: SoundPlayEvent) {
+        let id = SoundHandle(evt.id);
+        if let Some((name, _)) = self.cache.iter().find(|&(_, h)| *h == id) {
+            self.played.push(name.clone());
+        }
 
         if self.loading.borrow().contains(&id) {
             self.pending_play.push(evt);
@@ -104,13 +342,129 @@ impl SoundSystem {
         self.driver.borrow_mut().send_event(SoundEvent::Play(evt))
     }
 
+    /// Playback speed of the sounds (not of the music or streams), e.g. for slow motion
+    pub fn set_pitch(&mut self, pitch: f32) {
+        if (pitch - self.pitch).abs() > 0.001 {
+            self.pitch = pitch;
+            self.driver
+                .borrow_mut()
+                .send_event(SoundEvent::Pitch(pitch));
+        }
+    }
+
+    pub fn pitch(&self) -> f32 {
+        self.pitch
+    }
+
+    pub fn is_loaded(&self, id: SoundHandle) -> bool {
+        !self.loading.borrow().contains(&id)
+    }
+
+    fn send_music(&mut self, evt: MusicEvent) {
+        self.driver.borrow_mut().send_event(SoundEvent::Music(evt));
+    }
+
+    /// File names of the sounds played since last call, e.g. for triggering captions
+    pub fn poll_played(&mut self) -> Vec<String> {
+        self.played.drain(..).collect()
+    }
+
     pub fn stop_channel(&mut self, channel: usize) {
         self.driver
             .borrow_mut()
             .send_event(SoundEvent::StopChannel(channel));
     }
 
+    pub fn new_stream(&mut self) -> StreamHandle {
+        self.next_stream += 1;
+        StreamHandle(self.next_stream)
+    }
+
+    /// Append mono samples (between -1.0 and 1.0) to a stream
+    pub fn queue_stream(&mut self, stream: StreamHandle, sample_rate: usize, samples: Vec<f32>) {
+        self.driver
+            .borrow_mut()
+            .send_event(SoundEvent::StreamData(stream.0, sample_rate, samples));
+    }
+
+    /// Balance is from 0.0 (left) to 1.0 (right)
+    pub fn set_stream_params(&mut self, stream: StreamHandle, volume: f32, balance: f32) {
+        self.driver
+            .borrow_mut()
+            .send_event(SoundEvent::StreamParams(stream.0, volume, balance));
+    }
+
+    pub fn stop_stream(&mut self, stream: StreamHandle) {
+        self.driver
+            .borrow_mut()
+            .send_event(SoundEvent::StopStream(stream.0));
+    }
+
+    /// Add the reverb of a zone for the current frame, `weight` is from 0.0 (outside)
+    /// to 1.0 (inside). Zones of higher priority override the lower ones, zones of the
+    /// same priority are blended by weight.
+    pub fn add_reverb_zone(&mut self, params: ReverbParams, weight: f32, priority: i32) {
+        if weight > 0.0 {
+            self.reverb_zones.push((params, weight.min(1.0), priority));
+        }
+    }
+
+    /// The reverb the mixer is fading to
+    pub fn reverb(&self) -> ReverbParams {
+        self.reverb
+    }
+
+    fn step_reverb(&mut self) {
+        let mut zones: Vec<_> = self.reverb_zones.drain(0..).collect();
+        zones.sort_by(|a, b| b.2.cmp(&a.2));
+
+        let mut blend = ReverbParams {
+            wet: 0.0,
+            decay: 0.0,
+            damping: 0.0,
+        };
+        let mut add = |params: &ReverbParams, w: f32| {
+            blend.wet += params.wet * w;
+            blend.decay += params.decay * w;
+            blend.damping += params.damping * w;
+        };
+
+        let mut remaining = 1.0;
+        let mut i = 0;
+        while i < zones.len() && remaining > 0.0 {
+            let priority = zones[i].2;
+            let level: Vec<_> = zones[i..].iter().take_while(|z| z.2 == priority).collect();
+            i += level.len();
+
+            let total: f32 = level.iter().map(|z| z.1).sum();
+            let take = total.min(1.0) * remaining;
+            for z in level.iter() {
+                add(&z.0, take * z.1 / total);
+            }
+            remaining -= take;
+        }
+        add(&self.default_reverb, remaining);
+
+        let changed = (blend.wet - self.reverb.wet).abs() > 0.001
+            || (blend.decay - self.reverb.decay).abs() > 0.001
+            || (blend.damping - self.reverb.damping).abs() > 0.001;
+
+        if changed {
+            self.reverb = blend;
+            self.driver
+                .borrow_mut()
+                .send_event(SoundEvent::Reverb(blend, self.reverb_fade_time));
+        }
+    }
+
     pub fn step(&mut self) {
+        self.step_reverb();
+        self.allocate_voices();
+
+        if let Ok(mut lost) = self.lost_voices.lock() {
+            self.lost.extend(lost.drain(..));
+        }
+
         let pending: Vec<_> = self.pending_play.drain(0..).collect();
 
         let pending = pending
@@ -135,14 +489,29 @@ enum SoundEvent {
     LoadBuffer(usize, Vec<u8>, String),
     Play(SoundPlayEvent),
     StopChannel(usize),
+    VoiceParams(usize, f32, f32, f32),
+    VoicePitch(usize, f32),
+    PlaySynth(usize, SynthGraph, f32, f32),
+    SynthParam(usize, String, f32),
+    StopVoice(usize),
+    StreamData(usize, usize, Vec<f32>),
+    StreamParams(usize, f32, f32),
+    StopStream(usize),
+    Reverb(ReverbParams, f32),
+    Music(MusicEvent),
+    Pitch(f32),
 }
 
 #[derive(Clone, Copy)]
 pub struct SoundPlayEvent {
     id: usize,
+    /// 0 for the sounds without voice
+    voice: usize,
     channel: Option<usize>,
     do_loop: bool,
     priority: usize,
     volume: f32,
     balance: f32,
+    lowpass: f32,
+    pitch: f32,
 }
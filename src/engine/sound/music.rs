@@ -0,0 +1,330 @@
+use engine::asset::loader::{self, Loadable, Loader};
+use engine::asset::{AssetError, AssetResult, AssetSystem, File, Resource};
+
+use std::collections::HashMap;
+
+use super::music_mixer::{MusicEvent, Quantize};
+use super::{SoundHandle, SoundSystem};
+
+fn default_param() -> String {
+    "intensity".to_owned()
+}
+
+fn default_volume() -> f32 {
+    1.0
+}
+
+fn default_beats_per_bar() -> u32 {
+    4
+}
+
+/// A synchronized part of a section, its volume follows a gameplay parameter
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MusicStem {
+    pub file: String,
+    #[serde(default = "default_param")]
+    pub param: String,
+    /// The stem fades in while the parameter goes from `fade_in.0` to `fade_in.1`,
+    /// the default (0, 0) plays it always
+    #[serde(default)]
+    pub fade_in: (f32, f32),
+    #[serde(default = "default_volume")]
+    pub volume: f32,
+}
+
+impl MusicStem {
+    pub fn volume_at(&self, value: f32) -> f32 {
+        let (lo, hi) = self.fade_in;
+        let k = if hi <= lo {
+            if value >= lo {
+                1.0
+            } else {
+                0.0
+            }
+        } else {
+            ((value - lo) / (hi - lo)).max(0.0).min(1.0)
+        };
+
+        k * self.volume
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct MusicSection {
+    pub stems: Vec<MusicStem>,
+}
+
+/// Music asset, a json file :
+///
+/// ```json
+/// { "bpm": 110, "beats_per_bar": 4,
+///   "sections": {
+///     "explore": { "stems": [
+///         { "file": "music/explore_pad.wav" },
+///         { "file": "music/explore_drums.wav", "fade_in": [0.3, 0.6] }
+///     ]},
+///     "combat": { "stems": [ { "file": "music/combat.wav" } ] }
+///   },
+///   "stingers": { "victory": "music/victory.wav" }
+/// }
+/// ```
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct MusicTrack {
+    pub bpm: f32,
+    #[serde(default = "default_beats_per_bar")]
+    pub beats_per_bar: u32,
+    pub sections: HashMap<String, MusicSection>,
+    #[serde(default)]
+    pub stingers: HashMap<String, String>,
+}
+
+impl MusicTrack {
+    pub fn load(asys: &AssetSystem, filename: &str) -> Resource<MusicTrack> {
+        loader::load_json(asys, filename)
+    }
+}
+
+pub struct MusicTrackLoader {}
+
+impl Loader<MusicTrack> for MusicTrackLoader {
+    fn load<A>(_asys: A, mut file: Box<File>) -> AssetResult<MusicTrack> {
+        loader::read_json(&mut file)
+    }
+}
+
+impl Loadable for MusicTrack {
+    type Loader = MusicTrackLoader;
+}
+
+/// Adaptive music player
+///
+/// Plays the stems of a section of a `MusicTrack` in sync, with the volume of each stem
+/// driven by a parameter (e.g. `world.music.set_param("intensity", 0.8)` from an actor).
+/// Section changes and stingers are quantized to the beats or bars of the track.
+pub struct Music {
+    pub volume: f32,
+
+    track: Option<MusicTrack>,
+    pending: Option<Resource<MusicTrack>>,
+    handles: HashMap<String, SoundHandle>,
+    params: HashMap<String, f32>,
+
+    section: Option<String>,
+    requested: Option<(String, Quantize)>,
+    stingers: Vec<(String, f32, Quantize)>,
+    stop: Option<f32>,
+    sent_volumes: Vec<f32>,
+}
+
+impl Default for Music {
+    fn default() -> Music {
+        Music {
+            volume: 1.0,
+            track: None,
+            pending: None,
+            handles: HashMap::new(),
+            params: HashMap::new(),
+            section: None,
+            requested: None,
+            stingers: Vec::new(),
+            stop: None,
+            sent_volumes: Vec::new(),
+        }
+    }
+}
+
+impl Music {
+    pub fn new() -> Music {
+        Default::default()
+    }
+
+    /// Load a track from a json file, it is used when ready
+    pub fn load(&mut self, asys: &AssetSystem, filename: &str) {
+        self.pending = Some(MusicTrack::load(asys, filename));
+    }
+
+    pub fn set_track(&mut self, track: MusicTrack) {
+        self.track = Some(track);
+        self.handles.clear();
+    }
+
+    pub fn track(&self) -> Option<&MusicTrack> {
+        self.track.as_ref()
+    }
+
+    pub fn set_param(&mut self, name: &str, value: f32) {
+        self.params.insert(name.to_owned(), value);
+    }
+
+    pub fn param(&self, name: &str) -> f32 {
+        self.params.get(name).cloned().unwrap_or(0.0)
+    }
+
+    /// Switch to a section, the stems start when all of them are loaded
+    pub fn play_section(&mut self, name: &str, quantize: Quantize) {
+        self.requested = Some((name.to_owned(), quantize));
+        self.stop = None;
+    }
+
+    /// The section playing or about to play
+    pub fn current_section(&self) -> Option<&str> {
+        self.section.as_ref().map(|s| s.as_str())
+    }
+
+    /// Play a one shot sound over the music, on the next beat or bar
+    pub fn play_stinger(&mut self, name: &str, volume: f32, quantize: Quantize) {
+        self.stingers.push((name.to_owned(), volume, quantize));
+    }
+
+    /// Fade out the music, in seconds
+    pub fn stop(&mut self, fade_time: f32) {
+        self.requested = None;
+        self.stop = Some(fade_time);
+    }
+
+    pub fn step(&mut self, sound: &mut SoundSystem) {
+        self.load_pending(sound);
+
+        if let Some(fade_time) = self.stop.take() {
+            self.section = None;
+            sound.send_music(MusicEvent::Stop(fade_time));
+        }
+
+        self.step_section(sound);
+        self.step_stingers(sound);
+        self.step_volumes(sound);
+    }
+
+    fn load_pending(&mut self, sound: &mut SoundSystem) {
+        if let Some(res) = self.pending.take() {
+            match res.try_into() {
+                Ok(track) => self.set_track(track),
+                Err(AssetError::NotReady) => self.pending = Some(res),
+                Err(e) => println!("Fail to load music, reason: {:?}", e),
+            }
+        }
+
+        let track = match self.track {
+            Some(ref track) => track,
+            None => return,
+        };
+
+        if self.handles.len() > 0 {
+            return;
+        }
+
+        // Load all the files of the track up front, so the changes are not delayed
+        let files = track
+            .sections
+            .values()
+            .flat_map(|s| s.stems.iter().map(|stem| stem.file.clone()))
+            .chain(track.stingers.values().cloned());
+
+        for file in files {
+            let handle = sound.load_sound(&file);
+            self.handles.insert(file, handle);
+        }
+
+        sound.send_music(MusicEvent::Tempo(track.bpm, track.beats_per_bar));
+    }
+
+    fn loaded_handle(&self, sound: &SoundSystem, file: &str) -> Option<SoundHandle> {
+        match self.handles.get(file) {
+            Some(h) if sound.is_loaded(*h) => Some(*h),
+            _ => None,
+        }
+    }
+
+    fn step_section(&mut self, sound: &mut SoundSystem) {
+        let (name, quantize) = match self.requested {
+            Some(ref req) => req.clone(),
+            None => return,
+        };
+
+        let ids = {
+            let section = match self.track.as_ref().and_then(|t| t.sections.get(&name)) {
+                Some(section) => section,
+                None => {
+                    if self.track.is_some() {
+                        println!("Music section {} not found", name);
+                        self.requested = None;
+                    }
+                    return;
+                }
+            };
+
+            let ids: Option<Vec<usize>> = section
+                .stems
+                .iter()
+                .map(|stem| self.loaded_handle(sound, &stem.file).map(|h| h.0))
+                .collect();
+            ids
+        };
+
+        if let Some(ids) = ids {
+            sound.send_music(MusicEvent::Section(ids, quantize));
+            self.section = Some(name);
+            self.requested = None;
+            self.sent_volumes.clear();
+        }
+    }
+
+    fn step_stingers(&mut self, sound: &mut SoundSystem) {
+        let stingers: Vec<_> = self.stingers.drain(0..).collect();
+
+        for (name, volume, quantize) in stingers.into_iter() {
+            let file = match self.track.as_ref().and_then(|t| t.stingers.get(&name)) {
+                Some(file) => file.clone(),
+                None => {
+                    if self.track.is_some() {
+                        println!("Music stinger {} not found", name);
+                    } else {
+                        self.stingers.push((name, volume, quantize));
+                    }
+                    continue;
+                }
+            };
+
+            match self.loaded_handle(sound, &file) {
+                Some(h) => {
+                    let volume = volume * self.volume;
+                    sound.send_music(MusicEvent::Stinger(h.0, volume, quantize))
+                }
+                None => self.stingers.push((name, volume, quantize)),
+            }
+        }
+    }
+
+    fn step_volumes(&mut self, sound: &mut SoundSystem) {
+        let volumes: Vec<f32> = {
+            let section = match (self.track.as_ref(), self.section.as_ref()) {
+                (Some(track), Some(name)) => match track.sections.get(name) {
+                    Some(section) => section,
+                    None => return,
+                },
+                _ => return,
+            };
+
+            section
+                .stems
+                .iter()
+                .map(|stem| stem.volume_at(self.param(&stem.param)) * self.volume)
+                .collect()
+        };
+
+        for (layer, volume) in volumes.into_iter().enumerate() {
+            let changed = match self.sent_volumes.get(layer) {
+                Some(sent) => (sent - volume).abs() > 0.01,
+                None => true,
+            };
+
+            if changed {
+                while self.sent_volumes.len() <= layer {
+                    self.sent_volumes.push(-1.0);
+                }
+                self.sent_volumes[layer] = volume;
+                sound.send_music(MusicEvent::LayerVolume(layer, volume));
+            }
+        }
+    }
+}
@@ -0,0 +1,281 @@
+use std::sync::Arc;
+
+use super::generator::SoundBuffer;
+
+// Time of layer volume changes, in seconds
+const LAYER_FADE_TIME: f32 = 0.5;
+
+/// When a music change happens
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Quantize {
+    Immediate,
+    /// At the next beat
+    Beat,
+    /// At the start of the next bar
+    Bar,
+}
+
+impl Default for Quantize {
+    fn default() -> Quantize {
+        Quantize::Bar
+    }
+}
+
+pub enum MusicEvent {
+    Tempo(f32, u32),
+    /// Play the buffers of the stems of a section, in sync
+    Section(Vec<usize>, Quantize),
+    LayerVolume(usize, f32),
+    Stinger(usize, f32, Quantize),
+    Stop(f32),
+}
+
+struct Voice {
+    buffer: Arc<SoundBuffer>,
+    t: f32,
+    delta_t: f32,
+}
+
+impl Voice {
+    fn new(buffer: Arc<SoundBuffer>, sample_rate: f32) -> Voice {
+        Voice {
+            delta_t: buffer.sample_rate as f32 / sample_rate,
+            buffer,
+            t: 0.0,
+        }
+    }
+
+    fn frames(&self) -> usize {
+        self.buffer.samples.len() / self.buffer.output_count.max(1)
+    }
+
+    fn value(&self, output: usize) -> f32 {
+        let idx = self.t as usize;
+        if idx >= self.frames() {
+            return 0.0;
+        }
+
+        if self.buffer.output_count == 1 {
+            self.buffer.samples[idx]
+        } else {
+            self.buffer.samples[idx * self.buffer.output_count + output]
+        }
+    }
+
+    /// Advance a frame, return false at the end of the buffer
+    fn advance(&mut self, do_loop: bool) -> bool {
+        self.t += self.delta_t;
+
+        let frames = self.frames() as f32;
+        if self.t >= frames {
+            if do_loop && frames > 0.0 {
+                self.t -= frames;
+            } else {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+struct Stinger {
+    voice: Voice,
+    volume: f32,
+    /// Start frame
+    at: u64,
+    done: bool,
+}
+
+/// Plays the stems of the current music section in sync, and schedules the changes
+/// on the beats
+pub struct MusicMixer {
+    sample_rate: f32,
+    bpm: f32,
+    beats_per_bar: u32,
+    /// Frames since the start of the section
+    position: u64,
+
+    /// The stems by layer, None for a buffer missing from the cache, to keep the later
+    /// layers in place
+    stems: Vec<Option<Voice>>,
+    layers: Vec<(f32, f32)>,
+    next_section: Option<(Vec<Option<Arc<SoundBuffer>>>, u64)>,
+    stingers: Vec<Stinger>,
+
+    master: f32,
+    master_step: f32,
+    stopping: bool,
+    cur_output: usize,
+}
+
+impl MusicMixer {
+    pub fn new(sample_rate: f32) -> MusicMixer {
+        MusicMixer {
+            sample_rate,
+            bpm: 120.0,
+            beats_per_bar: 4,
+            position: 0,
+            stems: Vec::new(),
+            layers: Vec::new(),
+            next_section: None,
+            stingers: Vec::new(),
+            master: 1.0,
+            master_step: 0.0,
+            stopping: false,
+            cur_output: 0,
+        }
+    }
+
+    fn frames_per_beat(&self) -> f64 {
+        self.sample_rate as f64 * 60.0 / self.bpm.max(1.0) as f64
+    }
+
+    /// The first frame of the quantization after the current position
+    fn next_frame(&self, quantize: Quantize) -> u64 {
+        let unit = match quantize {
+            Quantize::Immediate => return self.position,
+            Quantize::Beat => self.frames_per_beat(),
+            Quantize::Bar => self.frames_per_beat() * self.beats_per_bar.max(1) as f64,
+        };
+
+        // Nothing playing, no need to wait
+        if self.stems.len() == 0 {
+            return self.position;
+        }
+
+        ((self.position as f64 / unit).ceil() * unit) as u64
+    }
+
+    pub fn handle_event<F>(&mut self, evt: MusicEvent, buffer: F)
+    where
+        F: Fn(usize) -> Option<Arc<SoundBuffer>>,
+    {
+        match evt {
+            MusicEvent::Tempo(bpm, beats_per_bar) => {
+                self.bpm = bpm;
+                self.beats_per_bar = beats_per_bar;
+            }
+            MusicEvent::Section(ids, quantize) => {
+                let buffers: Vec<_> = ids.into_iter().map(|id| buffer(id)).collect();
+                if buffers.iter().any(|b| b.is_none()) {
+                    println!("Music section with missing stems, their layers are silent");
+                }
+                let at = self.next_frame(quantize);
+                self.next_section = Some((buffers, at));
+            }
+            MusicEvent::LayerVolume(layer, volume) => {
+                while self.layers.len() <= layer {
+                    self.layers.push((0.0, 0.0));
+                }
+                self.layers[layer].1 = volume;
+            }
+            MusicEvent::Stinger(id, volume, quantize) => {
+                if let Some(buf) = buffer(id) {
+                    let at = self.next_frame(quantize);
+                    self.stingers.push(Stinger {
+                        voice: Voice::new(buf, self.sample_rate),
+                        volume,
+                        at,
+                        done: false,
+                    });
+                }
+            }
+            MusicEvent::Stop(fade_time) => {
+                self.next_section = None;
+                self.stopping = true;
+                self.master_step = 1.0 / (fade_time * self.sample_rate).max(1.0);
+            }
+        }
+    }
+
+    fn start_section(&mut self, buffers: Vec<Option<Arc<SoundBuffer>>>) {
+        let sample_rate = self.sample_rate;
+        self.stems = buffers
+            .into_iter()
+            .map(|b| b.map(|b| Voice::new(b, sample_rate)))
+            .collect();
+
+        // Stingers are scheduled relatively to the previous section
+        let position = self.position;
+        for s in self.stingers.iter_mut() {
+            s.at = s.at.saturating_sub(position);
+        }
+
+        self.position = 0;
+        self.master = 1.0;
+        self.stopping = false;
+    }
+
+    pub fn next_value(&mut self) -> f32 {
+        let output = self.cur_output;
+
+        if output == 0 {
+            let due = match self.next_section {
+                Some((_, at)) => at <= self.position,
+                None => false,
+            };
+            if due {
+                let (buffers, _) = self.next_section.take().unwrap();
+                self.start_section(buffers);
+            }
+        }
+
+        let mut value = 0.0;
+        for (i, stem) in self.stems.iter().enumerate() {
+            let volume = self.layers.get(i).map(|l| l.0).unwrap_or(0.0);
+            match *stem {
+                Some(ref stem) if volume > 0.0 => value += stem.value(output) * volume,
+                _ => (),
+            }
+        }
+        value *= self.master;
+
+        for s in self.stingers.iter() {
+            if s.at <= self.position {
+                value += s.voice.value(output) * s.volume;
+            }
+        }
+
+        self.cur_output = 1 - output;
+        if self.cur_output == 0 {
+            self.advance();
+        }
+
+        value
+    }
+
+    fn advance(&mut self) {
+        for stem in self.stems.iter_mut().filter_map(|s| s.as_mut()) {
+            stem.advance(true);
+        }
+
+        let position = self.position;
+        for s in self.stingers.iter_mut() {
+            if s.at <= position {
+                s.done = !s.voice.advance(false);
+            }
+        }
+        self.stingers.retain(|s| !s.done);
+
+        let layer_step = 1.0 / (LAYER_FADE_TIME * self.sample_rate);
+        for l in self.layers.iter_mut() {
+            if l.0 < l.1 {
+                l.0 = (l.0 + layer_step).min(l.1);
+            } else {
+                l.0 = (l.0 - layer_step).max(l.1);
+            }
+        }
+
+        if self.stopping {
+            self.master -= self.master_step;
+            if self.master <= 0.0 {
+                self.master = 0.0;
+                self.stems.clear();
+                self.stopping = false;
+            }
+        }
+
+        self.position += 1;
+    }
+}
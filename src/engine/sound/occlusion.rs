@@ -0,0 +1,190 @@
+use engine::physics::{ColliderHandle, PhysicsWorld};
+use math::*;
+
+use std::collections::HashMap;
+
+/// How a surface attenuates the sounds through it. The closed walls have two surfaces, so
+/// their sounds are attenuated twice.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AudioMaterial {
+    /// Part of the volume lost, from 0.0 (transparent) to 1.0 (blocks the sound)
+    pub absorption: f32,
+    /// Part of the high frequencies lost, from 0.0 to 1.0
+    pub damping: f32,
+}
+
+impl AudioMaterial {
+    pub fn new(absorption: f32, damping: f32) -> AudioMaterial {
+        AudioMaterial {
+            absorption,
+            damping,
+        }
+    }
+
+    pub fn glass() -> AudioMaterial {
+        AudioMaterial::new(0.3, 0.2)
+    }
+
+    pub fn wood() -> AudioMaterial {
+        AudioMaterial::new(0.5, 0.6)
+    }
+
+    pub fn concrete() -> AudioMaterial {
+        AudioMaterial::new(0.8, 0.9)
+    }
+}
+
+/// Attenuation of a sound by the walls between the source and the listener
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Occlusion {
+    /// Volume multiplier
+    pub volume: f32,
+    /// Low-pass coefficient of `SoundSystem::set_voice_params`, 1.0 for unfiltered
+    pub lowpass: f32,
+}
+
+impl Occlusion {
+    pub fn none() -> Occlusion {
+        Occlusion {
+            volume: 1.0,
+            lowpass: 1.0,
+        }
+    }
+
+    /// From `self` at 0 to `other` at 1, e.g. to smooth the changes
+    pub fn lerp(&self, other: &Occlusion, t: f32) -> Occlusion {
+        Occlusion {
+            volume: self.volume + (other.volume - self.volume) * t,
+            lowpass: self.lowpass + (other.lowpass - self.lowpass) * t,
+        }
+    }
+}
+
+/// Occlusion of the sounds by the static colliders of the physics world.
+///
+/// Rays are cast from the listener to the source and to points around it, so a sound
+/// behind a corner (obstructed) is muffled less than behind a wall (occluded). Each surface
+/// crossed by a ray attenuates it by the `AudioMaterial` of its collider.
+///
+/// ```ignore
+/// world.sound.occlusion.set_material(walls, AudioMaterial::concrete());
+/// ```
+pub struct AudioOcclusion {
+    pub enabled: bool,
+    /// Material of the colliders without one
+    pub default_material: AudioMaterial,
+    /// Distance of the points around the source, to let the sound go around small obstacles
+    pub spread: f32,
+    /// Surfaces counted by ray, the sound is blocked past them
+    pub max_surfaces: usize,
+
+    materials: HashMap<ColliderHandle, AudioMaterial>,
+}
+
+impl AudioOcclusion {
+    pub fn new() -> AudioOcclusion {
+        AudioOcclusion {
+            enabled: true,
+            default_material: AudioMaterial::wood(),
+            spread: 0.5,
+            max_surfaces: 8,
+            materials: HashMap::new(),
+        }
+    }
+
+    pub fn set_material(&mut self, collider: ColliderHandle, material: AudioMaterial) {
+        self.materials.insert(collider, material);
+    }
+
+    pub fn remove_material(&mut self, collider: ColliderHandle) {
+        self.materials.remove(&collider);
+    }
+
+    pub fn material(&self, collider: ColliderHandle) -> AudioMaterial {
+        self.materials
+            .get(&collider)
+            .cloned()
+            .unwrap_or(self.default_material)
+    }
+
+    /// Attenuation along the segment from `from` to `to`
+    fn trace(&self, physics: &PhysicsWorld, from: Vector3<f32>, to: Vector3<f32>) -> Occlusion {
+        let mut occlusion = Occlusion::none();
+        let mut origin = from;
+
+        for i in 0..self.max_surfaces + 1 {
+            let dir = to - origin;
+            let distance = dir.magnitude();
+            if distance < 1e-4 {
+                break;
+            }
+
+            let dir = dir / distance;
+            let (collider, hit) = match physics.raycast(origin, dir, distance) {
+                Some(hit) => hit,
+                None => break,
+            };
+
+            if i == self.max_surfaces {
+                return Occlusion {
+                    volume: 0.0,
+                    lowpass: 0.0,
+                };
+            }
+
+            let material = self.material(collider);
+            occlusion.volume *= 1.0 - material.absorption.max(0.0).min(1.0);
+            occlusion.lowpass *= 1.0 - material.damping.max(0.0).min(1.0);
+
+            // Continue past the surface
+            origin = hit.point + dir * 0.01;
+        }
+
+        occlusion
+    }
+
+    /// Attenuation of a sound at `source` heard at `listener`
+    pub fn occlusion(
+        &self,
+        physics: &PhysicsWorld,
+        listener: Vector3<f32>,
+        source: Vector3<f32>,
+    ) -> Occlusion {
+        if !self.enabled {
+            return Occlusion::none();
+        }
+
+        let direct = self.trace(physics, listener, source);
+        if direct.volume >= 1.0 || self.spread <= 0.0 {
+            return direct;
+        }
+
+        let dir = source - listener;
+        if dir.magnitude2() < 1e-8 {
+            return direct;
+        }
+
+        // Points around the source, perpendicular to the direction of the sound
+        let dir = dir.normalize();
+        let side = if dir.y.abs() < 0.99 {
+            dir.cross(Vector3::unit_y()).normalize()
+        } else {
+            dir.cross(Vector3::unit_x()).normalize()
+        };
+        let up = side.cross(dir);
+
+        let offsets = [side, -side, up, -up];
+        let mut total = direct;
+        for offset in offsets.iter() {
+            let o = self.trace(physics, listener, source + offset * self.spread);
+            total.volume += o.volume;
+            total.lowpass += o.lowpass;
+        }
+
+        let count = offsets.len() as f32 + 1.0;
+        Occlusion {
+            volume: total.volume / count,
+            lowpass: total.lowpass / count,
+        }
+    }
+}
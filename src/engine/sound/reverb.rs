@@ -0,0 +1,206 @@
+// Delays of the comb and allpass filters in samples at 44100Hz (from Freeverb)
+const COMB_DELAYS: [usize; 4] = [1116, 1188, 1277, 1356];
+const ALLPASS_DELAYS: [usize; 2] = [556, 441];
+// Added to the delays of the right output, for a wider stereo image
+const STEREO_SPREAD: usize = 23;
+const INPUT_GAIN: f32 = 0.1;
+
+/// Parameters of the reverb applied to the final mix
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct ReverbParams {
+    /// Level of the reverberated sound, 0.0 disables the reverb
+    pub wet: f32,
+    /// Length of the tail, from 0.0 to 1.0
+    pub decay: f32,
+    /// Absorption of high frequencies, from 0.0 to 1.0
+    pub damping: f32,
+}
+
+impl Default for ReverbParams {
+    fn default() -> ReverbParams {
+        ReverbParams::none()
+    }
+}
+
+impl ReverbParams {
+    pub fn none() -> ReverbParams {
+        ReverbParams {
+            wet: 0.0,
+            decay: 0.0,
+            damping: 0.5,
+        }
+    }
+
+    pub fn outdoors() -> ReverbParams {
+        ReverbParams {
+            wet: 0.05,
+            decay: 0.3,
+            damping: 0.7,
+        }
+    }
+
+    pub fn room() -> ReverbParams {
+        ReverbParams {
+            wet: 0.2,
+            decay: 0.6,
+            damping: 0.5,
+        }
+    }
+
+    pub fn hall() -> ReverbParams {
+        ReverbParams {
+            wet: 0.3,
+            decay: 0.85,
+            damping: 0.3,
+        }
+    }
+
+    pub fn cave() -> ReverbParams {
+        ReverbParams {
+            wet: 0.45,
+            decay: 0.93,
+            damping: 0.15,
+        }
+    }
+
+    pub fn lerp(&self, other: &ReverbParams, t: f32) -> ReverbParams {
+        ReverbParams {
+            wet: self.wet + (other.wet - self.wet) * t,
+            decay: self.decay + (other.decay - self.decay) * t,
+            damping: self.damping + (other.damping - self.damping) * t,
+        }
+    }
+
+    fn feedback(&self) -> f32 {
+        0.7 + 0.28 * self.decay.max(0.0).min(1.0)
+    }
+}
+
+struct Comb {
+    buffer: Vec<f32>,
+    index: usize,
+    filter: f32,
+}
+
+impl Comb {
+    fn new(len: usize) -> Comb {
+        Comb {
+            buffer: vec![0.0; len.max(1)],
+            index: 0,
+            filter: 0.0,
+        }
+    }
+
+    fn process(&mut self, input: f32, feedback: f32, damping: f32) -> f32 {
+        let out = self.buffer[self.index];
+        self.filter = out * (1.0 - damping) + self.filter * damping;
+        self.buffer[self.index] = input + self.filter * feedback;
+        self.index = (self.index + 1) % self.buffer.len();
+        out
+    }
+}
+
+struct Allpass {
+    buffer: Vec<f32>,
+    index: usize,
+}
+
+impl Allpass {
+    fn new(len: usize) -> Allpass {
+        Allpass {
+            buffer: vec![0.0; len.max(1)],
+            index: 0,
+        }
+    }
+
+    fn process(&mut self, input: f32) -> f32 {
+        let delayed = self.buffer[self.index];
+        self.buffer[self.index] = input + delayed * 0.5;
+        self.index = (self.index + 1) % self.buffer.len();
+        delayed - input
+    }
+}
+
+struct ReverbOutput {
+    combs: Vec<Comb>,
+    allpasses: Vec<Allpass>,
+}
+
+impl ReverbOutput {
+    fn new(sample_rate: f32, spread: usize) -> ReverbOutput {
+        let scale = |d: usize| ((d + spread) as f32 * sample_rate / 44100.0) as usize;
+
+        ReverbOutput {
+            combs: COMB_DELAYS.iter().map(|d| Comb::new(scale(*d))).collect(),
+            allpasses: ALLPASS_DELAYS.iter().map(|d| Allpass::new(scale(*d))).collect(),
+        }
+    }
+
+    fn process(&mut self, input: f32, params: &ReverbParams) -> f32 {
+        let feedback = params.feedback();
+        let damping = params.damping.max(0.0).min(1.0);
+
+        let mut out = 0.0;
+        for comb in self.combs.iter_mut() {
+            out += comb.process(input * INPUT_GAIN, feedback, damping);
+        }
+        for allpass in self.allpasses.iter_mut() {
+            out = allpass.process(out);
+        }
+
+        out
+    }
+}
+
+/// Stereo reverb of the mixer output, crossfading to new parameters
+pub struct Reverb {
+    outputs: Vec<ReverbOutput>,
+    current: ReverbParams,
+    target: ReverbParams,
+    /// Progress of the crossfade per output sample
+    fade_step: f32,
+    fade: f32,
+    from: ReverbParams,
+    sample_rate: f32,
+}
+
+impl Reverb {
+    pub fn new(sample_rate: f32) -> Reverb {
+        Reverb {
+            outputs: vec![
+                ReverbOutput::new(sample_rate, 0),
+                ReverbOutput::new(sample_rate, STEREO_SPREAD),
+            ],
+            current: ReverbParams::none(),
+            target: ReverbParams::none(),
+            fade_step: 1.0,
+            fade: 1.0,
+            from: ReverbParams::none(),
+            sample_rate,
+        }
+    }
+
+    /// Crossfade to new parameters in `fade_time` seconds
+    pub fn set_params(&mut self, params: ReverbParams, fade_time: f32) {
+        self.from = self.current;
+        self.target = params;
+        self.fade = 0.0;
+        self.fade_step = 1.0 / (fade_time * self.sample_rate * 2.0).max(1.0);
+    }
+
+    /// Process a sample of an output (0: left, 1: right)
+    pub fn process(&mut self, input: f32, output: usize) -> f32 {
+        if self.fade < 1.0 {
+            self.fade = (self.fade + self.fade_step).min(1.0);
+            self.current = self.from.lerp(&self.target, self.fade);
+        }
+
+        if self.current.wet <= 0.0 {
+            return input;
+        }
+
+        let params = self.current;
+        let wet = self.outputs[output].process(input, &params);
+        input + wet * params.wet
+    }
+}
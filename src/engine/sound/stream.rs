@@ -0,0 +1,72 @@
+use std::collections::VecDeque;
+
+// Samples kept when the producer is faster than the playback, in seconds
+const MAX_LATENCY: f32 = 0.5;
+
+/// A mono sound fed progressively (e.g. by voice chat)
+pub struct Stream {
+    samples: VecDeque<f32>,
+    sample_rate: f32,
+    driver_sample_rate: f32,
+    volume: f32,
+    balance: f32,
+    t: f32,
+    cur_output: usize,
+    value: f32,
+}
+
+impl Stream {
+    pub fn new(driver_sample_rate: f32) -> Self {
+        Self {
+            samples: VecDeque::new(),
+            sample_rate: driver_sample_rate,
+            driver_sample_rate,
+            volume: 1.0,
+            balance: 0.5,
+            t: 0.0,
+            cur_output: 0,
+            value: 0.0,
+        }
+    }
+    pub fn push(&mut self, sample_rate: usize, samples: Vec<f32>) {
+        self.sample_rate = sample_rate as f32;
+        self.samples.extend(samples);
+
+        let max = (self.sample_rate * MAX_LATENCY) as usize;
+        while self.samples.len() > max {
+            self.samples.pop_front();
+        }
+    }
+    pub fn set_params(&mut self, volume: f32, balance: f32) {
+        self.volume = volume;
+        self.balance = balance;
+    }
+    pub fn next_value(&mut self) -> f32 {
+        if self.cur_output == 0 {
+            // interpolate samples when stream sample rate is not equal to driver sample rate
+            self.value = match (self.samples.get(0), self.samples.get(1)) {
+                (Some(a), Some(b)) => a + (b - a) * self.t,
+                (Some(a), None) => *a,
+                _ => 0.0,
+            };
+
+            if self.samples.len() > 0 {
+                self.t += self.sample_rate / self.driver_sample_rate;
+                while self.t >= 1.0 && self.samples.len() > 0 {
+                    self.samples.pop_front();
+                    self.t -= 1.0;
+                }
+            }
+        }
+
+        // keep the center at full volume, pan by lowering the other side
+        let gain = if self.cur_output == 0 {
+            (2.0 * (1.0 - self.balance)).min(1.0)
+        } else {
+            (2.0 * self.balance).min(1.0)
+        };
+
+        self.cur_output = 1 - self.cur_output;
+        self.value * gain * self.volume
+    }
+}
@@ -0,0 +1,387 @@
+//! Sounds generated at runtime by a graph of DSP nodes, e.g. engine hums, UI blips or retro
+//! effects :
+//!
+//! ```ignore
+//! // A blip
+//! let mut graph = SynthGraph::new();
+//! let pitch = graph.param("pitch", 880.0);
+//! let osc = graph.oscillator(Waveform::Square, pitch, 0.3);
+//! let env = graph.envelope(Envelope::pluck(0.15));
+//! let out = graph.multiply(osc, env);
+//! graph.set_output(graph.low_pass(out, 4000.0));
+//! graph.set_duration(0.2);
+//!
+//! let voice = world.sound.play_synth(graph, 1.0, 0.5);
+//! ```
+//!
+//! The nodes are evaluated in the order they are added, a node only takes the earlier ones
+//! as inputs.
+
+use std::f32::consts::PI;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NodeId(usize);
+
+/// A constant or the output of a node, e.g. a frequency modulated by another oscillator
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SynthInput {
+    Value(f32),
+    Node(NodeId),
+}
+
+impl From<f32> for SynthInput {
+    fn from(v: f32) -> SynthInput {
+        SynthInput::Value(v)
+    }
+}
+
+impl From<NodeId> for SynthInput {
+    fn from(n: NodeId) -> SynthInput {
+        SynthInput::Node(n)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Waveform {
+    Sine,
+    Square,
+    Saw,
+    Triangle,
+}
+
+/// Attack, decay, sustain, release envelope from 0.0 to 1.0, in seconds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Envelope {
+    pub attack: f32,
+    pub decay: f32,
+    /// Level held after the decay
+    pub sustain: f32,
+    /// Time at the sustain level before the release, `None` to hold it forever
+    pub hold: Option<f32>,
+    pub release: f32,
+}
+
+impl Envelope {
+    /// A short sound fading out over `decay`
+    pub fn pluck(decay: f32) -> Envelope {
+        Envelope {
+            attack: 0.005,
+            decay,
+            sustain: 0.0,
+            hold: Some(0.0),
+            release: 0.0,
+        }
+    }
+
+    /// Level at `t` seconds
+    pub fn level(&self, t: f32) -> f32 {
+        if t < 0.0 {
+            return 0.0;
+        }
+        if t < self.attack {
+            return t / self.attack;
+        }
+
+        let t = t - self.attack;
+        if t < self.decay {
+            return 1.0 + (self.sustain - 1.0) * t / self.decay;
+        }
+
+        let t = t - self.decay;
+        let hold = match self.hold {
+            Some(hold) => hold,
+            None => return self.sustain,
+        };
+        if t < hold {
+            return self.sustain;
+        }
+
+        let t = t - hold;
+        if t < self.release {
+            self.sustain * (1.0 - t / self.release)
+        } else {
+            0.0
+        }
+    }
+
+    /// Seconds until the end of the release, `None` when held forever
+    pub fn length(&self) -> Option<f32> {
+        self.hold
+            .map(|hold| self.attack + self.decay + hold + self.release)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SynthNode {
+    Constant(f32),
+    /// A value changed while playing by `SoundSystem::set_synth_param`
+    Param(String, f32),
+    Oscillator {
+        waveform: Waveform,
+        frequency: SynthInput,
+        amplitude: SynthInput,
+    },
+    /// White noise
+    Noise { amplitude: SynthInput },
+    Envelope(Envelope),
+    /// One pole filters, the cutoff is in Hz
+    LowPass { input: NodeId, cutoff: SynthInput },
+    HighPass { input: NodeId, cutoff: SynthInput },
+    Mix(Vec<NodeId>),
+    Multiply(SynthInput, SynthInput),
+}
+
+/// Between -1.0 and 1.0, by xorshift32
+fn next_random(seed: &mut u32) -> f32 {
+    let mut x = *seed;
+    x ^= x << 13;
+    x ^= x >> 17;
+    x ^= x << 5;
+    *seed = x;
+    (x as f32 / u32::max_value() as f32) * 2.0 - 1.0
+}
+
+#[derive(Debug, Clone, Copy)]
+struct NodeState {
+    phase: f32,
+    filtered: f32,
+}
+
+/// A graph of DSP nodes producing a mono sound
+#[derive(Debug, Clone)]
+pub struct SynthGraph {
+    nodes: Vec<SynthNode>,
+    output: Option<NodeId>,
+    /// Seconds before it stops, `None` to play until stopped
+    duration: Option<f32>,
+
+    states: Vec<NodeState>,
+    values: Vec<f32>,
+    time: f32,
+    seed: u32,
+}
+
+impl SynthGraph {
+    pub fn new() -> SynthGraph {
+        SynthGraph {
+            nodes: Vec::new(),
+            output: None,
+            duration: None,
+            states: Vec::new(),
+            values: Vec::new(),
+            time: 0.0,
+            seed: 0x9E37_79B9,
+        }
+    }
+
+    pub fn add(&mut self, node: SynthNode) -> NodeId {
+        self.nodes.push(node);
+        self.states.push(NodeState {
+            phase: 0.0,
+            filtered: 0.0,
+        });
+        self.values.push(0.0);
+
+        // The last node is the output by default
+        let id = NodeId(self.nodes.len() - 1);
+        self.output = Some(id);
+        id
+    }
+
+    pub fn constant(&mut self, value: f32) -> NodeId {
+        self.add(SynthNode::Constant(value))
+    }
+
+    pub fn param(&mut self, name: &str, value: f32) -> NodeId {
+        self.add(SynthNode::Param(name.to_owned(), value))
+    }
+
+    pub fn oscillator<F, A>(&mut self, waveform: Waveform, frequency: F, amplitude: A) -> NodeId
+    where
+        F: Into<SynthInput>,
+        A: Into<SynthInput>,
+    {
+        self.add(SynthNode::Oscillator {
+            waveform,
+            frequency: frequency.into(),
+            amplitude: amplitude.into(),
+        })
+    }
+
+    pub fn noise<A: Into<SynthInput>>(&mut self, amplitude: A) -> NodeId {
+        self.add(SynthNode::Noise {
+            amplitude: amplitude.into(),
+        })
+    }
+
+    pub fn envelope(&mut self, envelope: Envelope) -> NodeId {
+        self.add(SynthNode::Envelope(envelope))
+    }
+
+    pub fn low_pass<C: Into<SynthInput>>(&mut self, input: NodeId, cutoff: C) -> NodeId {
+        self.add(SynthNode::LowPass {
+            input,
+            cutoff: cutoff.into(),
+        })
+    }
+
+    pub fn high_pass<C: Into<SynthInput>>(&mut self, input: NodeId, cutoff: C) -> NodeId {
+        self.add(SynthNode::HighPass {
+            input,
+            cutoff: cutoff.into(),
+        })
+    }
+
+    pub fn mix(&mut self, inputs: &[NodeId]) -> NodeId {
+        self.add(SynthNode::Mix(inputs.to_vec()))
+    }
+
+    pub fn multiply<A: Into<SynthInput>, B: Into<SynthInput>>(&mut self, a: A, b: B) -> NodeId {
+        self.add(SynthNode::Multiply(a.into(), b.into()))
+    }
+
+    pub fn set_output(&mut self, output: NodeId) {
+        self.output = Some(output);
+    }
+
+    pub fn set_duration(&mut self, duration: f32) {
+        self.duration = Some(duration);
+    }
+
+    /// Set the value of the `Param` nodes named `name`
+    pub fn set_param(&mut self, name: &str, value: f32) {
+        for node in self.nodes.iter_mut() {
+            if let SynthNode::Param(ref n, ref mut v) = *node {
+                if n == name {
+                    *v = value;
+                }
+            }
+        }
+    }
+
+    /// Whether it played for its duration
+    pub fn is_finished(&self) -> bool {
+        self.duration.map_or(false, |d| self.time >= d)
+    }
+
+    fn input(&self, input: SynthInput) -> f32 {
+        match input {
+            SynthInput::Value(v) => v,
+            // Later nodes are not evaluated yet, they give their previous sample
+            SynthInput::Node(NodeId(i)) => self.values.get(i).cloned().unwrap_or(0.0),
+        }
+    }
+
+    /// Next sample, between -1.0 and 1.0 for the usual levels
+    pub fn next_sample(&mut self, sample_rate: f32) -> f32 {
+        if self.is_finished() {
+            return 0.0;
+        }
+
+        let dt = 1.0 / sample_rate;
+        for i in 0..self.nodes.len() {
+            let value = match self.nodes[i] {
+                SynthNode::Constant(v) => v,
+                SynthNode::Param(_, v) => v,
+                SynthNode::Oscillator {
+                    waveform,
+                    frequency,
+                    amplitude,
+                } => {
+                    let phase = self.states[i].phase;
+                    let value = match waveform {
+                        Waveform::Sine => (phase * 2.0 * PI).sin(),
+                        Waveform::Square => if phase < 0.5 { 1.0 } else { -1.0 },
+                        Waveform::Saw => phase * 2.0 - 1.0,
+                        Waveform::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+                    };
+
+                    let phase = phase + self.input(frequency) * dt;
+                    self.states[i].phase = phase - phase.floor();
+                    value * self.input(amplitude)
+                }
+                SynthNode::Noise { amplitude } => {
+                    next_random(&mut self.seed) * self.input(amplitude)
+                }
+                SynthNode::Envelope(ref envelope) => envelope.level(self.time),
+                SynthNode::LowPass { input, cutoff } | SynthNode::HighPass { input, cutoff } => {
+                    let x = self.input(SynthInput::Node(input));
+                    let cutoff = self.input(cutoff).max(0.0);
+                    let a = 1.0 - (-2.0 * PI * cutoff * dt).exp();
+
+                    let filtered = self.states[i].filtered + (x - self.states[i].filtered) * a;
+                    self.states[i].filtered = filtered;
+
+                    match self.nodes[i] {
+                        SynthNode::LowPass { .. } => filtered,
+                        _ => x - filtered,
+                    }
+                }
+                SynthNode::Mix(ref inputs) => inputs
+                    .iter()
+                    .map(|n| self.values.get(n.0).cloned().unwrap_or(0.0))
+                    .sum(),
+                SynthNode::Multiply(a, b) => self.input(a) * self.input(b),
+            };
+
+            self.values[i] = value;
+        }
+
+        self.time += dt;
+        self.output.map_or(0.0, |o| self.values[o.0])
+    }
+
+    /// Render `seconds` of mono samples, e.g. to play it by `SoundSystem::queue_stream`
+    pub fn render(&mut self, sample_rate: f32, seconds: f32) -> Vec<f32> {
+        let count = (sample_rate * seconds).max(0.0) as usize;
+        (0..count).map(|_| self.next_sample(sample_rate)).collect()
+    }
+}
+
+/// A synth graph played by the mixer
+pub struct Synth {
+    graph: SynthGraph,
+    sample_rate: f32,
+    volume: f32,
+    balance: f32,
+    cur_output: usize,
+    value: f32,
+}
+
+impl Synth {
+    pub fn new(graph: SynthGraph, sample_rate: f32, volume: f32, balance: f32) -> Synth {
+        Synth {
+            graph,
+            sample_rate,
+            volume,
+            balance,
+            cur_output: 0,
+            value: 0.0,
+        }
+    }
+    pub fn set_params(&mut self, volume: f32, balance: f32) {
+        self.volume = volume;
+        self.balance = balance;
+    }
+    pub fn set_param(&mut self, name: &str, value: f32) {
+        self.graph.set_param(name, value);
+    }
+    pub fn is_finished(&self) -> bool {
+        self.graph.is_finished()
+    }
+    pub fn next_value(&mut self) -> f32 {
+        if self.cur_output == 0 {
+            self.value = self.graph.next_sample(self.sample_rate);
+        }
+
+        // keep the center at full volume, pan by lowering the other side
+        let gain = if self.cur_output == 0 {
+            (2.0 * (1.0 - self.balance)).min(1.0)
+        } else {
+            (2.0 * self.balance).min(1.0)
+        };
+
+        self.cur_output = 1 - self.cur_output;
+        self.value * gain * self.volume
+    }
+}
@@ -0,0 +1,160 @@
+//! Split-screen multiplayer
+//!
+//! `World::set_split_screen` creates a camera per player, each rendered in its part of the
+//! screen, and an `ActionMap` per player limited to the player's input device:
+//!
+//! ```ignore
+//! let cameras = world.set_split_screen(
+//!     SplitLayout::Columns,
+//!     &[InputDevice::KeyboardMouse, InputDevice::Gamepad(0)],
+//! );
+//!
+//! // In the player's actor
+//! let player = &world.split_screen.players[1];
+//! if player.actions.just_pressed("jump") { /* ... */ }
+//! imgui::label(player.ui_root().pos((0.5, 0.1)), "Player 2");
+//! ```
+
+#[cfg(feature = "ui")]
+use engine::imgui::Metric;
+use engine::input::{ActionMap, InputDevice};
+use engine::{Camera, Component, GameObject};
+use uni_app::AppEvent;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// Arrangement of the viewports, player 0 is always at the top left
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitLayout {
+    /// Side by side
+    Columns,
+    /// One above the other
+    Rows,
+    /// 2x2, two players are side by side
+    Grid,
+}
+
+/// Viewport rectangles of `count` players, as `Camera::rect`, in pixels from the bottom left
+/// like gl viewports
+pub fn viewports(
+    layout: SplitLayout,
+    count: usize,
+    screen_size: (u32, u32),
+) -> Vec<((i32, i32), (u32, u32))> {
+    let count = count.max(1) as u32;
+    let (cols, rows) = match layout {
+        SplitLayout::Columns => (count, 1),
+        SplitLayout::Rows => (1, count),
+        SplitLayout::Grid => (count.min(2), (count + 1) / 2),
+    };
+
+    let w = screen_size.0 / cols;
+    let h = screen_size.1 / rows;
+
+    (0..count)
+        .map(|i| {
+            let (col, row) = (i % cols, i / cols);
+            let y = screen_size.1 - (row + 1) * h;
+            (((col * w) as i32, y as i32), (w, h))
+        })
+        .collect()
+}
+
+/// Position of the UI of a viewport, in imgui native coordinates
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UiRoot {
+    pub offset: (f32, f32),
+    pub size: (f32, f32),
+}
+
+impl UiRoot {
+    /// A position in the viewport, from (0, 0) at its top left to (1, 1) at its bottom right
+    #[cfg(feature = "ui")]
+    pub fn pos(&self, p: (f32, f32)) -> Metric {
+        Metric::Native(
+            self.offset.0 + p.0 * self.size.0,
+            self.offset.1 + p.1 * self.size.1,
+        )
+    }
+}
+
+pub struct SplitPlayer {
+    pub device: InputDevice,
+    /// The bindings of `World::actions` for the device of the player
+    pub actions: ActionMap,
+    /// The game object of the player's camera
+    pub camera_object: Rc<RefCell<GameObject>>,
+    pub camera: Arc<Component>,
+    /// Viewport in pixels, from the bottom left
+    pub rect: ((i32, i32), (u32, u32)),
+    pub screen_size: (u32, u32),
+}
+
+impl SplitPlayer {
+    pub fn ui_root(&self) -> UiRoot {
+        let ((x, y), (w, h)) = self.rect;
+        let (sw, sh) = (self.screen_size.0 as f32, self.screen_size.1 as f32);
+
+        UiRoot {
+            offset: (x as f32 / sw, 1.0 - (y as f32 + h as f32) / sh),
+            size: (w as f32 / sw, h as f32 / sh),
+        }
+    }
+}
+
+/// The split-screen players of `World::split_screen`
+pub struct SplitScreen {
+    pub layout: SplitLayout,
+    pub players: Vec<SplitPlayer>,
+    /// Player whose camera is the audio listener, see `World::audio_listener`
+    pub listener: usize,
+}
+
+impl Default for SplitScreen {
+    fn default() -> SplitScreen {
+        SplitScreen::new()
+    }
+}
+
+impl SplitScreen {
+    pub fn new() -> SplitScreen {
+        SplitScreen {
+            layout: SplitLayout::Columns,
+            players: Vec::new(),
+            listener: 0,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.players.len() > 0
+    }
+
+    /// The camera of the audio listener, if split-screen is active
+    pub fn listener_camera(&self) -> Option<Arc<Component>> {
+        self.players
+            .get(self.listener)
+            .or(self.players.first())
+            .map(|p| p.camera.clone())
+    }
+
+    /// Update the viewports and the input of the players
+    pub fn step(&mut self, screen_size: (u32, u32), base: &ActionMap, events: &[AppEvent]) {
+        let rects = viewports(self.layout, self.players.len(), screen_size);
+
+        for (player, rect) in self.players.iter_mut().zip(rects.into_iter()) {
+            player.rect = rect;
+            player.screen_size = screen_size;
+
+            if let Some(camera) = player.camera.try_as::<Camera>() {
+                camera.borrow_mut().rect = Some(rect);
+            }
+
+            // Keep the bindings in sync with the shared map, which may be rebound
+            let bindings = base.for_device(player.device);
+            player.actions.set_bindings(&bindings);
+            player.actions.step(events);
+        }
+    }
+}
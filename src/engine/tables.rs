@@ -0,0 +1,478 @@
+//! Data tables for game balancing
+//!
+//! A `DataTable<T>` holds the rows of a csv or json file deserialized into `T` with serde, e.g.
+//! the stats of the enemies. Tables are loaded by `World::tables`, which can read the files
+//! again every few seconds so the numbers can be tweaked while the game runs:
+//!
+//! ```ignore
+//! #[derive(Deserialize)]
+//! struct Enemy { name: String, health: f32, speed: f32 }
+//!
+//! world.tables.hot_reload = Some(1.0);
+//! let enemies = world.tables.load::<Enemy>(world.asset_system(), "enemies.csv");
+//! enemies.on_change(|rows| println!("{} enemies", rows.len()));
+//! ```
+//!
+//! Json tables are an array of row objects. Csv tables have a header row naming the fields,
+//! the cells are converted to the types of the fields, e.g. `007` stays a string in a `String`
+//! field. Untyped cells (e.g. in a `serde_json::Value`) are guessed as booleans and numbers
+//! unless quoted. Empty cells are missing fields.
+
+use engine::asset::{AssetError, AssetResult, AssetSystem, FileFuture};
+
+use futures::{Async, Future};
+use serde::de::value::{Error as DeError, MapDeserializer, StringDeserializer};
+use serde::de::{DeserializeOwned, Deserializer, Error, IntoDeserializer, Unexpected, Visitor};
+use serde_json;
+use std::cell::{Ref, RefCell};
+use std::rc::Rc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableFormat {
+    Csv,
+    Json,
+}
+
+impl TableFormat {
+    /// Csv for a `.csv` file, json otherwise
+    pub fn from_name(name: &str) -> TableFormat {
+        if name.to_lowercase().ends_with(".csv") {
+            TableFormat::Csv
+        } else {
+            TableFormat::Json
+        }
+    }
+}
+
+/// Split a csv text in rows of cells. Quoted cells are returned with `true`.
+fn split_csv(text: &str) -> Vec<Vec<(String, bool)>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut cell = String::new();
+    let mut quoted = false;
+    let mut in_quotes = false;
+
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    cell.push('"');
+                }
+                '"' => in_quotes = false,
+                _ => cell.push(c),
+            }
+            continue;
+        }
+
+        match c {
+            '"' => {
+                in_quotes = true;
+                quoted = true;
+            }
+            ',' => {
+                row.push((cell.trim().to_owned(), quoted));
+                cell.clear();
+                quoted = false;
+            }
+            '\r' => (),
+            '\n' => {
+                row.push((cell.trim().to_owned(), quoted));
+                cell.clear();
+                quoted = false;
+
+                // Skip blank lines
+                if row.len() > 1 || row[0].0.len() > 0 || row[0].1 {
+                    rows.push(row);
+                }
+                row = Vec::new();
+            }
+            _ => cell.push(c),
+        }
+    }
+
+    if cell.len() > 0 || quoted || row.len() > 0 {
+        row.push((cell.trim().to_owned(), quoted));
+        rows.push(row);
+    }
+
+    rows
+}
+
+/// A csv cell, converted to the type of the field it is deserialized into
+struct CsvCell {
+    text: String,
+    quoted: bool,
+}
+
+impl CsvCell {
+    fn invalid<'de, V: Visitor<'de>>(&self, visitor: &V) -> DeError {
+        DeError::invalid_value(Unexpected::Str(&self.text), visitor)
+    }
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident: $ty:ty),*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+                match self.text.parse::<$ty>() {
+                    Ok(v) => visitor.$visit(v),
+                    Err(_) => Err(self.invalid(&visitor)),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for CsvCell {
+    type Error = DeError;
+
+    // Without a field type, guess it
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        if self.quoted {
+            return visitor.visit_string(self.text);
+        }
+
+        if let Ok(b) = self.text.parse::<bool>() {
+            return visitor.visit_bool(b);
+        }
+        if let Ok(i) = self.text.parse::<i64>() {
+            return visitor.visit_i64(i);
+        }
+        match self.text.parse::<f64>() {
+            Ok(f) if f.is_finite() => visitor.visit_f64(f),
+            _ => visitor.visit_string(self.text),
+        }
+    }
+
+    deserialize_parsed! {
+        deserialize_bool => visit_bool: bool,
+        deserialize_i8 => visit_i8: i8,
+        deserialize_i16 => visit_i16: i16,
+        deserialize_i32 => visit_i32: i32,
+        deserialize_i64 => visit_i64: i64,
+        deserialize_u8 => visit_u8: u8,
+        deserialize_u16 => visit_u16: u16,
+        deserialize_u32 => visit_u32: u32,
+        deserialize_u64 => visit_u64: u64,
+        deserialize_f32 => visit_f32: f32,
+        deserialize_f64 => visit_f64: f64
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        visitor.visit_string(self.text)
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        visitor.visit_string(self.text)
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        visitor.visit_string(self.text)
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        visitor.visit_string(self.text)
+    }
+
+    // Empty cells are missing fields, all the others are set
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// Unit variants, by name
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        let variant: StringDeserializer<DeError> = self.text.into_deserializer();
+        visitor.visit_enum(variant)
+    }
+
+    forward_to_deserialize_any! {
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, DeError> for CsvCell {
+    type Deserializer = CsvCell;
+
+    fn into_deserializer(self) -> CsvCell {
+        self
+    }
+}
+
+/// Deserialize the csv rows keyed by the header, the errors name the row
+fn parse_csv<T: DeserializeOwned>(text: &str) -> Result<Vec<T>, String> {
+    let mut rows = split_csv(text).into_iter();
+
+    let header: Vec<String> = match rows.next() {
+        Some(header) => header.into_iter().map(|(name, _)| name).collect(),
+        None => return Ok(Vec::new()),
+    };
+
+    rows.enumerate()
+        .map(|(i, row)| {
+            let cells = header
+                .iter()
+                .cloned()
+                .zip(row.into_iter())
+                .filter(|&(_, (ref text, quoted))| quoted || !text.is_empty())
+                .map(|(name, (text, quoted))| (name, CsvCell { text, quoted }));
+
+            let cells: MapDeserializer<_, DeError> = MapDeserializer::new(cells);
+            T::deserialize(cells).map_err(|e| format!("Row {}: {}", i + 1, e))
+        })
+        .collect()
+}
+
+/// Deserialize the rows of a table file, `name` is used in the errors
+pub fn parse_table<T>(format: TableFormat, name: &str, data: &[u8]) -> AssetResult<Vec<T>>
+where
+    T: DeserializeOwned,
+{
+    let invalid = |reason: String| AssetError::InvalidFormat {
+        path: name.to_owned(),
+        len: data.len(),
+        reason,
+    };
+
+    match format {
+        TableFormat::Json => serde_json::from_slice(data).map_err(|e| invalid(format!("{:?}", e))),
+        TableFormat::Csv => parse_csv(&String::from_utf8_lossy(data)).map_err(invalid),
+    }
+}
+
+struct TableInner<T> {
+    name: String,
+    format: TableFormat,
+    rows: Vec<T>,
+    data: Option<Vec<u8>>,
+    /// The last content which failed to parse, not parsed again until it changes
+    failed: Option<Vec<u8>>,
+    version: u32,
+    listeners: Vec<Rc<Fn(&[T])>>,
+}
+
+/// Rows of a table file
+///
+/// Cloning it is cheap, all clones share the same rows.
+pub struct DataTable<T> {
+    inner: Rc<RefCell<TableInner<T>>>,
+}
+
+impl<T> Clone for DataTable<T> {
+    fn clone(&self) -> Self {
+        DataTable {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T: DeserializeOwned + 'static> DataTable<T> {
+    /// An empty table, filled by `set_data`
+    pub fn new(name: &str) -> DataTable<T> {
+        DataTable {
+            inner: Rc::new(RefCell::new(TableInner {
+                name: name.to_owned(),
+                format: TableFormat::from_name(name),
+                rows: Vec::new(),
+                data: None,
+                failed: None,
+                version: 0,
+                listeners: Vec::new(),
+            })),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        self.inner.borrow().name.clone()
+    }
+
+    pub fn rows(&self) -> Ref<Vec<T>> {
+        Ref::map(self.inner.borrow(), |inner| &inner.rows)
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.borrow().rows.len()
+    }
+
+    /// Number of times the rows were loaded, 0 until the file is ready
+    pub fn version(&self) -> u32 {
+        self.inner.borrow().version
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        self.version() > 0
+    }
+
+    /// The first row matching the predicate
+    pub fn find<F>(&self, f: F) -> Option<T>
+    where
+        T: Clone,
+        F: Fn(&T) -> bool,
+    {
+        self.inner.borrow().rows.iter().find(|r| f(r)).cloned()
+    }
+
+    /// Call `f` with the new rows each time the table is loaded
+    pub fn on_change<F>(&self, f: F)
+    where
+        F: Fn(&[T]) + 'static,
+    {
+        self.inner.borrow_mut().listeners.push(Rc::new(f));
+    }
+
+    /// Replace the rows by the content of a file. Return false when the content did not
+    /// change, on error the current rows are kept. The error is returned once per content.
+    pub fn set_data(&self, data: &[u8]) -> AssetResult<bool> {
+        let (format, name) = {
+            let inner = self.inner.borrow();
+            let same = |d: &Option<Vec<u8>>| d.as_ref().map(|d| &d[..]) == Some(data);
+            if same(&inner.data) || same(&inner.failed) {
+                return Ok(false);
+            }
+            (inner.format, inner.name.clone())
+        };
+
+        let rows = match parse_table(format, &name, data) {
+            Ok(rows) => rows,
+            Err(e) => {
+                self.inner.borrow_mut().failed = Some(data.to_vec());
+                return Err(e);
+            }
+        };
+
+        let listeners = {
+            let mut inner = self.inner.borrow_mut();
+            inner.rows = rows;
+            inner.data = Some(data.to_vec());
+            inner.failed = None;
+            inner.version += 1;
+            inner.listeners.clone()
+        };
+
+        // Listeners may read the table
+        let inner = self.inner.borrow();
+        for f in listeners.iter() {
+            f(&inner.rows);
+        }
+
+        Ok(true)
+    }
+}
+
+trait TableSource {
+    fn name(&self) -> String;
+
+    fn set_data(&self, data: &[u8]) -> AssetResult<bool>;
+}
+
+impl<T: DeserializeOwned + 'static> TableSource for DataTable<T> {
+    fn name(&self) -> String {
+        DataTable::name(self)
+    }
+
+    fn set_data(&self, data: &[u8]) -> AssetResult<bool> {
+        DataTable::set_data(self, data)
+    }
+}
+
+/// The data tables loaded by the game, `World::tables`
+pub struct DataTables {
+    /// Seconds between two reads of the table files, `None` disables the hot reload.
+    /// In web the files may be served from the browser cache.
+    pub hot_reload: Option<f64>,
+
+    tables: Vec<Box<TableSource>>,
+    pending: Vec<(usize, FileFuture)>,
+    elapsed: f64,
+}
+
+impl Default for DataTables {
+    fn default() -> DataTables {
+        DataTables::new()
+    }
+}
+
+impl DataTables {
+    pub fn new() -> DataTables {
+        DataTables {
+            hot_reload: None,
+            tables: Vec::new(),
+            pending: Vec::new(),
+            elapsed: 0.0,
+        }
+    }
+
+    /// Load a table file, the returned table is empty until the file is ready
+    pub fn load<T>(&mut self, asys: &AssetSystem, name: &str) -> DataTable<T>
+    where
+        T: DeserializeOwned + 'static,
+    {
+        let table = DataTable::new(name);
+        self.tables.push(Box::new(table.clone()));
+
+        let index = self.tables.len() - 1;
+        self.pending.push((index, asys.new_file(name)));
+
+        table
+    }
+
+    pub fn is_loading(&self) -> bool {
+        self.pending.len() > 0
+    }
+
+    /// Read all the table files again, only the changed tables notify their listeners
+    pub fn reload(&mut self, asys: &AssetSystem) {
+        for (index, table) in self.tables.iter().enumerate() {
+            if !self.pending.iter().any(|&(i, _)| i == index) {
+                self.pending.push((index, asys.new_file(&table.name())));
+            }
+        }
+    }
+
+    pub fn step(&mut self, asys: &AssetSystem, dt: f64) {
+        let pending: Vec<_> = self.pending.drain(0..).collect();
+
+        for (index, mut f) in pending.into_iter() {
+            let table = &self.tables[index];
+
+            match f.poll() {
+                Ok(Async::NotReady) => self.pending.push((index, f)),
+                Ok(Async::Ready(mut file)) => {
+                    let result = file.read_binary()
+                        .map_err(|_| AssetError::ReadBufferFail(file.name()))
+                        .and_then(|data| table.set_data(&data));
+
+                    if let Err(e) = result {
+                        println!("Fail to load table {}, reason: {:?}", table.name(), e);
+                    }
+                }
+                Err(e) => println!("Fail to load table {}, reason: {:?}", table.name(), e),
+            }
+        }
+
+        match self.hot_reload {
+            Some(interval) if !self.is_loading() => {
+                self.elapsed += dt;
+                if self.elapsed >= interval {
+                    self.elapsed = 0.0;
+                    self.reload(asys);
+                }
+            }
+            _ => self.elapsed = 0.0,
+        }
+    }
+}
@@ -0,0 +1,137 @@
+//! Time scale
+//!
+//! `World::time` scales the simulation (slow motion, bullet time) in one place: the physics
+//! world integrates its bodies with the scaled time while ticking at real time, so slow motion
+//! stays as stable as normal speed, and scene sounds can follow the scale by their pitch.
+//! Game objects may override the scale, e.g. a player moving at normal speed in bullet time,
+//! actors use `delta_time_of` instead of `World::delta_time`.
+
+use engine::core::internal::GameObjectUtil;
+use engine::{GameObject, SceneTree};
+
+use std::collections::HashMap;
+
+pub struct Time {
+    /// Pitch the scene sounds with the time scale, music is not affected
+    pub pitch_audio: bool,
+    /// Lowest pitch when `pitch_audio` is set
+    pub min_pitch: f32,
+
+    scale: f32,
+    target: f32,
+    /// Change of the scale per real second
+    ramp_speed: f32,
+    overrides: HashMap<u64, f32>,
+    delta_time: f64,
+}
+
+impl Default for Time {
+    fn default() -> Time {
+        Time {
+            pitch_audio: false,
+            min_pitch: 0.25,
+            scale: 1.0,
+            target: 1.0,
+            ramp_speed: 0.0,
+            overrides: HashMap::new(),
+            delta_time: 0.0,
+        }
+    }
+}
+
+impl Time {
+    pub fn new() -> Time {
+        Default::default()
+    }
+
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    pub fn set_scale(&mut self, scale: f32) {
+        self.scale = scale.max(0.0);
+        self.target = self.scale;
+        self.ramp_speed = 0.0;
+    }
+
+    /// Change the scale progressively, in `duration` real seconds
+    pub fn ramp_to(&mut self, scale: f32, duration: f32) {
+        if duration <= 0.0 {
+            self.set_scale(scale);
+            return;
+        }
+
+        self.target = scale.max(0.0);
+        self.ramp_speed = (self.target - self.scale).abs() / duration;
+    }
+
+    /// Use `scale` for a game object instead of the global scale, None to remove the override
+    pub fn set_override(&mut self, go: &GameObject, scale: Option<f32>) {
+        let id = GameObjectUtil::node_id(go);
+        match scale {
+            Some(scale) => {
+                self.overrides.insert(id, scale.max(0.0));
+            }
+            None => {
+                self.overrides.remove(&id);
+            }
+        }
+    }
+
+    pub fn override_of(&self, go: &GameObject) -> Option<f32> {
+        self.overrides.get(&GameObjectUtil::node_id(go)).cloned()
+    }
+
+    pub fn clear_overrides(&mut self) {
+        self.overrides.clear();
+    }
+
+    /// Drop the overrides of the game objects removed from `tree`
+    pub(crate) fn remove_dead_overrides(&mut self, tree: &SceneTree) {
+        if !self.overrides.is_empty() {
+            self.overrides.retain(|id, _| tree.contains(*id));
+        }
+    }
+
+    /// The time scale of a game object
+    pub fn scale_of(&self, go: &GameObject) -> f32 {
+        self.override_of(go).unwrap_or(self.scale)
+    }
+
+    /// Duration of the last frame, in real seconds
+    pub fn unscaled_delta_time(&self) -> f64 {
+        self.delta_time
+    }
+
+    /// Duration of the last frame, in scaled seconds
+    pub fn delta_time(&self) -> f64 {
+        self.delta_time * self.scale as f64
+    }
+
+    pub fn delta_time_of(&self, go: &GameObject) -> f64 {
+        self.delta_time * self.scale_of(go) as f64
+    }
+
+    /// Pitch of the scene sounds
+    pub fn pitch(&self) -> f32 {
+        if self.pitch_audio {
+            self.scale.max(self.min_pitch)
+        } else {
+            1.0
+        }
+    }
+
+    /// Advance by `dt` real seconds
+    pub fn step(&mut self, dt: f64) {
+        self.delta_time = dt;
+
+        if self.scale != self.target {
+            let delta = self.ramp_speed * dt as f32;
+            self.scale = if self.scale < self.target {
+                (self.scale + delta).min(self.target)
+            } else {
+                (self.scale - delta).max(self.target)
+            };
+        }
+    }
+}
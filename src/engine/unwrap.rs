@@ -0,0 +1,380 @@
+//! Automatic uv unwrapping, for meshes without uvs or with uvs that cannot be used to store
+//! per-texel data (lightmaps, decals)
+//!
+//! The triangles are grouped in charts: connected triangles (by their positions, whatever
+//! their normals and uvs) facing within `max_chart_angle` of the first triangle of the chart,
+//! largest triangles first. Each chart is projected on the plane of its first triangle,
+//! turned to fit in the smallest rect, and the rects are packed in the unit square with a
+//! texel density proportional to the world area. Vertices are split along the seams between
+//! charts, so unwrapping changes the vertices and the indices of the mesh.
+//!
+//! The result only depends on the mesh, so the uvs generated at import match the ones an
+//! offline tool (e.g. the lightmap bake) generated for the same mesh.
+//!
+//! ```ignore
+//! if data.uvs.is_none() {
+//!     unwrap_uvs(&mut data, UvChannel::Uv0, &UnwrapOptions::new(512));
+//! }
+//! ```
+
+use engine::render::MeshData;
+use math::*;
+
+use std::cmp::Ordering;
+use std::collections::{HashMap, VecDeque};
+
+/// Rotations tried to fit a chart in the smallest rect, over a quarter turn
+const FIT_ROTATIONS: u32 = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UvChannel {
+    /// `MeshData::uvs`
+    Uv0,
+    /// `MeshData::uvs2`, e.g. the lightmap coordinates
+    Uv1,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct UnwrapOptions {
+    /// Largest angle between the normals of a chart and of its first triangle, below 90 degrees
+    pub max_chart_angle: Rad<f32>,
+    /// Size of the texture the charts are packed for, in texels
+    pub resolution: u32,
+    /// Texels between two charts, so the filtering does not bleed
+    pub padding: f32,
+}
+
+impl UnwrapOptions {
+    pub fn new(resolution: u32) -> UnwrapOptions {
+        UnwrapOptions {
+            max_chart_angle: Deg(60.0).into(),
+            resolution: resolution.max(1),
+            padding: 2.0,
+        }
+    }
+}
+
+impl Default for UnwrapOptions {
+    fn default() -> UnwrapOptions {
+        UnwrapOptions::new(256)
+    }
+}
+
+fn position(data: &MeshData, i: u16) -> Vector3f {
+    let i = i as usize * 3;
+    Vector3::new(data.vertices[i], data.vertices[i + 1], data.vertices[i + 2])
+}
+
+/// Normal and area of the triangles
+fn triangle_normals(data: &MeshData) -> Vec<(Vector3f, f32)> {
+    data.indices
+        .chunks(3)
+        .map(|t| {
+            let (a, b, c) = (position(data, t[0]), position(data, t[1]), position(data, t[2]));
+            let n = (b - a).cross(c - a);
+            let len = n.magnitude();
+            if len > 1e-12 {
+                (n / len, len * 0.5)
+            } else {
+                (Vector3::unit_y(), 0.0)
+            }
+        })
+        .collect()
+}
+
+/// Triangles sharing an edge, the vertices at the same position are the same
+fn triangle_neighbors(data: &MeshData) -> Vec<Vec<usize>> {
+    let mut welded: HashMap<[u32; 3], usize> = HashMap::new();
+    let ids: Vec<usize> = (0..data.vertices.len() / 3)
+        .map(|i| {
+            let p = &data.vertices[i * 3..i * 3 + 3];
+            let key = [p[0].to_bits(), p[1].to_bits(), p[2].to_bits()];
+            let next = welded.len();
+            *welded.entry(key).or_insert(next)
+        })
+        .collect();
+
+    let mut edges: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+    for (t, tri) in data.indices.chunks(3).enumerate() {
+        for k in 0..3 {
+            let a = ids[tri[k] as usize];
+            let b = ids[tri[(k + 1) % 3] as usize];
+            edges.entry((a.min(b), a.max(b))).or_insert_with(Vec::new).push(t);
+        }
+    }
+
+    let mut neighbors = vec![Vec::new(); data.indices.len() / 3];
+    for (t, tri) in data.indices.chunks(3).enumerate() {
+        for k in 0..3 {
+            let a = ids[tri[k] as usize];
+            let b = ids[tri[(k + 1) % 3] as usize];
+            for n in edges[&(a.min(b), a.max(b))].iter() {
+                if *n != t && !neighbors[t].contains(n) {
+                    neighbors[t].push(*n);
+                }
+            }
+        }
+    }
+
+    neighbors
+}
+
+/// Triangles unwrapped together
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chart {
+    /// Sorted
+    pub triangles: Vec<usize>,
+    /// Normal of the triangle the chart grew from, all the triangles face within
+    /// `max_chart_angle` of it
+    pub axis: Vector3f,
+}
+
+/// Group the triangles of a mesh in charts, see the module documentation
+pub fn build_charts(data: &MeshData, max_chart_angle: Rad<f32>) -> Vec<Chart> {
+    let normals = triangle_normals(data);
+    let neighbors = triangle_neighbors(data);
+    let min_dot = max_chart_angle.0.min(Rad::from(Deg(89.0f32)).0).cos();
+
+    let mut seeds: Vec<usize> = (0..normals.len()).collect();
+    seeds.sort_by(|a, b| {
+        normals[*b]
+            .1
+            .partial_cmp(&normals[*a].1)
+            .unwrap_or(Ordering::Equal)
+            .then(a.cmp(b))
+    });
+
+    let mut assigned = vec![false; normals.len()];
+    let mut charts = Vec::new();
+
+    for seed in seeds.into_iter() {
+        if assigned[seed] {
+            continue;
+        }
+
+        let axis = normals[seed].0;
+        let mut chart = Vec::new();
+        let mut queue = VecDeque::new();
+        assigned[seed] = true;
+        queue.push_back(seed);
+
+        while let Some(t) = queue.pop_front() {
+            chart.push(t);
+            for n in neighbors[t].iter() {
+                // Degenerated triangles join any chart
+                let (normal, area) = normals[*n];
+                if !assigned[*n] && (area == 0.0 || normal.dot(axis) >= min_dot) {
+                    assigned[*n] = true;
+                    queue.push_back(*n);
+                }
+            }
+        }
+
+        chart.sort();
+        charts.push(Chart {
+            triangles: chart,
+            axis,
+        });
+    }
+
+    charts
+}
+
+/// A chart flattened in its plane, from its own vertices
+struct FlatChart {
+    vertices: Vec<u16>,
+    uvs: Vec<Vector2f>,
+    size: Vector2f,
+}
+
+fn flatten(data: &MeshData, chart: &Chart) -> FlatChart {
+    let axis = chart.axis;
+    let mut vertices: Vec<u16> = chart
+        .triangles
+        .iter()
+        .flat_map(|t| data.indices[t * 3..t * 3 + 3].to_vec())
+        .collect();
+    vertices.sort();
+    vertices.dedup();
+
+    let helper = if axis.x.abs() < 0.9 {
+        Vector3::unit_x()
+    } else {
+        Vector3::unit_y()
+    };
+    let u = axis.cross(helper).normalize();
+    let v = axis.cross(u);
+
+    let projected: Vec<Vector2f> = vertices
+        .iter()
+        .map(|i| {
+            let p = position(data, *i);
+            Vector2::new(p.dot(u), p.dot(v))
+        })
+        .collect();
+
+    // The rotation of the smallest rect
+    let bounds = |angle: f32| {
+        let (s, c) = angle.sin_cos();
+        projected.iter().fold(
+            (
+                Vector2::new(::std::f32::MAX, ::std::f32::MAX),
+                Vector2::new(::std::f32::MIN, ::std::f32::MIN),
+            ),
+            |(lo, hi), p| {
+                let r = Vector2::new(p.x * c - p.y * s, p.x * s + p.y * c);
+                (
+                    Vector2::new(lo.x.min(r.x), lo.y.min(r.y)),
+                    Vector2::new(hi.x.max(r.x), hi.y.max(r.y)),
+                )
+            },
+        )
+    };
+
+    let mut best = (0.0, ::std::f32::MAX);
+    for k in 0..FIT_ROTATIONS {
+        let angle = k as f32 / FIT_ROTATIONS as f32 * ::std::f32::consts::FRAC_PI_2;
+        let (lo, hi) = bounds(angle);
+        let area = (hi.x - lo.x) * (hi.y - lo.y);
+        if area < best.1 - 1e-9 {
+            best = (angle, area);
+        }
+    }
+
+    // Wider than tall, packs better in shelves
+    let (lo, hi) = bounds(best.0);
+    let angle = if hi.y - lo.y > hi.x - lo.x {
+        best.0 + ::std::f32::consts::FRAC_PI_2
+    } else {
+        best.0
+    };
+    let (lo, hi) = bounds(angle);
+    let (s, c) = angle.sin_cos();
+
+    FlatChart {
+        vertices,
+        uvs: projected
+            .iter()
+            .map(|p| Vector2::new(p.x * c - p.y * s, p.x * s + p.y * c) - lo)
+            .collect(),
+        size: hi - lo,
+    }
+}
+
+/// Pack the rects in shelves at `scale`, None if they do not fit in the unit square
+fn pack(sizes: &[Vector2f], order: &[usize], scale: f32, pad: f32) -> Option<Vec<Vector2f>> {
+    let mut offsets = vec![Vector2::zero(); sizes.len()];
+    let (mut x, mut y, mut shelf) = (pad, pad, 0.0f32);
+
+    for i in order.iter() {
+        let size = sizes[*i] * scale;
+        if x + size.x + pad > 1.0 {
+            x = pad;
+            y += shelf + pad;
+            shelf = 0.0;
+        }
+
+        if x + size.x + pad > 1.0 || y + size.y + pad > 1.0 {
+            return None;
+        }
+
+        offsets[*i] = Vector2::new(x, y);
+        x += size.x + pad;
+        shelf = shelf.max(size.y);
+    }
+
+    Some(offsets)
+}
+
+fn split_channel(channel: &Option<Vec<f32>>, sources: &[u16], n: usize) -> Option<Vec<f32>> {
+    channel.as_ref().map(|c| {
+        sources
+            .iter()
+            .flat_map(|i| c[*i as usize * n..*i as usize * n + n].to_vec())
+            .collect()
+    })
+}
+
+/// Generate the uvs of a channel, see the module documentation. Returns false if the mesh
+/// needs too many vertices for u16 indices or the charts cannot be packed.
+pub fn unwrap_uvs(data: &mut MeshData, channel: UvChannel, options: &UnwrapOptions) -> bool {
+    let charts = build_charts(data, options.max_chart_angle);
+    let flat: Vec<FlatChart> = charts.iter().map(|chart| flatten(data, chart)).collect();
+
+    let count: usize = flat.iter().map(|c| c.vertices.len()).sum();
+    if count > u16::max_value() as usize + 1 {
+        println!(
+            "Cannot unwrap a mesh of {} triangles, too many vertices",
+            data.indices.len() / 3
+        );
+        return false;
+    }
+
+    let sizes: Vec<Vector2f> = flat.iter().map(|c| c.size).collect();
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by(|a, b| {
+        sizes[*b]
+            .y
+            .partial_cmp(&sizes[*a].y)
+            .unwrap_or(Ordering::Equal)
+            .then(a.cmp(b))
+    });
+
+    let pad = options.padding / options.resolution as f32;
+    let area: f32 = sizes.iter().map(|s| s.x * s.y).sum();
+    let mut scale = if area > 0.0 { (0.5 / area).sqrt() } else { 1.0 };
+
+    let mut packed = None;
+    for _ in 0..64 {
+        packed = pack(&sizes, &order, scale, pad);
+        if packed.is_some() {
+            break;
+        }
+        scale *= 0.9;
+    }
+
+    let offsets = match packed {
+        Some(offsets) => offsets,
+        None => {
+            println!("Cannot pack {} charts in the unit square", flat.len());
+            return false;
+        }
+    };
+
+    // A vertex per chart it belongs to
+    let mut sources = Vec::with_capacity(count);
+    let mut uvs = Vec::with_capacity(count * 2);
+    let mut indices = data.indices.clone();
+
+    for (c, chart) in charts.iter().enumerate() {
+        let first = sources.len();
+        for (k, uv) in flat[c].uvs.iter().enumerate() {
+            let uv = offsets[c] + *uv * scale;
+            sources.push(flat[c].vertices[k]);
+            uvs.push(uv.x);
+            uvs.push(uv.y);
+        }
+
+        for t in chart.triangles.iter() {
+            for i in indices[t * 3..t * 3 + 3].iter_mut() {
+                let k = flat[c].vertices.binary_search(i).unwrap();
+                *i = (first + k) as u16;
+            }
+        }
+    }
+
+    data.vertices = split_channel(&Some(data.vertices.clone()), &sources, 3).unwrap();
+    data.uvs = split_channel(&data.uvs, &sources, 2);
+    data.uvs2 = split_channel(&data.uvs2, &sources, 2);
+    data.normals = split_channel(&data.normals, &sources, 3);
+    data.tangents = split_channel(&data.tangents, &sources, 3);
+    data.bitangents = split_channel(&data.bitangents, &sources, 3);
+    data.indices = indices;
+
+    match channel {
+        UvChannel::Uv0 => data.uvs = Some(uvs),
+        UvChannel::Uv1 => data.uvs2 = Some(uvs),
+    }
+
+    true
+}
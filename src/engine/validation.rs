@@ -0,0 +1,329 @@
+//! Scene statistics and content validation
+//!
+//! `validate` walks a scene tree and reports the content errors which are easy to miss while
+//! editing: assets which fail to load, meshes missing the vertex attributes their shader reads,
+//! oversized textures, objects far outside the world and duplicate `Name`s. It is run by
+//! `World::validate_scene`, and can be asserted in tests:
+//!
+//! ```ignore
+//! let report = world.validate_scene();
+//! assert!(report.is_ok(), "{}", report);
+//! ```
+//!
+//! Assets still loading are counted in `SceneStats::loading` and not checked.
+
+use engine::asset::AssetError;
+use engine::core::internal::GameObjectUtil;
+use engine::{Aabb, GameObject, Material, Mesh, Name, Texture};
+
+use math::*;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::rc::Rc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum IssueKind {
+    /// A mesh, texture or shader file which fails to load
+    MissingAsset { asset: &'static str, reason: String },
+    /// The shader reads an attribute which the mesh does not have
+    MissingAttribute { attribute: &'static str },
+    OversizedTexture { size: (u32, u32) },
+    OutOfBounds { position: Vector3<f32> },
+    DuplicateName { name: String, count: usize },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Issue {
+    pub severity: Severity,
+    pub kind: IssueKind,
+    /// Node id of the game object
+    pub object: u64,
+    pub name: Option<String>,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+
+        write!(f, "{}: [{}", severity, self.object)?;
+        if let Some(ref name) = self.name {
+            write!(f, " {}", name)?;
+        }
+        write!(f, "] ")?;
+
+        match self.kind {
+            IssueKind::MissingAsset { asset, ref reason } => {
+                write!(f, "{} fails to load: {}", asset, reason)
+            }
+            IssueKind::MissingAttribute { attribute } => {
+                write!(f, "shader reads {} which the mesh does not have", attribute)
+            }
+            IssueKind::OversizedTexture { size } => {
+                write!(f, "texture of {}x{} is oversized", size.0, size.1)
+            }
+            IssueKind::OutOfBounds { position } => write!(
+                f,
+                "outside the world bounds at ({}, {}, {})",
+                position.x, position.y, position.z
+            ),
+            IssueKind::DuplicateName { ref name, count } => {
+                write!(f, "name {:?} is used by {} objects", name, count)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ValidationOptions {
+    /// Largest width or height of a texture
+    pub max_texture_size: u32,
+    pub world_bounds: Aabb,
+}
+
+impl Default for ValidationOptions {
+    fn default() -> ValidationOptions {
+        ValidationOptions {
+            max_texture_size: 4096,
+            world_bounds: Aabb {
+                min: Vector3::new(-10000.0, -10000.0, -10000.0),
+                max: Vector3::new(10000.0, 10000.0, 10000.0),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SceneStats {
+    pub objects: usize,
+    pub meshes: usize,
+    pub surfaces: usize,
+    pub vertices: usize,
+    pub triangles: usize,
+    /// Distinct textures
+    pub textures: usize,
+    /// Estimated bytes of the textures, uncompressed rgba
+    pub texture_bytes: usize,
+    /// Assets not loaded yet
+    pub loading: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SceneReport {
+    pub stats: SceneStats,
+    pub issues: Vec<Issue>,
+}
+
+impl SceneReport {
+    pub fn errors(&self) -> Vec<&Issue> {
+        self.issues
+            .iter()
+            .filter(|i| i.severity == Severity::Error)
+            .collect()
+    }
+
+    pub fn warnings(&self) -> Vec<&Issue> {
+        self.issues
+            .iter()
+            .filter(|i| i.severity == Severity::Warning)
+            .collect()
+    }
+
+    /// No errors, warnings are allowed
+    pub fn is_ok(&self) -> bool {
+        self.errors().len() == 0
+    }
+}
+
+impl fmt::Display for SceneReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = &self.stats;
+        writeln!(
+            f,
+            "scene: {} objects, {} meshes ({} surfaces, {} vertices, {} triangles), {} textures ({:.1} MB), {} loading",
+            s.objects,
+            s.meshes,
+            s.surfaces,
+            s.vertices,
+            s.triangles,
+            s.textures,
+            s.texture_bytes as f64 / (1024.0 * 1024.0),
+            s.loading
+        )?;
+
+        for issue in self.issues.iter() {
+            writeln!(f, "{}", issue)?;
+        }
+
+        Ok(())
+    }
+}
+
+struct Validator<'a> {
+    options: &'a ValidationOptions,
+    report: SceneReport,
+    textures: Vec<Rc<Texture>>,
+    names: BTreeMap<String, Vec<u64>>,
+}
+
+impl<'a> Validator<'a> {
+    fn issue(&mut self, severity: Severity, kind: IssueKind, object: u64, name: &Option<String>) {
+        self.report.issues.push(Issue {
+            severity,
+            kind,
+            object,
+            name: name.clone(),
+        });
+    }
+
+    fn missing(&mut self, asset: &'static str, e: AssetError, object: u64, name: &Option<String>) {
+        match e {
+            AssetError::NotReady => self.report.stats.loading += 1,
+            e => {
+                let kind = IssueKind::MissingAsset {
+                    asset,
+                    reason: format!("{:?}", e),
+                };
+                self.issue(Severity::Error, kind, object, name);
+            }
+        }
+    }
+
+    fn object(&mut self, go: &GameObject) {
+        let id = GameObjectUtil::node_id(go);
+        let name = go.find_component::<Name>().map(|(n, _)| n.0.clone());
+        self.report.stats.objects += 1;
+
+        if let Some(ref name) = name {
+            self.names.entry(name.clone()).or_insert_with(Vec::new).push(id);
+        }
+
+        let p = go.transform.global().disp;
+        let bounds = &self.options.world_bounds;
+        if p.x < bounds.min.x || p.y < bounds.min.y || p.z < bounds.min.z || p.x > bounds.max.x
+            || p.y > bounds.max.y || p.z > bounds.max.z
+        {
+            self.issue(
+                Severity::Warning,
+                IssueKind::OutOfBounds { position: p },
+                id,
+                &name,
+            );
+        }
+
+        if let Some((mesh, _)) = go.find_component::<Mesh>() {
+            self.mesh(&mesh, id, &name);
+        }
+    }
+
+    fn mesh(&mut self, mesh: &Mesh, id: u64, name: &Option<String>) {
+        self.report.stats.meshes += 1;
+
+        for surface in mesh.surfaces.iter() {
+            self.report.stats.surfaces += 1;
+            self.material(&surface.material, id, name);
+
+            let (has_uvs, has_uvs2, has_normals) = match surface.buffer.mesh_data() {
+                Ok(data) => {
+                    self.report.stats.vertices += data.vertices.len() / 3;
+                    self.report.stats.triangles += data.indices.len() / 3;
+                    (data.uvs.is_some(), data.uvs2.is_some(), data.normals.is_some())
+                }
+                Err(e) => {
+                    self.missing("mesh", e, id, name);
+                    continue;
+                }
+            };
+
+            let program = &surface.material.program;
+            let attributes = [
+                ("aTextureCoord", has_uvs),
+                ("aTextureCoord2", has_uvs2),
+                ("aVertexNormal", has_normals),
+            ];
+
+            for &(attribute, present) in attributes.iter() {
+                if !present && program.uses_attribute(attribute) == Some(true) {
+                    let kind = IssueKind::MissingAttribute { attribute };
+                    self.issue(Severity::Error, kind, id, name);
+                }
+            }
+        }
+    }
+
+    fn material(&mut self, material: &Material, id: u64, name: &Option<String>) {
+        if let Err(e) = material.program.status() {
+            self.missing("shader", e, id, name);
+        }
+
+        for tex in material.textures().into_iter() {
+            if let Err(e) = tex.status() {
+                self.missing("texture", e, id, name);
+                continue;
+            }
+
+            let size = match tex.size() {
+                Some(size) => size,
+                None => continue,
+            };
+
+            if size.0 > self.options.max_texture_size || size.1 > self.options.max_texture_size {
+                self.issue(
+                    Severity::Warning,
+                    IssueKind::OversizedTexture { size },
+                    id,
+                    name,
+                );
+            }
+
+            if !self.textures.iter().any(|t| Rc::ptr_eq(t, &tex)) {
+                self.report.stats.textures += 1;
+                self.report.stats.texture_bytes += size.0 as usize * size.1 as usize * 4;
+                self.textures.push(tex);
+            }
+        }
+    }
+}
+
+/// Validate the descendants of a game object, e.g. the root of a scene tree
+pub fn validate(root: &GameObject, options: &ValidationOptions) -> SceneReport {
+    let mut v = Validator {
+        options,
+        report: SceneReport::default(),
+        textures: Vec::new(),
+        names: BTreeMap::new(),
+    };
+
+    let mut stack = root.childen();
+    while let Some(go) = stack.pop() {
+        let go = go.borrow();
+        v.object(&go);
+        stack.extend(go.childen());
+    }
+
+    let duplicates: Vec<_> = v.names
+        .iter()
+        .filter(|&(_, ids)| ids.len() > 1)
+        .map(|(name, ids)| (name.clone(), ids.clone()))
+        .collect();
+
+    for (name, ids) in duplicates.into_iter() {
+        let kind = IssueKind::DuplicateName {
+            name: name.clone(),
+            count: ids.len(),
+        };
+        v.issue(Severity::Warning, kind, ids[0], &Some(name));
+    }
+
+    // Errors first
+    v.report.issues.sort_by(|a, b| b.severity.cmp(&a.severity));
+    v.report
+}
@@ -0,0 +1,317 @@
+use super::codec::{VoiceCodec, VoiceCodecId, VOICE_FRAME_SIZE, VOICE_SAMPLE_RATE};
+use super::microphone::Microphone;
+use engine::input::ActionMap;
+use engine::net::{Channel, Network, PeerId, RpcMessage};
+use engine::{AudioListener, SoundSystem, StreamHandle};
+use math::*;
+
+use std::collections::{HashMap, HashSet};
+
+// A speaker stops "talking" after this silence, in seconds
+const TALKING_TIMEOUT: f32 = 0.3;
+// Playback streams of silent speakers are released after this time
+const SPEAKER_TIMEOUT: f32 = 10.0;
+
+/// A frame of voice, clients send it to the server, which relays it to other clients
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct VoicePacket {
+    /// Set by the server when relaying
+    pub speaker: PeerId,
+    pub seq: u32,
+    pub codec: VoiceCodecId,
+    pub data: Vec<u8>,
+}
+
+impl RpcMessage for VoicePacket {
+    const NAME: &'static str = "unrust.voice";
+    const CHANNEL: Channel = Channel::Unreliable;
+}
+
+struct Speaker {
+    stream: StreamHandle,
+    decoders: HashMap<VoiceCodecId, Box<VoiceCodec>>,
+    last_seq: Option<u32>,
+    silence: f32,
+}
+
+/// Voice chat, captures the microphone, sends it by `Network` and plays the voices of other peers.
+/// The native `Microphone` captures nothing yet, native peers only listen.
+///
+/// It is disabled until `start` is called. With `push_to_talk`, the voice is only sent while the
+/// `push_to_talk_action` of the action map is down, otherwise when it is louder than `activation_level`.
+/// Voices of peers with a position (see `set_speaker_position`) are attenuated and panned.
+pub struct VoiceChat {
+    pub push_to_talk: bool,
+    pub push_to_talk_action: String,
+    pub activation_level: f32,
+    /// Do not send the local voice
+    pub muted: bool,
+    pub volume: f32,
+    pub max_distance: f32,
+
+    enabled: bool,
+    microphone: Microphone,
+    codec: Box<VoiceCodec>,
+    capture: Vec<f32>,
+    resample_pos: f32,
+    last_sample: f32,
+    seq: u32,
+    transmitting: bool,
+
+    speakers: HashMap<PeerId, Speaker>,
+    positions: HashMap<PeerId, Vector3<f32>>,
+    muted_peers: HashSet<PeerId>,
+}
+
+impl Default for VoiceChat {
+    fn default() -> VoiceChat {
+        VoiceChat {
+            push_to_talk: true,
+            push_to_talk_action: "push_to_talk".to_owned(),
+            activation_level: 0.02,
+            muted: false,
+            volume: 1.0,
+            max_distance: 30.0,
+
+            enabled: false,
+            microphone: Microphone::new(),
+            codec: VoiceCodecId::Opus.create(),
+            capture: Vec::new(),
+            resample_pos: 0.0,
+            last_sample: 0.0,
+            seq: 0,
+            transmitting: false,
+
+            speakers: HashMap::new(),
+            positions: HashMap::new(),
+            muted_peers: HashSet::new(),
+        }
+    }
+}
+
+impl VoiceChat {
+    pub fn new() -> VoiceChat {
+        Default::default()
+    }
+
+    /// Open the microphone and start sending and playing voices
+    pub fn start(&mut self) {
+        self.enabled = true;
+        self.microphone.start();
+    }
+
+    pub fn stop(&mut self) {
+        self.enabled = false;
+        self.microphone.stop();
+        self.capture.clear();
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Codec of the sent voice, receivers decode any codec they support
+    pub fn set_codec(&mut self, codec: VoiceCodecId) {
+        self.codec = codec.create();
+    }
+
+    pub fn codec(&self) -> VoiceCodecId {
+        self.codec.id()
+    }
+
+    /// The local voice is being sent
+    pub fn is_transmitting(&self) -> bool {
+        self.transmitting
+    }
+
+    /// Peers heard in the last moments
+    pub fn talking_peers(&self) -> Vec<PeerId> {
+        self.speakers
+            .iter()
+            .filter(|&(_, s)| s.silence < TALKING_TIMEOUT)
+            .map(|(peer, _)| *peer)
+            .collect()
+    }
+
+    /// Position of a peer's voice, `None` plays it without attenuation
+    pub fn set_speaker_position(&mut self, peer: PeerId, pos: Option<Vector3<f32>>) {
+        match pos {
+            Some(pos) => self.positions.insert(peer, pos),
+            None => self.positions.remove(&peer),
+        };
+    }
+
+    pub fn mute_peer(&mut self, peer: PeerId, muted: bool) {
+        if muted {
+            self.muted_peers.insert(peer);
+        } else {
+            self.muted_peers.remove(&peer);
+        }
+    }
+
+    pub fn step(
+        &mut self,
+        dt: f32,
+        net: &mut Network,
+        actions: &ActionMap,
+        sound: &mut SoundSystem,
+        listener: Option<&AudioListener>,
+    ) {
+        if !self.enabled {
+            for (_, speaker) in self.speakers.drain() {
+                sound.stop_stream(speaker.stream);
+            }
+            return;
+        }
+
+        net.register::<VoicePacket>();
+
+        self.send_voice(net, actions);
+        self.receive_voice(net, sound);
+        self.update_speakers(dt, sound, listener);
+    }
+
+    fn send_voice(&mut self, net: &mut Network, actions: &ActionMap) {
+        let samples = self.microphone.take_samples();
+        let rate = self.microphone.sample_rate();
+
+        // Nothing to send before the first samples of the microphone
+        self.transmitting = rate > 0 && !self.muted && net.local_id().is_some()
+            && (!self.push_to_talk || actions.is_down(&self.push_to_talk_action));
+
+        if !self.transmitting {
+            self.capture.clear();
+            return;
+        }
+
+        self.resample(rate, &samples);
+
+        while self.capture.len() >= VOICE_FRAME_SIZE {
+            let frame: Vec<f32> = self.capture.drain(0..VOICE_FRAME_SIZE).collect();
+
+            if !self.push_to_talk && rms(&frame) < self.activation_level {
+                continue;
+            }
+
+            self.seq = self.seq.wrapping_add(1);
+            let packet = VoicePacket {
+                speaker: net.local_id().unwrap_or(0),
+                seq: self.seq,
+                codec: self.codec.id(),
+                data: self.codec.encode(&frame),
+            };
+
+            net.call(&packet);
+        }
+    }
+
+    fn receive_voice(&mut self, net: &mut Network, sound: &mut SoundSystem) {
+        let local = net.local_id();
+
+        for call in net.receive::<VoicePacket>().into_iter() {
+            let mut packet = call.msg;
+
+            if net.is_server() {
+                packet.speaker = call.sender;
+
+                for peer in net.peers().into_iter() {
+                    if peer != call.sender {
+                        net.call_peer(peer, None, &packet);
+                    }
+                }
+            }
+
+            if Some(packet.speaker) == local || self.muted_peers.contains(&packet.speaker) {
+                continue;
+            }
+
+            self.play(sound, packet);
+        }
+    }
+
+    fn play(&mut self, sound: &mut SoundSystem, packet: VoicePacket) {
+        if !packet.codec.is_available() {
+            return;
+        }
+
+        let speaker = self.speakers
+            .entry(packet.speaker)
+            .or_insert_with(|| Speaker {
+                stream: sound.new_stream(),
+                decoders: HashMap::new(),
+                last_seq: None,
+                silence: 0.0,
+            });
+
+        // Drop late packets of the unreliable channel
+        if let Some(last) = speaker.last_seq {
+            let diff = packet.seq.wrapping_sub(last);
+            if diff == 0 || diff > u32::max_value() / 2 {
+                return;
+            }
+        }
+        speaker.last_seq = Some(packet.seq);
+        speaker.silence = 0.0;
+
+        let samples = speaker
+            .decoders
+            .entry(packet.codec)
+            .or_insert_with(|| packet.codec.create())
+            .decode(&packet.data);
+
+        sound.queue_stream(speaker.stream, VOICE_SAMPLE_RATE, samples);
+    }
+
+    fn update_speakers(&mut self, dt: f32, sound: &mut SoundSystem, listener: Option<&AudioListener>) {
+        for (peer, speaker) in self.speakers.iter_mut() {
+            speaker.silence += dt;
+
+            let (volume, balance) = match (listener, self.positions.get(peer)) {
+                (Some(listener), Some(pos)) => listener.attenuate(*pos, self.max_distance),
+                _ => (1.0, 0.5),
+            };
+
+            sound.set_stream_params(speaker.stream, volume * self.volume, balance);
+
+            if speaker.silence > SPEAKER_TIMEOUT {
+                sound.stop_stream(speaker.stream);
+            }
+        }
+
+        self.speakers.retain(|_, s| s.silence <= SPEAKER_TIMEOUT);
+    }
+
+    /// Linear resampling of the microphone to `VOICE_SAMPLE_RATE`, appended to the capture buffer
+    fn resample(&mut self, rate: usize, samples: &[f32]) {
+        if samples.len() == 0 {
+            return;
+        }
+
+        let step = rate as f32 / VOICE_SAMPLE_RATE as f32;
+        let last = (samples.len() - 1) as f32;
+        // position relative to samples[0], -1.0 is the last sample of the previous call
+        let mut pos = self.resample_pos;
+
+        while pos < last {
+            let i = pos.floor();
+            let k = pos - i;
+            let a = if i < 0.0 {
+                self.last_sample
+            } else {
+                samples[i as usize]
+            };
+            let b = samples[(i + 1.0) as usize];
+
+            self.capture.push(a + (b - a) * k);
+            pos += step;
+        }
+
+        self.resample_pos = pos - samples.len() as f32;
+        self.last_sample = samples[samples.len() - 1];
+    }
+}
+
+fn rms(frame: &[f32]) -> f32 {
+    let sum = frame.iter().fold(0.0, |acc, s| acc + s * s);
+    (sum / frame.len().max(1) as f32).sqrt()
+}
@@ -0,0 +1,124 @@
+//! Voice codecs, all of them work on mono frames at `VOICE_SAMPLE_RATE`
+
+pub const VOICE_SAMPLE_RATE: usize = 16000;
+/// 20ms frames
+pub const VOICE_FRAME_SIZE: usize = 320;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum VoiceCodecId {
+    /// Raw 16 bits PCM, always available
+    Pcm16,
+    /// Opus, needs the `voice_opus` feature (native only)
+    Opus,
+}
+
+impl VoiceCodecId {
+    pub fn is_available(&self) -> bool {
+        match *self {
+            VoiceCodecId::Pcm16 => true,
+            VoiceCodecId::Opus => cfg!(all(feature = "voice_opus", not(target_arch = "wasm32"))),
+        }
+    }
+
+    /// Create the codec, fall back to `Pcm16` if it is not available
+    pub fn create(&self) -> Box<VoiceCodec> {
+        match *self {
+            #[cfg(all(feature = "voice_opus", not(target_arch = "wasm32")))]
+            VoiceCodecId::Opus => match OpusCodec::new() {
+                Some(codec) => Box::new(codec),
+                None => Box::new(Pcm16Codec),
+            },
+            _ => Box::new(Pcm16Codec),
+        }
+    }
+}
+
+pub trait VoiceCodec {
+    fn id(&self) -> VoiceCodecId;
+
+    /// Encode a frame of `VOICE_FRAME_SIZE` samples
+    fn encode(&mut self, frame: &[f32]) -> Vec<u8>;
+
+    fn decode(&mut self, data: &[u8]) -> Vec<f32>;
+}
+
+pub struct Pcm16Codec;
+
+impl VoiceCodec for Pcm16Codec {
+    fn id(&self) -> VoiceCodecId {
+        VoiceCodecId::Pcm16
+    }
+
+    fn encode(&mut self, frame: &[f32]) -> Vec<u8> {
+        let mut data = Vec::with_capacity(frame.len() * 2);
+
+        for s in frame.iter() {
+            let v = (s.max(-1.0).min(1.0) * 32767.0) as i16;
+            data.push((v & 0xff) as u8);
+            data.push(((v >> 8) & 0xff) as u8);
+        }
+
+        data
+    }
+
+    fn decode(&mut self, data: &[u8]) -> Vec<f32> {
+        data.chunks(2)
+            .filter(|c| c.len() == 2)
+            .map(|c| ((c[0] as u16) | ((c[1] as u16) << 8)) as i16 as f32 / 32768.0)
+            .collect()
+    }
+}
+
+#[cfg(all(feature = "voice_opus", not(target_arch = "wasm32")))]
+pub struct OpusCodec {
+    encoder: ::opus::Encoder,
+    decoder: ::opus::Decoder,
+}
+
+#[cfg(all(feature = "voice_opus", not(target_arch = "wasm32")))]
+impl OpusCodec {
+    pub fn new() -> Option<OpusCodec> {
+        use opus::{Application, Channels, Decoder, Encoder};
+
+        let rate = VOICE_SAMPLE_RATE as u32;
+        let encoder = Encoder::new(rate, Channels::Mono, Application::Voip);
+        let decoder = Decoder::new(rate, Channels::Mono);
+
+        match (encoder, decoder) {
+            (Ok(encoder), Ok(decoder)) => Some(OpusCodec { encoder, decoder }),
+            (Err(e), _) | (_, Err(e)) => {
+                println!("Fail to create opus codec, reason: {:?}", e);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(all(feature = "voice_opus", not(target_arch = "wasm32")))]
+impl VoiceCodec for OpusCodec {
+    fn id(&self) -> VoiceCodecId {
+        VoiceCodecId::Opus
+    }
+
+    fn encode(&mut self, frame: &[f32]) -> Vec<u8> {
+        self.encoder.encode_vec_float(frame, 1024).unwrap_or_else(|e| {
+            println!("Fail to encode voice, reason: {:?}", e);
+            Vec::new()
+        })
+    }
+
+    fn decode(&mut self, data: &[u8]) -> Vec<f32> {
+        let mut out = vec![0.0; VOICE_FRAME_SIZE * 6];
+
+        match self.decoder.decode_float(data, &mut out, false) {
+            Ok(n) => {
+                out.truncate(n);
+                out
+            }
+            Err(e) => {
+                println!("Fail to decode voice, reason: {:?}", e);
+                Vec::new()
+            }
+        }
+    }
+}
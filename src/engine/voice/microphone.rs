@@ -0,0 +1,161 @@
+//! Microphone capture, mono samples between -1.0 and 1.0
+
+#[cfg(target_arch = "wasm32")]
+mod imp {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use stdweb::web::TypedArray;
+    use stdweb::Value;
+
+    #[derive(Default)]
+    struct Shared {
+        samples: Vec<f32>,
+        sample_rate: usize,
+    }
+
+    pub struct Microphone {
+        handle: Option<Value>,
+        shared: Rc<RefCell<Shared>>,
+    }
+
+    impl Microphone {
+        pub fn new() -> Microphone {
+            Microphone {
+                handle: None,
+                shared: Rc::new(RefCell::new(Shared::default())),
+            }
+        }
+
+        /// Ask the browser for the microphone, samples are available once the user accepts
+        pub fn start(&mut self) -> bool {
+            if self.handle.is_some() {
+                return true;
+            }
+
+            let on_samples = {
+                let shared = self.shared.clone();
+                move |sample_rate: f64, data: TypedArray<f32>| {
+                    let mut shared = shared.borrow_mut();
+                    shared.sample_rate = sample_rate as usize;
+                    shared.samples.extend(data.to_vec());
+                }
+            };
+
+            let handle = js! {
+                var on_samples = @{on_samples};
+                var state = { on_samples: on_samples, stopped: false };
+
+                if (!navigator.mediaDevices || !navigator.mediaDevices.getUserMedia) {
+                    console.log("Microphone is not supported");
+                    return state;
+                }
+
+                navigator.mediaDevices.getUserMedia({ audio: true }).then(function(stream) {
+                    if (state.stopped) {
+                        stream.getTracks().forEach(function(t) { t.stop(); });
+                        return;
+                    }
+
+                    var AudioContext = window.AudioContext || window.webkitAudioContext;
+                    var ctx = new AudioContext();
+                    var source = ctx.createMediaStreamSource(stream);
+                    var processor = ctx.createScriptProcessor(2048, 1, 1);
+
+                    processor.onaudioprocess = function(e) {
+                        on_samples(ctx.sampleRate, e.inputBuffer.getChannelData(0));
+                    };
+
+                    source.connect(processor);
+                    processor.connect(ctx.destination);
+
+                    state.stream = stream;
+                    state.ctx = ctx;
+                    state.processor = processor;
+                }).catch(function(e) {
+                    console.log("Fail to open microphone: " + e);
+                });
+
+                return state;
+            };
+
+            self.handle = Some(handle);
+            true
+        }
+
+        pub fn stop(&mut self) {
+            if let Some(handle) = self.handle.take() {
+                js! {
+                    var state = @{handle};
+                    state.stopped = true;
+                    if (state.processor) {
+                        state.processor.onaudioprocess = null;
+                        state.processor.disconnect();
+                    }
+                    if (state.stream) {
+                        state.stream.getTracks().forEach(function(t) { t.stop(); });
+                    }
+                    if (state.ctx) {
+                        state.ctx.close();
+                    }
+                    state.on_samples.drop();
+                }
+            }
+
+            self.shared.borrow_mut().samples.clear();
+        }
+
+        pub fn is_active(&self) -> bool {
+            self.handle.is_some()
+        }
+
+        /// 0 until the first samples are captured
+        pub fn sample_rate(&self) -> usize {
+            self.shared.borrow().sample_rate
+        }
+
+        /// Samples captured since last call
+        pub fn take_samples(&mut self) -> Vec<f32> {
+            self.shared.borrow_mut().samples.drain(..).collect()
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod imp {
+    /// Capture is not implemented in native yet, it never produces samples
+    pub struct Microphone {
+        active: bool,
+    }
+
+    impl Microphone {
+        pub fn new() -> Microphone {
+            Microphone { active: false }
+        }
+
+        pub fn start(&mut self) -> bool {
+            if !self.active {
+                println!("Microphone capture is not supported in native");
+            }
+            self.active = true;
+            false
+        }
+
+        pub fn stop(&mut self) {
+            self.active = false;
+        }
+
+        pub fn is_active(&self) -> bool {
+            self.active
+        }
+
+        pub fn sample_rate(&self) -> usize {
+            0
+        }
+
+        pub fn take_samples(&mut self) -> Vec<f32> {
+            Vec::new()
+        }
+    }
+}
+
+pub use self::imp::Microphone;
@@ -0,0 +1,13 @@
+//! Voice chat
+//!
+//! `VoiceChat` captures the microphone, encodes it (opus with the `voice_opus` feature,
+//! raw PCM otherwise) and sends it over `Network`; received voices are played as
+//! positional sound streams.
+
+mod chat;
+mod codec;
+mod microphone;
+
+pub use self::chat::{VoiceChat, VoicePacket};
+pub use self::codec::{Pcm16Codec, VoiceCodec, VoiceCodecId, VOICE_FRAME_SIZE, VOICE_SAMPLE_RATE};
+pub use self::microphone::Microphone;
@@ -0,0 +1,267 @@
+use super::{ChunkCoord, VoxelVolume};
+use engine::MeshData;
+use math::*;
+
+use std::collections::HashMap;
+
+/// The 6 tetrahedra of a cube around its diagonal from corner 0 to corner 7, the corners
+/// numbered by bits (x is 1, y is 2, z is 4). Every face of the cube is split along the
+/// diagonal from its lowest to its highest corner, which is what the LOD stitching relies on.
+const TETRAHEDRA: [[usize; 4]; 6] = [
+    [0, 1, 3, 7],
+    [0, 1, 5, 7],
+    [0, 2, 3, 7],
+    [0, 2, 6, 7],
+    [0, 4, 5, 7],
+    [0, 4, 6, 7],
+];
+
+type Lattice = (usize, usize, usize);
+
+fn to_vec(p: Lattice) -> Vector3<f32> {
+    Vector3::new(p.0 as f32, p.1 as f32, p.2 as f32)
+}
+
+/// Mesh of a chunk at a level of detail, in the local units of the volume.
+///
+/// `neighbor_lods` are the levels of detail of the neighbors, in the order of
+/// `CHUNK_NEIGHBORS` (those out of the volume can be anything not coarser than `lod`). The
+/// cells are marched as tetrahedra, which has no ambiguous cases and no case tables.
+pub fn mesh_chunk(
+    volume: &VoxelVolume,
+    chunk: ChunkCoord,
+    lod: u32,
+    neighbor_lods: &[u32; 6],
+) -> MeshData {
+    let lod = lod.min(volume.max_lod());
+    let step = 1usize << lod;
+    let cells = volume.chunk_size / step;
+    let cs = volume.chunk_size;
+    let origin = (chunk.0 * cs, chunk.1 * cs, chunk.2 * cs);
+
+    // Densities of the lattice points of the chunk, at its LOD
+    let n = cells + 1;
+    let mut values = vec![0.0; n * n * n];
+    for k in 0..n {
+        for j in 0..n {
+            for i in 0..n {
+                let p = (origin.0 + i * step, origin.1 + j * step, origin.2 + k * step);
+                values[(k * n + j) * n + i] =
+                    stitched_density(volume, origin, lod, neighbor_lods, p);
+            }
+        }
+    }
+
+    let mut mesher = Mesher {
+        volume,
+        chunk,
+        vertices: HashMap::new(),
+        data: MeshData::default(),
+        normals: Vec::new(),
+        uvs: Vec::new(),
+        full: false,
+    };
+
+    for k in 0..cells {
+        for j in 0..cells {
+            for i in 0..cells {
+                let mut d = [0.0; 8];
+                let mut lattice = [(0, 0, 0); 8];
+                for c in 0..8 {
+                    let (a, b, e) = (i + (c & 1), j + ((c >> 1) & 1), k + ((c >> 2) & 1));
+                    d[c] = values[(e * n + b) * n + a];
+                    lattice[c] = (origin.0 + a * step, origin.1 + b * step, origin.2 + e * step);
+                }
+
+                let solid = d.iter().filter(|v| **v > volume.iso_level).count();
+                if solid == 0 || solid == 8 {
+                    continue;
+                }
+
+                for t in TETRAHEDRA.iter() {
+                    let points = [lattice[t[0]], lattice[t[1]], lattice[t[2]], lattice[t[3]]];
+                    mesher.tetrahedron(&points, &[d[t[0]], d[t[1]], d[t[2]], d[t[3]]]);
+                }
+            }
+        }
+    }
+
+    let mut data = mesher.data;
+    data.normals = Some(mesher.normals);
+    data.uvs = Some(mesher.uvs);
+    data
+}
+
+/// Density of a lattice point, on the faces next to a coarser chunk it is interpolated from
+/// the coarse lattice like the coarse chunk does, so both surfaces cross the face alike
+fn stitched_density(
+    volume: &VoxelVolume,
+    origin: Lattice,
+    lod: u32,
+    neighbor_lods: &[u32; 6],
+    p: Lattice,
+) -> f32 {
+    let coords = [p.0, p.1, p.2];
+    let origin = [origin.0, origin.1, origin.2];
+
+    // The coarsest neighbor sharing a face with the point, by (axis, lod)
+    let mut coarse: Option<(usize, u32)> = None;
+    for (face, &neighbor_lod) in neighbor_lods.iter().enumerate() {
+        let axis = face / 2;
+        let on_face = if face % 2 == 0 {
+            coords[axis] == origin[axis]
+        } else {
+            coords[axis] == origin[axis] + volume.chunk_size
+        };
+
+        let neighbor_lod = neighbor_lod.min(volume.max_lod());
+        if on_face && neighbor_lod > lod && coarse.map_or(true, |(_, l)| neighbor_lod > l) {
+            coarse = Some((axis, neighbor_lod));
+        }
+    }
+
+    let (axis, coarse_lod) = match coarse {
+        Some(coarse) => coarse,
+        None => return volume.density(p.0 as i32, p.1 as i32, p.2 as i32),
+    };
+
+    // The two axes of the face, in the coarse cell containing the point
+    let step = 1usize << coarse_lod;
+    let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+    let mut base = coords;
+    base[u] -= coords[u] % step;
+    base[v] -= coords[v] % step;
+    let fu = (coords[u] - base[u]) as f32 / step as f32;
+    let fv = (coords[v] - base[v]) as f32 / step as f32;
+
+    let at = |du: usize, dv: usize| {
+        let mut c = base;
+        c[u] += du * step;
+        c[v] += dv * step;
+        volume.density(c[0] as i32, c[1] as i32, c[2] as i32)
+    };
+    let (c00, c10, c01, c11) = (at(0, 0), at(1, 0), at(0, 1), at(1, 1));
+
+    // Linear in the triangle of the face split along its diagonal
+    if fu >= fv {
+        c00 + fu * (c10 - c00) + fv * (c11 - c10)
+    } else {
+        c00 + fv * (c01 - c00) + fu * (c11 - c01)
+    }
+}
+
+struct Mesher<'a> {
+    volume: &'a VoxelVolume,
+    chunk: ChunkCoord,
+    /// Vertex of each crossed edge, by the indices of its lattice points
+    vertices: HashMap<(usize, usize), u16>,
+    data: MeshData,
+    normals: Vec<f32>,
+    uvs: Vec<f32>,
+    /// No more vertices fit in the 16 bits indices
+    full: bool,
+}
+
+impl<'a> Mesher<'a> {
+    fn tetrahedron(&mut self, points: &[Lattice; 4], d: &[f32; 4]) {
+        let iso = self.volume.iso_level;
+        let ins: Vec<usize> = (0..4).filter(|i| d[*i] > iso).collect();
+        let outs: Vec<usize> = (0..4).filter(|i| d[*i] <= iso).collect();
+
+        // Crossed edges, (solid, empty), around the polygon
+        let edges = match ins.len() {
+            1 => vec![(ins[0], outs[0]), (ins[0], outs[1]), (ins[0], outs[2])],
+            2 => vec![
+                (ins[0], outs[0]),
+                (ins[0], outs[1]),
+                (ins[1], outs[1]),
+                (ins[1], outs[0]),
+            ],
+            3 => vec![(ins[0], outs[0]), (ins[1], outs[0]), (ins[2], outs[0])],
+            _ => return,
+        };
+
+        let center = |list: &[usize]| {
+            let sum = list.iter()
+                .fold(Vector3::zero(), |acc, i| acc + to_vec(points[*i]));
+            sum / list.len() as f32
+        };
+        let outward = center(&outs) - center(&ins);
+
+        let mut polygon = Vec::new();
+        for &(a, b) in edges.iter() {
+            match self.vertex(points[a], points[b], d[a], d[b]) {
+                Some(v) => polygon.push(v),
+                None => return,
+            }
+        }
+
+        for i in 1..polygon.len() - 1 {
+            self.triangle(polygon[0], polygon[i], polygon[i + 1], outward);
+        }
+    }
+
+    /// Faces toward `outward`, degenerated triangles are skipped
+    fn triangle(
+        &mut self,
+        a: (u16, Vector3<f32>),
+        b: (u16, Vector3<f32>),
+        c: (u16, Vector3<f32>),
+        outward: Vector3<f32>,
+    ) {
+        if a.0 == b.0 || b.0 == c.0 || a.0 == c.0 {
+            return;
+        }
+
+        let normal = (b.1 - a.1).cross(c.1 - a.1);
+        let (b, c) = if normal.dot(outward) < 0.0 { (c, b) } else { (b, c) };
+        self.data.indices.extend_from_slice(&[a.0, b.0, c.0]);
+    }
+
+    fn vertex(&mut self, a: Lattice, b: Lattice, da: f32, db: f32) -> Option<(u16, Vector3<f32>)> {
+        let volume = self.volume;
+        let (ia, ib) = (volume.index(a.0, a.1, a.2), volume.index(b.0, b.1, b.2));
+        let key = (ia.min(ib), ia.max(ib));
+
+        if let Some(&i) = self.vertices.get(&key) {
+            let v = &self.data.vertices[i as usize * 3..i as usize * 3 + 3];
+            return Some((i, Vector3::new(v[0], v[1], v[2])));
+        }
+
+        let index = self.data.vertices.len() / 3;
+        if index >= u16::max_value() as usize {
+            if !self.full {
+                println!("Voxel chunk {:?} has too many vertices, its mesh is cut", self.chunk);
+                self.full = true;
+            }
+            return None;
+        }
+
+        let t = if (db - da).abs() > 1e-6 {
+            ((volume.iso_level - da) / (db - da)).max(0.0).min(1.0)
+        } else {
+            0.5
+        };
+        let position = (to_vec(a) + (to_vec(b) - to_vec(a)) * t) * volume.voxel_size;
+
+        // The density grows into the solid
+        let ga = volume.gradient(a.0 as i32, a.1 as i32, a.2 as i32);
+        let gb = volume.gradient(b.0 as i32, b.1 as i32, b.2 as i32);
+        let g = -(ga + (gb - ga) * t);
+        let normal = if g.magnitude2() > 1e-12 {
+            g.normalize()
+        } else {
+            Vector3::unit_y()
+        };
+
+        self.data
+            .vertices
+            .extend_from_slice(&[position.x, position.y, position.z]);
+        self.normals.extend_from_slice(&[normal.x, normal.y, normal.z]);
+        self.uvs.extend_from_slice(&[position.x, position.z]);
+
+        let index = index as u16;
+        self.vertices.insert(key, index);
+        Some((index, position))
+    }
+}
@@ -0,0 +1,296 @@
+//! Voxel volumes, meshed by marching cubes
+//!
+//! A `VoxelVolume` stores densities on a lattice, the surface is where the density crosses
+//! `iso_level` (above is solid). The volume is split in chunks, each meshed on its own by
+//! `mesh_chunk` at a level of detail: LOD n samples every 2^n voxels. The faces of a chunk
+//! next to a coarser one are sampled at the coarse resolution, so the meshes meet without
+//! cracks.
+//!
+//! Edits (`dig`, `build`, `set_density`) mark the chunks to remesh, see
+//! `actors::VoxelTerrain` for the meshes and colliders in a world.
+
+mod mesher;
+
+pub use self::mesher::mesh_chunk;
+
+use math::*;
+
+use std::collections::BTreeSet;
+
+/// Chunk index along x, y and z
+pub type ChunkCoord = (usize, usize, usize);
+
+/// Offsets of the 6 neighbors of a chunk: -x, +x, -y, +y, -z, +z
+pub const CHUNK_NEIGHBORS: [(i32, i32, i32); 6] = [
+    (-1, 0, 0),
+    (1, 0, 0),
+    (0, -1, 0),
+    (0, 1, 0),
+    (0, 0, -1),
+    (0, 0, 1),
+];
+
+#[derive(Debug, Clone)]
+pub struct VoxelVolume {
+    /// Voxels along a side of a chunk, a power of two
+    pub chunk_size: usize,
+    /// Chunks along x, y and z
+    pub chunks: (usize, usize, usize),
+    /// Size of a voxel in local units
+    pub voxel_size: f32,
+    /// Densities above are solid
+    pub iso_level: f32,
+
+    densities: Vec<f32>,
+    dirty: BTreeSet<ChunkCoord>,
+}
+
+impl VoxelVolume {
+    /// An empty volume
+    pub fn new(chunk_size: usize, chunks: (usize, usize, usize), voxel_size: f32) -> VoxelVolume {
+        assert!(chunk_size.is_power_of_two(), "chunk size must be a power of two");
+
+        let samples = (chunks.0 * chunk_size + 1) * (chunks.1 * chunk_size + 1)
+            * (chunks.2 * chunk_size + 1);
+
+        let mut volume = VoxelVolume {
+            chunk_size,
+            chunks,
+            voxel_size,
+            iso_level: 0.0,
+            densities: vec![-1.0; samples],
+            dirty: BTreeSet::new(),
+        };
+        volume.mark_all_dirty();
+        volume
+    }
+
+    /// A volume with the densities of `f` at the local positions of the lattice, e.g. noise
+    pub fn from_fn<F>(
+        chunk_size: usize,
+        chunks: (usize, usize, usize),
+        voxel_size: f32,
+        f: F,
+    ) -> VoxelVolume
+    where
+        F: Fn(Vector3<f32>) -> f32,
+    {
+        let mut volume = VoxelVolume::new(chunk_size, chunks, voxel_size);
+        let (sx, sy, sz) = volume.samples();
+
+        for z in 0..sz {
+            for y in 0..sy {
+                for x in 0..sx {
+                    let p = Vector3::new(x as f32, y as f32, z as f32) * voxel_size;
+                    volume.densities[(z * sy + y) * sx + x] = f(p);
+                }
+            }
+        }
+        volume
+    }
+
+    /// Lattice points along x, y and z
+    pub fn samples(&self) -> (usize, usize, usize) {
+        (
+            self.chunks.0 * self.chunk_size + 1,
+            self.chunks.1 * self.chunk_size + 1,
+            self.chunks.2 * self.chunk_size + 1,
+        )
+    }
+
+    /// Coarsest level of detail, a single cell per chunk
+    pub fn max_lod(&self) -> u32 {
+        self.chunk_size.trailing_zeros()
+    }
+
+    pub(crate) fn index(&self, x: usize, y: usize, z: usize) -> usize {
+        let (sx, sy, _) = self.samples();
+        (z * sy + y) * sx + x
+    }
+
+    /// Density of a lattice point, clamped to the volume
+    pub fn density(&self, x: i32, y: i32, z: i32) -> f32 {
+        let (sx, sy, sz) = self.samples();
+        let clamp = |v: i32, n: usize| v.max(0).min(n as i32 - 1) as usize;
+        self.densities[self.index(clamp(x, sx), clamp(y, sy), clamp(z, sz))]
+    }
+
+    pub fn set_density(&mut self, x: usize, y: usize, z: usize, density: f32) {
+        let (sx, sy, sz) = self.samples();
+        if x >= sx || y >= sy || z >= sz {
+            return;
+        }
+
+        let i = self.index(x, y, z);
+        if self.densities[i] != density {
+            self.densities[i] = density;
+            self.mark_point_dirty(x, y, z);
+        }
+    }
+
+    /// Trilinear density at a local position
+    pub fn sample(&self, p: Vector3<f32>) -> f32 {
+        let p = p / self.voxel_size;
+        let (x0, y0, z0) = (p.x.floor(), p.y.floor(), p.z.floor());
+        let (fx, fy, fz) = (p.x - x0, p.y - y0, p.z - z0);
+        let (x, y, z) = (x0 as i32, y0 as i32, z0 as i32);
+
+        let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+        let plane = |z: i32| {
+            lerp(
+                lerp(self.density(x, y, z), self.density(x + 1, y, z), fx),
+                lerp(self.density(x, y + 1, z), self.density(x + 1, y + 1, z), fx),
+                fy,
+            )
+        };
+        lerp(plane(z), plane(z + 1), fz)
+    }
+
+    pub fn is_solid(&self, p: Vector3<f32>) -> bool {
+        self.sample(p) > self.iso_level
+    }
+
+    /// Density gradient at a lattice point, pointing into the solid
+    pub fn gradient(&self, x: i32, y: i32, z: i32) -> Vector3<f32> {
+        Vector3::new(
+            self.density(x + 1, y, z) - self.density(x - 1, y, z),
+            self.density(x, y + 1, z) - self.density(x, y - 1, z),
+            self.density(x, y, z + 1) - self.density(x, y, z - 1),
+        ) * 0.5
+    }
+
+    /// Remove the matter in a sphere, at a local position
+    pub fn dig(&mut self, center: Vector3<f32>, radius: f32) {
+        self.brush(center, radius, false);
+    }
+
+    /// Add matter in a sphere, at a local position
+    pub fn build(&mut self, center: Vector3<f32>, radius: f32) {
+        self.brush(center, radius, true);
+    }
+
+    fn brush(&mut self, center: Vector3<f32>, radius: f32, add: bool) {
+        let (sx, sy, sz) = self.samples();
+        let c = center / self.voxel_size;
+        let r = radius / self.voxel_size;
+
+        // One voxel more, for the smooth edge of the sphere
+        let range = |c: f32, n: usize| {
+            let lo = (c - r - 1.0).floor().max(0.0) as usize;
+            let hi = ((c + r + 1.0).ceil().max(0.0) as usize).min(n - 1);
+            (lo, hi)
+        };
+        let (x0, x1) = range(c.x, sx);
+        let (y0, y1) = range(c.y, sy);
+        let (z0, z1) = range(c.z, sz);
+
+        for z in z0..z1 + 1 {
+            for y in y0..y1 + 1 {
+                for x in x0..x1 + 1 {
+                    let d = r - (Vector3::new(x as f32, y as f32, z as f32) - c).magnitude();
+                    let d = d.max(-1.0).min(1.0);
+                    let old = self.densities[self.index(x, y, z)];
+
+                    let new = if add {
+                        old.max(self.iso_level + d)
+                    } else {
+                        old.min(self.iso_level - d)
+                    };
+                    self.set_density(x, y, z, new);
+                }
+            }
+        }
+    }
+
+    /// Origin of a chunk, in local units
+    pub fn chunk_origin(&self, chunk: ChunkCoord) -> Vector3<f32> {
+        Vector3::new(chunk.0 as f32, chunk.1 as f32, chunk.2 as f32)
+            * (self.chunk_size as f32 * self.voxel_size)
+    }
+
+    /// The chunk containing a local position
+    pub fn chunk_at(&self, p: Vector3<f32>) -> Option<ChunkCoord> {
+        let p = p / (self.chunk_size as f32 * self.voxel_size);
+        if p.x < 0.0 || p.y < 0.0 || p.z < 0.0 {
+            return None;
+        }
+
+        let c = (p.x as usize, p.y as usize, p.z as usize);
+        if c.0 < self.chunks.0 && c.1 < self.chunks.1 && c.2 < self.chunks.2 {
+            Some(c)
+        } else {
+            None
+        }
+    }
+
+    /// The neighbor of a chunk by an offset of `CHUNK_NEIGHBORS`
+    pub fn neighbor(&self, chunk: ChunkCoord, offset: (i32, i32, i32)) -> Option<ChunkCoord> {
+        let (x, y, z) = (
+            chunk.0 as i32 + offset.0,
+            chunk.1 as i32 + offset.1,
+            chunk.2 as i32 + offset.2,
+        );
+        if x < 0 || y < 0 || z < 0 {
+            return None;
+        }
+
+        let (x, y, z) = (x as usize, y as usize, z as usize);
+        if x < self.chunks.0 && y < self.chunks.1 && z < self.chunks.2 {
+            Some((x, y, z))
+        } else {
+            None
+        }
+    }
+
+    pub fn chunk_coords(&self) -> Vec<ChunkCoord> {
+        let mut coords = Vec::new();
+        for z in 0..self.chunks.2 {
+            for y in 0..self.chunks.1 {
+                for x in 0..self.chunks.0 {
+                    coords.push((x, y, z));
+                }
+            }
+        }
+        coords
+    }
+
+    /// The chunks meshing the point, or its gradient
+    fn mark_point_dirty(&mut self, x: usize, y: usize, z: usize) {
+        let cs = self.chunk_size;
+        let range = |v: usize, n: usize| {
+            // The lowest chunk with the point before, on its upper face
+            let lo = v.saturating_sub(2) / cs;
+            let hi = ((v + 1) / cs).min(n - 1);
+            (lo.min(n - 1), hi)
+        };
+
+        let (x0, x1) = range(x, self.chunks.0);
+        let (y0, y1) = range(y, self.chunks.1);
+        let (z0, z1) = range(z, self.chunks.2);
+
+        for cz in z0..z1 + 1 {
+            for cy in y0..y1 + 1 {
+                for cx in x0..x1 + 1 {
+                    self.dirty.insert((cx, cy, cz));
+                }
+            }
+        }
+    }
+
+    pub fn mark_all_dirty(&mut self) {
+        for c in self.chunk_coords() {
+            self.dirty.insert(c);
+        }
+    }
+
+    pub fn is_dirty(&self, chunk: ChunkCoord) -> bool {
+        self.dirty.contains(&chunk)
+    }
+
+    /// The chunks edited since the last call
+    pub fn take_dirty(&mut self) -> Vec<ChunkCoord> {
+        let dirty = self.dirty.iter().cloned().collect();
+        self.dirty.clear();
+        dirty
+    }
+}
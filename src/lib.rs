@@ -8,14 +8,15 @@
 /* common */
 extern crate fnv;
 extern crate futures;
-extern crate hound;
 extern crate image;
 extern crate obj;
+#[macro_use]
+extern crate serde;
+extern crate serde_json;
 extern crate typed_arena;
 extern crate uni_app;
 extern crate uni_glsl;
 extern crate uni_pad;
-extern crate uni_snd;
 extern crate uni_gl;
 
 #[macro_use]
@@ -24,9 +25,39 @@ extern crate unrust_derive;
 #[macro_use]
 extern crate bitflags;
 
+#[macro_use]
+extern crate lazy_static;
+
+#[macro_use]
+extern crate serde_derive;
+
 #[cfg(feature = "flame_it")]
 extern crate flame;
 
+#[cfg(feature = "audio")]
+extern crate hound;
+#[cfg(feature = "audio")]
+extern crate uni_snd;
+
+#[cfg(target_arch = "wasm32")]
+#[macro_use]
+extern crate stdweb;
+
+#[cfg(all(feature = "net", not(target_arch = "wasm32")))]
+extern crate tungstenite;
+#[cfg(all(feature = "net", not(target_arch = "wasm32")))]
+extern crate url;
+
+#[cfg(all(feature = "voice_opus", not(target_arch = "wasm32")))]
+extern crate opus;
+
+#[cfg(not(target_arch = "wasm32"))]
+extern crate backtrace;
+
+#[cfg(all(feature = "profile_tracing", not(target_arch = "wasm32")))]
+#[macro_use]
+extern crate tracing;
+
 // This is here so that our procedural macros
 // can work within the crate.
 pub(crate) mod unrust {
@@ -52,4 +83,5 @@ pub mod math {
     pub type Isometry3<T> = Decomposed<Vector3<T>, Quaternion<T>>;
 }
 
+#[cfg(feature = "ui")]
 pub use engine::imgui;
@@ -15,6 +15,25 @@ pub trait Actor {
     }
 
     fn update(&mut self, &mut GameObject, &mut World) {}
+
+    fn late_update_rc(&mut self, go: Handle<GameObject>, world: &mut World) {
+        self.late_update(&mut go.borrow_mut(), world)
+    }
+
+    // Called after the update of all actors, e.g. to follow objects moved by other actors
+    fn late_update(&mut self, &mut GameObject, &mut World) {}
+
+    fn turn_rc(&mut self, go: Handle<GameObject>, world: &mut World) {
+        self.turn(&mut go.borrow_mut(), world)
+    }
+
+    // Called once per turn in turn-based mode, before the update of that frame
+    fn turn(&mut self, &mut GameObject, &mut World) {}
+
+    // Whether update should still be called while the world is paused
+    fn update_when_paused(&self) -> bool {
+        false
+    }
 }
 
 impl ComponentBased for Box<Actor> {}
\ No newline at end of file
@@ -1,16 +1,25 @@
-use engine::{Engine, File, FileFuture, FileIoError, FileSystem};
+use engine::asset::cache::{self, CacheLookup};
+use engine::{AssetRoots, Engine, File, FileFuture, FileIoError, FileSystem};
 use uni_app::fs;
 
 use futures::{Async, Future};
 use futures::future;
 use std::collections::BTreeSet;
 use std::cell::RefCell;
+use std::path::Path;
 use std::rc::Rc;
 
+// Directory of the assets, the page directory in web
+#[cfg(not(target_arch = "wasm32"))]
+const ASSET_BASE: &'static str = "static";
+#[cfg(target_arch = "wasm32")]
+const ASSET_BASE: &'static str = "";
+
 // unrust engine support different file system.
 #[derive(Default)]
 pub struct AppFileSystem {
     loading_files: Rc<RefCell<BTreeSet<String>>>,
+    roots: AssetRoots,
 }
 
 pub struct AppFile(String, fs::File, Rc<RefCell<BTreeSet<String>>>);
@@ -34,18 +43,101 @@ impl Future for AppFileReader {
     }
 }
 
+/// A file read from the asset cache, or downloaded then stored in it
+pub struct MemoryFile(String, Vec<u8>);
+
+enum CachedState {
+    Lookup(Rc<RefCell<CacheLookup>>),
+    Download(fs::File),
+}
+
+pub struct CachedFileReader {
+    filename: String,
+    abs_filename: String,
+    hash: String,
+    state: CachedState,
+    loading_files: Rc<RefCell<BTreeSet<String>>>,
+}
+
+impl CachedFileReader {
+    fn ready(&self, data: Vec<u8>) -> Result<Async<Box<File>>, FileIoError> {
+        self.loading_files.borrow_mut().remove(&self.filename);
+        Ok(Async::Ready(Box::new(MemoryFile(self.filename.clone(), data))))
+    }
+}
+
+impl Future for CachedFileReader {
+    type Item = Box<File>;
+    type Error = FileIoError;
+
+    fn poll(&mut self) -> Result<Async<Self::Item>, Self::Error> {
+        let data = match self.state {
+            CachedState::Lookup(ref lookup) => match *lookup.borrow() {
+                CacheLookup::Pending => return Ok(Async::NotReady),
+                CacheLookup::Hit(ref data) => Some(data.clone()),
+                CacheLookup::Miss => None,
+            },
+            CachedState::Download(ref mut file) => {
+                if !file.is_ready() {
+                    return Ok(Async::NotReady);
+                }
+
+                let data = file.read_binary().map_err(|_| FileIoError::NotReady)?;
+                if cache::content_hash(&data) == self.hash {
+                    cache::store(&self.hash, &data);
+                } else {
+                    println!(
+                        "Asset {} does not match the cache manifest, not cached",
+                        self.filename
+                    );
+                }
+                Some(data)
+            }
+        };
+
+        match data {
+            Some(data) => self.ready(data),
+            None => {
+                // Not cached, download it
+                let file = match fs::FileSystem::open(&self.abs_filename) {
+                    Ok(file) => file,
+                    Err(_) => {
+                        self.loading_files.borrow_mut().remove(&self.filename);
+                        return Err(FileIoError::NoSuchFile(self.filename.clone()));
+                    }
+                };
+                self.state = CachedState::Download(file);
+                self.poll()
+            }
+        }
+    }
+}
+
 impl FileSystem for AppFileSystem {
     type File = AppFile;
 
     fn open(&self, filename: &str) -> FileFuture {
-        let mut abs_filename = filename.to_string();
-
-        if cfg!(not(target_arch = "wasm32")) {
-            abs_filename = "static/".to_string() + filename;
+        let filename = filename.replace("\\", "/");
+        let filename = filename.as_str();
+
+        // Overlay roots first, the files in web cannot be probed,
+        // so they must be listed by the root.
+        let abs_filename = self.roots.resolve(ASSET_BASE, filename, |path| {
+            cfg!(not(target_arch = "wasm32")) && Path::new(path).is_file()
+        });
+
+        // Files listed by the cache manifest, on web
+        if let Some((hash, lookup)) = cache::lookup(filename) {
+            self.loading_files.borrow_mut().insert(filename.to_string());
+            return Box::new(CachedFileReader {
+                filename: filename.to_string(),
+                abs_filename,
+                hash,
+                state: CachedState::Lookup(lookup),
+                loading_files: self.loading_files.clone(),
+            });
         }
 
-        abs_filename = abs_filename.replace("\\", "/");
-
         let f = fs::FileSystem::open(&abs_filename)
             .map_err(|_| FileIoError::NoSuchFile(filename.to_string()));
 
@@ -69,6 +161,10 @@ impl FileSystem for AppFileSystem {
             .map(|s| s.clone())
             .collect()
     }
+
+    fn roots(&self) -> &AssetRoots {
+        &self.roots
+    }
 }
 
 impl File for AppFile {
@@ -81,6 +177,16 @@ impl File for AppFile {
     }
 }
 
+impl File for MemoryFile {
+    fn name(&self) -> String {
+        self.0.clone()
+    }
+
+    fn read_binary(&mut self) -> Result<Vec<u8>, FileIoError> {
+        Ok(self.1.clone())
+    }
+}
+
 impl AppFile {
     fn is_ready(&self) -> bool {
         self.1.is_ready()
@@ -0,0 +1,96 @@
+use engine::net::{LoopbackHub, PeerId, Transport};
+use world::{World, WorldBuilder};
+
+const BOT_DELTA_TIME: f64 = 1.0 / 60.0;
+
+/// A server and bot clients running as headless worlds in the same process,
+/// connected by a `LoopbackHub`, for testing multiplayer logic without sockets.
+///
+/// ```ignore
+/// let mut session = BotSession::new(3, |b| b.with_processor::<Game>());
+/// session.run(120);
+/// assert_eq!(session.server.net.peers().len(), 3);
+/// ```
+pub struct BotSession {
+    pub hub: LoopbackHub,
+    pub server: World,
+    pub bots: Vec<World>,
+    server_id: PeerId,
+}
+
+impl BotSession {
+    /// Build the server and `bots` clients, `setup` configures the builder of each world
+    pub fn new<F>(bots: usize, setup: F) -> BotSession
+    where
+        F: Fn(WorldBuilder<'static>) -> WorldBuilder<'static>,
+    {
+        let hub = LoopbackHub::new();
+
+        let mut server = BotSession::build_world("Server", &setup);
+        let transport = hub.connect();
+        let server_id = transport.local_id();
+        server.net.host(Box::new(transport));
+
+        let mut session = BotSession {
+            hub,
+            server,
+            bots: Vec::new(),
+            server_id,
+        };
+
+        for _ in 0..bots {
+            session.add_bot(&setup);
+        }
+
+        session
+    }
+
+    /// Build and connect another bot client, return its index
+    pub fn add_bot<F>(&mut self, setup: &F) -> usize
+    where
+        F: Fn(WorldBuilder<'static>) -> WorldBuilder<'static>,
+    {
+        let mut bot = BotSession::build_world("Bot", setup);
+        bot.net.connect(Box::new(self.hub.connect()), self.server_id);
+
+        self.bots.push(bot);
+        self.bots.len() - 1
+    }
+
+    pub fn server_id(&self) -> PeerId {
+        self.server_id
+    }
+
+    /// Step all worlds by one frame, server first, return false if any world has quit
+    pub fn step(&mut self) -> bool {
+        let mut running = self.server.poll_events();
+
+        for bot in self.bots.iter_mut() {
+            running = bot.poll_events() && running;
+        }
+
+        running
+    }
+
+    pub fn run(&mut self, frames: usize) -> bool {
+        for _ in 0..frames {
+            if !self.step() {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    fn build_world<F>(title: &'static str, setup: &F) -> World
+    where
+        F: Fn(WorldBuilder<'static>) -> WorldBuilder<'static>,
+    {
+        let builder = WorldBuilder::new(title)
+            .with_headless(true)
+            .with_size((64, 64))
+            .with_fixed_delta_time(BOT_DELTA_TIME);
+
+        setup(builder).build()
+    }
+}
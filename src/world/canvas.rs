@@ -0,0 +1,139 @@
+//! Embedding in a web page, see `WorldBuilder::with_canvas`
+//!
+//! The canvas created for the world replaces an existing canvas element (keeping its id,
+//! classes and style), or is appended to another element, found by a css selector. Several
+//! worlds can run on one page, each in its own canvas.
+//!
+//! The input listeners of uni-app are global to the page, so a world embedded in a canvas
+//! only keeps the keyboard events while its canvas has the focus, and the mouse events while
+//! the pointer is over it. Releases are always kept so no key stays stuck.
+
+#[cfg(target_arch = "wasm32")]
+use stdweb::unstable::TryInto;
+use uni_app::AppEvent;
+
+use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+
+static NEXT_WORLD: AtomicUsize = ATOMIC_USIZE_INIT;
+
+/// The canvas of a world
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CanvasScope {
+    /// Css selector of the canvas of the world
+    pub selector: String,
+    /// Drop the input events of the other canvases
+    pub scope_events: bool,
+}
+
+impl Default for CanvasScope {
+    fn default() -> CanvasScope {
+        CanvasScope {
+            selector: "canvas".to_owned(),
+            scope_events: false,
+        }
+    }
+}
+
+impl CanvasScope {
+    /// Move the canvas just created for a world to `target`, returns its scope
+    pub(crate) fn attach(target: &str) -> CanvasScope {
+        let id = NEXT_WORLD.fetch_add(1, Ordering::SeqCst);
+        let selector = format!("canvas[data-unrust-world=\"{}\"]", id);
+
+        if !attach_canvas(target, id) {
+            println!("Canvas target {} not found, the world stays in the page body", target);
+        }
+
+        CanvasScope {
+            selector,
+            scope_events: true,
+        }
+    }
+
+    /// Keep the events of this canvas only, when `scope_events`
+    pub fn filter(&self, events: &mut Vec<AppEvent>) {
+        if !self.scope_events {
+            return;
+        }
+
+        let focused = has_focus(&self.selector);
+        let hovered = is_hovered(&self.selector);
+
+        events.retain(|evt| match *evt {
+            AppEvent::KeyDown(_) | AppEvent::CharEvent(_) => focused,
+            AppEvent::MouseDown(_) | AppEvent::Click(_) | AppEvent::MousePos(_) => hovered,
+            _ => true,
+        });
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn attach_canvas(target: &str, id: usize) -> bool {
+    let id = id as u32;
+
+    let found = js! {
+        // The canvas of uni-app is the last one appended to the body
+        var canvases = document.querySelectorAll("canvas:not([data-unrust-world])");
+        var canvas = canvases[canvases.length - 1];
+        if (!canvas) {
+            return false;
+        }
+
+        canvas.setAttribute("data-unrust-world", @{id});
+        // Focusable, to scope the keyboard events
+        canvas.tabIndex = 0;
+
+        var target = document.querySelector(@{target});
+        if (!target || target === canvas) {
+            return false;
+        }
+
+        if (target.tagName === "CANVAS") {
+            canvas.id = target.id;
+            canvas.className = target.className;
+            canvas.style.cssText = target.style.cssText;
+            target.parentNode.replaceChild(canvas, target);
+        } else {
+            target.appendChild(canvas);
+        }
+        return true;
+    };
+
+    found.try_into().unwrap_or(false)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn attach_canvas(_target: &str, _id: usize) -> bool {
+    // A native world has its own window
+    true
+}
+
+#[cfg(target_arch = "wasm32")]
+fn has_focus(selector: &str) -> bool {
+    let focused = js! {
+        var canvas = document.querySelector(@{selector});
+        return canvas !== null && document.activeElement === canvas;
+    };
+
+    focused.try_into().unwrap_or(false)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn has_focus(_selector: &str) -> bool {
+    true
+}
+
+#[cfg(target_arch = "wasm32")]
+fn is_hovered(selector: &str) -> bool {
+    let hovered = js! {
+        var canvas = document.querySelector(@{selector});
+        return canvas !== null && canvas.matches(":hover");
+    };
+
+    hovered.try_into().unwrap_or(false)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn is_hovered(_selector: &str) -> bool {
+    true
+}
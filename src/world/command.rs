@@ -0,0 +1,201 @@
+use world::World;
+
+use std::mem;
+
+/// An undoable edit of the world, e.g. from the brush tools of `world::editor`
+pub trait Command {
+    /// Short description, e.g. for an undo menu
+    fn name(&self) -> &str;
+
+    /// Apply the edit, called again to redo it after an undo
+    fn execute(&mut self, world: &mut World);
+
+    fn undo(&mut self, world: &mut World);
+
+    /// Approximate memory held by the command, for `UndoStack::max_bytes`
+    fn memory_size(&self) -> usize {
+        0
+    }
+}
+
+/// Commands undone and redone together
+pub struct CommandGroup {
+    pub name: String,
+    pub commands: Vec<Box<Command>>,
+}
+
+impl Command for CommandGroup {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn execute(&mut self, world: &mut World) {
+        for cmd in self.commands.iter_mut() {
+            cmd.execute(world);
+        }
+    }
+
+    fn undo(&mut self, world: &mut World) {
+        for cmd in self.commands.iter_mut().rev() {
+            cmd.undo(world);
+        }
+    }
+
+    fn memory_size(&self) -> usize {
+        self.commands.iter().map(|c| c.memory_size()).sum()
+    }
+}
+
+/// History of the executed commands, `World::history`
+///
+/// Commands are run by `World::execute`, `World::undo` and `World::redo`. The oldest commands
+/// are dropped when the history exceeds `max_commands` or `max_bytes`.
+pub struct UndoStack {
+    pub max_commands: usize,
+    pub max_bytes: usize,
+
+    undo: Vec<Box<Command>>,
+    redo: Vec<Box<Command>>,
+    group: Option<CommandGroup>,
+    group_depth: usize,
+}
+
+impl Default for UndoStack {
+    fn default() -> UndoStack {
+        UndoStack {
+            max_commands: 100,
+            max_bytes: 64 * 1024 * 1024,
+            undo: Vec::new(),
+            redo: Vec::new(),
+            group: None,
+            group_depth: 0,
+        }
+    }
+}
+
+impl UndoStack {
+    pub fn new() -> UndoStack {
+        Default::default()
+    }
+
+    /// Record an executed command, the commands undone before can not be redone anymore
+    pub fn push(&mut self, cmd: Box<Command>) {
+        self.redo.clear();
+
+        if let Some(ref mut group) = self.group {
+            group.commands.push(cmd);
+            return;
+        }
+
+        self.undo.push(cmd);
+        self.trim();
+    }
+
+    /// Record the next commands as one, until the matching `end_group`. Groups can be nested,
+    /// e.g. all the dabs of a brush stroke
+    pub fn begin_group(&mut self, name: &str) {
+        if self.group_depth == 0 {
+            self.group = Some(CommandGroup {
+                name: name.to_owned(),
+                commands: Vec::new(),
+            });
+        }
+        self.group_depth += 1;
+    }
+
+    pub fn end_group(&mut self) {
+        if self.group_depth == 0 {
+            return;
+        }
+
+        self.group_depth -= 1;
+        if self.group_depth == 0 {
+            self.close_group();
+        }
+    }
+
+    fn close_group(&mut self) {
+        self.group_depth = 0;
+        if let Some(group) = self.group.take() {
+            if group.commands.len() > 0 {
+                self.undo.push(Box::new(group));
+                self.trim();
+            }
+        }
+    }
+
+    fn trim(&mut self) {
+        let max_commands = self.max_commands.max(1);
+        let mut bytes = self.memory_size();
+        let mut count = 0;
+
+        // Keep the last command, whatever its size
+        while count + 1 < self.undo.len()
+            && (self.undo.len() - count > max_commands || bytes > self.max_bytes)
+        {
+            bytes -= self.undo[count].memory_size();
+            count += 1;
+        }
+
+        self.undo.drain(..count);
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.undo.len() > 0 || self.group.as_ref().map_or(false, |g| g.commands.len() > 0)
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.redo.len() > 0
+    }
+
+    /// Name of the command undone by the next undo
+    pub fn undo_name(&self) -> Option<&str> {
+        self.undo.last().map(|c| c.name())
+    }
+
+    pub fn redo_name(&self) -> Option<&str> {
+        self.redo.last().map(|c| c.name())
+    }
+
+    pub fn len(&self) -> usize {
+        self.undo.len()
+    }
+
+    pub fn memory_size(&self) -> usize {
+        self.undo
+            .iter()
+            .chain(self.redo.iter())
+            .map(|c| c.memory_size())
+            .sum()
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.group = None;
+        self.group_depth = 0;
+    }
+
+    /// The command to undo, an open group is closed first
+    pub fn take_undo(&mut self) -> Option<Box<Command>> {
+        self.close_group();
+        self.undo.pop()
+    }
+
+    pub fn take_redo(&mut self) -> Option<Box<Command>> {
+        self.close_group();
+        self.redo.pop()
+    }
+
+    /// Record a command which was undone
+    pub fn push_undone(&mut self, cmd: Box<Command>) {
+        self.redo.push(cmd);
+    }
+
+    /// Record a command which was redone, unlike `push` the redo history is kept
+    pub fn push_redone(&mut self, cmd: Box<Command>) {
+        let redo = mem::replace(&mut self.redo, Vec::new());
+        self.push(cmd);
+        self.redo = redo;
+    }
+}
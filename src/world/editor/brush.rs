@@ -0,0 +1,365 @@
+use engine::physics::{Collider, ColliderHandle, HeightField};
+use engine::{Asset, Texture, TextureImage};
+use world::{Command, World};
+
+use image::{ImageBuffer, Rgba, RgbaImage};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Weights of the terrain materials, one sample per height field sample
+#[derive(Debug, Clone)]
+pub struct SplatMap {
+    pub columns: usize,
+    pub rows: usize,
+    pub layers: usize,
+    /// `layers` weights per sample, row major, summing to 1.0
+    pub weights: Vec<f32>,
+}
+
+impl SplatMap {
+    /// All samples use the first layer
+    pub fn new(columns: usize, rows: usize, layers: usize) -> SplatMap {
+        let layers = layers.max(1);
+        let mut weights = vec![0.0; columns * rows * layers];
+        for i in 0..columns * rows {
+            weights[i * layers] = 1.0;
+        }
+
+        SplatMap {
+            columns,
+            rows,
+            layers,
+            weights,
+        }
+    }
+
+    pub fn for_height_field(hf: &HeightField, layers: usize) -> SplatMap {
+        SplatMap::new(hf.columns, hf.rows, layers)
+    }
+
+    pub fn weight(&self, col: usize, row: usize, layer: usize) -> f32 {
+        self.weights[(row * self.columns + col) * self.layers + layer]
+    }
+
+    /// The weights of the first 4 layers in the RGBA channels, e.g. for a splat texture
+    pub fn to_image(&self) -> RgbaImage {
+        ImageBuffer::from_fn(self.columns as u32, self.rows as u32, |x, y| {
+            let mut p = [0u8; 4];
+            for layer in 0..self.layers.min(4) {
+                p[layer] = (self.weight(x as usize, y as usize, layer) * 255.0).round() as u8;
+            }
+            Rgba(p)
+        })
+    }
+
+    pub fn to_texture(&self) -> Rc<Texture> {
+        Texture::new(TextureImage::Rgba(self.to_image()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BrushMode {
+    Raise,
+    Lower,
+    /// Average with the neighbour samples
+    Smooth,
+    /// Move toward a height
+    Flatten(f32),
+    /// Increase the weight of a layer of a `SplatMap`
+    Paint(usize),
+}
+
+/// Samples changed by a brush dab, with their values before and after
+#[derive(Debug, Clone)]
+struct GridPatch {
+    col: usize,
+    row: usize,
+    columns: usize,
+    rows: usize,
+    /// Values per sample
+    stride: usize,
+    before: Vec<f32>,
+    after: Vec<f32>,
+}
+
+impl GridPatch {
+    fn memory_size(&self) -> usize {
+        (self.before.len() + self.after.len()) * 4
+    }
+
+    fn write(&self, values: &mut [f32], grid_columns: usize, after: bool) {
+        let src = if after { &self.after } else { &self.before };
+        let width = self.columns * self.stride;
+
+        for r in 0..self.rows {
+            let dst = ((self.row + r) * grid_columns + self.col) * self.stride;
+            values[dst..dst + width].copy_from_slice(&src[r * width..(r + 1) * width]);
+        }
+    }
+}
+
+/// Brush of the terrain tools, each dab of a stroke is an undoable `Command`
+///
+/// ```ignore
+/// let brush = TerrainBrush::new(BrushMode::Raise, 4.0, 0.2);
+/// world.history.begin_group("Raise terrain");
+/// for p in stroke.iter() {
+///     if let Some(cmd) = brush.dab_height(&world, terrain, p.x, p.z) {
+///         world.execute(cmd);
+///     }
+/// }
+/// world.history.end_group();
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct TerrainBrush {
+    pub mode: BrushMode,
+    pub radius: f32,
+    /// Height per dab for `Raise` and `Lower`, blend factor (0.0 - 1.0) for the others
+    pub strength: f32,
+    /// Part of the radius at full strength, the brush fades out beyond
+    pub hardness: f32,
+}
+
+impl TerrainBrush {
+    pub fn new(mode: BrushMode, radius: f32, strength: f32) -> TerrainBrush {
+        TerrainBrush {
+            mode,
+            radius,
+            strength,
+            hardness: 0.5,
+        }
+    }
+
+    pub fn with_hardness(mut self, hardness: f32) -> TerrainBrush {
+        self.hardness = hardness.max(0.0).min(1.0);
+        self
+    }
+
+    /// Strength at a distance from the center, from 1.0 to 0.0
+    pub fn falloff(&self, distance: f32) -> f32 {
+        if self.radius <= 0.0 || distance >= self.radius {
+            return 0.0;
+        }
+
+        let t = distance / self.radius;
+        if t <= self.hardness {
+            return 1.0;
+        }
+
+        let k = (t - self.hardness) / (1.0 - self.hardness);
+        1.0 - k * k * (3.0 - 2.0 * k)
+    }
+
+    /// The samples of the grid under the brush, (col, row, columns, rows)
+    fn region(&self, hf: &HeightField, x: f32, z: f32) -> Option<(usize, usize, usize, usize)> {
+        let to_index = |v: f32, origin: f32| ((v - origin) / hf.cell_size).floor() as isize;
+
+        let c0 = (to_index(x - self.radius, hf.origin.x) + 1).max(0);
+        let c1 = to_index(x + self.radius, hf.origin.x).min(hf.columns as isize - 1);
+        let r0 = (to_index(z - self.radius, hf.origin.z) + 1).max(0);
+        let r1 = to_index(z + self.radius, hf.origin.z).min(hf.rows as isize - 1);
+
+        if c1 < c0 || r1 < r0 {
+            return None;
+        }
+
+        Some((
+            c0 as usize,
+            r0 as usize,
+            (c1 - c0 + 1) as usize,
+            (r1 - r0 + 1) as usize,
+        ))
+    }
+
+    fn sample_weight(&self, hf: &HeightField, col: usize, row: usize, x: f32, z: f32) -> f32 {
+        let sx = hf.origin.x + col as f32 * hf.cell_size;
+        let sz = hf.origin.z + row as f32 * hf.cell_size;
+        let d = ((sx - x) * (sx - x) + (sz - z) * (sz - z)).sqrt();
+        self.falloff(d)
+    }
+
+    /// New heights of a dab at (x, z)
+    fn height_patch(&self, hf: &HeightField, x: f32, z: f32) -> Option<GridPatch> {
+        let (col, row, columns, rows) = self.region(hf, x, z)?;
+        let height = |c: usize, r: usize| hf.heights[r * hf.columns + c];
+
+        let mut before = Vec::with_capacity(columns * rows);
+        let mut after = Vec::with_capacity(columns * rows);
+
+        for r in row..row + rows {
+            for c in col..col + columns {
+                let h = height(c, r);
+                let w = self.sample_weight(hf, c, r, x, z);
+                let blend = (w * self.strength).max(0.0).min(1.0);
+
+                let new_h = match self.mode {
+                    BrushMode::Raise => h + w * self.strength,
+                    BrushMode::Lower => h - w * self.strength,
+                    BrushMode::Smooth => {
+                        let mut sum = 0.0;
+                        let mut n = 0.0;
+                        for nr in r.saturating_sub(1)..(r + 2).min(hf.rows) {
+                            for nc in c.saturating_sub(1)..(c + 2).min(hf.columns) {
+                                sum += height(nc, nr);
+                                n += 1.0;
+                            }
+                        }
+                        h + (sum / n - h) * blend
+                    }
+                    BrushMode::Flatten(target) => h + (target - hf.origin.y - h) * blend,
+                    BrushMode::Paint(_) => h,
+                };
+
+                before.push(h);
+                after.push(new_h);
+            }
+        }
+
+        Some(GridPatch {
+            col,
+            row,
+            columns,
+            rows,
+            stride: 1,
+            before,
+            after,
+        })
+    }
+
+    /// New weights of a `Paint` dab at (x, z), the splat map follows the layout of `hf`
+    fn splat_patch(&self, hf: &HeightField, splat: &SplatMap, x: f32, z: f32) -> Option<GridPatch> {
+        let layer = match self.mode {
+            BrushMode::Paint(layer) if layer < splat.layers => layer,
+            _ => return None,
+        };
+        let (col, row, columns, rows) = self.region(hf, x, z)?;
+
+        let stride = splat.layers;
+        let mut before = Vec::with_capacity(columns * rows * stride);
+        let mut after = Vec::with_capacity(columns * rows * stride);
+
+        for r in row..row + rows {
+            for c in col..col + columns {
+                let start = (r * splat.columns + c) * stride;
+                let weights = &splat.weights[start..start + stride];
+                before.extend_from_slice(weights);
+
+                let blend = (self.sample_weight(hf, c, r, x, z) * self.strength)
+                    .max(0.0)
+                    .min(1.0);
+
+                // Move toward the layer, the sum stays 1.0
+                for (i, w) in weights.iter().enumerate() {
+                    let target = if i == layer { 1.0 } else { 0.0 };
+                    after.push(w + (target - w) * blend);
+                }
+            }
+        }
+
+        Some(GridPatch {
+            col,
+            row,
+            columns,
+            rows,
+            stride,
+            before,
+            after,
+        })
+    }
+
+    /// A dab on a height field collider of `World::physics`, None outside of the field
+    pub fn dab_height(
+        &self,
+        world: &World,
+        collider: ColliderHandle,
+        x: f32,
+        z: f32,
+    ) -> Option<Box<Command>> {
+        let patch = match world.physics.collider(collider) {
+            Some(&Collider::HeightField(ref hf)) => self.height_patch(hf, x, z)?,
+            _ => return None,
+        };
+
+        Some(Box::new(HeightCommand { collider, patch }))
+    }
+
+    /// A `Paint` dab on a splat map of a height field collider
+    pub fn dab_splat(
+        &self,
+        world: &World,
+        collider: ColliderHandle,
+        splat: &Rc<RefCell<SplatMap>>,
+        x: f32,
+        z: f32,
+    ) -> Option<Box<Command>> {
+        let patch = match world.physics.collider(collider) {
+            Some(&Collider::HeightField(ref hf)) => self.splat_patch(hf, &splat.borrow(), x, z)?,
+            _ => return None,
+        };
+
+        Some(Box::new(SplatCommand {
+            splat: splat.clone(),
+            patch,
+        }))
+    }
+}
+
+struct HeightCommand {
+    collider: ColliderHandle,
+    patch: GridPatch,
+}
+
+impl HeightCommand {
+    fn write(&self, world: &mut World, after: bool) {
+        if let Some(&mut Collider::HeightField(ref mut hf)) = world.physics.collider_mut(self.collider)
+        {
+            let columns = hf.columns;
+            self.patch.write(&mut hf.heights, columns, after);
+        }
+    }
+}
+
+impl Command for HeightCommand {
+    fn name(&self) -> &str {
+        "Terrain height"
+    }
+
+    fn execute(&mut self, world: &mut World) {
+        self.write(world, true);
+    }
+
+    fn undo(&mut self, world: &mut World) {
+        self.write(world, false);
+    }
+
+    fn memory_size(&self) -> usize {
+        self.patch.memory_size()
+    }
+}
+
+struct SplatCommand {
+    splat: Rc<RefCell<SplatMap>>,
+    patch: GridPatch,
+}
+
+impl Command for SplatCommand {
+    fn name(&self) -> &str {
+        "Terrain paint"
+    }
+
+    fn execute(&mut self, _world: &mut World) {
+        let mut splat = self.splat.borrow_mut();
+        let columns = splat.columns;
+        self.patch.write(&mut splat.weights, columns, true);
+    }
+
+    fn undo(&mut self, _world: &mut World) {
+        let mut splat = self.splat.borrow_mut();
+        let columns = splat.columns;
+        self.patch.write(&mut splat.weights, columns, false);
+    }
+
+    fn memory_size(&self) -> usize {
+        self.patch.memory_size()
+    }
+}
@@ -0,0 +1,317 @@
+use engine::physics::PhysicsWorld;
+use engine::GameObject;
+use world::editor::Snap;
+use world::{Command, Handle, World};
+
+use math::*;
+
+/// The closest multiple of `step`
+pub fn snap_angle(angle: Rad<f32>, step: Rad<f32>) -> Rad<f32> {
+    if step.0 <= 0.0 {
+        return angle;
+    }
+    Rad((angle.0 / step.0).round() * step.0)
+}
+
+/// Rotation with its Euler angles snapped to multiples of `step`
+pub fn snap_rotation(rot: Quaternion<f32>, step: Rad<f32>) -> Quaternion<f32> {
+    let e = Euler::from(rot);
+    Quaternion::from(Euler::new(
+        snap_angle(e.x, step),
+        snap_angle(e.y, step),
+        snap_angle(e.z, step),
+    ))
+}
+
+/// A transform on the surface of the colliders under `p` (within `distance`), with its up
+/// axis along the normal of the surface, turned by `yaw` around it
+pub fn align_to_surface(
+    physics: &PhysicsWorld,
+    p: Vector3<f32>,
+    yaw: Rad<f32>,
+    distance: f32,
+) -> Option<Isometry3<f32>> {
+    let origin = p + Vector3::unit_y() * distance;
+    let (_, hit) = physics.raycast(origin, -Vector3::unit_y(), distance * 2.0)?;
+
+    let tilt = Quaternion::from_arc(Vector3::unit_y(), hit.normal, None);
+    Some(Decomposed {
+        scale: 1.0,
+        rot: tilt * Quaternion::from_angle_y(yaw),
+        disp: hit.point,
+    })
+}
+
+/// Reflection of a point across the plane through `point` with a normal
+pub fn mirror_point(p: Vector3<f32>, point: Vector3<f32>, normal: Vector3<f32>) -> Vector3<f32> {
+    let n = normal.normalize();
+    p - n * (2.0 * (p - point).dot(n))
+}
+
+/// The rotation of the reflection of an object across a plane with a normal. A reflection
+/// is not a rotation: the object keeps its handedness, so it matches the mirror image only
+/// for the pieces symmetric across their own mirrored axis.
+pub fn mirror_rotation(rot: Quaternion<f32>, normal: Vector3<f32>) -> Quaternion<f32> {
+    let n = normal.normalize();
+    let v = rot.v;
+    Quaternion::from_sv(rot.s, n * (2.0 * v.dot(n)) - v)
+}
+
+/// Copies made of each piece placed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Symmetry {
+    /// Across the plane through `point` with a normal
+    Mirror {
+        point: Vector3<f32>,
+        normal: Vector3<f32>,
+    },
+    /// `count` pieces in all, evenly rotated around an axis through `center`
+    Radial {
+        center: Vector3<f32>,
+        axis: Vector3<f32>,
+        count: usize,
+    },
+}
+
+/// Closer than this to a mirror plane or a symmetry axis, a piece has no copy
+const ON_SYMMETRY: f32 = 1e-3;
+
+impl Symmetry {
+    /// Transforms of the copies of a piece at `t`, without `t`
+    pub fn copies(&self, t: &Isometry3<f32>) -> Vec<Isometry3<f32>> {
+        match *self {
+            Symmetry::Mirror { point, normal } => {
+                let disp = mirror_point(t.disp, point, normal);
+                if (disp - t.disp).magnitude() < ON_SYMMETRY {
+                    return Vec::new();
+                }
+
+                vec![Decomposed {
+                    scale: t.scale,
+                    rot: mirror_rotation(t.rot, normal),
+                    disp,
+                }]
+            }
+            Symmetry::Radial {
+                center,
+                axis,
+                count,
+            } => {
+                let axis = axis.normalize();
+                let offset = t.disp - center;
+                if count < 2 || (offset - axis * offset.dot(axis)).magnitude() < ON_SYMMETRY {
+                    return Vec::new();
+                }
+
+                (1..count)
+                    .map(|i| {
+                        let turn = Quaternion::from_axis_angle(
+                            axis,
+                            Rad(2.0 * ::std::f32::consts::PI * i as f32 / count as f32),
+                        );
+                        Decomposed {
+                            scale: t.scale,
+                            rot: turn * t.rot,
+                            disp: center + turn.rotate_vector(offset),
+                        }
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlacementValidity {
+    Valid,
+    /// In a collider of `World::physics`, e.g. a piece placed before
+    Overlapping,
+    /// No ground under the piece
+    Floating,
+    /// The ground under the piece is too steep
+    TooSteep,
+}
+
+/// Whether a piece fits at a place, by the queries of `World::physics`. The piece is a
+/// sphere for the overlaps.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlacementCheck {
+    pub radius: f32,
+    /// Center of the sphere in the space of the piece
+    pub center: Vector3<f32>,
+    /// Penetration allowed, e.g. touching the neighbors or the ground
+    pub tolerance: f32,
+    /// Distance under the piece where the ground is searched, without ground check when 0
+    pub ground_distance: f32,
+    /// Cosine of the steepest slope of the ground
+    pub max_slope: f32,
+}
+
+impl PlacementCheck {
+    /// A piece of `radius` standing on the ground
+    pub fn new(radius: f32) -> PlacementCheck {
+        PlacementCheck {
+            radius,
+            center: Vector3::new(0.0, radius, 0.0),
+            tolerance: 0.01,
+            ground_distance: 0.1,
+            max_slope: 0.7,
+        }
+    }
+
+    pub fn check(&self, physics: &PhysicsWorld, t: &Isometry3<f32>) -> PlacementValidity {
+        let center = t.transform_point(Point3::from_vec(self.center)).to_vec();
+        let overlapping = physics
+            .sphere_contacts(center, self.radius * t.scale)
+            .iter()
+            .any(|c| c.depth > self.tolerance);
+        if overlapping {
+            return PlacementValidity::Overlapping;
+        }
+
+        if self.ground_distance > 0.0 {
+            let origin = t.disp + Vector3::unit_y() * self.ground_distance;
+            match physics.raycast(origin, -Vector3::unit_y(), self.ground_distance * 2.0) {
+                None => return PlacementValidity::Floating,
+                Some((_, hit)) if hit.normal.y < self.max_slope => {
+                    return PlacementValidity::TooSteep
+                }
+                _ => (),
+            }
+        }
+
+        PlacementValidity::Valid
+    }
+}
+
+/// Placement of pieces in a building game: the position is snapped, the rotation snapped
+/// or aligned to the surface, the symmetric copies added and the places checked.
+///
+/// ```ignore
+/// let mut tool = BuildTool::new();
+/// tool.snap = Some(Snap::Grid(1.0));
+/// tool.symmetry.push(Symmetry::Mirror { point: Vector3::zero(), normal: Vector3::unit_x() });
+/// tool.check = Some(PlacementCheck::new(0.5));
+///
+/// if let Some(cmd) = tool.place(&world, cursor_point, yaw, |world| new_wall(world)) {
+///     world.execute(cmd);
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct BuildTool {
+    pub snap: Option<Snap>,
+    pub angle_step: Option<Rad<f32>>,
+    /// Align the pieces with the surface under them, within this distance
+    pub align_to_surface: Option<f32>,
+    /// Applied in turn, e.g. two mirrors make 4 pieces
+    pub symmetry: Vec<Symmetry>,
+    pub check: Option<PlacementCheck>,
+}
+
+impl BuildTool {
+    pub fn new() -> BuildTool {
+        Default::default()
+    }
+
+    /// Transform of a piece placed at `p`, turned by `yaw`
+    pub fn placement(
+        &self,
+        physics: &PhysicsWorld,
+        p: Vector3<f32>,
+        yaw: Rad<f32>,
+    ) -> Isometry3<f32> {
+        let p = match self.snap {
+            Some(snap) => snap.snap(physics, p),
+            None => p,
+        };
+        let yaw = match self.angle_step {
+            Some(step) => snap_angle(yaw, step),
+            None => yaw,
+        };
+
+        let aligned = self.align_to_surface
+            .and_then(|distance| align_to_surface(physics, p, yaw, distance));
+        aligned.unwrap_or(Decomposed {
+            scale: 1.0,
+            rot: Quaternion::from_angle_y(yaw),
+            disp: p,
+        })
+    }
+
+    /// The piece at `t` and its symmetric copies
+    pub fn with_copies(&self, t: Isometry3<f32>) -> Vec<Isometry3<f32>> {
+        let mut all = vec![t];
+        for symmetry in self.symmetry.iter() {
+            let copies: Vec<_> = all.iter().flat_map(|t| symmetry.copies(t)).collect();
+            all.extend(copies);
+        }
+        all
+    }
+
+    /// Validity of each transform, all valid without check
+    pub fn validity(
+        &self,
+        physics: &PhysicsWorld,
+        ts: &[Isometry3<f32>],
+    ) -> Vec<PlacementValidity> {
+        ts.iter()
+            .map(|t| match self.check {
+                Some(ref check) => check.check(physics, t),
+                None => PlacementValidity::Valid,
+            })
+            .collect()
+    }
+
+    /// A command creating the pieces by `factory` and removing them on undo, `None` when a
+    /// piece does not fit
+    pub fn place<F>(
+        &self,
+        world: &World,
+        p: Vector3<f32>,
+        yaw: Rad<f32>,
+        factory: F,
+    ) -> Option<Box<Command>>
+    where
+        F: Fn(&mut World) -> Handle<GameObject> + 'static,
+    {
+        let placements = self.with_copies(self.placement(&world.physics, p, yaw));
+        let valid = self.validity(&world.physics, &placements)
+            .iter()
+            .all(|v| *v == PlacementValidity::Valid);
+        if !valid {
+            return None;
+        }
+
+        Some(Box::new(PlaceCommand {
+            placements,
+            factory: Box::new(factory),
+            objects: Vec::new(),
+        }))
+    }
+}
+
+struct PlaceCommand {
+    placements: Vec<Isometry3<f32>>,
+    factory: Box<Fn(&mut World) -> Handle<GameObject>>,
+    objects: Vec<Handle<GameObject>>,
+}
+
+impl Command for PlaceCommand {
+    fn name(&self) -> &str {
+        "Place"
+    }
+
+    fn execute(&mut self, world: &mut World) {
+        for placement in self.placements.iter() {
+            let go = (self.factory)(world);
+            go.borrow_mut().transform.set_global(*placement);
+            self.objects.push(go);
+        }
+    }
+
+    fn undo(&mut self, world: &mut World) {
+        for go in self.objects.drain(..) {
+            world.remove_game_object(&go);
+        }
+    }
+}
@@ -0,0 +1,17 @@
+//! Editing tools
+//!
+//! Programmatic editing operations for in-engine tools and in-game builders: terrain brushes
+//! on height field colliders and splat maps, scattering of objects on surfaces, snapping, and
+//! the placement of pieces with symmetry for building games.
+//! Each operation returns a `Command`, which applies the edit when executed and can undo it.
+
+mod brush;
+mod building;
+mod scatter;
+mod snap;
+
+pub use self::brush::{BrushMode, SplatMap, TerrainBrush};
+pub use self::building::{align_to_surface, mirror_point, mirror_rotation, snap_angle, snap_rotation,
+                         BuildTool, PlacementCheck, PlacementValidity, Symmetry};
+pub use self::scatter::Scatter;
+pub use self::snap::{Snap, TransformCommand};
@@ -0,0 +1,159 @@
+use engine::physics::PhysicsWorld;
+use engine::GameObject;
+use world::{Command, Handle, World};
+
+use math::*;
+use std::f32::consts::PI;
+
+/// Xorshift generator, so a scatter is reproducible from its seed
+struct Random(u32);
+
+impl Random {
+    fn next(&mut self) -> f32 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.0 = x;
+        (x >> 8) as f32 / (1 << 24) as f32
+    }
+
+    fn range(&mut self, lo: f32, hi: f32) -> f32 {
+        lo + (hi - lo) * self.next()
+    }
+}
+
+/// Places copies of an object on the surfaces of `World::physics` around a point, e.g. rocks
+/// or trees, with random rotation and scale
+#[derive(Debug, Clone)]
+pub struct Scatter {
+    pub count: usize,
+    pub radius: f32,
+    /// Minimum distance between two objects
+    pub spacing: f32,
+    /// Maximum random rotation around the up axis, in radians
+    pub yaw_jitter: f32,
+    pub scale_range: (f32, f32),
+    /// Tilt the objects with the surface, instead of keeping them upright
+    pub align_to_surface: bool,
+    /// Surfaces steeper than this (cosine of the slope) are skipped
+    pub max_slope: f32,
+    /// Height above the center from which the surfaces are searched
+    pub probe_height: f32,
+    pub seed: u32,
+}
+
+impl Scatter {
+    pub fn new(count: usize, radius: f32) -> Scatter {
+        Scatter {
+            count,
+            radius,
+            spacing: 0.0,
+            yaw_jitter: PI,
+            scale_range: (1.0, 1.0),
+            align_to_surface: false,
+            max_slope: 0.0,
+            probe_height: 50.0,
+            seed: 1,
+        }
+    }
+
+    pub fn with_seed(mut self, seed: u32) -> Scatter {
+        self.seed = seed.max(1);
+        self
+    }
+
+    /// Transforms of the objects, there may be less than `count` of them when the surface
+    /// is missing, too steep or too crowded
+    pub fn placements(&self, physics: &PhysicsWorld, center: Vector3<f32>) -> Vec<Isometry3<f32>> {
+        let mut rng = Random(self.seed.max(1));
+        let mut positions: Vec<Vector3<f32>> = Vec::new();
+        let mut result = Vec::new();
+
+        // A few attempts per object, for the rejected ones
+        for _ in 0..self.count * 4 {
+            if result.len() >= self.count {
+                break;
+            }
+
+            // Uniform in the disc
+            let r = self.radius * rng.next().sqrt();
+            let a = rng.range(0.0, 2.0 * PI);
+            let yaw = rng.range(-self.yaw_jitter, self.yaw_jitter);
+            let scale = rng.range(self.scale_range.0, self.scale_range.1);
+
+            let origin = center + Vector3::new(r * a.cos(), self.probe_height, r * a.sin());
+            let hit = match physics.raycast(origin, -Vector3::unit_y(), self.probe_height * 2.0) {
+                Some((_, hit)) => hit,
+                None => continue,
+            };
+
+            if hit.normal.y < self.max_slope {
+                continue;
+            }
+
+            let crowded = positions
+                .iter()
+                .any(|p| (p - hit.point).magnitude() < self.spacing);
+            if crowded {
+                continue;
+            }
+
+            let mut rot = Quaternion::from_angle_y(Rad(yaw));
+            if self.align_to_surface {
+                rot = Quaternion::from_arc(Vector3::unit_y(), hit.normal, None) * rot;
+            }
+
+            positions.push(hit.point);
+            result.push(Decomposed {
+                scale,
+                rot,
+                disp: hit.point,
+            });
+        }
+
+        result
+    }
+
+    /// A command creating the objects by `factory`, and removing them on undo
+    pub fn scatter<F>(&self, world: &World, center: Vector3<f32>, factory: F) -> Box<Command>
+    where
+        F: Fn(&mut World) -> Handle<GameObject> + 'static,
+    {
+        Box::new(ScatterCommand {
+            placements: self.placements(&world.physics, center),
+            factory: Box::new(factory),
+            objects: Vec::new(),
+        })
+    }
+}
+
+struct ScatterCommand {
+    placements: Vec<Isometry3<f32>>,
+    factory: Box<Fn(&mut World) -> Handle<GameObject>>,
+    objects: Vec<Handle<GameObject>>,
+}
+
+impl Command for ScatterCommand {
+    fn name(&self) -> &str {
+        "Scatter"
+    }
+
+    fn execute(&mut self, world: &mut World) {
+        for placement in self.placements.iter() {
+            let go = (self.factory)(world);
+            go.borrow_mut().transform.set_global(*placement);
+            self.objects.push(go);
+        }
+    }
+
+    fn undo(&mut self, world: &mut World) {
+        for go in self.objects.drain(..) {
+            world.remove_game_object(&go);
+        }
+    }
+
+    fn memory_size(&self) -> usize {
+        self.placements.len() * ::std::mem::size_of::<Isometry3<f32>>()
+    }
+}
@@ -0,0 +1,74 @@
+use engine::physics::PhysicsWorld;
+use engine::GameObject;
+use world::{Command, Handle, World};
+
+use math::*;
+
+/// How positions are snapped by the editing tools
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Snap {
+    /// To the closest multiple of the cell size on each axis
+    Grid(f32),
+    /// To the closest vertex of the colliders of `World::physics`, within a distance
+    Vertex(f32),
+    /// Down (or up) to the surface of the colliders, within a distance
+    Surface(f32),
+}
+
+impl Snap {
+    /// The snapped position, `p` when there is nothing to snap to
+    pub fn snap(&self, physics: &PhysicsWorld, p: Vector3<f32>) -> Vector3<f32> {
+        match *self {
+            Snap::Grid(cell) if cell > 0.0 => Vector3::new(
+                (p.x / cell).round() * cell,
+                (p.y / cell).round() * cell,
+                (p.z / cell).round() * cell,
+            ),
+            Snap::Grid(_) => p,
+            Snap::Vertex(distance) => physics.nearest_vertex(p, distance).unwrap_or(p),
+            Snap::Surface(distance) => {
+                let origin = p + Vector3::unit_y() * distance;
+                match physics.raycast(origin, -Vector3::unit_y(), distance * 2.0) {
+                    Some((_, hit)) => hit.point,
+                    None => p,
+                }
+            }
+        }
+    }
+
+    /// A command moving a game object to its snapped position
+    pub fn snap_object(&self, world: &World, go: &Handle<GameObject>) -> Box<Command> {
+        let from = go.borrow().transform.global();
+        let mut to = from;
+        to.disp = self.snap(&world.physics, from.disp);
+
+        Box::new(TransformCommand::new(go.clone(), from, to))
+    }
+}
+
+/// Change of the global transform of a game object, e.g. by a gizmo or by snapping
+pub struct TransformCommand {
+    object: Handle<GameObject>,
+    from: Isometry3<f32>,
+    to: Isometry3<f32>,
+}
+
+impl TransformCommand {
+    pub fn new(object: Handle<GameObject>, from: Isometry3<f32>, to: Isometry3<f32>) -> Self {
+        TransformCommand { object, from, to }
+    }
+}
+
+impl Command for TransformCommand {
+    fn name(&self) -> &str {
+        "Transform"
+    }
+
+    fn execute(&mut self, _world: &mut World) {
+        self.object.borrow_mut().transform.set_global(self.to);
+    }
+
+    fn undo(&mut self, _world: &mut World) {
+        self.object.borrow_mut().transform.set_global(self.from);
+    }
+}
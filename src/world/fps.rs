@@ -39,6 +39,8 @@ impl DeltaTimeStats {
 pub struct FPS {
     counter: u32,
     delta_time: f64,
+    /// Use this delta time instead of the measured one, e.g. for deterministic tests
+    pub fixed_delta_time: Option<f64>,
 
     delta_time_stats: DeltaTimeStats,
     last_delta_time_stats: DeltaTimeStats,
@@ -56,6 +58,7 @@ impl FPS {
             last_frame: now(),
             fps: 0,
             delta_time: 0.0,
+            fixed_delta_time: None,
             delta_time_stats: DeltaTimeStats::new(),
             last_delta_time_stats: DeltaTimeStats::new(),
         };
@@ -74,7 +77,7 @@ impl FPS {
     pub fn step(&mut self) {
         self.counter += 1;
         let curr = now();
-        self.delta_time = curr - self.last_frame;
+        self.delta_time = self.fixed_delta_time.unwrap_or(curr - self.last_frame);
         self.delta_time_stats.update(self.delta_time);
 
         if curr - self.last_second > 1.0 {
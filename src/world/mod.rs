@@ -4,9 +4,29 @@ mod fps;
 mod actor;
 mod type_watcher;
 mod processor;
+#[cfg(feature = "net")]
+mod bots;
+mod command;
+mod window;
+mod canvas;
+mod turns;
+mod tags;
+mod render_features;
+
+#[cfg(feature = "physics")]
+pub mod editor;
 
 pub use self::actor::Actor;
+pub use self::command::{Command, CommandGroup, UndoStack};
 pub use self::world::{Handle, World, WorldBuilder};
+pub use self::window::WindowFlags;
+pub use self::canvas::CanvasScope;
+pub use self::turns::Turns;
+pub use self::tags::{Query, TagMask, Tags, MAX_TAGS};
+pub use self::render_features::{RenderFeature, RenderFeatures, RenderStage};
+pub use self::app_fs::AppEngine;
+#[cfg(feature = "net")]
+pub use self::bots::BotSession;
 
 pub use self::processor::{Processor, ProcessorContext};
 
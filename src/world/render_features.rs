@@ -0,0 +1,188 @@
+//! Named render features registered by user crates, e.g. extra passes or custom draws
+//!
+//! A feature renders with the engine and the main camera at its `RenderStage`: before the
+//! scene for the passes the scene samples (a reflection or a custom depth map), after it for
+//! the draws on top of the frame (outlines, highlighted objects). Within a stage the features
+//! are ordered by their `dependencies`, the names of the features they need to be rendered
+//! after, and by registration order otherwise. A dependency in the earlier stage is always
+//! rendered before. Unknown dependencies, dependencies in the later stage and cycles are
+//! reported and ignored. Features are skipped when the scene has no main camera, and rendered
+//! once with the main camera in split-screen.
+//!
+//! ```ignore
+//! world.render_features.register(Box::new(OutlineFeature::new()));
+//! world.render_features.set_enabled("outline", false);
+//! ```
+
+use engine::profiler;
+use engine::Camera;
+use world::app_fs::AppEngine;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderStage {
+    BeforeScene,
+    AfterScene,
+}
+
+pub trait RenderFeature {
+    fn name(&self) -> &str;
+
+    fn stage(&self) -> RenderStage {
+        RenderStage::BeforeScene
+    }
+
+    // Names of the features rendered before this one
+    fn dependencies(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn render(&mut self, engine: &mut AppEngine, camera: &Camera);
+}
+
+struct Entry {
+    feature: Box<RenderFeature>,
+    enabled: bool,
+}
+
+#[derive(Default)]
+pub struct RenderFeatures {
+    entries: Vec<Entry>,
+    // Indices of the entries in render order, None when it must be sorted again
+    order: Option<Vec<usize>>,
+}
+
+impl RenderFeatures {
+    pub fn new() -> RenderFeatures {
+        Default::default()
+    }
+
+    /// Register a feature, replacing the one with the same name
+    pub fn register(&mut self, feature: Box<RenderFeature>) {
+        let name = feature.name().to_string();
+        self.entries.retain(|e| e.feature.name() != name);
+        self.entries.push(Entry {
+            feature,
+            enabled: true,
+        });
+        self.order = None;
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Box<RenderFeature>> {
+        let i = self.entries.iter().position(|e| e.feature.name() == name)?;
+        self.order = None;
+        Some(self.entries.remove(i).feature)
+    }
+
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) {
+        match self.entries.iter_mut().find(|e| e.feature.name() == name) {
+            Some(e) => e.enabled = enabled,
+            None => println!("Unknown render feature {}", name),
+        }
+    }
+
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.entries
+            .iter()
+            .any(|e| e.enabled && e.feature.name() == name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Names of the features in render order
+    pub fn names(&mut self) -> Vec<String> {
+        self.sort();
+
+        let entries = &self.entries;
+        self.order
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|i| entries[*i].feature.name().to_string())
+            .collect()
+    }
+
+    /// Render the enabled features of a stage
+    pub fn render(&mut self, stage: RenderStage, engine: &mut AppEngine, camera: &Camera) {
+        if self.entries.len() == 0 {
+            return;
+        }
+
+        let _scope = profiler::scope("render_features");
+        self.sort();
+
+        let order = self.order.clone().unwrap();
+        for i in order.into_iter() {
+            let entry = &mut self.entries[i];
+            if entry.enabled && entry.feature.stage() == stage {
+                entry.feature.render(engine, camera);
+            }
+        }
+    }
+
+    fn sort(&mut self) {
+        if self.order.is_some() {
+            return;
+        }
+
+        let names: Vec<String> = self.entries
+            .iter()
+            .map(|e| e.feature.name().to_string())
+            .collect();
+
+        // Indices of the dependencies of each entry, in the same stage
+        let mut deps: Vec<Vec<usize>> = Vec::new();
+        for e in self.entries.iter() {
+            let mut d = Vec::new();
+            let stage = e.feature.stage();
+            for dep in e.feature.dependencies().iter() {
+                let found = names.iter().position(|n| n == dep);
+                match found.map(|j| (j, self.entries[j].feature.stage())) {
+                    Some((j, s)) if s == stage => d.push(j),
+                    // Rendered before the scene, so before this one
+                    Some((_, RenderStage::BeforeScene)) => (),
+                    Some(_) => println!(
+                        "Render feature {} renders before the scene, not after {}",
+                        e.feature.name(),
+                        dep
+                    ),
+                    None => println!(
+                        "Render feature {} depends on unknown feature {}",
+                        e.feature.name(),
+                        dep
+                    ),
+                }
+            }
+            deps.push(d);
+        }
+
+        let mut order = Vec::new();
+        for stage in [RenderStage::BeforeScene, RenderStage::AfterScene].iter() {
+            let mut pending: Vec<usize> = (0..self.entries.len())
+                .filter(|i| self.entries[*i].feature.stage() == *stage)
+                .collect();
+
+            while pending.len() > 0 {
+                let ready = pending
+                    .iter()
+                    .position(|i| deps[*i].iter().all(|d| order.contains(d)));
+
+                let next = match ready {
+                    Some(p) => p,
+                    None => {
+                        println!(
+                            "Render feature {} has cyclic dependencies",
+                            names[pending[0]]
+                        );
+                        0
+                    }
+                };
+
+                order.push(pending.remove(next));
+            }
+        }
+
+        self.order = Some(order);
+    }
+}
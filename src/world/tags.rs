@@ -0,0 +1,306 @@
+//! Tags and component masks of the game objects, for fast gameplay queries
+//!
+//! Every tracked game object has a `TagMask` of up to `MAX_TAGS` bits, packed next to each
+//! other, so a query like "enemies that are burning and not shielded" is a few word operations
+//! per object instead of a component downcast. A bit is either a named tag, set by `Tags::add`,
+//! or a component type registered with `Tags::component`, which is set and cleared when such a
+//! component is added to or removed from a game object of the world. Components added before
+//! their type is registered are not tracked.
+//!
+//! ```ignore
+//! let enemy = world.tags.tag("enemy");
+//! let burning = world.tags.tag("burning");
+//! let shielded = world.tags.component::<Shield>();
+//!
+//! let query = Query::new().with(enemy).with(burning).without(shielded);
+//! for go in world.tags.find(&query) { .. }
+//! ```
+
+use std::any::TypeId;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use engine::{Component, ComponentEvent, GameObject, SceneTree};
+use world::Handle;
+
+pub const MAX_TAGS: usize = 128;
+
+const WORDS: usize = MAX_TAGS / 64;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct TagMask([u64; WORDS]);
+
+impl TagMask {
+    pub fn new() -> TagMask {
+        Default::default()
+    }
+
+    pub fn of(tags: &[usize]) -> TagMask {
+        let mut mask = TagMask::new();
+        for t in tags.iter() {
+            mask.set(*t);
+        }
+        mask
+    }
+
+    pub fn set(&mut self, tag: usize) {
+        self.0[tag / 64] |= 1 << (tag % 64);
+    }
+
+    pub fn clear(&mut self, tag: usize) {
+        self.0[tag / 64] &= !(1 << (tag % 64));
+    }
+
+    pub fn contains(&self, tag: usize) -> bool {
+        self.0[tag / 64] & (1 << (tag % 64)) != 0
+    }
+
+    pub fn contains_all(&self, other: &TagMask) -> bool {
+        (0..WORDS).all(|i| self.0[i] & other.0[i] == other.0[i])
+    }
+
+    pub fn intersects(&self, other: &TagMask) -> bool {
+        (0..WORDS).any(|i| self.0[i] & other.0[i] != 0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.iter().all(|w| *w == 0)
+    }
+}
+
+/// Filter of game objects by their tags
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Query {
+    pub all: TagMask,
+    pub any: TagMask,
+    pub none: TagMask,
+}
+
+impl Query {
+    pub fn new() -> Query {
+        Default::default()
+    }
+
+    /// The object must have the tag
+    pub fn with(mut self, tag: usize) -> Query {
+        self.all.set(tag);
+        self
+    }
+
+    /// The object must have at least one of the `with_any` tags
+    pub fn with_any(mut self, tag: usize) -> Query {
+        self.any.set(tag);
+        self
+    }
+
+    /// The object must not have the tag
+    pub fn without(mut self, tag: usize) -> Query {
+        self.none.set(tag);
+        self
+    }
+
+    pub fn matches(&self, mask: &TagMask) -> bool {
+        mask.contains_all(&self.all) && !mask.intersects(&self.none)
+            && (self.any.is_empty() || mask.intersects(&self.any))
+    }
+}
+
+#[derive(Default)]
+struct TagIndex {
+    names: HashMap<String, usize>,
+    components: HashMap<TypeId, usize>,
+    count: usize,
+
+    // Keyed by the address of the game object, which does not need to borrow it
+    slots: HashMap<usize, usize>,
+    masks: Vec<TagMask>,
+    objects: Vec<rc::Weak<RefCell<GameObject>>>,
+}
+
+fn key_of(go: &Handle<GameObject>) -> usize {
+    go.as_ptr() as usize
+}
+
+impl TagIndex {
+    fn next_bit(&mut self, name: &str) -> usize {
+        if self.count >= MAX_TAGS {
+            panic!(format!("Too many tags, cannot add {} (max: {})", name, MAX_TAGS));
+        }
+
+        self.count += 1;
+        self.count - 1
+    }
+
+    fn slot(&mut self, go: &Handle<GameObject>) -> usize {
+        let key = key_of(go);
+        if let Some(slot) = self.slots.get(&key).cloned() {
+            // The address may be reused by a new object once the old one is dropped
+            match self.objects[slot].upgrade() {
+                Some(ref o) if Rc::ptr_eq(o, go) => return slot,
+                _ => {
+                    self.masks[slot] = TagMask::new();
+                    self.objects[slot] = Rc::downgrade(go);
+                    return slot;
+                }
+            }
+        }
+
+        self.slots.insert(key, self.masks.len());
+        self.masks.push(TagMask::new());
+        self.objects.push(Rc::downgrade(go));
+        self.masks.len() - 1
+    }
+
+    fn find_slot(&self, go: &Handle<GameObject>) -> Option<usize> {
+        self.slots.get(&key_of(go)).cloned().filter(|slot| {
+            self.objects[*slot]
+                .upgrade()
+                .map_or(false, |o| Rc::ptr_eq(&o, go))
+        })
+    }
+
+    fn compact(&mut self) {
+        let len = self.objects.len();
+        let mut i = 0;
+        while i < self.objects.len() {
+            if self.objects[i].upgrade().is_some() {
+                i += 1;
+                continue;
+            }
+
+            self.masks.swap_remove(i);
+            self.objects.swap_remove(i);
+        }
+
+        if self.objects.len() == len {
+            return;
+        }
+
+        self.slots.clear();
+        for (i, o) in self.objects.iter().enumerate() {
+            if let Some(go) = o.upgrade() {
+                self.slots.insert(key_of(&go), i);
+            }
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct Tags {
+    index: Rc<RefCell<TagIndex>>,
+}
+
+impl Tags {
+    pub fn new() -> Tags {
+        Default::default()
+    }
+
+    /// Track the registered component types of the objects in `tree`
+    pub fn watch(&self, tree: &SceneTree) {
+        let index = self.index.clone();
+
+        tree.add_watcher(move |changed, go, c: &Arc<Component>| {
+            let mut index = index.borrow_mut();
+            let bit = match index.components.get(&c.typeid()) {
+                Some(bit) => *bit,
+                None => return,
+            };
+
+            match changed {
+                ComponentEvent::Add => {
+                    let slot = index.slot(go);
+                    index.masks[slot].set(bit);
+                }
+                ComponentEvent::Remove => {
+                    if let Some(slot) = index.find_slot(go) {
+                        index.masks[slot].clear(bit);
+                    }
+                }
+            }
+        });
+    }
+
+    /// The bit of a named tag, registered on first use
+    pub fn tag(&self, name: &str) -> usize {
+        let mut index = self.index.borrow_mut();
+        if let Some(bit) = index.names.get(name) {
+            return *bit;
+        }
+
+        let bit = index.next_bit(name);
+        index.names.insert(name.to_string(), bit);
+        bit
+    }
+
+    /// The bit of a component type, registered on first use
+    pub fn component<T: 'static>(&self) -> usize {
+        let typeid = TypeId::of::<T>();
+        let mut index = self.index.borrow_mut();
+        if let Some(bit) = index.components.get(&typeid) {
+            return *bit;
+        }
+
+        let bit = index.next_bit("component");
+        index.components.insert(typeid, bit);
+        bit
+    }
+
+    pub fn add(&self, go: &Handle<GameObject>, tag: usize) {
+        let mut index = self.index.borrow_mut();
+        let slot = index.slot(go);
+        index.masks[slot].set(tag);
+    }
+
+    pub fn remove(&self, go: &Handle<GameObject>, tag: usize) {
+        let mut index = self.index.borrow_mut();
+        if let Some(slot) = index.find_slot(go) {
+            index.masks[slot].clear(tag);
+        }
+    }
+
+    pub fn has(&self, go: &Handle<GameObject>, tag: usize) -> bool {
+        self.mask(go).contains(tag)
+    }
+
+    pub fn mask(&self, go: &Handle<GameObject>) -> TagMask {
+        let index = self.index.borrow();
+        index
+            .find_slot(go)
+            .map_or(TagMask::new(), |slot| index.masks[slot])
+    }
+
+    /// The live game objects matching the query
+    pub fn find(&self, query: &Query) -> Vec<Handle<GameObject>> {
+        let index = self.index.borrow();
+        index
+            .masks
+            .iter()
+            .zip(index.objects.iter())
+            .filter(|&(mask, _)| query.matches(mask))
+            .filter_map(|(_, o)| o.upgrade())
+            .collect()
+    }
+
+    pub fn count(&self, query: &Query) -> usize {
+        let index = self.index.borrow();
+        index
+            .masks
+            .iter()
+            .zip(index.objects.iter())
+            .filter(|&(mask, o)| query.matches(mask) && o.upgrade().is_some())
+            .count()
+    }
+
+    /// Number of tracked game objects, including the dropped ones not compacted yet
+    pub fn len(&self) -> usize {
+        self.index.borrow().masks.len()
+    }
+
+    /// Forget the dropped game objects, called by the world every frame
+    pub fn compact(&self) {
+        self.index.borrow_mut().compact();
+    }
+}
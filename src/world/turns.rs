@@ -0,0 +1,91 @@
+//! Turn-based stepping, see `WorldBuilder::with_turn_based`
+//!
+//! In turn-based mode the simulation only advances on `World::advance_turn`: the scaled time
+//! and the physics stay still between turns, and each turn moves them by `duration` seconds
+//! in a single frame. Actors are still updated every frame with the real `World::delta_time`,
+//! so rendering, UI and animations keep running, and the `Actor::turn` hook is called once
+//! per turn, before the updates of that frame. Turns queued in the same frame are played
+//! one per frame, and none is played while the world is paused.
+
+#[derive(Debug, Clone)]
+pub struct Turns {
+    /// Simulated seconds of one turn, for the scaled time and the physics
+    pub duration: f64,
+
+    enabled: bool,
+    count: u64,
+    pending: u32,
+    advancing: bool,
+}
+
+impl Default for Turns {
+    fn default() -> Turns {
+        Turns {
+            duration: 1.0 / 60.0,
+            enabled: false,
+            count: 0,
+            pending: 0,
+            advancing: false,
+        }
+    }
+}
+
+impl Turns {
+    pub fn new() -> Turns {
+        Default::default()
+    }
+
+    /// Switch between turn-based and real time stepping, the pending turns are dropped
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.pending = 0;
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Queue a turn, ignored in real time mode
+    pub fn advance(&mut self) {
+        if self.enabled {
+            self.pending += 1;
+        }
+    }
+
+    /// Number of turns played so far
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Number of turns queued but not played yet
+    pub fn pending(&self) -> u32 {
+        self.pending
+    }
+
+    /// Whether a turn is played in the current frame
+    pub fn is_advancing(&self) -> bool {
+        self.advancing
+    }
+
+    /// Start a frame, returns the simulated delta time given the real one
+    pub fn step(&mut self, dt: f64, paused: bool) -> f64 {
+        self.advancing = false;
+
+        if paused {
+            return 0.0;
+        }
+
+        if !self.enabled {
+            return dt;
+        }
+
+        if self.pending == 0 {
+            return 0.0;
+        }
+
+        self.pending -= 1;
+        self.count += 1;
+        self.advancing = true;
+        self.duration
+    }
+}
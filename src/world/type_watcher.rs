@@ -29,6 +29,44 @@ pub trait Watcher {
 
     fn object_step(&self, _go: &Handle<GameObject>, _com: &Arc<Component>, &mut World) {}
 
+    fn object_late_update(&self, _go: &Handle<GameObject>, _com: &Arc<Component>, &mut World) {}
+
+    fn object_turn(&self, _go: &Handle<GameObject>, _com: &Arc<Component>, &mut World) {}
+
+    fn watch_late_update(
+        &self,
+        actors: &RefCell<Vec<GameObjectComponentPair>>,
+        world: &mut World,
+    ) {
+        let objects: Vec<_> = actors
+            .borrow()
+            .iter()
+            .filter_map(|&(ref wgo, ref c)| match (wgo.upgrade(), c.upgrade()) {
+                (Some(go), Some(com)) => Some((go, com)),
+                _ => None,
+            })
+            .collect();
+
+        for &(ref go, ref com) in objects.iter() {
+            self.object_late_update(go, com, world);
+        }
+    }
+
+    fn watch_turn(&self, actors: &RefCell<Vec<GameObjectComponentPair>>, world: &mut World) {
+        let objects: Vec<_> = actors
+            .borrow()
+            .iter()
+            .filter_map(|&(ref wgo, ref c)| match (wgo.upgrade(), c.upgrade()) {
+                (Some(go), Some(com)) => Some((go, com)),
+                _ => None,
+            })
+            .collect();
+
+        for &(ref go, ref com) in objects.iter() {
+            self.object_turn(go, com, world);
+        }
+    }
+
     fn watch_pre_render(
         &self,
         _actors: &RefCell<Vec<GameObjectComponentPair>>,
@@ -105,8 +143,26 @@ where
 
     fn object_step(&self, go: &Handle<GameObject>, com: &Arc<Component>, world: &mut World) {
         let actor = com.try_as::<T>().unwrap();
+        if world.is_paused() && !actor.borrow().update_when_paused() {
+            return;
+        }
+
         (*actor).borrow_mut().update_rc(go.clone(), world);
     }
+
+    fn object_late_update(&self, go: &Handle<GameObject>, com: &Arc<Component>, world: &mut World) {
+        let actor = com.try_as::<T>().unwrap();
+        if world.is_paused() && !actor.borrow().update_when_paused() {
+            return;
+        }
+
+        (*actor).borrow_mut().late_update_rc(go.clone(), world);
+    }
+
+    fn object_turn(&self, go: &Handle<GameObject>, com: &Arc<Component>, world: &mut World) {
+        let actor = com.try_as::<T>().unwrap();
+        (*actor).borrow_mut().turn_rc(go.clone(), world);
+    }
 }
 
 impl Watcher for ActorWatcher<Box<Actor>> {
@@ -121,8 +177,26 @@ impl Watcher for ActorWatcher<Box<Actor>> {
 
     fn object_step(&self, go: &Handle<GameObject>, com: &Arc<Component>, world: &mut World) {
         let actor = com.try_as::<Box<Actor>>().unwrap();
+        if world.is_paused() && !actor.borrow().update_when_paused() {
+            return;
+        }
+
         (*actor).borrow_mut().update_rc(go.clone(), world);
     }
+
+    fn object_late_update(&self, go: &Handle<GameObject>, com: &Arc<Component>, world: &mut World) {
+        let actor = com.try_as::<Box<Actor>>().unwrap();
+        if world.is_paused() && !actor.borrow().update_when_paused() {
+            return;
+        }
+
+        (*actor).borrow_mut().late_update_rc(go.clone(), world);
+    }
+
+    fn object_turn(&self, go: &Handle<GameObject>, com: &Arc<Component>, world: &mut World) {
+        let actor = com.try_as::<Box<Actor>>().unwrap();
+        (*actor).borrow_mut().turn_rc(go.clone(), world);
+    }
 }
 
 pub struct TypeWatcherBuilder {
@@ -164,6 +238,20 @@ impl TypeWatcher {
         }
     }
 
+    /// Called after `step`, once all actors are updated
+    pub fn late_update(&self, world: &mut World) {
+        for &(ref watcher, ref container) in self.object_containers.iter() {
+            watcher.watch_late_update(&container.objects, world);
+        }
+    }
+
+    /// Called when a turn is played, before `step`
+    pub fn turn(&self, world: &mut World) {
+        for &(ref watcher, ref container) in self.object_containers.iter() {
+            watcher.watch_turn(&container.objects, world);
+        }
+    }
+
     pub fn pre_render(&self, world: &mut World) {
         for &(ref watcher, ref container) in self.object_containers.iter() {
             watcher.watch_pre_render(&container.objects, world);
@@ -0,0 +1,129 @@
+//! Window flags for overlay tools and desktop widgets, see `WorldBuilder::with_window_flags`
+//!
+//! On web the flags apply to the canvas: a transparent canvas shows the page behind it,
+//! a click-through canvas lets the pointer events reach the page, and an always-on-top
+//! canvas is raised above the other elements. The native window is created by uni-app,
+//! whose `AppConfig` has no option for these flags yet: they are kept in the API, and
+//! `WindowFlags::unsupported` lists the ones the platform ignores. They are reported when
+//! the world is built.
+
+use engine::ClearOption;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WindowFlags {
+    /// Transparent framebuffer, the scene is cleared with a zero alpha
+    pub transparent: bool,
+    pub always_on_top: bool,
+    /// No title bar and borders
+    pub undecorated: bool,
+    /// The pointer events go through to what is behind the window
+    pub click_through: bool,
+}
+
+impl WindowFlags {
+    pub fn new() -> WindowFlags {
+        Default::default()
+    }
+
+    /// Transparent, undecorated and always on top, e.g. a desktop widget
+    pub fn overlay() -> WindowFlags {
+        WindowFlags {
+            transparent: true,
+            always_on_top: true,
+            undecorated: true,
+            click_through: false,
+        }
+    }
+
+    pub fn with_transparent(mut self, b: bool) -> WindowFlags {
+        self.transparent = b;
+        self
+    }
+
+    pub fn with_always_on_top(mut self, b: bool) -> WindowFlags {
+        self.always_on_top = b;
+        self
+    }
+
+    pub fn with_undecorated(mut self, b: bool) -> WindowFlags {
+        self.undecorated = b;
+        self
+    }
+
+    pub fn with_click_through(mut self, b: bool) -> WindowFlags {
+        self.click_through = b;
+        self
+    }
+
+    /// Names of the flags set which the platform cannot apply
+    pub fn unsupported(&self) -> Vec<&'static str> {
+        let mut names = Vec::new();
+
+        if cfg!(target_arch = "wasm32") {
+            // A canvas has no decorations to remove
+            return names;
+        }
+
+        if self.transparent {
+            names.push("transparent");
+        }
+        if self.always_on_top {
+            names.push("always_on_top");
+        }
+        if self.undecorated {
+            names.push("undecorated");
+        }
+        if self.click_through {
+            names.push("click_through");
+        }
+        names
+    }
+
+    /// The clear of the scene, with a zero alpha when transparent
+    pub(crate) fn clear_option(&self) -> ClearOption {
+        let mut clear = ClearOption::default();
+        if self.transparent {
+            clear.color = Some((0.0, 0.0, 0.0, 0.0));
+        }
+        clear
+    }
+
+    /// Apply the flags to the window, or to the canvas matching `canvas` on web
+    pub(crate) fn apply(&self, canvas: &str) {
+        let unsupported = self.unsupported();
+        if !unsupported.is_empty() {
+            println!(
+                "Window flags not supported on this platform: {}",
+                unsupported.join(", ")
+            );
+        }
+
+        apply_canvas_flags(self, canvas);
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn apply_canvas_flags(flags: &WindowFlags, canvas: &str) {
+    let transparent = flags.transparent;
+    let on_top = flags.always_on_top;
+    let click_through = flags.click_through;
+
+    js! { @(no_return)
+        var canvas = document.querySelector(@{canvas});
+        if (canvas) {
+            if (@{transparent}) {
+                canvas.style.background = "transparent";
+            }
+            if (@{on_top}) {
+                canvas.style.position = "fixed";
+                canvas.style.zIndex = "2147483647";
+            }
+            if (@{click_through}) {
+                canvas.style.pointerEvents = "none";
+            }
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn apply_canvas_flags(_flags: &WindowFlags, _canvas: &str) {}
@@ -4,17 +4,55 @@ use std::rc::Rc;
 use std::sync::Arc;
 
 use engine::{
-    AssetSystem, Camera, ClearOption, Component, ComponentBased, ComponentType, Engine, GameObject,
-    IEngine, SceneTree,
+    AssetSystem, Camera, Component, ComponentBased, ComponentType, Engine, GameObject, IEngine,
+    Localization, SceneTree,
 };
 use world::app_fs::AppEngine;
 
+#[cfg(feature = "ui")]
 use engine::imgui;
-use engine::SoundSystem;
+#[cfg(feature = "audio")]
+use engine::{Music, SoundSystem};
+use engine::accessibility::Accessibility;
+#[cfg(feature = "ui")]
+use engine::captions::Captions;
+use engine::crash;
+use engine::diagnostics::{Diagnostics, HitchDetector, HitchReport};
+use engine::haptics::Haptics;
+use engine::input::{ActionMap, InputDevice};
+#[cfg(feature = "ui")]
+use engine::cursor::Cursor;
+use engine::asset::cache::{self, CacheManifest};
+use engine::asset::IdleQueue;
+use engine::input_glyphs::InputGlyphs;
+use engine::input_recording::InputRecorder;
+use engine::local_players::LocalPlayers;
+#[cfg(feature = "net")]
+use engine::net::Network;
+#[cfg(feature = "physics")]
+use engine::physics::PhysicsWorld;
+use engine::profiler;
+use engine::settings::Settings;
+use engine::split_screen::{SplitLayout, SplitPlayer, SplitScreen};
+use engine::tables::DataTables;
+use engine::time::Time;
+use engine::validation::{self, SceneReport, ValidationOptions};
+#[cfg(all(feature = "net", feature = "audio"))]
+use engine::voice::VoiceChat;
+#[cfg(feature = "audio")]
+use engine::AudioListener;
+use engine::{AssetError, Resource};
+use engine::quest::QuestLog;
 use world::fps::FPS;
 use world::processor::{IProcessorBuilder, Processor};
 use world::type_watcher::{ActorWatcher, TypeWatcher, TypeWatcherBuilder};
-use world::Actor;
+use world::window::WindowFlags;
+use world::canvas::CanvasScope;
+use world::turns::Turns;
+use world::tags::Tags;
+use world::render_features::{RenderFeature, RenderFeatures, RenderStage};
+use world::command::UndoStack;
+use world::{Actor, Command};
 
 use std::default::Default;
 use std::marker::PhantomData;
@@ -24,14 +62,58 @@ use uni_pad as pad;
 pub type Handle<T> = Rc<RefCell<T>>;
 
 pub struct World {
+    #[cfg(feature = "audio")]
     pub sound: SoundSystem,
+    #[cfg(feature = "audio")]
+    pub music: Music,
+    pub haptics: Haptics,
+    pub localization: Localization,
+    #[cfg(feature = "ui")]
+    pub captions: Captions,
+    pub quests: QuestLog,
+    pub settings: Settings,
+    pub tables: DataTables,
+    #[cfg(feature = "net")]
+    pub net: Network,
+    #[cfg(all(feature = "net", feature = "audio"))]
+    pub voice: VoiceChat,
+    #[cfg(feature = "physics")]
+    pub physics: PhysicsWorld,
+    pub time: Time,
+    pub turns: Turns,
+    pub tags: Tags,
+    pub render_features: RenderFeatures,
+    pub history: UndoStack,
+    pub actions: ActionMap,
+    /// Icons of the actions for the last used device
+    pub input_glyphs: InputGlyphs,
+    /// Cursor sprite drawn by the engine, disabled by default
+    #[cfg(feature = "ui")]
+    pub cursor: Cursor,
+    /// Low-priority asset work, run in the idle time between frames
+    pub idle: IdleQueue,
+    /// Records the input, or plays back a recording through the events
+    pub input_recorder: InputRecorder,
+    /// Input devices claimed by the local players
+    pub local_players: LocalPlayers,
+    pub split_screen: SplitScreen,
+
+    accessibility: Accessibility,
+    pending_settings: Option<Resource<Settings>>,
+    diagnostics: Diagnostics,
+    diagnostics_interval: f64,
+    hitches: HitchDetector,
 
     app_ref: Option<&'static mut App>,
 
     main_tree: Rc<SceneTree>,
     fps: FPS,
     watcher: Rc<TypeWatcher>,
+    #[cfg(feature = "ui")]
     shown_stats: bool,
+    paused: bool,
+    window_flags: WindowFlags,
+    canvas: CanvasScope,
     events: Rc<RefCell<Vec<AppEvent>>>,
     golist: Vec<Handle<GameObject>>,
     processor_builders: Vec<Rc<Box<IProcessorBuilder>>>,
@@ -47,9 +129,16 @@ pub struct WorldBuilder<'a> {
     size: Option<(u32, u32)>,
     headless: bool,
     fullscreen: bool,
+    #[cfg(feature = "ui")]
     shown_stats: Option<bool>,
+    fixed_delta_time: Option<f64>,
+    turn_based: bool,
+    window_flags: WindowFlags,
+    canvas: Option<String>,
+    crash_handler: Option<crash::CrashHandler>,
     watcher_builder: TypeWatcherBuilder,
     processor_builders: Vec<Rc<Box<IProcessorBuilder>>>,
+    render_features: Vec<Box<RenderFeature>>,
 }
 
 impl<'a> WorldBuilder<'a> {
@@ -57,11 +146,18 @@ impl<'a> WorldBuilder<'a> {
         WorldBuilder {
             title: title,
             size: None,
+            #[cfg(feature = "ui")]
             shown_stats: None,
+            fixed_delta_time: None,
+            turn_based: false,
+            window_flags: WindowFlags::new(),
+            canvas: None,
+            crash_handler: None,
             headless: false,
             fullscreen: false,
             watcher_builder: TypeWatcherBuilder::new(),
             processor_builders: Vec::new(),
+            render_features: Vec::new(),
         }
     }
 
@@ -80,11 +176,45 @@ impl<'a> WorldBuilder<'a> {
         self
     }
 
+    /// Show the frame statistics with imgui
+    #[cfg(feature = "ui")]
     pub fn with_stats(mut self, stats: bool) -> WorldBuilder<'a> {
         self.shown_stats = Some(stats);
         self
     }
 
+    /// Step with a constant delta time (in seconds) instead of the real one
+    pub fn with_fixed_delta_time(mut self, dt: f64) -> WorldBuilder<'a> {
+        self.fixed_delta_time = Some(dt);
+        self
+    }
+
+    /// Advance the simulation only on `World::advance_turn`, see `Turns`
+    pub fn with_turn_based(mut self, turn_based: bool) -> WorldBuilder<'a> {
+        self.turn_based = turn_based;
+        self
+    }
+
+    /// Window flags, e.g. a transparent always-on-top overlay, see `WindowFlags`.
+    /// The flags the platform cannot apply are reported by `WindowFlags::unsupported`
+    pub fn with_window_flags(mut self, flags: WindowFlags) -> WorldBuilder<'a> {
+        self.window_flags = flags;
+        self
+    }
+
+    /// Embed the world in a web page, in place of the canvas matching the css `selector`
+    /// (or in the element matching it), see `CanvasScope`
+    pub fn with_canvas(mut self, selector: &str) -> WorldBuilder<'a> {
+        self.canvas = Some(selector.to_owned());
+        self
+    }
+
+    /// Install a panic hook which reports the crash, see `engine::crash`
+    pub fn with_crash_handler(mut self, handler: crash::CrashHandler) -> WorldBuilder<'a> {
+        self.crash_handler = Some(handler);
+        self
+    }
+
     pub fn with_actor<T: Actor + 'static>(mut self) -> WorldBuilder<'a> {
         self.watcher_builder = self.watcher_builder.add_watcher(ActorWatcher::<T>::new());
         self
@@ -98,13 +228,28 @@ impl<'a> WorldBuilder<'a> {
         self
     }
 
+    /// Register a render feature, see `RenderFeatures`
+    pub fn with_render_feature(mut self, feature: Box<RenderFeature>) -> WorldBuilder<'a> {
+        self.render_features.push(feature);
+        self
+    }
+
     pub fn build<'b>(self) -> World {
+        if let Some(handler) = self.crash_handler {
+            crash::install(handler);
+        }
+
         let size = self.size.unwrap_or((800, 600));
         let mut config = AppConfig::new(self.title, size);
         config.headless = self.headless;
         config.fullscreen = self.fullscreen;
 
         let app = App::new(config);
+        let canvas = match self.canvas {
+            Some(ref target) => CanvasScope::attach(target),
+            None => CanvasScope::default(),
+        };
+        self.window_flags.apply(&canvas.selector);
 
         let hidpi = app.hidpi_factor();
         let engine = Engine::new(
@@ -124,15 +269,55 @@ impl<'a> WorldBuilder<'a> {
             .add_watcher(ActorWatcher::<Box<Actor>>::new())
             .build(main_tree.clone());
 
+        #[cfg(feature = "audio")]
         let asys = engine.asset_system.clone();
+        let localization = Localization::default();
 
         let mut w = World {
+            #[cfg(feature = "audio")]
             sound: SoundSystem::new(asys),
+            #[cfg(feature = "audio")]
+            music: Music::new(),
+            haptics: Haptics::new(),
+            #[cfg(feature = "ui")]
+            captions: Captions::new(localization.clone()),
+            localization,
+            quests: QuestLog::new(),
+            settings: Settings::default(),
+            tables: DataTables::new(),
+            #[cfg(feature = "net")]
+            net: Network::new(),
+            #[cfg(all(feature = "net", feature = "audio"))]
+            voice: VoiceChat::new(),
+            #[cfg(feature = "physics")]
+            physics: PhysicsWorld::new(),
+            time: Time::new(),
+            turns: Turns::new(),
+            tags: Tags::new(),
+            render_features: RenderFeatures::new(),
+            history: UndoStack::new(),
+            actions: ActionMap::new(),
+            input_glyphs: InputGlyphs::new(),
+            #[cfg(feature = "ui")]
+            cursor: Cursor::new(),
+            idle: IdleQueue::new(),
+            input_recorder: InputRecorder::new(),
+            local_players: LocalPlayers::new(),
+            split_screen: SplitScreen::new(),
+            accessibility: Accessibility::default(),
+            pending_settings: None,
+            diagnostics: Diagnostics::default(),
+            diagnostics_interval: 1.0,
+            hitches: HitchDetector::new(),
             engine,
             app_instance: Some(app),
             main_tree: main_tree.clone(),
             watcher: Rc::new(watcher),
+            #[cfg(feature = "ui")]
             shown_stats: self.shown_stats.unwrap_or(false),
+            paused: false,
+            window_flags: self.window_flags,
+            canvas,
             fps: FPS::new(),
             events: events,
             golist: Vec::new(),
@@ -140,6 +325,15 @@ impl<'a> WorldBuilder<'a> {
             app_ref: None,
         };
 
+        w.fps.fixed_delta_time = self.fixed_delta_time;
+        w.turns.set_enabled(self.turn_based);
+        w.tags.watch(&w.main_tree);
+        for feature in self.render_features.into_iter() {
+            w.render_features.register(feature);
+        }
+        #[cfg(feature = "ui")]
+        w.cursor.set_canvas(&w.canvas.selector);
+
         // add all processor into the scenes
         let go = w.new_game_object();
         for builder in self.processor_builders.into_iter() {
@@ -254,12 +448,43 @@ impl<'a> World {
     fn pre_render(&mut self) {
         let watcher = self.watcher.clone();
         watcher.pre_render(self);
+
+        // After all the UI, on top
+        #[cfg(feature = "ui")]
+        {
+            let (w, h) = self.engine.screen_size();
+            let hidpi = self.engine.hidpi_factor();
+            self.cursor
+                .render(self.engine.asset_system(), (w as f32 / hidpi, h as f32 / hidpi));
+        }
     }
 
     pub fn delta_time(&self) -> f64 {
         self.fps.delta_time()
     }
 
+    /// Pause the simulation, only actors which `update_when_paused` will be updated
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Play a turn in turn-based mode, the simulation advances by `turns.duration`
+    pub fn advance_turn(&mut self) {
+        self.turns.advance();
+    }
+
+    pub fn window_flags(&self) -> WindowFlags {
+        self.window_flags
+    }
+
+    pub fn canvas(&self) -> &CanvasScope {
+        &self.canvas
+    }
+
     #[cfg_attr(feature = "flame_it", flame)]
     fn step(&mut self) {
         for evt in self.events.borrow().iter() {
@@ -271,16 +496,104 @@ impl<'a> World {
             profile::dump(evt);
         }
 
-        let watcher = self.watcher.clone();
-        watcher.step(self);
+        self.canvas.filter(&mut self.events.borrow_mut());
+        self.input_recorder.step(&mut self.events.borrow_mut(), &mut self.actions);
+        self.actions.step(&self.events.borrow());
+        self.input_glyphs.step(&self.events.borrow());
+        #[cfg(feature = "ui")]
+        self.cursor.step(&self.events.borrow());
+        self.local_players.step(&self.events.borrow());
+        self.step_split_screen();
+
+        #[cfg(feature = "net")]
+        {
+            let _scope = profiler::scope("net");
+            self.net.step();
+            #[cfg(feature = "audio")]
+            self.step_voice();
+        }
+
+        {
+            let _scope = profiler::scope("physics");
+            let dt = self.delta_time();
+            let dt = self.turns.step(dt, self.paused);
+            self.time.step(dt);
+            self.time.remove_dead_overrides(&self.main_tree);
+
+            #[cfg(feature = "physics")]
+            {
+                self.physics.time_scale = self.time.scale();
+                self.physics.step(dt as f32);
+            }
+        }
 
-        self.sound.step();
+        if self.turns.is_advancing() {
+            let _scope = profiler::scope("turn");
+            let watcher = self.watcher.clone();
+            watcher.turn(self);
+        }
+
+        {
+            let _scope = profiler::scope("actors");
+            let watcher = self.watcher.clone();
+            watcher.step(self);
+        }
+
+        {
+            let _scope = profiler::scope("late_update");
+            let watcher = self.watcher.clone();
+            watcher.late_update(self);
+        }
 
-        use engine::imgui::Metric::*;
+        let _scope = profiler::scope("services");
+        #[cfg(feature = "audio")]
+        {
+            self.music.step(&mut self.sound);
+            self.sound.set_pitch(self.time.pitch());
+            self.sound.step();
+        }
+        self.haptics.step(self.delta_time() as f32);
+        self.localization.step();
+
+        #[cfg(all(feature = "audio", feature = "ui"))]
+        for name in self.sound.poll_played().iter() {
+            self.captions.trigger(name);
+        }
+        #[cfg(feature = "ui")]
+        {
+            let dt = if self.paused { 0.0 } else { self.delta_time() };
+            self.captions.step(dt as f32);
+        }
+
+        self.step_settings();
+
+        let dt = self.delta_time();
+        self.tables.step(self.engine.asset_system(), dt);
+
+        self.quests.step();
+        self.tags.compact();
+
+        if World::now() - self.diagnostics.time >= self.diagnostics_interval {
+            self.diagnostics = Diagnostics::collect(&self.engine, World::now());
+
+            if crash::is_installed() {
+                crash::set_scene_summary(format!(
+                    "fps: {} actors: {} scene objects: {} paused: {}\n{:?}",
+                    self.fps.fps,
+                    self.watcher.len(),
+                    self.main_tree.len(),
+                    self.paused,
+                    self.diagnostics
+                ));
+            }
+        }
 
         self.fps.step();
 
+        #[cfg(feature = "ui")]
         if self.shown_stats {
+            use engine::imgui::Metric::*;
+
             let loading_files = self.engine().asset_system().loading_files();
 
             let mut loading_stats = "".to_string();
@@ -315,6 +628,104 @@ impl<'a> World {
         }
     }
 
+    #[cfg(all(feature = "net", feature = "audio"))]
+    fn step_voice(&mut self) {
+        let listener = self.audio_listener();
+
+        self.voice.step(
+            self.delta_time() as f32,
+            &mut self.net,
+            &self.actions,
+            &mut self.sound,
+            listener.as_ref(),
+        );
+    }
+
+    /// Engine memory usage, refreshed every `diagnostics_interval` seconds
+    pub fn diagnostics(&self) -> &Diagnostics {
+        &self.diagnostics
+    }
+
+    pub fn set_diagnostics_interval(&mut self, seconds: f64) {
+        self.diagnostics_interval = seconds;
+    }
+
+    /// Cache the downloaded assets listed by the cache `manifest` in IndexedDB, up to
+    /// `max_bytes`, see `engine::asset::cache`. Web only.
+    pub fn enable_asset_cache(&self, manifest: &str, max_bytes: usize) {
+        cache::enable(CacheManifest::load(self.asset_system(), manifest), max_bytes);
+    }
+
+    /// Frames longer than the budget (in seconds) are reported, see `hitches`
+    pub fn set_frame_budget(&mut self, budget: Option<f64>) {
+        self.hitches.set_budget(budget);
+    }
+
+    /// Reports of the latest frames which exceeded the frame budget
+    pub fn hitches(&self) -> &[HitchReport] {
+        self.hitches.reports()
+    }
+
+    pub fn take_hitches(&mut self) -> Vec<HitchReport> {
+        self.hitches.take_reports()
+    }
+
+    /// Execute a command and record it in `history`
+    pub fn execute(&mut self, mut cmd: Box<Command>) {
+        cmd.execute(self);
+        self.history.push(cmd);
+    }
+
+    /// Undo the last command, return false when there is nothing to undo
+    pub fn undo(&mut self) -> bool {
+        match self.history.take_undo() {
+            Some(mut cmd) => {
+                cmd.undo(self);
+                self.history.push_undone(cmd);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn redo(&mut self) -> bool {
+        match self.history.take_redo() {
+            Some(mut cmd) => {
+                cmd.execute(self);
+                self.history.push_redone(cmd);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Load settings from a json file, replacing current settings when it is ready
+    pub fn load_settings(&mut self, filename: &str) {
+        self.pending_settings = Some(Settings::load(self.asset_system(), filename));
+    }
+
+    fn step_settings(&mut self) {
+        if let Some(res) = self.pending_settings.take() {
+            match res.try_into() {
+                Ok(settings) => self.settings = settings,
+                Err(AssetError::NotReady) => self.pending_settings = Some(res),
+                Err(e) => println!("Fail to load settings, reason: {:?}", e),
+            }
+        }
+
+        self.accessibility
+            .apply(&self.settings.accessibility, &mut self.engine);
+    }
+
+    /// Statistics and content errors of the scene, with the default options
+    pub fn validate_scene(&self) -> SceneReport {
+        self.validate_scene_with(&ValidationOptions::default())
+    }
+
+    pub fn validate_scene_with(&self, options: &ValidationOptions) -> SceneReport {
+        validation::validate(&self.main_tree.root(), options)
+    }
+
     pub fn events(&self) -> Ref<Vec<AppEvent>> {
         self.events.borrow()
     }
@@ -349,19 +760,52 @@ impl<'a> World {
 
     #[cfg_attr(feature = "flame_it", flame)]
     fn render(&mut self) {
-        self.engine.render(ClearOption::default());
+        let clear = self.window_flags.clear_option();
+
+        let camera = self.engine.main_camera();
+        let camera = camera.as_ref().and_then(|c| c.try_as::<Camera>());
+
+        if let Some(camera) = camera {
+            self.render_features
+                .render(RenderStage::BeforeScene, &mut self.engine, &camera.borrow());
+        }
+
+        self.engine.render(clear);
+
+        if let Some(camera) = camera {
+            self.render_features
+                .render(RenderStage::AfterScene, &mut self.engine, &camera.borrow());
+        }
     }
 
     pub fn run_frame<'b: 'a>(&mut self, app: *mut App) {
         // We can make sure the lifetime of the App will longer then engine itself
         self.app_ref = Some(unsafe { &mut *app });
 
+        let frame_start = World::now();
+        self.hitches.begin_frame();
+        let frame_scope = profiler::scope("frame");
+
         self.begin();
-        self.step();
-        self.pre_render();
-        self.render();
+        {
+            let _scope = profiler::scope("step");
+            self.step();
+        }
+        {
+            let _scope = profiler::scope("pre_render");
+            self.pre_render();
+        }
+        {
+            let _scope = profiler::scope("render");
+            self.render();
+        }
+        self.hitches.record_events(&self.events.borrow());
         self.end();
 
+        drop(frame_scope);
+        self.hitches.end_frame(self.engine.stats);
+        self.idle.end_frame(World::now() - frame_start, self.hitches.budget());
+
         profile::clear();
 
         self.app_ref = None;
@@ -406,6 +850,97 @@ impl<'a> World {
             .map(|c| ComponentBorrow::new(c))
     }
 
+    /// Split the screen between players, one per input device (up to 4). Returns the game
+    /// objects of the players' cameras, which are rendered instead of the main camera.
+    pub fn set_split_screen(
+        &mut self,
+        layout: SplitLayout,
+        devices: &[InputDevice],
+    ) -> Vec<Handle<GameObject>> {
+        use engine::RenderQueue;
+        use std::collections::BTreeSet;
+
+        self.clear_split_screen();
+        self.split_screen.layout = layout;
+
+        let mut objects = Vec::new();
+        for device in devices.iter().take(4) {
+            let go = self.new_game_object();
+
+            let mut camera = Camera::new();
+            let mut queues = BTreeSet::new();
+            queues.insert(RenderQueue::Opaque);
+            queues.insert(RenderQueue::Skybox);
+            queues.insert(RenderQueue::Transparent);
+            queues.insert(RenderQueue::Viewmodel);
+            camera.included_render_queues = Some(queues);
+            let camera = go.borrow_mut().add_component(camera);
+
+            self.split_screen.players.push(SplitPlayer {
+                device: *device,
+                actions: self.actions.for_device(*device),
+                camera_object: go.clone(),
+                camera: camera.clone(),
+                rect: ((0, 0), self.engine.screen_size),
+                screen_size: self.engine.screen_size,
+            });
+            self.engine.viewports.push(camera);
+            objects.push(go);
+        }
+
+        self.step_split_screen();
+        objects
+    }
+
+    /// Split the screen between the joined `local_players`, in the order of their slots.
+    /// Returns the player and the camera game object of each viewport.
+    pub fn set_split_screen_players(
+        &mut self,
+        layout: SplitLayout,
+    ) -> Vec<(usize, Handle<GameObject>)> {
+        let players = self.local_players.players();
+        let devices: Vec<InputDevice> = players.iter().map(|&(_, d)| d).collect();
+        let cameras = self.set_split_screen(layout, &devices);
+
+        players
+            .into_iter()
+            .map(|(player, _)| player)
+            .zip(cameras.into_iter())
+            .collect()
+    }
+
+    /// Remove the split-screen cameras, the main camera is rendered again
+    pub fn clear_split_screen(&mut self) {
+        let players: Vec<_> = self.split_screen.players.drain(..).collect();
+        for player in players.iter() {
+            self.remove_game_object(&player.camera_object);
+        }
+        self.engine.viewports.clear();
+    }
+
+    fn step_split_screen(&mut self) {
+        if self.split_screen.is_active() {
+            let screen_size = self.engine.screen_size;
+            self.split_screen
+                .step(screen_size, &self.actions, &self.events.borrow());
+        }
+    }
+
+    /// Where the positional sounds are heard from: the camera of `split_screen.listener`
+    /// in split-screen, the main camera otherwise
+    #[cfg(feature = "audio")]
+    pub fn audio_listener(&self) -> Option<AudioListener> {
+        let camera = match self.split_screen.listener_camera() {
+            Some(camera) => camera,
+            None => self.engine.main_camera()?,
+        };
+
+        let listener = camera
+            .try_as::<Camera>()
+            .map(|cam| AudioListener::from_camera(&cam.borrow()));
+        listener
+    }
+
     pub fn set_fullscreen(&mut self, b: bool) {
         self.app_ref.as_mut().unwrap().set_fullscreen(b);
     }
@@ -106,7 +106,7 @@ vec3 CalcPointLight(PointLight light, vec3 normal, vec3 fragPos, vec3 viewDir, M
     // attenuation
     float distance = length(light.position - fragPos);
     float d = (light.constant + light.linear * distance + light.quadratic * (distance * distance));
-    float attenuation = 1.0 / max(d, 0.001);
+    float attenuation = spotFactor(light, normalize(light.position - fragPos)) / max(d, 0.001);
     
     // combine results
     vec3 ambient = light.ambient * color.ambient;
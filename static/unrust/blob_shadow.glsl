@@ -0,0 +1,84 @@
+// Analytic shadows of spheres and capsules, see `actors::BlobShadows`
+
+#define UNI_BLOB_OCCLUDERS 8
+
+uniform vec4 uBlobOccluderA[UNI_BLOB_OCCLUDERS];
+uniform vec4 uBlobOccluderB[UNI_BLOB_OCCLUDERS];
+uniform float uBlobOccluderCount;
+// Toward the light
+uniform vec3 uBlobLightDirection;
+uniform float uBlobShadowStrength;
+uniform float uBlobShadowSharpness;
+uniform float uBlobAmbientOcclusion;
+
+// Point of the segment [a, b] nearest to the ray
+vec3 blobNearestPoint(vec3 ro, vec3 rd, vec3 a, vec3 b) {
+    vec3 ba = b - a;
+    vec3 oa = ro - a;
+    float baba = dot(ba, ba);
+    if (baba < 0.000001) {
+        return a;
+    }
+
+    float bard = dot(ba, rd);
+    float baoa = dot(ba, oa);
+    float rdoa = dot(rd, oa);
+    float d = baba - bard * bard;
+    float t = d > 0.000001 ? (baoa - rdoa * bard) / d : 0.0;
+    return a + ba * clamp(t, 0.0, 1.0);
+}
+
+// Point of the segment [a, b] nearest to p
+vec3 blobSegmentPoint(vec3 p, vec3 a, vec3 b) {
+    vec3 ba = b - a;
+    return a + ba * clamp(dot(p - a, ba) / max(dot(ba, ba), 0.000001), 0.0, 1.0);
+}
+
+// Soft shadow of a sphere on the ray, 1 when not shadowed
+float blobSphereShadow(vec3 ro, vec3 rd, vec3 center, float radius) {
+    vec3 oc = ro - center;
+    float b = dot(oc, rd);
+    float c = dot(oc, oc) - radius * radius;
+    float h = b * b - c;
+
+    float d = sqrt(max(0.0, radius * radius - h)) - radius;
+    float t = -b - sqrt(max(h, 0.0));
+    return t <= 0.0 ? 1.0 : smoothstep(0.0, 1.0, 2.5 * uBlobShadowSharpness * d / t);
+}
+
+// Occlusion of the light around a sphere, 1 when not occluded
+float blobSphereOcclusion(vec3 p, vec3 n, vec3 center, float radius) {
+    vec3 d = center - p;
+    // At the center, the normal gives no direction: fully occluded
+    float l = length(d);
+    if (l < 0.000001) {
+        return 0.0;
+    }
+    return 1.0 - clamp(dot(n, d / l) * (radius * radius) / (l * l), 0.0, 1.0);
+}
+
+// Light left at `p` of normal `n` by the occluders, from 0 to 1
+float blobShadow(vec3 p, vec3 n) {
+    vec3 rd = normalize(uBlobLightDirection);
+    float shadow = 1.0;
+    float occlusion = 1.0;
+
+    for (int i = 0; i < UNI_BLOB_OCCLUDERS; i++) {
+        if (float(i) >= uBlobOccluderCount) {
+            break;
+        }
+
+        vec3 a = uBlobOccluderA[i].xyz;
+        vec3 b = uBlobOccluderB[i].xyz;
+        float radius = uBlobOccluderA[i].w;
+
+        vec3 c = blobNearestPoint(p, rd, a, b);
+        shadow = min(shadow, blobSphereShadow(p, rd, c, radius));
+
+        occlusion *= blobSphereOcclusion(p, n, blobSegmentPoint(p, a, b), radius);
+    }
+
+    shadow = mix(1.0, shadow, uBlobShadowStrength);
+    occlusion = mix(1.0, occlusion, uBlobAmbientOcclusion);
+    return shadow * occlusion;
+}
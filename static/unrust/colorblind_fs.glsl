@@ -0,0 +1,63 @@
+#ifndef GL_ES
+#define varying in
+#define gl_FragColor FragColor
+#define texture2D texture
+out vec4 FragColor;
+#endif
+
+varying vec2 vTexCoords;
+
+uniform sampler2D uDiffuse;
+
+// 1: protanopia, 2: deuteranopia, 3: tritanopia, 4: achromatopsia
+uniform int uMode;
+// 0: simulate the deficiency, 1: compensate (daltonize)
+uniform int uCompensate;
+uniform float uStrength;
+
+vec3 rgb_to_lms(vec3 c) {
+    return vec3(
+        dot(c, vec3(17.8824, 43.5161, 4.11935)),
+        dot(c, vec3(3.45565, 27.1554, 3.86714)),
+        dot(c, vec3(0.0299566, 0.184309, 1.46709)));
+}
+
+vec3 lms_to_rgb(vec3 c) {
+    return vec3(
+        dot(c, vec3(0.0809444479, -0.130504409, 0.116721066)),
+        dot(c, vec3(-0.0102485335, 0.0540193266, -0.113614708)),
+        dot(c, vec3(-0.000365296938, -0.00412161469, 0.693511405)));
+}
+
+vec3 simulate(vec3 c) {
+    if (uMode == 4) {
+        return vec3(dot(c, vec3(0.299, 0.587, 0.114)));
+    }
+
+    vec3 lms = rgb_to_lms(c);
+
+    if (uMode == 1) {
+        lms.x = 2.02344 * lms.y - 2.52581 * lms.z;
+    } else if (uMode == 2) {
+        lms.y = 0.494207 * lms.x + 1.24827 * lms.z;
+    } else if (uMode == 3) {
+        lms.z = -0.395913 * lms.x + 0.801109 * lms.y;
+    }
+
+    return lms_to_rgb(lms);
+}
+
+void main(void) {
+    vec4 color = texture2D(uDiffuse, vTexCoords);
+    vec3 sim = simulate(color.rgb);
+    vec3 result = sim;
+
+    if (uCompensate == 1) {
+        // Shift the invisible error to the visible channels
+        vec3 err = color.rgb - sim;
+        vec3 shift = vec3(0.0, 0.7 * err.r + err.g, 0.7 * err.r + err.b);
+        result = color.rgb + shift;
+    }
+
+    gl_FragColor = vec4(clamp(mix(color.rgb, result, uStrength), 0.0, 1.0), color.a);
+}
@@ -0,0 +1,14 @@
+#ifndef GL_ES
+#define varying in
+#define gl_FragColor FragColor
+#define texture2D texture
+out vec4 FragColor;
+#endif
+
+varying vec2 vTexCoords;
+
+uniform sampler2D uDiffuse;
+
+void main(void) {
+    gl_FragColor = texture2D(uDiffuse, vTexCoords);
+}
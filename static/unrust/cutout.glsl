@@ -0,0 +1,62 @@
+// Clip plane, alpha cutout and dissolve of the standard material, see
+// `Material::set_cutout` and `Material::set_dissolve`. Unset, nothing is discarded.
+
+// World space plane set by the engine from `Camera::clip_plane`, the fragments below are
+// discarded
+uniform vec4 uClipPlane;
+// Fragments of a lower alpha are discarded
+uniform float uCutoff;
+// From 0 (opaque) to 1 (fully dissolved)
+uniform float uDissolve;
+// Width of the glowing edge of the dissolve, in noise units
+uniform float uDissolveEdge;
+uniform vec3 uDissolveColor;
+// Size of the noise over the uvs, 16 if unset
+uniform float uDissolveScale;
+
+float cutoutHash(vec2 p) {
+    return fract(sin(dot(p, vec2(12.9898, 78.233))) * 43758.5453);
+}
+
+// Value noise, from 0 to 1
+float cutoutNoise(vec2 p) {
+    vec2 i = floor(p);
+    vec2 f = fract(p);
+    vec2 u = f * f * (3.0 - 2.0 * f);
+
+    float a = cutoutHash(i);
+    float b = cutoutHash(i + vec2(1.0, 0.0));
+    float c = cutoutHash(i + vec2(0.0, 1.0));
+    float d = cutoutHash(i + vec2(1.0, 1.0));
+    return mix(mix(a, b, u.x), mix(c, d, u.x), u.y);
+}
+
+// Discards the clipped, cut out and dissolved fragments. Returns the glow of the dissolve
+// edge to add to the color.
+vec3 cutout(vec3 fragPos, vec2 uv, float alpha) {
+    if (dot(uClipPlane, vec4(fragPos, 1.0)) < 0.0) {
+        discard;
+    }
+
+    if (alpha < uCutoff) {
+        discard;
+    }
+
+    if (uDissolve <= 0.0) {
+        return vec3(0.0);
+    }
+
+    float n = cutoutNoise(uv * (uDissolveScale > 0.0 ? uDissolveScale : 16.0));
+    // Remapped so 1 dissolves the whole surface, edge included
+    float threshold = uDissolve * (1.0 + uDissolveEdge);
+    if (n < threshold - uDissolveEdge) {
+        discard;
+    }
+
+    if (uDissolveEdge <= 0.0) {
+        return vec3(0.0);
+    }
+
+    float edge = clamp((n - threshold + uDissolveEdge) / uDissolveEdge, 0.0, 1.0);
+    return uDissolveColor * (1.0 - edge);
+}
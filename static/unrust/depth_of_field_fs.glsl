@@ -0,0 +1,57 @@
+#ifndef GL_ES
+#define varying in
+#define gl_FragColor FragColor
+#define texture2D texture
+out vec4 FragColor;
+#endif
+
+varying vec2 vTexCoords;
+
+uniform sampler2D uDiffuse;
+uniform sampler2D uDepth;
+uniform vec2 uScreenSize;
+uniform float uZNear;
+uniform float uZFar;
+
+// distance to the focus plane, in world unit
+uniform float uFocusDistance;
+// blur strength, 0 means everything is sharp
+uniform float uAperture;
+// maximum blur radius in pixels
+uniform float uMaxBlur;
+
+const int SAMPLES = 16;
+const float GOLDEN_ANGLE = 2.39996;
+
+float linear_depth(vec2 uv) {
+    float z = texture2D(uDepth, uv).r * 2.0 - 1.0;
+    return 2.0 * uZNear * uZFar / (uZFar + uZNear - z * (uZFar - uZNear));
+}
+
+float circle_of_confusion(float depth) {
+    float coc = uAperture * abs(depth - uFocusDistance) / max(depth, 0.0001);
+    return clamp(coc, 0.0, 1.0);
+}
+
+void main(void) {
+    float coc = circle_of_confusion(linear_depth(vTexCoords));
+    vec2 radius = coc * uMaxBlur / uScreenSize;
+
+    vec4 color = texture2D(uDiffuse, vTexCoords);
+    float total = 1.0;
+
+    // Sample on a spiral disk
+    for (int i = 1; i < SAMPLES; i++) {
+        float r = sqrt(float(i) / float(SAMPLES));
+        float theta = float(i) * GOLDEN_ANGLE;
+        vec2 uv = vTexCoords + vec2(cos(theta), sin(theta)) * r * radius;
+
+        // Prevent sharp foreground from bleeding into the background
+        float w = circle_of_confusion(linear_depth(uv)) >= coc * r ? 1.0 : 0.0;
+
+        color += texture2D(uDiffuse, uv) * w;
+        total += w;
+    }
+
+    gl_FragColor = vec4((color / total).rgb, 1.0);
+}
@@ -0,0 +1,29 @@
+#ifndef GL_ES
+#define varying in
+#define gl_FragColor FragColor
+#define texture2D texture
+out vec4 FragColor;
+#endif
+
+uniform sampler2D uDetailTexture;
+uniform vec4 uDetailColor;
+// Toward the light
+uniform vec3 uDetailLightDirection;
+
+varying vec2 vTexCoord;
+varying vec3 vNormal;
+varying float vFade;
+varying float vShade;
+
+void main(void) {
+    vec4 color = texture2D(uDetailTexture, vTexCoord) * uDetailColor;
+
+    // Alpha cutout, and a dithered fade so the copies need no sorting
+    float dither = fract(sin(dot(gl_FragCoord.xy, vec2(12.9898, 78.233))) * 43758.5453);
+    if (color.a < 0.5 || vFade < dither) {
+        discard;
+    }
+
+    float light = 0.5 + 0.5 * max(dot(normalize(vNormal), normalize(uDetailLightDirection)), 0.0);
+    gl_FragColor = vec4(color.rgb * light * vShade, 1.0);
+}
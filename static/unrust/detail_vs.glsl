@@ -0,0 +1,132 @@
+#ifndef GL_ES
+#define attribute in
+#define varying out
+#define texture2D texture
+#endif
+
+#include "unrust/default_uniforms.glsl"
+
+attribute vec3 aVertexPosition;
+attribute vec2 aTextureCoord;
+attribute vec3 aVertexNormal;
+// Base (x, z) of the copy in the patch, and its random seed
+attribute vec3 aVertexTangent;
+
+uniform vec3 uDetailCenter;
+uniform float uDetailPatchSize;
+uniform float uDetailTime;
+// Distances where the fade starts and ends
+uniform vec2 uDetailFade;
+// Smallest and largest scale of the copies
+uniform vec2 uDetailScale;
+// Height of the shape, for the bending
+uniform float uDetailShapeHeight;
+
+// Heights packed in 16 bits (red high, green low)
+uniform sampler2D uDetailHeight;
+// Origin (x, z) and size of the cells of the terrain
+uniform vec3 uDetailTerrain;
+// Samples along x and z
+uniform vec2 uDetailSamples;
+// Lowest and highest height
+uniform vec2 uDetailHeightRange;
+
+uniform sampler2D uDetailDensity;
+// Selects the channel of the density
+uniform vec4 uDetailDensityMask;
+uniform float uDetailDensityScale;
+
+// Direction times strength (x, z), speed and frequency of the gusts
+uniform vec4 uDetailWind;
+
+// Position and radius of the objects bending the copies
+uniform vec4 uDetailBender0;
+uniform vec4 uDetailBender1;
+uniform vec4 uDetailBender2;
+uniform vec4 uDetailBender3;
+uniform float uDetailBendStrength;
+
+varying vec2 vTexCoord;
+varying vec3 vNormal;
+varying float vFade;
+varying float vShade;
+
+float hash(float n) {
+    return fract(sin(n) * 43758.5453);
+}
+
+float heightSample(vec2 cell) {
+    vec4 t = texture2D(uDetailHeight, (cell + 0.5) / uDetailSamples);
+    float h = (t.r * 255.0 * 256.0 + t.g * 255.0) / 65535.0;
+    return mix(uDetailHeightRange.x, uDetailHeightRange.y, h);
+}
+
+// Bilinear by hand, the packed heights cannot be filtered
+float terrainHeight(vec2 cell) {
+    vec2 c0 = floor(cell);
+    vec2 f = cell - c0;
+    float h0 = mix(heightSample(c0), heightSample(c0 + vec2(1.0, 0.0)), f.x);
+    float h1 = mix(heightSample(c0 + vec2(0.0, 1.0)), heightSample(c0 + vec2(1.0, 1.0)), f.x);
+    return mix(h0, h1, f.y);
+}
+
+vec2 push(vec4 bender, vec3 p) {
+    vec2 d = p.xz - bender.xz;
+    float l = length(d);
+    if (bender.w <= 0.0 || l >= bender.w || abs(p.y - bender.y) > bender.w * 2.0) {
+        return vec2(0.0);
+    }
+    return d / max(l, 0.001) * (bender.w - l);
+}
+
+void main(void) {
+    float size = uDetailPatchSize;
+    vec2 base = aVertexTangent.xy;
+    float seed = aVertexTangent.z;
+
+    // Each copy wraps around the center, so it stays in place in the world
+    base += size * floor((uDetailCenter.xz - base) / size + 0.5);
+
+    vec2 cell = (base - uDetailTerrain.xy) / uDetailTerrain.z;
+    vec2 last = uDetailSamples - 1.0;
+    float inside = step(0.0, cell.x) * step(cell.x, last.x) * step(0.0, cell.y) * step(cell.y, last.y);
+
+    vec4 density = texture2D(uDetailDensity, (cell + 0.5) / uDetailSamples);
+    float visible = step(seed, dot(density, uDetailDensityMask) * uDetailDensityScale) * inside;
+
+    float dist = length(base - uDetailCenter.xz);
+    float fade = 1.0 - smoothstep(uDetailFade.x, uDetailFade.y, dist);
+
+    // Hidden copies are collapsed
+    float scale = mix(uDetailScale.x, uDetailScale.y, hash(seed * 91.7)) * visible * step(0.001, fade);
+
+    float angle = hash(seed * 13.1) * 6.2831853;
+    mat2 rot = mat2(cos(angle), sin(angle), -sin(angle), cos(angle));
+    vec3 p = aVertexPosition * scale;
+    p.xz = rot * p.xz;
+    vec3 n = aVertexNormal;
+    n.xz = rot * n.xz;
+
+    vec3 world = vec3(base.x, terrainHeight(clamp(cell, vec2(0.0), last)), base.y) + p;
+
+    // The bending grows with the height in the shape
+    float bend = clamp(aVertexPosition.y / uDetailShapeHeight, 0.0, 1.0);
+    bend *= bend;
+
+    float phase = uDetailTime * uDetailWind.z + dot(base, uDetailWind.xy) * uDetailWind.w + seed * 6.2831853;
+    vec2 offset = uDetailWind.xy * (0.5 + 0.5 * sin(phase));
+    offset += uDetailBendStrength * (push(uDetailBender0, world) + push(uDetailBender1, world)
+        + push(uDetailBender2, world) + push(uDetailBender3, world));
+    offset *= bend * scale;
+
+    world.xz += offset;
+    world.y -= 0.5 * min(length(offset), p.y);
+
+    vTexCoord = aTextureCoord;
+    vNormal = n;
+    vFade = fade;
+    vShade = mix(0.75, 1.0, hash(seed * 7.3));
+
+    // The patch object has no rotation nor scale
+    gl_Position = uPMatrix * uMVMatrix * vec4(world - uMMatrix[3].xyz, 1.0);
+}
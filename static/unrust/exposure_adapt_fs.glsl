@@ -0,0 +1,41 @@
+#ifndef GL_ES
+#define varying in
+#define gl_FragColor FragColor
+#define texture2D texture
+out vec4 FragColor;
+#endif
+
+#include "unrust/float_target.glsl"
+
+// log luminance of the targets, from log(0.0001)
+#define LOG_LUMINANCE_RANGE 16.0
+
+varying vec2 vTexCoords;
+
+// average log luminance of the scene, 1x1
+uniform sampler2D uCurrent;
+// adapted log luminance of the previous frame, 1x1
+uniform sampler2D uPrevious;
+// skip the adaptation, e.g. in the first frame
+uniform bool uReset;
+uniform float uDeltaTime;
+uniform float uSpeedUp;
+uniform float uSpeedDown;
+uniform float uMinLuminance;
+uniform float uMaxLuminance;
+
+void main(void) {
+    float current = exp(readTarget(uCurrent, vec2(0.5), LOG_LUMINANCE_RANGE));
+    current = clamp(current, uMinLuminance, uMaxLuminance);
+
+    if (uReset) {
+        gl_FragColor = writeTarget(log(current), LOG_LUMINANCE_RANGE);
+        return;
+    }
+
+    float previous = exp(readTarget(uPrevious, vec2(0.5), LOG_LUMINANCE_RANGE));
+    float rate = current > previous ? uSpeedUp : uSpeedDown;
+    float adapted = previous + (current - previous) * (1.0 - exp(-uDeltaTime * rate));
+
+    gl_FragColor = writeTarget(log(adapted), LOG_LUMINANCE_RANGE);
+}
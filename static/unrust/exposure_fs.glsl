@@ -0,0 +1,39 @@
+#ifndef GL_ES
+#define varying in
+#define gl_FragColor FragColor
+#define texture2D texture
+out vec4 FragColor;
+#endif
+
+#include "unrust/float_target.glsl"
+
+// log luminance of the targets, from log(0.0001)
+#define LOG_LUMINANCE_RANGE 16.0
+
+varying vec2 vTexCoords;
+
+// hdr scene color
+uniform sampler2D uDiffuse;
+// exposure compensation as a scale, 2^stops
+uniform float uExposure;
+// use the adapted luminance for the exposure
+uniform bool uAuto;
+// adapted log luminance, 1x1
+uniform sampler2D uAdapted;
+// luminance mapped to the middle grey
+uniform float uKey;
+
+// Narkowicz ACES filmic curve approximation
+vec3 tone_map(vec3 x) {
+    return clamp((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14), 0.0, 1.0);
+}
+
+void main(void) {
+    float exposure = uExposure;
+    if (uAuto) {
+        exposure *= uKey / exp(readTarget(uAdapted, vec2(0.5), LOG_LUMINANCE_RANGE));
+    }
+
+    vec4 c = texture2D(uDiffuse, vTexCoords);
+    gl_FragColor = vec4(tone_map(c.rgb * exposure), 1.0);
+}
@@ -0,0 +1,49 @@
+// Values of the Color0Float render textures. Without float render targets they are RGBA8
+// and UNRUST_NO_FLOAT_TARGETS is defined: the values are packed in two bytes each, in a
+// range known by the reader and the writer.
+
+// [0, 1] in two bytes, in 255 * 256 steps so that 0.5 is exact
+vec2 packUnit16(float v) {
+    v = floor(clamp(v, 0.0, 1.0) * 65280.0 + 0.5);
+    float hi = floor(v / 256.0);
+    return vec2(hi, v - hi * 256.0) / 255.0;
+}
+
+float unpackUnit16(vec2 p) {
+    return (floor(p.x * 255.0 + 0.5) * 256.0 + floor(p.y * 255.0 + 0.5)) / 65280.0;
+}
+
+// A value in [-range, range] in the red channel
+vec4 writeTarget(float v, float range) {
+#ifdef UNRUST_NO_FLOAT_TARGETS
+    return vec4(packUnit16(v / range * 0.5 + 0.5), 0.0, 1.0);
+#else
+    return vec4(v, 0.0, 0.0, 1.0);
+#endif
+}
+
+float readTarget(sampler2D target, vec2 uv, float range) {
+#ifdef UNRUST_NO_FLOAT_TARGETS
+    return (unpackUnit16(texture2D(target, uv).rg) * 2.0 - 1.0) * range;
+#else
+    return texture2D(target, uv).r;
+#endif
+}
+
+// A motion in uv units, in [-1, 1]
+vec4 writeMotion(vec2 motion) {
+#ifdef UNRUST_NO_FLOAT_TARGETS
+    return vec4(packUnit16(motion.x * 0.5 + 0.5), packUnit16(motion.y * 0.5 + 0.5));
+#else
+    return vec4(motion, 0.0, 1.0);
+#endif
+}
+
+vec2 readMotion(sampler2D target, vec2 uv) {
+#ifdef UNRUST_NO_FLOAT_TARGETS
+    vec4 p = texture2D(target, uv);
+    return vec2(unpackUnit16(p.rg), unpackUnit16(p.ba)) * 2.0 - 1.0;
+#else
+    return texture2D(target, uv).rg;
+#endif
+}
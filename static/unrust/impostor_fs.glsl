@@ -0,0 +1,21 @@
+#ifndef GL_ES
+#define varying in
+#define gl_FragColor FragColor
+#define texture2D texture
+out vec4 FragColor;
+#endif
+
+uniform sampler2D uImpostorAtlas;
+
+varying vec2 vTexCoord;
+
+void main(void) {
+    vec4 color = texture2D(uImpostorAtlas, vTexCoord);
+
+    // The background of the views is transparent
+    if (color.a < 0.5) {
+        discard;
+    }
+
+    gl_FragColor = vec4(color.rgb, 1.0);
+}
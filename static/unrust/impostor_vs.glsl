@@ -0,0 +1,51 @@
+#ifndef GL_ES
+#define attribute in
+#define varying out
+#endif
+
+#include "unrust/default_uniforms.glsl"
+
+attribute vec3 aVertexPosition;
+// The corner of the quad
+attribute vec2 aTextureCoord;
+
+uniform vec3 uViewPos;
+
+// Center and half size of the views, in the local space of the mesh
+uniform vec3 uImpostorCenter;
+uniform float uImpostorExtent;
+// Columns (azimuths) and rows (elevations) of the atlas
+uniform vec2 uImpostorFrames;
+uniform float uImpostorMaxElevation;
+
+varying vec2 vTexCoord;
+
+const float PI = 3.14159265;
+
+void main(void) {
+    // The inverse of the model matrix is the transpose of the normal matrix
+    vec3 eye = (vec4(uViewPos, 1.0) * uNMatrix).xyz;
+    vec3 dir = eye - uImpostorCenter;
+    dir = length(dir) > 0.0 ? normalize(dir) : vec3(0.0, 0.0, 1.0);
+
+    // The nearest view
+    float azimuth = atan(dir.x, dir.z);
+    float elevation = asin(clamp(dir.y, -1.0, 1.0));
+    float column = mod(floor(azimuth / (2.0 * PI) * uImpostorFrames.x + 0.5), uImpostorFrames.x);
+    float row = 0.0;
+    if (uImpostorFrames.y > 1.0 && uImpostorMaxElevation > 0.0) {
+        row = elevation / uImpostorMaxElevation * (uImpostorFrames.y - 1.0);
+        row = clamp(floor(row + 0.5), 0.0, uImpostorFrames.y - 1.0);
+    }
+
+    // Facing the camera, with the same right and up as the baking cameras
+    vec3 right = cross(vec3(0.0, 1.0, 0.0), dir);
+    right = length(right) > 0.0001 ? normalize(right) : vec3(1.0, 0.0, 0.0);
+    vec3 up = cross(dir, right);
+
+    vec2 corner = aTextureCoord * 2.0 - 1.0;
+    vec3 p = uImpostorCenter + (right * corner.x + up * corner.y) * uImpostorExtent;
+
+    vTexCoord = (vec2(column, row) + aTextureCoord) / uImpostorFrames;
+    gl_Position = uPMatrix * uMVMatrix * vec4(p, 1.0);
+}
@@ -0,0 +1,34 @@
+#ifndef GL_ES
+#define varying in
+#define gl_FragColor FragColor
+#define texture2D texture
+out vec4 FragColor;
+#endif
+
+#include "unrust/blob_shadow.glsl"
+
+struct Material {
+    sampler2D diffuse;
+};
+
+uniform Material uMaterial;
+// Baked light in RGBM, see `engine::lightmap`
+uniform sampler2D uLightmap;
+uniform float uLightmapRange;
+
+varying vec3 vFragPos;
+varying vec3 vNormal;
+varying vec2 vTexCoords;
+varying vec2 vLightmapCoords;
+
+void main(void) {
+    vec4 rgbm = texture2D(uLightmap, vLightmapCoords);
+    vec3 light = rgbm.rgb * rgbm.a * uLightmapRange;
+
+    // Dynamic objects on the baked light, see `actors::BlobShadows`
+    vec3 norm = length(vNormal) > 0.0 ? normalize(vNormal) : vec3(0.0, 1.0, 0.0);
+    light *= blobShadow(vFragPos, norm);
+
+    vec4 diffuse = texture2D(uMaterial.diffuse, vTexCoords);
+    gl_FragColor = vec4(diffuse.rgb * light, diffuse.a);
+}
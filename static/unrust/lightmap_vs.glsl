@@ -0,0 +1,25 @@
+#ifndef GL_ES
+#define attribute in
+#define varying out
+#endif
+
+#include "unrust/default_uniforms.glsl"
+
+attribute vec3 aVertexPosition;
+attribute vec3 aVertexNormal;
+attribute vec2 aTextureCoord;
+attribute vec2 aTextureCoord2;
+
+varying vec3 vFragPos;
+varying vec3 vNormal;
+varying vec2 vTexCoords;
+varying vec2 vLightmapCoords;
+
+void main(void) {
+    vFragPos = vec3(uMMatrix * vec4(aVertexPosition, 1.0));
+    vNormal = mat3(uNMatrix) * aVertexNormal;
+    vTexCoords = aTextureCoord;
+    vLightmapCoords = aTextureCoord2;
+
+    gl_Position = uPMatrix * uMVMatrix * vec4(aVertexPosition, 1.0);
+}
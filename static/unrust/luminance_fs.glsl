@@ -0,0 +1,39 @@
+#ifndef GL_ES
+#define varying in
+#define gl_FragColor FragColor
+#define texture2D texture
+out vec4 FragColor;
+#endif
+
+#include "unrust/float_target.glsl"
+
+// log luminance of the targets, from log(0.0001)
+#define LOG_LUMINANCE_RANGE 16.0
+
+varying vec2 vTexCoords;
+
+// downsample by 4 in each direction, averaging 4x4 texels
+uniform sampler2D uDiffuse;
+// the source is the scene color, otherwise a log luminance of a previous pass
+uniform bool uFirst;
+// size of a destination texel in uv
+uniform vec2 uTexel;
+
+void main(void) {
+    float sum = 0.0;
+
+    for (int i = 0; i < 4; i++) {
+        for (int j = 0; j < 4; j++) {
+            vec2 uv = vTexCoords + (vec2(float(i), float(j)) - 1.5) * uTexel / 4.0;
+            if (uFirst) {
+                vec4 c = texture2D(uDiffuse, uv);
+                float l = dot(c.rgb, vec3(0.2126, 0.7152, 0.0722));
+                sum += log(max(l, 0.0001));
+            } else {
+                sum += readTarget(uDiffuse, uv, LOG_LUMINANCE_RANGE);
+            }
+        }
+    }
+
+    gl_FragColor = writeTarget(sum / 16.0, LOG_LUMINANCE_RANGE);
+}
@@ -0,0 +1,18 @@
+#ifndef GL_ES
+#define varying in
+#define gl_FragColor FragColor
+out vec4 FragColor;
+#endif
+
+#include "unrust/float_target.glsl"
+
+varying vec4 vCurrPos;
+varying vec4 vPrevPos;
+
+void main(void) {
+    // from the previous to the current position, in uv units
+    vec2 curr = vCurrPos.xy / vCurrPos.w;
+    vec2 prev = vPrevPos.xy / vPrevPos.w;
+
+    gl_FragColor = writeMotion((curr - prev) * 0.5);
+}
@@ -0,0 +1,24 @@
+#ifndef GL_ES
+#define attribute in
+#define varying out
+#endif
+
+#include "unrust/default_uniforms.glsl"
+
+attribute vec3 aVertexPosition;
+
+uniform mat4 uPVMatrix;
+uniform mat4 uPrevMMatrix;
+uniform mat4 uPrevPVMatrix;
+
+varying vec4 vCurrPos;
+varying vec4 vPrevPos;
+
+void main(void) {
+    vec4 pos = vec4(aVertexPosition, 1.0);
+
+    vCurrPos = uPVMatrix * uMMatrix * pos;
+    vPrevPos = uPrevPVMatrix * uPrevMMatrix * pos;
+
+    gl_Position = uPMatrix * uMVMatrix * pos;
+}
@@ -18,4 +18,20 @@ struct PointLight {
     vec3 specular;
 
     float rate;
-};
\ No newline at end of file
+
+    // A spot light lights the cone around spotDirection, a point light has spotCosOuter < -1
+    vec3 spotDirection;
+    float spotCosOuter;
+    float spotCosInner;
+};
+
+// Light of a point or spot light in the direction lightDir, from the fragment to the light
+float spotFactor(PointLight light, vec3 lightDir) {
+    if (light.spotCosOuter < -1.0) {
+        return 1.0;
+    }
+
+    float cosTheta = dot(-lightDir, normalize(light.spotDirection));
+    float width = max(light.spotCosInner - light.spotCosOuter, 0.0001);
+    return clamp((cosTheta - light.spotCosOuter) / width, 0.0, 1.0);
+}
\ No newline at end of file
@@ -8,6 +8,7 @@ out vec4 FragColor;
 
 #include "unrust/phong_light.glsl"
 #include "unrust/shadow_utils.glsl"
+#include "unrust/cutout.glsl"
 
 struct Material {
     sampler2D diffuse;
@@ -29,6 +30,9 @@ vec3 CalcDirectionalLight(DirectionalLight light, vec3 normal, vec3 viewDir);
 vec3 CalcPointLight(PointLight light, vec3 normal, vec3 fragPos, vec3 viewDir);
 
 void main(void) {
+    float alpha = texture2D(uMaterial.diffuse, vTexCoords).a;
+    vec3 glow = cutout(vFragPos, vTexCoords, alpha);
+
     vec3 norm = normalize(vNormal);
     vec3 viewDir = normalize(uViewPos - vFragPos);
 
@@ -39,7 +43,7 @@ void main(void) {
     for(int i = 0; i < UNI_POINT_LIGHTS; i++)
         result += CalcPointLight(uPointLights[i], norm, vFragPos, viewDir);
 
-    gl_FragColor = vec4(result, 1.0);           
+    gl_FragColor = vec4(result + glow, 1.0);           
 }
 
 vec3 CalcDirectionalLight(DirectionalLight light, vec3 normal, vec3 viewDir)
@@ -74,7 +78,7 @@ vec3 CalcPointLight(PointLight light, vec3 normal, vec3 fragPos, vec3 viewDir)
     // attenuation
     float distance = length(light.position - fragPos);
     float d = (light.constant + light.linear * distance + light.quadratic * (distance * distance));
-    float attenuation = 1.0 / max(d, 0.001);
+    float attenuation = spotFactor(light, normalize(light.position - fragPos)) / max(d, 0.001);
     
     // combine results
     vec3 ambient = light.ambient * vec3(texture2D(uMaterial.diffuse, vTexCoords));
@@ -0,0 +1,14 @@
+#ifndef GL_ES
+#define varying in
+#define gl_FragColor FragColor
+#define texture2D texture
+out vec4 FragColor;
+#endif
+
+uniform sampler2D uReflectionTexture;
+varying vec4 vReflectionPos;
+
+void main(void) {
+    vec2 uv = vReflectionPos.xy / vReflectionPos.w;
+    gl_FragColor = vec4(texture2D(uReflectionTexture, uv).rgb, 1.0);
+}
@@ -0,0 +1,16 @@
+#ifndef GL_ES
+#define attribute in
+#define varying out
+#endif
+
+#include "unrust/default_uniforms.glsl"
+
+attribute vec3 aVertexPosition;
+
+uniform mat4 uReflectionMatrix;
+varying vec4 vReflectionPos;
+
+void main(void) {
+    vReflectionPos = uReflectionMatrix * uMMatrix * vec4(aVertexPosition, 1.0);
+    gl_Position = uPMatrix * uMVMatrix * vec4(aVertexPosition, 1.0);
+}
@@ -0,0 +1,46 @@
+#ifndef GL_ES
+#define varying in
+#define gl_FragColor FragColor
+#define texture2D texture
+out vec4 FragColor;
+#endif
+
+#include "unrust/phong_light.glsl"
+
+struct Material {
+    sampler2D diffuse;
+};
+
+uniform Material uMaterial;
+uniform DirectionalLight uDirectionalLight;
+
+// Baked ambient light of the probes, from +x, -x, +y, -y, +z and -z
+uniform vec3 uProbe0;
+uniform vec3 uProbe1;
+uniform vec3 uProbe2;
+uniform vec3 uProbe3;
+uniform vec3 uProbe4;
+uniform vec3 uProbe5;
+
+varying vec3 vNormal;
+varying vec2 vTexCoords;
+
+vec3 ambientCube(vec3 n) {
+    vec3 n2 = n * n;
+    vec3 x = n.x >= 0.0 ? uProbe0 : uProbe1;
+    vec3 y = n.y >= 0.0 ? uProbe2 : uProbe3;
+    vec3 z = n.z >= 0.0 ? uProbe4 : uProbe5;
+    return x * n2.x + y * n2.y + z * n2.z;
+}
+
+void main(void) {
+    vec3 norm = normalize(vNormal);
+    vec4 diffuse = texture2D(uMaterial.diffuse, vTexCoords);
+
+    // Baked indirect light, and the dynamic direct light
+    vec3 light = ambientCube(norm);
+    vec3 lightDir = normalize(-uDirectionalLight.direction);
+    light += uDirectionalLight.diffuse * max(dot(norm, lightDir), 0.0);
+
+    gl_FragColor = vec4(diffuse.rgb * light, diffuse.a);
+}
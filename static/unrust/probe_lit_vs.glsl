@@ -0,0 +1,20 @@
+#ifndef GL_ES
+#define attribute in
+#define varying out
+#endif
+
+#include "unrust/default_uniforms.glsl"
+
+attribute vec3 aVertexPosition;
+attribute vec3 aVertexNormal;
+attribute vec2 aTextureCoord;
+
+varying vec3 vNormal;
+varying vec2 vTexCoords;
+
+void main(void) {
+    vNormal = mat3(uNMatrix) * aVertexNormal;
+    vTexCoords = aTextureCoord;
+
+    gl_Position = uPMatrix * uMVMatrix * vec4(aVertexPosition, 1.0);
+}
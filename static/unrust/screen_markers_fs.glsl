@@ -0,0 +1,25 @@
+#ifndef GL_ES
+#define varying in
+#define gl_FragColor FragColor
+#define texture2D texture
+out vec4 FragColor;
+#endif
+
+uniform sampler2D uMarkerFont;
+
+varying vec2 vTexCoord;
+varying vec4 vColor;
+varying float vTextured;
+
+void main(void) {
+    vec4 color = vColor;
+    if (vTextured > 0.5) {
+        color *= texture2D(uMarkerFont, vTexCoord);
+    }
+
+    if (color.a < 0.01) {
+        discard;
+    }
+
+    gl_FragColor = color;
+}
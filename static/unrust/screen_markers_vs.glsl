@@ -0,0 +1,22 @@
+#ifndef GL_ES
+#define attribute in
+#define varying out
+#endif
+
+// In normalized device coordinates
+attribute vec3 aVertexPosition;
+attribute vec2 aTextureCoord;
+attribute vec3 aVertexNormal;
+// Alpha, and whether the font is sampled
+attribute vec3 aVertexTangent;
+
+varying vec2 vTexCoord;
+varying vec4 vColor;
+varying float vTextured;
+
+void main(void) {
+    vTexCoord = aTextureCoord;
+    vColor = vec4(aVertexNormal, aVertexTangent.x);
+    vTextured = aVertexTangent.y;
+    gl_Position = vec4(aVertexPosition.xy, 0.0, 1.0);
+}
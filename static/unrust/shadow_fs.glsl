@@ -5,6 +5,21 @@
 out vec4 FragColor;
 #endif
 
+#include "unrust/cutout.glsl"
+
+// the diffuse texture of the object, for its alpha cutout
+struct Material {
+    sampler2D diffuse;
+};
+
+uniform Material uMaterial;
+
+varying vec3 vFragPos;
+varying vec2 vTexCoords;
+
 void main()
 {
-}         
\ No newline at end of file
+    // The holes of the cut out and dissolved objects let the light through
+    float alpha = uCutoff > 0.0 ? texture2D(uMaterial.diffuse, vTexCoords).a : 1.0;
+    cutout(vFragPos, vTexCoords, alpha);
+}
@@ -6,10 +6,18 @@
 #include "unrust/default_uniforms.glsl"
 
 attribute vec3 aVertexPosition;
-uniform mat4 uShadowMatrix;            
+attribute vec2 aTextureCoord;
+uniform mat4 uShadowMatrix;
+
+varying vec3 vFragPos;
+varying vec2 vTexCoords;
 
 void main(void) {
-    vec4 pos = uShadowMatrix * uMMatrix * vec4(aVertexPosition, 1.0);    
+    vec4 world = uMMatrix * vec4(aVertexPosition, 1.0);
+    vec4 pos = uShadowMatrix * world;
     pos.z *= pos.w;
     gl_Position = pos;
-}
\ No newline at end of file
+
+    vFragPos = world.xyz;
+    vTexCoords = aTextureCoord;
+}
@@ -0,0 +1,47 @@
+#ifndef GL_ES
+#define varying in
+#define gl_FragColor FragColor
+#define texture2D texture
+out vec4 FragColor;
+#endif
+
+#include "unrust/float_target.glsl"
+
+varying vec2 vTexCoords;
+
+// current jittered frame
+uniform sampler2D uDiffuse;
+// packed without float render targets
+uniform sampler2D uMotion;
+// resolved previous frame
+uniform sampler2D uHistory;
+// the history is empty
+uniform bool uReset;
+// weight of the current frame
+uniform float uBlend;
+// size of a pixel in uv
+uniform vec2 uTexel;
+
+void main(void) {
+    vec4 current = texture2D(uDiffuse, vTexCoords);
+    vec2 prev_uv = vTexCoords - readMotion(uMotion, vTexCoords);
+
+    if (uReset || prev_uv.x < 0.0 || prev_uv.x > 1.0 || prev_uv.y < 0.0 || prev_uv.y > 1.0) {
+        gl_FragColor = current;
+        return;
+    }
+
+    // Clamp the history to the colors around the pixel, which rejects disoccluded pixels
+    vec4 lo = current;
+    vec4 hi = current;
+    for (int x = -1; x <= 1; x++) {
+        for (int y = -1; y <= 1; y++) {
+            vec4 c = texture2D(uDiffuse, vTexCoords + vec2(float(x), float(y)) * uTexel);
+            lo = min(lo, c);
+            hi = max(hi, c);
+        }
+    }
+
+    vec4 history = clamp(texture2D(uHistory, prev_uv), lo, hi);
+    gl_FragColor = mix(history, current, uBlend);
+}
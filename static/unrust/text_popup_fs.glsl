@@ -0,0 +1,20 @@
+#ifndef GL_ES
+#define varying in
+#define gl_FragColor FragColor
+#define texture2D texture
+out vec4 FragColor;
+#endif
+
+uniform sampler2D uPopupFont;
+
+varying vec2 vTexCoord;
+varying vec4 vColor;
+
+void main(void) {
+    vec4 glyph = texture2D(uPopupFont, vTexCoord);
+    if (glyph.a * vColor.a < 0.01) {
+        discard;
+    }
+
+    gl_FragColor = vec4(glyph.rgb * vColor.rgb, glyph.a * vColor.a);
+}
@@ -0,0 +1,65 @@
+#ifndef GL_ES
+#define attribute in
+#define varying out
+#endif
+
+#include "unrust/default_uniforms.glsl"
+
+// Spawn position of the popup
+attribute vec3 aVertexPosition;
+attribute vec2 aTextureCoord;
+// Corner in glyph heights from the center of the text, and spawn time
+attribute vec3 aVertexNormal;
+// Scale and random seed of the popup
+attribute vec3 aVertexTangent;
+// Color of the popup
+attribute vec3 aVertexBitangent;
+
+uniform float uPopupTime;
+uniform float uPopupLifetime;
+// Height of a glyph in world units
+uniform float uPopupSize;
+uniform float uPopupSpread;
+
+// The motion curves over the normalized lifetime, see `actors::PopupMotion`
+uniform vec3 uPopupOffset[16];
+uniform float uPopupScale[16];
+uniform float uPopupAlpha[16];
+
+varying vec2 vTexCoord;
+varying vec4 vColor;
+
+float hash(float n) {
+    return fract(sin(n) * 43758.5453);
+}
+
+void main(void) {
+    float t = (uPopupTime - aVertexNormal.z) / uPopupLifetime;
+
+    // Expired or unused glyphs are collapsed
+    if (t < 0.0 || t >= 1.0) {
+        gl_Position = vec4(0.0, 0.0, -2.0, 1.0);
+        vTexCoord = vec2(0.0);
+        vColor = vec4(0.0);
+        return;
+    }
+
+    float x = t * 15.0;
+    int i = int(floor(x));
+    int j = int(min(float(i + 1), 15.0));
+    float f = x - float(i);
+
+    vec3 offset = mix(uPopupOffset[i], uPopupOffset[j], f);
+    float scale = mix(uPopupScale[i], uPopupScale[j], f) * aVertexTangent.x;
+    float alpha = mix(uPopupAlpha[i], uPopupAlpha[j], f);
+
+    // The popup is centered on its position, the glyphs face the camera
+    vec4 center = uMVMatrix * vec4(aVertexPosition + offset, 1.0);
+    vec2 corner = aVertexNormal.xy * scale;
+    corner.x += (hash(aVertexTangent.y * 12.9898) - 0.5) * uPopupSpread;
+    center.xy += corner * uPopupSize;
+
+    vTexCoord = aTextureCoord;
+    vColor = vec4(aVertexBitangent, alpha);
+    gl_Position = uPMatrix * center;
+}
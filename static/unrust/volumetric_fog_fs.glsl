@@ -0,0 +1,140 @@
+#ifndef GL_ES
+#define varying in
+#define gl_FragColor FragColor
+#define texture2D texture
+out vec4 FragColor;
+#endif
+
+#include "unrust/shadow_map.glsl"
+#include "unrust/phong_light.glsl"
+
+varying vec2 vTexCoords;
+
+uniform sampler2D uDiffuse;
+uniform sampler2D uDepth;
+
+uniform mat4 uViewProj;
+uniform mat4 uInvViewProj;
+uniform vec3 uCameraPos;
+
+uniform int uSteps;
+uniform float uDensity;
+uniform float uFogHeight;
+uniform float uHeightFalloff;
+uniform vec3 uFogColor;
+uniform float uAnisotropy;
+uniform float uScattering;
+uniform float uMaxDistance;
+uniform float uJitter;
+
+// direction of the light rays, and color of the main light
+uniform vec3 uLightDirection;
+uniform vec3 uLightColor;
+
+// Spot lights scattered by the fog, without shadows
+const int MAX_SPOT_LIGHTS = 4;
+uniform int uSpotLightCount;
+uniform PointLight uSpotLights[MAX_SPOT_LIGHTS];
+
+uniform bool uShadowEnabled;
+uniform ShadowMap uShadowMap[4];
+uniform sampler2D uShadowMapTexture;
+
+const int MAX_STEPS = 32;
+
+vec3 world_position(vec2 uv, float depth) {
+    vec4 p = uInvViewProj * vec4(vec3(uv, depth) * 2.0 - 1.0, 1.0);
+    return p.xyz / p.w;
+}
+
+float fog_density(vec3 p) {
+    return uDensity * exp(-uHeightFalloff * max(p.y - uFogHeight, 0.0));
+}
+
+// Henyey-Greenstein phase function, 1 for isotropic scattering
+float phase(float cos_theta) {
+    float g = uAnisotropy;
+    float d = 1.0 + g * g - 2.0 * g * cos_theta;
+    return (1.0 - g * g) / (d * sqrt(d));
+}
+
+// Light scattered toward the camera at p by the spot lights
+vec3 spot_light(vec3 p, vec3 dir) {
+    vec3 result = vec3(0.0);
+    for (int i = 0; i < MAX_SPOT_LIGHTS; i++) {
+        if (i >= uSpotLightCount) {
+            break;
+        }
+
+        vec3 to_light = uSpotLights[i].position - p;
+        float d = length(to_light);
+        vec3 l = to_light / max(d, 0.0001);
+        float att = uSpotLights[i].constant + uSpotLights[i].linear * d
+            + uSpotLights[i].quadratic * d * d;
+
+        float cone = spotFactor(uSpotLights[i], l);
+        result += uSpotLights[i].diffuse * (cone / max(att, 0.001) * phase(dot(dir, l)));
+    }
+
+    return result * uScattering;
+}
+
+float visibility(vec3 p) {
+    if (!uShadowEnabled) {
+        return 1.0;
+    }
+
+    vec4 clip = uViewProj * vec4(p, 1.0);
+    float nz = clip.z / clip.w;
+    int index = 0;
+    for (int i = 1; i < 4; i++) {
+        if (nz > uShadowMap[i].range.x) {
+            index = i;
+        }
+    }
+
+    vec4 lp = uShadowMap[index].light_matrix * vec4(p, 1.0);
+    vec3 proj = lp.xyz / lp.w * 0.5 + 0.5;
+    if (proj.x < 0.0 || proj.x > 1.0 || proj.y < 0.0 || proj.y > 1.0 || proj.z > 1.0) {
+        return 1.0;
+    }
+
+    vec2 uv = uShadowMap[index].viewport_offset + proj.xy * uShadowMap[index].viewport_scale;
+    float bias = uShadowMap[index].tex_size;
+    return float(proj.z - bias <= texture2D(uShadowMapTexture, uv).r);
+}
+
+void main(void) {
+    vec4 color = texture2D(uDiffuse, vTexCoords);
+    vec3 target = world_position(vTexCoords, texture2D(uDepth, vTexCoords).r);
+
+    vec3 ray = target - uCameraPos;
+    float dist = min(length(ray), uMaxDistance);
+    vec3 dir = normalize(ray);
+
+    float step_len = dist / float(uSteps);
+    float light_phase = phase(dot(dir, -normalize(uLightDirection)));
+    vec3 light = uLightColor * light_phase * uScattering;
+
+    float transmittance = 1.0;
+    vec3 inscatter = vec3(0.0);
+
+    // Interleave the sample positions between neighbour pixels and frames
+    float offset = fract(uJitter + dot(gl_FragCoord.xy, vec2(0.0671, 0.00584)) * 13.0);
+
+    for (int i = 0; i < MAX_STEPS; i++) {
+        if (i >= uSteps) {
+            break;
+        }
+
+        vec3 p = uCameraPos + dir * (float(i) + offset) * step_len;
+        float extinction = fog_density(p) * step_len;
+        float absorbed = 1.0 - exp(-extinction);
+
+        vec3 lit = uFogColor + light * visibility(p) + spot_light(p, dir);
+        inscatter += transmittance * absorbed * lit;
+        transmittance *= 1.0 - absorbed;
+    }
+
+    gl_FragColor = vec4(color.rgb * transmittance + inscatter, color.a);
+}
@@ -0,0 +1,214 @@
+extern crate unrust;
+
+use unrust::engine::animation::{blend_additive, blend_override, AnimationClip, Bone, BoneMap,
+                                BoneMask, ClipSampler, CompressedClip, CompressionSettings,
+                                Curve, Keyframe, LocalPose, QuantizedQuat, Retargeter, Skeleton,
+                                Track};
+use unrust::engine::GameObject;
+use unrust::math::*;
+
+fn walk_clip() -> AnimationClip {
+    let mut track = Track::new("hips");
+    track.translation = Curve::new(
+        (0..31)
+            .map(|i| Keyframe {
+                time: i as f32 / 30.0,
+                value: Vector3::new(i as f32 / 30.0, 0.0, 0.0),
+            })
+            .collect(),
+    );
+    track.rotation = Curve::new(
+        (0..31)
+            .map(|i| Keyframe {
+                time: i as f32 / 30.0,
+                value: Quaternion::from(Euler::new(Deg(0.0), Deg(i as f32 * 3.0), Deg(0.0))),
+            })
+            .collect(),
+    );
+
+    let mut still = Track::new("head");
+    still.scale = Curve::new(
+        (0..31)
+            .map(|i| Keyframe {
+                time: i as f32 / 30.0,
+                value: Vector3::new(1.0, 1.0, 1.0),
+            })
+            .collect(),
+    );
+
+    AnimationClip {
+        name: "walk".to_owned(),
+        duration: 1.0,
+        tracks: vec![track, still],
+    }
+}
+
+#[test]
+fn test_curve_sample_nan() {
+    let key = |time: f32, value: f32| Keyframe { time, value };
+    let curve = Curve::new(vec![key(0.0, 0.0), key(1.0, 1.0), key(2.0, 0.0)]);
+    assert_eq!(curve.sample(0.5), Some(0.5));
+    assert_eq!(curve.sample(::std::f32::NAN), Some(0.0));
+
+    // A key of invalid time does not panic
+    let curve = Curve::new(vec![key(0.0, 0.0), key(::std::f32::NAN, 1.0), key(2.0, 0.0)]);
+    assert!(curve.sample(1.0).is_some());
+    let curve = Curve::new(vec![key(::std::f32::NAN, 1.0)]);
+    assert_eq!(curve.sample(1.0), Some(1.0));
+}
+
+#[test]
+fn test_quantized_quat() {
+    let q = Quaternion::from(Euler::new(Deg(30.0), Deg(-70.0), Deg(120.0)));
+    let r = QuantizedQuat::new(q).get();
+
+    // q and -q are the same rotation
+    assert!(q.dot(r).abs() > 0.99999);
+}
+
+#[test]
+fn test_compress_clip() {
+    let clip = walk_clip();
+    let compressed = CompressedClip::compress(&clip, &CompressionSettings::default());
+
+    // Linear translation and constant scale keep their end keys only
+    assert_eq!(compressed.tracks[0].translation.keys.len(), 2);
+    assert_eq!(compressed.tracks[1].scale.keys.len(), 1);
+    assert!(compressed.tracks[0].rotation.keys.len() < 31);
+    assert!(compressed.tracks[1].rotation.keys.is_empty());
+
+    let mut sampler = ClipSampler::new();
+    for &time in [0.0, 0.25, 0.5, 0.9, 1.0, 0.1].iter() {
+        let expected = clip.sample(time);
+        let poses = sampler.sample(&compressed, time);
+
+        let t = poses[0].translation.unwrap();
+        assert!((t - expected[0].translation.unwrap()).magnitude() < 0.002);
+
+        let r = poses[0].rotation.unwrap();
+        assert!(r.dot(expected[0].rotation.unwrap()).abs() > 0.9999);
+
+        assert_eq!(poses[1].rotation, None);
+        assert!((poses[1].scale.unwrap() - Vector3::new(1.0, 1.0, 1.0)).magnitude() < 0.001);
+    }
+}
+
+fn bone(name: &str, rest: Isometry3<f32>, height: f32) -> Bone {
+    Bone {
+        name: name.to_owned(),
+        object: GameObject::empty(),
+        parent: None,
+        rest,
+        rest_scale: Vector3::new(1.0, 1.0, 1.0),
+        rest_model: Isometry3 {
+            disp: Vector3::new(0.0, height, 0.0),
+            rot: Quaternion::one(),
+            scale: 1.0,
+        },
+    }
+}
+
+#[test]
+fn test_retarget_clip() {
+    let turn = Quaternion::from_angle_y(Deg(90.0));
+    let rest = |y: f32, rot: Quaternion<f32>| Isometry3 {
+        disp: Vector3::new(0.0, y, 0.0),
+        rot,
+        scale: 1.0,
+    };
+
+    let source = Skeleton {
+        bones: vec![
+            bone("Hips", rest(1.0, Quaternion::one()), 1.0),
+            bone("Head", rest(0.5, Quaternion::one()), 1.5),
+        ],
+    };
+    let target = Skeleton {
+        bones: vec![
+            bone("hips", rest(2.0, Quaternion::one()), 2.0),
+            bone("head", rest(1.0, turn), 3.0),
+        ],
+    };
+
+    let mut map = BoneMap::default();
+    map.root = Some("Hips".to_owned());
+    map.bones.insert("Hips".to_owned(), "hips".to_owned());
+    map.bones.insert("Head".to_owned(), "head".to_owned());
+
+    let mut hips = Track::new("Hips");
+    hips.translation = Curve::new(vec![Keyframe {
+        time: 0.0,
+        value: Vector3::new(0.5, 1.0, 0.0),
+    }]);
+    let mut head = Track::new("Head");
+    head.translation = Curve::new(vec![Keyframe {
+        time: 0.0,
+        value: Vector3::new(0.0, 0.5, 0.0),
+    }]);
+    head.rotation = Curve::new(vec![Keyframe {
+        time: 0.0,
+        value: Quaternion::one(),
+    }]);
+
+    let clip = AnimationClip {
+        name: "idle".to_owned(),
+        duration: 1.0,
+        tracks: vec![hips, head, Track::new("Tail")],
+    };
+
+    let retargeter = Retargeter::new(map, &source, &target);
+    assert_eq!(retargeter.root_scale(), 2.0);
+
+    let out = retargeter.retarget(&clip);
+    assert_eq!(out.tracks.len(), 2);
+    assert_eq!(out.tracks[0].bone, "hips");
+    assert_eq!(
+        out.tracks[0].translation.sample(0.0),
+        Some(Vector3::new(1.0, 2.0, 0.0))
+    );
+
+    // The head keeps the target proportions and rest orientation
+    assert!(out.tracks[1].translation.keys.is_empty());
+    let r = out.tracks[1].rotation.sample(0.0).unwrap();
+    assert!(r.dot(turn).abs() > 0.9999);
+}
+
+#[test]
+fn test_layer_blend() {
+    let rest = Isometry3::one();
+    let mut spine = bone("spine", rest, 1.0);
+    spine.parent = Some(0);
+    let mut arm = bone("arm", rest, 1.5);
+    arm.parent = Some(1);
+
+    let skeleton = Skeleton {
+        bones: vec![bone("hips", rest, 0.5), spine, arm],
+    };
+
+    let mask = BoneMask::new()
+        .with_branch("spine", 1.0)
+        .with_branch("arm", 0.5);
+    assert_eq!(mask.weights(&skeleton), vec![0.0, 1.0, 0.5]);
+
+    let at = |x: f32| LocalPose {
+        translation: Vector3::new(x, 0.0, 0.0),
+        rotation: Quaternion::one(),
+        scale: Vector3::new(1.0, 1.0, 1.0),
+    };
+
+    // The legs keep running, the upper body aims
+    let mut pose = vec![at(1.0), at(1.0), at(1.0)];
+    blend_override(&mut pose, &[at(3.0), at(3.0), at(3.0)], &mask.weights(&skeleton));
+    assert_eq!(pose[0].translation.x, 1.0);
+    assert_eq!(pose[1].translation.x, 3.0);
+    assert_eq!(pose[2].translation.x, 2.0);
+
+    // Only the difference from the reference is added
+    let mut pose = vec![at(1.0), at(1.0), at(1.0)];
+    let mut layer = vec![at(2.0), at(2.0), at(2.0)];
+    layer[1].rotation = Quaternion::from_angle_z(Deg(90.0));
+    blend_additive(&mut pose, &layer, &[at(1.5), at(1.5), at(1.5)], &[1.0, 1.0, 0.0]);
+    assert_eq!(pose[0].translation.x, 1.5);
+    assert_eq!(pose[2].translation.x, 1.0);
+    assert!(pose[1].rotation.dot(Quaternion::from_angle_z(Deg(90.0))).abs() > 0.9999);
+}
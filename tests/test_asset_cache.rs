@@ -0,0 +1,29 @@
+extern crate unrust;
+
+use unrust::engine::asset::cache::{content_hash, entry_bytes, entry_data, transcoded_key,
+                                   CacheManifest};
+
+#[test]
+fn test_cache_manifest() {
+    // FNV-1a test vectors
+    assert_eq!(content_hash(b""), "cbf29ce484222325");
+    assert_eq!(content_hash(b"a"), "af63dc4c8601ec8c");
+
+    let mut manifest = CacheManifest::new(2);
+    manifest.add("level1\\rock.png", b"a");
+    assert_eq!(manifest.files["level1/rock.png"], "af63dc4c8601ec8c");
+    assert_eq!(transcoded_key("af63dc4c8601ec8c"), "af63dc4c8601ec8c:dds");
+}
+
+#[test]
+fn test_cache_entry_hash() {
+    let entry = entry_bytes(b"rock");
+    assert_eq!(entry_data(&entry), Some(&b"rock"[..]));
+
+    // A partial write or a flipped byte is not returned
+    assert_eq!(entry_data(&entry[..entry.len() - 1]), None);
+    let mut damaged = entry.clone();
+    damaged[0] ^= 1;
+    assert_eq!(entry_data(&damaged), None);
+    assert_eq!(entry_data(b"rock"), None);
+}
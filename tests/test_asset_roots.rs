@@ -0,0 +1,29 @@
+extern crate unrust;
+
+use unrust::engine::{asset_path, AssetRoot, AssetRoots};
+
+#[test]
+fn test_asset_roots_resolve() {
+    let roots = AssetRoots::default();
+    roots.add(AssetRoot::new("hd", "mods/hd", 10));
+    roots.add(AssetRoot::new("red", "mods/red", 0).with_files(vec!["tex_a.png".to_owned()]));
+
+    // Probed roots and base files are both in the asset directory
+    let exists = |path: &str| path == "static/mods/hd/tex_b.png";
+    assert_eq!(roots.resolve("static", "tex_b.png", exists), "static/mods/hd/tex_b.png");
+    assert_eq!(roots.resolve("static", "tex_a.png", exists), "static/mods/red/tex_a.png");
+    assert_eq!(roots.resolve("static", "tex_c.png", exists), "static/tex_c.png");
+
+    // Web has no base directory
+    assert_eq!(roots.resolve("", "tex_a.png", |_| false), "mods/red/tex_a.png");
+    assert_eq!(roots.resolve("", "tex_c.png", |_| false), "tex_c.png");
+}
+
+#[test]
+fn test_asset_path() {
+    assert_eq!(asset_path("static/", "a/b.png"), "static/a/b.png");
+    assert_eq!(asset_path("", "a/b.png"), "a/b.png");
+    if cfg!(unix) {
+        assert_eq!(asset_path("static", "/opt/mods/b.png"), "/opt/mods/b.png");
+    }
+}
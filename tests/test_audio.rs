@@ -0,0 +1,74 @@
+#![cfg(feature = "audio")]
+
+extern crate unrust;
+
+use unrust::engine::sound::{AudioLod, AudioLodLevel, Envelope, LowPass, SynthGraph, Waveform};
+
+#[test]
+fn test_audio_lod_level() {
+    let lod = AudioLod::new(4);
+
+    assert_eq!(lod.level(1.0, 1.0), AudioLodLevel::Full);
+    assert_eq!(lod.level(1.0, 0.1), AudioLodLevel::Reduced);
+    assert_eq!(lod.level(20.0, 1.0), AudioLodLevel::Reduced);
+    assert_eq!(lod.level(50.0, 1.0), AudioLodLevel::Flat);
+
+    assert!(lod.is_update_frame(AudioLodLevel::Full, 3));
+    assert!(!lod.is_update_frame(AudioLodLevel::Reduced, 3));
+    assert!(lod.is_update_frame(AudioLodLevel::Reduced, 4));
+    assert!(!lod.is_update_frame(AudioLodLevel::Flat, 4));
+    assert!(lod.is_update_frame(AudioLodLevel::Flat, 16));
+}
+
+#[test]
+fn test_lowpass() {
+    assert_eq!(LowPass::cutoff(0.0), 0.0);
+    assert!((LowPass::cutoff(1.0) - 20000.0).abs() < 1.0);
+
+    // The coefficient depends on the sample rate
+    let a = LowPass::alpha(1000.0, 44100.0);
+    let b = LowPass::alpha(1000.0, 22050.0);
+    assert!(a > 0.0 && a < b && b < 1.0);
+    assert_eq!(LowPass::alpha(0.0, 44100.0), 0.0);
+
+    // A constant signal passes through
+    let mut filter = LowPass::new();
+    let mut out = 0.0;
+    for _ in 0..1000 {
+        out = filter.process(1.0, 1000.0, 44100.0);
+    }
+    assert!((out - 1.0).abs() < 1e-3);
+
+    // Closed, it is silent at once instead of holding the last value
+    assert_eq!(filter.process(1.0, 0.0, 44100.0), 0.0);
+    assert!(filter.process(1.0, 1000.0, 44100.0) < 0.2);
+}
+
+#[test]
+fn test_voice_stealing() {
+    let lod = AudioLod::new(2);
+
+    // (priority, audibility)
+    let requests = [(0, 0.5), (0, 0.9), (1, 0.1), (0, 0.0)];
+    assert_eq!(lod.allocate(&requests), vec![2, 1]);
+}
+
+#[test]
+fn test_synth_graph() {
+    let mut graph = SynthGraph::new();
+    let pitch = graph.param("pitch", 100.0);
+    let osc = graph.oscillator(Waveform::Square, pitch, 0.5);
+    let env = graph.envelope(Envelope::pluck(0.1));
+    graph.multiply(osc, env);
+    graph.set_duration(0.2);
+
+    let samples = graph.render(1000.0, 0.3);
+    assert_eq!(samples.len(), 300);
+
+    let peak = samples.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+    assert!(peak > 0.4 && peak <= 0.5);
+
+    // Silent after the envelope and the duration
+    assert!(samples[150..].iter().all(|s| s.abs() < 1e-6));
+    assert!(graph.is_finished());
+}
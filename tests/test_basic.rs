@@ -1,3 +1,5 @@
+#![cfg(feature = "ui")]
+
 extern crate image;
 extern crate uni_pad;
 extern crate unrust;
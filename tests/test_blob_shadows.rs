@@ -0,0 +1,30 @@
+extern crate unrust;
+
+use unrust::actors::{BlobShadows, MAX_BLOB_OCCLUDERS};
+use unrust::math::*;
+
+use std::f32;
+
+#[test]
+fn test_blob_shadows_nearest() {
+    let mut shadows = BlobShadows::new();
+    for i in (0..12).rev() {
+        let p = Vector3::new(i as f32, 0.0, 0.0);
+        shadows.add_occluder(p, p + Vector3::unit_y(), 0.5);
+    }
+
+    // Invalid occluders are dropped instead of breaking the sort
+    let nan = Vector3::new(f32::NAN, 0.0, 0.0);
+    shadows.add_occluder(nan, nan, 0.5);
+    shadows.add_occluder(Vector3::zero(), Vector3::zero(), f32::NAN);
+    shadows.add_occluder(Vector3::zero(), Vector3::zero(), 0.0);
+
+    shadows.keep_nearest(Vector3::zero());
+
+    let occluders = shadows.occluders();
+    assert_eq!(occluders.len(), MAX_BLOB_OCCLUDERS);
+    for (i, o) in occluders.iter().enumerate() {
+        assert_eq!(o.0.x, i as f32);
+        assert_eq!(o.2, 0.5);
+    }
+}
@@ -0,0 +1,85 @@
+extern crate unrust;
+
+use unrust::engine::{Camera, TemporalAA, Viewmodel};
+use unrust::math::*;
+
+fn ndc_z(p: &Matrix4<f32>, z: f32) -> f32 {
+    let clip = p * Vector4::new(0.0, 0.0, z, 1.0);
+    clip.z / clip.w
+}
+
+#[test]
+fn test_camera_depth_range() {
+    let mut camera = Camera::new();
+    camera.znear = 0.1;
+    camera.zfar = 10.0;
+
+    let p = camera.perspective((100, 100));
+    assert!((ndc_z(&p, -0.1) + 1.0).abs() < 1e-4);
+    assert!((ndc_z(&p, -10.0) - 1.0).abs() < 1e-4);
+
+    camera.depth_range = Some((0.0, 0.25));
+    let p = camera.perspective((100, 100));
+    assert!((ndc_z(&p, -0.1) + 1.0).abs() < 1e-4);
+    assert!((ndc_z(&p, -10.0) + 0.5).abs() < 1e-4);
+}
+
+#[test]
+fn test_camera_viewmodel() {
+    let mut camera = Camera::new();
+    assert!(camera.viewmodel_camera().is_none());
+
+    camera.lookat(
+        &Point3::new(1.0, 2.0, 3.0),
+        &Point3::new(0.0, 0.0, 0.0),
+        &Vector3::unit_y(),
+    );
+    camera.viewmodel = Some(Viewmodel::default());
+
+    let vm = camera.viewmodel_camera().unwrap();
+    assert_eq!(vm.v, camera.v);
+    assert_eq!(vm.eye(), camera.eye());
+    assert_eq!(vm.fovy, Viewmodel::default().fovy);
+    assert_eq!(vm.depth_range, Some(Viewmodel::default().depth_range));
+}
+
+#[test]
+fn test_camera_taa_jitter() {
+    let taa = TemporalAA::new();
+    let j = taa.jitter();
+    assert!(j.x.abs() <= 0.5 && j.y.abs() <= 0.5);
+
+    let mut camera = Camera::new();
+    let p = camera.perspective((100, 100));
+    camera.taa = Some(taa);
+    let jittered = camera.perspective((100, 100));
+
+    // Offset by the jitter in clip space
+    let v = Vector4::new(0.0, 0.0, -1.0, 1.0);
+    let (a, b) = (p * v, jittered * v);
+    assert!((b.x / b.w - a.x / a.w - 2.0 * j.x / 100.0).abs() < 1e-5);
+    assert!((b.y / b.w - a.y / a.w - 2.0 * j.y / 100.0).abs() < 1e-5);
+
+    // No jitter while capturing tiles
+    camera.tile = Some((0, 0, 1));
+    assert_eq!(camera.perspective((100, 100)), p);
+}
+
+#[test]
+fn test_camera_taa_motion_encoding() {
+    for &(x, y) in [(0.0, 0.0), (-0.25, 0.5), (0.001, -0.999), (-1.0, 1.0)].iter() {
+        let m = TemporalAA::decode_motion(TemporalAA::encode_motion(Vector2::new(x, y)));
+        assert!((m.x - x).abs() < 1e-4 && (m.y - y).abs() < 1e-4);
+    }
+
+    // No motion is exact, it is the clear color of the motion vectors
+    assert_eq!(TemporalAA::encode_motion(Vector2::new(0.0, 0.0)), [127, 128, 127, 128]);
+    assert_eq!(
+        TemporalAA::decode_motion([127, 128, 127, 128]),
+        Vector2::new(0.0, 0.0)
+    );
+
+    // Clamped to the uv range
+    let m = TemporalAA::decode_motion(TemporalAA::encode_motion(Vector2::new(-3.0, 2.0)));
+    assert_eq!(m, Vector2::new(-1.0, 1.0));
+}
@@ -0,0 +1,18 @@
+extern crate unrust;
+
+use unrust::actors::AimConstraint;
+use unrust::math::*;
+
+#[test]
+fn test_aim_rotation() {
+    let aim = AimConstraint::new();
+
+    let dir = Vector3::new(1.0, 1.0, 0.0).normalize();
+    let rot = aim.aim_rotation(dir);
+    assert!((rot * aim.aim_axis - dir).magnitude() < 1e-5);
+
+    // The up axis stays in the vertical plane of the aim direction
+    let up = rot * aim.up_axis;
+    assert!(up.z.abs() < 1e-5);
+    assert!(up.y > 0.0);
+}
@@ -0,0 +1,32 @@
+#![cfg(feature = "ui")]
+
+extern crate uni_app;
+extern crate unrust;
+
+use uni_app::AppEvent;
+use unrust::engine::cursor::{Cursor, CursorSprite, CursorState};
+
+#[test]
+fn test_cursor_sprite_fallback() {
+    let mut cursor = Cursor::new();
+    assert!(cursor.sprite(CursorState::Hover).is_none());
+
+    cursor.set_sprite(CursorState::Normal, CursorSprite::new("arrow.png", (32.0, 32.0)));
+    cursor.set_sprite(
+        CursorState::Hover,
+        CursorSprite::new("hand.png", (32.0, 32.0)).with_hotspot((10.0, 2.0)),
+    );
+
+    assert_eq!(cursor.sprite(CursorState::Hover).unwrap().texture, "hand.png");
+    assert_eq!(cursor.sprite(CursorState::Drag).unwrap().texture, "arrow.png");
+}
+
+#[test]
+fn test_cursor_position() {
+    let mut cursor = Cursor::new();
+    cursor.step(&[AppEvent::MousePos((100.0, 50.0))]);
+    assert_eq!(cursor.position(), (100.0, 50.0));
+
+    cursor.move_by((-200.0, 20.0), (800.0, 600.0));
+    assert_eq!(cursor.position(), (0.0, 70.0));
+}
@@ -0,0 +1,36 @@
+extern crate unrust;
+
+use unrust::actors::{build_detail_patch, DetailShape};
+
+#[test]
+fn test_detail_patch() {
+    let shape = DetailShape::Blades {
+        width: 0.4,
+        height: 0.6,
+    };
+    let buffers = build_detail_patch(&shape, 10000, 20.0, 5.0, 3);
+
+    // 8 vertices per copy, in 16 bits indices
+    assert_eq!(buffers.len(), 2);
+    let copies: usize = buffers.iter().map(|b| b.indices.len() / 12).sum();
+    assert_eq!(copies, 10000);
+
+    for data in buffers.iter() {
+        let count = data.vertices.len() / 3;
+        assert!(count <= 65535);
+        assert_eq!(data.tangents.as_ref().unwrap().len(), count * 3);
+        assert_eq!(data.uvs.as_ref().unwrap().len(), count * 2);
+
+        // The bounds cover the patch and the heights
+        let bound = data.compute_bound();
+        assert_eq!(bound.aabb.min.x, -10.0);
+        assert_eq!(bound.aabb.max.y, 5.0);
+
+        let tangents = data.tangents.as_ref().unwrap();
+        for t in tangents[..tangents.len() - 6].chunks(3) {
+            assert!(t[0] >= -10.0 && t[0] <= 10.0);
+            assert!(t[1] >= -10.0 && t[1] <= 10.0);
+            assert!(t[2] >= 0.0 && t[2] < 1.0);
+        }
+    }
+}
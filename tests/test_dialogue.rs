@@ -0,0 +1,85 @@
+extern crate serde_json;
+extern crate unrust;
+
+use unrust::engine::dialogue::{eval_condition, DialogueEvent, DialogueGraph, DialoguePlayer,
+                               DialogueValue, DialogueVariables};
+use unrust::engine::localization::Localization;
+
+use std::rc::Rc;
+
+fn graph() -> Rc<DialogueGraph> {
+    let graph = serde_json::from_str(
+        r#"{
+            "start": "hello",
+            "nodes": {
+                "hello": { "speaker": "npc", "text": "dlg.hello", "next": "ask" },
+                "ask": {
+                    "text": "dlg.ask",
+                    "choices": [
+                        { "text": "dlg.yes", "next": "yes" },
+                        { "text": "dlg.pay", "next": "yes", "condition": "gold >= 10" }
+                    ]
+                },
+                "yes": { "events": ["give_key"], "set": { "has_key": true }, "next": "again" },
+                "again": {
+                    "branches": [ { "condition": "has_key && !angry", "next": "bye" } ],
+                    "text": "dlg.no"
+                },
+                "bye": { "text": "dlg.bye" }
+            }
+        }"#,
+    ).unwrap();
+
+    Rc::new(graph)
+}
+
+#[test]
+fn test_dialogue_conditions() {
+    let mut vars = DialogueVariables::new();
+    vars.insert("gold".to_owned(), DialogueValue::Int(12));
+    vars.insert("name".to_owned(), DialogueValue::Str("bob".to_owned()));
+
+    assert!(eval_condition("gold >= 10", &vars));
+    assert!(!eval_condition("gold < 10.5", &vars));
+    assert!(eval_condition("name == \"bob\" && gold != 3", &vars));
+    assert!(eval_condition("!missing", &vars));
+    assert!(!eval_condition("missing == 1", &vars));
+    assert!(eval_condition("", &vars));
+}
+
+#[test]
+fn test_dialogue_player() {
+    let mut player = DialoguePlayer::new(graph(), Localization::default());
+    player.start();
+
+    assert_eq!(
+        player.poll_events(),
+        vec![DialogueEvent::Line {
+            node: "hello".to_owned(),
+            speaker: Some("npc".to_owned()),
+            text: "dlg.hello".to_owned(),
+        }]
+    );
+
+    // Waiting for a choice, the one without enough gold is hidden
+    player.advance();
+    assert!(player.is_waiting_choice());
+    let events = player.poll_events();
+    assert_eq!(events[1], DialogueEvent::Choices(vec![(0, "dlg.yes".to_owned())]));
+
+    player.advance();
+    assert_eq!(player.current_node(), Some("ask"));
+    player.choose(1);
+    assert_eq!(player.current_node(), Some("ask"));
+
+    // Pass through the node without text, then the branch
+    player.choose(0);
+    assert_eq!(player.current_node(), Some("bye"));
+    assert_eq!(player.variables.get("has_key"), Some(&DialogueValue::Bool(true)));
+    let events = player.poll_events();
+    assert!(events.contains(&DialogueEvent::Event("give_key".to_owned())));
+
+    player.advance();
+    assert!(!player.is_running());
+    assert_eq!(player.poll_events(), vec![DialogueEvent::End]);
+}
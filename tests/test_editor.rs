@@ -0,0 +1,170 @@
+#![cfg(feature = "physics")]
+
+extern crate unrust;
+
+use unrust::engine::physics::{Collider, PhysicsWorld};
+use unrust::math::*;
+use unrust::world::editor::{snap_angle, BrushMode, BuildTool, PlacementCheck, PlacementValidity,
+                            Scatter, Snap, Symmetry, TerrainBrush};
+use unrust::world::{Command, UndoStack, World};
+
+fn ground() -> PhysicsWorld {
+    let mut physics = PhysicsWorld::new();
+    physics.add_collider(Collider::Plane {
+        normal: Vector3::unit_y(),
+        distance: 2.0,
+    });
+    physics
+}
+
+#[test]
+fn test_scatter_placements() {
+    let physics = ground();
+    let mut scatter = Scatter::new(10, 5.0).with_seed(42);
+    scatter.spacing = 0.5;
+
+    let placements = scatter.placements(&physics, Vector3::zero());
+    assert!(placements.len() > 0 && placements.len() <= 10);
+
+    for (i, p) in placements.iter().enumerate() {
+        assert!((p.disp.y - 2.0).abs() < 1e-4);
+        assert!(Vector2::new(p.disp.x, p.disp.z).magnitude() <= 5.0);
+
+        for q in placements[i + 1..].iter() {
+            assert!((p.disp - q.disp).magnitude() >= 0.5);
+        }
+    }
+
+    // Same seed, same placements
+    let again = scatter.placements(&physics, Vector3::zero());
+    assert_eq!(placements.len(), again.len());
+    assert_eq!(placements[0].disp, again[0].disp);
+}
+
+#[test]
+fn test_snap() {
+    let physics = ground();
+    let p = Vector3::new(1.3, 2.6, -0.4);
+
+    assert_eq!(Snap::Grid(0.5).snap(&physics, p), Vector3::new(1.5, 2.5, -0.5));
+    assert!((Snap::Surface(1.0).snap(&physics, p).y - 2.0).abs() < 1e-4);
+    assert_eq!(Snap::Surface(0.1).snap(&physics, p), p);
+}
+
+#[test]
+fn test_symmetry() {
+    let mut tool = BuildTool::new();
+    tool.snap = Some(Snap::Grid(1.0));
+    tool.angle_step = Some(Rad(::std::f32::consts::FRAC_PI_2));
+    tool.symmetry.push(Symmetry::Mirror {
+        point: Vector3::zero(),
+        normal: Vector3::unit_x(),
+    });
+    tool.symmetry.push(Symmetry::Mirror {
+        point: Vector3::zero(),
+        normal: Vector3::unit_z(),
+    });
+
+    let t = tool.placement(&ground(), Vector3::new(2.2, 2.0, 2.9), Rad(0.1));
+    assert_eq!(t.disp, Vector3::new(2.0, 2.0, 3.0));
+    assert_eq!(snap_angle(Rad(1.7), Rad(0.5)), Rad(1.5));
+
+    let all = tool.with_copies(t);
+    assert_eq!(all.len(), 4);
+    assert!(all.iter().any(|c| c.disp == Vector3::new(-2.0, 2.0, -3.0)));
+
+    // On the mirror plane, no copy
+    assert_eq!(tool.with_copies(tool.placement(&ground(), Vector3::zero(), Rad(0.0))).len(), 1);
+
+    let radial = Symmetry::Radial {
+        center: Vector3::zero(),
+        axis: Vector3::unit_y(),
+        count: 4,
+    };
+    let copies = radial.copies(&t);
+    assert_eq!(copies.len(), 3);
+    for c in copies.iter() {
+        assert!((c.disp.magnitude() - t.disp.magnitude()).abs() < 1e-4);
+    }
+}
+
+#[test]
+fn test_placement_check() {
+    let physics = ground();
+    let check = PlacementCheck::new(0.5);
+    let at = |y: f32| Decomposed {
+        scale: 1.0,
+        rot: Quaternion::one(),
+        disp: Vector3::new(0.0, y, 0.0),
+    };
+
+    assert_eq!(check.check(&physics, &at(2.0)), PlacementValidity::Valid);
+    assert_eq!(check.check(&physics, &at(1.5)), PlacementValidity::Overlapping);
+    assert_eq!(check.check(&physics, &at(5.0)), PlacementValidity::Floating);
+}
+
+#[test]
+fn test_brush_falloff() {
+    let brush = TerrainBrush::new(BrushMode::Raise, 4.0, 1.0).with_hardness(0.5);
+
+    assert_eq!(brush.falloff(1.0), 1.0);
+    assert!(brush.falloff(3.0) > 0.0 && brush.falloff(3.0) < 1.0);
+    assert_eq!(brush.falloff(4.0), 0.0);
+}
+
+struct Named(&'static str, usize);
+
+impl Command for Named {
+    fn name(&self) -> &str {
+        self.0
+    }
+
+    fn execute(&mut self, _world: &mut World) {}
+
+    fn undo(&mut self, _world: &mut World) {}
+
+    fn memory_size(&self) -> usize {
+        self.1
+    }
+}
+
+#[test]
+fn test_undo_stack() {
+    let mut stack = UndoStack::new();
+    stack.push(Box::new(Named("a", 0)));
+
+    stack.begin_group("stroke");
+    stack.push(Box::new(Named("b", 0)));
+    stack.begin_group("nested");
+    stack.push(Box::new(Named("c", 0)));
+    stack.end_group();
+    stack.end_group();
+    assert_eq!(stack.len(), 2);
+    assert_eq!(stack.undo_name(), Some("stroke"));
+
+    let cmd = stack.take_undo().unwrap();
+    stack.push_undone(cmd);
+    assert_eq!(stack.redo_name(), Some("stroke"));
+
+    let cmd = stack.take_redo().unwrap();
+    stack.push_redone(cmd);
+    assert!(!stack.can_redo());
+    assert_eq!(stack.len(), 2);
+
+    // Undone commands are lost by a new command
+    let cmd = stack.take_undo().unwrap();
+    stack.push_undone(cmd);
+    stack.push(Box::new(Named("d", 0)));
+    assert!(!stack.can_redo());
+
+    // Memory cap drops the oldest commands
+    stack.max_bytes = 100;
+    stack.push(Box::new(Named("e", 60)));
+    stack.push(Box::new(Named("f", 60)));
+    assert_eq!(stack.undo_name(), Some("f"));
+    assert!(stack.memory_size() <= 100);
+
+    stack.max_commands = 1;
+    stack.push(Box::new(Named("g", 0)));
+    assert_eq!(stack.len(), 1);
+}
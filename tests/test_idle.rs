@@ -0,0 +1,27 @@
+extern crate unrust;
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+use unrust::engine::asset::IdleQueue;
+
+#[test]
+fn test_idle_steps() {
+    let queue = IdleQueue::new();
+    let count = Rc::new(Cell::new(0));
+
+    let c = count.clone();
+    queue.push(move || {
+        c.set(c.get() + 1);
+        c.get() == 3
+    });
+
+    // At least one step, even past the deadline
+    assert_eq!(queue.run(0.0), 1);
+    assert_eq!(queue.len(), 1);
+
+    while !queue.is_empty() {
+        queue.run(0.0);
+    }
+    assert_eq!(count.get(), 3);
+}
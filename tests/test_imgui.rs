@@ -0,0 +1,38 @@
+#![cfg(feature = "ui")]
+
+extern crate unrust;
+
+use unrust::imgui::{self, ImguiScope, Theme};
+
+#[test]
+fn test_imgui_scopes() {
+    // Two worlds on one page
+    let a = ImguiScope::new();
+    let b = ImguiScope::new();
+
+    a.enter();
+    imgui::begin();
+    imgui::set_ui_scale(2.0);
+    imgui::set_theme(Theme::HighContrast);
+
+    b.enter();
+    imgui::begin();
+    assert_eq!(imgui::ui_scale(), 1.0);
+    assert_eq!(imgui::theme(), Theme::Default);
+    imgui::set_ui_scale(0.5);
+
+    // The global settings last from a frame to the next
+    a.enter();
+    imgui::begin();
+    assert_eq!(imgui::ui_scale(), 2.0);
+    assert_eq!(imgui::theme(), Theme::HighContrast);
+
+    b.enter();
+    assert_eq!(imgui::ui_scale(), 0.5);
+
+    // A new world starts from the defaults
+    drop(b);
+    let c = ImguiScope::new();
+    c.enter();
+    assert_eq!(imgui::ui_scale(), 1.0);
+}
@@ -0,0 +1,26 @@
+extern crate unrust;
+
+use unrust::actors::ImpostorAtlas;
+use unrust::math::*;
+
+#[test]
+fn test_impostor_views() {
+    let atlas = ImpostorAtlas::new(8, 3, 64).with_max_elevation(Deg(60.0));
+    assert!(!atlas.is_baked());
+
+    // The first view is from +z on the horizon, the azimuth turns toward +x
+    let d = atlas.view_direction(0, 0);
+    assert!((d - Vector3::unit_z()).magnitude() < 1e-5);
+
+    let d = atlas.view_direction(2, 0);
+    assert!((d - Vector3::unit_x()).magnitude() < 1e-5);
+
+    // The last row is at the highest elevation
+    let d = atlas.view_direction(0, 2);
+    assert!((d.y - Rad::from(Deg(60.0f32)).0.sin()).abs() < 1e-5);
+    assert!((d.magnitude() - 1.0).abs() < 1e-5);
+
+    // Never looking straight down
+    let atlas = ImpostorAtlas::new(8, 2, 64).with_max_elevation(Deg(90.0));
+    assert!(atlas.view_direction(0, 1).y < 1.0);
+}
@@ -0,0 +1,37 @@
+extern crate unrust;
+
+use unrust::engine::input::{ActionMap, Binding};
+use unrust::engine::input_glyphs::{GlyphSpan, GlyphStyle, InputGlyphs};
+
+#[test]
+fn test_glyph_style() {
+    let ps = "Wireless Controller (STANDARD GAMEPAD Vendor: 054c Product: 09cc)";
+    assert_eq!(GlyphStyle::from_gamepad_id(ps), GlyphStyle::PlayStation);
+    assert_eq!(
+        GlyphStyle::from_gamepad_id("Xbox 360 Controller (XInput STANDARD GAMEPAD)"),
+        GlyphStyle::Xbox
+    );
+}
+
+#[test]
+fn test_glyph_markup() {
+    let mut actions = ActionMap::new();
+    actions.bind("jump", Binding::Key("Space".to_owned()));
+    actions.bind("use", Binding::Key("KeyE".to_owned()));
+
+    let mut glyphs = InputGlyphs::new();
+    glyphs.set_icon(
+        GlyphStyle::Keyboard,
+        &Binding::Key("Space".to_owned()),
+        "kb_space.png",
+    );
+
+    assert_eq!(
+        glyphs.markup(&actions, "Press {jump} to jump, {use} to use {{"),
+        vec![
+            GlyphSpan::Text("Press ".to_owned()),
+            GlyphSpan::Icon("kb_space.png".to_owned()),
+            GlyphSpan::Text(" to jump, [KeyE] to use {".to_owned()),
+        ]
+    );
+}
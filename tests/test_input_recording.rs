@@ -0,0 +1,50 @@
+extern crate serde_json;
+extern crate uni_app;
+extern crate unrust;
+
+use std::collections::HashSet;
+
+use uni_app::{AppEvent, MouseButtonEvent};
+use unrust::engine::input_recording::{InputRecorder, InputRecording, RecordedEvent};
+
+#[test]
+fn test_record_playback() {
+    let mut recorder = InputRecorder::new();
+    let mut buttons = HashSet::new();
+
+    recorder.start_recording_at(10.0);
+    recorder.record(10.5, &[AppEvent::MouseDown(MouseButtonEvent { button: 0 })], &buttons);
+    recorder.record(10.6, &[], &buttons);
+    buttons.insert((0, 1));
+    recorder.record(11.0, &[], &buttons);
+    let recording = recorder.stop_recording_at(12.0);
+
+    // Frames without input are not stored
+    assert_eq!(recording.frames.len(), 2);
+    assert_eq!(recording.duration, 2.0);
+    assert_eq!(
+        recording.frames[1].events,
+        vec![RecordedEvent::GamepadButton {
+            gamepad: 0,
+            button: 1,
+            pressed: true,
+        }]
+    );
+
+    let json = recording.to_json();
+    let recording: InputRecording = serde_json::from_str(&json).unwrap();
+
+    recorder.play_at(recording, 100.0);
+    assert!(recorder.playback(100.1).is_empty());
+    assert_eq!(recorder.playback(100.5).len(), 1);
+    assert!(recorder.playback(101.0).is_empty());
+    assert!(recorder.buttons().contains(&(0, 1)));
+
+    // The mouse button still down is released at the end
+    match recorder.playback(102.0).as_slice() {
+        &[AppEvent::MouseUp(ref e)] => assert_eq!(e.button, 0),
+        _ => panic!("expected a mouse release"),
+    }
+    assert!(!recorder.is_playing());
+    assert!(recorder.buttons().is_empty());
+}
@@ -0,0 +1,55 @@
+extern crate unrust;
+
+use unrust::actors::FirstPersonCamera;
+use unrust::engine::inspect::{self, FieldKind, FieldValue, Inspect};
+use unrust::math::*;
+use unrust::world::Processor;
+
+#[test]
+fn test_inspect_fields() {
+    let camera = FirstPersonCamera::new();
+
+    let fields = camera.fields();
+    let names: Vec<_> = fields.iter().map(|f| f.name).collect();
+    assert_eq!(names, vec!["speed", "angle_speed", "eye", "eye_dir"]);
+    assert_eq!(fields[0].kind, FieldKind::Float);
+    assert_eq!(fields[0].range, Some((0.0, 100.0)));
+    assert_eq!(fields[2].kind, FieldKind::Vec3);
+    assert_eq!(fields[2].range, None);
+
+    assert_eq!(camera.type_name(), "FirstPersonCamera");
+    assert_eq!(camera.get("eye"), Some(FieldValue::Vec3([0.0, 0.0, -3.0])));
+    assert_eq!(camera.get("state"), None);
+}
+
+#[test]
+fn test_inspect_set() {
+    let mut camera = FirstPersonCamera::new();
+
+    assert!(camera.set("speed", &FieldValue::Float(20.0)));
+    assert_eq!(camera.speed, 20.0);
+
+    // Clamped to the range
+    assert!(camera.set("speed", &FieldValue::Float(500.0)));
+    assert_eq!(camera.speed, 100.0);
+
+    assert!(!camera.set("speed", &FieldValue::Bool(true)));
+    assert!(!camera.set("handlers", &FieldValue::Int(1)));
+}
+
+#[test]
+fn test_inspect_overrides() {
+    let base = FirstPersonCamera::new();
+    let mut instance = FirstPersonCamera::new();
+    instance.eye = Vector3::new(1.0, 2.0, 3.0);
+
+    let overrides = inspect::diff(&base, &instance);
+    assert_eq!(overrides.len(), 1);
+    assert!(overrides.contains_key("eye"));
+
+    let json = inspect::to_json(&instance);
+    let mut copy = FirstPersonCamera::new();
+    inspect::apply_json(&mut copy, &json).unwrap();
+    assert_eq!(copy.eye, instance.eye);
+    assert!(inspect::diff(&instance, &copy).is_empty());
+}
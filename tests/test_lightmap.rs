@@ -0,0 +1,103 @@
+extern crate unrust;
+
+use unrust::engine::lightmap::bake::{BakeLight, Lightmapper};
+use unrust::engine::lightmap::{generate_lightmap_uvs, AmbientCube, LightProbes};
+use unrust::engine::{CubeMesh, MeshData};
+use unrust::math::*;
+
+fn floor() -> MeshData {
+    MeshData {
+        vertices: vec![
+            -1.0, 0.0, -1.0, -1.0, 0.0, 1.0, 1.0, 0.0, 1.0, 1.0, 0.0, -1.0,
+        ],
+        normals: Some(vec![0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0]),
+        indices: vec![0, 1, 2, 0, 2, 3],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_lightmap_uvs() {
+    let mut data = CubeMesh::new();
+    assert!(generate_lightmap_uvs(&mut data, 64));
+
+    let uvs2 = data.uvs2.as_ref().unwrap();
+    assert_eq!(uvs2.len(), data.vertices.len() / 3 * 2);
+    assert!(uvs2.iter().all(|v| *v >= 0.0 && *v <= 1.0));
+
+    // The charts (triangles sharing vertices) do not overlap
+    let mut chart: Vec<usize> = (0..data.vertices.len() / 3).collect();
+    for t in data.indices.chunks(3) {
+        let c = chart[t[0] as usize];
+        for i in t.iter() {
+            let old = chart[*i as usize];
+            for k in chart.iter_mut() {
+                if *k == old {
+                    *k = c;
+                }
+            }
+        }
+    }
+
+    let mut rects: Vec<(usize, [f32; 4])> = Vec::new();
+    for (i, c) in chart.iter().enumerate() {
+        let (u, v) = (uvs2[i * 2], uvs2[i * 2 + 1]);
+        match rects.iter().position(|r| r.0 == *c) {
+            Some(k) => {
+                let r = rects[k].1;
+                rects[k].1 = [r[0].min(u), r[1].min(v), r[2].max(u), r[3].max(v)];
+            }
+            None => rects.push((*c, [u, v, u, v])),
+        }
+    }
+    let rects: Vec<[f32; 4]> = rects.into_iter().map(|r| r.1).collect();
+    assert_eq!(rects.len(), 6);
+
+    for (i, a) in rects.iter().enumerate() {
+        for b in rects[i + 1..].iter() {
+            let overlap = a[0] < b[2] && b[0] < a[2] && a[1] < b[3] && b[1] < a[3];
+            assert!(!overlap);
+        }
+    }
+}
+
+#[test]
+fn test_light_probes_sample() {
+    let mut probes = LightProbes::new(Vector3::zero(), vec3(2.0, 1.0, 1.0), [2, 1, 1]);
+    probes.probes[1] = AmbientCube {
+        colors: [[1.0, 1.0, 1.0]; 6],
+    };
+
+    let c = probes.sample(vec3(1.0, 0.0, 0.0)).sample(Vector3::unit_y());
+    assert!((c.x - 0.5).abs() < 1e-5);
+
+    let c = probes.sample(vec3(10.0, 0.0, 0.0)).sample(Vector3::unit_y());
+    assert!((c.x - 1.0).abs() < 1e-5);
+}
+
+#[test]
+fn test_bake_floor() {
+    let mut data = floor();
+    assert!(generate_lightmap_uvs(&mut data, 16));
+
+    let mut lightmapper = Lightmapper::new(16);
+    lightmapper.samples = 4;
+    lightmapper.sky = Vector3::zero();
+    lightmapper.add_mesh("floor", &data, Matrix4::identity());
+    lightmapper.add_light(BakeLight::Directional {
+        direction: vec3(0.0, -1.0, 0.0),
+        color: vec3(1.0, 1.0, 1.0),
+    });
+    lightmapper.build();
+
+    let lightmaps = lightmapper.bake();
+    assert_eq!(lightmaps.len(), 1);
+    assert_eq!(lightmaps[0].name, "floor");
+
+    // Somewhere in the charts, the floor receives the full light
+    let lit = lightmaps[0].image.pixels().any(|p| {
+        let light = p.data[1] as f32 / 255.0 * p.data[3] as f32 / 255.0 * 6.0;
+        (light - 1.0).abs() < 0.05
+    });
+    assert!(lit);
+}
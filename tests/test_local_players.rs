@@ -0,0 +1,32 @@
+extern crate unrust;
+
+use unrust::engine::input::InputDevice;
+use unrust::engine::local_players::{LocalPlayers, PlayerEvent};
+
+#[test]
+fn test_join_leave() {
+    let mut players = LocalPlayers::new();
+    players.max_players = 2;
+
+    assert_eq!(players.join(InputDevice::KeyboardMouse), Some(0));
+    assert_eq!(players.join(InputDevice::Gamepad(1)), Some(1));
+    // Claimed device, then full
+    assert_eq!(players.join(InputDevice::Gamepad(1)), None);
+    assert_eq!(players.join(InputDevice::Gamepad(2)), None);
+
+    // The slot of a player who left is reused, the others keep theirs
+    players.leave(0);
+    assert_eq!(players.player_of(InputDevice::Gamepad(1)), Some(1));
+    assert_eq!(players.join(InputDevice::Gamepad(2)), Some(0));
+
+    let events = players.poll_events();
+    assert_eq!(events.len(), 4);
+    assert_eq!(
+        events[2],
+        PlayerEvent::Left {
+            player: 0,
+            device: InputDevice::KeyboardMouse,
+        }
+    );
+    assert!(players.poll_events().is_empty());
+}
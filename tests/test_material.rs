@@ -0,0 +1,37 @@
+extern crate unrust;
+
+use unrust::engine::{Material, MaterialParam, Resource, ShaderFs, ShaderProgram, ShaderVs};
+use unrust::math::*;
+
+const VS: &str = "attribute vec3 aVertexPosition;
+void main(void) {
+    gl_Position = vec4(aVertexPosition, 1.0);
+}";
+
+const FS: &str = "void main(void) {
+    gl_FragColor = vec4(1.0);
+}";
+
+fn material() -> Material {
+    Material::new(ShaderProgram::new((
+        Resource::new(ShaderVs::new("test_vs.glsl", VS)),
+        Resource::new(ShaderFs::new("test_fs.glsl", FS)),
+    )))
+}
+
+#[test]
+fn test_material_cutout_dissolve() {
+    let material = material();
+    assert_eq!(material.get("uCutoff"), None);
+
+    material.set_cutout(0.5);
+    assert_eq!(material.get("uCutoff"), Some(MaterialParam::Float(0.5)));
+
+    material.set_dissolve(1.5, 0.1, vec3(1.0, 0.5, 0.0));
+    assert_eq!(material.get("uDissolve"), Some(MaterialParam::Float(1.0)));
+    assert_eq!(material.get("uDissolveEdge"), Some(MaterialParam::Float(0.1)));
+    assert_eq!(
+        material.get("uDissolveColor"),
+        Some(MaterialParam::Vec3(vec3(1.0, 0.5, 0.0)))
+    );
+}
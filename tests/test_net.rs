@@ -0,0 +1,331 @@
+#![cfg(feature = "net")]
+
+extern crate unrust;
+
+#[macro_use]
+extern crate serde_derive;
+
+use unrust::engine::net::{BitReader, BitWriter, Channel, DesyncChecker, InputQueue, LoopbackHub,
+                          NetEvent, NetTransform, Network, PredictedInput, RpcAuthority,
+                          RpcMessage, TransformQuantization, Transport, TransportEvent,
+                          WebRtcTransport};
+use unrust::math::*;
+
+use std::net::UdpSocket;
+use std::thread;
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Move {
+    x: i32,
+}
+
+impl RpcMessage for Move {
+    const NAME: &'static str = "move";
+    const AUTHORITY: RpcAuthority = RpcAuthority::Client;
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Ping {
+    n: u32,
+}
+
+impl RpcMessage for Ping {
+    const NAME: &'static str = "ping";
+    const CHANNEL: Channel = Channel::Unreliable;
+    const AUTHORITY: RpcAuthority = RpcAuthority::Server;
+}
+
+#[test]
+fn test_loopback_rpc() {
+    let hub = LoopbackHub::new();
+
+    let mut server = Network::new();
+    let server_transport = hub.connect();
+    let server_id = server_transport.local_id();
+    server.host(Box::new(server_transport));
+
+    let mut client = Network::new();
+    client.connect(Box::new(hub.connect()), server_id);
+    let client_id = client.local_id().unwrap();
+
+    server.register::<Move>();
+    client.register::<Ping>();
+
+    server.step();
+    client.step();
+    assert_eq!(server.poll_events(), vec![NetEvent::PeerConnected(client_id)]);
+
+    assert!(client.call(&Move { x: 3 }));
+    // Clients have no authority to send server messages
+    assert!(!client.call(&Ping { n: 1 }));
+
+    server.step();
+    let calls = server.receive::<Move>();
+    assert_eq!(calls.len(), 1);
+    assert_eq!(calls[0].sender, client_id);
+    assert_eq!(calls[0].msg, Move { x: 3 });
+
+    assert!(server.call(&Ping { n: 7 }));
+    client.step();
+    let pings = client.receive::<Ping>();
+    assert_eq!(pings.len(), 1);
+    assert_eq!(pings[0].msg, Ping { n: 7 });
+}
+
+#[test]
+fn test_rpc_before_register() {
+    let hub = LoopbackHub::new();
+
+    let mut server = Network::new();
+    let server_transport = hub.connect();
+    let server_id = server_transport.local_id();
+    server.host(Box::new(server_transport));
+
+    let mut client = Network::new();
+    client.connect(Box::new(hub.connect()), server_id);
+    server.step();
+    client.step();
+
+    // Received before the server registers the message type
+    assert!(client.call(&Move { x: 1 }));
+    assert!(client.call(&Move { x: 2 }));
+    server.step();
+
+    server.register::<Move>();
+    let calls = server.receive::<Move>();
+    let xs: Vec<i32> = calls.iter().map(|c| c.msg.x).collect();
+    assert_eq!(xs, vec![1, 2]);
+}
+
+#[test]
+fn test_rpc_unregistered_names_capped() {
+    let hub = LoopbackHub::new();
+
+    let mut server = Network::new();
+    let server_transport = hub.connect();
+    let server_id = server_transport.local_id();
+    server.host(Box::new(server_transport));
+
+    let mut client = Network::new();
+    client.connect(Box::new(hub.connect()), server_id);
+    server.step();
+    client.step();
+
+    assert!(client.call(&Move { x: 1 }));
+
+    // Made-up message names push the oldest unregistered ones out
+    for i in 0..100 {
+        let data = format!(r#"{{"m":"made_up_{}","p":null}}"#, i);
+        let transport = client.transport_mut().unwrap();
+        transport.send(server_id, Channel::ReliableOrdered, data.as_bytes());
+    }
+    server.step();
+
+    server.register::<Move>();
+    assert!(server.receive::<Move>().is_empty());
+}
+
+#[test]
+fn test_loopback_latency_and_loss() {
+    let hub = LoopbackHub::new();
+    hub.set_latency(2);
+    hub.set_drop_every(2);
+
+    let mut a = hub.connect();
+    let mut b = hub.connect();
+    a.poll();
+    b.poll();
+
+    for i in 0..4u8 {
+        a.send(b.local_id(), Channel::Unreliable, &[i]);
+    }
+
+    assert_eq!(b.poll().len(), 0);
+    assert_eq!(b.poll().len(), 0);
+    // Every second message is lost
+    assert_eq!(b.poll().len(), 2);
+
+    a.disconnect(b.local_id());
+    assert_eq!(a.peers().len(), 0);
+    assert_eq!(b.peers().len(), 0);
+}
+
+/// Poll the transports until `b` has received `n` events
+fn poll_udp(a: &mut WebRtcTransport, b: &mut WebRtcTransport, n: usize) -> Vec<TransportEvent> {
+    let mut events = Vec::new();
+    for _ in 0..200 {
+        a.poll();
+        events.extend(b.poll());
+        if events.len() >= n {
+            break;
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+    events
+}
+
+#[test]
+fn test_udp_transport() {
+    let mut a = WebRtcTransport::new(1);
+    let mut b = WebRtcTransport::new(2);
+
+    a.connect(2);
+    for (_, data) in a.take_signals() {
+        b.handle_signal(1, data);
+    }
+    for (_, data) in b.take_signals() {
+        a.handle_signal(2, data);
+    }
+
+    assert_eq!(poll_udp(&mut a, &mut b, 1), vec![TransportEvent::Connected(1)]);
+    assert_eq!(poll_udp(&mut b, &mut a, 1), vec![TransportEvent::Connected(2)]);
+
+    for i in 0..3u8 {
+        a.send(2, Channel::ReliableOrdered, &[i]);
+    }
+    let events = poll_udp(&mut a, &mut b, 3);
+    assert_eq!(
+        events,
+        (0..3u8)
+            .map(|i| TransportEvent::Message(1, Channel::ReliableOrdered, vec![i]))
+            .collect::<Vec<_>>()
+    );
+
+    // An address which was not signaled, whatever it claims to be
+    let intruder = UdpSocket::bind("0.0.0.0:0").unwrap();
+    let mut signal = None;
+    b.connect(1);
+    for (_, data) in b.take_signals() {
+        signal = Some(data);
+    }
+    let addr = signal.unwrap()["addr"].as_str().unwrap().to_string();
+    for seq in 3..6u8 {
+        let packet = [1, seq, 0, 0, 0, 1, 0, 0, 0, 42];
+        intruder.send_to(&packet, &addr).unwrap();
+    }
+    a.send(2, Channel::Unreliable, &[7]);
+
+    let events = poll_udp(&mut a, &mut b, 1);
+    assert_eq!(
+        events,
+        vec![TransportEvent::Message(1, Channel::Unreliable, vec![7])]
+    );
+}
+
+#[test]
+fn test_transform_quantization() {
+    let quant = TransformQuantization::default();
+    let rot = Quaternion::from_axis_angle(Vector3::new(1.0, 2.0, 3.0).normalize(), Rad(2.0));
+    let t = NetTransform::new(Vector3::new(12.345, -0.5, 900.0), rot);
+
+    let mut w = BitWriter::new();
+    quant.write(&mut w, &t);
+    assert_eq!(w.bit_len(), 21 * 3 + 2 + 15 * 3);
+
+    let data = w.into_bytes();
+    let decoded = quant.read(&mut BitReader::new(&data)).unwrap();
+    assert!((decoded.position - t.position).magnitude() < 0.002);
+    assert!(decoded.rotation.dot(t.rotation).abs() > 0.9999);
+
+    // Only the changed axis is sent
+    let mut moved = t;
+    moved.position.y += 1.0;
+
+    let mut w = BitWriter::new();
+    quant.write_delta(&mut w, &moved, &t);
+    assert_eq!(w.bit_len(), 4 + 21);
+
+    let data = w.into_bytes();
+    let decoded = quant
+        .read_delta(&mut BitReader::new(&data), &t)
+        .unwrap();
+    assert!((decoded.position - moved.position).magnitude() < 0.002);
+}
+
+fn run_simulation(checker: &mut DesyncChecker, diverge_at: Option<u64>) {
+    let mut pos = 0.0f32;
+    let mut score = 0u32;
+
+    for tick in 0..10 {
+        pos += 0.5;
+        if Some(tick) == diverge_at {
+            score += 1;
+        }
+
+        checker.begin_tick(tick);
+        checker.hash_floats("position", &[pos]);
+        checker.hash("score", &score);
+        checker.end_tick();
+    }
+}
+
+#[test]
+fn test_desync_checker() {
+    let mut first = DesyncChecker::new();
+    first.start_recording();
+    run_simulation(&mut first, None);
+    let recording = first.take_recording().unwrap();
+    assert_eq!(recording.ticks.len(), 10);
+
+    let mut same = DesyncChecker::new();
+    same.compare_with(recording.clone());
+    run_simulation(&mut same, None);
+    assert!(same.first_desync().is_none());
+
+    let mut diverged = DesyncChecker::new();
+    diverged.compare_with(recording);
+    run_simulation(&mut diverged, Some(6));
+
+    let desync = diverged.first_desync().unwrap();
+    assert_eq!(desync.tick, 6);
+    assert_eq!(desync.section, "score");
+    assert_eq!(desync.peer, None);
+
+    // The ticks after the divergence are not reported again
+    assert_eq!(diverged.poll_desyncs().len(), 1);
+}
+
+#[test]
+fn test_input_queue_wraps() {
+    let input = |seq| PredictedInput {
+        seq,
+        dt: 0.1,
+        input: (),
+    };
+
+    let mut queue = InputQueue::default();
+    for seq in [1, u32::max_value() - 1, 0, u32::max_value()].iter() {
+        queue.push(input(*seq));
+    }
+
+    let order: Vec<_> = (0..4).filter_map(|_| queue.pop()).map(|i| i.seq).collect();
+    assert_eq!(order, vec![u32::max_value() - 1, u32::max_value(), 0, 1]);
+    assert_eq!(queue.last_processed(), Some(1));
+
+    // Already processed, from before the wrap around
+    queue.push(input(u32::max_value()));
+    queue.push(input(1));
+    assert!(queue.pop().is_none());
+}
+
+#[test]
+fn test_input_queue_capped() {
+    let input = |seq| PredictedInput {
+        seq,
+        dt: 0.1,
+        input: (),
+    };
+
+    let mut queue = InputQueue::new();
+    queue.max_pending = 4;
+
+    // A far future input is the farthest, dropped first
+    queue.push(input(1_000_000));
+    for seq in 1..10 {
+        queue.push(input(seq));
+    }
+
+    let order: Vec<_> = (0..10).filter_map(|_| queue.pop()).map(|i| i.seq).collect();
+    assert_eq!(order, vec![1, 2, 3, 4]);
+}
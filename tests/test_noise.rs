@@ -0,0 +1,63 @@
+extern crate unrust;
+
+use unrust::engine::noise::{Fbm, Heightmap, Noise, NoiseKind};
+use unrust::engine::IdleQueue;
+
+#[test]
+fn test_noise_seeded() {
+    let (a, b, c) = (Noise::new(7), Noise::new(7), Noise::new(8));
+
+    let points = [(0.3, 1.7), (5.2, 3.9), (12.5, 0.1)];
+    for &(x, y) in points.iter() {
+        assert_eq!(a.simplex2(x, y), b.simplex2(x, y));
+        assert!(a.perlin2(x, y).abs() <= 1.0);
+        assert!(a.worley2(x, y) >= 0.0);
+    }
+
+    let differs = points
+        .iter()
+        .any(|&(x, y)| a.perlin3(x, y, 0.5) != c.perlin3(x, y, 0.5));
+    assert!(differs);
+
+    // Perlin noise is 0 on the lattice
+    assert_eq!(a.perlin2(3.0, 4.0), 0.0);
+}
+
+#[test]
+fn test_noise_tiled() {
+    let noise = Noise::new(3);
+    let fbm = Fbm::new(3, NoiseKind::Simplex).with_octaves(3);
+
+    for &(x, y) in [(0.25, 0.5), (1.75, 2.5)].iter() {
+        for kind in [NoiseKind::Perlin, NoiseKind::Simplex, NoiseKind::Worley].iter() {
+            let v = noise.sample2_tiled(*kind, x, y, 4);
+            assert!((v - noise.sample2_tiled(*kind, x + 4.0, y - 8.0, 4)).abs() < 1e-4);
+        }
+
+        let v = fbm.sample2_tiled(x, y, 2.0);
+        assert!((v - fbm.sample2_tiled(x + 2.0, y + 2.0, 2.0)).abs() < 1e-4);
+    }
+}
+
+#[test]
+fn test_heightmap_bake() {
+    let fbm = Fbm::new(11, NoiseKind::Perlin).with_frequency(0.1);
+    let f = move |x: usize, y: usize| fbm.sample2(x as f32, y as f32);
+
+    let mut hm = Heightmap::from_fn(64, 64, f.clone());
+    let idle = IdleQueue::new();
+    let bake = Heightmap::bake_idle(&idle, 64, 64, f);
+    assert!(!bake.is_done());
+    assert!(bake.heightmap().is_none());
+
+    while !idle.is_empty() {
+        idle.run(0.0);
+    }
+    assert_eq!(bake.progress(), 1.0);
+    assert_eq!(bake.heightmap(), Some(hm.clone()));
+
+    hm.normalize();
+    let (lo, hi) = hm.range();
+    assert_eq!(lo, 0.0);
+    assert!((hi - 1.0).abs() < 1e-5);
+}
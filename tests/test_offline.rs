@@ -0,0 +1,39 @@
+extern crate serde_json;
+extern crate unrust;
+
+use unrust::engine::asset::cache::CacheManifest;
+use unrust::engine::asset::offline::{OfflineManifest, CACHE_MANIFEST, OFFLINE_MANIFEST,
+                                     SERVICE_WORKER};
+
+use std::env;
+use std::fs;
+
+#[test]
+fn test_generate_offline_manifest() {
+    let dir = env::temp_dir().join("unrust_test_offline");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("textures")).unwrap();
+    fs::write(dir.join("game.wasm"), b"wasm").unwrap();
+    fs::write(dir.join("textures/rock.png"), b"rock").unwrap();
+
+    let manifest = OfflineManifest::generate(&dir, 3).unwrap();
+    let urls: Vec<_> = manifest.files.iter().map(|f| f.url.as_str()).collect();
+    assert_eq!(urls, vec!["game.wasm", "textures/rock.png"]);
+    assert_eq!(manifest.total_size(), 8);
+    assert!(dir.join(SERVICE_WORKER).is_file());
+
+    // Generating again does not list the generated files
+    let manifest = OfflineManifest::generate(&dir, 4).unwrap();
+    assert_eq!(manifest.files.len(), 2);
+
+    let json = fs::read_to_string(dir.join(OFFLINE_MANIFEST)).unwrap();
+    let read: OfflineManifest = serde_json::from_str(&json).unwrap();
+    assert_eq!(read, manifest);
+
+    let json = fs::read_to_string(dir.join(CACHE_MANIFEST)).unwrap();
+    let cache: CacheManifest = serde_json::from_str(&json).unwrap();
+    assert_eq!(cache.version, 4);
+    assert_eq!(cache.files["textures/rock.png"], manifest.files[1].hash);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,257 @@
+#![cfg(feature = "physics")]
+
+extern crate unrust;
+
+use unrust::engine::physics::{Collider, HeightField, Interpolation, Joint, PhysicsWorld,
+                              RigidBody, SplashKind, TriMesh, WaterVolume};
+use unrust::engine::time::Time;
+use unrust::math::*;
+
+fn slope() -> HeightField {
+    // 4x4 samples, rising by 1.0 per cell along x
+    let heights = (0..16).map(|i| (i % 4) as f32).collect();
+    HeightField::new(4, 4, 1.0, heights).unwrap()
+}
+
+#[test]
+fn test_height_field() {
+    let hf = slope();
+
+    assert_eq!(hf.height_at(0.0, 0.0), Some(0.0));
+
+    // A single row has no cell
+    assert!(HeightField::new(4, 1, 1.0, vec![0.0; 4]).is_none());
+    assert!(HeightField::new(4, 4, 1.0, vec![0.0; 15]).is_none());
+    assert!((hf.height_at(1.5, 2.5).unwrap() - 1.5).abs() < 1e-5);
+    assert_eq!(hf.height_at(-1.0, 0.0), None);
+
+    let hit = hf.raycast(Vector3::new(2.0, 10.0, 1.0), -Vector3::unit_y(), 20.0)
+        .unwrap();
+    assert!((hit.point.y - 2.0).abs() < 1e-3);
+    assert!(hit.normal.x < 0.0 && hit.normal.y > 0.0);
+}
+
+#[test]
+fn test_tri_mesh_raycast() {
+    // A 2x2 floor at y = 1.0
+    let vertices = vec![
+        Vector3::new(-1.0, 1.0, -1.0),
+        Vector3::new(1.0, 1.0, -1.0),
+        Vector3::new(1.0, 1.0, 1.0),
+        Vector3::new(-1.0, 1.0, 1.0),
+    ];
+    let mesh = TriMesh::new(vertices, vec![[0, 2, 1], [0, 3, 2]]);
+
+    let hit = mesh.raycast(Vector3::new(0.5, 5.0, 0.2), -Vector3::unit_y(), 10.0)
+        .unwrap();
+    assert!((hit.distance - 4.0).abs() < 1e-5);
+    assert_eq!(hit.normal, Vector3::unit_y());
+
+    assert!(mesh.raycast(Vector3::new(3.0, 5.0, 0.0), -Vector3::unit_y(), 10.0)
+        .is_none());
+}
+
+#[test]
+fn test_body_rests_on_terrain() {
+    let terrain = HeightField::new(4, 4, 1.0, vec![1.0; 16]).unwrap();
+    let mut physics = PhysicsWorld::new();
+    physics.add_collider(Collider::HeightField(terrain));
+    let body = physics.add_body(RigidBody::new(Vector3::new(1.5, 5.0, 1.5), 0.25, 1.0));
+
+    for _ in 0..300 {
+        physics.step(1.0 / 60.0);
+    }
+
+    let body = physics.body(body).unwrap();
+    assert!((body.position.y - 1.25).abs() < 0.05);
+    assert!(body.is_grounded());
+}
+
+#[test]
+fn test_substeps_prevent_tunneling() {
+    let floor = TriMesh::new(
+        vec![
+            Vector3::new(-10.0, 0.0, -10.0),
+            Vector3::new(10.0, 0.0, -10.0),
+            Vector3::new(10.0, 0.0, 10.0),
+            Vector3::new(-10.0, 0.0, 10.0),
+        ],
+        vec![[0, 2, 1], [0, 3, 2]],
+    );
+
+    let fall = |substeps| {
+        let mut physics = PhysicsWorld::new();
+        physics.substeps = substeps;
+        physics.add_collider(Collider::TriMesh(floor.clone()));
+
+        let mut body = RigidBody::new(Vector3::new(0.0, 0.5, 0.0), 0.1, 1.0);
+        body.velocity = Vector3::new(0.0, -60.0, 0.0);
+        let body = physics.add_body(body);
+
+        for _ in 0..10 {
+            physics.tick(1.0 / 60.0);
+        }
+        physics.body(body).unwrap().position.y
+    };
+
+    assert!(fall(1) < 0.0);
+    assert!(fall(20) > 0.0);
+}
+
+#[test]
+fn test_interpolation() {
+    let mut physics = PhysicsWorld::new();
+    physics.gravity = Vector3::zero();
+
+    let mut body = RigidBody::new(Vector3::zero(), 0.5, 1.0);
+    body.velocity = Vector3::new(60.0, 0.0, 0.0);
+    let body = physics.add_body(body);
+
+    // A tick and a half
+    physics.step(1.5 / 60.0);
+    assert!((physics.alpha() - 0.5).abs() < 1e-3);
+
+    physics.interpolation = Interpolation::None;
+    assert!((physics.render_position(body).unwrap().x - 1.0).abs() < 1e-3);
+
+    physics.interpolation = Interpolation::Interpolate;
+    assert!((physics.render_position(body).unwrap().x - 0.5).abs() < 1e-3);
+
+    physics.interpolation = Interpolation::Extrapolate;
+    assert!((physics.render_position(body).unwrap().x - 1.5).abs() < 1e-3);
+}
+
+#[test]
+fn test_buoyancy_and_splash() {
+    let mut physics = PhysicsWorld::new();
+    let water = physics.add_water(WaterVolume::new(
+        Vector3::zero(),
+        Vector2::new(10.0, 10.0),
+        5.0,
+    ));
+
+    // Half the density of water, floats half submerged
+    let radius = 0.5;
+    let mass = 500.0 * 4.0 / 3.0 * ::std::f32::consts::PI * radius * radius * radius;
+    let body = physics.add_body(RigidBody::new(Vector3::new(0.0, 2.0, 0.0), radius, mass));
+
+    physics.step(1.0 / 60.0);
+    assert_eq!(physics.splashes().len(), 0);
+
+    let mut entered = false;
+    for _ in 0..600 {
+        physics.step(1.0 / 60.0);
+        if let Some(splash) = physics.splashes().first() {
+            assert_eq!(splash.kind, SplashKind::Enter);
+            assert_eq!(splash.water, water);
+            entered = true;
+        }
+    }
+
+    assert!(entered);
+    let body = physics.body(body).unwrap();
+    assert_eq!(body.water(), Some(water));
+    assert!(body.position.y.abs() < 0.1);
+}
+
+#[test]
+fn test_owned_water() {
+    let mut physics = PhysicsWorld::new();
+    let guard = physics.add_owned_water(WaterVolume::new(
+        Vector3::zero(),
+        Vector2::new(10.0, 10.0),
+        5.0,
+    ));
+    let water = guard.handle();
+
+    physics.step(1.0 / 60.0);
+    assert!(physics.water(water).is_some());
+
+    drop(guard);
+    assert!(physics.water(water).is_some());
+    physics.step(1.0 / 60.0);
+    assert!(physics.water(water).is_none());
+    assert_eq!(physics.water_height(0.0, 0.0), None);
+}
+
+#[test]
+fn test_time_scale() {
+    let mut physics = PhysicsWorld::new();
+    physics.gravity = Vector3::zero();
+    physics.time_scale = 0.25;
+
+    let mut slow = RigidBody::new(Vector3::zero(), 0.5, 1.0);
+    slow.velocity = Vector3::new(1.0, 0.0, 0.0);
+    let mut fast = slow.clone();
+    fast.position = Vector3::new(0.0, 0.0, 10.0);
+    fast.time_scale = Some(1.0);
+
+    let slow = physics.add_body(slow);
+    let fast = physics.add_body(fast);
+
+    for _ in 0..60 {
+        physics.step(1.0 / 60.0);
+    }
+
+    assert!((physics.body(slow).unwrap().position.x - 0.25).abs() < 1e-3);
+    assert!((physics.body(fast).unwrap().position.x - 1.0).abs() < 1e-3);
+    assert!((physics.time() - 0.25).abs() < 1e-3);
+
+    let mut time = Time::new();
+    time.pitch_audio = true;
+    time.ramp_to(0.5, 1.0);
+    time.step(0.5);
+    assert!((time.scale() - 0.75).abs() < 1e-5);
+    assert!((time.delta_time() - 0.375).abs() < 1e-5);
+    time.step(1.0);
+    assert_eq!(time.scale(), 0.5);
+    assert_eq!(time.pitch(), 0.5);
+}
+
+#[test]
+fn test_joints() {
+    let mut physics = PhysicsWorld::new();
+    physics.solver_iterations = 4;
+
+    // A chain hanging from a kinematic anchor
+    let anchor = physics.add_body(RigidBody::new(Vector3::new(0.0, 2.0, 0.0), 0.1, 0.0));
+    let mut middle = RigidBody::new(Vector3::new(1.0, 2.0, 0.0), 0.1, 1.0);
+    middle.collision_group = 1;
+    let mut end = RigidBody::new(Vector3::new(2.0, 2.0, 0.0), 0.1, 1.0);
+    end.collision_group = 1;
+    let (middle, end) = (physics.add_body(middle), physics.add_body(end));
+
+    physics.add_joint(Joint::Distance {
+        a: anchor,
+        b: middle,
+        length: 1.0,
+        stiffness: 1.0,
+    });
+    physics.add_joint(Joint::Distance {
+        a: middle,
+        b: end,
+        length: 1.0,
+        stiffness: 1.0,
+    });
+    physics.add_joint(Joint::Cone {
+        a: anchor,
+        center: middle,
+        b: end,
+        max_angle: Deg(30.0).into(),
+    });
+
+    for _ in 0..120 {
+        physics.step(1.0 / 60.0);
+    }
+
+    let p0 = physics.body(anchor).unwrap().position;
+    let p1 = physics.body(middle).unwrap().position;
+    let p2 = physics.body(end).unwrap().position;
+
+    assert!(((p1 - p0).magnitude() - 1.0).abs() < 0.05);
+    assert!(((p2 - p1).magnitude() - 1.0).abs() < 0.05);
+    assert!(p1.y < 2.0);
+
+    let bend = (p1 - p0).normalize().dot((p2 - p1).normalize()).acos();
+    assert!(bend < Rad::from(Deg(35.0)).0);
+}
@@ -0,0 +1,67 @@
+extern crate unrust;
+
+use unrust::engine::procgen::{group_by_chunk, Caves, RoomsAndCorridors, Tile, TilePrefabs, Wfc};
+
+#[test]
+fn test_rooms_connected() {
+    let generator = RoomsAndCorridors::new(64, 48).with_seed(7);
+    let (map, rooms) = generator.generate();
+    assert!(rooms.len() > 1);
+
+    // Same seed, same level
+    assert_eq!(generator.generate().0, map);
+
+    // Every room is reachable from the first one
+    let reachable = map.flood_fill(rooms[0].center());
+    for room in rooms.iter() {
+        assert!(reachable.contains(&room.center()));
+    }
+    assert_eq!(map.walkable_regions().len(), 1);
+
+    let mut prefabs = TilePrefabs::new();
+    prefabs.insert(Tile::Wall, "wall.prefab".to_owned());
+    let placements = map.placements(2.0, &prefabs);
+    assert_eq!(placements.len(), map.count(Tile::Wall));
+
+    let chunks = group_by_chunk(&placements, 16);
+    let total: usize = chunks.values().map(|c| c.len()).sum();
+    assert_eq!(total, placements.len());
+}
+
+#[test]
+fn test_caves_single_region() {
+    let map = Caves::new(48, 48).with_seed(3).generate();
+    assert_eq!(map.walkable_regions().len(), 1);
+    assert_eq!(map.get(0, 0), Some(Tile::Wall));
+}
+
+#[test]
+fn test_wfc_edges_match() {
+    // Grass (0) or road (1) sockets, a tile for each combination of edges
+    let mut wfc = Wfc::new(12, 12).with_seed(5);
+    for i in 0..16u32 {
+        let edges = [i & 1, (i >> 1) & 1, (i >> 2) & 1, (i >> 3) & 1];
+        let weight = if i == 0 { 8.0 } else { 1.0 };
+        wfc.add_tile(Tile::Custom(i as u16), edges, weight);
+    }
+    wfc.fix(0, 0, 15);
+
+    let map = wfc.solve().unwrap();
+    assert_eq!(map.get(0, 0), Some(Tile::Custom(15)));
+
+    let edges = |t: Tile| match t {
+        Tile::Custom(i) => wfc.tiles()[i as usize].edges,
+        _ => panic!("unexpected tile"),
+    };
+    for y in 0..12 {
+        for x in 0..12 {
+            let a = edges(map.get(x, y).unwrap());
+            if x + 1 < 12 {
+                assert_eq!(a[1], edges(map.get(x + 1, y).unwrap())[3]);
+            }
+            if y + 1 < 12 {
+                assert_eq!(a[2], edges(map.get(x, y + 1).unwrap())[0]);
+            }
+        }
+    }
+}
@@ -0,0 +1,79 @@
+extern crate serde_json;
+extern crate unrust;
+
+use unrust::engine::quest::{QuestDatabase, QuestEvent, QuestLog, QuestStatus};
+
+fn quests() -> QuestDatabase {
+    serde_json::from_str(
+        r#"{ "quests": [
+            { "id": "wolves", "title": "quest.wolves", "objectives": [
+                { "id": "kill_wolf", "text": "quest.wolves.kill", "target": 5 },
+                { "id": "pet_dog", "text": "quest.wolves.dog", "optional": true }
+            ]},
+            { "id": "report", "title": "quest.report", "prerequisites": ["wolves"],
+              "auto_start": true, "objectives": [
+                { "id": "count_coins", "text": "quest.report.coins", "target": 4294967295 }
+            ]}
+        ]}"#,
+    ).unwrap()
+}
+
+#[test]
+fn test_quest_progress() {
+    let mut log = QuestLog::new();
+    log.add_quests(quests());
+
+    assert_eq!(log.status("wolves"), Some(QuestStatus::Available));
+    assert_eq!(log.status("report"), Some(QuestStatus::Locked));
+
+    // Not started, no progress
+    log.notify("kill_wolf", 1);
+    assert_eq!(log.objective_progress("wolves", "kill_wolf"), Some((0, 5)));
+
+    assert!(log.start("wolves"));
+    assert!(!log.start("wolves"));
+    log.notify("kill_wolf", 3);
+    log.progress("wolves", "kill_wolf", 10);
+    assert_eq!(log.objective_progress("wolves", "kill_wolf"), Some((5, 5)));
+
+    // The optional objective is not needed, the next quest starts by itself
+    assert!(log.is_completed("wolves"));
+    assert!(log.is_active("report"));
+
+    let events = log.poll_events();
+    assert!(events.contains(&QuestEvent::Completed("wolves".to_owned())));
+    assert!(events.contains(&QuestEvent::Started("report".to_owned())));
+}
+
+#[test]
+fn test_quest_progress_saturates() {
+    let mut log = QuestLog::new();
+    log.add_quests(quests());
+    log.start("wolves");
+    log.notify("kill_wolf", 5);
+
+    log.progress("report", "count_coins", 10);
+    log.progress("report", "count_coins", u32::max_value());
+    assert_eq!(
+        log.objective_progress("report", "count_coins"),
+        Some((u32::max_value(), u32::max_value()))
+    );
+    assert!(log.is_completed("report"));
+}
+
+#[test]
+fn test_quest_save_state() {
+    let mut log = QuestLog::new();
+    log.add_quests(quests());
+    log.start("wolves");
+    log.notify("kill_wolf", 2);
+    let state = log.save_state();
+
+    let mut loaded = QuestLog::new();
+    loaded.add_quests(quests());
+    loaded.load_state(&state);
+
+    assert!(loaded.is_active("wolves"));
+    assert_eq!(loaded.objective_progress("wolves", "kill_wolf"), Some((2, 5)));
+    assert!(loaded.poll_events().is_empty());
+}
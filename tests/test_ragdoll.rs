@@ -0,0 +1,104 @@
+extern crate unrust;
+
+use unrust::engine::animation::{blend_pose, Bone, RagdollBuilder, Skeleton};
+use unrust::engine::physics::PhysicsWorld;
+use unrust::engine::{ComponentArena, SceneTree};
+use unrust::math::*;
+
+use std::rc::Rc;
+
+fn at(position: Vector3<f32>) -> Isometry3<f32> {
+    Isometry3 {
+        disp: position,
+        rot: Quaternion::one(),
+        scale: 1.0,
+    }
+}
+
+/// A chain of bones at `heights`, each the parent of the next
+fn chain(tree: &Rc<SceneTree>, arena: &Rc<ComponentArena>, heights: &[f32]) -> Skeleton {
+    let bones = heights
+        .iter()
+        .enumerate()
+        .map(|(i, height)| {
+            let object = tree.new_node(&tree.root(), arena);
+            let pose = at(Vector3::new(0.0, *height, 0.0));
+            object.borrow_mut().transform.set_global(pose);
+
+            Bone {
+                name: format!("bone{}", i),
+                object,
+                parent: if i > 0 { Some(i - 1) } else { None },
+                rest: pose,
+                rest_scale: Vector3::new(1.0, 1.0, 1.0),
+                rest_model: pose,
+            }
+        })
+        .collect();
+
+    Skeleton { bones }
+}
+
+#[test]
+fn test_ragdoll_segments() {
+    let tree = SceneTree::new();
+    let arena = Rc::new(ComponentArena::new());
+    let skeleton = chain(&tree, &arena, &[1.0, 1.45, 1.7]);
+
+    let mut builder = RagdollBuilder::default();
+    builder.radius = 0.1;
+    assert_eq!(builder.segment_spheres(0.45), 4);
+    assert_eq!(builder.segment_spheres(0.25), 2);
+    assert_eq!(builder.segment_spheres(0.05), 0);
+
+    let mut physics = PhysicsWorld::new();
+    let bodies = builder.build(&skeleton, Quaternion::one(), &mut physics, 7);
+
+    assert_eq!(bodies.segments.len(), 6);
+    assert_eq!(physics.body_count(), 9);
+    // A distance joint per segment and two per filling sphere, a cone joint at the middle bone
+    assert_eq!(bodies.joints.len(), 2 + 12 + 1);
+
+    let mut mass = 0.0;
+    for handle in bodies.bodies.iter().filter_map(|b| *b).chain(bodies.segments.clone()) {
+        let body = physics.body(handle).unwrap();
+        assert!(body.position.x.abs() < 1e-5 && body.position.z.abs() < 1e-5);
+        assert!(body.position.y >= 1.0 - 1e-5 && body.position.y <= 1.7 + 1e-5);
+        assert_eq!(body.collision_group, 7);
+        mass += body.mass;
+    }
+    assert!((mass - builder.mass).abs() < 1e-3);
+
+    // The filling spheres are spread evenly along the first segment
+    let first = physics.body(bodies.segments[0]).unwrap().position;
+    assert!((first.y - 1.09).abs() < 1e-5);
+
+    bodies.remove(&mut physics);
+    assert_eq!(physics.body_count(), 0);
+}
+
+#[test]
+fn test_ragdoll_blend_pose() {
+    let tree = SceneTree::new();
+    let arena = Rc::new(ComponentArena::new());
+    let skeleton = chain(&tree, &arena, &[0.0, 1.0]);
+
+    let target = at(Vector3::new(2.0, 0.0, 0.0));
+    let pose = vec![Some(target), None];
+
+    blend_pose(&skeleton, &pose, 0.0);
+    let hips = skeleton.bones[0].object.borrow().transform.global();
+    assert!(hips.disp.magnitude() < 1e-5);
+
+    blend_pose(&skeleton, &pose, 0.5);
+    let hips = skeleton.bones[0].object.borrow().transform.global();
+    assert!((hips.disp - Vector3::new(1.0, 0.0, 0.0)).magnitude() < 1e-5);
+
+    // A bone without a target keeps its transform
+    let head = skeleton.bones[1].object.borrow().transform.global();
+    assert!((head.disp - Vector3::new(0.0, 1.0, 0.0)).magnitude() < 1e-5);
+
+    blend_pose(&skeleton, &pose, 1.0);
+    let hips = skeleton.bones[0].object.borrow().transform.global();
+    assert!((hips.disp - target.disp).magnitude() < 1e-5);
+}
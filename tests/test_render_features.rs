@@ -0,0 +1,67 @@
+extern crate unrust;
+
+use unrust::engine::Camera;
+use unrust::world::{AppEngine, RenderFeature, RenderFeatures, RenderStage};
+
+struct Feature {
+    name: &'static str,
+    stage: RenderStage,
+    dependencies: Vec<String>,
+}
+
+impl RenderFeature for Feature {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn stage(&self) -> RenderStage {
+        self.stage
+    }
+
+    fn dependencies(&self) -> Vec<String> {
+        self.dependencies.clone()
+    }
+
+    fn render(&mut self, _engine: &mut AppEngine, _camera: &Camera) {}
+}
+
+fn feature(name: &'static str, stage: RenderStage, dependencies: &[&str]) -> Box<RenderFeature> {
+    Box::new(Feature {
+        name,
+        stage,
+        dependencies: dependencies.iter().map(|d| d.to_string()).collect(),
+    })
+}
+
+#[test]
+fn test_render_feature_order() {
+    let mut features = RenderFeatures::new();
+    features.register(feature("outline", RenderStage::AfterScene, &["mask"]));
+    features.register(feature("mask", RenderStage::AfterScene, &[]));
+    features.register(feature("water", RenderStage::BeforeScene, &["reflection", "depth"]));
+    features.register(feature("reflection", RenderStage::BeforeScene, &[]));
+    features.register(feature("depth", RenderStage::BeforeScene, &["reflection"]));
+
+    assert_eq!(
+        features.names(),
+        vec!["reflection", "depth", "water", "mask", "outline"]
+    );
+
+    features.remove("depth");
+    assert_eq!(
+        features.names(),
+        vec!["reflection", "water", "mask", "outline"]
+    );
+
+    // Across the stages, the scene order wins
+    features.register(feature("glow", RenderStage::AfterScene, &["reflection"]));
+    features.register(feature("sky", RenderStage::BeforeScene, &["outline"]));
+    assert_eq!(
+        features.names(),
+        vec!["reflection", "water", "sky", "mask", "outline", "glow"]
+    );
+
+    features.set_enabled("mask", false);
+    assert!(!features.is_enabled("mask"));
+    assert!(features.is_enabled("outline"));
+}
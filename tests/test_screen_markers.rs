@@ -0,0 +1,70 @@
+#![cfg(feature = "ui")]
+
+extern crate image;
+extern crate unrust;
+
+use unrust::actors::{clamp_to_screen, Marker, MarkerAnchor, MarkerKind, PopupFont,
+                     ScreenMarkers};
+use unrust::engine::{Texture, TextureImage};
+use unrust::math::*;
+
+fn font() -> PopupFont {
+    PopupFont {
+        texture: Texture::new(TextureImage::Rgba(image::RgbaImage::new(128, 64))),
+        texture_size: (128, 64),
+        glyph_size: (8, 8),
+    }
+}
+
+fn health_bar() -> Marker {
+    Marker::new(
+        MarkerAnchor::Position(vec3(0.0, 1.0, 0.0)),
+        MarkerKind::HealthBar {
+            value: 1.0,
+            width: 40.0,
+            height: 4.0,
+            color: vec3(0.0, 1.0, 0.0),
+        },
+    )
+}
+
+#[test]
+fn test_clamp_to_screen() {
+    let (p, on_screen) = clamp_to_screen(Vector4::new(0.5, 0.0, 0.0, 1.0), (800, 600), 20.0);
+    assert!(on_screen);
+    assert!((p.x - 200.0).abs() < 1e-4 && p.y.abs() < 1e-4);
+
+    // Far to the right, on the right edge
+    let (p, on_screen) = clamp_to_screen(Vector4::new(10.0, 0.0, 0.0, 1.0), (800, 600), 20.0);
+    assert!(!on_screen);
+    assert!((p.x - 380.0).abs() < 1e-4 && p.y.abs() < 1e-4);
+
+    // Behind the camera, on an edge
+    let (p, on_screen) = clamp_to_screen(Vector4::new(0.2, 0.1, 0.0, -1.0), (800, 600), 20.0);
+    assert!(!on_screen);
+    assert!((p.x.abs() - 380.0).abs() < 1e-3 || (p.y.abs() - 280.0).abs() < 1e-3);
+}
+
+#[test]
+fn test_screen_markers_ids() {
+    let mut markers = ScreenMarkers::new(font());
+    let a = markers.add(health_bar());
+    let b = markers.add(health_bar());
+    assert_eq!(markers.len(), 2);
+
+    markers.set_value(a, 2.0);
+    match markers.get_mut(a).unwrap().kind {
+        MarkerKind::HealthBar { value, .. } => assert_eq!(value, 1.0),
+        _ => panic!(),
+    }
+
+    assert!(markers.remove(a).is_some());
+    assert!(markers.get_mut(a).is_none());
+
+    // The slot is reused, the old id stays invalid
+    let c = markers.add(health_bar());
+    assert!(c != a);
+    assert!(markers.get_mut(a).is_none());
+    assert!(markers.get_mut(b).is_some() && markers.get_mut(c).is_some());
+    assert_eq!(markers.len(), 2);
+}
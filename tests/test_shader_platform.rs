@@ -0,0 +1,95 @@
+extern crate unrust;
+
+use unrust::engine::shader_platform::{self, FallbackChoice, Precision, ShaderFeature,
+                                      ShaderTarget};
+use unrust::engine::{PreprocessedShaderCode, ShaderKind};
+
+use std::collections::HashMap;
+
+const FS: &str = "varying highp vec2 vUV;
+void main(void) {
+#ifdef UNRUST_NO_DERIVATIVES
+    float width = 0.01;
+#else
+    float width = fwidth(vUV.x);
+#endif
+    gl_FragColor = vec4(width);
+}";
+
+fn patch(s: &str, target: &ShaderTarget) -> PreprocessedShaderCode {
+    PreprocessedShaderCode::new_for_target(ShaderKind::Fragment, s, &HashMap::new(), target)
+        .unwrap()
+}
+
+#[test]
+fn test_shader_platform_desktop() {
+    let code = patch(FS, &ShaderTarget::desktop());
+    assert!(code.as_string().starts_with("#version 150\n"));
+    assert!(code.as_string().contains("fwidth"));
+    assert!(code.fallbacks().is_empty());
+}
+
+#[test]
+fn test_shader_platform_extension() {
+    let code = patch(FS, &ShaderTarget::webgl());
+    assert!(
+        code.as_string()
+            .starts_with("#extension GL_OES_standard_derivatives : enable\nprecision highp float;")
+    );
+    assert!(code.fallbacks().is_empty());
+}
+
+#[test]
+fn test_shader_platform_fallbacks() {
+    let target = ShaderTarget::mobile_webgl().with_features(vec![]);
+    let code = patch(FS, &target);
+
+    assert!(code.as_string().starts_with("precision mediump float;"));
+    assert!(!code.as_string().contains("highp"));
+    assert!(!code.as_string().contains("fwidth"));
+    assert_eq!(
+        code.fallbacks(),
+        &[
+            FallbackChoice::Disabled(ShaderFeature::Derivatives),
+            FallbackChoice::Precision(Precision::Medium),
+        ]
+    );
+
+    // Without a fallback path the shader cannot compile
+    let code = patch("void main(void) { gl_FragColor = vec4(dFdx(1.0)); }", &target);
+    assert!(code.fallbacks()[0].is_error());
+}
+
+#[test]
+fn test_shader_platform_float_targets() {
+    let fs = "void main(void) {
+#ifdef UNRUST_NO_FLOAT_TARGETS
+    gl_FragColor = vec4(0.5, 0.25, 0.0, 1.0);
+#else
+    gl_FragColor = vec4(-1.0, 0.0, 0.0, 1.0);
+#endif
+}";
+
+    assert!(patch(fs, &ShaderTarget::webgl()).as_string().contains("-1.0"));
+    assert!(!patch(fs, &ShaderTarget::mobile_webgl()).as_string().contains("-1.0"));
+
+    let target = ShaderTarget::webgl().with_float_render_targets(false);
+    assert!(patch(fs, &target).as_string().contains("0.25"));
+}
+
+#[test]
+fn test_shader_platform_report() {
+    shader_platform::clear_report();
+    shader_platform::set_target(ShaderTarget::mobile_webgl());
+
+    unrust::engine::ShaderFs::new("test_fs.glsl", FS);
+    // Reloaded
+    unrust::engine::ShaderFs::new("test_fs.glsl", FS);
+
+    let report = shader_platform::report();
+    assert_eq!(report.len(), 1);
+    assert_eq!(
+        report[0].to_string(),
+        "test_fs.glsl: float precision lowered to mediump"
+    );
+}
@@ -0,0 +1,64 @@
+extern crate unrust;
+
+use unrust::engine::input::{ActionMap, Binding, InputDevice};
+use unrust::engine::split_screen::{viewports, SplitLayout};
+
+#[test]
+fn test_split_screen_viewports() {
+    let size = (800, 600);
+
+    assert_eq!(viewports(SplitLayout::Columns, 1, size), vec![((0, 0), (800, 600))]);
+    assert_eq!(
+        viewports(SplitLayout::Columns, 2, size),
+        vec![((0, 0), (400, 600)), ((400, 0), (400, 600))]
+    );
+
+    // Player 0 at the top, gl viewports start at the bottom
+    assert_eq!(
+        viewports(SplitLayout::Rows, 2, size),
+        vec![((0, 300), (800, 300)), ((0, 0), (800, 300))]
+    );
+
+    assert_eq!(
+        viewports(SplitLayout::Grid, 3, size),
+        vec![
+            ((0, 300), (400, 300)),
+            ((400, 300), (400, 300)),
+            ((0, 0), (400, 300)),
+        ]
+    );
+}
+
+#[test]
+#[cfg(feature = "ui")]
+fn test_split_screen_ui_root() {
+    use unrust::engine::split_screen::UiRoot;
+    use unrust::engine::Metric;
+
+    let root = UiRoot {
+        offset: (0.5, 0.0),
+        size: (0.5, 1.0),
+    };
+
+    assert_eq!(root.pos((0.0, 0.0)), Metric::Native(0.5, 0.0));
+    assert_eq!(root.pos((0.5, 0.5)), Metric::Native(0.75, 0.5));
+}
+
+#[test]
+fn test_action_map_for_device() {
+    let mut map = ActionMap::new();
+    map.bind("jump", Binding::Key("Space".to_owned()));
+    map.bind("jump", Binding::GamepadButton { gamepad: 0, button: 0 });
+    map.bind("fire", Binding::MouseButton(0));
+
+    let keyboard = map.for_device(InputDevice::KeyboardMouse);
+    assert_eq!(keyboard.bindings("jump"), &[Binding::Key("Space".to_owned())]);
+    assert_eq!(keyboard.bindings("fire"), &[Binding::MouseButton(0)]);
+
+    let pad = map.for_device(InputDevice::Gamepad(2));
+    assert_eq!(
+        pad.bindings("jump"),
+        &[Binding::GamepadButton { gamepad: 2, button: 0 }]
+    );
+    assert!(pad.bindings("fire").is_empty());
+}
@@ -0,0 +1,80 @@
+extern crate unrust;
+#[macro_use]
+extern crate serde_derive;
+
+use unrust::engine::tables::{parse_table, DataTable, TableFormat};
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+struct Enemy {
+    name: String,
+    health: f32,
+    level: u32,
+    #[serde(default)]
+    boss: bool,
+}
+
+#[test]
+fn test_parse_csv_table() {
+    let csv = "name,health,level,boss\r\nslime,10,1,\n\n\"Orc, the big\",42.5,3,true\n\"007\",1,1,false\n";
+    let rows: Vec<Enemy> = parse_table(TableFormat::Csv, "enemies.csv", csv.as_bytes()).unwrap();
+
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[0].name, "slime");
+    assert_eq!(rows[0].boss, false);
+    assert_eq!(rows[1].name, "Orc, the big");
+    assert_eq!(rows[1].health, 42.5);
+    assert!(rows[1].boss);
+    assert_eq!(rows[2].name, "007");
+
+    // Unquoted cells take the type of the field
+    let csv = "name,health,level\n007,1e1,2\ntrue,1,3\n";
+    let rows: Vec<Enemy> = parse_table(TableFormat::Csv, "enemies.csv", csv.as_bytes()).unwrap();
+    assert_eq!(rows[0].name, "007");
+    assert_eq!(rows[0].health, 10.0);
+    assert_eq!(rows[1].name, "true");
+
+    let bad = parse_table::<Enemy>(TableFormat::Csv, "enemies.csv", b"name,health,level\na,1,-1\n");
+    assert!(bad.is_err());
+}
+
+#[test]
+fn test_parse_json_table() {
+    let json = r#"[{"name": "slime", "health": 10, "level": 1}]"#;
+    let rows: Vec<Enemy> = parse_table(TableFormat::Json, "enemies.json", json.as_bytes()).unwrap();
+    assert_eq!(rows[0].level, 1);
+
+    let bad = parse_table::<Enemy>(TableFormat::Json, "enemies.json", b"[{\"name\": 1}]");
+    assert!(bad.is_err());
+}
+
+#[test]
+fn test_table_change_notification() {
+    let table = DataTable::<Enemy>::new("enemies.csv");
+    assert_eq!(TableFormat::from_name("enemies.csv"), TableFormat::Csv);
+    assert!(!table.is_loaded());
+
+    let changes = Rc::new(Cell::new(0));
+    let c = changes.clone();
+    table.on_change(move |rows| c.set(c.get() + rows.len()));
+
+    let v1 = b"name,health,level\nslime,10,1\n";
+    assert!(table.set_data(v1).unwrap());
+    assert_eq!(changes.get(), 1);
+
+    // Same content, no notification
+    assert!(!table.set_data(v1).unwrap());
+    assert_eq!(changes.get(), 1);
+
+    // Invalid content keeps the rows, and is reported once
+    assert!(table.set_data(b"name,health,level\nslime,lots,1\n").is_err());
+    assert_eq!(table.len(), 1);
+    assert!(!table.set_data(b"name,health,level\nslime,lots,1\n").unwrap());
+
+    assert!(table.set_data(b"name,health,level\nslime,20,1\norc,40,2\n").unwrap());
+    assert_eq!(changes.get(), 3);
+    assert_eq!(table.version(), 2);
+    assert_eq!(table.find(|e| e.name == "orc").map(|e| e.health), Some(40.0));
+}
@@ -0,0 +1,44 @@
+extern crate unrust;
+
+use unrust::engine::GameObject;
+use unrust::world::{Query, TagMask, Tags};
+
+#[test]
+fn test_tag_mask() {
+    let mask = TagMask::of(&[1, 70]);
+    assert!(mask.contains(1) && mask.contains(70));
+    assert!(!mask.contains(2));
+    assert!(mask.contains_all(&TagMask::of(&[70])));
+    assert!(!mask.intersects(&TagMask::of(&[0, 64])));
+}
+
+#[test]
+fn test_tag_query() {
+    let tags = Tags::new();
+    let enemy = tags.tag("enemy");
+    let burning = tags.tag("burning");
+    let shielded = tags.tag("shielded");
+    assert_eq!(tags.tag("enemy"), enemy);
+
+    let a = GameObject::empty();
+    let b = GameObject::empty();
+    let c = GameObject::empty();
+    for go in [&a, &b, &c].iter() {
+        tags.add(go, enemy);
+        tags.add(go, burning);
+    }
+    tags.add(&b, shielded);
+    tags.remove(&c, burning);
+
+    let query = Query::new().with(enemy).with(burning).without(shielded);
+    let found = tags.find(&query);
+    assert_eq!(found.len(), 1);
+    assert!(std::rc::Rc::ptr_eq(&found[0], &a));
+
+    drop(found);
+    drop(a);
+    assert_eq!(tags.count(&query), 0);
+    tags.compact();
+    assert_eq!(tags.len(), 2);
+    assert!(tags.has(&b, shielded));
+}
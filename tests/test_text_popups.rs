@@ -0,0 +1,45 @@
+#![cfg(feature = "ui")]
+
+extern crate image;
+extern crate unrust;
+
+use unrust::actors::{PopupFont, PopupMotion, TextPopups};
+use unrust::engine::{Texture, TextureImage};
+use unrust::math::*;
+
+fn font() -> PopupFont {
+    PopupFont {
+        texture: Texture::new(TextureImage::Rgba(image::RgbaImage::new(128, 64))),
+        texture_size: (128, 64),
+        glyph_size: (8, 8),
+    }
+}
+
+#[test]
+fn test_text_popups_pool() {
+    let mut popups = TextPopups::new(font(), 4, 6);
+    assert_eq!(popups.capacity(), 4);
+    assert_eq!(popups.alive(), 0);
+
+    for i in 0..6 {
+        popups.spawn_number(vec3(0.0, 1.0, 0.0), i * 100, vec3(1.0, 0.0, 0.0));
+    }
+
+    // The oldest popups are replaced
+    assert_eq!(popups.alive(), 4);
+}
+
+#[test]
+fn test_text_popups_capacity() {
+    // Within 16 bits indices
+    let popups = TextPopups::new(font(), 100000, 8);
+    assert!(popups.capacity() * 8 * 4 <= 65535);
+}
+
+#[test]
+fn test_popup_motion_curves() {
+    let motion = PopupMotion::default();
+    assert_eq!(motion.alpha.sample(0.0), Some(1.0));
+    assert!((motion.alpha.sample(0.8).unwrap() - 0.5).abs() < 1e-5);
+    assert_eq!(motion.alpha.sample(1.0), Some(0.0));
+}
@@ -0,0 +1,103 @@
+extern crate image;
+extern crate unrust;
+
+use image::{Rgba, RgbaImage};
+use unrust::engine::texture_import::{ImportPlatform, TextureContainer, TextureImporter};
+use unrust::engine::{DDSFormat, DDS};
+
+use std::env;
+use std::fs;
+
+#[test]
+fn test_compress_texture() {
+    let importer = TextureImporter::new(ImportPlatform::Desktop);
+
+    let opaque = RgbaImage::from_pixel(8, 8, Rgba([255, 0, 0, 255]));
+    let dds = importer.compress(&opaque).unwrap();
+    assert!(match dds.format {
+        DDSFormat::DXT1 => true,
+        _ => false,
+    });
+    assert!(!dds.has_alpha);
+
+    // 8x8, 4x4, 2x2 and 1x1, a block per level
+    let sizes: Vec<_> = dds.images.iter().map(|i| (i.width, i.height)).collect();
+    assert_eq!(sizes, vec![(8, 8), (4, 4), (2, 2), (1, 1)]);
+    assert_eq!(dds.images[0].data.len(), 4 * 8);
+    assert_eq!(dds.images[3].data.len(), 8);
+
+    let read = DDS::from_bytes(dds.to_bytes(), &"test.dds".to_owned()).unwrap();
+    assert_eq!(read.images.len(), 4);
+    assert_eq!(read.images[0].data, dds.images[0].data);
+
+    let transparent = RgbaImage::from_pixel(4, 4, Rgba([255, 255, 255, 128]));
+    let dds = importer.compress(&transparent).unwrap();
+    assert!(dds.has_alpha);
+    assert_eq!(dds.images[0].data.len(), 16);
+
+    // S3TC needs sizes multiple of 4
+    assert!(importer.compress(&RgbaImage::new(6, 4)).is_none());
+}
+
+#[test]
+fn test_ktx2_texture() {
+    let importer = TextureImporter::new(ImportPlatform::Desktop);
+
+    for &alpha in [255, 128].iter() {
+        let img = RgbaImage::from_fn(16, 8, |x, y| Rgba([x as u8 * 16, y as u8 * 32, 0, alpha]));
+        let dds = importer.compress(&img).unwrap();
+        let name = "test.ktx2".to_owned();
+
+        let bytes = dds.to_ktx2();
+        assert_eq!(&bytes[1..4], b"KTX");
+
+        let read = DDS::from_ktx2(&bytes, &name).unwrap();
+        assert_eq!(read.has_alpha, dds.has_alpha);
+        assert_eq!(read.images.len(), 5);
+        for (a, b) in read.images.iter().zip(dds.images.iter()) {
+            assert_eq!((a.width, a.height), (b.width, b.height));
+            assert_eq!(a.data, b.data);
+        }
+
+        // Truncated
+        assert!(DDS::from_ktx2(&bytes[..bytes.len() - 1], &name).is_err());
+    }
+}
+
+#[test]
+fn test_import_textures() {
+    let root = env::temp_dir().join("unrust_test_texture_import");
+    let _ = fs::remove_dir_all(&root);
+    let (source, output) = (root.join("source"), root.join("output"));
+    fs::create_dir_all(source.join("sub")).unwrap();
+
+    RgbaImage::from_pixel(4, 4, Rgba([0, 255, 0, 255]))
+        .save(source.join("sub/a.png"))
+        .unwrap();
+    RgbaImage::from_pixel(3, 3, Rgba([0, 255, 0, 255]))
+        .save(source.join("b.png"))
+        .unwrap();
+    fs::write(source.join("c.txt"), "text").unwrap();
+
+    let manifest = TextureImporter::new(ImportPlatform::Web)
+        .import(&source, &output)
+        .unwrap();
+
+    assert_eq!(manifest.platform, "web");
+    assert_eq!(manifest.textures.len(), 1);
+    assert_eq!(manifest.textures["sub/a.png"], "sub/a.dds");
+
+    assert!(output.join("sub/a.dds").exists());
+    assert!(output.join("sub/a.png").exists());
+    assert!(output.join("b.png").exists());
+    assert!(output.join("c.txt").exists());
+    assert!(output.join("textures.json").exists());
+
+    let mut importer = TextureImporter::new(ImportPlatform::Web);
+    importer.container = TextureContainer::Ktx2;
+    let manifest = importer.import(&source, &output).unwrap();
+    assert_eq!(manifest.textures["sub/a.png"], "sub/a.ktx2");
+    assert!(output.join("sub/a.ktx2").exists());
+
+    let _ = fs::remove_dir_all(&root);
+}
@@ -0,0 +1,34 @@
+extern crate unrust;
+
+use unrust::world::Turns;
+
+#[test]
+fn test_turns_real_time() {
+    let mut turns = Turns::new();
+    turns.advance();
+    assert_eq!(turns.pending(), 0);
+    assert_eq!(turns.step(0.5, false), 0.5);
+    assert_eq!(turns.step(0.5, true), 0.0);
+}
+
+#[test]
+fn test_turns_advance() {
+    let mut turns = Turns::new();
+    turns.set_enabled(true);
+    turns.duration = 0.25;
+
+    assert_eq!(turns.step(0.1, false), 0.0);
+    assert!(!turns.is_advancing());
+
+    turns.advance();
+    turns.advance();
+    assert_eq!(turns.step(0.1, true), 0.0);
+    assert_eq!(turns.pending(), 2);
+
+    assert_eq!(turns.step(0.1, false), 0.25);
+    assert!(turns.is_advancing());
+    assert_eq!(turns.step(0.1, false), 0.25);
+    assert_eq!(turns.step(0.1, false), 0.0);
+    assert!(!turns.is_advancing());
+    assert_eq!(turns.count(), 2);
+}
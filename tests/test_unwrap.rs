@@ -0,0 +1,91 @@
+extern crate unrust;
+
+use unrust::engine::unwrap::{build_charts, unwrap_uvs, UnwrapOptions, UvChannel};
+use unrust::engine::{CubeMesh, MeshData};
+use unrust::math::*;
+
+/// A 2x1 grid of quads on y = 0, with a crease of 30 degrees in the middle if `folded`
+fn strip(folded: bool) -> MeshData {
+    let h = if folded { 30.0f32.to_radians().tan() } else { 0.0 };
+    MeshData {
+        vertices: vec![
+            0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 2.0, h, 0.0,
+            0.0, 0.0, 1.0, 1.0, 0.0, 1.0, 2.0, h, 1.0,
+        ],
+        indices: vec![0, 3, 4, 0, 4, 1, 1, 4, 5, 1, 5, 2],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_unwrap_charts() {
+    let cube = CubeMesh::new();
+    assert_eq!(build_charts(&cube, Deg(60.0).into()).len(), 6);
+
+    assert_eq!(build_charts(&strip(false), Deg(10.0).into()).len(), 1);
+    assert_eq!(build_charts(&strip(true), Deg(45.0).into()).len(), 1);
+    assert_eq!(build_charts(&strip(true), Deg(10.0).into()).len(), 2);
+}
+
+#[test]
+fn test_unwrap_uvs() {
+    let mut data = strip(true);
+    assert!(unwrap_uvs(&mut data, UvChannel::Uv0, &UnwrapOptions::new(64)));
+
+    // The vertices of the crease are split between the two charts
+    let uvs = data.uvs.as_ref().unwrap();
+    assert_eq!(data.vertices.len() / 3, 8);
+    assert_eq!(uvs.len(), 16);
+    assert!(uvs.iter().all(|v| *v >= 0.0 && *v <= 1.0));
+    assert!(data.uvs2.is_none());
+
+    // The texel density follows the world area
+    let uv = |i: u16| Vector2::new(uvs[i as usize * 2], uvs[i as usize * 2 + 1]);
+    let area = |t: &[u16]| {
+        let (ab, ac) = (uv(t[1]) - uv(t[0]), uv(t[2]) - uv(t[0]));
+        ((ab.x * ac.y - ab.y * ac.x) * 0.5).abs()
+    };
+    let first = area(&data.indices[0..3]);
+    let last = area(&data.indices[9..12]);
+    let ratio = 1.0 / 30.0f32.to_radians().cos();
+    assert!((last / first - ratio).abs() < 1e-3);
+}
+
+/// Wings sloping down by 50 degrees on each side of a flat quad, the wings are 100 degrees
+/// apart. The first triangles are the ones of the left wing, the chart grows from the middle.
+fn gable() -> MeshData {
+    let (c, s) = (0.5 * 50.0f32.to_radians().cos(), 0.5 * 50.0f32.to_radians().sin());
+    MeshData {
+        vertices: vec![
+            -c, -s, 0.0, -c, -s, 1.0,
+            0.0, 0.0, 0.0, 0.0, 0.0, 1.0,
+            2.0, 0.0, 0.0, 2.0, 0.0, 1.0,
+            2.0 + c, -s, 0.0, 2.0 + c, -s, 1.0,
+        ],
+        indices: vec![0, 1, 2, 2, 1, 3, 2, 3, 4, 4, 3, 5, 4, 5, 6, 6, 5, 7],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_unwrap_seed_axis() {
+    let mut data = gable();
+    let charts = build_charts(&data, Deg(60.0).into());
+    assert_eq!(charts.len(), 1);
+    assert!((charts[0].axis - Vector3::unit_y()).magnitude() < 1e-4);
+
+    // Projected on the plane of the middle, no triangle is flipped
+    assert!(unwrap_uvs(&mut data, UvChannel::Uv0, &UnwrapOptions::new(64)));
+    let uvs = data.uvs.as_ref().unwrap();
+    let uv = |i: u16| Vector2::new(uvs[i as usize * 2], uvs[i as usize * 2 + 1]);
+    let signed_areas: Vec<f32> = data.indices
+        .chunks(3)
+        .map(|t| {
+            let (ab, ac) = (uv(t[1]) - uv(t[0]), uv(t[2]) - uv(t[0]));
+            ab.x * ac.y - ab.y * ac.x
+        })
+        .collect();
+
+    let sign = signed_areas[2].signum();
+    assert!(signed_areas.iter().all(|a| a.abs() > 1e-6 && a.signum() == sign));
+}
@@ -0,0 +1,114 @@
+extern crate image;
+extern crate unrust;
+
+use unrust::engine::validation::{validate, IssueKind, Severity, ValidationOptions};
+use unrust::engine::{Asset, ComponentArena, Material, Mesh, MeshBuffer, MeshData, Name,
+                     Resource, SceneTree, ShaderFs, ShaderProgram, ShaderVs, Texture, TextureImage};
+use unrust::math::*;
+
+use std::rc::Rc;
+
+const VS: &str = "attribute vec3 aVertexPosition;
+attribute vec2 aTextureCoord;
+varying vec2 vUV;
+void main(void) {
+    vUV = aTextureCoord;
+    gl_Position = vec4(aVertexPosition, 1.0);
+}";
+
+const FS: &str = "varying vec2 vUV;
+uniform sampler2D uDiffuse;
+void main(void) {
+    gl_FragColor = texture2D(uDiffuse, vUV);
+}";
+
+fn material(texture_size: u32) -> Rc<Material> {
+    let program = ShaderProgram::new((
+        Resource::new(ShaderVs::new("test_vs.glsl", VS)),
+        Resource::new(ShaderFs::new("test_fs.glsl", FS)),
+    ));
+
+    let img = image::RgbaImage::new(texture_size, texture_size);
+    let material = Material::new(program);
+    material.set("uDiffuse", Texture::new(TextureImage::Rgba(img)));
+    Rc::new(material)
+}
+
+fn triangle(uvs: bool) -> Rc<MeshBuffer> {
+    MeshBuffer::new(MeshData {
+        vertices: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0],
+        uvs: if uvs {
+            Some(vec![0.0, 0.0, 1.0, 0.0, 0.0, 1.0])
+        } else {
+            None
+        },
+        indices: vec![0, 1, 2],
+        ..Default::default()
+    })
+}
+
+#[test]
+fn test_validate_scene() {
+    let tree = SceneTree::new();
+    let arena = Rc::new(ComponentArena::new());
+
+    let add = |name: &str, uvs: bool, texture_size: u32, position: Vector3<f32>| {
+        let go = tree.new_node(&tree.root(), &arena);
+        let mut mesh = Mesh::new();
+        mesh.add_surface(triangle(uvs), material(texture_size));
+
+        let mut go_mut = go.borrow_mut();
+        go_mut.add_component(mesh);
+        go_mut.add_component(Name::new(name));
+        go_mut.transform.set_global(Decomposed {
+            scale: 1.0,
+            rot: Quaternion::one(),
+            disp: position,
+        });
+        drop(go_mut);
+        go
+    };
+
+    let _a = add("crate", true, 8, Vector3::zero());
+    let _b = add("crate", false, 8, Vector3::zero());
+    let _c = add("far", true, 64, Vector3::new(0.0, -50000.0, 0.0));
+
+    let options = ValidationOptions {
+        max_texture_size: 32,
+        ..Default::default()
+    };
+    let report = validate(&tree.root(), &options);
+
+    assert_eq!(report.stats.objects, 3);
+    assert_eq!(report.stats.meshes, 3);
+    assert_eq!(report.stats.triangles, 3);
+    assert_eq!(report.stats.textures, 3);
+
+    // The mesh without uvs is the only error
+    assert!(!report.is_ok());
+    let errors = report.errors();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        errors[0].kind,
+        IssueKind::MissingAttribute {
+            attribute: "aTextureCoord"
+        }
+    );
+    assert_eq!(report.issues[0].severity, Severity::Error);
+
+    let warnings = report.warnings();
+    assert!(warnings.iter().any(|i| match i.kind {
+        IssueKind::OversizedTexture { size } => size == (64, 64),
+        _ => false,
+    }));
+    assert!(warnings.iter().any(|i| match i.kind {
+        IssueKind::OutOfBounds { .. } => i.name == Some("far".to_owned()),
+        _ => false,
+    }));
+    assert!(warnings.iter().any(|i| match i.kind {
+        IssueKind::DuplicateName { ref name, count } => name == "crate" && count == 2,
+        _ => false,
+    }));
+
+    assert!(format!("{}", report).contains("3 objects"));
+}
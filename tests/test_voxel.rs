@@ -0,0 +1,104 @@
+extern crate unrust;
+
+use unrust::engine::voxel::{mesh_chunk, VoxelVolume};
+use unrust::engine::MeshData;
+use unrust::math::*;
+
+fn vertex(data: &MeshData, i: usize) -> Vector3<f32> {
+    Vector3::new(data.vertices[i * 3], data.vertices[i * 3 + 1], data.vertices[i * 3 + 2])
+}
+
+#[test]
+fn test_voxel_sphere() {
+    let center = Vector3::new(8.0, 8.0, 8.0);
+    let volume = VoxelVolume::from_fn(8, (2, 2, 2), 1.0, |p| 6.0 - (p - center).magnitude());
+
+    let mut triangles = 0;
+    for c in volume.chunk_coords() {
+        let data = mesh_chunk(&volume, c, 0, &[0; 6]);
+        let normals = data.normals.as_ref().unwrap();
+        triangles += data.indices.len() / 3;
+
+        for i in 0..data.vertices.len() / 3 {
+            let p = vertex(&data, i) - center;
+            let n = Vector3::new(normals[i * 3], normals[i * 3 + 1], normals[i * 3 + 2]);
+            assert!((p.magnitude() - 6.0).abs() < 0.2);
+            assert!(n.dot(p) > 0.0);
+        }
+
+        // Counter-clockwise seen from outside
+        for t in data.indices.chunks(3) {
+            let (a, b, c) = (
+                vertex(&data, t[0] as usize),
+                vertex(&data, t[1] as usize),
+                vertex(&data, t[2] as usize),
+            );
+            let normal = (b - a).cross(c - a);
+            if normal.magnitude() > 1e-3 {
+                assert!(normal.dot(a - center) > 0.0);
+            }
+        }
+    }
+    assert!(triangles > 100);
+}
+
+#[test]
+fn test_voxel_dig() {
+    let mut volume = VoxelVolume::from_fn(8, (2, 1, 2), 1.0, |p| 4.0 - p.y);
+    assert_eq!(volume.take_dirty().len(), 4);
+    assert!(volume.take_dirty().is_empty());
+
+    assert!(volume.is_solid(Vector3::new(8.0, 3.0, 8.0)));
+    volume.dig(Vector3::new(8.0, 4.0, 8.0), 2.0);
+    assert!(!volume.is_solid(Vector3::new(8.0, 3.0, 8.0)));
+    assert_eq!(volume.take_dirty().len(), 4);
+
+    // Far from the chunk borders
+    volume.build(Vector3::new(3.0, 5.0, 3.0), 1.0);
+    assert!(volume.is_solid(Vector3::new(3.0, 5.0, 3.0)));
+    assert_eq!(volume.take_dirty(), vec![(0, 0, 0)]);
+}
+
+fn distance_to_segment(p: Vector3<f32>, a: Vector3<f32>, b: Vector3<f32>) -> f32 {
+    let ab = b - a;
+    let t = ((p - a).dot(ab) / ab.magnitude2()).max(0.0).min(1.0);
+    (a + ab * t - p).magnitude()
+}
+
+#[test]
+fn test_voxel_lod_stitching() {
+    let volume = VoxelVolume::from_fn(8, (2, 1, 1), 1.0, |p| {
+        4.3 + 1.5 * (p.x * 0.7).sin() * (p.z * 0.9).cos() - p.y
+    });
+
+    // The fine chunk has its coarse neighbor on +x
+    let fine = mesh_chunk(&volume, (0, 0, 0), 0, &[0, 2, 0, 0, 0, 0]);
+    let coarse = mesh_chunk(&volume, (1, 0, 0), 2, &[0; 6]);
+
+    let mut segments = Vec::new();
+    for t in coarse.indices.chunks(3) {
+        for &(a, b) in [(t[0], t[1]), (t[1], t[2]), (t[2], t[0])].iter() {
+            let (a, b) = (vertex(&coarse, a as usize), vertex(&coarse, b as usize));
+            if a.x == 8.0 && b.x == 8.0 {
+                segments.push((a, b));
+            }
+        }
+    }
+    assert!(!segments.is_empty());
+
+    let mut on_face = 0;
+    for i in 0..fine.vertices.len() / 3 {
+        let p = vertex(&fine, i);
+        if p.x != 8.0 {
+            continue;
+        }
+
+        on_face += 1;
+        let d = segments
+            .iter()
+            .map(|&(a, b)| distance_to_segment(p, a, b))
+            .fold(std::f32::MAX, f32::min);
+        assert!(d < 1e-3);
+    }
+    assert!(on_face > 2);
+}
@@ -0,0 +1,15 @@
+extern crate unrust;
+
+use unrust::world::WindowFlags;
+
+#[test]
+fn test_window_flags_unsupported() {
+    assert!(WindowFlags::new().unsupported().is_empty());
+
+    // uni-app creates the native window without these options
+    let flags = WindowFlags::overlay().with_click_through(true);
+    assert_eq!(
+        flags.unsupported(),
+        vec!["transparent", "always_on_top", "undecorated", "click_through"]
+    );
+}
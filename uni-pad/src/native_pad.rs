@@ -8,3 +8,15 @@ pub fn gamepad_axis(_player_num: i32) -> (f32, f32) {
 pub fn gamepad_button(_player_num: i32, _button_num: i32) -> bool {
     false
 }
+
+pub fn gamepad_id(_player_num: i32) -> Option<String> {
+    None
+}
+
+pub fn gamepad_any_input(_player_num: i32) -> bool {
+    false
+}
+
+pub fn gamepad_vibrate(_player_num: i32, _strong: f32, _weak: f32, _duration_ms: u32) -> bool {
+    false
+}
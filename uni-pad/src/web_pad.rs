@@ -73,3 +73,65 @@ pub fn gamepad_button(player_num: i32, button_num: i32) -> bool {
         .unwrap();
     ret
 }
+
+/// Id string of a gamepad, with its name and usb vendor / product ids
+pub fn gamepad_id(player_num: i32) -> Option<String> {
+    let ret = js! {
+        var pad = window.pads[@{player_num}];
+        return pad ? pad.id : null;
+    }.try_into()
+        .unwrap();
+    ret
+}
+
+/// Whether a button of the gamepad is pressed or a stick is pushed
+pub fn gamepad_any_input(player_num: i32) -> bool {
+    let ret = js! {
+        var pad = window.pads[@{player_num}];
+        if (!pad) {
+            return false;
+        }
+        for (var i = 0; i < pad.buttons.length; i++) {
+            var button = pad.buttons[i];
+            if (typeof button == "object" ? button.pressed : button == 1.0) {
+                return true;
+            }
+        }
+        for (var i = 0; i < pad.axes.length; i++) {
+            if (Math.abs(pad.axes[i]) > 0.5) {
+                return true;
+            }
+        }
+        return false;
+    }.try_into()
+        .unwrap();
+    ret
+}
+
+/// Start vibration of a gamepad, magnitudes are from 0.0 to 1.0.
+/// Return false if the gamepad has no vibration support.
+pub fn gamepad_vibrate(player_num: i32, strong: f32, weak: f32, duration_ms: u32) -> bool {
+    let ret = js! {
+        var pad = window.pads[@{player_num}];
+        if (!pad) {
+            return false;
+        }
+        if (pad.vibrationActuator && pad.vibrationActuator.playEffect) {
+            pad.vibrationActuator.playEffect("dual-rumble", {
+                startDelay: 0,
+                duration: @{duration_ms},
+                strongMagnitude: @{strong},
+                weakMagnitude: @{weak}
+            });
+            return true;
+        }
+        // Firefox
+        if (pad.hapticActuators && pad.hapticActuators.length > 0) {
+            pad.hapticActuators[0].pulse(Math.max(@{strong}, @{weak}), @{duration_ms});
+            return true;
+        }
+        return false;
+    }.try_into()
+        .unwrap();
+    ret
+}
@@ -47,3 +47,148 @@ fn impl_actor(ast: &syn::DeriveInput) -> quote::Tokens {
         }
     }
 }
+
+#[proc_macro_derive(Inspect, attributes(inspect))]
+pub fn inspect(input: TokenStream) -> TokenStream {
+    let ast = syn::parse(input).unwrap();
+
+    let gen = impl_inspect(&ast);
+
+    gen.into()
+}
+
+struct InspectAttrs {
+    skip: bool,
+    min: Option<f64>,
+    max: Option<f64>,
+}
+
+fn lit_to_f64(lit: &syn::Lit) -> f64 {
+    match *lit {
+        syn::Lit::Float(ref f) => f.value(),
+        syn::Lit::Int(ref i) => i.value() as f64,
+        syn::Lit::Str(ref s) => s.value()
+            .parse()
+            .unwrap_or_else(|_| panic!("#[inspect] bound \"{}\" is not a number", s.value())),
+        _ => panic!("#[inspect] bounds must be numbers"),
+    }
+}
+
+fn inspect_attrs(field: &syn::Field) -> InspectAttrs {
+    let mut attrs = InspectAttrs {
+        skip: false,
+        min: None,
+        max: None,
+    };
+
+    for meta in field.attrs.iter().filter_map(|a| a.interpret_meta()) {
+        let list = match meta {
+            syn::Meta::List(ref list) if list.ident.as_ref() == "inspect" => list,
+            _ => continue,
+        };
+
+        for nested in list.nested.iter() {
+            match *nested {
+                syn::NestedMeta::Meta(syn::Meta::Word(ref ident)) if ident.as_ref() == "skip" => {
+                    attrs.skip = true;
+                }
+                syn::NestedMeta::Meta(syn::Meta::NameValue(ref nv)) if nv.ident.as_ref() == "min" => {
+                    attrs.min = Some(lit_to_f64(&nv.lit));
+                }
+                syn::NestedMeta::Meta(syn::Meta::NameValue(ref nv)) if nv.ident.as_ref() == "max" => {
+                    attrs.max = Some(lit_to_f64(&nv.lit));
+                }
+                _ => panic!("unknown #[inspect] option, expected skip, min or max"),
+            }
+        }
+    }
+
+    attrs
+}
+
+fn impl_inspect(ast: &syn::DeriveInput) -> quote::Tokens {
+    let name = &ast.ident;
+    let type_name = name.as_ref().to_owned();
+
+    let fields = match ast.data {
+        syn::Data::Struct(syn::DataStruct {
+            fields: syn::Fields::Named(ref fields),
+            ..
+        }) => &fields.named,
+        _ => panic!("#[derive(Inspect)] is only supported on structs with named fields"),
+    };
+
+    let mut idents = Vec::new();
+    let mut names = Vec::new();
+    let mut types = Vec::new();
+    let mut ranges = Vec::new();
+
+    for field in fields.iter() {
+        let attrs = inspect_attrs(field);
+        if attrs.skip {
+            continue;
+        }
+
+        let ident = field.ident.as_ref().unwrap();
+        let range = match (attrs.min, attrs.max) {
+            (None, None) => quote!{ None },
+            (min, max) => {
+                let min = min.unwrap_or(::std::f64::MIN);
+                let max = max.unwrap_or(::std::f64::MAX);
+                quote!{ Some((#min, #max)) }
+            }
+        };
+
+        names.push(ident.as_ref().to_owned());
+        idents.push(ident.clone());
+        types.push(field.ty.clone());
+        ranges.push(range);
+    }
+
+    // Each repetition needs its own iterator
+    let (names1, names2, names3) = (&names, &names, &names);
+    let (idents1, idents2) = (&idents, &idents);
+    let (types1, types2) = (&types, &types);
+    let (ranges1, ranges2) = (&ranges, &ranges);
+
+    quote!{
+        impl ::unrust::engine::inspect::Inspect for #name {
+            fn type_name(&self) -> &'static str {
+                #type_name
+            }
+
+            fn fields(&self) -> Vec<::unrust::engine::inspect::FieldInfo> {
+                vec![#(
+                    ::unrust::engine::inspect::FieldInfo {
+                        name: #names1,
+                        kind: <#types1 as ::unrust::engine::inspect::InspectField>::kind(),
+                        range: #ranges1,
+                    }
+                ),*]
+            }
+
+            fn get(&self, name: &str) -> Option<::unrust::engine::inspect::FieldValue> {
+                match name {
+                    #(#names2 => Some(::unrust::engine::inspect::InspectField::to_value(&self.#idents1)),)*
+                    _ => None,
+                }
+            }
+
+            fn set(&mut self, name: &str, value: &::unrust::engine::inspect::FieldValue) -> bool {
+                match name {
+                    #(#names3 => {
+                        let value = value.clamp(#ranges2);
+                        match <#types2 as ::unrust::engine::inspect::InspectField>::from_value(&value) {
+                            Some(v) => {
+                                self.#idents2 = v;
+                                true
+                            }
+                            None => false,
+                        }
+                    })*
+                    _ => false,
+                }
+            }
+        }
+    }
+}